@@ -6,9 +6,11 @@ use chrono::Utc;
 use near_primitives::block::BlockValidityError;
 use near_primitives::challenge::{ChunkProofs, ChunkState};
 use near_primitives::errors::{EpochError, StorageError};
+use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayoutError;
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
-use near_primitives::types::{BlockHeight, EpochId, ShardId};
+use near_primitives::types::{BlockHeight, BlockHeightDelta, EpochId, ShardId};
+use near_primitives::validator_signer::ValidatorSignerError;
 
 #[derive(thiserror::Error, Debug)]
 pub enum QueryError {
@@ -134,8 +136,11 @@ pub enum Error {
     /// Invalid chunk state.
     #[error("Invalid Chunk State")]
     InvalidChunkState(Box<ChunkState>),
-    #[error("Invalid Chunk State Witness")]
-    InvalidChunkStateWitness(String),
+    #[error("Invalid Chunk State Witness: {0}")]
+    InvalidChunkStateWitness(#[from] ChunkWitnessValidationError),
+    /// Invalid chunk endorsement (wrong chunk or signature).
+    #[error("Invalid Chunk Endorsement")]
+    InvalidChunkEndorsement,
     /// Invalid chunk mask
     #[error("Invalid Chunk Mask")]
     InvalidChunkMask,
@@ -204,6 +209,10 @@ pub enum Error {
     /// Validator error.
     #[error("Validator Error: {0}")]
     ValidatorError(String),
+    /// The validator signer failed to sign, e.g. a remote one couldn't reach
+    /// its signing service.
+    #[error("Validator Signer Error: {0}")]
+    ValidatorSignerError(#[from] ValidatorSignerError),
     /// Epoch out of bounds. Usually if received block is too far in the future or alternative fork.
     #[error("Epoch Out Of Bounds: {:?}", _0)]
     EpochOutOfBounds(EpochId),
@@ -230,6 +239,84 @@ pub enum Error {
     Other(String),
 }
 
+/// Reasons for which a chunk state witness can fail validation.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ChunkWitnessValidationError {
+    #[error("Witness size {size} exceeds the limit of {limit} bytes")]
+    WitnessTooLarge { size: u64, limit: u64 },
+    #[error(
+        "Witness height {height} is more than {horizon} blocks away from head height {head_height}"
+    )]
+    HeightOutsideHorizon {
+        height: BlockHeight,
+        head_height: BlockHeight,
+        horizon: BlockHeightDelta,
+    },
+    #[error("Shard {shard_id} does not exist in block {block_hash:?}")]
+    ShardNotInBlock { shard_id: ShardId, block_hash: CryptoHash },
+    #[error("Missing source receipt proof for chunk {chunk_hash:?}")]
+    MissingSourceReceiptProof { chunk_hash: ChunkHash },
+    #[error(
+        "Provided receipt proof failed verification against receipt root for chunk {chunk_hash:?}"
+    )]
+    InvalidSourceReceiptProof { chunk_hash: ChunkHash },
+    #[error("Receipt proof for chunk {chunk_hash:?} is for shard {proof_shard_id}, expected shard {shard_id}")]
+    SourceReceiptProofShardMismatch {
+        chunk_hash: ChunkHash,
+        proof_shard_id: ShardId,
+        shard_id: ShardId,
+    },
+    #[error("Receipts hash {actual:?} does not match expected receipts hash {expected:?}")]
+    ReceiptsHashMismatch { expected: CryptoHash, actual: CryptoHash },
+    #[error("Transaction root {actual:?} does not match expected transaction root {expected:?}")]
+    TransactionsRootMismatch { expected: CryptoHash, actual: CryptoHash },
+    #[error("Witness has {actual} implicit transitions, expected {expected}")]
+    ImplicitTransitionsCountMismatch { expected: usize, actual: usize },
+    #[error("Post state root {actual:?} for main transition does not match expected post state root {expected:?}")]
+    MainTransitionPostStateRootMismatch { expected: CryptoHash, actual: CryptoHash },
+    #[error("Post state root {actual:?} for implicit transition at block {block_hash:?}, does not match expected state root {expected:?}")]
+    ImplicitTransitionPostStateRootMismatch {
+        block_hash: CryptoHash,
+        expected: CryptoHash,
+        actual: CryptoHash,
+    },
+    #[error("Witness is missing {} trie nodes which had to be read from storage", .missing_nodes.len())]
+    IncompleteWitness { missing_nodes: Vec<CryptoHash> },
+    #[error(
+        "Witness builds upon transitions up to block {last_block_hash:?} which were not validated"
+    )]
+    MissingTransitionsBase { last_block_hash: CryptoHash },
+    #[error("Witness builds upon transitions up to block {last_block_hash:?} which do not precede the chunk")]
+    InvalidTransitionsBase { last_block_hash: CryptoHash },
+}
+
+impl ChunkWitnessValidationError {
+    /// Value of the label reported in prometheus metrics for this validation
+    /// failure, see `Error::prometheus_label_value`.
+    pub fn prometheus_label_value(&self) -> &'static str {
+        match self {
+            Self::WitnessTooLarge { .. } => "witness_too_large",
+            Self::HeightOutsideHorizon { .. } => "height_outside_horizon",
+            Self::ShardNotInBlock { .. } => "shard_not_in_block",
+            Self::MissingSourceReceiptProof { .. } => "missing_source_receipt_proof",
+            Self::InvalidSourceReceiptProof { .. } => "invalid_source_receipt_proof",
+            Self::SourceReceiptProofShardMismatch { .. } => "source_receipt_proof_shard_mismatch",
+            Self::ReceiptsHashMismatch { .. } => "receipts_hash_mismatch",
+            Self::TransactionsRootMismatch { .. } => "transactions_root_mismatch",
+            Self::ImplicitTransitionsCountMismatch { .. } => "implicit_transitions_count_mismatch",
+            Self::MainTransitionPostStateRootMismatch { .. } => {
+                "main_transition_post_state_root_mismatch"
+            }
+            Self::ImplicitTransitionPostStateRootMismatch { .. } => {
+                "implicit_transition_post_state_root_mismatch"
+            }
+            Self::IncompleteWitness { .. } => "incomplete_witness",
+            Self::MissingTransitionsBase { .. } => "missing_transitions_base",
+            Self::InvalidTransitionsBase { .. } => "invalid_transitions_base",
+        }
+    }
+}
+
 /// For now StorageError can happen at any time from ViewClient because of
 /// the used isolation level + running ViewClient in a separate thread.
 pub trait LogTransientStorageError {
@@ -257,6 +344,7 @@ impl Error {
             | Error::IOErr(_)
             | Error::Other(_)
             | Error::ValidatorError(_)
+            | Error::ValidatorSignerError(_)
             | Error::EpochOutOfBounds(_)
             | Error::ChallengedBlockOnChain
             | Error::CannotBeFinalized
@@ -271,6 +359,7 @@ impl Error {
             | Error::InvalidChunkProofs(_)
             | Error::InvalidChunkState(_)
             | Error::InvalidChunkStateWitness(_)
+            | Error::InvalidChunkEndorsement
             | Error::InvalidChunkMask
             | Error::InvalidStateRoot
             | Error::InvalidTxRoot
@@ -330,6 +419,7 @@ impl Error {
             Error::IOErr(_) => "io_err",
             Error::Other(_) => "other",
             Error::ValidatorError(_) => "validator_error",
+            Error::ValidatorSignerError(_) => "validator_signer_error",
             Error::EpochOutOfBounds(_) => "epoch_out_of_bounds",
             Error::ChallengedBlockOnChain => "challenged_block_on_chain",
             Error::CannotBeFinalized => "cannot_be_finalized",
@@ -344,6 +434,7 @@ impl Error {
             Error::InvalidChunkProofs(_) => "invalid_chunk_proofs",
             Error::InvalidChunkState(_) => "invalid_chunk_state",
             Error::InvalidChunkStateWitness(_) => "invalid_chunk_state_witness",
+            Error::InvalidChunkEndorsement => "invalid_chunk_endorsement",
             Error::InvalidChunkMask => "invalid_chunk_mask",
             Error::InvalidStateRoot => "invalid_state_root",
             Error::InvalidTxRoot => "invalid_tx_root",
@@ -1,6 +1,7 @@
 use crate::chain::BlockMissingChunks;
 use crate::near_chain_primitives::error::BlockKnownError::KnownInProcessing;
 use crate::orphan::OrphanMissingChunks;
+use crate::update_shard::RecordedStateTransition;
 use crate::Provenance;
 use near_primitives::block::Block;
 use near_primitives::challenge::{ChallengeBody, ChallengesResult};
@@ -66,6 +67,9 @@ pub struct BlockProcessingArtifact {
     pub blocks_missing_chunks: Vec<BlockMissingChunks>,
     pub challenges: Vec<ChallengeBody>,
     pub invalid_chunks: Vec<ShardChunkHeader>,
+    /// State transitions recorded while applying chunks of the processed
+    /// blocks, used by chunk producers to produce state witnesses.
+    pub state_transitions: Vec<RecordedStateTransition>,
 }
 
 /// This struct defines the callback function that will be called after apply chunks are finished
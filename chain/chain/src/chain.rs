@@ -8,7 +8,7 @@ use crate::lightclient::get_epoch_block_producers_view;
 use crate::migrations::check_if_block_is_first_with_chunk_of_version;
 use crate::missing_chunks::MissingChunksPool;
 use crate::orphan::{Orphan, OrphanBlockPool};
-use crate::sharding::shuffle_receipt_proofs;
+use crate::sharding::{group_and_shuffle_receipt_proofs, new_chunk_headers};
 use crate::state_request_tracker::StateRequestTracker;
 use crate::state_snapshot_actor::SnapshotCallbacks;
 use crate::store::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
@@ -19,10 +19,11 @@ use crate::types::{
 };
 pub use crate::update_shard::{
     apply_new_chunk, apply_old_chunk, NewChunkData, NewChunkResult, OldChunkData, OldChunkResult,
-    ShardContext, StorageContext,
+    RecordedStateTransition, ShardContext, StorageContext,
 };
 use crate::update_shard::{
-    process_shard_update, ReshardingData, ShardUpdateReason, ShardUpdateResult,
+    process_shard_update, ReshardingData, ShardBlockUpdateResult, ShardUpdateReason,
+    ShardUpdateResult,
 };
 use crate::validate::{
     validate_challenge, validate_chunk_proofs, validate_chunk_with_chunk_extra,
@@ -38,12 +39,16 @@ use chrono::Duration;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use itertools::Itertools;
 use lru::LruCache;
-use near_chain_configs::{MutableConfigValue, ReshardingConfig, ReshardingHandle};
+use near_chain_configs::{
+    MutableConfigValue, ReshardingConfig, ReshardingHandle, ShadowApplyVariant,
+};
 #[cfg(feature = "new_epoch_sync")]
 use near_chain_primitives::error::epoch_sync::EpochSyncInfoError;
 use near_chain_primitives::error::{BlockKnownError, Error, LogTransientStorageError};
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::types::BlockHeaderInfo;
+#[cfg(feature = "new_epoch_sync")]
+use near_epoch_manager::EpochManager;
 use near_epoch_manager::EpochManagerAdapter;
 use near_o11y::log_assert;
 use near_primitives::block::{genesis_chunks, Block, BlockValidityError, Tip};
@@ -56,7 +61,9 @@ use near_primitives::checked_feature;
 #[cfg(feature = "new_epoch_sync")]
 use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 #[cfg(feature = "new_epoch_sync")]
-use near_primitives::errors::epoch_sync::EpochSyncHashType;
+use near_primitives::errors::epoch_sync::{
+    EpochSyncHashType, EpochSyncInfoError as EpochSyncInfoErr,
+};
 use near_primitives::errors::EpochError;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{
@@ -89,7 +96,7 @@ use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
 use near_primitives::views::{
     BlockStatusView, DroppedReason, ExecutionOutcomeWithIdView, ExecutionStatusView,
     FinalExecutionOutcomeView, FinalExecutionOutcomeWithReceiptView, FinalExecutionStatus,
-    LightClientBlockView, SignedTransactionView,
+    LightClientBlockView, ReceiptProfileView, SignedTransactionView,
 };
 use near_store::config::StateSnapshotType;
 use near_store::flat::{store_helper, FlatStorageReadyStatus, FlatStorageStatus};
@@ -273,6 +280,10 @@ pub struct Chain {
     // A handle that allows the main process to interrupt resharding if needed.
     // This typically happens when the main process is interrupted.
     pub resharding_handle: ReshardingHandle,
+
+    /// If set, new chunks are applied a second time with this configuration
+    /// to compare the timings.
+    shadow_apply: Option<ShadowApplyVariant>,
 }
 
 impl Drop for Chain {
@@ -371,6 +382,7 @@ impl Chain {
                 "resharding_config",
             ),
             resharding_handle: ReshardingHandle::new(),
+            shadow_apply: None,
         })
     }
 
@@ -391,6 +403,7 @@ impl Chain {
             chain_genesis.height,
             chain_config.save_trie_changes,
         );
+        chain_store.set_save_receipt_origins(chain_config.save_receipt_origins);
         let genesis_chunks = genesis_chunks(
             state_roots.clone(),
             &epoch_manager.shard_ids(&EpochId::default())?,
@@ -431,7 +444,9 @@ impl Chain {
                 let mut header_head = store_update.header_head()?;
                 if store_update.get_block_header(&header_head.last_block_hash).is_err() {
                     // Reset header head and "sync" head to be consistent with current block head.
-                    store_update.save_header_head_if_not_challenged(&block_head)?;
+                    if !chain_config.read_only {
+                        store_update.save_header_head_if_not_challenged(&block_head)?;
+                    }
                     header_head = block_head.clone();
                 }
 
@@ -439,6 +454,11 @@ impl Chain {
 
                 (block_head, header_head)
             }
+            Err(Error::DBNotFoundErr(_)) if chain_config.read_only => {
+                return Err(Error::Other(
+                    "Read-only chain requires a database with an initialized head".to_string(),
+                ));
+            }
             Err(Error::DBNotFoundErr(_)) => {
                 for chunk in genesis_chunks {
                     store_update.save_chunk(chunk.clone());
@@ -496,7 +516,9 @@ impl Chain {
             }
             Err(err) => return Err(err),
         };
-        store_update.commit()?;
+        if !chain_config.read_only {
+            store_update.commit()?;
+        }
 
         // We must load in-memory tries here, and not inside runtime, because
         // if we were initializing from genesis, the runtime would be
@@ -548,6 +570,7 @@ impl Chain {
             snapshot_callbacks,
             resharding_config: chain_config.resharding_config,
             resharding_handle: ReshardingHandle::new(),
+            shadow_apply: chain_config.shadow_apply,
         })
     }
 
@@ -684,7 +707,13 @@ impl Chain {
         if shards_to_state_sync.is_empty() {
             Ok(None)
         } else {
-            debug!(target: "chain", "Downloading state for {:?}, I'm {:?}", shards_to_state_sync, me);
+            info!(
+                target: "chain",
+                ?shards_to_state_sync,
+                ?me,
+                epoch_id = ?block.header().epoch_id(),
+                "Catching up the state of the shards tracked in the next epoch",
+            );
 
             let state_sync_info = StateSyncInfo {
                 epoch_tail_hash: *block.header().hash(),
@@ -964,11 +993,17 @@ impl Chain {
 
             // Check that challenges root is empty to ensure later that block doesn't contain challenges.
             // TODO (#2445): Enable challenges when they are working correctly.
-            if header.challenges_root() != &MerkleHash::default() {
-                return Err(Error::InvalidChallengeRoot);
-            }
-            if !header.challenges_result().is_empty() {
-                return Err(Error::InvalidChallenge);
+            // Since `ChunkEndorsementSlashing`, blocks can contain double signed chunk
+            // endorsements, which are checked when the block is preprocessed.
+            let protocol_version =
+                self.epoch_manager.get_epoch_protocol_version(header.epoch_id())?;
+            if !checked_feature!("stable", ChunkEndorsementSlashing, protocol_version) {
+                if header.challenges_root() != &MerkleHash::default() {
+                    return Err(Error::InvalidChallengeRoot);
+                }
+                if !header.challenges_result().is_empty() {
+                    return Err(Error::InvalidChallenge);
+                }
             }
         }
 
@@ -1067,8 +1102,10 @@ impl Chain {
             ) {
                 Ok((hash, account_ids)) => {
                     let is_double_sign = match challenge.body {
-                        // If it's double signed block, we don't invalidate blocks just slash.
-                        ChallengeBody::BlockDoubleSign(_) => true,
+                        // If it's double signed block or chunk endorsement, we don't invalidate
+                        // blocks just slash.
+                        ChallengeBody::BlockDoubleSign(_)
+                        | ChallengeBody::ChunkEndorsementDoubleSign(_) => true,
                         _ => {
                             challenged_blocks.push(hash);
                             false
@@ -1255,28 +1292,14 @@ impl Chain {
         if !self.care_about_any_shard_or_part(me, *block.header().prev_hash())? {
             return Ok(HashMap::new());
         }
-        let height = block.header().height();
-        let mut receipt_proofs_by_shard_id = HashMap::new();
-
-        for chunk_header in block.chunks().iter() {
-            if chunk_header.height_included() != height {
-                continue;
-            }
+        let mut receipt_proofs = Vec::new();
+        for chunk_header in new_chunk_headers(block) {
             let partial_encoded_chunk =
                 self.chain_store.get_partial_chunk(&chunk_header.chunk_hash()).unwrap();
-            for receipt in partial_encoded_chunk.receipts().iter() {
-                let ReceiptProof(_, shard_proof) = receipt;
-                let ShardProof { to_shard_id, .. } = shard_proof;
-                receipt_proofs_by_shard_id
-                    .entry(*to_shard_id)
-                    .or_insert_with(Vec::new)
-                    .push(receipt.clone());
-            }
-        }
-        // sort the receipts deterministically so the order that they will be processed is deterministic
-        for (_, receipt_proofs) in receipt_proofs_by_shard_id.iter_mut() {
-            shuffle_receipt_proofs(receipt_proofs, block.hash());
+            receipt_proofs.extend(partial_encoded_chunk.receipts().iter().cloned());
         }
+        let receipt_proofs_by_shard_id =
+            group_and_shuffle_receipt_proofs(receipt_proofs, block.hash());
 
         Ok(receipt_proofs_by_shard_id)
     }
@@ -1768,6 +1791,7 @@ impl Chain {
                 }
             }
         }
+        let state_transitions = collect_recorded_state_transitions(&apply_results);
         let new_head =
             match self.postprocess_block_only(me, &block, block_preprocess_info, apply_results) {
                 Err(err) => {
@@ -1777,6 +1801,7 @@ impl Chain {
                 }
                 Ok(new_head) => new_head,
             };
+        block_processing_artifacts.state_transitions.extend(state_transitions);
 
         // Update flat storage head to be the last final block. Note that this update happens
         // in a separate db transaction from the update from block processing. This is intentional
@@ -2006,6 +2031,18 @@ impl Chain {
             return Err(Error::InvalidGasPrice);
         }
 
+        if checked_feature!("stable", ChunkEndorsementSlashing, protocol_version) {
+            if block.challenges().iter().any(|challenge| {
+                !matches!(challenge.body, ChallengeBody::ChunkEndorsementDoubleSign(_))
+            }) {
+                return Err(Error::InvalidChallenge);
+            }
+            // The header carries the result of the challenges of the previous block.
+            let prev_block_extra = self.chain_store.get_block_extra(&prev_hash)?;
+            if header.challenges_result() != &prev_block_extra.challenges_result {
+                return Err(Error::InvalidChallenge);
+            }
+        }
         let (challenges_result, challenged_blocks) =
             self.verify_challenges(block.challenges(), header.epoch_id(), header.prev_hash())?;
 
@@ -2403,6 +2440,65 @@ impl Chain {
             return Ok(state_part.into());
         }
 
+        let (prev_prev_hash, state_root, part_id) =
+            self.get_state_part_location(shard_id, part_id, sync_hash)?;
+        let current_time = Instant::now();
+        let state_part = self
+            .runtime_adapter
+            .obtain_state_part(shard_id, &prev_prev_hash, &state_root, part_id)
+            .log_storage_error("obtain_state_part fail")?;
+
+        let elapsed_ms = current_time.elapsed().as_millis();
+        self.requested_state_parts.save_state_part_elapsed(
+            &sync_hash,
+            &shard_id,
+            &part_id.idx,
+            elapsed_ms,
+        );
+
+        // Before saving State Part data, we need to make sure we can calculate and save State Header
+        self.get_state_response_header(shard_id, sync_hash)?;
+
+        // Saving the part data
+        let mut store_update = self.chain_store.store().store_update();
+        store_update.set(DBCol::StateParts, &key, &state_part);
+        store_update.commit()?;
+
+        Ok(state_part)
+    }
+
+    /// Computes a state part by traversing the trie of the shard, without a
+    /// state snapshot, and without caching the part. Used by split storage
+    /// archival nodes to serve the state of past epochs from the cold storage,
+    /// where caching would leave parts behind in the hot storage forever.
+    pub fn compute_state_response_part_from_trie(
+        &self,
+        shard_id: ShardId,
+        part_id: u64,
+        sync_hash: CryptoHash,
+    ) -> Result<Vec<u8>, Error> {
+        let _span = tracing::debug_span!(
+            target: "sync",
+            "compute_state_response_part_from_trie",
+            shard_id,
+            part_id,
+            %sync_hash)
+        .entered();
+        let (prev_prev_hash, state_root, part_id) =
+            self.get_state_part_location(shard_id, part_id, sync_hash)?;
+        self.runtime_adapter
+            .obtain_state_part_from_trie(shard_id, &prev_prev_hash, &state_root, part_id)
+            .log_storage_error("obtain_state_part_from_trie fail")
+    }
+
+    /// Returns the hash of the block whose post state root is the state to
+    /// sync, the state root, and the id of the requested part.
+    fn get_state_part_location(
+        &self,
+        shard_id: ShardId,
+        part_id: u64,
+        sync_hash: CryptoHash,
+    ) -> Result<(CryptoHash, StateRoot, PartId), Error> {
         let block = self
             .get_block(&sync_hash)
             .log_storage_error("block has already been checked for existence")?;
@@ -2427,30 +2523,7 @@ impl Chain {
         if part_id >= num_parts {
             return Err(shard_id_out_of_bounds(shard_id));
         }
-        let current_time = Instant::now();
-        let state_part = self
-            .runtime_adapter
-            .obtain_state_part(
-                shard_id,
-                &prev_prev_hash,
-                &state_root,
-                PartId::new(part_id, num_parts),
-            )
-            .log_storage_error("obtain_state_part fail")?;
-
-        let elapsed_ms = current_time.elapsed().as_millis();
-        self.requested_state_parts
-            .save_state_part_elapsed(&sync_hash, &shard_id, &part_id, elapsed_ms);
-
-        // Before saving State Part data, we need to make sure we can calculate and save State Header
-        self.get_state_response_header(shard_id, sync_hash)?;
-
-        // Saving the part data
-        let mut store_update = self.chain_store.store().store_update();
-        store_update.set(DBCol::StateParts, &key, &state_part);
-        store_update.commit()?;
-
-        Ok(state_part)
+        Ok((prev_prev_hash, state_root, PartId::new(part_id, num_parts)))
     }
 
     pub fn set_state_header(
@@ -3061,7 +3134,20 @@ impl Chain {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(FinalExecutionOutcomeWithReceiptView { final_outcome, receipts })
+        let mut receipt_profiles = vec![];
+        for outcome in &final_outcome.receipts_outcome {
+            if let Some(profile) =
+                self.chain_store.get_receipt_profile(&outcome.id, &outcome.block_hash)?
+            {
+                receipt_profiles.push(ReceiptProfileView::new(
+                    outcome.id,
+                    outcome.block_hash,
+                    profile,
+                ));
+            }
+        }
+
+        Ok(FinalExecutionOutcomeWithReceiptView { final_outcome, receipts, receipt_profiles })
     }
 
     pub fn check_blocks_final_and_canonical(
@@ -3171,38 +3257,6 @@ impl Chain {
             .collect()
     }
 
-    /// Returns sequence of blocks in chain from `last_block_hash` (inclusive)
-    /// until the block with height `first_block_height` (inclusive if `include_with_height`
-    /// is true). For each block hash in resulting `Vec`, next entry contains hash of its
-    /// parent on chain.
-    /// TODO(logunov): consider uniting with `get_incoming_receipts_for_shard` because it
-    /// has the same purpose.
-    pub fn get_blocks_until_height(
-        &self,
-        mut last_block_hash: CryptoHash,
-        first_block_height: BlockHeight,
-        include_with_height: bool,
-    ) -> Result<Vec<CryptoHash>, Error> {
-        let mut blocks = vec![];
-        loop {
-            let header = self.get_block_header(&last_block_hash)?;
-            if header.height() < first_block_height {
-                return Err(Error::InvalidBlockHeight(first_block_height));
-            }
-
-            if header.height() == first_block_height {
-                break;
-            }
-
-            blocks.push(last_block_hash);
-            last_block_hash = *header.prev_hash();
-        }
-        if include_with_height {
-            blocks.push(last_block_hash);
-        }
-        Ok(blocks)
-    }
-
     /// Checks whether `me` is chunk producer for this or next epoch, given
     /// block header which is not in DB yet. If this is the case, node must
     /// produce necessary data for state witness.
@@ -3472,6 +3526,7 @@ impl Chain {
 
         let runtime = self.runtime_adapter.clone();
         let epoch_manager = self.epoch_manager.clone();
+        let shadow_apply = self.shadow_apply;
         Ok(Some((
             shard_id,
             Box::new(move |parent_span| -> Result<ShardUpdateResult, Error> {
@@ -3481,6 +3536,7 @@ impl Chain {
                     epoch_manager.as_ref(),
                     shard_update_reason,
                     shard_context,
+                    shadow_apply,
                 )?))
             }),
         )))
@@ -4090,9 +4146,17 @@ impl Chain {
         let mut headers = vec![];
         let header_head_height = self.header_head()?.height;
         let max_height = max_height.unwrap_or(header_head_height);
+        let mut prev_hash = *header.hash();
         // TODO: this may be inefficient if there are a lot of skipped blocks.
         for h in header.height() + 1..=max_height {
             if let Ok(header) = self.get_block_header_by_height(h) {
+                // The headers of old heights may be garbage collected, see
+                // `GCConfig::gc_block_headers`, while the heights are still
+                // indexed. Only return headers the peer can connect.
+                if header.prev_hash() != &prev_hash {
+                    break;
+                }
+                prev_hash = *header.hash();
                 headers.push(header.clone());
                 if headers.len() >= max_headers_returned as usize {
                     break;
@@ -4195,11 +4259,18 @@ impl Chain {
 /// Epoch sync specific functions.
 #[cfg(feature = "new_epoch_sync")]
 impl Chain {
-    /// TODO(posvyatokum): validate `epoch_sync_info` before `store_update` commit.
+    /// Validates `epoch_sync_info` and records the headers and epoch infos of
+    /// the epoch, moving the header head to the last block of the epoch.
+    /// The epoch infos of the epoch and of the next one have to be known, either
+    /// from genesis or from the `EpochSyncInfo` of the previous epoch.
     pub fn validate_and_record_epoch_sync_info(
         &mut self,
         epoch_sync_info: &EpochSyncInfo,
     ) -> Result<(), EpochSyncInfoError> {
+        self.validate_epoch_sync_info_epoch_infos(epoch_sync_info)?;
+        epoch_sync_info.validate_headers()?;
+        self.validate_epoch_sync_chunk_validators(epoch_sync_info)?;
+
         let store = self.chain_store().store().clone();
         let epoch_manager = self.epoch_manager.clone();
         let mut chain_store_update = self.chain_store.store_update();
@@ -4235,6 +4306,17 @@ impl Chain {
             chain_store_update.save_block_merkle_tree(*hash, cur_block_merkle_tree.clone());
             prev_hash = hash;
         }
+        // The merkle root in the last header of the epoch, which is signed,
+        // commits to `all_block_hashes`.
+        let epoch_last_header = epoch_sync_info.get_epoch_last_header()?;
+        if cur_block_merkle_tree.root() != *epoch_last_header.block_merkle_root() {
+            return Err(EpochSyncInfoErr::InvalidHeaderChain {
+                hash: *epoch_last_header.hash(),
+                hash_type: EpochSyncHashType::LastEpochBlock,
+                epoch_height: epoch_sync_info.epoch_info.epoch_height(),
+            }
+            .into());
+        }
 
         // save all block data in headers_to_save
 
@@ -4278,12 +4360,81 @@ impl Chain {
         epoch_manager
             .force_update_aggregator(epoch_id, epoch_sync_info.get_epoch_last_finalised_hash()?);
 
-        // TODO(posvyatokum): add EpochSyncInfo validation.
-
         chain_store_update.merge(store_update);
         chain_store_update.commit()?;
         Ok(())
     }
+
+    /// Checks that the epoch infos of the epoch and of the next one in
+    /// `epoch_sync_info` match the ones the node already knows. The headers
+    /// of the epoch are verified against them, and the info of the epoch
+    /// after the next one becomes known once the `EpochSyncInfo` is recorded.
+    fn validate_epoch_sync_info_epoch_infos(
+        &self,
+        epoch_sync_info: &EpochSyncInfo,
+    ) -> Result<(), EpochSyncInfoError> {
+        for (epoch_id, epoch_info) in [
+            (epoch_sync_info.get_epoch_id()?, &epoch_sync_info.epoch_info),
+            (epoch_sync_info.get_next_epoch_id()?, &epoch_sync_info.next_epoch_info),
+        ] {
+            let known_epoch_info =
+                self.epoch_manager.get_epoch_info(epoch_id).map_err(Error::from)?;
+            if known_epoch_info.as_ref() != epoch_info {
+                return Err(EpochSyncInfoErr::InvalidEpochInfo {
+                    epoch_height: epoch_info.epoch_height(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the chunk validators of every chunk included in the saved
+    /// headers, recomputed from the epoch info of `epoch_sync_info`, are
+    /// validators of that epoch and match the assignment of the known epoch.
+    /// The node doesn't have the block history of the epoch, so this is the
+    /// only source of the assignments needed to verify chunk endorsements.
+    /// TODO: verify the endorsements themselves once headers carry them.
+    fn validate_epoch_sync_chunk_validators(
+        &self,
+        epoch_sync_info: &EpochSyncInfo,
+    ) -> Result<(), EpochSyncInfoError> {
+        let epoch_info = &epoch_sync_info.epoch_info;
+        if !checked_feature!("stable", ChunkValidation, epoch_info.protocol_version()) {
+            return Ok(());
+        }
+        let epoch_id = epoch_sync_info.get_epoch_id()?;
+        for hash in &epoch_sync_info.headers_to_save {
+            let header = epoch_sync_info.get_header(*hash, EpochSyncHashType::BlockToSave)?;
+            if header.epoch_id() != epoch_id {
+                continue;
+            }
+            for (shard_id, _) in header.chunk_mask().iter().enumerate().filter(|(_, new)| **new) {
+                let shard_id = shard_id as ShardId;
+                let chunk_validators =
+                    EpochManager::chunk_validators_from_info(epoch_info, header.height(), shard_id)
+                        .map_err(Error::from)?;
+                let known_chunk_validators = self
+                    .epoch_manager
+                    .get_chunk_validators(epoch_id, shard_id, header.height())
+                    .map_err(Error::from)?;
+                let is_valid = !chunk_validators.is_empty()
+                    && chunk_validators
+                        .keys()
+                        .all(|account_id| epoch_info.get_validator_id(account_id).is_some())
+                    && chunk_validators == known_chunk_validators;
+                if !is_valid {
+                    return Err(EpochSyncInfoErr::InvalidChunkValidators {
+                        hash: *hash,
+                        shard_id,
+                        epoch_height: epoch_info.epoch_height(),
+                    }
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 pub fn do_apply_chunks(
@@ -4310,6 +4461,26 @@ where
     receipt_proofs.into_iter().flat_map(|ReceiptProof(receipts, _)| receipts).cloned().collect()
 }
 
+/// Copies out the state transitions recorded while applying chunks of a
+/// block, so that they can be handed over to the client for producing state
+/// witnesses.
+fn collect_recorded_state_transitions(
+    apply_results: &[(ShardId, Result<ShardUpdateResult, Error>)],
+) -> Vec<RecordedStateTransition> {
+    apply_results
+        .iter()
+        .filter_map(|(_, result)| match result {
+            Ok(ShardUpdateResult::Stateful(ShardBlockUpdateResult::NewChunk(result))) => {
+                result.state_transition.clone()
+            }
+            Ok(ShardUpdateResult::Stateful(ShardBlockUpdateResult::OldChunk(result))) => {
+                result.state_transition.clone()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn collect_receipts_from_response(
     receipt_proof_response: &[ReceiptProofResponse],
 ) -> Vec<Receipt> {
@@ -311,6 +311,7 @@ impl<'a> ChainUpdate<'a> {
                 shard_uid,
                 apply_result,
                 resharding_results,
+                state_transition,
             }) => {
                 let (outcome_root, outcome_paths) =
                     ApplyChunkResult::compute_outcomes_proof(&apply_result.outcomes);
@@ -347,18 +348,18 @@ impl<'a> ChainUpdate<'a> {
                     apply_result.outgoing_receipts,
                 );
                 // Save receipt and transaction results.
+                self.chain_store_update.save_receipt_origins(&apply_result.outcomes)?;
                 self.chain_store_update.save_outcomes_with_proofs(
                     block_hash,
                     shard_id,
                     apply_result.outcomes,
                     outcome_paths,
                 );
-                self.chain_store_update.save_state_transition_data(
-                    *block_hash,
-                    shard_id,
-                    apply_result.proof,
-                    apply_result.applied_receipts_hash,
-                );
+                self.chain_store_update
+                    .save_receipt_profiles(block_hash, apply_result.receipt_profiles);
+                if let Some(state_transition) = state_transition {
+                    self.chain_store_update.save_state_transition_data(state_transition);
+                }
                 if let Some(resharding_results) = resharding_results {
                     self.process_resharding_results(block, &shard_uid, resharding_results)?;
                 }
@@ -367,6 +368,7 @@ impl<'a> ChainUpdate<'a> {
                 shard_uid,
                 apply_result,
                 resharding_results,
+                state_transition,
             }) => {
                 let old_extra = self.chain_store_update.get_chunk_extra(prev_hash, &shard_uid)?;
 
@@ -385,12 +387,9 @@ impl<'a> ChainUpdate<'a> {
 
                 self.chain_store_update.save_chunk_extra(block_hash, &shard_uid, new_extra);
                 self.chain_store_update.save_trie_changes(apply_result.trie_changes);
-                self.chain_store_update.save_state_transition_data(
-                    *block_hash,
-                    shard_uid.shard_id(),
-                    apply_result.proof,
-                    apply_result.applied_receipts_hash,
-                );
+                if let Some(state_transition) = state_transition {
+                    self.chain_store_update.save_state_transition_data(state_transition);
+                }
 
                 if let Some(resharding_config) = resharding_results {
                     self.process_resharding_results(block, &shard_uid, resharding_config)?;
@@ -782,12 +781,15 @@ impl<'a> ChainUpdate<'a> {
             apply_result.outgoing_receipts,
         );
         // Saving transaction results.
+        self.chain_store_update.save_receipt_origins(&apply_result.outcomes)?;
         self.chain_store_update.save_outcomes_with_proofs(
             block_header.hash(),
             shard_id,
             apply_result.outcomes,
             outcome_proofs,
         );
+        self.chain_store_update
+            .save_receipt_profiles(block_header.hash(), apply_result.receipt_profiles);
         // Saving all incoming receipts.
         for receipt_proof_response in incoming_receipts_proofs {
             self.chain_store_update.save_incoming_receipt(
@@ -4,6 +4,7 @@ use std::time::{Duration, Instant};
 
 use crate::doomslug::trackable::TrackableBlockHeightValue;
 use crate::metrics;
+use near_chain_configs::DoomslugAdaptiveTimersConfig;
 use near_client_primitives::debug::{ApprovalAtHeightStatus, ApprovalHistoryEntry};
 use near_crypto::Signature;
 use near_primitives::block::{Approval, ApprovalInner};
@@ -30,6 +31,9 @@ const MAX_HEIGHTS_BEFORE_TO_STORE_APPROVALS: u64 = 20;
 // Maximum amount of historical approvals that we'd keep for debugging purposes.
 const MAX_HISTORY_SIZE: usize = 1000;
 
+/// Minimum number of observations of the approval latency before the skip delay adapts to them.
+const MIN_APPROVAL_LATENCY_OBSERVATIONS: usize = 10;
+
 /// The threshold for doomslug to create a block.
 /// `TwoThirds` means the block can only be produced if at least 2/3 of the stake is approving it,
 ///             and is what should be used in production (and what guarantees finality)
@@ -56,6 +60,64 @@ struct DoomslugTimer {
     min_delay: Duration,
     delay_step: Duration,
     max_delay: Duration,
+    /// If set, adapts `min_delay` to the observed approval latency.
+    approval_latency: Option<ApprovalLatencyTracker>,
+}
+
+/// Tracks how long it takes for the approvals of the tip to reach the threshold after the tip is
+/// set, and adapts the base skip delay to the recent distribution of these latencies. The delay
+/// never goes below twice the endorsement delay, nor above the configured `min_delay`, so a slow
+/// network falls back to the static timers.
+/// Approvals are only sent to the block producer of their target height, so the latency is only
+/// observed at heights the node produces.
+struct ApprovalLatencyTracker {
+    config: DoomslugAdaptiveTimersConfig,
+    latencies: VecDeque<Duration>,
+    /// When the current tip was set, until the latency for it is observed.
+    tip_set_at: Option<Instant>,
+    lower_bound: Duration,
+    upper_bound: Duration,
+    /// The adapted base skip delay.
+    delay: Duration,
+}
+
+impl ApprovalLatencyTracker {
+    fn new(
+        config: DoomslugAdaptiveTimersConfig,
+        endorsement_delay: Duration,
+        min_delay: Duration,
+    ) -> Self {
+        metrics::DOOMSLUG_SKIP_DELAY.set(min_delay.as_millis() as i64);
+        Self {
+            config,
+            latencies: VecDeque::new(),
+            tip_set_at: None,
+            lower_bound: std::cmp::min(2 * endorsement_delay, min_delay),
+            upper_bound: min_delay,
+            delay: min_delay,
+        }
+    }
+
+    fn observe(&mut self, latency: Duration) {
+        metrics::DOOMSLUG_APPROVAL_LATENCY.observe(latency.as_secs_f64());
+        while self.latencies.len() >= self.config.window_size {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+        if self.latencies.len()
+            < std::cmp::min(MIN_APPROVAL_LATENCY_OBSERVATIONS, self.config.window_size)
+        {
+            return;
+        }
+
+        let mut latencies = self.latencies.iter().copied().collect::<Vec<_>>();
+        latencies.sort();
+        let p90 = latencies[(latencies.len() * 9 / 10).min(latencies.len() - 1)];
+        self.delay = p90
+            .saturating_mul(self.config.latency_multiplier)
+            .clamp(self.lower_bound, self.upper_bound);
+        metrics::DOOMSLUG_SKIP_DELAY.set(self.delay.as_millis() as i64);
+    }
 }
 
 struct DoomslugTip {
@@ -137,6 +199,9 @@ pub struct Doomslug {
     /// Information to track the timer (see `start_timer` routine in the paper)
     timer: DoomslugTimer,
     signer: Option<Arc<dyn ValidatorSigner>>,
+    /// Approvals are only signed for larger target heights, see
+    /// `set_approval_height_floor`.
+    approval_height_floor: BlockHeight,
     /// How many approvals to have before producing a block. In production should be always `HalfStake`,
     ///    but for many tests we use `NoApprovals` to invoke more forkfulness
     threshold_mode: DoomslugThresholdMode,
@@ -157,7 +222,8 @@ impl DoomslugTimer {
     /// Duration to sleep
     pub fn get_delay(&self, n: BlockHeightDelta) -> Duration {
         let n32 = u32::try_from(n).unwrap_or(u32::MAX);
-        std::cmp::min(self.max_delay, self.min_delay + self.delay_step * n32.saturating_sub(2))
+        let min_delay = self.approval_latency.as_ref().map_or(self.min_delay, |x| x.delay);
+        std::cmp::min(self.max_delay, min_delay + self.delay_step * n32.saturating_sub(2))
     }
 }
 
@@ -360,6 +426,7 @@ impl Doomslug {
         min_delay: Duration,
         delay_step: Duration,
         max_delay: Duration,
+        adaptive_timers: Option<DoomslugAdaptiveTimersConfig>,
         signer: Option<Arc<dyn ValidatorSigner>>,
         threshold_mode: DoomslugThresholdMode,
     ) -> Self {
@@ -388,13 +455,30 @@ impl Doomslug {
                 min_delay,
                 delay_step,
                 max_delay,
+                approval_latency: adaptive_timers.map(|config| {
+                    ApprovalLatencyTracker::new(config, endorsement_delay, min_delay)
+                }),
             },
             signer,
+            approval_height_floor: 0,
             threshold_mode,
             history: VecDeque::new(),
         }
     }
 
+    /// Replaces the signer used to sign approvals, e.g. after the validator
+    /// key was reloaded.
+    pub fn set_signer(&mut self, signer: Option<Arc<dyn ValidatorSigner>>) {
+        self.signer = signer;
+    }
+
+    /// Stops signing approvals for target heights up to `height`, e.g.
+    /// because another node holding the same validator key signed them.
+    pub fn set_approval_height_floor(&mut self, height: BlockHeight) {
+        self.approval_height_floor = std::cmp::max(self.approval_height_floor, height);
+        self.largest_target_height.set(std::cmp::max(height, self.largest_target_height.get()));
+    }
+
     #[cfg(feature = "test_features")]
     pub fn adv_disable(&mut self) {
         self.threshold_mode = DoomslugThresholdMode::NoApprovals
@@ -531,9 +615,15 @@ impl Doomslug {
     }
 
     fn create_approval(&self, target_height: BlockHeight) -> Option<Approval> {
-        self.signer.as_ref().map(|signer| {
-            Approval::new(self.tip.block_hash, self.tip.height, target_height, &**signer)
-        })
+        if target_height <= self.approval_height_floor {
+            return None;
+        }
+        let signer = self.signer.as_ref()?;
+        Approval::new(self.tip.block_hash, self.tip.height, target_height, &**signer)
+            .map_err(|err| {
+                tracing::error!(target: "doomslug", target_height, %err, "Failed to sign approval")
+            })
+            .ok()
     }
 
     /// Determines whether a block has enough approvals to be produced.
@@ -614,6 +704,9 @@ impl Doomslug {
         self.largest_final_height.set(last_final_height);
         self.timer.height = height + 1;
         self.timer.started = now;
+        if let Some(approval_latency) = &mut self.timer.approval_latency {
+            approval_latency.tip_set_at = Some(now);
+        }
 
         self.approval_tracking.retain(|h, _| {
             *h > height.saturating_sub(MAX_HEIGHTS_BEFORE_TO_STORE_APPROVALS)
@@ -644,15 +737,33 @@ impl Doomslug {
             self.largest_approval_height.set(approval.target_height);
         }
 
-        if ret != DoomslugBlockProductionReadiness::NotReady {
+        if let DoomslugBlockProductionReadiness::ReadySince(when) = ret {
             if approval.target_height > self.largest_threshold_height.get() {
                 self.largest_threshold_height.set(approval.target_height);
             }
+            self.observe_approval_latency(when, approval);
         }
 
         ret
     }
 
+    /// Observes the time it took for the endorsements of the tip to reach the threshold, if the
+    /// approval endorses the tip and the latency wasn't observed for the tip yet. Approvals that
+    /// reached the threshold before the tip was set count as zero latency.
+    fn observe_approval_latency(&mut self, threshold_time: Instant, approval: &Approval) {
+        let Some(approval_latency) = &mut self.timer.approval_latency else {
+            return;
+        };
+        if approval.target_height != self.tip.height + 1
+            || approval.inner != ApprovalInner::Endorsement(self.tip.block_hash)
+        {
+            return;
+        }
+        if let Some(tip_set_at) = approval_latency.tip_set_at.take() {
+            approval_latency.observe(threshold_time.saturating_duration_since(tip_set_at));
+        }
+    }
+
     /// Processes single approval
     pub fn on_approval_message(
         &mut self,
@@ -774,6 +885,7 @@ mod tests {
     use std::sync::Arc;
     use std::time::Duration;
 
+    use near_chain_configs::DoomslugAdaptiveTimersConfig;
     use near_crypto::{KeyType, SecretKey};
     use near_primitives::block::{Approval, ApprovalInner};
     use near_primitives::hash::hash;
@@ -794,6 +906,7 @@ mod tests {
             Duration::from_millis(1000),
             Duration::from_millis(100),
             Duration::from_millis(3000),
+            None,
             Some(Arc::new(create_test_signer("test"))),
             DoomslugThresholdMode::TwoThirds,
         );
@@ -914,6 +1027,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_approval_height_floor() {
+        let mut ds = Doomslug::new(
+            0,
+            Duration::from_millis(400),
+            Duration::from_millis(1000),
+            Duration::from_millis(100),
+            Duration::from_millis(3000),
+            None,
+            Some(Arc::new(create_test_signer("test"))),
+            DoomslugThresholdMode::TwoThirds,
+        );
+        ds.set_approval_height_floor(3);
+        assert_eq!(ds.get_largest_target_height(), 3);
+
+        // Neither the endorsement of the tip for height 2 nor the skip to
+        // height 3 are signed, the skip to height 4 is.
+        let now = StaticClock::instant();
+        ds.set_tip(now, hash(&[1]), 1, 1);
+        assert_eq!(ds.process_timer(now + Duration::from_millis(400)), vec![]);
+        assert_eq!(ds.process_timer(now + Duration::from_millis(1000)), vec![]);
+        let approvals = ds.process_timer(now + Duration::from_millis(2100));
+        assert_eq!(approvals.len(), 1);
+        assert_eq!(approvals[0].inner, ApprovalInner::Skip(1));
+        assert_eq!(approvals[0].target_height, 4);
+    }
+
     #[test]
     fn test_doomslug_approvals() {
         let accounts: Vec<(&str, u128, u128)> =
@@ -940,6 +1080,7 @@ mod tests {
             Duration::from_millis(1000),
             Duration::from_millis(100),
             Duration::from_millis(3000),
+            None,
             Some(signer),
             DoomslugThresholdMode::TwoThirds,
         );
@@ -954,7 +1095,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 2, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -964,7 +1105,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -974,7 +1115,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -984,7 +1125,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now + Duration::from_millis(100),
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -994,7 +1135,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[1]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(now),
@@ -1004,7 +1145,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(now),
@@ -1014,7 +1155,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 2, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -1026,7 +1167,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 2, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(now),
@@ -1036,7 +1177,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[2]), 2, 4, &signers[1]),
+                &Approval::new(hash(&[2]), 2, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -1062,13 +1203,13 @@ mod tests {
             .collect::<Vec<_>>();
         let mut tracker = DoomslugApprovalsTrackersAtHeight::new();
 
-        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]);
-        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]);
-        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]);
+        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap();
+        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap();
+        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap();
 
-        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]);
-        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]);
-        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]);
+        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]).unwrap();
+        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]).unwrap();
+        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]).unwrap();
 
         // Process first approval, and then process it again and make sure it works
         tracker.process_approval(
@@ -1260,4 +1401,63 @@ mod tests {
             5
         );
     }
+
+    #[test]
+    fn test_doomslug_adaptive_timers() {
+        let stakes = vec![(
+            ApprovalStake {
+                account_id: "test1".parse().unwrap(),
+                stake_this_epoch: 1,
+                stake_next_epoch: 1,
+                public_key: SecretKey::from_seed(KeyType::ED25519, "test1").public_key(),
+            },
+            false,
+        )];
+        let signer = create_test_signer("test1");
+        let mut ds = Doomslug::new(
+            0,
+            Duration::from_millis(400),
+            Duration::from_millis(1000),
+            Duration::from_millis(100),
+            Duration::from_millis(3000),
+            Some(DoomslugAdaptiveTimersConfig { window_size: 20, latency_multiplier: 3 }),
+            Some(Arc::new(create_test_signer("test"))),
+            DoomslugThresholdMode::TwoThirds,
+        );
+
+        let mut now = StaticClock::instant();
+        let mut height = 0;
+        let mut observe = |ds: &mut Doomslug, latency: Duration| {
+            height += 1;
+            now += Duration::from_millis(1000);
+            ds.set_tip(now, hash(&[height as u8]), height, height);
+            let approval =
+                Approval::new(hash(&[height as u8]), height, height + 1, &signer).unwrap();
+            let _ = ds.on_approval_message_internal(now + latency, &approval, &stakes);
+            // Only the first approval to reach the threshold is observed.
+            let _ = ds.on_approval_message_internal(now + latency * 10, &approval, &stakes);
+        };
+
+        // The delay only adapts after enough observations.
+        for _ in 0..9 {
+            observe(&mut ds, Duration::from_millis(50));
+        }
+        assert_eq!(ds.timer.get_delay(1), Duration::from_millis(1000));
+
+        // On a fast network the delay goes down to twice the endorsement delay.
+        observe(&mut ds, Duration::from_millis(50));
+        assert_eq!(ds.timer.get_delay(1), Duration::from_millis(800));
+        assert_eq!(ds.timer.get_delay(5), Duration::from_millis(1100));
+
+        for _ in 0..10 {
+            observe(&mut ds, Duration::from_millis(300));
+        }
+        assert_eq!(ds.timer.get_delay(1), Duration::from_millis(900));
+
+        // On a slow network it goes back up to the configured delay.
+        for _ in 0..20 {
+            observe(&mut ds, Duration::from_millis(500));
+        }
+        assert_eq!(ds.timer.get_delay(1), Duration::from_millis(1000));
+    }
 }
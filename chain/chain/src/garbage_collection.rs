@@ -2,17 +2,21 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::{fmt, io};
 
+use borsh::BorshDeserialize;
 use near_chain_configs::GCConfig;
 use near_chain_primitives::Error;
+use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
-use near_primitives::block::Block;
+use near_primitives::block::{Block, BlockHeader};
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::get_block_shard_uid;
 use near_primitives::state_sync::{StateHeaderKey, StatePartKey};
-use near_primitives::types::{BlockHeight, BlockHeightDelta, EpochId, NumBlocks, ShardId};
+use near_primitives::types::{
+    AccountId, BlockHeight, BlockHeightDelta, EpochId, NumBlocks, ShardId,
+};
 use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, index_to_bytes};
-use near_store::flat::store_helper;
-use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId};
+use near_store::flat::{store_helper, FlatStorageStatus};
+use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId, Store, CHUNK_TAIL_KEY};
 
 use crate::types::RuntimeAdapter;
 use crate::{metrics, Chain, ChainStoreAccess, ChainStoreUpdate};
@@ -20,15 +24,59 @@ use crate::{metrics, Chain, ChainStoreAccess, ChainStoreUpdate};
 #[derive(Clone)]
 pub enum GCMode {
     Fork(ShardTries),
-    Canonical(ShardTries),
-    StateSync { clear_block_info: bool },
+    /// `gc_block_headers` is whether to delete the block headers of the
+    /// garbage collected heights, see [`GCConfig::gc_block_headers`].
+    Canonical {
+        tries: ShardTries,
+        gc_block_headers: bool,
+    },
+    StateSync {
+        clear_block_info: bool,
+    },
+}
+
+/// Returns whether the block header is retained when the block headers are
+/// garbage collected: the headers of the first and the last block of every
+/// epoch are kept for light client proofs. The hash of the last block of an
+/// epoch is the id of a later epoch, and genesis is the id of the first one.
+fn is_retained_block_header(store: &Store, header: &BlockHeader) -> Result<bool, Error> {
+    Ok(store.exists(DBCol::EpochInfo, header.hash().as_ref())?
+        || store.exists(DBCol::EpochInfo, header.prev_hash().as_ref())?)
+}
+
+/// Deletes the block headers below the chunk tail which the garbage collection
+/// would have deleted if `GCConfig::gc_block_headers` had been enabled from the
+/// start. Only the block headers of the heights garbage collected from now on
+/// are deleted by the garbage collection itself. Returns the number of deleted
+/// headers. Must not be run on an archival node.
+pub fn clear_old_block_headers(store: &Store) -> Result<u64, Error> {
+    let Some(chunk_tail) = store.get_ser::<BlockHeight>(DBCol::BlockMisc, CHUNK_TAIL_KEY)? else {
+        // Nothing was garbage collected yet.
+        return Ok(0);
+    };
+    let mut store_update = store.store_update();
+    let mut num_deleted = 0;
+    for item in store.iter(DBCol::BlockHeader) {
+        let (key, value) = item?;
+        let header = BlockHeader::try_from_slice(&value)?;
+        if header.height() >= chunk_tail || is_retained_block_header(store, &header)? {
+            continue;
+        }
+        store_update.delete(DBCol::BlockHeader, &key);
+        num_deleted += 1;
+        if num_deleted % 10_000 == 0 {
+            std::mem::replace(&mut store_update, store.store_update()).commit()?;
+        }
+    }
+    store_update.commit()?;
+    Ok(num_deleted)
 }
 
 impl fmt::Debug for GCMode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             GCMode::Fork(_) => write!(f, "GCMode::Fork"),
-            GCMode::Canonical(_) => write!(f, "GCMode::Canonical"),
+            GCMode::Canonical { .. } => write!(f, "GCMode::Canonical"),
             GCMode::StateSync { .. } => write!(f, "GCMode::StateSync"),
         }
     }
@@ -110,7 +158,12 @@ impl Chain {
     //    and the Trie is updated with having only Genesis data.
     // 4. State Sync Clearing happens in `reset_data_pre_state_sync()`.
     //
-    pub fn clear_data(&mut self, tries: ShardTries, gc_config: &GCConfig) -> Result<(), Error> {
+    pub fn clear_data(
+        &mut self,
+        tries: ShardTries,
+        gc_config: &GCConfig,
+        me: Option<&AccountId>,
+    ) -> Result<(), Error> {
         let _span = tracing::debug_span!(target: "garbage_collection", "clear_data").entered();
 
         let head = self.chain_store().head()?;
@@ -164,6 +217,7 @@ impl Chain {
                 .collect::<Vec<_>>();
             let epoch_manager = self.epoch_manager.clone();
             let runtime = self.runtime_adapter.clone();
+            let shard_tracker = self.shard_tracker.clone();
             let mut chain_store_update = self.mut_chain_store().store_update();
             if let Some(block_hash) = blocks_current_height.first() {
                 let prev_hash = *chain_store_update.get_block_header(block_hash)?.prev_hash();
@@ -176,13 +230,25 @@ impl Chain {
                     chain_store_update.clear_block_data(
                         epoch_manager.as_ref(),
                         *block_hash,
-                        GCMode::Canonical(tries.clone()),
+                        GCMode::Canonical {
+                            tries: tries.clone(),
+                            gc_block_headers: gc_config.gc_block_headers,
+                        },
                     )?;
                     chain_store_update.clear_resharding_data(
                         runtime.as_ref(),
                         epoch_manager.as_ref(),
                         *block_hash,
                     )?;
+                    if gc_config.gc_untracked_shards {
+                        chain_store_update.clear_untracked_shards_data(
+                            runtime.as_ref(),
+                            epoch_manager.as_ref(),
+                            &shard_tracker,
+                            me,
+                            *block_hash,
+                        )?;
+                    }
                     gc_blocks_remaining -= 1;
                 } else {
                     return Err(Error::GCError(
@@ -359,7 +425,11 @@ impl<'a> ChainStoreUpdate<'a> {
         Ok(())
     }
 
-    fn clear_chunk_data_and_headers(&mut self, min_chunk_height: BlockHeight) -> Result<(), Error> {
+    fn clear_chunk_data_and_headers(
+        &mut self,
+        min_chunk_height: BlockHeight,
+        gc_block_headers: bool,
+    ) -> Result<(), Error> {
         let chunk_tail = self.chunk_tail()?;
         for height in chunk_tail..min_chunk_height {
             let chunk_hashes = self.chain_store().get_all_chunk_hashes_by_height(height)?;
@@ -381,17 +451,24 @@ impl<'a> ChainStoreUpdate<'a> {
                 self.gc_col(DBCol::InvalidChunks, chunk_hash);
             }
 
-            let header_hashes = self.chain_store().get_all_header_hashes_by_height(height)?;
-            for _header_hash in header_hashes {
-                // 3. Delete header_hash-indexed data
-                // TODO #3488: enable
-                //self.gc_col(DBCol::BlockHeader, header_hash.as_bytes());
+            if gc_block_headers {
+                let header_hashes = self.chain_store().get_all_header_hashes_by_height(height)?;
+                for header_hash in header_hashes {
+                    // 3. Delete header_hash-indexed data, except the epoch
+                    // boundaries needed for light client proofs
+                    let header = self.get_block_header(&header_hash)?;
+                    if !is_retained_block_header(self.store(), &header)? {
+                        self.gc_col(DBCol::BlockHeader, header_hash.as_bytes());
+                    }
+                }
             }
 
             // 4. Delete chunks_tail-related data
             let key = index_to_bytes(height);
             self.gc_col(DBCol::ChunkHashesByHeight, &key);
             self.gc_col(DBCol::HeaderHashesByHeight, &key);
+            self.gc_col(DBCol::EndorsedChunks, &key);
+            self.gc_state_witnesses(height)?;
         }
         self.update_chunk_tail(min_chunk_height);
         Ok(())
@@ -515,6 +592,102 @@ impl<'a> ChainStoreUpdate<'a> {
         Ok(())
     }
 
+    /// GC trie state, flat state and in-memory tries of the shards the node no longer tracks.
+    /// Happens on the last block of an epoch, once no block left after GC belongs to an epoch
+    /// in which the node tracks the shard, neither because of the config nor because of the
+    /// validator duties of `me`, and the node doesn't track it in the epoch after the head.
+    fn clear_untracked_shards_data(
+        &mut self,
+        runtime: &dyn RuntimeAdapter,
+        epoch_manager: &dyn EpochManagerAdapter,
+        shard_tracker: &ShardTracker,
+        me: Option<&AccountId>,
+        block_hash: CryptoHash,
+    ) -> Result<(), Error> {
+        if !epoch_manager.is_last_block_in_finished_epoch(&block_hash)? {
+            return Ok(());
+        }
+        let block_info = epoch_manager.get_block_info(&block_hash)?;
+        let shard_layout = epoch_manager.get_shard_layout(block_info.epoch_id())?;
+        let head = self.head()?;
+        if epoch_manager.get_shard_layout(&head.next_epoch_id)? != shard_layout {
+            // The state of the parent shards is removed after resharding.
+            return Ok(());
+        }
+        let flat_storage_manager = runtime.get_flat_storage_manager();
+        let shard_uids_with_state: Vec<_> = shard_layout
+            .shard_uids()
+            .filter(|shard_uid| {
+                flat_storage_manager.get_flat_storage_status(*shard_uid) != FlatStorageStatus::Empty
+            })
+            .collect();
+        if shard_uids_with_state.is_empty() {
+            return Ok(());
+        }
+
+        // The last blocks of the epochs before the epochs of the blocks left after GC, and the
+        // head. The epochs of their next blocks and the epochs after them are all the epochs
+        // in which the state of a shard may still be needed.
+        let mut prev_epoch_last_block_hashes = vec![head.last_block_hash];
+        let mut last_block_hash = head.last_block_hash;
+        while last_block_hash != block_hash {
+            let last_block_info = epoch_manager.get_block_info(&last_block_hash)?;
+            let first_block_hash = last_block_info.epoch_first_block();
+            if last_block_info.height() <= block_info.height()
+                || first_block_hash == &CryptoHash::default()
+            {
+                return Err(Error::GCError(format!(
+                    "block {block_hash} isn't on the canonical chain of the head"
+                )));
+            }
+            last_block_hash = *epoch_manager.get_block_info(first_block_hash)?.prev_hash();
+            prev_epoch_last_block_hashes.push(last_block_hash);
+        }
+        // `ShardTracker` treats errors as not tracking the shard, so make sure the epochs are
+        // known before deleting anything.
+        for hash in &prev_epoch_last_block_hashes {
+            epoch_manager.get_epoch_info(&epoch_manager.get_epoch_id_from_prev_block(hash)?)?;
+            epoch_manager
+                .get_epoch_info(&epoch_manager.get_next_epoch_id_from_prev_block(hash)?)?;
+        }
+        let tracks_shard = |hash: &CryptoHash, shard_id: ShardId| {
+            shard_tracker.care_about_shard(me, hash, shard_id, true)
+                || shard_tracker.will_care_about_shard(me, hash, shard_id, true)
+        };
+
+        let mut store_update = self.store().store_update();
+        let mut num_pending = 0;
+        for shard_uid in shard_uids_with_state {
+            let shard_id = shard_uid.shard_id as ShardId;
+            if tracks_shard(&head.last_block_hash, shard_id) {
+                continue;
+            }
+            if prev_epoch_last_block_hashes.iter().any(|hash| tracks_shard(hash, shard_id)) {
+                num_pending += 1;
+                continue;
+            }
+            tracing::info!(target: "garbage_collection", ?block_hash, ?shard_uid, "GC untracked shard");
+            let tries = runtime.get_tries();
+            tries.delete_trie_for_shard(shard_uid, &mut store_update);
+            tries.unload_mem_tries_for_shard(shard_uid);
+            if !flat_storage_manager.remove_flat_storage_for_shard(shard_uid, &mut store_update)? {
+                // The flat storage of an untracked shard isn't loaded after a restart.
+                store_helper::remove_all_flat_state_values(&mut store_update, shard_uid);
+                store_helper::remove_all_deltas(&mut store_update, shard_uid);
+                store_helper::set_flat_storage_status(
+                    &mut store_update,
+                    shard_uid,
+                    FlatStorageStatus::Empty,
+                );
+            }
+            metrics::GC_UNTRACKED_SHARDS_TOTAL.inc();
+        }
+        metrics::GC_UNTRACKED_SHARDS_PENDING.set(num_pending);
+
+        self.merge(store_update);
+        Ok(())
+    }
+
     // Clearing block data of `block_hash`, if on a fork.
     // Clearing block data of `block_hash.prev`, if on the Canonical Chain.
     pub fn clear_block_data(
@@ -547,7 +720,7 @@ impl<'a> ChainStoreUpdate<'a> {
                         }
                     }
                 }
-                GCMode::Canonical(tries) => {
+                GCMode::Canonical { tries, .. } => {
                     // If the block is on canonical chain, we delete the state that's before applying this block
                     for shard_uid in shard_uids_to_gc {
                         let trie_changes = self.store().get_ser(
@@ -586,6 +759,7 @@ impl<'a> ChainStoreUpdate<'a> {
             let block_shard_id = get_block_shard_id(&block_hash, shard_id);
             self.gc_outgoing_receipts(&block_hash, shard_id);
             self.gc_col(DBCol::IncomingReceipts, &block_shard_id);
+            self.gc_col(DBCol::StateTransitionData, &block_shard_id);
 
             // For incoming State Parts it's done in chain.clear_downloaded_parts()
             // The following code is mostly for outgoing State Parts.
@@ -638,7 +812,7 @@ impl<'a> ChainStoreUpdate<'a> {
                 // 5. Forks only clearing
                 self.dec_block_refcount(block.header().prev_hash())?;
             }
-            GCMode::Canonical(_) => {
+            GCMode::Canonical { gc_block_headers, .. } => {
                 // 6. Canonical Chain only clearing
                 // Delete chunks, chunk-indexed data and block headers
                 let mut min_chunk_height = self.tail()?;
@@ -647,7 +821,7 @@ impl<'a> ChainStoreUpdate<'a> {
                         min_chunk_height = chunk_header.height_created();
                     }
                 }
-                self.clear_chunk_data_and_headers(min_chunk_height)?;
+                self.clear_chunk_data_and_headers(min_chunk_height, gc_block_headers)?;
             }
             GCMode::StateSync { .. } => {
                 // 7. State Sync clearing
@@ -690,6 +864,9 @@ impl<'a> ChainStoreUpdate<'a> {
             // delete DBCol::ChunkExtra based on shard_uid since it's indexed by shard_uid in the storage
             self.gc_col(DBCol::ChunkExtra, &block_shard_id);
 
+            // delete state witness data
+            self.gc_col(DBCol::StateTransitionData, &get_block_shard_id(&block_hash, shard_id));
+
             // delete state parts and state headers
             if let Ok(shard_state_header) =
                 self.chain_store().get_state_header(shard_id, block_hash)
@@ -763,10 +940,26 @@ impl<'a> ChainStoreUpdate<'a> {
         // 4. Delete chunk hashes per height
         let key = index_to_bytes(height);
         self.gc_col(DBCol::ChunkHashesByHeight, &key);
+        self.gc_col(DBCol::EndorsedChunks, &key);
+        self.gc_state_witnesses(height)?;
 
         Ok(())
     }
 
+    /// Deletes the saved state witnesses of the chunks created at the height,
+    /// including those of chunks which never made it into a block.
+    fn gc_state_witnesses(&mut self, height: BlockHeight) -> Result<(), Error> {
+        let keys: Vec<Box<[u8]>> = self
+            .store()
+            .iter_prefix(DBCol::StateWitnesses, &index_to_bytes(height))
+            .map(|item| item.map(|(key, _)| key))
+            .collect::<io::Result<Vec<_>>>()?;
+        for key in keys {
+            self.gc_col(DBCol::StateWitnesses, &key);
+        }
+        Ok(())
+    }
+
     fn gc_col_block_per_height(
         &mut self,
         block_hash: &CryptoHash,
@@ -853,10 +1046,10 @@ impl<'a> ChainStoreUpdate<'a> {
             let outcome_ids =
                 self.chain_store().get_outcomes_by_block_hash_and_shard_id(block_hash, shard_id)?;
             for outcome_id in outcome_ids {
-                self.gc_col(
-                    DBCol::TransactionResultForBlock,
-                    &get_outcome_id_block_hash(&outcome_id, block_hash),
-                );
+                let key = get_outcome_id_block_hash(&outcome_id, block_hash);
+                self.gc_col(DBCol::TransactionResultForBlock, &key);
+                self.gc_col(DBCol::ReceiptProfiles, &key);
+                self.gc_col(DBCol::ReceiptOrigins, outcome_id.as_ref());
             }
             self.gc_col(DBCol::OutcomeIds, &get_block_shard_id(block_hash, shard_id));
         }
@@ -878,13 +1071,10 @@ impl<'a> ChainStoreUpdate<'a> {
                 store_update.delete(col, key);
             }
             DBCol::BlockHeader => {
-                // TODO(#3488) At the moment header sync needs block headers.
-                // However, we want to eventually garbage collect headers.
-                // When that happens we should make sure that block headers is
-                // copied to the cold storage.
+                // Only non-archival nodes garbage collect headers, so cold
+                // storage always gets the headers copied.
                 store_update.delete(col, key);
                 self.chain_store().headers.pop(key);
-                unreachable!();
             }
             DBCol::Block => {
                 store_update.delete(col, key);
@@ -975,6 +1165,18 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::StateTransitionData => {
                 store_update.delete(col, key);
             }
+            DBCol::StateWitnesses => {
+                store_update.delete(col, key);
+            }
+            DBCol::ReceiptProfiles => {
+                store_update.delete(col, key);
+            }
+            DBCol::ReceiptOrigins => {
+                store_update.delete(col, key);
+            }
+            DBCol::EndorsedChunks => {
+                store_update.delete(col, key);
+            }
             DBCol::DbVersion
             | DBCol::BlockMisc
             | DBCol::_GCCount
@@ -2,10 +2,13 @@ pub use block_processing_utils::{BlockProcessingArtifact, DoneApplyChunkCallback
 pub use chain::{check_known, collect_receipts, Chain};
 pub use chain_update::ChainUpdate;
 pub use doomslug::{Doomslug, DoomslugBlockProductionReadiness, DoomslugThresholdMode};
+pub use garbage_collection::clear_old_block_headers;
 pub use lightclient::{create_light_client_block_view, get_epoch_block_producers_view};
 pub use near_chain_primitives::{self, Error};
 pub use near_primitives::receipt::ReceiptResult;
-pub use store::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
+pub use store::{
+    filter_incoming_receipts_for_shard, ChainStore, ChainStoreAccess, ChainStoreUpdate,
+};
 pub use store_validator::{ErrorMessage, StoreValidator};
 pub use types::{Block, BlockHeader, BlockStatus, ChainGenesis, Provenance};
 
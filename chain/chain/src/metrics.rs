@@ -1,7 +1,8 @@
 use near_o11y::metrics::{
     exponential_buckets, processing_time_buckets, try_create_histogram, try_create_histogram_vec,
-    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_gauge,
-    try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter, IntGauge, IntGaugeVec,
+    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
+    try_create_int_gauge, try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec,
 };
 use once_cell::sync::Lazy;
 
@@ -73,6 +74,20 @@ pub static FORK_TAIL_HEIGHT: Lazy<IntGauge> =
     Lazy::new(|| try_create_int_gauge("near_fork_tail_height", "Height of fork tail").unwrap());
 pub static GC_STOP_HEIGHT: Lazy<IntGauge> =
     Lazy::new(|| try_create_int_gauge("near_gc_stop_height", "Target height of gc").unwrap());
+pub static GC_UNTRACKED_SHARDS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_gc_untracked_shards_total",
+        "Number of shards no longer tracked whose state was garbage collected",
+    )
+    .unwrap()
+});
+pub static GC_UNTRACKED_SHARDS_PENDING: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_gc_untracked_shards_pending",
+        "Number of shards no longer tracked whose state is kept until it leaves the GC window",
+    )
+    .unwrap()
+});
 pub static CHUNK_RECEIVED_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_chunk_receive_delay_seconds",
@@ -134,6 +149,24 @@ pub(crate) static LARGEST_APPROVAL_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub(crate) static DOOMSLUG_APPROVAL_LATENCY: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram_with_buckets(
+        "near_doomslug_approval_latency",
+        "Time for approvals of the head to reach the doomslug threshold after the head was set, observed when adaptive timers are enabled",
+        exponential_buckets(0.01, 1.5, 15).unwrap(),
+    )
+    .unwrap()
+});
+
+pub(crate) static DOOMSLUG_SKIP_DELAY: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_doomslug_skip_delay_millis",
+        "Base delay before sending a skip message, adapted to the observed approval latency",
+    )
+    .unwrap()
+});
+
 pub(crate) static LARGEST_FINAL_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge(
         "near_largest_final_height",
@@ -238,3 +271,32 @@ pub(crate) static RESHARDING_STATUS: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub(crate) static SHADOW_APPLY_CHUNK_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_shadow_apply_chunk_time_seconds",
+        "Time to apply a chunk, when it is applied a second time with an alternate configuration",
+        &["shard_id", "variant", "apply"],
+        Some(exponential_buckets(0.001, 1.6, 20).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static SHADOW_APPLY_CHUNK_TIME_RATIO: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_shadow_apply_chunk_time_ratio",
+        "Time to apply a chunk with the alternate configuration divided by the actual time",
+        &["shard_id", "variant"],
+        Some(exponential_buckets(0.1, 1.2, 30).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static SHADOW_APPLY_RESULTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_shadow_apply_results_total",
+        "Chunks applied again with an alternate configuration, by whether the state roots match",
+        &["shard_id", "variant", "result"],
+    )
+    .unwrap()
+});
@@ -8,7 +8,8 @@ use crate::metrics::{
     RESHARDING_BATCH_COUNT, RESHARDING_BATCH_PREPARE_TIME, RESHARDING_BATCH_SIZE,
     RESHARDING_STATUS,
 };
-use crate::Chain;
+use crate::{Chain, ChainStoreAccess};
+use borsh::{BorshDeserialize, BorshSerialize};
 use itertools::Itertools;
 use near_chain_configs::{MutableConfigValue, ReshardingConfig, ReshardingHandle};
 use near_chain_primitives::error::Error;
@@ -24,8 +25,12 @@ use near_store::flat::{
 };
 use near_store::resharding::get_delayed_receipts;
 use near_store::trie::SnapshotError;
-use near_store::{ShardTries, ShardUId, StorageError, Store, Trie, TrieDBStorage, TrieStorage};
-use std::collections::{HashMap, HashSet};
+use near_store::{
+    DBCol, ShardTries, ShardUId, StorageError, Store, Trie, TrieDBStorage, TrieStorage,
+    RESHARDING_PROGRESS_KEY_PREFIX,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use std::time::Duration;
@@ -106,6 +111,40 @@ fn get_checked_account_id_to_shard_uid_fn(
     }
 }
 
+/// Progress of building the states of the children of a parent shard. It is
+/// saved together with every batch, so that resharding resumes from the last
+/// batch after a restart instead of starting over.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReshardingProgress {
+    /// The first block of the epoch in which the parent shard is split.
+    pub sync_hash: CryptoHash,
+    /// The last key of the parent flat storage added to the children states.
+    /// The delta of the last block is applied after the flat storage, so it
+    /// is applied again when resharding resumes, which doesn't change the
+    /// children states.
+    pub last_flat_state_key: Option<Vec<u8>>,
+    /// The state roots of the children after the last batch.
+    pub state_roots: BTreeMap<ShardUId, StateRoot>,
+    pub num_batches: u64,
+    pub num_bytes: u64,
+}
+
+fn resharding_progress_key(shard_uid: ShardUId) -> Vec<u8> {
+    [RESHARDING_PROGRESS_KEY_PREFIX, &shard_uid.to_bytes()].concat()
+}
+
+/// Returns the progress of splitting the parent shard in the epoch starting
+/// with `sync_hash`, if any batch was saved.
+pub fn get_resharding_progress(
+    store: &Store,
+    shard_uid: ShardUId,
+    sync_hash: &CryptoHash,
+) -> Result<Option<ReshardingProgress>, Error> {
+    let progress: Option<ReshardingProgress> =
+        store.get_ser(DBCol::Misc, &resharding_progress_key(shard_uid))?;
+    Ok(progress.filter(|progress| &progress.sync_hash == sync_hash))
+}
+
 // Format of the trie key, value pair that is used in tries.add_values_to_children_states() function
 type TrieEntry = (Vec<u8>, Option<Vec<u8>>);
 
@@ -288,6 +327,7 @@ impl Chain {
     ) -> Result<HashMap<ShardUId, StateRoot>, Error> {
         let ReshardingRequest {
             tries,
+            sync_hash,
             prev_hash,
             prev_prev_hash,
             shard_uid,
@@ -305,6 +345,21 @@ impl Chain {
             .ok_or(Error::InvalidShardId(shard_id))?;
         let mut state_roots: HashMap<_, _> =
             new_shards.iter().map(|shard_uid| (*shard_uid, Trie::EMPTY_ROOT)).collect();
+        let progress = get_resharding_progress(&tries.get_store(), shard_uid, &sync_hash)?;
+        let mut progress = match progress {
+            Some(progress) => {
+                tracing::info!(target: "resharding", ?shard_uid, num_batches = progress.num_batches, "resuming resharding");
+                state_roots = progress.state_roots.iter().map(|(k, v)| (*k, *v)).collect();
+                progress
+            }
+            None => ReshardingProgress {
+                sync_hash,
+                last_flat_state_key: None,
+                state_roots: BTreeMap::new(),
+                num_batches: 0,
+                num_bytes: 0,
+            },
+        };
 
         RESHARDING_STATUS
             .with_label_values(&[&shard_uid.to_string()])
@@ -327,10 +382,17 @@ impl Chain {
         let flat_storage_chunk_view = flat_storage_chunk_view.ok_or_else(|| {
             StorageInconsistentState("Chunk view missing for snapshot flat storage".to_string())
         })?;
-        // Get the flat storage iter and wrap the value in Optional::Some to
-        // match the delta iterator so that they can be chained.
-        let flat_storage_iter = flat_storage_chunk_view.iter_flat_state_entries(None, None);
-        let flat_storage_iter = flat_storage_iter.map_ok(|(key, value)| (key, Some(value)));
+        // Get the flat storage iter, starting after the last key added before
+        // a restart, and wrap the value in Optional::Some to match the delta
+        // iterator so that they can be chained.
+        let from = progress.last_flat_state_key.as_ref().map(|key| [key.as_slice(), &[0]].concat());
+        let last_flat_state_key = RefCell::new(progress.last_flat_state_key.clone());
+        let flat_storage_iter =
+            flat_storage_chunk_view.iter_flat_state_entries(from.as_deref(), None);
+        let flat_storage_iter = flat_storage_iter.map_ok(|(key, value)| {
+            *last_flat_state_key.borrow_mut() = Some(key.clone());
+            (key, Some(value))
+        });
 
         // Get the delta iter and wrap the items in Result to match the flat
         // storage iter so that they can be chained.
@@ -378,7 +440,7 @@ impl Chain {
 
             // Apply the batch - add values to the children shards.
             let TrieUpdateBatch { entries, size } = batch;
-            let store_update = {
+            let mut store_update = {
                 let histogram = RESHARDING_BATCH_APPLY_TIME.with_label_values(&metrics_labels);
                 let _timer = histogram.start_timer();
                 // TODO(#9435): This is highly inefficient as for each key in the batch, we are parsing the account_id
@@ -392,6 +454,13 @@ impl Chain {
                 store_update
             };
 
+            // Save the progress together with the batch.
+            progress.last_flat_state_key = last_flat_state_key.borrow().clone();
+            progress.state_roots = state_roots.iter().map(|(k, v)| (*k, *v)).collect();
+            progress.num_batches += 1;
+            progress.num_bytes += size as u64;
+            store_update.set_ser(DBCol::Misc, &resharding_progress_key(shard_uid), &progress)?;
+
             // Commit the store update.
             {
                 let histogram = RESHARDING_BATCH_COMMIT_TIME.with_label_values(&metrics_labels);
@@ -441,6 +510,10 @@ impl Chain {
         }
         chain_store_update.commit()?;
 
+        let mut store_update = self.chain_store().store().store_update();
+        store_update.delete(DBCol::Misc, &resharding_progress_key(shard_uid));
+        store_update.commit()?;
+
         RESHARDING_STATUS
             .with_label_values(&[&shard_uid.to_string()])
             .set(ReshardingStatus::Finished.into());
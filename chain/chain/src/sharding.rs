@@ -1,7 +1,11 @@
+use near_primitives::block::Block;
 use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::{ReceiptProof, ShardChunkHeader};
+use near_primitives::types::ShardId;
 use rand::seq::SliceRandom;
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
+use std::collections::HashMap;
 
 pub fn shuffle_receipt_proofs<ReceiptProofType>(
     receipt_proofs: &mut Vec<ReceiptProofType>,
@@ -13,10 +17,44 @@ pub fn shuffle_receipt_proofs<ReceiptProofType>(
     receipt_proofs.shuffle(&mut rng);
 }
 
+/// Returns the headers of the chunks that are new in the block, in the order
+/// of their shards. Only these chunks send receipts which are applied after
+/// the block.
+pub fn new_chunk_headers(block: &Block) -> Vec<ShardChunkHeader> {
+    let height = block.header().height();
+    block.chunks().iter().filter(|chunk| chunk.height_included() == height).cloned().collect()
+}
+
+/// Groups the receipt proofs sent by the new chunks of the block with the
+/// given hash by the shard receiving them, and orders each group the way the
+/// receiving shard applies them. `receipt_proofs` must follow the order of
+/// the chunks returned by `new_chunk_headers`.
+///
+/// Both the chunk producers collecting incoming receipts and the chunk
+/// validators checking the source receipt proofs of a state witness go
+/// through this function, so that the receipts are applied in the same order.
+pub fn group_and_shuffle_receipt_proofs(
+    receipt_proofs: impl IntoIterator<Item = ReceiptProof>,
+    block_hash: &CryptoHash,
+) -> HashMap<ShardId, Vec<ReceiptProof>> {
+    let mut receipt_proofs_by_shard_id = HashMap::new();
+    for receipt_proof in receipt_proofs {
+        let to_shard_id = receipt_proof.1.to_shard_id;
+        receipt_proofs_by_shard_id.entry(to_shard_id).or_insert_with(Vec::new).push(receipt_proof);
+    }
+    // sort the receipts deterministically so the order that they will be processed is deterministic
+    for (_, receipt_proofs) in receipt_proofs_by_shard_id.iter_mut() {
+        shuffle_receipt_proofs(receipt_proofs, block_hash);
+    }
+    receipt_proofs_by_shard_id
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::sharding::shuffle_receipt_proofs;
+    use crate::sharding::{group_and_shuffle_receipt_proofs, shuffle_receipt_proofs};
     use near_primitives::hash::CryptoHash;
+    use near_primitives::sharding::{ReceiptProof, ShardProof};
+    use near_primitives::types::ShardId;
 
     #[test]
     pub fn receipt_randomness_reproducibility() {
@@ -25,4 +63,40 @@ mod tests {
         shuffle_receipt_proofs(&mut receipt_proofs, &CryptoHash::hash_bytes(&[1, 2, 3, 4, 5]));
         assert_eq!(receipt_proofs, vec![2, 3, 1, 4, 0, 5, 6],);
     }
+
+    fn receipt_proof(from_shard_id: ShardId, to_shard_id: ShardId) -> ReceiptProof {
+        ReceiptProof(vec![], ShardProof { from_shard_id, to_shard_id, proof: vec![] })
+    }
+
+    #[test]
+    pub fn grouped_receipt_proofs_match_shuffled_chunks() {
+        // Each of the 7 new chunks sends a proof to both shards. The proofs
+        // received by each shard must be ordered the same way as the chunks
+        // they were sent by.
+        let block_hash = CryptoHash::hash_bytes(&[1, 2, 3, 4, 5]);
+        let receipt_proofs =
+            (0..7).flat_map(|from_shard_id| (0..2).map(move |to| receipt_proof(from_shard_id, to)));
+        let by_shard_id = group_and_shuffle_receipt_proofs(receipt_proofs, &block_hash);
+        assert_eq!(by_shard_id.len(), 2);
+
+        let mut chunk_order: Vec<ShardId> = (0..7).collect();
+        shuffle_receipt_proofs(&mut chunk_order, &block_hash);
+        assert_eq!(chunk_order, vec![2, 3, 1, 4, 0, 5, 6]);
+        for (to_shard_id, receipt_proofs) in by_shard_id {
+            let from_shard_ids: Vec<ShardId> =
+                receipt_proofs.iter().map(|proof| proof.1.from_shard_id).collect();
+            assert_eq!(from_shard_ids, chunk_order);
+            assert!(receipt_proofs.iter().all(|proof| proof.1.to_shard_id == to_shard_id));
+        }
+    }
+
+    #[test]
+    pub fn grouped_receipt_proofs_are_deterministic() {
+        let block_hash = CryptoHash::hash_bytes(&[6, 7, 8]);
+        let receipt_proofs: Vec<_> = (0..10).map(|i| receipt_proof(i, i % 3)).collect();
+        assert_eq!(
+            group_and_shuffle_receipt_proofs(receipt_proofs.clone(), &block_hash),
+            group_and_shuffle_receipt_proofs(receipt_proofs, &block_hash),
+        );
+    }
 }
@@ -13,8 +13,9 @@ use std::sync::Arc;
 
 /// Runs tasks related to state snapshots.
 /// There are three main handlers in StateSnapshotActor and they are called in sequence
-/// 1. DeleteSnapshotRequest: deletes a snapshot and optionally calls CreateSnapshotRequest.
-/// 2. CreateSnapshotRequest: creates a new snapshot and optionally calls CompactSnapshotRequest based on config.
+/// 1. DeleteSnapshotRequest: deletes the snapshots, or calls CreateSnapshotRequest instead.
+/// 2. CreateSnapshotRequest: creates a new snapshot, deletes the snapshots beyond retention and
+///    optionally calls CompactSnapshotRequest based on config.
 /// 3. CompactSnapshotRequest: compacts a snapshot store.
 pub struct StateSnapshotActor {
     flat_storage_manager: FlatStorageManager,
@@ -39,7 +40,8 @@ impl actix::Actor for StateSnapshotActor {
 #[derive(actix::Message, Debug)]
 #[rtype(result = "()")]
 struct DeleteAndMaybeCreateSnapshotRequest {
-    /// Optionally send request to create a new snapshot after deleting any existing snapshots.
+    /// Optionally send request to create a new snapshot instead of deleting the existing snapshots.
+    /// The existing snapshots are then deleted only beyond the retention limit.
     create_snapshot_request: Option<CreateSnapshotRequest>,
 }
 
@@ -74,11 +76,12 @@ impl actix::Handler<WithSpanContext<DeleteAndMaybeCreateSnapshotRequest>> for St
 
         // We don't need to acquire any locks on flat storage or snapshot.
         let DeleteAndMaybeCreateSnapshotRequest { create_snapshot_request } = msg;
-        self.tries.delete_state_snapshot();
-
-        // Optionally send a create_snapshot_request after deletion
-        if let Some(create_snapshot_request) = create_snapshot_request {
-            context.address().do_send(create_snapshot_request.with_span_context());
+        match create_snapshot_request {
+            // Creating a snapshot deletes the snapshots beyond retention.
+            Some(create_snapshot_request) => {
+                context.address().do_send(create_snapshot_request.with_span_context());
+            }
+            None => self.tries.delete_state_snapshot(),
         }
     }
 }
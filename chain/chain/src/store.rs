@@ -27,7 +27,7 @@ use near_primitives::state_sync::{
 };
 use near_primitives::transaction::{
     ExecutionOutcomeWithId, ExecutionOutcomeWithIdAndProof, ExecutionOutcomeWithProof,
-    SignedTransaction,
+    ReceiptProfile, SignedTransaction,
 };
 use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::chunk_extra::ChunkExtra;
@@ -42,14 +42,15 @@ use near_primitives::utils::{
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::LightClientBlockView;
 use near_store::{
-    DBCol, KeyForStateChanges, PartialStorage, Store, StoreUpdate, WrappedTrieChanges,
-    CHUNK_TAIL_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY,
-    LARGEST_TARGET_HEIGHT_KEY, LATEST_KNOWN_KEY, TAIL_KEY,
+    DBCol, KeyForStateChanges, Store, StoreUpdate, WrappedTrieChanges, CHUNK_TAIL_KEY,
+    FINAL_HEAD_KEY, FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY,
+    LATEST_KNOWN_KEY, TAIL_KEY,
 };
 
 use crate::byzantine_assert;
 use crate::chunks_store::ReadOnlyChunksStore;
 use crate::types::{Block, BlockHeader, LatestKnown};
+use crate::update_shard::RecordedStateTransition;
 use near_primitives::chunk_validation::StoredChunkStateTransitionData;
 use near_store::db::{StoreStatistics, STATE_SYNC_DUMP_KEY};
 use std::sync::Arc;
@@ -366,7 +367,7 @@ pub trait ChainStoreAccess {
 /// Given a vector of receipts return only the receipts that should be assigned
 /// to the target shard id in the target shard layout. Used when collecting the
 /// incoming receipts and the shard layout changed.
-fn filter_incoming_receipts_for_shard(
+pub fn filter_incoming_receipts_for_shard(
     target_shard_layout: &ShardLayout,
     target_shard_id: u64,
     receipt_proofs: Arc<Vec<ReceiptProof>>,
@@ -450,6 +451,9 @@ pub struct ChainStore {
     /// - archive is true, cold_store is configured and migration to split_storage is finished - node
     /// working in split storage mode needs trie changes in order to do garbage collection on hot.
     save_trie_changes: bool,
+    /// Whether to save the transaction each executed receipt originates from,
+    /// see `DBCol::ReceiptOrigins`.
+    save_receipt_origins: bool,
 }
 
 fn option_to_not_found<T, F>(res: io::Result<Option<T>>, field_name: F) -> Result<T, Error>
@@ -492,9 +496,14 @@ impl ChainStore {
             block_ordinal_to_hash: CellLruCache::new(CACHE_SIZE),
             processed_block_heights: CellLruCache::new(CACHE_SIZE),
             save_trie_changes,
+            save_receipt_origins: false,
         }
     }
 
+    pub fn set_save_receipt_origins(&mut self, save_receipt_origins: bool) {
+        self.save_receipt_origins = save_receipt_origins;
+    }
+
     pub fn new_read_only_chunks_store(&self) -> ReadOnlyChunksStore {
         ReadOnlyChunksStore::new(self.store.clone())
     }
@@ -503,6 +512,38 @@ impl ChainStore {
         ChainStoreUpdate::new(self)
     }
 
+    /// Returns sequence of blocks in chain from `last_block_hash` (inclusive)
+    /// until the block with height `first_block_height` (inclusive if `include_with_height`
+    /// is true). For each block hash in resulting `Vec`, next entry contains hash of its
+    /// parent on chain.
+    /// TODO(logunov): consider uniting with `get_incoming_receipts_for_shard` because it
+    /// has the same purpose.
+    pub fn get_blocks_until_height(
+        &self,
+        mut last_block_hash: CryptoHash,
+        first_block_height: BlockHeight,
+        include_with_height: bool,
+    ) -> Result<Vec<CryptoHash>, Error> {
+        let mut blocks = vec![];
+        loop {
+            let header = self.get_block_header(&last_block_hash)?;
+            if header.height() < first_block_height {
+                return Err(Error::InvalidBlockHeight(first_block_height));
+            }
+
+            if header.height() == first_block_height {
+                break;
+            }
+
+            blocks.push(last_block_hash);
+            last_block_hash = *header.prev_hash();
+        }
+        if include_with_height {
+            blocks.push(last_block_hash);
+        }
+        Ok(blocks)
+    }
+
     pub fn iterate_state_sync_infos(&self) -> Result<Vec<(CryptoHash, StateSyncInfo)>, Error> {
         self.store
             .iter(DBCol::StateDlInfos)
@@ -787,6 +828,24 @@ impl ChainStore {
         )?)
     }
 
+    /// Returns the profile of the receipt executed in the given block, if the
+    /// node saved it.
+    pub fn get_receipt_profile(
+        &self,
+        receipt_id: &CryptoHash,
+        block_hash: &CryptoHash,
+    ) -> Result<Option<ReceiptProfile>, Error> {
+        Ok(self
+            .store
+            .get_ser(DBCol::ReceiptProfiles, &get_outcome_id_block_hash(receipt_id, block_hash))?)
+    }
+
+    /// Returns the hash of the transaction the receipt originates from, if the
+    /// node recorded it.
+    pub fn get_receipt_origin(&self, receipt_id: &CryptoHash) -> Result<Option<CryptoHash>, Error> {
+        Ok(self.store.get_ser(DBCol::ReceiptOrigins, receipt_id.as_ref())?)
+    }
+
     /// Returns a vector of Outcome ids for given block and shard id
     pub fn get_outcomes_by_block_hash_and_shard_id(
         &self,
@@ -1036,6 +1095,29 @@ impl ChainStore {
                 }
                 changes
             }
+            StateChangesRequest::FilteredChanges { filter } => {
+                let mut changes = StateChanges::new();
+                match filter.raw_trie_key_prefixes() {
+                    Some(raw_prefixes) => {
+                        for data_key in raw_prefixes {
+                            let storage_key =
+                                KeyForStateChanges::from_raw_key(block_hash, &data_key);
+                            let changes_per_key_prefix = storage_key.find_iter(&self.store);
+                            changes.extend(StateChanges::from_changes(changes_per_key_prefix)?);
+                        }
+                    }
+                    None => {
+                        let storage_key = KeyForStateChanges::for_block(block_hash);
+                        changes.extend(StateChanges::from_changes(
+                            storage_key.find_iter(&self.store),
+                        )?);
+                    }
+                }
+                changes.retain(|change| {
+                    filter.matches(change.value.affected_account_id(), change.value.data_key())
+                });
+                changes
+            }
         })
     }
 
@@ -1440,6 +1522,8 @@ pub struct ChainStoreUpdate<'a> {
     largest_target_height: Option<BlockHeight>,
     trie_changes: Vec<WrappedTrieChanges>,
     state_transition_data: HashMap<(CryptoHash, ShardId), StoredChunkStateTransitionData>,
+    receipt_profiles: HashMap<(CryptoHash, CryptoHash), ReceiptProfile>,
+    receipt_origins: HashMap<CryptoHash, CryptoHash>,
     // All state changes made by a chunk, this is only used for resharding.
     add_state_changes_for_resharding: HashMap<(CryptoHash, ShardId), StateChangesForResharding>,
     remove_state_changes_for_resharding: HashSet<(CryptoHash, ShardId)>,
@@ -1468,6 +1552,8 @@ impl<'a> ChainStoreUpdate<'a> {
             largest_target_height: None,
             trie_changes: vec![],
             state_transition_data: Default::default(),
+            receipt_profiles: Default::default(),
+            receipt_origins: Default::default(),
             add_state_changes_for_resharding: HashMap::new(),
             remove_state_changes_for_resharding: HashSet::new(),
             add_blocks_to_catchup: vec![],
@@ -2074,26 +2160,60 @@ impl<'a> ChainStoreUpdate<'a> {
         self.chain_store_cache_update.outcome_ids.insert((*block_hash, shard_id), outcome_ids);
     }
 
-    pub fn save_trie_changes(&mut self, trie_changes: WrappedTrieChanges) {
-        self.trie_changes.push(trie_changes);
+    pub fn save_receipt_profiles(
+        &mut self,
+        block_hash: &CryptoHash,
+        receipt_profiles: Vec<(CryptoHash, ReceiptProfile)>,
+    ) {
+        for (receipt_id, profile) in receipt_profiles {
+            self.receipt_profiles.insert((receipt_id, *block_hash), profile);
+        }
     }
 
-    pub fn save_state_transition_data(
+    /// Records the transaction the receipts generated by the outcomes
+    /// originate from, if enabled. The origin of a receipt outcome is only
+    /// known if the node recorded it when the receipt was generated, so it
+    /// must be called with the outcomes in the order they were produced.
+    pub fn save_receipt_origins(
         &mut self,
-        block_hash: CryptoHash,
-        shard_id: ShardId,
-        partial_storage: Option<PartialStorage>,
-        applied_receipts_hash: CryptoHash,
-    ) {
-        if let Some(partial_storage) = partial_storage {
-            self.state_transition_data.insert(
-                (block_hash, shard_id),
-                StoredChunkStateTransitionData {
-                    base_state: partial_storage.nodes,
-                    receipts_hash: applied_receipts_hash,
+        outcomes: &[ExecutionOutcomeWithId],
+    ) -> Result<(), Error> {
+        if !self.chain_store.save_receipt_origins {
+            return Ok(());
+        }
+        for outcome_with_id in outcomes {
+            let id = &outcome_with_id.id;
+            let origin = match self.receipt_origins.get(id) {
+                Some(origin) => Some(*origin),
+                None => match self.chain_store.get_receipt_origin(id)? {
+                    Some(origin) => Some(origin),
+                    None => self.get_transaction(id)?.map(|_| *id),
                 },
-            );
+            };
+            let Some(origin) = origin else {
+                continue;
+            };
+            for receipt_id in &outcome_with_id.outcome.receipt_ids {
+                self.receipt_origins.insert(*receipt_id, origin);
+            }
         }
+        Ok(())
+    }
+
+    pub fn save_trie_changes(&mut self, trie_changes: WrappedTrieChanges) {
+        self.trie_changes.push(trie_changes);
+    }
+
+    pub fn save_state_transition_data(&mut self, state_transition: RecordedStateTransition) {
+        let RecordedStateTransition { shard_id, transition, applied_receipts_hash } =
+            state_transition;
+        self.state_transition_data.insert(
+            (transition.block_hash, shard_id),
+            StoredChunkStateTransitionData {
+                base_state: transition.base_state,
+                receipts_hash: applied_receipts_hash,
+            },
+        );
     }
 
     pub fn add_state_changes_for_resharding(
@@ -2552,6 +2672,16 @@ impl<'a> ChainStoreUpdate<'a> {
                 &state_transition_data,
             )?;
         }
+        for ((receipt_id, block_hash), profile) in self.receipt_profiles.drain() {
+            store_update.set_ser(
+                DBCol::ReceiptProfiles,
+                &get_outcome_id_block_hash(&receipt_id, &block_hash),
+                &profile,
+            )?;
+        }
+        for (receipt_id, origin) in self.receipt_origins.drain() {
+            store_update.set_ser(DBCol::ReceiptOrigins, receipt_id.as_ref(), &origin)?;
+        }
         for ((block_hash, shard_id), state_changes) in self.add_state_changes_for_resharding.drain()
         {
             store_update.set_ser(
@@ -466,6 +466,16 @@ pub(crate) fn canonical_header_validity(
     height: &BlockHeight,
     hash: &CryptoHash,
 ) -> Result<(), StoreValidatorError> {
+    if *height < sv.inner.chunk_tail
+        && !unwrap_or_err!(
+            sv.store.exists(DBCol::BlockHeader, hash.as_ref()),
+            "DB error while checking Block Header {:?} in DBCol::BlockHeader",
+            hash
+        )
+    {
+        // The header may be garbage collected
+        return Ok(());
+    }
     let header = unwrap_or_err_db!(
         sv.store.get_ser::<BlockHeader>(DBCol::BlockHeader, hash.as_ref()),
         "Can't get Block Header {:?} from DBCol::BlockHeader",
@@ -483,12 +493,32 @@ pub(crate) fn canonical_prev_block_validity(
     hash: &CryptoHash,
 ) -> Result<(), StoreValidatorError> {
     if *height != sv.config.genesis_height {
+        // The headers below the chunk tail may be garbage collected
+        let may_be_gced = *height <= sv.inner.chunk_tail;
+        if may_be_gced
+            && !unwrap_or_err!(
+                sv.store.exists(DBCol::BlockHeader, hash.as_ref()),
+                "DB error while checking Block Header {:?} in DBCol::BlockHeader",
+                hash
+            )
+        {
+            return Ok(());
+        }
         let header = unwrap_or_err_db!(
             sv.store.get_ser::<BlockHeader>(DBCol::BlockHeader, hash.as_ref()),
             "Can't get Block Header {:?} from DBCol::BlockHeader",
             hash
         );
         let prev_hash = *header.prev_hash();
+        if may_be_gced
+            && !unwrap_or_err!(
+                sv.store.exists(DBCol::BlockHeader, prev_hash.as_ref()),
+                "DB error while checking prev Block Header {:?} in DBCol::BlockHeader",
+                prev_hash
+            )
+        {
+            return Ok(());
+        }
         let prev_header = unwrap_or_err_db!(
             sv.store.get_ser::<BlockHeader>(DBCol::BlockHeader, prev_hash.as_ref()),
             "Can't get prev Block Header {:?} from DBCol::BlockHeader",
@@ -1,7 +1,7 @@
 use super::ValidatorSchedule;
 use crate::types::{
     ApplyChunkBlockContext, ApplyChunkResult, ApplyChunkShardContext, ApplyResultForResharding,
-    RuntimeAdapter, RuntimeStorageConfig,
+    PreparedTransactions, RuntimeAdapter, RuntimeStorageConfig,
 };
 use crate::BlockHeader;
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -38,7 +38,7 @@ use near_primitives::validator_mandates::AssignmentWeight;
 use near_primitives::version::{ProtocolVersion, PROTOCOL_VERSION};
 use near_primitives::views::{
     AccessKeyInfoView, AccessKeyList, CallResult, ContractCodeView, EpochValidatorInfo,
-    QueryRequest, QueryResponse, QueryResponseKind, ViewStateResult,
+    QueryRequest, QueryResponse, QueryResponseKind, TransactionEstimateView, ViewStateResult,
 };
 use near_store::test_utils::TestTriesBuilder;
 use near_store::{
@@ -114,6 +114,8 @@ struct EpochValidatorSet {
     block_producers: Vec<ValidatorStake>,
     /// index of this list is shard_id
     chunk_producers: Vec<Vec<ValidatorStake>>,
+    /// index of this list is shard_id
+    chunk_validators: Vec<Vec<ValidatorStake>>,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Hash, PartialEq, Eq, Ord, PartialOrd, Clone, Debug)]
@@ -172,7 +174,9 @@ impl MockEpochManager {
                     })
                     .collect();
 
-                EpochValidatorSet { block_producers, chunk_producers }
+                let chunk_validators = vec![vec![]; vs.num_shards as usize];
+
+                EpochValidatorSet { block_producers, chunk_producers, chunk_validators }
             })
             .collect();
 
@@ -196,6 +200,26 @@ impl MockEpochManager {
             }
         }
 
+        if !vs.chunk_validators.is_empty() {
+            assert_eq!(validators_by_valset.len(), vs.chunk_validators.len());
+            for (epoch_idx, epoch_cvs) in vs.chunk_validators.into_iter().enumerate() {
+                assert_eq!(epoch_cvs.len() as u64, vs.num_shards);
+                for (shard_idx, shard_cvs) in epoch_cvs.into_iter().enumerate() {
+                    for account_id in shard_cvs {
+                        let stake = ValidatorStake::new(
+                            account_id.clone(),
+                            SecretKey::from_seed(KeyType::ED25519, account_id.as_ref())
+                                .public_key(),
+                            1_000_000,
+                        );
+                        let prev = validators.insert(account_id, stake.clone());
+                        assert!(prev.is_none(), "chunk validator is also a producer");
+                        validators_by_valset[epoch_idx].chunk_validators[shard_idx].push(stake)
+                    }
+                }
+            }
+        }
+
         Arc::new(MockEpochManager {
             store,
             num_shards: vs.num_shards,
@@ -478,6 +502,7 @@ impl EpochManagerAdapter for MockEpochManager {
             protocol_upgrade_stake_threshold: Ratio::new(3i32, 4i32),
             shard_layout: ShardLayout::v1_test(),
             validator_selection_config: ValidatorSelectionConfig::default(),
+            chunk_validation_config: Default::default(),
         })
     }
 
@@ -685,6 +710,14 @@ impl EpochManagerAdapter for MockEpochManager {
         Ok(vec![])
     }
 
+    fn get_epoch_chunk_validators(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<Vec<ValidatorStake>, EpochError> {
+        let validators = &self.validators_by_valset[self.get_valset_for_epoch(epoch_id)?];
+        Ok(validators.chunk_validators.iter().flatten().cloned().collect())
+    }
+
     fn get_block_producer(
         &self,
         epoch_id: &EpochId,
@@ -708,11 +741,15 @@ impl EpochManagerAdapter for MockEpochManager {
 
     fn get_chunk_validators(
         &self,
-        _epoch_id: &EpochId,
-        _shard_id: ShardId,
+        epoch_id: &EpochId,
+        shard_id: ShardId,
         _height: BlockHeight,
     ) -> Result<HashMap<AccountId, AssignmentWeight>, EpochError> {
-        Ok(HashMap::new())
+        let validators = &self.validators_by_valset[self.get_valset_for_epoch(epoch_id)?];
+        Ok(validators.chunk_validators[shard_id as usize]
+            .iter()
+            .map(|stake| (stake.account_id().clone(), AssignmentWeight::new(1, 0)))
+            .collect())
     }
 
     fn get_validator_by_account_id(
@@ -732,6 +769,11 @@ impl EpochManagerAdapter for MockEpochManager {
                 return Ok((validator_stake.clone(), false));
             }
         }
+        for validator_stake in validators.chunk_validators.iter().flatten() {
+            if validator_stake.account_id() == account_id {
+                return Ok((validator_stake.clone(), false));
+            }
+        }
         Err(EpochError::NotAValidator(account_id.clone(), epoch_id.clone()))
     }
 
@@ -760,6 +802,14 @@ impl EpochManagerAdapter for MockEpochManager {
         })
     }
 
+    fn get_estimated_validators(
+        &self,
+        _block_hash: &CryptoHash,
+        _epoch_offset: EpochHeight,
+    ) -> Result<Arc<EpochInfo>, EpochError> {
+        self.get_epoch_info(&EpochId::default())
+    }
+
     fn add_validator_proposals(
         &self,
         _block_header_info: BlockHeaderInfo,
@@ -1032,12 +1082,13 @@ impl RuntimeAdapter for KeyValueRuntime {
         _chain_validate: &mut dyn FnMut(&SignedTransaction) -> bool,
         _current_protocol_version: ProtocolVersion,
         _time_limit: Option<Duration>,
-    ) -> Result<Vec<SignedTransaction>, Error> {
+        _storage_proof_size_limit: Option<usize>,
+    ) -> Result<PreparedTransactions, Error> {
         let mut res = vec![];
         while let Some(iter) = transactions.next() {
             res.push(iter.next().unwrap());
         }
-        Ok(res)
+        Ok(PreparedTransactions { transactions: res, limited_by: None })
     }
 
     fn apply_chunk(
@@ -1199,6 +1250,8 @@ impl RuntimeAdapter for KeyValueRuntime {
             proof: None,
             processed_delayed_receipts: vec![],
             applied_receipts_hash: hash(&borsh::to_vec(receipts).unwrap()),
+            fallback_trie_nodes: vec![],
+            receipt_profiles: vec![],
         })
     }
 
@@ -1265,6 +1318,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                 kind: QueryResponseKind::CallResult(CallResult {
                     result: Default::default(),
                     logs: Default::default(),
+                    gas_burnt: 0,
                 }),
                 block_height,
                 block_hash: *block_hash,
@@ -1287,6 +1341,16 @@ impl RuntimeAdapter for KeyValueRuntime {
         Ok(data)
     }
 
+    fn obtain_state_part_from_trie(
+        &self,
+        shard_id: ShardId,
+        block_hash: &CryptoHash,
+        state_root: &StateRoot,
+        part_id: PartId,
+    ) -> Result<Vec<u8>, Error> {
+        self.obtain_state_part(shard_id, block_hash, state_root, part_id)
+    }
+
     fn validate_state_part(&self, _state_root: &StateRoot, _part_id: PartId, _data: &[u8]) -> bool {
         // We do not care about deeper validation in test_utils
         true
@@ -1392,4 +1456,31 @@ impl RuntimeAdapter for KeyValueRuntime {
     fn load_mem_tries_on_startup(&self, _shard_uids: &[ShardUId]) -> Result<(), StorageError> {
         Ok(())
     }
+
+    fn precompile_contracts_speculatively(
+        &self,
+        _epoch_id: &EpochId,
+        _shard_uid: ShardUId,
+        _state_root: StateRoot,
+        _account_ids: Vec<AccountId>,
+    ) {
+    }
+
+    fn estimate_transaction(
+        &self,
+        _block_header: &BlockHeader,
+        _signer_state: (ShardUId, StateRoot),
+        _receiver_state: Option<(ShardUId, StateRoot)>,
+        _transaction: &SignedTransaction,
+        _verify_signature: bool,
+    ) -> Result<TransactionEstimateView, Error> {
+        Ok(TransactionEstimateView {
+            gas_burnt: 0,
+            tokens_burnt: 0,
+            transaction_outcome: None,
+            receipt_outcome: None,
+            receipts: vec![],
+            failure: None,
+        })
+    }
 }
@@ -13,6 +13,7 @@ use std::collections::HashSet;
 pub struct ValidatorSchedule {
     pub(super) block_producers: Vec<Vec<AccountId>>,
     pub(super) chunk_only_producers: Vec<Vec<Vec<AccountId>>>,
+    pub(super) chunk_validators: Vec<Vec<Vec<AccountId>>>,
     pub(super) validator_groups: u64,
     pub(super) num_shards: NumShards,
 }
@@ -26,6 +27,7 @@ impl ValidatorSchedule {
         Self {
             block_producers: Vec::new(),
             chunk_only_producers: Vec::new(),
+            chunk_validators: Vec::new(),
             validator_groups: 1,
             num_shards,
         }
@@ -55,6 +57,22 @@ impl ValidatorSchedule {
         self
     }
 
+    /// Specifies, for each shard in each epoch, the set of chunk validators
+    /// for the shard.
+    ///
+    /// Chunk validators are dedicated accounts, distinct from block and
+    /// chunk producers, which allows testing stateless validation with
+    /// non-overlapping roles. If not specified, there are no chunk
+    /// validators.
+    pub fn chunk_validators_per_epoch_per_shard(
+        mut self,
+        chunk_validators: Vec<Vec<Vec<AccountId>>>,
+    ) -> Self {
+        self.chunk_validators = chunk_validators;
+        self.sanity_check();
+        self
+    }
+
     /// Controls how chunk_producers are selected from the block producers.
     ///
     /// This parameter splits the validators in each shard into that many
@@ -81,7 +99,9 @@ impl ValidatorSchedule {
     }
 
     pub fn all_validators(&self) -> impl Iterator<Item = &AccountId> {
-        self.all_block_producers().chain(self.chunk_only_producers.iter().flatten().flatten())
+        self.all_block_producers()
+            .chain(self.chunk_only_producers.iter().flatten().flatten())
+            .chain(self.chunk_validators.iter().flatten().flatten())
     }
 
     fn sanity_check(&self) {
@@ -100,5 +120,16 @@ impl ValidatorSchedule {
         if let Some(v) = block_producers.intersection(&chunk_only_producers).next() {
             panic!("{v} is both a block and a chunk only producer")
         }
+        let mut chunk_validators = HashSet::new();
+        for cv in self.chunk_validators.iter().flatten().flatten() {
+            if !chunk_validators.insert(cv) {
+                panic!("chunk validator {cv} is specified twice")
+            }
+        }
+        if let Some(v) =
+            block_producers.union(&chunk_only_producers).find(|v| chunk_validators.contains(*v))
+        {
+            panic!("{v} is both a producer and a chunk validator")
+        }
     }
 }
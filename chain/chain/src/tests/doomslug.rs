@@ -59,6 +59,7 @@ fn one_iter(
                 Duration::from_millis(1000),
                 Duration::from_millis(100),
                 delta * 20, // some arbitrary number larger than delta * 6
+                None,
                 Some(signer.clone()),
                 DoomslugThresholdMode::TwoThirds,
             )
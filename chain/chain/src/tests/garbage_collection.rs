@@ -8,7 +8,7 @@ use crate::test_utils::{
     get_chain_with_num_shards,
 };
 use crate::types::Tip;
-use crate::{ChainStoreAccess, StoreValidator};
+use crate::{clear_old_block_headers, ChainStoreAccess, Error, StoreValidator};
 
 use near_chain_configs::{GCConfig, GenesisConfig};
 use near_epoch_manager::EpochManagerAdapter;
@@ -176,7 +176,11 @@ fn gc_fork_common(simple_chains: Vec<SimpleChain>, max_changes: usize) {
 
     // GC execution
     chain1
-        .clear_data(tries1.clone(), &GCConfig { gc_blocks_limit: 1000, ..GCConfig::default() })
+        .clear_data(
+            tries1.clone(),
+            &GCConfig { gc_blocks_limit: 1000, ..GCConfig::default() },
+            None,
+        )
         .unwrap();
 
     let tries2 = get_chain_with_num_shards(num_shards).runtime_adapter.get_tries();
@@ -641,6 +645,7 @@ fn test_fork_far_away_from_epoch_end() {
                 gc_fork_clean_step: fork_clean_step,
                 ..GCConfig::default()
             },
+            None,
         )
         .expect("Clear data failed");
 
@@ -682,7 +687,7 @@ fn test_fork_far_away_from_epoch_end() {
         );
     }
     chain1
-        .clear_data(tries1, &GCConfig { gc_blocks_limit: 100, ..GCConfig::default() })
+        .clear_data(tries1, &GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }, None)
         .expect("Clear data failed");
     // And now all these blocks should be safely removed.
     for i in 6..50 {
@@ -719,7 +724,9 @@ fn test_clear_old_data() {
     }
 
     let trie = chain.runtime_adapter.get_tries();
-    chain.clear_data(trie, &GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }).unwrap();
+    chain
+        .clear_data(trie, &GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }, None)
+        .unwrap();
 
     // epoch didn't change so no data is garbage collected.
     for i in 0..15 {
@@ -742,6 +749,100 @@ fn test_clear_old_data() {
     }
 }
 
+/// Test that the block headers below the chunk tail are garbage collected
+/// with `gc_block_headers`, except the headers at epoch boundaries.
+#[test]
+fn test_clear_old_data_gc_block_headers() {
+    let mut chain = get_chain_with_epoch_length(1);
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    for i in 1..15 {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i,
+        );
+    }
+    // The mock epoch manager doesn't store epoch infos, so mark the block at
+    // height 3 as the last block of an epoch.
+    let mut store_update = chain.chain_store().store().store_update();
+    store_update.set(DBCol::EpochInfo, blocks[3].hash().as_ref(), &[]);
+    store_update.commit().unwrap();
+
+    let trie = chain.runtime_adapter.get_tries();
+    let gc_config =
+        GCConfig { gc_blocks_limit: 100, gc_block_headers: true, ..GCConfig::default() };
+    chain.clear_data(trie, &gc_config, None).unwrap();
+
+    let chunk_tail = chain.chain_store().chunk_tail().unwrap();
+    assert!(chunk_tail > 5);
+    for (i, block) in blocks.iter().enumerate() {
+        let header_exists = chain.get_block_header(block.hash()).is_ok();
+        let retained = i as BlockHeight >= chunk_tail || i == 3 || i == 4;
+        assert_eq!(header_exists, retained, "height = {}", i);
+    }
+
+    // The heights of the deleted headers are still indexed, but looking the
+    // headers up by height fails like for unknown heights.
+    assert_eq!(chain.get_block_hash_by_height(1).unwrap(), *blocks[1].hash());
+    assert!(matches!(chain.get_block_header_by_height(1), Err(Error::DBNotFoundErr(_))));
+    // Header sync only gets the headers which connect to the common header.
+    let headers = chain.retrieve_headers(vec![*blocks[4].hash()], 100, None).unwrap();
+    assert!(headers.is_empty());
+    let headers = chain.retrieve_headers(vec![*blocks[chunk_tail as usize].hash()], 100, None);
+    let heights: Vec<_> = headers.unwrap().iter().map(|header| header.height()).collect();
+    assert_eq!(heights, (chunk_tail + 1..15).collect::<Vec<_>>());
+}
+
+/// Test that `clear_old_block_headers` deletes the headers which were kept
+/// because `gc_block_headers` was enabled after the garbage collection ran.
+#[test]
+fn test_clear_old_block_headers() {
+    let mut chain = get_chain_with_epoch_length(1);
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    for i in 1..15 {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i,
+        );
+    }
+    let mut store_update = chain.chain_store().store().store_update();
+    store_update.set(DBCol::EpochInfo, blocks[3].hash().as_ref(), &[]);
+    store_update.commit().unwrap();
+
+    let trie = chain.runtime_adapter.get_tries();
+    let gc_config = GCConfig { gc_blocks_limit: 100, ..GCConfig::default() };
+    chain.clear_data(trie, &gc_config, None).unwrap();
+    for block in &blocks {
+        assert!(chain.get_block_header(block.hash()).is_ok());
+    }
+
+    let chunk_tail = chain.chain_store().chunk_tail().unwrap();
+    let store = chain.chain_store().store();
+    let num_deleted = clear_old_block_headers(store).unwrap();
+    // The headers of the epoch boundaries at heights 3 and 4 are kept.
+    assert_eq!(num_deleted, chunk_tail - 2);
+    for (i, block) in blocks.iter().enumerate() {
+        let header_exists = store.exists(DBCol::BlockHeader, block.hash().as_ref()).unwrap();
+        let retained = i as BlockHeight >= chunk_tail || i == 3 || i == 4;
+        assert_eq!(header_exists, retained, "height = {}", i);
+    }
+}
+
 // Adds block to the chain at given height after prev_block.
 fn add_block(
     chain: &mut Chain,
@@ -827,7 +928,11 @@ fn test_clear_old_data_fixed_height() {
     let trie = chain.runtime_adapter.get_tries();
     let mut store_update = chain.mut_chain_store().store_update();
     assert!(store_update
-        .clear_block_data(epoch_manager.as_ref(), *blocks[5].hash(), GCMode::Canonical(trie))
+        .clear_block_data(
+            epoch_manager.as_ref(),
+            *blocks[5].hash(),
+            GCMode::Canonical { tries: trie, gc_block_headers: false }
+        )
         .is_ok());
     store_update.commit().unwrap();
 
@@ -901,7 +1006,7 @@ fn test_clear_old_data_too_many_heights_common(gc_blocks_limit: NumBlocks) {
     for iter in 0..10 {
         println!("ITERATION #{:?}", iter);
         assert!(chain
-            .clear_data(trie.clone(), &GCConfig { gc_blocks_limit, ..GCConfig::default() })
+            .clear_data(trie.clone(), &GCConfig { gc_blocks_limit, ..GCConfig::default() }, None)
             .is_ok());
 
         // epoch didn't change so no data is garbage collected.
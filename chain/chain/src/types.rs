@@ -6,6 +6,7 @@ use chrono::DateTime;
 use chrono::Utc;
 use near_chain_configs::MutableConfigValue;
 use near_chain_configs::ReshardingConfig;
+use near_chain_configs::ShadowApplyVariant;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_store::flat::FlatStorageManager;
 use near_store::StorageError;
@@ -22,17 +23,17 @@ use near_primitives::merkle::{merklize, MerklePath};
 use near_primitives::receipt::Receipt;
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::state_part::PartId;
-use near_primitives::transaction::{ExecutionOutcomeWithId, SignedTransaction};
+use near_primitives::transaction::{ExecutionOutcomeWithId, ReceiptProfile, SignedTransaction};
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
 use near_primitives::types::{
-    Balance, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash, NumBlocks, ShardId,
-    StateChangesForResharding, StateRoot, StateRootNode,
+    AccountId, Balance, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash, NumBlocks,
+    ShardId, StateChangesForResharding, StateRoot, StateRootNode,
 };
 use near_primitives::version::{
     ProtocolVersion, MIN_GAS_PRICE_NEP_92, MIN_GAS_PRICE_NEP_92_FIX, MIN_PROTOCOL_VERSION_NEP_92,
     MIN_PROTOCOL_VERSION_NEP_92_FIX,
 };
-use near_primitives::views::{QueryRequest, QueryResponse};
+use near_primitives::views::{QueryRequest, QueryResponse, TransactionEstimateView};
 use near_store::{PartialStorage, ShardTries, Store, Trie, WrappedTrieChanges};
 
 pub use near_epoch_manager::EpochManagerAdapter;
@@ -115,6 +116,13 @@ pub struct ApplyChunkResult {
     /// Note that applied receipts are not necessarily executed as they can
     /// be delayed.
     pub applied_receipts_hash: CryptoHash,
+    /// Trie nodes which were missing from the recorded storage and were read
+    /// from disk instead. Only non-empty for
+    /// `StorageDataSource::RecordedWithDbFallback`.
+    pub fallback_trie_nodes: Vec<CryptoHash>,
+    /// Profiles of the executed receipts, by receipt id. Only collected if
+    /// `save_receipt_profiles` is enabled.
+    pub receipt_profiles: Vec<(CryptoHash, ReceiptProfile)>,
 }
 
 impl ApplyChunkResult {
@@ -219,6 +227,14 @@ pub struct ChainConfig {
     pub background_migration_threads: usize,
     /// The resharding configuration.
     pub resharding_config: MutableConfigValue<ReshardingConfig>,
+    /// Whether to save the transaction each executed receipt originates from.
+    pub save_receipt_origins: bool,
+    /// If set, new chunks are applied a second time with this configuration.
+    pub shadow_apply: Option<ShadowApplyVariant>,
+    /// Whether the chain is opened on top of a database it must never write
+    /// to, e.g. the secondary RocksDB instance of a read replica. The database
+    /// then has to be initialized already, and nothing is saved on startup.
+    pub read_only: bool,
 }
 
 impl ChainConfig {
@@ -230,6 +246,9 @@ impl ChainConfig {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_receipt_origins: false,
+            shadow_apply: None,
+            read_only: false,
         }
     }
 }
@@ -254,6 +273,10 @@ impl ChainGenesis {
 pub enum StorageDataSource {
     /// Full state data is present in DB.
     Db,
+    /// Like `Db`, but the in-memory tries are not used even if they are
+    /// loaded. Used to compare the performance of applying chunks with and
+    /// without them, see `ShadowApplyConfig`.
+    DbWithoutMemtries,
     /// Trie is present in DB and flat storage is not.
     /// Used for testing stateless validation jobs, should be removed after
     /// stateless validation release.
@@ -261,6 +284,11 @@ pub enum StorageDataSource {
     /// State data is supplied from state witness, there is no state data
     /// stored on disk.
     Recorded(PartialStorage),
+    /// Like `Recorded`, but nodes missing from the state witness are read
+    /// from the trie on disk. Only usable by nodes tracking the shard.
+    /// Nodes read from disk are reported in
+    /// `ApplyChunkResult::fallback_trie_nodes`.
+    RecordedWithDbFallback(PartialStorage),
 }
 
 pub struct RuntimeStorageConfig {
@@ -316,6 +344,25 @@ pub struct ApplyChunkShardContext<'a> {
     pub is_first_block_with_chunk_of_version: bool,
 }
 
+/// Limit that stopped the selection of transactions for a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum PrepareTransactionsLimit {
+    Gas,
+    Size,
+    Time,
+    ReceiptCount,
+    StorageProofSize,
+}
+
+/// Transactions selected for a chunk by `RuntimeAdapter::prepare_transactions`.
+pub struct PreparedTransactions {
+    pub transactions: Vec<SignedTransaction>,
+    /// The limit that stopped the selection, or `None` if the pool ran out of
+    /// transactions.
+    pub limited_by: Option<PrepareTransactionsLimit>,
+}
+
 /// Bridge between the chain and the runtime.
 /// Main function is to update state given transactions.
 /// Additionally handles validators.
@@ -369,6 +416,10 @@ pub trait RuntimeAdapter: Send + Sync {
     /// against the given `chain_validate` closure and runtime's transaction verifier.
     /// If the transaction is valid for both, it's added to the result and the temporary state
     /// update is preserved for validation of next transactions.
+    /// If `storage_proof_size_limit` is given, the state read while validating the transactions
+    /// is recorded, and no more transactions are added once its size, which is part of the
+    /// state witness of the chunk, reaches the limit.
+    /// Returns the limit that stopped the selection alongside the transactions.
     /// Throws an `Error` with `ErrorKind::StorageError` in case the runtime throws
     /// `RuntimeError::StorageError`.
     fn prepare_transactions(
@@ -383,7 +434,8 @@ pub trait RuntimeAdapter: Send + Sync {
         chain_validate: &mut dyn FnMut(&SignedTransaction) -> bool,
         current_protocol_version: ProtocolVersion,
         time_limit: Option<Duration>,
-    ) -> Result<Vec<SignedTransaction>, Error>;
+        storage_proof_size_limit: Option<usize>,
+    ) -> Result<PreparedTransactions, Error>;
 
     /// Returns true if the shard layout will change in the next epoch
     /// Current epoch is the epoch of the block after `parent_hash`
@@ -428,6 +480,18 @@ pub trait RuntimeAdapter: Send + Sync {
         part_id: PartId,
     ) -> Result<Vec<u8>, Error>;
 
+    /// Get part of the state corresponding to the given state root by traversing
+    /// the trie, without using a state snapshot or flat storage. Much slower than
+    /// `obtain_state_part`, but works for any state root whose trie nodes are in
+    /// storage, e.g. the state of past epochs in the cold storage.
+    fn obtain_state_part_from_trie(
+        &self,
+        shard_id: ShardId,
+        prev_hash: &CryptoHash,
+        state_root: &StateRoot,
+        part_id: PartId,
+    ) -> Result<Vec<u8>, Error>;
+
     /// Validate state part that expected to be given state root with provided data.
     /// Returns false if the resulting part doesn't match the expected one.
     fn validate_state_part(&self, state_root: &StateRoot, part_id: PartId, data: &[u8]) -> bool;
@@ -475,6 +539,32 @@ pub trait RuntimeAdapter: Send + Sync {
     /// but which exact shards to load depends on configuration. This may only be called when flat
     /// storage is ready.
     fn load_mem_tries_on_startup(&self, shard_uids: &[ShardUId]) -> Result<(), StorageError>;
+
+    /// Compiles the contracts deployed to the given accounts, as of the given
+    /// state root, in the background, so that they are already in the
+    /// compiled contract cache when a chunk calling them is applied. Returns
+    /// without waiting for the compilation.
+    fn precompile_contracts_speculatively(
+        &self,
+        epoch_id: &EpochId,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+        account_ids: Vec<AccountId>,
+    );
+
+    /// Dry-runs the transaction on the state after the given block: converts
+    /// it to a receipt on the state of the shard of the signer, then executes
+    /// the receipt on the state of the shard of the receiver if it's given.
+    /// Nothing is committed. The signature of the transaction is only checked
+    /// if `verify_signature` is set.
+    fn estimate_transaction(
+        &self,
+        block_header: &BlockHeader,
+        signer_state: (ShardUId, StateRoot),
+        receiver_state: Option<(ShardUId, StateRoot)>,
+        transaction: &SignedTransaction,
+        verify_signature: bool,
+    ) -> Result<TransactionEstimateView, Error>;
 }
 
 /// The last known / checked height and time when we have processed it.
@@ -520,7 +610,7 @@ mod tests {
         assert!(b1.header().verify_block_producer(&signer.public_key()));
         let other_signer = create_test_signer("other2");
         let approvals =
-            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).signature))];
+            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).unwrap().signature))];
         let b2 = TestBlockBuilder::new(&b1, signer.clone()).approvals(approvals).build();
         b2.header().verify_block_producer(&signer.public_key());
     }
@@ -1,10 +1,13 @@
 use crate::crypto_hash_timer::CryptoHashTimer;
+use crate::metrics;
 use crate::types::{
     ApplyChunkBlockContext, ApplyChunkResult, ApplyChunkShardContext, ApplyResultForResharding,
     ReshardingResults, RuntimeAdapter, RuntimeStorageConfig, StorageDataSource,
 };
+use near_chain_configs::ShadowApplyVariant;
 use near_chain_primitives::Error;
 use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::chunk_validation::ChunkStateTransition;
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::Receipt;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
@@ -12,8 +15,9 @@ use near_primitives::shard_layout::ShardUId;
 use near_primitives::sharding::ShardChunkHeader;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::chunk_extra::ChunkExtra;
-use near_primitives::types::{BlockHeight, Gas, StateChangesForResharding, StateRoot};
+use near_primitives::types::{BlockHeight, Gas, ShardId, StateChangesForResharding, StateRoot};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 /// Result of updating a shard for some block when it has a new chunk for this
 /// shard.
@@ -23,6 +27,9 @@ pub struct NewChunkResult {
     pub gas_limit: Gas,
     pub apply_result: ApplyChunkResult,
     pub resharding_results: Option<ReshardingResults>,
+    /// State transition recorded while applying the chunk. Only present if
+    /// storage was recorded, i.e. if we need to produce state witnesses.
+    pub state_transition: Option<RecordedStateTransition>,
 }
 
 /// Result of updating a shard for some block when it doesn't have a new chunk
@@ -34,6 +41,43 @@ pub struct OldChunkResult {
     /// TODO(logunov): exclude receipts/txs context from all related types.
     pub apply_result: ApplyChunkResult,
     pub resharding_results: Option<ReshardingResults>,
+    /// Implicit state transition recorded while applying the missing chunk.
+    /// Only present if storage was recorded.
+    pub state_transition: Option<RecordedStateTransition>,
+}
+
+/// State transition of a shard recorded while applying a chunk (or a missing
+/// chunk) of a block. It is everything needed to include the transition into
+/// a chunk state witness, so it doesn't have to be collected from the store
+/// afterwards.
+#[derive(Debug, Clone)]
+pub struct RecordedStateTransition {
+    pub shard_id: ShardId,
+    pub transition: ChunkStateTransition,
+    /// Hash of the receipts applied in the transition. See
+    /// `ApplyChunkResult::applied_receipts_hash`.
+    pub applied_receipts_hash: CryptoHash,
+}
+
+impl RecordedStateTransition {
+    /// Takes the recorded storage proof out of `apply_result`, if any, and
+    /// turns it into a state transition for the given block.
+    fn take_from_apply_result(
+        block_hash: CryptoHash,
+        shard_id: ShardId,
+        apply_result: &mut ApplyChunkResult,
+    ) -> Option<Self> {
+        let proof = apply_result.proof.take()?;
+        Some(Self {
+            shard_id,
+            transition: ChunkStateTransition {
+                block_hash,
+                base_state: proof.nodes,
+                post_state_root: apply_result.new_root,
+            },
+            applied_receipts_hash: apply_result.applied_receipts_hash,
+        })
+    }
 }
 
 /// Result of updating a shard for some block when we apply only resharding
@@ -132,15 +176,27 @@ pub(crate) fn process_shard_update(
     epoch_manager: &dyn EpochManagerAdapter,
     shard_update_reason: ShardUpdateReason,
     shard_context: ShardContext,
+    shadow_apply: Option<ShadowApplyVariant>,
 ) -> Result<ShardBlockUpdateResult, Error> {
     Ok(match shard_update_reason {
-        ShardUpdateReason::NewChunk(data) => ShardBlockUpdateResult::NewChunk(apply_new_chunk(
-            parent_span,
-            data,
-            shard_context,
-            runtime,
-            epoch_manager,
-        )?),
+        ShardUpdateReason::NewChunk(data) => {
+            let shadow_data = shadow_apply
+                .and_then(|variant| Some((variant, shadow_new_chunk_data(&data, variant)?)));
+            let start = Instant::now();
+            let result = apply_new_chunk(parent_span, data, shard_context, runtime, epoch_manager)?;
+            if let Some((variant, shadow_data)) = shadow_data {
+                shadow_apply_new_chunk(
+                    parent_span,
+                    variant,
+                    shadow_data,
+                    runtime,
+                    epoch_manager,
+                    &result,
+                    start.elapsed(),
+                );
+            }
+            ShardBlockUpdateResult::NewChunk(result)
+        }
         ShardUpdateReason::OldChunk(data) => ShardBlockUpdateResult::OldChunk(apply_old_chunk(
             parent_span,
             data,
@@ -189,6 +245,7 @@ pub fn apply_new_chunk(
         state_patch: storage_context.state_patch,
         record_storage: storage_context.record_storage,
     };
+    let block_hash = block.block_hash;
     match runtime.apply_chunk(
         storage_config,
         ApplyChunkShardContext {
@@ -202,7 +259,7 @@ pub fn apply_new_chunk(
         &receipts,
         &transactions,
     ) {
-        Ok(apply_result) => {
+        Ok(mut apply_result) => {
             let apply_split_result_or_state_changes = if shard_context.will_shard_layout_change {
                 Some(apply_resharding_state_changes(
                     epoch_manager,
@@ -214,17 +271,119 @@ pub fn apply_new_chunk(
             } else {
                 None
             };
+            let state_transition = RecordedStateTransition::take_from_apply_result(
+                block_hash,
+                shard_id,
+                &mut apply_result,
+            );
             Ok(NewChunkResult {
                 gas_limit,
                 shard_uid: shard_context.shard_uid,
                 apply_result,
                 resharding_results: apply_split_result_or_state_changes,
+                state_transition,
             })
         }
         Err(err) => Err(err),
     }
 }
 
+/// Returns a copy of the data to apply a new chunk with the alternate
+/// configuration, or `None` if the chunk can't be applied a second time
+/// the same way.
+fn shadow_new_chunk_data(data: &NewChunkData, variant: ShadowApplyVariant) -> Option<NewChunkData> {
+    if !matches!(data.storage_context.storage_data_source, StorageDataSource::Db)
+        || !data.storage_context.state_patch.is_empty()
+    {
+        return None;
+    }
+    let storage_data_source = match variant {
+        ShadowApplyVariant::NoMemtrie => StorageDataSource::DbWithoutMemtries,
+    };
+    Some(NewChunkData {
+        chunk_header: data.chunk_header.clone(),
+        transactions: data.transactions.clone(),
+        receipts: data.receipts.clone(),
+        resharding_state_roots: None,
+        block: data.block.clone(),
+        is_first_block_with_chunk_of_version: data.is_first_block_with_chunk_of_version,
+        storage_context: StorageContext {
+            storage_data_source,
+            state_patch: Default::default(),
+            record_storage: false,
+        },
+    })
+}
+
+/// Applies a new chunk a second time with the alternate configuration and
+/// reports how long it took compared to the actual application. The result
+/// is only checked against the actual one and then discarded.
+fn shadow_apply_new_chunk(
+    parent_span: &tracing::Span,
+    variant: ShadowApplyVariant,
+    data: NewChunkData,
+    runtime: &dyn RuntimeAdapter,
+    epoch_manager: &dyn EpochManagerAdapter,
+    main_result: &NewChunkResult,
+    main_elapsed: Duration,
+) {
+    // Resharding state changes are left to the actual application.
+    let shard_context = ShardContext {
+        shard_uid: main_result.shard_uid,
+        cares_about_shard_this_epoch: true,
+        will_shard_layout_change: false,
+        should_apply_chunk: true,
+        need_to_reshard: false,
+    };
+    let shard_id = shard_context.shard_uid.shard_id();
+    let chunk_hash = data.chunk_header.chunk_hash();
+    let start = Instant::now();
+    let result = apply_new_chunk(parent_span, data, shard_context, runtime, epoch_manager);
+    let shadow_elapsed = start.elapsed();
+
+    let shard_label = shard_id.to_string();
+    let outcome = match &result {
+        Ok(result) if result.apply_result.new_root == main_result.apply_result.new_root => "match",
+        Ok(_) => "mismatch",
+        Err(_) => "error",
+    };
+    metrics::SHADOW_APPLY_RESULTS_TOTAL
+        .with_label_values(&[shard_label.as_str(), variant.as_str(), outcome])
+        .inc();
+    if outcome != "match" {
+        tracing::warn!(
+            target: "chain",
+            shard_id,
+            ?chunk_hash,
+            variant = variant.as_str(),
+            expected_root = ?main_result.apply_result.new_root,
+            result = ?result.as_ref().map(|result| result.apply_result.new_root),
+            "Shadow application of the chunk doesn't match"
+        );
+        return;
+    }
+    metrics::SHADOW_APPLY_CHUNK_TIME
+        .with_label_values(&[shard_label.as_str(), variant.as_str(), "main"])
+        .observe(main_elapsed.as_secs_f64());
+    metrics::SHADOW_APPLY_CHUNK_TIME
+        .with_label_values(&[shard_label.as_str(), variant.as_str(), "shadow"])
+        .observe(shadow_elapsed.as_secs_f64());
+    if !main_elapsed.is_zero() {
+        metrics::SHADOW_APPLY_CHUNK_TIME_RATIO
+            .with_label_values(&[shard_label.as_str(), variant.as_str()])
+            .observe(shadow_elapsed.as_secs_f64() / main_elapsed.as_secs_f64());
+    }
+    tracing::debug!(
+        target: "chain",
+        shard_id,
+        ?chunk_hash,
+        variant = variant.as_str(),
+        ?main_elapsed,
+        ?shadow_elapsed,
+        "Applied the chunk a second time"
+    );
+}
+
 /// Applies shard update corresponding to missing chunk.
 /// (logunov) From what I know, the state update may include only validator
 /// accounts update on epoch start.
@@ -251,6 +410,7 @@ pub fn apply_old_chunk(
         state_patch: storage_context.state_patch,
         record_storage: storage_context.record_storage,
     };
+    let block_hash = block.block_hash;
     match runtime.apply_chunk(
         storage_config,
         ApplyChunkShardContext {
@@ -264,7 +424,7 @@ pub fn apply_old_chunk(
         &[],
         &[],
     ) {
-        Ok(apply_result) => {
+        Ok(mut apply_result) => {
             let apply_split_result_or_state_changes = if shard_context.will_shard_layout_change {
                 Some(apply_resharding_state_changes(
                     epoch_manager,
@@ -276,10 +436,16 @@ pub fn apply_old_chunk(
             } else {
                 None
             };
+            let state_transition = RecordedStateTransition::take_from_apply_result(
+                block_hash,
+                shard_id,
+                &mut apply_result,
+            );
             Ok(OldChunkResult {
                 shard_uid: shard_context.shard_uid,
                 apply_result,
                 resharding_results: apply_split_result_or_state_changes,
+                state_transition,
             })
         }
         Err(err) => Err(err),
@@ -6,8 +6,11 @@ use near_crypto::PublicKey;
 use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::block::{Block, BlockHeader};
 use near_primitives::challenge::{
-    BlockDoubleSign, Challenge, ChallengeBody, ChunkProofs, ChunkState, MaybeEncodedShardChunk,
+    BlockDoubleSign, Challenge, ChallengeBody, ChunkEndorsementDoubleSign, ChunkProofs, ChunkState,
+    MaybeEncodedShardChunk,
 };
+use near_primitives::checked_feature;
+use near_primitives::chunk_validation::ChunkEndorsementPayload;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::merklize;
 use near_primitives::sharding::{ShardChunk, ShardChunkHeader};
@@ -217,6 +220,65 @@ fn validate_double_sign(
     }
 }
 
+/// Validates that both endorsements are signed by the same chunk validator
+/// of the shard and height of two different chunks. The endorsements must
+/// be signed with the epoch id and height of the chunks, as they are since
+/// `ChunkEndorsementV2`, so that the validator can be checked without
+/// knowing the blocks the chunks are built on.
+fn validate_chunk_endorsement_double_sign(
+    epoch_manager: &dyn EpochManagerAdapter,
+    double_sign: &ChunkEndorsementDoubleSign,
+) -> Result<(CryptoHash, Vec<AccountId>), Error> {
+    let ChunkEndorsementDoubleSign {
+        epoch_id,
+        left_chunk_header,
+        left_endorsement,
+        right_chunk_header,
+        right_endorsement,
+    } = double_sign;
+    let protocol_version = epoch_manager.get_epoch_protocol_version(epoch_id)?;
+    if !checked_feature!("stable", ChunkEndorsementV2, protocol_version) {
+        return Err(Error::MaliciousChallenge);
+    }
+    let account_id = &left_endorsement.account_id;
+    let height_created = left_chunk_header.height_created();
+    if &right_endorsement.account_id != account_id
+        || left_chunk_header.chunk_hash() == right_chunk_header.chunk_hash()
+        || left_chunk_header.shard_id() != right_chunk_header.shard_id()
+        || right_chunk_header.height_created() != height_created
+    {
+        return Err(Error::MaliciousChallenge);
+    }
+    let chunk_validators = epoch_manager.get_chunk_validators(
+        epoch_id,
+        left_chunk_header.shard_id(),
+        height_created,
+    )?;
+    if !chunk_validators.contains_key(account_id) {
+        return Err(Error::MaliciousChallenge);
+    }
+    let Some(validator) =
+        epoch_manager.get_epoch_info(epoch_id)?.get_validator_by_account(account_id)
+    else {
+        return Err(Error::MaliciousChallenge);
+    };
+    let payload = |chunk_header: &ShardChunkHeader| {
+        ChunkEndorsementPayload::new(
+            chunk_header.chunk_hash(),
+            epoch_id.clone(),
+            height_created,
+            protocol_version,
+        )
+    };
+    if !left_endorsement.verify(validator.public_key(), &payload(left_chunk_header))
+        || !right_endorsement.verify(validator.public_key(), &payload(right_chunk_header))
+    {
+        return Err(Error::MaliciousChallenge);
+    }
+    // Deterministically return the hash of the higher chunk.
+    Ok((right_chunk_header.chunk_hash().0, vec![account_id.clone()]))
+}
+
 fn validate_header_authorship(
     epoch_manager: &dyn EpochManagerAdapter,
     block_header: &BlockHeader,
@@ -408,6 +470,13 @@ pub fn validate_challenge(
         ChallengeBody::ChunkState(chunk_state) => {
             validate_chunk_state_challenge(runtime, chunk_state)
         }
+        ChallengeBody::ChunkEndorsementDoubleSign(double_sign) => {
+            let protocol_version = epoch_manager.get_epoch_protocol_version(epoch_id)?;
+            if !checked_feature!("stable", ChunkEndorsementSlashing, protocol_version) {
+                return Err(Error::InvalidChallenge);
+            }
+            validate_chunk_endorsement_double_sign(epoch_manager, double_sign)
+        }
     }
 }
 
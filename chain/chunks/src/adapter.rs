@@ -5,7 +5,7 @@ use near_primitives::{
     merkle::MerklePath,
     receipt::Receipt,
     sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunkHeader},
-    types::EpochId,
+    types::{AccountId, EpochId},
 };
 
 #[derive(Message, Debug, strum::IntoStaticStr)]
@@ -50,4 +50,7 @@ pub enum ShardsManagerRequestFromClient {
     /// proofs, but cannot be marked as complete because the previous block isn't available),
     /// and completes them if so.
     CheckIncompleteChunks(CryptoHash),
+    /// Lets the ShardsManager know the account of the validator running the node, which becomes
+    /// None when the validator key is removed.
+    SetValidatorAccountId(Option<AccountId>),
 }
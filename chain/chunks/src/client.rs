@@ -1,17 +1,24 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use actix::Message;
 
 use near_pool::types::PoolIterator;
-use near_pool::{InsertTransactionResult, PoolIteratorWrapper, TransactionPool};
+use near_pool::{
+    InsertTransactionResult, PoolIteratorWrapper, PoolOrder, TransactionPool, TransactionPoolLimits,
+};
 use near_primitives::shard_layout::{account_id_to_shard_uid, ShardLayout, ShardUId};
 use near_primitives::{
     epoch_manager::RngSeed,
     sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
     transaction::SignedTransaction,
-    types::{AccountId, ShardId},
+    types::{AccountId, Nonce, ShardId},
+    views::{PendingTransactionView, ShardTxPoolStatusView, TxPoolStatusView},
 };
 
+/// Number of recent included chunks of a shard used to estimate how many
+/// transactions a chunk of the shard takes.
+const INCLUDED_CHUNKS_WINDOW: usize = 16;
+
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub enum ShardsManagerResponse {
@@ -39,27 +46,45 @@ pub struct ShardedTransactionPool {
     /// while keeping the security of randomization of transactions in pool
     rng_seed: RngSeed,
 
-    /// If set, new transactions that bring the size of the pool over this limit will be rejected.
-    /// The size is tracked and enforced separately for each shard.
-    pool_size_limit: Option<u64>,
+    /// Limits of the transactions in the pool. They are tracked and enforced separately for each
+    /// shard.
+    pool_limits: TransactionPoolLimits,
+
+    /// Number of transactions of the recent chunks included in each shard, the most recent last.
+    included_transactions: HashMap<ShardUId, VecDeque<usize>>,
 }
 
 impl ShardedTransactionPool {
-    pub fn new(rng_seed: RngSeed, pool_size_limit: Option<u64>) -> Self {
-        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit }
+    pub fn new(rng_seed: RngSeed, pool_limits: TransactionPoolLimits) -> Self {
+        Self {
+            tx_pools: HashMap::new(),
+            rng_seed,
+            pool_limits,
+            included_transactions: HashMap::new(),
+        }
     }
 
     pub fn get_pool_iterator(&mut self, shard_uid: ShardUId) -> Option<PoolIteratorWrapper<'_>> {
         self.tx_pools.get_mut(&shard_uid).map(|pool| pool.pool_iterator())
     }
 
-    /// Tries to insert the transaction into the pool for a given shard.
+    pub fn get_pool_iterator_with_order(
+        &mut self,
+        shard_uid: ShardUId,
+        order: PoolOrder,
+    ) -> Option<PoolIteratorWrapper<'_>> {
+        self.tx_pools.get_mut(&shard_uid).map(|pool| pool.pool_iterator_with_order(order))
+    }
+
+    /// Tries to insert the transaction into the pool for a given shard. See
+    /// `TransactionPool::insert_transaction` for `access_key_nonce`.
     pub fn insert_transaction(
         &mut self,
         shard_uid: ShardUId,
         tx: SignedTransaction,
+        access_key_nonce: Option<Nonce>,
     ) -> InsertTransactionResult {
-        self.pool_for_shard(shard_uid).insert_transaction(tx)
+        self.pool_for_shard(shard_uid).insert_transaction(tx, access_key_nonce)
     }
 
     pub fn remove_transactions(&mut self, shard_uid: ShardUId, transactions: &[SignedTransaction]) {
@@ -68,6 +93,73 @@ impl ShardedTransactionPool {
         }
     }
 
+    /// Records the number of transactions of a chunk included in a block, to estimate the
+    /// inclusion delays reported by `status`.
+    pub fn record_included_chunk(&mut self, shard_uid: ShardUId, num_transactions: usize) {
+        let included = self.included_transactions.entry(shard_uid).or_default();
+        if included.len() == INCLUDED_CHUNKS_WINDOW {
+            included.pop_front();
+        }
+        included.push_back(num_transactions);
+    }
+
+    /// Returns the status of the pools of all shards and, if given, of the transactions of the
+    /// account in them, estimating their inclusion delays assuming chunks take transactions in
+    /// the given order.
+    pub fn status(&self, account_id: Option<&AccountId>, order: PoolOrder) -> TxPoolStatusView {
+        let mut shards = vec![];
+        let mut transactions = vec![];
+        let mut shard_uids: Vec<_> = self.tx_pools.keys().copied().collect();
+        shard_uids.sort();
+        for shard_uid in shard_uids {
+            let pool = &self.tx_pools[&shard_uid];
+            let shard_id = shard_uid.shard_id();
+            // The most transactions recent chunks took is the best guess of what a chunk can
+            // take, as chunks produced when the pool is empty take fewer.
+            let chunk_capacity = self
+                .included_transactions
+                .get(&shard_uid)
+                .and_then(|included| included.iter().max().copied())
+                .filter(|capacity| *capacity > 0);
+            let estimate = |transactions_ahead: usize| {
+                chunk_capacity.map(|capacity| (transactions_ahead / capacity) as u64 + 1)
+            };
+            let transactions_ahead_of_new = match order {
+                PoolOrder::RoundRobin => pool.num_groups(),
+                PoolOrder::Priority { .. } => pool.len(),
+            };
+            shards.push(ShardTxPoolStatusView {
+                shard_id,
+                num_transactions: pool.len() as u64,
+                size_bytes: pool.transaction_size(),
+                estimated_inclusion_delay: estimate(transactions_ahead_of_new),
+            });
+
+            let Some(account_id) = account_id else {
+                continue;
+            };
+            for (rank, tx) in pool.account_transactions(account_id).into_iter().enumerate() {
+                let estimated_inclusion_delay = match order {
+                    // Each round over the groups takes one transaction of the account.
+                    PoolOrder::RoundRobin => estimate(rank * pool.num_groups()),
+                    // A chunk takes at most `max_transactions_per_account` of them.
+                    PoolOrder::Priority { max_transactions_per_account } => {
+                        estimate(pool.num_transactions_with_higher_priority(tx)).map(|delay| {
+                            delay.max((rank / max_transactions_per_account.max(1)) as u64 + 1)
+                        })
+                    }
+                };
+                transactions.push(PendingTransactionView {
+                    hash: tx.get_hash(),
+                    shard_id,
+                    nonce: tx.transaction.nonce,
+                    estimated_inclusion_delay,
+                });
+            }
+        }
+        TxPoolStatusView { shards, transactions }
+    }
+
     /// Computes a deterministic random seed for given `shard_id`.
     /// This seed is used to randomize the transaction pool.
     /// For better security we want the seed to different in each shard.
@@ -83,7 +175,7 @@ impl ShardedTransactionPool {
         self.tx_pools.entry(shard_uid).or_insert_with(|| {
             TransactionPool::new(
                 Self::random_seed(&self.rng_seed, shard_uid.shard_id()),
-                self.pool_size_limit,
+                self.pool_limits,
                 &shard_uid.to_string(),
             )
         })
@@ -99,9 +191,10 @@ impl ShardedTransactionPool {
         let mut reintroduced_count = 0;
         let pool = self.pool_for_shard(shard_uid);
         for tx in transactions {
-            reintroduced_count += match pool.insert_transaction(tx.clone()) {
+            reintroduced_count += match pool.insert_transaction(tx.clone(), None) {
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate => 1,
-                InsertTransactionResult::NoSpaceLeft => 0,
+                InsertTransactionResult::NoSpaceLeft
+                | InsertTransactionResult::AccountLimitReached => 0,
             }
         }
         reintroduced_count
@@ -124,6 +217,9 @@ impl ShardedTransactionPool {
         let mut transactions = vec![];
 
         for old_shard_uid in old_shard_layout.shard_uids() {
+            if let Some(pool) = self.tx_pools.get_mut(&old_shard_uid) {
+                pool.release_all_future_transactions();
+            }
             if let Some(mut iter) = self.get_pool_iterator(old_shard_uid) {
                 while let Some(group) = iter.next() {
                     while let Some(tx) = group.next() {
@@ -136,7 +232,7 @@ impl ShardedTransactionPool {
         for tx in transactions {
             let signer_id = &tx.transaction.signer_id;
             let new_shard_uid = account_id_to_shard_uid(&signer_id, new_shard_layout);
-            self.insert_transaction(new_shard_uid, tx);
+            self.insert_transaction(new_shard_uid, tx, None);
         }
     }
 }
@@ -147,6 +243,7 @@ mod tests {
     use near_crypto::{InMemorySigner, KeyType};
     use near_o11y::testonly::init_test_logger;
     use near_pool::types::PoolIterator;
+    use near_pool::{PoolOrder, TransactionPoolLimits};
     use near_primitives::{
         epoch_manager::RngSeed,
         hash::CryptoHash,
@@ -185,7 +282,7 @@ mod tests {
         let old_shard_layout = ShardLayout::get_simple_nightshade_layout();
         let new_shard_layout = ShardLayout::get_simple_nightshade_layout_v2();
 
-        let mut pool = ShardedTransactionPool::new(TEST_SEED, None);
+        let mut pool = ShardedTransactionPool::new(TEST_SEED, TransactionPoolLimits::default());
 
         let mut shard_id_to_accounts = HashMap::new();
         shard_id_to_accounts.insert(0, vec!["aaa", "abcd", "a-a-a-a-a"]);
@@ -227,7 +324,7 @@ mod tests {
 
             let shard_uid =
                 ShardUId { shard_id: signer_shard_id as u32, version: old_shard_layout.version() };
-            pool.insert_transaction(shard_uid, tx);
+            pool.insert_transaction(shard_uid, tx, None);
         }
 
         // reshard
@@ -269,4 +366,56 @@ mod tests {
         }
         tracing::info!("finished");
     }
+
+    #[test]
+    fn test_transaction_pool_status() {
+        let mut pool = ShardedTransactionPool::new(TEST_SEED, TransactionPoolLimits::default());
+        let shard_uid = ShardUId::single_shard();
+        let transactions: Vec<_> = ["alice", "alice", "alice", "bob", "carol"]
+            .iter()
+            .enumerate()
+            .map(|(nonce, signer_id)| {
+                let signer_id = AccountId::from_str(signer_id).unwrap();
+                let signer = InMemorySigner::from_seed(signer_id.clone(), KeyType::ED25519, "seed");
+                SignedTransaction::send_money(
+                    nonce as u64,
+                    signer_id.clone(),
+                    signer_id,
+                    &signer,
+                    1,
+                    CryptoHash::default(),
+                )
+            })
+            .collect();
+        for tx in transactions.iter().cloned() {
+            pool.insert_transaction(shard_uid, tx, None);
+        }
+        let alice = AccountId::from_str("alice").unwrap();
+
+        // Nothing can be estimated before any chunk with transactions was included.
+        pool.record_included_chunk(shard_uid, 0);
+        let status = pool.status(Some(&alice), PoolOrder::RoundRobin);
+        assert_eq!(status.shards.len(), 1);
+        assert_eq!(status.shards[0].num_transactions, 5);
+        assert_eq!(status.shards[0].estimated_inclusion_delay, None);
+        let nonces: Vec<_> = status.transactions.iter().map(|tx| tx.nonce).collect();
+        assert_eq!(nonces, vec![0, 1, 2]);
+        assert_eq!(status.transactions[0].hash, transactions[0].get_hash());
+
+        pool.record_included_chunk(shard_uid, 2);
+        let status = pool.status(Some(&alice), PoolOrder::RoundRobin);
+        assert_eq!(status.shards[0].estimated_inclusion_delay, Some(2));
+        let delays: Vec<_> =
+            status.transactions.iter().map(|tx| tx.estimated_inclusion_delay).collect();
+        assert_eq!(delays, vec![Some(1), Some(2), Some(4)]);
+
+        let order = PoolOrder::Priority { max_transactions_per_account: 1 };
+        let status = pool.status(Some(&alice), order);
+        assert_eq!(status.shards[0].estimated_inclusion_delay, Some(3));
+        let delays: Vec<_> =
+            status.transactions.iter().map(|tx| tx.estimated_inclusion_delay).collect();
+        assert_eq!(delays[2], Some(3));
+
+        assert!(pool.status(None, order).transactions.is_empty());
+    }
 }
@@ -2020,6 +2020,7 @@ impl ShardsManager {
             ShardsManagerRequestFromClient::CheckIncompleteChunks(prev_block_hash) => {
                 self.check_incomplete_chunks(&prev_block_hash)
             }
+            ShardsManagerRequestFromClient::SetValidatorAccountId(me) => self.me = me,
         }
     }
 
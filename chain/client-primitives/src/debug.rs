@@ -2,7 +2,8 @@
 //! without backwards compatibility of JSON encoding.
 use crate::types::StatusError;
 use chrono::DateTime;
-use near_primitives::types::EpochId;
+use near_primitives::serialize::dec_format;
+use near_primitives::types::{Balance, EpochId, ShardId};
 use near_primitives::views::{
     CatchupStatusView, ChainProcessingInfo, EpochValidatorInfo, RequestedStatePartsView,
     SyncStatusView,
@@ -44,6 +45,29 @@ pub struct DebugChunkStatus {
     pub processing_time_ms: Option<u64>,
 }
 
+/// Endorsements collected by this node for a chunk, to diagnose why the chunk
+/// was not included in a block.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct EndorsementStatus {
+    pub chunk_hash: ChunkHash,
+    pub shard_id: ShardId,
+    /// Chunk validators which endorsed the chunk.
+    pub endorsed_by: Vec<AccountId>,
+    /// Chunk validators from which no endorsement was received yet.
+    pub missing: Vec<AccountId>,
+    #[serde(with = "dec_format")]
+    pub endorsed_stake: Balance,
+    #[serde(with = "dec_format")]
+    pub total_stake: Balance,
+    pub endorsed_stake_fraction: f64,
+    /// Whether the endorsed stake is above the endorsement quorum.
+    pub has_quorum: bool,
+    /// Height of the block the chunk is meant to be included in. After the
+    /// head passes this height, the endorsements can no longer be used.
+    pub inclusion_height: BlockHeight,
+    pub head_height: BlockHeight,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct DebugBlockStatus {
     pub block_hash: CryptoHash,
@@ -185,6 +209,8 @@ pub enum DebugStatus {
     ChainProcessingStatus,
     // The state parts already requested.
     RequestedStateParts,
+    // Endorsements collected for the given chunk.
+    ChunkEndorsementStatus(ChunkHash),
 }
 
 impl actix::Message for DebugStatus {
@@ -206,4 +232,6 @@ pub enum DebugStatusResponse {
     ChainProcessingStatus(ChainProcessingInfo),
     // The state parts already requested.
     RequestedStateParts(Vec<RequestedStatePartsView>),
+    // Endorsements collected for a chunk.
+    ChunkEndorsementStatus(EndorsementStatus),
 }
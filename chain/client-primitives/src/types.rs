@@ -6,16 +6,22 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::network::PeerId;
 use near_primitives::sharding::ChunkHash;
+use near_primitives::static_clock::StaticClock;
+use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, MaybeBlockId, ShardId,
-    TransactionOrReceiptId,
+    AccountId, BlockHeight, BlockHeightDelta, BlockReference, EpochHeight, EpochId, EpochReference,
+    MaybeBlockId, ShardId, TransactionOrReceiptId,
 };
+pub use near_primitives::validator_failover::ValidatorSignedHeights;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, DownloadStatusView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    GasPriceView, LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView,
-    QueryRequest, QueryResponse, ReceiptView, ShardSyncDownloadView, SplitStorageInfoView,
-    StateChangesKindsView, StateChangesRequestView, StateChangesView, SyncStatusView, TxStatusView,
+    BlockRangeField, BlockSummaryView, BlockView, ChunkExecutionProofView, ChunkStateWitnessView,
+    ChunkView, DownloadStatusView, EpochValidatorInfo, EstimatedValidatorsView,
+    ExecutionOutcomeWithIdView, GasPriceView, LightClientBlockLiteView,
+    LightClientBlockOutcomesProofView, LightClientBlockView, MaintenanceWindowsView, QueryRequest,
+    QueryResponse, ReceiptView, ReceiptWithOutcomeView, ScheduledMaintenanceWindowView,
+    ShardSyncDownloadView, SplitStorageInfoView, StateChangesKindsView, StateChangesRequestView,
+    StateChangesView, SyncStatusView, TransactionEstimateView, TxPoolStatusView, TxStatusView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 use std::collections::HashMap;
@@ -46,6 +52,12 @@ impl From<near_primitives::errors::EpochError> for Error {
     }
 }
 
+impl From<near_primitives::validator_signer::ValidatorSignerError> for Error {
+    fn from(err: near_primitives::validator_signer::ValidatorSignerError) -> Self {
+        Error::Chain(err.into())
+    }
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct DownloadStatus {
     pub start_time: DateTime<Utc>,
@@ -151,6 +163,7 @@ impl From<ShardSyncDownload> for ShardSyncDownloadView {
         ShardSyncDownloadView {
             downloads: download.downloads.iter().map(|x| x.into()).collect(),
             status: download.status.to_string(),
+            eta_seconds: download.parts_eta(StaticClock::utc()).map(|eta| eta.as_secs()),
         }
     }
 }
@@ -186,6 +199,30 @@ impl ShardSyncDownload {
         }
         Self { downloads, status: ShardSyncStatus::StateDownloadParts }
     }
+
+    /// Estimates the time left to download the remaining state parts from the
+    /// rate at which parts were downloaded so far. Parts resumed from the store
+    /// after a restart were never requested and don't count towards the rate.
+    pub fn parts_eta(&self, now: DateTime<Utc>) -> Option<std::time::Duration> {
+        if self.status != ShardSyncStatus::StateDownloadParts {
+            return None;
+        }
+        let start_time = self.downloads.iter().map(|download| download.start_time).min()?;
+        let mut num_parts_downloaded = 0;
+        let mut num_parts_left = 0;
+        for download in &self.downloads {
+            if !download.done {
+                num_parts_left += 1;
+            } else if download.state_requests_count > 0 {
+                num_parts_downloaded += 1;
+            }
+        }
+        if num_parts_downloaded == 0 {
+            return None;
+        }
+        let elapsed = (now - start_time).to_std().ok()?;
+        Some(elapsed.mul_f64(num_parts_left as f64 / num_parts_downloaded as f64))
+    }
 }
 
 pub fn format_shard_sync_phase_per_shard(
@@ -231,7 +268,10 @@ pub fn format_shard_sync_phase(
                     num_parts_not_done += 1;
                 }
             }
-            format!("num_parts_done={num_parts_done} num_parts_not_done={num_parts_not_done}")
+            let eta = shard_sync_download.parts_eta(StaticClock::utc());
+            format!(
+                "num_parts_done={num_parts_done} num_parts_not_done={num_parts_not_done} eta={eta:?}"
+            )
         }
         status => format!("{status:?}"),
     }
@@ -412,6 +452,47 @@ impl Message for GetBlock {
     type Result = Result<BlockView, GetBlockError>;
 }
 
+/// Summaries of the blocks of the canonical chain with heights from
+/// `from_height` to `to_height`, inclusive. Heights without a block, either
+/// skipped or above the head, are left out.
+#[derive(Debug)]
+pub struct GetBlockRange {
+    pub from_height: BlockHeight,
+    pub to_height: BlockHeight,
+    pub fields: Vec<BlockRangeField>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetBlockRangeError {
+    #[error("IO Error: {error_message}")]
+    IOError { error_message: String },
+    #[error("to_height {to_height} is lower than from_height {from_height}")]
+    InvalidRange { from_height: BlockHeight, to_height: BlockHeight },
+    #[error("The range of {num_blocks} heights exceeds the limit of {max_num_blocks}")]
+    RangeTooLarge { num_blocks: BlockHeight, max_num_blocks: BlockHeight },
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {error_message}")]
+    Unreachable { error_message: String },
+}
+
+impl From<near_chain_primitives::Error> for GetBlockRangeError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => {
+                Self::IOError { error_message: error.to_string() }
+            }
+            _ => Self::Unreachable { error_message: error.to_string() },
+        }
+    }
+}
+
+impl Message for GetBlockRange {
+    type Result = Result<Vec<BlockSummaryView>, GetBlockRangeError>;
+}
+
 /// Get block with the block merkle tree. Used for testing
 #[derive(Debug)]
 pub struct GetBlockWithMerkleTree(pub BlockReference);
@@ -493,6 +574,20 @@ impl Message for Query {
     type Result = Result<QueryResponse, QueryError>;
 }
 
+/// Dry-runs a transaction on the state after the given block, without
+/// committing anything. See `RuntimeAdapter::estimate_transaction`.
+#[derive(Debug)]
+pub struct EstimateTransaction {
+    pub block_reference: BlockReference,
+    pub transaction: SignedTransaction,
+    /// Not set for unsigned transactions.
+    pub verify_signature: bool,
+}
+
+impl Message for EstimateTransaction {
+    type Result = Result<TransactionEstimateView, QueryError>;
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum QueryError {
     #[error("There are no fully synchronized blocks on the node yet")]
@@ -760,6 +855,10 @@ pub enum GetValidatorInfoError {
     UnknownEpoch,
     #[error("Validator info unavailable")]
     ValidatorInfoUnavailable,
+    #[error(
+        "Validators can't be estimated {epoch_offset} epochs ahead, at most {max_epoch_offset}"
+    )]
+    EpochOffsetTooLarge { epoch_offset: EpochHeight, max_epoch_offset: EpochHeight },
     // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
     // expected cases, we cannot statically guarantee that no other errors will be returned
     // in the future.
@@ -788,6 +887,17 @@ impl Message for GetValidatorOrdered {
     type Result = Result<Vec<ValidatorStakeView>, GetValidatorInfoError>;
 }
 
+/// Validators of the epoch `epoch_offset` epochs after the epoch of the header
+/// head, estimated for the epoch after next.
+#[derive(Debug)]
+pub struct GetEstimatedValidators {
+    pub epoch_offset: EpochHeight,
+}
+
+impl Message for GetEstimatedValidators {
+    type Result = Result<EstimatedValidatorsView, GetValidatorInfoError>;
+}
+
 #[derive(Debug)]
 pub struct GetStateChanges {
     pub block_hash: CryptoHash,
@@ -975,6 +1085,61 @@ impl Message for GetBlockProof {
     type Result = Result<GetBlockProofResponse, GetBlockProofError>;
 }
 
+/// Proofs of the execution outcomes of `account_id` in the blocks of the
+/// canonical chain with heights from `from_height` to `to_height`, inclusive,
+/// against the light client head `head_block_hash`. Outcomes are grouped by
+/// the block whose header includes their outcome root.
+#[derive(Debug)]
+pub struct GetAccountOutcomesProof {
+    pub account_id: AccountId,
+    pub from_height: BlockHeight,
+    pub to_height: BlockHeight,
+    pub head_block_hash: CryptoHash,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetAccountOutcomesProofError {
+    #[error("Block either has never been observed on the node or has been garbage collected: {error_message}")]
+    UnknownBlock { error_message: String },
+    #[error("to_height {to_height} is lower than from_height {from_height}")]
+    InvalidRange { from_height: BlockHeight, to_height: BlockHeight },
+    #[error("The range of {num_blocks} heights exceeds the limit of {max_num_blocks}")]
+    RangeTooLarge { num_blocks: BlockHeight, max_num_blocks: BlockHeight },
+    #[error("Outcomes in the block at height {block_height} have not been confirmed")]
+    NotConfirmed { block_height: BlockHeight },
+    #[error("Node doesn't track the shard {shard_id} of the account")]
+    UnavailableShard { shard_id: ShardId },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {error_message}")]
+    Unreachable { error_message: String },
+}
+
+impl From<near_chain_primitives::error::Error> for GetAccountOutcomesProofError {
+    fn from(error: near_chain_primitives::error::Error) -> Self {
+        match error {
+            near_chain_primitives::error::Error::IOErr(error) => {
+                Self::InternalError { error_message: error.to_string() }
+            }
+            near_chain_primitives::error::Error::DBNotFoundErr(error_message) => {
+                Self::UnknownBlock { error_message }
+            }
+            near_chain_primitives::error::Error::Other(error_message) => {
+                Self::InternalError { error_message }
+            }
+            err => Self::Unreachable { error_message: err.to_string() },
+        }
+    }
+}
+
+impl Message for GetAccountOutcomesProof {
+    type Result = Result<Vec<LightClientBlockOutcomesProofView>, GetAccountOutcomesProofError>;
+}
+
 #[derive(Debug)]
 pub struct GetReceipt {
     pub receipt_id: CryptoHash,
@@ -1007,6 +1172,16 @@ impl Message for GetReceipt {
     type Result = Result<Option<ReceiptView>, GetReceiptError>;
 }
 
+/// Gets a receipt together with its execution outcome and origin.
+#[derive(Debug)]
+pub struct GetReceiptWithOutcome {
+    pub receipt_id: CryptoHash,
+}
+
+impl Message for GetReceiptWithOutcome {
+    type Result = Result<ReceiptWithOutcomeView, GetReceiptError>;
+}
+
 #[derive(Debug)]
 pub struct GetProtocolConfig(pub BlockReference);
 
@@ -1064,6 +1239,40 @@ impl From<near_chain_primitives::Error> for GetMaintenanceWindowsError {
     }
 }
 
+/// Finds the nearest window for a maintenance of `num_blocks` blocks of the
+/// account. Both default to the validator of the node and the maintenance
+/// declared in its config.
+#[derive(Debug)]
+pub struct GetScheduledMaintenanceWindow {
+    pub account_id: Option<AccountId>,
+    pub num_blocks: Option<BlockHeightDelta>,
+}
+
+impl Message for GetScheduledMaintenanceWindow {
+    type Result = Result<ScheduledMaintenanceWindowView, GetScheduledMaintenanceWindowError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetScheduledMaintenanceWindowError {
+    #[error("The node has no validator and no account was given")]
+    NoValidator,
+    #[error("The node has no maintenance declared and no number of blocks was given")]
+    NoMaintenanceDeclared,
+    #[error("IO Error: {0}")]
+    IOError(String),
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetScheduledMaintenanceWindowError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GetClientConfig {}
 
@@ -1092,6 +1301,51 @@ impl From<near_chain_primitives::Error> for GetClientConfigError {
     }
 }
 
+/// Returns whether the validator key of the node is attached.
+#[derive(Debug)]
+pub struct GetValidatorKeyStatus {}
+
+impl Message for GetValidatorKeyStatus {
+    type Result = Result<ValidatorKeyStatus, ValidatorKeyError>;
+}
+
+/// Detaches the validator key of the node, so that it stops producing blocks
+/// and chunks and signing approvals and chunk endorsements, or attaches it
+/// back. Used to fail over between nodes holding the same validator key
+/// without restarting them.
+#[derive(Debug)]
+pub struct SetValidatorKeyAttached {
+    pub attached: bool,
+    /// When attaching, the heights last signed at by the node the key is
+    /// taken over from, as returned when it detached the key. The node won't
+    /// sign anything at or below them.
+    pub signed_heights: Option<ValidatorSignedHeights>,
+}
+
+impl Message for SetValidatorKeyAttached {
+    type Result = Result<ValidatorKeyStatus, ValidatorKeyError>;
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorKeyStatus {
+    pub account_id: AccountId,
+    pub public_key: near_crypto::PublicKey,
+    /// Whether the node signs with the key.
+    pub attached: bool,
+    /// Largest heights the node signed at with the key. Once the key is
+    /// detached, they don't change anymore. Blocks and chunks produced before
+    /// the node was restarted are not accounted for.
+    pub signed_heights: ValidatorSignedHeights,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ValidatorKeyError {
+    #[error("The node has no validator key")]
+    NoValidatorKey,
+    #[error("{error_message}")]
+    InternalError { error_message: String },
+}
+
 #[derive(Debug)]
 pub struct GetSplitStorageInfo {}
 
@@ -1126,6 +1380,124 @@ impl From<std::io::Error> for GetSplitStorageInfoError {
     }
 }
 
+#[derive(Debug)]
+pub struct GetChunkStateWitness {
+    pub chunk_hash: ChunkHash,
+}
+
+impl Message for GetChunkStateWitness {
+    type Result = Result<ChunkStateWitnessView, GetChunkStateWitnessError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetChunkStateWitnessError {
+    #[error("IO Error: {error_message}")]
+    IOError { error_message: String },
+    #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
+    UnknownChunk { chunk_hash: ChunkHash },
+    #[error("State witness for the chunk is not available on this node: {error_message}")]
+    WitnessUnavailable { error_message: String },
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {error_message}")]
+    Unreachable { error_message: String },
+}
+
+impl From<near_chain_primitives::Error> for GetChunkStateWitnessError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => {
+                Self::IOError { error_message: error.to_string() }
+            }
+            near_chain_primitives::Error::ChunkMissing(chunk_hash) => {
+                Self::UnknownChunk { chunk_hash }
+            }
+            near_chain_primitives::Error::DBNotFoundErr(error_message)
+            | near_chain_primitives::Error::Other(error_message) => {
+                Self::WitnessUnavailable { error_message }
+            }
+            _ => Self::Unreachable { error_message: error.to_string() },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GetChunkExecutionProof {
+    pub chunk_hash: ChunkHash,
+}
+
+impl Message for GetChunkExecutionProof {
+    type Result = Result<ChunkExecutionProofView, GetChunkExecutionProofError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetChunkExecutionProofError {
+    #[error("IO Error: {error_message}")]
+    IOError { error_message: String },
+    #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
+    UnknownChunk { chunk_hash: ChunkHash },
+    #[error("Execution proof for the chunk is not available on this node: {error_message}")]
+    ProofUnavailable { error_message: String },
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {error_message}")]
+    Unreachable { error_message: String },
+}
+
+impl From<near_chain_primitives::Error> for GetChunkExecutionProofError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => {
+                Self::IOError { error_message: error.to_string() }
+            }
+            near_chain_primitives::Error::ChunkMissing(chunk_hash) => {
+                Self::UnknownChunk { chunk_hash }
+            }
+            near_chain_primitives::Error::DBNotFoundErr(error_message)
+            | near_chain_primitives::Error::Other(error_message) => {
+                Self::ProofUnavailable { error_message }
+            }
+            _ => Self::Unreachable { error_message: error.to_string() },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GetTxPoolStatus {
+    pub account_id: Option<AccountId>,
+}
+
+impl Message for GetTxPoolStatus {
+    type Result = Result<TxPoolStatusView, GetTxPoolStatusError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetTxPoolStatusError {
+    #[error("IO Error: {error_message}")]
+    IOError { error_message: String },
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {error_message}")]
+    Unreachable { error_message: String },
+}
+
+impl From<near_chain_primitives::Error> for GetTxPoolStatusError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => {
+                Self::IOError { error_message: error.to_string() }
+            }
+            _ => Self::Unreachable { error_message: error.to_string() },
+        }
+    }
+}
+
 #[cfg(feature = "sandbox")]
 #[derive(Debug)]
 pub enum SandboxMessage {
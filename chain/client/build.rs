@@ -0,0 +1,9 @@
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "remote_chunk_validation")]
+    {
+        println!("cargo:rerun-if-changed=proto/remote_chunk_validation.proto");
+        tonic_build::compile_protos("proto/remote_chunk_validation.proto")?;
+    }
+    Ok(())
+}
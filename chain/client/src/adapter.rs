@@ -1,5 +1,6 @@
 use crate::client_actor::ClientActor;
 use crate::view_client::ViewClientActor;
+use near_network::state_witness::{ChunkEndorsementMessage, ChunkStateWitnessMessage};
 use near_network::types::{
     NetworkInfo, PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg,
     PartialEncodedChunkResponseMsg, ReasonForBan, StateResponseInfo,
@@ -8,10 +9,12 @@ use near_o11y::WithSpanContextExt;
 use near_primitives::block::{Approval, Block, BlockHeader};
 use near_primitives::challenge::Challenge;
 use near_primitives::chunk_validation::{ChunkEndorsement, ChunkStateWitness};
+#[cfg(feature = "new_epoch_sync")]
+use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
-use near_primitives::sharding::PartialEncodedChunk;
+use near_primitives::sharding::{ChunkHash, PartialEncodedChunk};
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, EpochId, ShardId};
 use near_primitives::views::FinalExecutionOutcomeView;
@@ -34,6 +37,27 @@ pub(crate) struct TxStatusResponse(pub Box<FinalExecutionOutcomeView>);
 #[rtype(result = "Option<Box<Block>>")]
 pub(crate) struct BlockRequest(pub CryptoHash);
 
+/// Request the state witness of a chunk produced by this node.
+#[derive(actix::Message, Debug)]
+#[rtype(result = "Option<ChunkStateWitness>")]
+pub(crate) struct ChunkStateWitnessRequest(pub ChunkHash);
+
+/// Request the `EpochSyncInfo` of a finished epoch.
+#[cfg(feature = "new_epoch_sync")]
+#[derive(actix::Message, Debug)]
+#[rtype(result = "Option<Box<EpochSyncInfo>>")]
+pub(crate) struct EpochSyncRequest(pub EpochId);
+
+/// Response to an `EpochSyncRequest`.
+#[cfg(feature = "new_epoch_sync")]
+#[derive(actix::Message, Debug)]
+#[rtype(result = "()")]
+pub(crate) struct EpochSyncResponse {
+    pub peer_id: PeerId,
+    pub epoch_id: EpochId,
+    pub epoch_sync_info: Option<Box<EpochSyncInfo>>,
+}
+
 /// Block response.
 #[derive(actix::Message, Debug)]
 #[rtype(result = "()")]
@@ -136,14 +160,6 @@ pub enum ProcessTxResponse {
     DoesNotTrackShard,
 }
 
-#[derive(actix::Message, Debug, PartialEq, Eq)]
-#[rtype(result = "()")]
-pub struct ChunkStateWitnessMessage(pub ChunkStateWitness);
-
-#[derive(actix::Message, Debug)]
-#[rtype(result = "()")]
-pub struct ChunkEndorsementMessage(pub ChunkEndorsement);
-
 pub struct Adapter {
     /// Address of the client actor.
     client_addr: actix::Addr<ClientActor>,
@@ -350,6 +366,23 @@ impl near_network::client::Client for Adapter {
         }
     }
 
+    async fn chunk_state_witness_request(
+        &self,
+        chunk_hash: ChunkHash,
+    ) -> Option<ChunkStateWitness> {
+        match self
+            .view_client_addr
+            .send(ChunkStateWitnessRequest(chunk_hash).with_span_context())
+            .await
+        {
+            Ok(witness) => witness,
+            Err(err) => {
+                tracing::error!("mailbox error: {err}");
+                None
+            }
+        }
+    }
+
     async fn chunk_endorsement(&self, endorsement: ChunkEndorsement) {
         match self.client_addr.send(ChunkEndorsementMessage(endorsement).with_span_context()).await
         {
@@ -357,4 +390,32 @@ impl near_network::client::Client for Adapter {
             Err(err) => tracing::error!("mailbox error: {err}"),
         }
     }
+
+    #[cfg(feature = "new_epoch_sync")]
+    async fn epoch_sync_request(&self, epoch_id: EpochId) -> Option<Box<EpochSyncInfo>> {
+        match self.view_client_addr.send(EpochSyncRequest(epoch_id).with_span_context()).await {
+            Ok(epoch_sync_info) => epoch_sync_info,
+            Err(err) => {
+                tracing::error!("mailbox error: {err}");
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "new_epoch_sync")]
+    async fn epoch_sync_response(
+        &self,
+        peer_id: PeerId,
+        epoch_id: EpochId,
+        epoch_sync_info: Option<Box<EpochSyncInfo>>,
+    ) {
+        match self
+            .client_addr
+            .send(EpochSyncResponse { peer_id, epoch_id, epoch_sync_info }.with_span_context())
+            .await
+        {
+            Ok(()) => {}
+            Err(err) => tracing::error!("mailbox error: {err}"),
+        }
+    }
 }
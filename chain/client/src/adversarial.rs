@@ -6,6 +6,10 @@ mod adv {
     struct Inner {
         disable_header_sync: std::sync::atomic::AtomicBool,
         disable_doomslug: std::sync::atomic::AtomicBool,
+        produce_invalid_witnesses: std::sync::atomic::AtomicBool,
+        withhold_chunk_endorsements: std::sync::atomic::AtomicBool,
+        double_endorse_chunks: std::sync::atomic::AtomicBool,
+        witness_distribution_delay_ms: std::sync::atomic::AtomicU64,
         is_archival: bool,
     }
 
@@ -33,6 +37,41 @@ mod adv {
             self.0.disable_doomslug.store(value, Ordering::SeqCst);
         }
 
+        pub fn produce_invalid_witnesses(&self) -> bool {
+            self.0.produce_invalid_witnesses.load(Ordering::SeqCst)
+        }
+
+        pub fn set_produce_invalid_witnesses(&self, value: bool) {
+            self.0.produce_invalid_witnesses.store(value, Ordering::SeqCst);
+        }
+
+        pub fn withhold_chunk_endorsements(&self) -> bool {
+            self.0.withhold_chunk_endorsements.load(Ordering::SeqCst)
+        }
+
+        pub fn set_withhold_chunk_endorsements(&self, value: bool) {
+            self.0.withhold_chunk_endorsements.store(value, Ordering::SeqCst);
+        }
+
+        pub fn double_endorse_chunks(&self) -> bool {
+            self.0.double_endorse_chunks.load(Ordering::SeqCst)
+        }
+
+        pub fn set_double_endorse_chunks(&self, value: bool) {
+            self.0.double_endorse_chunks.store(value, Ordering::SeqCst);
+        }
+
+        pub fn witness_distribution_delay(&self) -> Option<std::time::Duration> {
+            match self.0.witness_distribution_delay_ms.load(Ordering::SeqCst) {
+                0 => None,
+                delay_ms => Some(std::time::Duration::from_millis(delay_ms)),
+            }
+        }
+
+        pub fn set_witness_distribution_delay_ms(&self, value: u64) {
+            self.0.witness_distribution_delay_ms.store(value, Ordering::SeqCst);
+        }
+
         pub fn is_archival(&self) -> bool {
             self.0.is_archival
         }
@@ -56,6 +95,22 @@ mod adv {
         pub const fn disable_doomslug(&self) -> bool {
             false
         }
+
+        pub const fn produce_invalid_witnesses(&self) -> bool {
+            false
+        }
+
+        pub const fn withhold_chunk_endorsements(&self) -> bool {
+            false
+        }
+
+        pub const fn double_endorse_chunks(&self) -> bool {
+            false
+        }
+
+        pub const fn witness_distribution_delay(&self) -> Option<std::time::Duration> {
+            None
+        }
     }
 }
 
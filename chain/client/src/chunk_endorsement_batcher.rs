@@ -0,0 +1,200 @@
+use near_async::messaging::{CanSend, Sender};
+use near_network::state_witness::SendChunkEndorsementsRequest;
+use near_primitives::chunk_validation::ChunkEndorsement;
+use near_primitives::static_clock::StaticClock;
+use near_primitives::types::AccountId;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct PendingBatch {
+    endorsements: Vec<ChunkEndorsement>,
+    /// When the first endorsement of the batch was added.
+    started: Instant,
+}
+
+/// Coalesces chunk endorsements sent to the same block producer within a
+/// short window into a single network message. Chunk validators validating
+/// chunks of several shards at the same height would otherwise send one
+/// message per endorsement.
+///
+/// Endorsements are added from the validation threads, and batches are sent
+/// either when an endorsement is added to an expired batch, or when
+/// `flush_expired` is called periodically by the client actor.
+pub(crate) struct ChunkEndorsementBatcher {
+    network_sender: Sender<SendChunkEndorsementsRequest>,
+    /// How long endorsements for the same block producer are held back,
+    /// waiting for other endorsements to be sent along with them.
+    window: Mutex<Duration>,
+    pending: Mutex<HashMap<AccountId, PendingBatch>>,
+}
+
+impl ChunkEndorsementBatcher {
+    pub fn new(network_sender: Sender<SendChunkEndorsementsRequest>, window: Duration) -> Self {
+        Self { network_sender, window: Mutex::new(window), pending: Mutex::new(HashMap::new()) }
+    }
+
+    /// Changes the batch window. Applies to the pending batches as well.
+    pub fn set_window(&self, window: Duration) {
+        *self.window.lock().unwrap() = window;
+    }
+
+    /// Queues the endorsement to be sent to `block_producer`.
+    pub fn add(&self, block_producer: AccountId, endorsement: ChunkEndorsement) {
+        let now = StaticClock::instant();
+        let window = *self.window.lock().unwrap();
+        let mut pending = self.pending.lock().unwrap();
+        let batch = pending
+            .entry(block_producer.clone())
+            .or_insert_with(|| PendingBatch { endorsements: vec![], started: now });
+        batch.endorsements.push(endorsement);
+        if now.saturating_duration_since(batch.started) >= window {
+            let batch = pending.remove(&block_producer).unwrap();
+            drop(pending);
+            self.send(block_producer, batch.endorsements);
+        }
+    }
+
+    /// Queues the endorsement to be sent to each of `block_producers`.
+    pub fn add_for_all(
+        &self,
+        block_producers: impl IntoIterator<Item = AccountId>,
+        endorsement: ChunkEndorsement,
+    ) {
+        for block_producer in block_producers {
+            self.add(block_producer, endorsement.clone());
+        }
+    }
+
+    /// Sends all batches which have been pending for at least the batch
+    /// window. Returns how long until the next pending batch expires, if any.
+    pub fn flush_expired(&self) -> Option<Duration> {
+        self.flush_expired_at(StaticClock::instant())
+    }
+
+    /// Sends all pending batches, regardless of how long they have been
+    /// pending for.
+    pub fn flush_all(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        for (block_producer, batch) in pending {
+            self.send(block_producer, batch.endorsements);
+        }
+    }
+
+    fn flush_expired_at(&self, now: Instant) -> Option<Duration> {
+        let window = *self.window.lock().unwrap();
+        let mut expired = vec![];
+        let mut next_expiry = None;
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.retain(|block_producer, batch| {
+                let elapsed = now.saturating_duration_since(batch.started);
+                if elapsed >= window {
+                    expired.push((block_producer.clone(), std::mem::take(&mut batch.endorsements)));
+                    false
+                } else {
+                    let remaining = window - elapsed;
+                    next_expiry =
+                        Some(next_expiry.map_or(remaining, |d: Duration| d.min(remaining)));
+                    true
+                }
+            });
+        }
+        for (block_producer, endorsements) in expired {
+            self.send(block_producer, endorsements);
+        }
+        next_expiry
+    }
+
+    fn send(&self, block_producer: AccountId, endorsements: Vec<ChunkEndorsement>) {
+        self.network_sender.send(SendChunkEndorsementsRequest { block_producer, endorsements });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_async::messaging::IntoSender;
+    use near_network::test_utils::MockPeerManagerAdapter;
+    use near_network::types::{NetworkRequests, PeerManagerMessageRequest};
+    use near_primitives::chunk_validation::{ChunkEndorsementInner, ChunkEndorsementPayload};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::sharding::ChunkHash;
+    use near_primitives::test_utils::create_test_chunk_endorsement;
+    use std::sync::Arc;
+
+    fn endorsement(chunk: &[u8]) -> ChunkEndorsement {
+        let inner = ChunkEndorsementInner::new(ChunkHash(CryptoHash::hash_bytes(chunk)));
+        create_test_chunk_endorsement("validator", &ChunkEndorsementPayload::V1(inner))
+    }
+
+    #[test]
+    fn test_batch_endorsements() {
+        let network = Arc::new(MockPeerManagerAdapter::default());
+        let batcher = ChunkEndorsementBatcher::new(network.as_sender(), Duration::ZERO);
+        let producer: AccountId = "producer".parse().unwrap();
+        // With a zero window, every endorsement is sent right away.
+        batcher.add(producer.clone(), endorsement(b"a"));
+        assert!(matches!(
+            network.requests.write().unwrap().pop_front(),
+            Some(PeerManagerMessageRequest::NetworkRequests(NetworkRequests::ChunkEndorsement(..)))
+        ));
+
+        let batcher = ChunkEndorsementBatcher::new(network.as_sender(), Duration::from_secs(3600));
+        batcher.add(producer.clone(), endorsement(b"a"));
+        batcher.add(producer.clone(), endorsement(b"b"));
+        assert!(network.requests.read().unwrap().is_empty());
+        assert!(batcher.flush_expired().is_some());
+        assert!(network.requests.read().unwrap().is_empty());
+        let later = StaticClock::instant() + Duration::from_secs(3600);
+        assert_eq!(batcher.flush_expired_at(later), None);
+        match network.requests.write().unwrap().pop_front() {
+            Some(PeerManagerMessageRequest::NetworkRequests(
+                NetworkRequests::ChunkEndorsementBatch(target, endorsements),
+            )) => {
+                assert_eq!(target, producer);
+                assert_eq!(endorsements.len(), 2);
+            }
+            request => panic!("unexpected request {request:?}"),
+        }
+    }
+
+    #[test]
+    fn test_set_window() {
+        let network = Arc::new(MockPeerManagerAdapter::default());
+        let batcher = ChunkEndorsementBatcher::new(network.as_sender(), Duration::from_secs(3600));
+        let producer: AccountId = "producer".parse().unwrap();
+        batcher.add(producer.clone(), endorsement(b"a"));
+        assert!(batcher.flush_expired().is_some());
+        assert!(network.requests.read().unwrap().is_empty());
+        // A shorter window applies to the pending batches too.
+        batcher.set_window(Duration::ZERO);
+        assert_eq!(batcher.flush_expired(), None);
+        assert_eq!(network.requests.read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_endorsement_for_all_block_producers() {
+        let network = Arc::new(MockPeerManagerAdapter::default());
+        let batcher = ChunkEndorsementBatcher::new(network.as_sender(), Duration::from_secs(3600));
+        let producers: Vec<AccountId> =
+            vec!["producer1".parse().unwrap(), "producer2".parse().unwrap()];
+        batcher.add_for_all(producers.clone(), endorsement(b"a"));
+        batcher.flush_all();
+        let mut targets: Vec<AccountId> = network
+            .requests
+            .write()
+            .unwrap()
+            .drain(..)
+            .map(|request| match request {
+                PeerManagerMessageRequest::NetworkRequests(NetworkRequests::ChunkEndorsement(
+                    target,
+                    _,
+                )) => target,
+                request => panic!("unexpected request {request:?}"),
+            })
+            .collect();
+        targets.sort();
+        assert_eq!(targets, producers);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::chunk_validation::chunk_endorsement_payload;
+use lru::LruCache;
+use near_chain_primitives::Error;
+use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::challenge::ChunkEndorsementDoubleSign;
+use near_primitives::checked_feature;
+use near_primitives::chunk_validation::ChunkEndorsement;
+use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
+use near_primitives::types::{AccountId, BlockHeight, EpochId, ShardId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// This is the number of unique chunks for which we would track the chunk endorsements.
+// Ideally, we should not be processing more than num_shards chunks at a time.
+const NUM_CHUNKS_IN_CHUNK_ENDORSEMENTS_CACHE: usize = 100;
+
+/// The validated chunk endorsements received from chunk validators, keyed on
+/// chunk hash and account id of the validator to avoid duplicates, as
+/// validators send endorsements to several block producers and may send the
+/// same one again, e.g. after retrying a failed validation. Clones share the
+/// same endorsements, so that the view client can serve the ones collected by
+/// the client.
+#[derive(Clone)]
+pub struct ChunkEndorsementPool(
+    Arc<Mutex<LruCache<ChunkHash, HashMap<AccountId, ChunkEndorsement>>>>,
+);
+
+impl Default for ChunkEndorsementPool {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(LruCache::new(NUM_CHUNKS_IN_CHUNK_ENDORSEMENTS_CACHE))))
+    }
+}
+
+impl ChunkEndorsementPool {
+    fn contains(&self, chunk_hash: &ChunkHash, account_id: &AccountId) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .get(chunk_hash)
+            .is_some_and(|endorsements| endorsements.contains_key(account_id))
+    }
+
+    fn insert(&self, endorsement: ChunkEndorsement) {
+        let mut pool = self.0.lock().unwrap();
+        let chunk_hash = endorsement.inner.chunk_hash.clone();
+        let mut endorsements = pool.pop(&chunk_hash).unwrap_or_default();
+        endorsements.insert(endorsement.account_id.clone(), endorsement);
+        pool.put(chunk_hash, endorsements);
+    }
+
+    /// Returns the endorsement of the chunk by the validator, without
+    /// affecting which chunks are kept in the cache.
+    fn peek(&self, chunk_hash: &ChunkHash, account_id: &AccountId) -> Option<ChunkEndorsement> {
+        let pool = self.0.lock().unwrap();
+        pool.peek(chunk_hash).and_then(|endorsements| endorsements.get(account_id)).cloned()
+    }
+
+    /// Returns the validated endorsements received so far for the chunk.
+    pub fn get_chunk_endorsements(&self, chunk_hash: &ChunkHash) -> Vec<ChunkEndorsement> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(chunk_hash)
+            .map(|endorsements| endorsements.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of validated endorsements received so far for the
+    /// chunk, without affecting which chunks are kept in the cache.
+    pub fn num_chunk_endorsements(&self, chunk_hash: &ChunkHash) -> usize {
+        self.0.lock().unwrap().peek(chunk_hash).map_or(0, |endorsements| endorsements.len())
+    }
+}
+
+/// Module to track chunk endorsements received from chunk validators.
+pub struct ChunkEndorsementTracker {
+    epoch_manager: Arc<dyn EpochManagerAdapter>,
+    /// We store the validated chunk endorsements received from chunk validators.
+    chunk_endorsements: ChunkEndorsementPool,
+    /// Headers of the chunks with stored endorsements, by epoch, shard and
+    /// height created, to detect validators endorsing different chunks of the
+    /// same shard and height.
+    chunk_headers_by_height: LruCache<(EpochId, ShardId, BlockHeight), Vec<ShardChunkHeader>>,
+    /// Evidence of double signed endorsements detected since the last call
+    /// to `take_double_signs`.
+    double_signs: Vec<ChunkEndorsementDoubleSign>,
+}
+
+impl ChunkEndorsementTracker {
+    pub fn new(
+        epoch_manager: Arc<dyn EpochManagerAdapter>,
+        chunk_endorsements: ChunkEndorsementPool,
+    ) -> Self {
+        Self {
+            epoch_manager,
+            chunk_endorsements,
+            chunk_headers_by_height: LruCache::new(NUM_CHUNKS_IN_CHUNK_ENDORSEMENTS_CACHE),
+            double_signs: vec![],
+        }
+    }
+
+    /// Validates the chunk endorsement against the header of the chunk it
+    /// endorses and, if valid, stores it. The signature must be of the
+    /// payload expected for the chunk at the protocol version of its epoch,
+    /// which since `ChunkEndorsementV2` includes the epoch id and height of
+    /// the chunk.
+    pub fn process_chunk_endorsement(
+        &mut self,
+        chunk_header: &ShardChunkHeader,
+        endorsement: ChunkEndorsement,
+    ) -> Result<(), Error> {
+        let chunk_hash = chunk_header.chunk_hash();
+        if endorsement.inner.chunk_hash != chunk_hash {
+            return Err(Error::InvalidChunkEndorsement);
+        }
+        if self.chunk_endorsements.contains(&chunk_hash, &endorsement.account_id) {
+            tracing::debug!(target: "chunk_validation", ?endorsement, "Already received chunk endorsement.");
+            return Ok(());
+        }
+
+        let epoch_id =
+            self.epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
+        let chunk_validators = self.epoch_manager.get_chunk_validators(
+            &epoch_id,
+            chunk_header.shard_id(),
+            chunk_header.height_created(),
+        )?;
+        if !chunk_validators.contains_key(&endorsement.account_id) {
+            return Err(Error::NotAChunkValidator);
+        }
+        let (validator, _) = self.epoch_manager.get_validator_by_account_id(
+            &epoch_id,
+            chunk_header.prev_block_hash(),
+            &endorsement.account_id,
+        )?;
+        let payload =
+            chunk_endorsement_payload(self.epoch_manager.as_ref(), &epoch_id, chunk_header)?;
+        if !endorsement.verify(validator.public_key(), &payload) {
+            return Err(Error::InvalidChunkEndorsement);
+        }
+
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
+        if checked_feature!("stable", ChunkEndorsementSlashing, protocol_version) {
+            self.detect_double_sign(epoch_id, chunk_header, &endorsement);
+        }
+        self.chunk_endorsements.insert(endorsement);
+        Ok(())
+    }
+
+    /// Records evidence if the validator of the endorsement already endorsed
+    /// another chunk of the same shard and height.
+    fn detect_double_sign(
+        &mut self,
+        epoch_id: EpochId,
+        chunk_header: &ShardChunkHeader,
+        endorsement: &ChunkEndorsement,
+    ) {
+        let chunk_hash = chunk_header.chunk_hash();
+        let key = (epoch_id, chunk_header.shard_id(), chunk_header.height_created());
+        let mut chunk_headers = self.chunk_headers_by_height.pop(&key).unwrap_or_default();
+        for other_chunk_header in &chunk_headers {
+            if other_chunk_header.chunk_hash() == chunk_hash {
+                continue;
+            }
+            let Some(other_endorsement) = self
+                .chunk_endorsements
+                .peek(&other_chunk_header.chunk_hash(), &endorsement.account_id)
+            else {
+                continue;
+            };
+            tracing::warn!(
+                target: "chunk_validation",
+                account_id = %endorsement.account_id,
+                shard_id = key.1,
+                height_created = key.2,
+                "Chunk validator endorsed two different chunks of the same shard and height",
+            );
+            self.double_signs.push(ChunkEndorsementDoubleSign::new(
+                key.0.clone(),
+                chunk_header.clone(),
+                endorsement.clone(),
+                other_chunk_header.clone(),
+                other_endorsement,
+            ));
+        }
+        if !chunk_headers
+            .iter()
+            .any(|other_chunk_header| other_chunk_header.chunk_hash() == chunk_hash)
+        {
+            chunk_headers.push(chunk_header.clone());
+        }
+        self.chunk_headers_by_height.put(key, chunk_headers);
+    }
+
+    /// Returns the evidence of double signed endorsements detected since the
+    /// last call, to be sent out as challenges.
+    pub fn take_double_signs(&mut self) -> Vec<ChunkEndorsementDoubleSign> {
+        std::mem::take(&mut self.double_signs)
+    }
+
+    /// Returns the validated endorsements received so far for the chunk.
+    pub fn get_chunk_endorsements(&self, chunk_hash: &ChunkHash) -> Vec<ChunkEndorsement> {
+        self.chunk_endorsements.get_chunk_endorsements(chunk_hash)
+    }
+
+    /// Returns the number of validated endorsements received so far for the
+    /// chunk, without affecting which chunks are kept in the cache.
+    pub fn num_chunk_endorsements(&self, chunk_hash: &ChunkHash) -> usize {
+        self.chunk_endorsements.num_chunk_endorsements(chunk_hash)
+    }
+}
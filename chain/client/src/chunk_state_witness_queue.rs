@@ -0,0 +1,123 @@
+use crate::metrics;
+use near_primitives::sharding::{ShardChunk, ShardChunkHeader};
+use near_primitives::types::{AccountId, ShardId};
+use std::collections::VecDeque;
+
+/// Maximum number of chunks waiting for their state witness to be produced.
+pub(crate) const CHUNK_STATE_WITNESS_QUEUE_CAPACITY: usize = 16;
+
+/// A produced chunk for which the state witness has not been sent yet.
+#[derive(Debug)]
+pub struct PendingChunkStateWitness {
+    /// The chunk validators to send the witness to.
+    pub chunk_validators: Vec<AccountId>,
+    pub prev_chunk_header: ShardChunkHeader,
+    pub chunk: ShardChunk,
+}
+
+impl PendingChunkStateWitness {
+    fn shard_id(&self) -> ShardId {
+        self.chunk.shard_id()
+    }
+}
+
+/// Bounded queue of chunks whose state witnesses are yet to be produced and
+/// sent to chunk validators.
+///
+/// A chunk producer which falls behind (e.g. when processing a batch of
+/// blocks at once) may produce several chunks for the same shard before it
+/// gets to send their witnesses. Once a newer chunk is produced for a shard,
+/// the witness for the older one is of no use, so only the latest chunk per
+/// shard is kept. If the queue is full, the oldest entry is dropped.
+pub(crate) struct ChunkStateWitnessQueue {
+    capacity: usize,
+    pending: VecDeque<PendingChunkStateWitness>,
+}
+
+impl ChunkStateWitnessQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, pending: VecDeque::new() }
+    }
+
+    pub fn push(&mut self, witness: PendingChunkStateWitness) {
+        let shard_id = witness.shard_id();
+        let height = witness.chunk.height_created();
+        if let Some(existing) = self.pending.iter().find(|pending| pending.shard_id() == shard_id) {
+            if existing.chunk.height_created() >= height {
+                Self::record_dropped(shard_id, "superseded");
+                return;
+            }
+            self.pending.retain(|pending| pending.shard_id() != shard_id);
+            Self::record_dropped(shard_id, "superseded");
+        }
+        if self.pending.len() >= self.capacity {
+            if let Some(oldest) = self.pending.pop_front() {
+                Self::record_dropped(oldest.shard_id(), "queue_full");
+            }
+        }
+        self.pending.push_back(witness);
+        metrics::CHUNK_STATE_WITNESS_QUEUE_SIZE.set(self.pending.len() as i64);
+    }
+
+    /// Returns the number of pending witnesses for the shard.
+    pub fn num_pending(&self, shard_id: ShardId) -> usize {
+        self.pending.iter().filter(|pending| pending.shard_id() == shard_id).count()
+    }
+
+    /// Takes all pending witnesses, oldest first.
+    pub fn drain(&mut self) -> Vec<PendingChunkStateWitness> {
+        metrics::CHUNK_STATE_WITNESS_QUEUE_SIZE.set(0);
+        self.pending.drain(..).collect()
+    }
+
+    fn record_dropped(shard_id: ShardId, reason: &str) {
+        metrics::CHUNK_STATE_WITNESS_QUEUE_DROPPED_TOTAL
+            .with_label_values(&[&shard_id.to_string(), reason])
+            .inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::sharding::ShardChunkV2;
+    use near_primitives::test_utils::create_test_chunk_header;
+    use near_primitives::types::BlockHeight;
+
+    fn pending(shard_id: ShardId, height: BlockHeight) -> PendingChunkStateWitness {
+        let header = create_test_chunk_header(height, shard_id);
+        let chunk = ShardChunk::V2(ShardChunkV2 {
+            chunk_hash: header.chunk_hash(),
+            header: header.clone(),
+            transactions: vec![],
+            prev_outgoing_receipts: vec![],
+        });
+        PendingChunkStateWitness { chunk_validators: vec![], prev_chunk_header: header, chunk }
+    }
+
+    fn heights(witnesses: &[PendingChunkStateWitness]) -> Vec<(ShardId, BlockHeight)> {
+        witnesses.iter().map(|w| (w.shard_id(), w.chunk.height_created())).collect()
+    }
+
+    #[test]
+    fn test_superseded_witnesses_are_dropped() {
+        let mut queue = ChunkStateWitnessQueue::new(CHUNK_STATE_WITNESS_QUEUE_CAPACITY);
+        queue.push(pending(0, 1));
+        queue.push(pending(1, 1));
+        queue.push(pending(0, 2));
+        // An older chunk does not replace a newer one.
+        queue.push(pending(1, 0));
+        assert_eq!(queue.pending.len(), 2);
+        assert_eq!(heights(&queue.drain()), vec![(1, 1), (0, 2)]);
+        assert_eq!(queue.pending.len(), 0);
+    }
+
+    #[test]
+    fn test_full_queue_drops_oldest() {
+        let mut queue = ChunkStateWitnessQueue::new(2);
+        queue.push(pending(0, 1));
+        queue.push(pending(1, 1));
+        queue.push(pending(2, 1));
+        assert_eq!(heights(&queue.drain()), vec![(1, 1), (2, 1)]);
+    }
+}
@@ -0,0 +1,173 @@
+use lru::LruCache;
+use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
+use near_primitives::types::{AccountId, BlockHeight, ShardId};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the pushed state witness of a chunk, after its header
+/// became known, before requesting it from the chunk producer.
+pub(crate) const CHUNK_STATE_WITNESS_REQUEST_DELAY: Duration = Duration::from_millis(300);
+
+/// How long to wait for the response to a witness request before sending it
+/// again.
+pub(crate) const CHUNK_STATE_WITNESS_REQUEST_RETRY_PERIOD: Duration = Duration::from_millis(300);
+
+/// Number of chunks whose witness has been received which are remembered,
+/// in case their header becomes known only after the witness arrived.
+const RECEIVED_WITNESSES_CACHE_SIZE: usize = 100;
+
+/// A request for the state witness of a chunk, to send to its producer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MissingWitnessRequest {
+    pub chunk_producer: AccountId,
+    pub chunk_hash: ChunkHash,
+    pub shard_id: ShardId,
+}
+
+struct AwaitedWitness {
+    chunk_producer: AccountId,
+    shard_id: ShardId,
+    height_created: BlockHeight,
+    /// When to request the witness next if it still hasn't arrived.
+    next_request: Instant,
+}
+
+/// Keeps track of the chunks this node has to validate whose state witness
+/// hasn't arrived, and decides when to request them from the chunk
+/// producers. A witness pushed by the chunk producer may be missed, e.g. if
+/// the node was offline for a moment, and there is no other way to get it.
+///
+/// Witnesses are requested until they arrive, or until the head reaches the
+/// height the chunk was created at: by then the block which could include
+/// the chunk has been produced, so the endorsement would be of no use.
+pub(crate) struct ChunkStateWitnessRequester {
+    delay: Duration,
+    retry_period: Duration,
+    awaited: HashMap<ChunkHash, AwaitedWitness>,
+    received: LruCache<ChunkHash, ()>,
+}
+
+impl ChunkStateWitnessRequester {
+    pub fn new(delay: Duration, retry_period: Duration) -> Self {
+        Self {
+            delay,
+            retry_period,
+            awaited: HashMap::new(),
+            received: LruCache::new(RECEIVED_WITNESSES_CACHE_SIZE),
+        }
+    }
+
+    /// Starts waiting for the state witness of the chunk, unless it has
+    /// already been received.
+    pub fn await_witness(
+        &mut self,
+        chunk_header: &ShardChunkHeader,
+        chunk_producer: AccountId,
+        now: Instant,
+    ) {
+        let chunk_hash = chunk_header.chunk_hash();
+        if self.received.contains(&chunk_hash) {
+            return;
+        }
+        self.awaited.entry(chunk_hash).or_insert_with(|| AwaitedWitness {
+            chunk_producer,
+            shard_id: chunk_header.shard_id(),
+            height_created: chunk_header.height_created(),
+            next_request: now + self.delay,
+        });
+    }
+
+    /// Stops waiting for the state witness of the chunk.
+    pub fn witness_received(&mut self, chunk_hash: &ChunkHash) {
+        self.awaited.remove(chunk_hash);
+        self.received.put(chunk_hash.clone(), ());
+    }
+
+    /// Returns the witness requests to send now, given the height of the
+    /// head, and how long until the next request is due, if any.
+    pub fn poll(
+        &mut self,
+        head_height: BlockHeight,
+        now: Instant,
+    ) -> (Vec<MissingWitnessRequest>, Option<Duration>) {
+        self.awaited.retain(|_, awaited| awaited.height_created > head_height);
+        let mut requests = vec![];
+        let mut next_request = None;
+        for (chunk_hash, awaited) in self.awaited.iter_mut() {
+            if awaited.next_request <= now {
+                requests.push(MissingWitnessRequest {
+                    chunk_producer: awaited.chunk_producer.clone(),
+                    chunk_hash: chunk_hash.clone(),
+                    shard_id: awaited.shard_id,
+                });
+                awaited.next_request = now + self.retry_period;
+            }
+            let remaining = awaited.next_request.saturating_duration_since(now);
+            next_request = Some(next_request.map_or(remaining, |d: Duration| d.min(remaining)));
+        }
+        (requests, next_request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::test_utils::create_test_chunk_header;
+
+    const DELAY: Duration = Duration::from_millis(100);
+    const RETRY_PERIOD: Duration = Duration::from_millis(300);
+
+    fn request(chunk_header: &ShardChunkHeader) -> MissingWitnessRequest {
+        MissingWitnessRequest {
+            chunk_producer: "producer".parse().unwrap(),
+            chunk_hash: chunk_header.chunk_hash(),
+            shard_id: chunk_header.shard_id(),
+        }
+    }
+
+    #[test]
+    fn test_request_missing_witness() {
+        let mut requester = ChunkStateWitnessRequester::new(DELAY, RETRY_PERIOD);
+        let start = Instant::now();
+        let header = create_test_chunk_header(10, 0);
+        requester.await_witness(&header, "producer".parse().unwrap(), start);
+
+        // Nothing is requested before the delay passes.
+        assert_eq!(requester.poll(9, start), (vec![], Some(DELAY)));
+        let now = start + DELAY;
+        assert_eq!(requester.poll(9, now), (vec![request(&header)], Some(RETRY_PERIOD)));
+        // The request is retried once the retry period passes.
+        assert_eq!(requester.poll(9, now + RETRY_PERIOD / 2), (vec![], Some(RETRY_PERIOD / 2)));
+        assert_eq!(
+            requester.poll(9, now + RETRY_PERIOD),
+            (vec![request(&header)], Some(RETRY_PERIOD))
+        );
+
+        requester.witness_received(&header.chunk_hash());
+        assert_eq!(requester.poll(9, now + RETRY_PERIOD * 2), (vec![], None));
+    }
+
+    #[test]
+    fn test_witness_received_before_header() {
+        let mut requester = ChunkStateWitnessRequester::new(DELAY, RETRY_PERIOD);
+        let start = Instant::now();
+        let header = create_test_chunk_header(10, 0);
+        requester.witness_received(&header.chunk_hash());
+        requester.await_witness(&header, "producer".parse().unwrap(), start);
+        assert_eq!(requester.poll(9, start + DELAY), (vec![], None));
+    }
+
+    #[test]
+    fn test_stop_requesting_after_deadline() {
+        let mut requester = ChunkStateWitnessRequester::new(DELAY, RETRY_PERIOD);
+        let start = Instant::now();
+        let old = create_test_chunk_header(10, 0);
+        let new = create_test_chunk_header(11, 1);
+        requester.await_witness(&old, "producer".parse().unwrap(), start);
+        requester.await_witness(&new, "producer".parse().unwrap(), start);
+        // Once the block at the height of the chunk is produced, endorsing it
+        // is of no use anymore.
+        assert_eq!(requester.poll(10, start + DELAY), (vec![request(&new)], Some(RETRY_PERIOD)));
+        assert_eq!(requester.poll(11, start + DELAY + RETRY_PERIOD), (vec![], None));
+    }
+}
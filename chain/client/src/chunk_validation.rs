@@ -1,3 +1,5 @@
+use lru::LruCache;
+use near_async::futures::AsyncComputationSpawner;
 use near_async::messaging::{CanSend, Sender};
 use near_chain::chain::{
     apply_new_chunk, apply_old_chunk, NewChunkData, NewChunkResult, OldChunkData, OldChunkResult,
@@ -7,27 +9,50 @@ use near_chain::types::{
     ApplyChunkBlockContext, ApplyChunkResult, RuntimeAdapter, StorageDataSource,
 };
 use near_chain::validate::validate_chunk_with_chunk_extra_and_receipts_root;
-use near_chain::{Chain, ChainStore, ChainStoreAccess};
+use near_chain::{filter_incoming_receipts_for_shard, Chain, ChainStore, ChainStoreAccess};
+use near_chain_configs::{ChunkValidatorConfig, MutableConfigValue, RemoteChunkValidationConfig};
+use near_chain_primitives::error::ChunkWitnessValidationError;
 use near_chain_primitives::Error;
+use near_client_primitives::debug::EndorsementStatus;
 use near_epoch_manager::EpochManagerAdapter;
+use near_network::state_witness::SendChunkEndorsementsRequest;
 use near_network::types::{NetworkRequests, PeerManagerMessageRequest};
-use near_primitives::challenge::PartialState;
+use near_primitives::challenge::{ChallengeBody, PartialState};
 use near_primitives::checked_feature;
 use near_primitives::chunk_validation::{
-    ChunkEndorsement, ChunkEndorsementInner, ChunkStateTransition, ChunkStateWitness,
-    StoredChunkStateTransitionData,
+    ChunkEndorsement, ChunkEndorsementPayload, ChunkStateWitness, WitnessTransitionsBase,
 };
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::merklize;
-use near_primitives::sharding::{ShardChunk, ShardChunkHeader};
+use near_primitives::receipt::Receipt;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::sharding::{ChunkHash, ShardChunk, ShardChunkHeader};
+use near_primitives::static_clock::StaticClock;
 use near_primitives::types::chunk_extra::ChunkExtra;
-use near_primitives::types::EpochId;
-use near_primitives::validator_signer::ValidatorSigner;
-use near_store::PartialStorage;
+use near_primitives::types::{AccountId, Balance, BlockHeight, EpochId, ShardId};
+use near_primitives::utils::index_to_bytes;
+use near_primitives::validator_signer::{ValidatorSigner, ValidatorSignerError};
+use near_primitives::views::{
+    ChunkEndorsementCountView, ChunkValidationStatusView, ShardChunkValidationStatusView,
+};
+use near_store::{DBCol, PartialStorage, Store};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::chunk_endorsement_batcher::ChunkEndorsementBatcher;
+use crate::chunk_state_witness_queue::PendingChunkStateWitness;
+use crate::chunk_witness_actor::{
+    get_chunk_state_witness, save_chunk_state_witness, ChunkWitnessRequest,
+};
+#[cfg(feature = "remote_chunk_validation")]
+use crate::remote_chunk_validation::RemoteChunkValidator;
+use crate::witness_analytics::{WitnessAnalyticsExporter, WitnessAnalyticsRecord};
+use crate::{metrics, Client};
 
-use crate::Client;
+/// Number of last blocks whose new chunks are reported in the chunk
+/// validation status.
+const DEBUG_CHUNK_ENDORSEMENT_BLOCKS: usize = 5;
 
 /// A module that handles chunk validation logic. Chunk validation refers to a
 /// critical process of stateless validation, where chunk validators (certain
@@ -38,27 +63,270 @@ pub struct ChunkValidator {
     /// The signer for our own node, if we are a validator. If not, this is None.
     my_signer: Option<Arc<dyn ValidatorSigner>>,
     epoch_manager: Arc<dyn EpochManagerAdapter>,
-    network_sender: Sender<PeerManagerMessageRequest>,
+    endorsement_batcher: Arc<ChunkEndorsementBatcher>,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
+    validation_spawner: Arc<dyn AsyncComputationSpawner>,
+    /// Thread pool of `validation_threads` threads, with its number of
+    /// threads. Rebuilt when the number changes.
+    validation_thread_pool: Mutex<Option<(usize, rayon::ThreadPool)>>,
+    validated_transitions: Arc<ValidatedTransitionsCache>,
+    /// Shared with the client config, so that it can be updated while the
+    /// node is running.
+    config: MutableConfigValue<ChunkValidatorConfig>,
+    /// Number of block producers after the one of the chunk's height which
+    /// endorsements are also sent to, in case it misses its slot.
+    num_next_block_producers: u64,
+    /// If set, a summary of every validated witness is exported.
+    witness_analytics: Option<Arc<WitnessAnalyticsExporter>>,
+    /// If set, witnesses are validated by remote workers when possible.
+    #[cfg(feature = "remote_chunk_validation")]
+    remote_validator: Option<Arc<RemoteChunkValidator>>,
+    /// Set by the client actor, so that endorsements can be withheld or
+    /// doubled with the adversarial controls.
+    adv: crate::adversarial::Controls,
+    endorsed_chunks: Arc<EndorsedChunks>,
+    in_flight_validations: Arc<InFlightValidations>,
 }
 
 impl ChunkValidator {
     pub fn new(
         my_signer: Option<Arc<dyn ValidatorSigner>>,
         epoch_manager: Arc<dyn EpochManagerAdapter>,
-        network_sender: Sender<PeerManagerMessageRequest>,
+        network_sender: Sender<SendChunkEndorsementsRequest>,
         runtime_adapter: Arc<dyn RuntimeAdapter>,
+        validation_spawner: Arc<dyn AsyncComputationSpawner>,
+        num_next_block_producers: u64,
+        config: MutableConfigValue<ChunkValidatorConfig>,
+        witness_analytics_file: Option<&Path>,
+        remote_validation_config: Option<&RemoteChunkValidationConfig>,
     ) -> Self {
-        Self { my_signer, epoch_manager, network_sender, runtime_adapter }
+        let witness_analytics = witness_analytics_file.and_then(|path| {
+            match WitnessAnalyticsExporter::open(path) {
+                Ok(exporter) => Some(Arc::new(exporter)),
+                Err(err) => {
+                    tracing::error!(target: "chunk_validation", ?path, %err, "Failed to open the witness analytics file, not exporting witness records");
+                    None
+                }
+            }
+        });
+        let endorsement_batcher = Arc::new(ChunkEndorsementBatcher::new(
+            network_sender,
+            config.get().endorsement_batch_window,
+        ));
+        #[cfg(feature = "remote_chunk_validation")]
+        let remote_validator = remote_validation_config.and_then(|config| {
+            match RemoteChunkValidator::new(config).map(Arc::new).and_then(|validator| {
+                validator.start_health_checks()?;
+                Ok(validator)
+            }) {
+                Ok(validator) => Some(validator),
+                Err(err) => {
+                    tracing::error!(target: "chunk_validation", %err, "Failed to set up remote chunk validation, validating locally");
+                    None
+                }
+            }
+        });
+        #[cfg(not(feature = "remote_chunk_validation"))]
+        if remote_validation_config.is_some() {
+            tracing::warn!(target: "chunk_validation", "remote_chunk_validation is configured, but the node is built without the remote_chunk_validation feature");
+        }
+        let endorsed_chunks = Arc::new(EndorsedChunks::new(runtime_adapter.store().clone()));
+        Self {
+            my_signer,
+            epoch_manager,
+            endorsement_batcher,
+            runtime_adapter,
+            validation_spawner,
+            validation_thread_pool: Mutex::new(None),
+            validated_transitions: Arc::new(Mutex::new(LruCache::new(
+                VALIDATED_TRANSITIONS_CACHE_SIZE,
+            ))),
+            config,
+            num_next_block_producers,
+            witness_analytics,
+            #[cfg(feature = "remote_chunk_validation")]
+            remote_validator,
+            adv: crate::adversarial::Controls::default(),
+            endorsed_chunks,
+            in_flight_validations: Arc::new(InFlightValidations::default()),
+        }
+    }
+
+    /// Applies the updated options, e.g. after `config.json` was reloaded.
+    /// Witnesses which are already being validated are not affected.
+    pub fn update_config(&self, config: ChunkValidatorConfig) {
+        self.config.update(config);
+        self.endorsement_batcher.set_window(config.endorsement_batch_window);
+    }
+
+    /// Runs the validation of a witness on a thread pool of
+    /// `validation_threads` threads if set, or with the validation spawner
+    /// otherwise. Validations running on a thread pool replaced after the
+    /// number of threads changed finish there.
+    fn spawn_validation(&self, f: impl FnOnce() + Send + 'static) {
+        let Some(num_threads) = self.config.get().validation_threads else {
+            self.validation_spawner.spawn("validate_chunk_state_witness", f);
+            return;
+        };
+        let mut thread_pool = self.validation_thread_pool.lock().unwrap();
+        if thread_pool.as_ref().map_or(true, |(current, _)| *current != num_threads) {
+            let new_thread_pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .thread_name(|i| format!("chunk_validation_{}", i))
+                .build();
+            match new_thread_pool {
+                Ok(new_thread_pool) => *thread_pool = Some((num_threads, new_thread_pool)),
+                Err(err) => {
+                    tracing::error!(target: "chunk_validation", num_threads, %err, "Failed to create the chunk validation thread pool");
+                    drop(thread_pool);
+                    self.validation_spawner.spawn("validate_chunk_state_witness", f);
+                    return;
+                }
+            }
+        }
+        thread_pool.as_ref().unwrap().1.spawn(f);
+    }
+
+    /// Sends the chunk endorsements which have been batched for long enough.
+    /// Returns how long until the next batch should be sent, if any.
+    pub fn flush_chunk_endorsements(&self) -> Option<std::time::Duration> {
+        self.endorsement_batcher.flush_expired()
+    }
+
+    /// Sends all batched chunk endorsements right away, regardless of how
+    /// long they have been batched for.
+    pub fn flush_all_chunk_endorsements(&self) {
+        self.endorsement_batcher.flush_all();
+    }
+
+    /// Sets the adversarial controls changing how chunks are endorsed.
+    pub fn set_adv_controls(&mut self, adv: crate::adversarial::Controls) {
+        self.adv = adv;
+    }
+
+    /// Replaces the signer of our own node, e.g. after the validator key was
+    /// reloaded.
+    pub fn set_signer(&mut self, my_signer: Option<Arc<dyn ValidatorSigner>>) {
+        self.my_signer = my_signer;
+    }
+
+    /// Waits for the validations in progress, which were started with the
+    /// signer of our own node, to finish and sends their endorsements right
+    /// away.
+    pub fn wait_for_validations(&self) {
+        self.in_flight_validations.wait_all();
+        self.endorsement_batcher.flush_all();
+    }
+
+    /// Returns the largest height created of the chunks endorsed by our own
+    /// node.
+    pub fn last_endorsed_height(&self) -> BlockHeight {
+        self.endorsed_chunks.heights.lock().unwrap().last_endorsed
+    }
+
+    /// Stops endorsing chunks created at heights up to `height`, e.g. because
+    /// another node holding the same validator key endorsed them.
+    pub fn set_endorsement_height_floor(&self, height: BlockHeight) {
+        let mut heights = self.endorsed_chunks.heights.lock().unwrap();
+        heights.floor = heights.floor.max(height);
+    }
+
+    /// Picks the signer holding the key registered for our validator in the
+    /// given epoch. During key rotation this may be the next key rather than
+    /// the current one.
+    fn signer_for_epoch(
+        &self,
+        my_signer: &Arc<dyn ValidatorSigner>,
+        epoch_id: &EpochId,
+        last_known_block_hash: &CryptoHash,
+    ) -> Result<Arc<dyn ValidatorSigner>, Error> {
+        let (validator, _) = self.epoch_manager.get_validator_by_account_id(
+            epoch_id,
+            last_known_block_hash,
+            my_signer.validator_id(),
+        )?;
+        my_signer.signer_for_public_key(validator.public_key()).ok_or_else(|| {
+            Error::Other(format!(
+                "No validator key matches key {} registered for epoch {:?}",
+                validator.public_key(),
+                epoch_id
+            ))
+        })
+    }
+
+    /// Returns the block producers to send endorsements of the chunk to: the
+    /// one of the chunk's height, which is supposed to include the chunk, and
+    /// the ones of the following heights, which include it if the former
+    /// misses its slot. Heights past the end of the epoch are assigned by the
+    /// chunk's epoch, so the list is only a best guess near epoch boundaries.
+    fn endorsement_block_producers(
+        &self,
+        epoch_id: &EpochId,
+        chunk_header: &ShardChunkHeader,
+    ) -> Result<Vec<AccountId>, Error> {
+        let height = chunk_header.height_created();
+        let mut block_producers = vec![];
+        for height in height..=height + self.num_next_block_producers {
+            let block_producer = self.epoch_manager.get_block_producer(epoch_id, height)?;
+            if !block_producers.contains(&block_producer) {
+                block_producers.push(block_producer);
+            }
+        }
+        Ok(block_producers)
+    }
+
+    /// Endorses a chunk produced by this node, for which it is also a chunk
+    /// validator. The chunk was produced on top of the state this node
+    /// computed itself, so there is no state witness to validate. Returns the
+    /// endorsement if this node is one of the block producers to send it to,
+    /// so that it can be processed locally, and sends it to the others.
+    pub fn endorse_own_chunk(
+        &self,
+        epoch_id: &EpochId,
+        chunk_header: &ShardChunkHeader,
+    ) -> Result<Option<ChunkEndorsement>, Error> {
+        let Some(my_signer) = self.my_signer.as_ref() else {
+            return Err(Error::NotAValidator);
+        };
+        let signer = self.signer_for_epoch(my_signer, epoch_id, chunk_header.prev_block_hash())?;
+        let payload =
+            chunk_endorsement_payload(self.epoch_manager.as_ref(), epoch_id, chunk_header)?;
+        let endorsement = create_chunk_endorsement(signer.as_ref(), &payload)?;
+        if !self.endorsed_chunks.record(chunk_header) {
+            return Ok(None);
+        }
+        let mut block_producers = self.endorsement_block_producers(epoch_id, chunk_header)?;
+        let is_block_producer = block_producers.contains(my_signer.validator_id());
+        block_producers.retain(|block_producer| block_producer != my_signer.validator_id());
+        send_chunk_endorsement(
+            &self.endorsement_batcher,
+            &self.adv,
+            block_producers,
+            signer.as_ref(),
+            &payload,
+            endorsement.clone(),
+        );
+        Ok(is_block_producer.then_some(endorsement))
     }
 
     /// Performs the chunk validation logic. When done, it will send the chunk
     /// endorsement message to the block producer. The actual validation logic
-    /// happens in a separate thread.
+    /// is run by the validation spawner, i.e. in a separate thread.
+    /// If `db_fallback` is set, trie nodes missing from the witness are read
+    /// from storage, which is only possible if we track the shard. The chunk
+    /// is still not endorsed in that case.
+    /// If `determinism_check` is set, the main state transition is also
+    /// executed from local state, see `check_execution_determinism`.
+    /// If `stateful_fallback` is set and validation fails, the chunk is
+    /// validated once more from local state, see `validate_chunk_statefully`.
     pub fn start_validating_chunk(
         &self,
         state_witness: ChunkStateWitness,
         chain_store: &ChainStore,
+        db_fallback: bool,
+        determinism_check: bool,
+        stateful_fallback: bool,
+        save_state_witness: bool,
     ) -> Result<(), Error> {
         let chunk_header = state_witness.chunk_header.clone();
         let Some(my_signer) = self.my_signer.as_ref() else {
@@ -78,44 +346,220 @@ impl ChunkValidator {
             return Err(Error::NotAChunkValidator);
         }
 
+        let export_analytics = {
+            let witness_analytics = self.witness_analytics.clone();
+            let size_bytes = match &witness_analytics {
+                Some(_) => borsh::object_length(&state_witness)? as u64,
+                None => 0,
+            };
+            let chunk_header = chunk_header.clone();
+            let validation_start = StaticClock::instant();
+            move |result: &str| {
+                if let Some(witness_analytics) = &witness_analytics {
+                    witness_analytics.export(&WitnessAnalyticsRecord::new(
+                        chunk_header.chunk_hash(),
+                        chunk_header.shard_id(),
+                        chunk_header.height_created(),
+                        size_bytes,
+                        validation_start.elapsed(),
+                        result,
+                    ));
+                }
+            }
+        };
         let pre_validation_result = pre_validate_chunk_state_witness(
             &state_witness,
             chain_store,
             self.epoch_manager.as_ref(),
-        )?;
+            self.config.get().max_witness_size.map(|size| size.as_u64()),
+            db_fallback,
+            determinism_check,
+        )
+        .map_err(|err| {
+            record_validation_failure(chunk_header.shard_id(), &err);
+            export_analytics(validation_failure_reason(&err));
+            err
+        })?;
+        let stateful_transitions = stateful_fallback.then(|| {
+            (
+                stateful_transition(&pre_validation_result.main_transition_params),
+                pre_validation_result.implicit_transition_params.clone(),
+            )
+        });
+        // An incremental witness can only be validated on top of the
+        // transitions of the previous witness of the shard. Without them, e.g.
+        // if the previous witness was missed or is still being validated, the
+        // caller falls back to requesting the full witness.
+        if let Some(base) = &state_witness.transitions_base {
+            let main_transition = &pre_validation_result.main_transition_params;
+            let main_epoch_id =
+                self.epoch_manager.get_epoch_id(&main_transition.block.block_hash)?;
+            let shard_uid = self
+                .epoch_manager
+                .shard_id_to_uid(main_transition.chunk_header.shard_id(), &main_epoch_id)?;
+            if !self.validated_transitions.lock().unwrap().contains(&(shard_uid, base.clone())) {
+                return Err(ChunkWitnessValidationError::MissingTransitionsBase {
+                    last_block_hash: base.last_block_hash,
+                }
+                .into());
+            }
+        }
 
-        let block_producer =
-            self.epoch_manager.get_block_producer(&epoch_id, chunk_header.height_created())?;
+        let block_producers = self.endorsement_block_producers(&epoch_id, &chunk_header)?;
 
-        let network_sender = self.network_sender.clone();
-        let signer = self.my_signer.clone().unwrap();
+        let endorsement_batcher = self.endorsement_batcher.clone();
+        let signer = self.signer_for_epoch(my_signer, &epoch_id, chunk_header.prev_block_hash())?;
+        let endorsement_payload =
+            chunk_endorsement_payload(self.epoch_manager.as_ref(), &epoch_id, &chunk_header)?;
         let epoch_manager = self.epoch_manager.clone();
         let runtime_adapter = self.runtime_adapter.clone();
-        rayon::spawn(move || {
+        let validated_transitions = self.validated_transitions.clone();
+        let adv = self.adv.clone();
+        let endorsed_chunks = self.endorsed_chunks.clone();
+        #[cfg(feature = "remote_chunk_validation")]
+        let remote_validator = self.remote_validator.clone();
+        // The witness is only persisted once it's known to be valid, so that
+        // peers can't fill the store with arbitrary data. Incremental witnesses
+        // aren't, as their transitions base isn't part of their encoding.
+        let save_validated_witness = {
+            let store = self.runtime_adapter.store().clone();
+            let witness = (save_state_witness && state_witness.transitions_base.is_none())
+                .then(|| state_witness.clone());
+            move || {
+                let Some(witness) = &witness else {
+                    return;
+                };
+                if let Err(err) = save_chunk_state_witness(&store, witness) {
+                    tracing::error!(
+                        target: "chunk_validation",
+                        chunk_hash=?witness.chunk_header.chunk_hash(),
+                        ?err,
+                        "Failed to save the chunk state witness",
+                    );
+                }
+            }
+        };
+        let in_flight_validation = InFlightValidations::start(&self.in_flight_validations);
+        self.spawn_validation(move || {
+            let _in_flight_validation = in_flight_validation;
+            // Remote workers don't have the transitions incremental witnesses
+            // build upon.
+            #[cfg(feature = "remote_chunk_validation")]
+            if let Some(remote_validator) =
+                remote_validator.filter(|_| state_witness.transitions_base.is_none())
+            {
+                match remote_validator.validate(
+                    &state_witness,
+                    &endorsement_payload,
+                    signer.validator_id(),
+                    &signer.public_key(),
+                ) {
+                    Ok(endorsement) => {
+                        export_analytics("valid");
+                        save_validated_witness();
+                        if !endorsed_chunks.record(&chunk_header) {
+                            return;
+                        }
+                        send_chunk_endorsement(
+                            &endorsement_batcher,
+                            &adv,
+                            block_producers,
+                            signer.as_ref(),
+                            &endorsement_payload,
+                            endorsement,
+                        );
+                        return;
+                    }
+                    Err(err) => {
+                        tracing::debug!(
+                            target: "chunk_validation",
+                            chunk_hash=?chunk_header.chunk_hash(),
+                            %err,
+                            "Remote validation failed, validating locally",
+                        );
+                    }
+                }
+            }
             match validate_chunk_state_witness(
                 state_witness,
                 pre_validation_result,
                 epoch_manager.as_ref(),
                 runtime_adapter.as_ref(),
+                &validated_transitions,
             ) {
                 Ok(()) => {
                     tracing::debug!(
                         target: "chunk_validation",
                         chunk_hash=?chunk_header.chunk_hash(),
-                        block_producer=%block_producer,
+                        ?block_producers,
                         "Chunk validated successfully, sending endorsement",
                     );
-                    let endorsement_to_sign = ChunkEndorsementInner::new(chunk_header.chunk_hash());
-                    let endorsement = ChunkEndorsement {
-                        account_id: signer.validator_id().clone(),
-                        signature: signer.sign_chunk_endorsement(&endorsement_to_sign),
-                        inner: endorsement_to_sign,
-                    };
-                    network_sender.send(PeerManagerMessageRequest::NetworkRequests(
-                        NetworkRequests::ChunkEndorsement(block_producer, endorsement),
-                    ));
+                    export_analytics("valid");
+                    save_validated_witness();
+                    let endorsement =
+                        match create_chunk_endorsement(signer.as_ref(), &endorsement_payload) {
+                            Ok(endorsement) => endorsement,
+                            Err(err) => {
+                                tracing::error!(
+                                    target: "chunk_validation",
+                                    chunk_hash=?chunk_header.chunk_hash(),
+                                    %err,
+                                    "Failed to sign the chunk endorsement",
+                                );
+                                return;
+                            }
+                        };
+                    if !endorsed_chunks.record(&chunk_header) {
+                        return;
+                    }
+                    send_chunk_endorsement(
+                        &endorsement_batcher,
+                        &adv,
+                        block_producers,
+                        signer.as_ref(),
+                        &endorsement_payload,
+                        endorsement,
+                    );
                 }
                 Err(err) => {
+                    if let Error::InvalidChunkStateWitness(
+                        ChunkWitnessValidationError::IncompleteWitness { missing_nodes },
+                    ) = &err
+                    {
+                        record_validation_failure(chunk_header.shard_id(), &err);
+                        export_analytics(validation_failure_reason(&err));
+                        report_incomplete_witness(
+                            epoch_manager.as_ref(),
+                            &chunk_header,
+                            missing_nodes,
+                        );
+                        return;
+                    }
+                    if let Some((main_transition, implicit_transitions)) = stateful_transitions {
+                        match validate_chunk_statefully(
+                            &chunk_header,
+                            main_transition,
+                            implicit_transitions,
+                            epoch_manager.as_ref(),
+                            runtime_adapter.as_ref(),
+                        ) {
+                            Ok(()) => {
+                                report_recorded_storage_divergence(&chunk_header, &err);
+                                export_analytics("recorded_storage_divergence");
+                                return;
+                            }
+                            Err(stateful_err) => {
+                                tracing::debug!(
+                                    target: "chunk_validation",
+                                    chunk_hash=?chunk_header.chunk_hash(),
+                                    ?stateful_err,
+                                    "Chunk is invalid from local state too",
+                                );
+                            }
+                        }
+                    }
+                    record_validation_failure(chunk_header.shard_id(), &err);
+                    export_analytics(validation_failure_reason(&err));
                     tracing::error!("Failed to validate chunk: {:?}", err);
                 }
             }
@@ -124,6 +568,303 @@ impl ChunkValidator {
     }
 }
 
+/// Returns the payload that chunk validators sign to endorse the chunk, in the
+/// format used by the protocol version of the epoch the chunk belongs to.
+pub(crate) fn chunk_endorsement_payload(
+    epoch_manager: &dyn EpochManagerAdapter,
+    epoch_id: &EpochId,
+    chunk_header: &ShardChunkHeader,
+) -> Result<ChunkEndorsementPayload, Error> {
+    let protocol_version = epoch_manager.get_epoch_protocol_version(epoch_id)?;
+    Ok(ChunkEndorsementPayload::new(
+        chunk_header.chunk_hash(),
+        epoch_id.clone(),
+        chunk_header.height_created(),
+        protocol_version,
+    ))
+}
+
+fn create_chunk_endorsement(
+    signer: &dyn ValidatorSigner,
+    payload: &ChunkEndorsementPayload,
+) -> Result<ChunkEndorsement, ValidatorSignerError> {
+    Ok(ChunkEndorsement {
+        account_id: signer.validator_id().clone(),
+        signature: signer.sign_chunk_endorsement(payload)?,
+        inner: payload.inner(),
+    })
+}
+
+/// Counts the witness validations run by the validation spawner, so that they
+/// can be waited for.
+#[derive(Default)]
+struct InFlightValidations {
+    count: Mutex<usize>,
+    all_done: Condvar,
+}
+
+impl InFlightValidations {
+    /// Counts a validation until the returned guard is dropped.
+    fn start(this: &Arc<Self>) -> InFlightValidationGuard {
+        *this.count.lock().unwrap() += 1;
+        InFlightValidationGuard(this.clone())
+    }
+
+    fn wait_all(&self) {
+        let count = self.count.lock().unwrap();
+        let _count = self.all_done.wait_while(count, |count| *count > 0).unwrap();
+    }
+}
+
+struct InFlightValidationGuard(Arc<InFlightValidations>);
+
+impl Drop for InFlightValidationGuard {
+    fn drop(&mut self) {
+        let mut count = self.0.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.0.all_done.notify_all();
+        }
+    }
+}
+
+/// Chunks endorsed by this node, persisted in `DBCol::EndorsedChunks`, so
+/// that it never endorses two different chunks of the same shard and height,
+/// which would get it slashed, not even after a restart.
+struct EndorsedChunks {
+    store: Store,
+    /// Also serializes recording the chunks, as they are endorsed from the
+    /// validation threads.
+    heights: Mutex<EndorsedHeights>,
+}
+
+#[derive(Default)]
+struct EndorsedHeights {
+    /// Largest height created of the endorsed chunks.
+    last_endorsed: BlockHeight,
+    /// Chunks created at heights up to this one are not endorsed.
+    floor: BlockHeight,
+}
+
+impl EndorsedChunks {
+    fn new(store: Store) -> Self {
+        let last_endorsed = store
+            .iter(DBCol::EndorsedChunks)
+            .filter_map(|item| item.ok())
+            .filter_map(|(key, _)| Some(u64::from_le_bytes((*key).try_into().ok()?)))
+            .max()
+            .unwrap_or_default();
+        Self { store, heights: Mutex::new(EndorsedHeights { last_endorsed, floor: 0 }) }
+    }
+
+    /// Records the chunk as endorsed and returns whether it may be endorsed,
+    /// which is not the case if another chunk of the same shard and height
+    /// was endorsed before.
+    fn record(&self, chunk_header: &ShardChunkHeader) -> bool {
+        let mut heights = self.heights.lock().unwrap();
+        if chunk_header.height_created() <= heights.floor {
+            tracing::warn!(
+                target: "chunk_validation",
+                chunk_hash=?chunk_header.chunk_hash(),
+                height_created=chunk_header.height_created(),
+                floor=heights.floor,
+                "Not endorsing chunk, its height may have been endorsed by another node with the same validator key",
+            );
+            return false;
+        }
+        match self.record_impl(chunk_header) {
+            Ok(None) => {
+                heights.last_endorsed = heights.last_endorsed.max(chunk_header.height_created());
+                true
+            }
+            Ok(Some(endorsed_chunk_hash)) => {
+                tracing::warn!(
+                    target: "chunk_validation",
+                    chunk_hash=?chunk_header.chunk_hash(),
+                    ?endorsed_chunk_hash,
+                    shard_id=chunk_header.shard_id(),
+                    height_created=chunk_header.height_created(),
+                    "Not endorsing chunk, another chunk of the same shard and height was endorsed",
+                );
+                false
+            }
+            Err(err) => {
+                tracing::error!(target: "chunk_validation", chunk_hash=?chunk_header.chunk_hash(), ?err, "Failed to record endorsed chunk");
+                false
+            }
+        }
+    }
+
+    /// Returns the hash of the other chunk of the same shard and height which
+    /// was endorsed, if any, and otherwise records the chunk.
+    fn record_impl(&self, chunk_header: &ShardChunkHeader) -> Result<Option<ChunkHash>, Error> {
+        let key = index_to_bytes(chunk_header.height_created());
+        let mut endorsed: HashMap<ShardId, ChunkHash> =
+            self.store.get_ser(DBCol::EndorsedChunks, &key)?.unwrap_or_default();
+        let chunk_hash = chunk_header.chunk_hash();
+        match endorsed.get(&chunk_header.shard_id()) {
+            Some(endorsed_chunk_hash) if endorsed_chunk_hash == &chunk_hash => return Ok(None),
+            Some(endorsed_chunk_hash) => return Ok(Some(endorsed_chunk_hash.clone())),
+            None => {}
+        }
+        endorsed.insert(chunk_header.shard_id(), chunk_hash);
+        let mut store_update = self.store.store_update();
+        store_update.set_ser(DBCol::EndorsedChunks, &key, &endorsed)?;
+        store_update.commit()?;
+        Ok(None)
+    }
+}
+
+/// Sends the endorsement to the block producers. Adversarial controls may
+/// make the node withhold the endorsement instead, or also endorse a
+/// conflicting chunk of the same height and shard. `payload` is what
+/// `endorsement` signs.
+fn send_chunk_endorsement(
+    endorsement_batcher: &ChunkEndorsementBatcher,
+    adv: &crate::adversarial::Controls,
+    block_producers: Vec<AccountId>,
+    signer: &dyn ValidatorSigner,
+    payload: &ChunkEndorsementPayload,
+    endorsement: ChunkEndorsement,
+) {
+    if adv.withhold_chunk_endorsements() {
+        tracing::debug!(target: "adversary", chunk_hash=?endorsement.inner.chunk_hash, "Withholding chunk endorsement");
+        return;
+    }
+    if adv.double_endorse_chunks() {
+        let mut conflicting = payload.clone();
+        let conflicting_chunk_hash = ChunkHash(hash(payload.chunk_hash().0.as_ref()));
+        match &mut conflicting {
+            ChunkEndorsementPayload::V1(inner) => inner.chunk_hash = conflicting_chunk_hash.clone(),
+            ChunkEndorsementPayload::V2(inner) => inner.chunk_hash = conflicting_chunk_hash.clone(),
+        }
+        tracing::debug!(target: "adversary", chunk_hash=?endorsement.inner.chunk_hash, ?conflicting_chunk_hash, "Endorsing a conflicting chunk");
+        if let Ok(conflicting_endorsement) = create_chunk_endorsement(signer, &conflicting) {
+            endorsement_batcher.add_for_all(block_producers.clone(), conflicting_endorsement);
+        }
+    }
+    endorsement_batcher.add_for_all(block_producers, endorsement);
+}
+
+/// Returns the reason of a failed witness validation, as reported in the
+/// metrics.
+fn validation_failure_reason(err: &Error) -> &'static str {
+    match err {
+        Error::InvalidChunkStateWitness(err) => err.prometheus_label_value(),
+        err => err.prometheus_label_value(),
+    }
+}
+
+/// Records a failed witness validation in the metrics, labelled by the reason
+/// of the failure.
+fn record_validation_failure(shard_id: ShardId, err: &Error) {
+    metrics::CHUNK_STATE_WITNESS_VALIDATION_FAILURES_TOTAL
+        .with_label_values(&[&shard_id.to_string(), validation_failure_reason(err)])
+        .inc();
+}
+
+/// Reports a state witness which was missing trie nodes, but could be
+/// validated by reading them from storage. This is a misbehavior of the chunk
+/// producer, so it is logged as such rather than as a generic failure.
+fn report_incomplete_witness(
+    epoch_manager: &dyn EpochManagerAdapter,
+    chunk_header: &ShardChunkHeader,
+    missing_nodes: &[CryptoHash],
+) {
+    let shard_id = chunk_header.shard_id();
+    metrics::CHUNK_STATE_WITNESS_FALLBACK_TRIE_NODES_TOTAL
+        .with_label_values(&[&shard_id.to_string()])
+        .inc_by(missing_nodes.len() as u64);
+    let chunk_producer = epoch_manager
+        .get_epoch_id_from_prev_block(chunk_header.prev_block_hash())
+        .and_then(|epoch_id| {
+            epoch_manager.get_chunk_producer(&epoch_id, chunk_header.height_created(), shard_id)
+        });
+    tracing::warn!(
+        target: "chunk_validation",
+        chunk_hash=?chunk_header.chunk_hash(),
+        shard_id,
+        ?chunk_producer,
+        num_missing_nodes=missing_nodes.len(),
+        ?missing_nodes,
+        "Chunk producer misbehavior: witness incomplete",
+    );
+}
+
+/// Reports a chunk which is valid according to local state although its
+/// witness failed validation. Either the chunk producer recorded the storage
+/// accesses wrongly or the recorded state isn't applied the same way as the
+/// local one. This is a bug rather than misbehavior of the chunk producer.
+fn report_recorded_storage_divergence(chunk_header: &ShardChunkHeader, err: &Error) {
+    metrics::CHUNK_VALIDATION_RECORDED_STORAGE_DIVERGENCES_TOTAL
+        .with_label_values(&[&chunk_header.shard_id().to_string()])
+        .inc();
+    tracing::error!(
+        target: "chunk_validation",
+        chunk_hash=?chunk_header.chunk_hash(),
+        shard_id=chunk_header.shard_id(),
+        ?err,
+        "Chunk is valid from local state but its witness failed validation: recorded storage diverged",
+    );
+}
+
+/// Storage to apply a state transition of the witness with. With
+/// `db_fallback`, trie nodes missing from `base_state` are read from storage.
+fn witness_storage_data_source(base_state: PartialState, db_fallback: bool) -> StorageDataSource {
+    let storage = PartialStorage { nodes: base_state };
+    if db_fallback {
+        StorageDataSource::RecordedWithDbFallback(storage)
+    } else {
+        StorageDataSource::Recorded(storage)
+    }
+}
+
+/// A block whose new chunks sent receipts which are applied by a later chunk.
+pub(crate) struct ReceiptSourceBlock {
+    pub block_hash: CryptoHash,
+    /// The id of the receiving shard in the shard layout of the block.
+    pub shard_id: ShardId,
+    pub new_chunks: Vec<ShardChunkHeader>,
+}
+
+/// Returns the blocks whose new chunks sent the receipts applied along with
+/// the new chunk of the shard included in the block with the given hash,
+/// newest first: that block, and the blocks before it down to the one which
+/// includes the previous new chunk of the shard (exclusive). `shard_id` is in
+/// the shard layout of the given block.
+pub(crate) fn get_receipt_source_blocks(
+    store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    mut block_hash: CryptoHash,
+    mut shard_id: ShardId,
+) -> Result<Vec<ReceiptSourceBlock>, Error> {
+    let mut block = store.get_block(&block_hash)?;
+    let mut source_blocks = Vec::new();
+    loop {
+        source_blocks.push(ReceiptSourceBlock {
+            block_hash,
+            shard_id,
+            new_chunks: near_chain::sharding::new_chunk_headers(&block),
+        });
+        let prev_hash = *block.header().prev_hash();
+        let prev_block = store.get_block(&prev_hash)?;
+        let prev_shard_id = epoch_manager.get_prev_shard_ids(&prev_hash, vec![shard_id])?[0];
+        let Some(is_new_chunk) =
+            prev_block.chunks().get(prev_shard_id as usize).map(|chunk| chunk.is_new_chunk())
+        else {
+            return Err(ChunkWitnessValidationError::ShardNotInBlock {
+                shard_id: prev_shard_id,
+                block_hash: prev_hash,
+            }
+            .into());
+        };
+        if is_new_chunk {
+            return Ok(source_blocks);
+        }
+        (block_hash, shard_id, block) = (prev_hash, prev_shard_id, prev_block);
+    }
+}
+
 /// Pre-validates the chunk's receipts and transactions against the chain.
 /// We do this before handing off the computationally intensive part to a
 /// validation thread.
@@ -131,114 +872,140 @@ fn pre_validate_chunk_state_witness(
     state_witness: &ChunkStateWitness,
     store: &ChainStore,
     epoch_manager: &dyn EpochManagerAdapter,
+    max_witness_size: Option<u64>,
+    db_fallback: bool,
+    determinism_check: bool,
 ) -> Result<PreValidationOutput, Error> {
     let shard_id = state_witness.chunk_header.shard_id();
 
-    // First, go back through the blockchain history to locate the last new chunk
-    // and last last new chunk for the shard.
+    let epoch_id =
+        epoch_manager.get_epoch_id_from_prev_block(state_witness.chunk_header.prev_block_hash())?;
+    let protocol_limit = epoch_manager
+        .get_epoch_config(&epoch_id)?
+        .chunk_validation_config
+        .max_chunk_state_witness_size;
+    let limit = max_witness_size.map_or(protocol_limit, |limit| limit.min(protocol_limit));
+    let size = borsh::object_length(state_witness)? as u64;
+    if size > limit {
+        return Err(ChunkWitnessValidationError::WitnessTooLarge { size, limit }.into());
+    }
+
+    // First, go back through the blockchain history to locate the last new
+    // chunk for the shard.
 
-    // Blocks from the last new chunk (exclusive) to the parent block (inclusive).
+    // Blocks from the last new chunk (inclusive) to the parent block (inclusive).
     let mut blocks_after_last_chunk = Vec::new();
-    // Blocks from the last last new chunk (exclusive) to the last new chunk (inclusive).
-    let mut blocks_after_last_last_chunk = Vec::new();
+
+    // The shard id of the chunk in the last new chunk block. It differs from
+    // `shard_id` if the shard layout changed since the last new chunk.
+    let mut last_chunk_shard_id = shard_id;
 
     {
         let mut block_hash = *state_witness.chunk_header.prev_block_hash();
-        let mut prev_chunks_seen = 0;
+        // Shard id in the layout of the epoch of the block after `block_hash`.
+        let mut next_shard_id = shard_id;
         loop {
             let block = store.get_block(&block_hash)?;
+            // Map the shard id to the shard layout of this block, in case we
+            // are crossing an epoch boundary with a shard layout change.
+            let block_shard_id =
+                epoch_manager.get_prev_shard_ids(&block_hash, vec![next_shard_id])?[0];
             let chunks = block.chunks();
-            let Some(chunk) = chunks.get(shard_id as usize) else {
-                return Err(Error::InvalidChunkStateWitness(format!(
-                    "Shard {} does not exist in block {:?}",
-                    shard_id, block_hash
-                )));
+            let Some(chunk) = chunks.get(block_shard_id as usize) else {
+                return Err(ChunkWitnessValidationError::ShardNotInBlock {
+                    shard_id: block_shard_id,
+                    block_hash,
+                }
+                .into());
             };
             let is_new_chunk = chunk.is_new_chunk();
             block_hash = *block.header().prev_hash();
-            if prev_chunks_seen == 0 {
-                blocks_after_last_chunk.push(block);
-            } else if prev_chunks_seen == 1 {
-                blocks_after_last_last_chunk.push(block);
-            }
+            next_shard_id = block_shard_id;
+            blocks_after_last_chunk.push(block);
+            last_chunk_shard_id = block_shard_id;
             if is_new_chunk {
-                prev_chunks_seen += 1;
-            }
-            if prev_chunks_seen == 2 {
                 break;
             }
         }
     }
 
-    // Compute the chunks from which receipts should be collected.
-    // let mut chunks_to_collect_receipts_from = Vec::new();
-    // for block in blocks_after_last_last_chunk.iter().rev() {
-    //     // To stay consistent with the order in which receipts are applied,
-    //     // blocks are iterated in reverse order (from new to old), and
-    //     // chunks are shuffled for each block.
-    //     let mut chunks_in_block = block
-    //         .chunks()
-    //         .iter()
-    //         .map(|chunk| (chunk.chunk_hash(), chunk.prev_outgoing_receipts_root()))
-    //         .collect::<Vec<_>>();
-    //     shuffle_receipt_proofs(&mut chunks_in_block, block.hash());
-    //     chunks_to_collect_receipts_from.extend(chunks_in_block);
-    // }
-
-    // Verify that for each chunk, the receipts that have been provided match
-    // the receipts that we are expecting.
-    // let mut receipts_to_apply = Vec::new();
-    // for (chunk_hash, receipt_root) in chunks_to_collect_receipts_from {
-    //     let Some(receipt_proof) = state_witness.source_receipt_proofs.get(&chunk_hash) else {
-    //         return Err(Error::InvalidChunkStateWitness(format!(
-    //             "Missing source receipt proof for chunk {:?}",
-    //             chunk_hash
-    //         )));
-    //     };
-    //     if !receipt_proof.verify_against_receipt_root(receipt_root) {
-    //         return Err(Error::InvalidChunkStateWitness(format!(
-    //             "Provided receipt proof failed verification against receipt root for chunk {:?}",
-    //             chunk_hash
-    //         )));
-    //     }
-    //     // TODO(#10265): This does not currently handle shard layout change.
-    //     if receipt_proof.1.to_shard_id != shard_id {
-    //         return Err(Error::InvalidChunkStateWitness(format!(
-    //             "Receipt proof for chunk {:?} is for shard {}, expected shard {}",
-    //             chunk_hash, receipt_proof.1.to_shard_id, shard_id
-    //         )));
-    //     }
-    //     receipts_to_apply.extend(receipt_proof.0.iter().cloned());
-    // }
     let (last_chunk_block, implicit_transition_blocks) =
         blocks_after_last_chunk.split_last().unwrap();
-    let receipts_response = &store.get_incoming_receipts_for_shard(
-        epoch_manager,
-        shard_id,
-        *last_chunk_block.header().hash(),
-        blocks_after_last_last_chunk.last().unwrap().header().height(),
-    )?;
-    let receipts_to_apply = near_chain::chain::collect_receipts_from_response(receipts_response);
+    // Verify that for each new chunk, the receipts that have been provided
+    // match the receipts that we are expecting, and order them the same way
+    // as the chunk producer does when collecting incoming receipts.
+    let last_chunk_block_hash = *last_chunk_block.header().hash();
+    let target_shard_layout =
+        epoch_manager.get_shard_layout_from_prev_block(&last_chunk_block_hash)?;
+    let mut receipts_to_apply = Vec::new();
+    for source_block in
+        get_receipt_source_blocks(store, epoch_manager, last_chunk_block_hash, last_chunk_shard_id)?
+    {
+        let mut receipt_proofs = Vec::new();
+        for chunk in &source_block.new_chunks {
+            let chunk_hash = chunk.chunk_hash();
+            let Some(receipt_proof) = state_witness.source_receipt_proofs.get(&chunk_hash) else {
+                return Err(
+                    ChunkWitnessValidationError::MissingSourceReceiptProof { chunk_hash }.into()
+                );
+            };
+            if !receipt_proof.verify_against_receipt_root(chunk.prev_outgoing_receipts_root()) {
+                return Err(
+                    ChunkWitnessValidationError::InvalidSourceReceiptProof { chunk_hash }.into()
+                );
+            }
+            if receipt_proof.1.to_shard_id != source_block.shard_id {
+                return Err(ChunkWitnessValidationError::SourceReceiptProofShardMismatch {
+                    chunk_hash,
+                    proof_shard_id: receipt_proof.1.to_shard_id,
+                    shard_id: source_block.shard_id,
+                }
+                .into());
+            }
+            receipt_proofs.push(receipt_proof.clone());
+        }
+        let mut receipt_proofs_by_shard_id = near_chain::sharding::group_and_shuffle_receipt_proofs(
+            receipt_proofs,
+            &source_block.block_hash,
+        );
+        let receipt_proofs =
+            receipt_proofs_by_shard_id.remove(&source_block.shard_id).unwrap_or_default();
+        // Like when collecting incoming receipts, only the receipts of the
+        // shard in the shard layout of the chunk are applied.
+        for receipt_proof in filter_incoming_receipts_for_shard(
+            &target_shard_layout,
+            last_chunk_shard_id,
+            Arc::new(receipt_proofs),
+        ) {
+            receipts_to_apply.extend(receipt_proof.0);
+        }
+    }
     let applied_receipts_hash = hash(&borsh::to_vec(receipts_to_apply.as_slice()).unwrap());
     if applied_receipts_hash != state_witness.applied_receipts_hash {
-        return Err(Error::InvalidChunkStateWitness(format!(
-            "Receipts hash {:?} does not match expected receipts hash {:?}",
-            applied_receipts_hash, state_witness.applied_receipts_hash
-        )));
+        return Err(ChunkWitnessValidationError::ReceiptsHashMismatch {
+            expected: state_witness.applied_receipts_hash,
+            actual: applied_receipts_hash,
+        }
+        .into());
     }
     let (tx_root_from_state_witness, _) = merklize(&state_witness.transactions);
     let last_new_chunk_tx_root =
-        last_chunk_block.chunks().get(shard_id as usize).unwrap().tx_root();
+        last_chunk_block.chunks().get(last_chunk_shard_id as usize).unwrap().tx_root();
     if last_new_chunk_tx_root != tx_root_from_state_witness {
-        return Err(Error::InvalidChunkStateWitness(format!(
-            "Transaction root {:?} does not match expected transaction root {:?}",
-            tx_root_from_state_witness, last_new_chunk_tx_root
-        )));
+        return Err(ChunkWitnessValidationError::TransactionsRootMismatch {
+            expected: last_new_chunk_tx_root,
+            actual: tx_root_from_state_witness,
+        }
+        .into());
     }
 
     Ok(PreValidationOutput {
         main_transition_params: NewChunkData {
-            chunk_header: last_chunk_block.chunks().get(shard_id as usize).unwrap().clone(),
+            chunk_header: last_chunk_block
+                .chunks()
+                .get(last_chunk_shard_id as usize)
+                .unwrap()
+                .clone(),
             transactions: state_witness.transactions.clone(),
             receipts: receipts_to_apply,
             resharding_state_roots: None,
@@ -250,9 +1017,10 @@ fn pre_validate_chunk_state_witness(
             )?,
             is_first_block_with_chunk_of_version: false,
             storage_context: StorageContext {
-                storage_data_source: StorageDataSource::Recorded(PartialStorage {
-                    nodes: state_witness.main_state_transition.base_state.clone(),
-                }),
+                storage_data_source: witness_storage_data_source(
+                    state_witness.main_state_transition.base_state.clone(),
+                    db_fallback,
+                ),
                 state_patch: Default::default(),
                 record_storage: false,
             },
@@ -269,6 +1037,8 @@ fn pre_validate_chunk_state_witness(
                 )?)
             })
             .collect::<Result<_, _>>()?,
+        db_fallback,
+        determinism_check,
     })
 }
 
@@ -276,24 +1046,84 @@ fn pre_validate_chunk_state_witness(
 struct PreValidationOutput {
     main_transition_params: NewChunkData,
     implicit_transition_params: Vec<ApplyChunkBlockContext>,
+    /// Whether trie nodes missing from the witness are read from storage.
+    db_fallback: bool,
+    /// Whether to also execute the main transition from local state.
+    determinism_check: bool,
 }
 
-#[allow(unused)]
-fn validate_chunk_state_witness(
-    state_witness: ChunkStateWitness,
-    pre_validation_output: PreValidationOutput,
+/// Number of validated witness transitions kept for incremental witnesses to
+/// build upon.
+const VALIDATED_TRANSITIONS_CACHE_SIZE: usize = 100;
+
+/// The results of applying the transitions of a validated witness.
+#[derive(Clone)]
+struct ValidatedTransitions {
+    /// The block of the main transition.
+    main_block_hash: CryptoHash,
+    /// The chunk extra after applying all transitions of the witness.
+    chunk_extra: ChunkExtra,
+    /// The outgoing receipts of the main transition.
+    outgoing_receipts: Vec<Receipt>,
+}
+
+/// Transitions of validated witnesses, keyed by the shard and the state they
+/// ended at, so that incremental witnesses only need to carry the implicit
+/// transitions of the blocks after that.
+type ValidatedTransitionsCache =
+    Mutex<LruCache<(ShardUId, WitnessTransitionsBase), ValidatedTransitions>>;
+
+/// Looks up the validated transitions an incremental witness builds upon, and
+/// removes the implicit transitions they already cover from
+/// `implicit_transition_params`.
+fn get_validated_transitions(
+    validated_transitions: &ValidatedTransitionsCache,
+    shard_uid: ShardUId,
+    base: &WitnessTransitionsBase,
+    main_block_hash: CryptoHash,
+    implicit_transition_params: &mut Vec<ApplyChunkBlockContext>,
+) -> Result<ValidatedTransitions, Error> {
+    let validated =
+        validated_transitions.lock().unwrap().get(&(shard_uid, base.clone())).cloned().ok_or(
+            ChunkWitnessValidationError::MissingTransitionsBase {
+                last_block_hash: base.last_block_hash,
+            },
+        )?;
+    let num_validated = if base.last_block_hash == main_block_hash {
+        0
+    } else {
+        implicit_transition_params
+            .iter()
+            .position(|block| block.block_hash == base.last_block_hash)
+            .map(|i| i + 1)
+            .unwrap_or(usize::MAX)
+    };
+    if validated.main_block_hash != main_block_hash || num_validated == usize::MAX {
+        return Err(ChunkWitnessValidationError::InvalidTransitionsBase {
+            last_block_hash: base.last_block_hash,
+        }
+        .into());
+    }
+    implicit_transition_params.drain(..num_validated);
+    Ok(validated)
+}
+
+/// Applies the main transition of the witness, returning its outgoing
+/// receipts, the resulting chunk extra and the trie nodes read from storage.
+fn apply_main_transition(
+    span: &tracing::Span,
+    state_witness: &ChunkStateWitness,
+    main_transition: NewChunkData,
+    shard_uid: ShardUId,
+    determinism_check: bool,
     epoch_manager: &dyn EpochManagerAdapter,
     runtime_adapter: &dyn RuntimeAdapter,
-) -> Result<(), Error> {
-    let span = tracing::debug_span!(target: "chain", "validate_chunk_state_witness").entered();
-    let main_transition = pre_validation_output.main_transition_params;
+) -> Result<(Vec<Receipt>, ChunkExtra, Vec<CryptoHash>), Error> {
+    let stateful_transition = determinism_check.then(|| stateful_transition(&main_transition));
     let chunk_header = main_transition.chunk_header.clone();
-    let epoch_id = epoch_manager.get_epoch_id(&main_transition.block.block_hash)?;
-    let shard_uid =
-        epoch_manager.shard_id_to_uid(main_transition.chunk_header.shard_id(), &epoch_id)?;
     // Should we validate other fields?
     let NewChunkResult { apply_result: mut main_apply_result, .. } = apply_new_chunk(
-        &span,
+        span,
         main_transition,
         ShardContext {
             shard_uid,
@@ -305,38 +1135,100 @@ fn validate_chunk_state_witness(
         runtime_adapter,
         epoch_manager,
     )?;
+    if let Some(stateful_transition) = stateful_transition {
+        check_execution_determinism(
+            span,
+            stateful_transition,
+            shard_uid,
+            &main_apply_result,
+            epoch_manager,
+            runtime_adapter,
+        );
+    }
     let outgoing_receipts = std::mem::take(&mut main_apply_result.outgoing_receipts);
-    let mut chunk_extra = apply_result_to_chunk_extra(main_apply_result, &chunk_header);
+    let fallback_trie_nodes = std::mem::take(&mut main_apply_result.fallback_trie_nodes);
+    let chunk_extra = apply_result_to_chunk_extra(main_apply_result, &chunk_header);
     if chunk_extra.state_root() != &state_witness.main_state_transition.post_state_root {
         // This is an early check, it's not for correctness, only for better
         // error reporting in case of an invalid state witness due to a bug.
         // Only the final state root check against the chunk header is required.
-        return Err(Error::InvalidChunkStateWitness(format!(
-            "Post state root {:?} for main transition does not match expected post state root {:?}",
-            chunk_extra.state_root(),
-            state_witness.main_state_transition.post_state_root,
-        )));
+        return Err(ChunkWitnessValidationError::MainTransitionPostStateRootMismatch {
+            expected: state_witness.main_state_transition.post_state_root,
+            actual: *chunk_extra.state_root(),
+        }
+        .into());
     }
+    Ok((outgoing_receipts, chunk_extra, fallback_trie_nodes))
+}
 
-    for (block, transition) in pre_validation_output
-        .implicit_transition_params
-        .into_iter()
-        .zip(state_witness.implicit_transitions.into_iter())
+#[allow(unused)]
+fn validate_chunk_state_witness(
+    state_witness: ChunkStateWitness,
+    pre_validation_output: PreValidationOutput,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime_adapter: &dyn RuntimeAdapter,
+    validated_transitions: &ValidatedTransitionsCache,
+) -> Result<(), Error> {
+    let span = tracing::debug_span!(target: "chain", "validate_chunk_state_witness").entered();
+    let main_transition = pre_validation_output.main_transition_params;
+    let main_block_hash = main_transition.block.block_hash;
+    let epoch_id = epoch_manager.get_epoch_id(&main_block_hash)?;
+    let shard_uid =
+        epoch_manager.shard_id_to_uid(main_transition.chunk_header.shard_id(), &epoch_id)?;
+    let mut implicit_transition_params = pre_validation_output.implicit_transition_params;
+    let (outgoing_receipts, mut chunk_extra, mut fallback_trie_nodes) =
+        match &state_witness.transitions_base {
+            Some(base) => {
+                let validated = get_validated_transitions(
+                    validated_transitions,
+                    shard_uid,
+                    base,
+                    main_block_hash,
+                    &mut implicit_transition_params,
+                )?;
+                (validated.outgoing_receipts, validated.chunk_extra, vec![])
+            }
+            None => apply_main_transition(
+                &span,
+                &state_witness,
+                main_transition,
+                shard_uid,
+                pre_validation_output.determinism_check,
+                epoch_manager,
+                runtime_adapter,
+            )?,
+        };
+
+    if implicit_transition_params.len() != state_witness.implicit_transitions.len() {
+        return Err(ChunkWitnessValidationError::ImplicitTransitionsCountMismatch {
+            expected: implicit_transition_params.len(),
+            actual: state_witness.implicit_transitions.len(),
+        }
+        .into());
+    }
+    let mut last_block_hash = state_witness
+        .transitions_base
+        .as_ref()
+        .map_or(main_block_hash, |base| base.last_block_hash);
+    for (block, transition) in
+        implicit_transition_params.into_iter().zip(state_witness.implicit_transitions.into_iter())
     {
         let block_hash = block.block_hash;
+        last_block_hash = block_hash;
         let old_chunk_data = OldChunkData {
             prev_chunk_extra: chunk_extra.clone(),
             resharding_state_roots: None,
             block,
             storage_context: StorageContext {
-                storage_data_source: StorageDataSource::Recorded(PartialStorage {
-                    nodes: transition.base_state,
-                }),
+                storage_data_source: witness_storage_data_source(
+                    transition.base_state,
+                    pre_validation_output.db_fallback,
+                ),
                 state_patch: Default::default(),
                 record_storage: false,
             },
         };
-        let OldChunkResult { apply_result, .. } = apply_old_chunk(
+        let OldChunkResult { mut apply_result, .. } = apply_old_chunk(
             &span,
             old_chunk_data,
             ShardContext {
@@ -350,17 +1242,33 @@ fn validate_chunk_state_witness(
             runtime_adapter,
             epoch_manager,
         )?;
+        fallback_trie_nodes.append(&mut apply_result.fallback_trie_nodes);
         *chunk_extra.state_root_mut() = apply_result.new_root;
         if chunk_extra.state_root() != &transition.post_state_root {
             // This is an early check, it's not for correctness, only for better
             // error reporting in case of an invalid state witness due to a bug.
             // Only the final state root check against the chunk header is required.
-            return Err(Error::InvalidChunkStateWitness(format!(
-                "Post state root {:?} for implicit transition at block {:?}, does not match expected state root {:?}",
-                chunk_extra.state_root(), block_hash, transition.post_state_root
-            )));
+            return Err(ChunkWitnessValidationError::ImplicitTransitionPostStateRootMismatch {
+                block_hash,
+                expected: transition.post_state_root,
+                actual: *chunk_extra.state_root(),
+            }
+            .into());
         }
     }
+    // The transitions are correct regardless of whether the chunk itself
+    // turns out to be, so later witnesses can build upon them.
+    validated_transitions.lock().unwrap().put(
+        (
+            shard_uid,
+            WitnessTransitionsBase { last_block_hash, post_state_root: *chunk_extra.state_root() },
+        ),
+        ValidatedTransitions {
+            main_block_hash,
+            chunk_extra: chunk_extra.clone(),
+            outgoing_receipts: outgoing_receipts.clone(),
+        },
+    );
 
     // Finally, verify that the newly proposed chunk matches everything we have computed.
     let outgoing_receipts_hashes = {
@@ -379,9 +1287,193 @@ fn validate_chunk_state_witness(
     // are valid.
     // TODO(#9292): Not sure how to do this.
 
+    // The witness is valid, but it was not self-contained, so we must not
+    // endorse it: other chunk validators can't validate it.
+    if !fallback_trie_nodes.is_empty() {
+        fallback_trie_nodes.sort();
+        fallback_trie_nodes.dedup();
+        return Err(ChunkWitnessValidationError::IncompleteWitness {
+            missing_nodes: fallback_trie_nodes,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Validates the chunk like `validate_chunk_state_witness`, but applying
+/// all transitions from local state instead of from the state recorded in
+/// the witness. Only possible for shards tracked by the node.
+fn validate_chunk_statefully(
+    chunk_header: &ShardChunkHeader,
+    main_transition: NewChunkData,
+    implicit_transition_params: Vec<ApplyChunkBlockContext>,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime_adapter: &dyn RuntimeAdapter,
+) -> Result<(), Error> {
+    let span = tracing::debug_span!(target: "chain", "validate_chunk_statefully").entered();
+    let epoch_id = epoch_manager.get_epoch_id(&main_transition.block.block_hash)?;
+    let shard_uid = epoch_manager.shard_id_to_uid(chunk_header.shard_id(), &epoch_id)?;
+    let shard_context = |should_apply_chunk| ShardContext {
+        shard_uid,
+        cares_about_shard_this_epoch: true,
+        will_shard_layout_change: false,
+        should_apply_chunk,
+        need_to_reshard: false,
+    };
+    let main_chunk_header = main_transition.chunk_header.clone();
+    let NewChunkResult { apply_result: mut main_apply_result, .. } = apply_new_chunk(
+        &span,
+        main_transition,
+        shard_context(true),
+        runtime_adapter,
+        epoch_manager,
+    )?;
+    let outgoing_receipts = std::mem::take(&mut main_apply_result.outgoing_receipts);
+    let mut chunk_extra = apply_result_to_chunk_extra(main_apply_result, &main_chunk_header);
+    for block in implicit_transition_params {
+        let old_chunk_data = OldChunkData {
+            prev_chunk_extra: chunk_extra.clone(),
+            resharding_state_roots: None,
+            block,
+            storage_context: StorageContext {
+                storage_data_source: StorageDataSource::DbTrieOnly,
+                state_patch: Default::default(),
+                record_storage: false,
+            },
+        };
+        let OldChunkResult { apply_result, .. } = apply_old_chunk(
+            &span,
+            old_chunk_data,
+            shard_context(false),
+            runtime_adapter,
+            epoch_manager,
+        )?;
+        *chunk_extra.state_root_mut() = apply_result.new_root;
+    }
+    let outgoing_receipts_hashes = {
+        let shard_layout =
+            epoch_manager.get_shard_layout_from_prev_block(chunk_header.prev_block_hash())?;
+        Chain::build_receipts_hashes(&outgoing_receipts, &shard_layout)
+    };
+    let (outgoing_receipts_root, _) = merklize(&outgoing_receipts_hashes);
+    validate_chunk_with_chunk_extra_and_receipts_root(
+        &chunk_extra,
+        chunk_header,
+        &outgoing_receipts_root,
+    )?;
     Ok(())
 }
 
+/// Returns the parameters of the given state transition, but reading the
+/// state from the local trie instead of from the witness.
+fn stateful_transition(transition: &NewChunkData) -> NewChunkData {
+    NewChunkData {
+        chunk_header: transition.chunk_header.clone(),
+        transactions: transition.transactions.clone(),
+        receipts: transition.receipts.clone(),
+        resharding_state_roots: None,
+        block: transition.block.clone(),
+        is_first_block_with_chunk_of_version: transition.is_first_block_with_chunk_of_version,
+        storage_context: StorageContext {
+            // Flat storage may have moved past the block already, so read
+            // from the trie directly.
+            storage_data_source: StorageDataSource::DbTrieOnly,
+            state_patch: Default::default(),
+            record_storage: false,
+        },
+    }
+}
+
+/// Executes the main state transition of a witness once more, from local
+/// state rather than from the state recorded in the witness, and compares the
+/// results. Any difference means that the runtime isn't deterministic, which
+/// is reported in the metrics so that it can be alerted on.
+fn check_execution_determinism(
+    parent_span: &tracing::Span,
+    stateful_transition: NewChunkData,
+    shard_uid: ShardUId,
+    stateless_result: &ApplyChunkResult,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime_adapter: &dyn RuntimeAdapter,
+) {
+    let chunk_hash = stateful_transition.chunk_header.chunk_hash();
+    let shard_id = stateful_transition.chunk_header.shard_id();
+    let result = match apply_new_chunk(
+        parent_span,
+        stateful_transition,
+        ShardContext {
+            shard_uid,
+            cares_about_shard_this_epoch: true,
+            will_shard_layout_change: false,
+            should_apply_chunk: true,
+            need_to_reshard: false,
+        },
+        runtime_adapter,
+        epoch_manager,
+    ) {
+        Ok(NewChunkResult { apply_result, .. }) => {
+            let divergences = execution_divergences(&apply_result, stateless_result);
+            if divergences.is_empty() {
+                "match"
+            } else {
+                tracing::error!(
+                    target: "chunk_validation",
+                    ?chunk_hash,
+                    shard_id,
+                    ?divergences,
+                    "Stateful and stateless execution of the chunk diverged",
+                );
+                "divergence"
+            }
+        }
+        Err(err) => {
+            // E.g. the state has already been garbage collected.
+            tracing::warn!(
+                target: "chunk_validation",
+                ?chunk_hash,
+                shard_id,
+                ?err,
+                "Failed to execute the chunk from local state",
+            );
+            "error"
+        }
+    };
+    metrics::CHUNK_EXECUTION_DETERMINISM_CHECKS_TOTAL
+        .with_label_values(&[&shard_id.to_string(), result])
+        .inc();
+}
+
+/// Returns the names of the parts of the results of applying the same chunk
+/// which differ.
+fn execution_divergences(
+    stateful: &ApplyChunkResult,
+    stateless: &ApplyChunkResult,
+) -> Vec<&'static str> {
+    let mut divergences = vec![];
+    if stateful.new_root != stateless.new_root {
+        divergences.push("state_root");
+    }
+    if ApplyChunkResult::compute_outcomes_proof(&stateful.outcomes).0
+        != ApplyChunkResult::compute_outcomes_proof(&stateless.outcomes).0
+    {
+        divergences.push("outcome_root");
+    }
+    if stateful.outgoing_receipts != stateless.outgoing_receipts {
+        divergences.push("outgoing_receipts");
+    }
+    if stateful.validator_proposals != stateless.validator_proposals {
+        divergences.push("validator_proposals");
+    }
+    if stateful.total_gas_burnt != stateless.total_gas_burnt {
+        divergences.push("total_gas_burnt");
+    }
+    if stateful.total_balance_burnt != stateless.total_balance_burnt {
+        divergences.push("total_balance_burnt");
+    }
+    divergences
+}
+
 #[allow(unused)]
 fn apply_result_to_chunk_extra(
     apply_result: ApplyChunkResult,
@@ -402,77 +1494,190 @@ impl Client {
     /// Responds to a network request to verify a `ChunkStateWitness`, which is
     /// sent by chunk producers after they produce a chunk.
     pub fn process_chunk_state_witness(&mut self, witness: ChunkStateWitness) -> Result<(), Error> {
+        let chunk_hash = witness.chunk_header.chunk_hash();
         // TODO(#10265): If the previous block does not exist, we should
         // queue this (similar to orphans) to retry later.
-        self.chunk_validator.start_validating_chunk(witness, self.chain.chain_store())
+        self.check_chunk_state_witness_height(&witness)?;
+        let tracks_shard = self.shard_tracker.care_about_shard(
+            self.validator_signer.as_ref().map(|signer| signer.validator_id()),
+            witness.chunk_header.prev_block_hash(),
+            witness.chunk_header.shard_id(),
+            true,
+        );
+        let db_fallback = self.config.chunk_validation_db_fallback && tracks_shard;
+        let determinism_check = self.config.chunk_validation_determinism_check && tracks_shard;
+        let stateful_fallback = self.config.chunk_validation_stateful_fallback && tracks_shard;
+        let chunk_header = witness.chunk_header.clone();
+        match self.chunk_validator.start_validating_chunk(
+            witness,
+            self.chain.chain_store(),
+            db_fallback,
+            determinism_check,
+            stateful_fallback,
+            self.config.save_state_witnesses,
+        ) {
+            Ok(()) => {}
+            Err(Error::InvalidChunkStateWitness(
+                ChunkWitnessValidationError::MissingTransitionsBase { last_block_hash },
+            )) => {
+                return self.request_full_chunk_state_witness(&chunk_header, last_block_hash);
+            }
+            Err(err) => return Err(err),
+        }
+        // The witness stops being awaited only once it passed pre-validation,
+        // so that an invalid witness sent by anyone doesn't prevent the valid
+        // one from being requested from the chunk producer.
+        self.chunk_state_witness_requester.witness_received(&chunk_hash);
+        Ok(())
     }
 
-    /// Collect state transition data necessary to produce state witness for
-    /// `chunk_header`.
-    fn collect_state_transition_data(
+    /// Requests the full state witness of a chunk from its producer, when an
+    /// incremental witness arrived but the transitions it builds upon were
+    /// not validated by this node. The witness keeps being awaited, so the
+    /// request is retried until the full witness arrives.
+    fn request_full_chunk_state_witness(
         &mut self,
         chunk_header: &ShardChunkHeader,
-        prev_chunk_header: ShardChunkHeader,
-    ) -> Result<(ChunkStateTransition, Vec<ChunkStateTransition>, CryptoHash), Error> {
-        let shard_id = chunk_header.shard_id();
+        last_block_hash: CryptoHash,
+    ) -> Result<(), Error> {
         let epoch_id =
             self.epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
-        let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
-        let prev_chunk_height_included = prev_chunk_header.height_included();
-
-        // TODO(#9292): previous chunk is genesis chunk - consider proper
-        // result for this corner case.
-        // let prev_chunk_prev_hash = *prev_chunk_header.prev_block_hash();
-        // if prev_chunk_prev_hash == CryptoHash::default() {
-        //     return Ok(vec![]);
-        // }
-
-        let mut prev_blocks = self.chain.get_blocks_until_height(
-            *chunk_header.prev_block_hash(),
-            prev_chunk_height_included,
-            true,
+        let chunk_producer = self.epoch_manager.get_chunk_producer(
+            &epoch_id,
+            chunk_header.height_created(),
+            chunk_header.shard_id(),
         )?;
-        prev_blocks.reverse();
-        let (main_block, implicit_blocks) = prev_blocks.split_first().unwrap();
-        let store = self.chain.chain_store().store();
-        let StoredChunkStateTransitionData { base_state, receipts_hash } = store
-            .get_ser(
-                near_store::DBCol::StateTransitionData,
-                &near_primitives::utils::get_block_shard_id(main_block, shard_id),
-            )?
-            .ok_or(Error::Other(format!(
-                "Missing state proof for block {main_block} and shard {shard_id}"
-            )))?;
-        let main_transition = ChunkStateTransition {
-            block_hash: *main_block,
-            base_state,
-            post_state_root: *self.chain.get_chunk_extra(main_block, &shard_uid)?.state_root(),
+        tracing::debug!(
+            target: "chunk_validation",
+            chunk_hash=?chunk_header.chunk_hash(),
+            ?last_block_hash,
+            %chunk_producer,
+            "Missing the base of an incremental chunk state witness, requesting the full witness",
+        );
+        self.chunk_state_witness_requester.await_witness(
+            chunk_header,
+            chunk_producer.clone(),
+            StaticClock::instant(),
+        );
+        metrics::CHUNK_STATE_WITNESS_REQUESTS_TOTAL
+            .with_label_values(&[&chunk_header.shard_id().to_string()])
+            .inc();
+        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::ChunkStateWitnessRequest(chunk_producer, chunk_header.chunk_hash()),
+        ));
+        Ok(())
+    }
+
+    /// Starts waiting for the state witness of a chunk whose header became
+    /// known, if this node is one of its chunk validators, so that the
+    /// witness is requested from the chunk producer if it doesn't arrive.
+    pub(crate) fn await_chunk_state_witness(
+        &mut self,
+        chunk_header: &ShardChunkHeader,
+        chunk_producer: AccountId,
+    ) -> Result<(), Error> {
+        let Some(my_signer) = self.validator_signer.as_ref() else {
+            return Ok(());
         };
-        let mut implicit_transitions = vec![];
-        for block_hash in implicit_blocks {
-            let StoredChunkStateTransitionData { base_state, .. } = store
-                .get_ser(
-                    near_store::DBCol::StateTransitionData,
-                    &near_primitives::utils::get_block_shard_id(block_hash, shard_id),
-                )?
-                .ok_or(Error::Other(format!(
-                    "Missing state proof for block {block_hash} and shard {shard_id}"
-                )))?;
-            implicit_transitions.push(ChunkStateTransition {
-                block_hash: *block_hash,
-                base_state,
-                post_state_root: *self.chain.get_chunk_extra(block_hash, &shard_uid)?.state_root(),
-            });
+        // Our own chunks are endorsed without a witness.
+        if &chunk_producer == my_signer.validator_id() {
+            return Ok(());
+        }
+        let epoch_id =
+            self.epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
+        let chunk_validators = self.epoch_manager.get_chunk_validators(
+            &epoch_id,
+            chunk_header.shard_id(),
+            chunk_header.height_created(),
+        )?;
+        if chunk_validators.contains_key(my_signer.validator_id()) {
+            self.chunk_state_witness_requester.await_witness(
+                chunk_header,
+                chunk_producer,
+                StaticClock::instant(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Requests the state witnesses which are awaited for too long from the
+    /// chunk producers. Returns how long until the next request is due, if
+    /// any.
+    pub fn request_missing_chunk_state_witnesses(&mut self) -> Option<std::time::Duration> {
+        let head_height = self.chain.head().ok()?.height;
+        let (requests, next_request) =
+            self.chunk_state_witness_requester.poll(head_height, StaticClock::instant());
+        for request in requests {
+            tracing::debug!(
+                target: "chunk_validation",
+                chunk_hash=?request.chunk_hash,
+                chunk_producer=%request.chunk_producer,
+                "Requesting missing chunk state witness",
+            );
+            metrics::CHUNK_STATE_WITNESS_REQUESTS_TOTAL
+                .with_label_values(&[&request.shard_id.to_string()])
+                .inc();
+            self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                NetworkRequests::ChunkStateWitnessRequest(
+                    request.chunk_producer,
+                    request.chunk_hash,
+                ),
+            ));
         }
+        next_request
+    }
 
-        // TODO(#10265): If the previous block does not exist, we should
-        // queue this (similar to orphans) to retry later.
+    /// Rejects witnesses of chunks too far below or above the head, before
+    /// spending any work on them. Only reads the cached head.
+    fn check_chunk_state_witness_height(&self, witness: &ChunkStateWitness) -> Result<(), Error> {
+        let height = witness.chunk_header.height_created();
+        let head_height = self.chain.head()?.height;
+        let horizon = self.config.chunk_witness_height_horizon;
+        let reason = if height.saturating_add(horizon) < head_height {
+            "too_old"
+        } else if height > head_height.saturating_add(horizon) {
+            "too_new"
+        } else {
+            return Ok(());
+        };
+        metrics::CHUNK_STATE_WITNESS_HEIGHT_REJECTIONS_TOTAL
+            .with_label_values(&[&witness.chunk_header.shard_id().to_string(), reason])
+            .inc();
+        Err(ChunkWitnessValidationError::HeightOutsideHorizon { height, head_height, horizon }
+            .into())
+    }
 
-        Ok((main_transition, implicit_transitions, receipts_hash))
+    /// Runs both pre-validation and validation of the state witness on the
+    /// current thread, without endorsing the chunk. Unlike
+    /// `process_chunk_state_witness`, this does not require the node to be
+    /// a chunk validator of the chunk. Used to fuzz witness validation.
+    pub fn run_chunk_state_witness_validation(
+        &self,
+        witness: ChunkStateWitness,
+    ) -> Result<(), Error> {
+        let pre_validation_output = pre_validate_chunk_state_witness(
+            &witness,
+            self.chain.chain_store(),
+            self.epoch_manager.as_ref(),
+            None,
+            false,
+            false,
+        )?;
+        validate_chunk_state_witness(
+            witness,
+            pre_validation_output,
+            self.epoch_manager.as_ref(),
+            self.runtime_adapter.as_ref(),
+            &self.chunk_validator.validated_transitions,
+        )
     }
 
-    /// Distributes the chunk state witness to chunk validators that are
-    /// selected to validate this chunk.
+    /// Queues the chunk state witness to be distributed to chunk validators
+    /// that are selected to validate this chunk. The witness is handed over
+    /// to the `ChunkWitnessActor` by `send_pending_chunk_state_witnesses`,
+    /// unless it gets superseded by a newer chunk of the same shard in the
+    /// meantime. If this node is one of the chunk validators, it endorses the
+    /// chunk itself, and no witness is produced if it is the only one.
     pub fn send_chunk_state_witness_to_chunk_validators(
         &mut self,
         epoch_id: &EpochId,
@@ -488,50 +1693,205 @@ impl Client {
             return Ok(());
         }
         let chunk_header = chunk.cloned_header();
-        let chunk_validators = self.epoch_manager.get_chunk_validators(
-            epoch_id,
-            chunk_header.shard_id(),
-            chunk_header.height_created(),
-        )?;
-        let prev_chunk = self.chain.get_chunk(&prev_chunk_header.chunk_hash())?;
-        let (main_state_transition, implicit_transitions, applied_receipts_hash) =
-            self.collect_state_transition_data(&chunk_header, prev_chunk_header)?;
-        let witness = ChunkStateWitness {
-            chunk_header: chunk_header.clone(),
-            main_state_transition,
-            // TODO(#9292): Iterate through the chain to derive this.
-            source_receipt_proofs: HashMap::new(),
-            transactions: prev_chunk.transactions().to_vec(),
-            // (Could also be derived from iterating through the receipts, but
-            // that defeats the purpose of this check being a debugging
-            // mechanism.)
-            applied_receipts_hash,
-            implicit_transitions,
-            new_transactions: chunk.transactions().to_vec(),
-            // TODO(#9292): Derive this during chunk production, during
-            // prepare_transactions or the like.
-            new_transactions_validation_state: PartialState::default(),
-        };
-        tracing::debug!(
-            target: "chunk_validation",
-            "Sending chunk state witness for chunk {:?} to chunk validators {:?}",
-            chunk_header.chunk_hash(),
-            chunk_validators.keys(),
-        );
-        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-            NetworkRequests::ChunkStateWitness(chunk_validators.into_keys().collect(), witness),
-        ));
+        let mut chunk_validators: Vec<AccountId> = self
+            .epoch_manager
+            .get_chunk_validators(epoch_id, chunk_header.shard_id(), chunk_header.height_created())?
+            .into_keys()
+            .collect();
+        // If we validate our own chunk, endorse it right away instead of
+        // sending the witness to ourselves over the network. In small
+        // networks we may be the only chunk validator.
+        let me = self.validator_signer.as_ref().map(|signer| signer.validator_id().clone());
+        if let Some(me) = me.filter(|me| chunk_validators.contains(me)) {
+            chunk_validators.retain(|account_id| account_id != &me);
+            if let Some(endorsement) =
+                self.chunk_validator.endorse_own_chunk(epoch_id, &chunk_header)?
+            {
+                self.chunk_endorsement_tracker
+                    .process_chunk_endorsement(&chunk_header, endorsement)?;
+            }
+        }
+        if chunk_validators.is_empty() {
+            return Ok(());
+        }
+        self.chunk_state_witness_queue.push(PendingChunkStateWitness {
+            chunk_validators,
+            prev_chunk_header,
+            chunk: chunk.clone(),
+        });
         Ok(())
     }
 
+    /// Hands the queued chunks over to the `ChunkWitnessActor`, which
+    /// produces their state witnesses and sends them to chunk validators.
+    /// The queue holds at most one chunk per shard, so the witnesses of the
+    /// whole batch can be produced in parallel.
+    pub fn send_pending_chunk_state_witnesses(&mut self) {
+        let pending = self.chunk_state_witness_queue.drain();
+        if pending.is_empty() {
+            return;
+        }
+        for pending in &pending {
+            self.chunk_witness_production_tracker.record_requested(pending.chunk.shard_id());
+        }
+        self.chunk_witness_sender.send(ChunkWitnessRequest::DistributeChunkStateWitnesses(pending));
+    }
+
     /// Function to process an incoming chunk endorsement from chunk validators.
+    /// The endorsement is stored in the chunk endorsement tracker, to be used
+    /// later during block production and for building chunk execution proofs.
     pub fn process_chunk_endorsement(
         &mut self,
-        _endorsement: ChunkEndorsement,
+        endorsement: ChunkEndorsement,
     ) -> Result<(), Error> {
-        // TODO(10265): Here if we are the current block producer, we would store the chunk endorsement
-        // for each chunk which would later be used during block production to check whether to include the
-        // chunk or not.
+        // TODO(10265): If we haven't seen the chunk yet, we should keep the
+        // endorsement around and process it once the chunk header arrives.
+        let chunk_header =
+            match self.chain.chain_store().get_partial_chunk(&endorsement.inner.chunk_hash) {
+                Ok(partial_chunk) => partial_chunk.cloned_header(),
+                Err(Error::ChunkMissing(chunk_hash)) => {
+                    tracing::debug!(
+                        target: "chunk_validation",
+                        ?chunk_hash,
+                        "Received chunk endorsement for unknown chunk",
+                    );
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            };
+        self.chunk_endorsement_tracker.process_chunk_endorsement(&chunk_header, endorsement)?;
+        // Endorsements of different chunks of the same shard and height are
+        // sent out as challenges, to be included in blocks and slash the
+        // validator.
+        let double_signs = self.chunk_endorsement_tracker.take_double_signs();
+        self.send_challenges(
+            double_signs.into_iter().map(ChallengeBody::ChunkEndorsementDoubleSign).collect(),
+        );
         Ok(())
     }
+
+    /// Returns the endorsements collected so far for the chunk with the given
+    /// hash, along with the chunk validators which haven't endorsed it yet.
+    pub fn chunk_endorsement_status(
+        &mut self,
+        chunk_hash: &ChunkHash,
+    ) -> Result<EndorsementStatus, Error> {
+        let chunk_header = self.chain.chain_store().get_partial_chunk(chunk_hash)?.cloned_header();
+        let prev_block_hash = chunk_header.prev_block_hash();
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(prev_block_hash)?;
+        let chunk_validators = self.epoch_manager.get_chunk_validators(
+            &epoch_id,
+            chunk_header.shard_id(),
+            chunk_header.height_created(),
+        )?;
+        let quorum = self
+            .epoch_manager
+            .get_epoch_config(&epoch_id)?
+            .chunk_validation_config
+            .chunk_endorsement_quorum;
+        let endorsements = self.chunk_endorsement_tracker.get_chunk_endorsements(chunk_hash);
+
+        let mut endorsed_by = vec![];
+        let mut missing = vec![];
+        let mut endorsed_stake: Balance = 0;
+        let mut total_stake: Balance = 0;
+        for account_id in chunk_validators.into_keys() {
+            let (validator, _) = self.epoch_manager.get_validator_by_account_id(
+                &epoch_id,
+                prev_block_hash,
+                &account_id,
+            )?;
+            total_stake += validator.stake();
+            if endorsements.iter().any(|endorsement| endorsement.account_id == account_id) {
+                endorsed_stake += validator.stake();
+                endorsed_by.push(account_id);
+            } else {
+                missing.push(account_id);
+            }
+        }
+        endorsed_by.sort();
+        missing.sort();
+        let endorsed_stake_fraction =
+            if total_stake == 0 { 0.0 } else { endorsed_stake as f64 / total_stake as f64 };
+        let has_quorum = endorsed_stake * (*quorum.denom() as Balance)
+            > total_stake * (*quorum.numer() as Balance);
+        Ok(EndorsementStatus {
+            chunk_hash: chunk_hash.clone(),
+            shard_id: chunk_header.shard_id(),
+            endorsed_by,
+            missing,
+            endorsed_stake,
+            total_stake,
+            endorsed_stake_fraction,
+            has_quorum,
+            inclusion_height: chunk_header.height_created(),
+            head_height: self.chain.head()?.height,
+        })
+    }
+
+    /// Returns the chunk validation state reported in the detailed status:
+    /// witness production and in-memory tries of each shard, and the
+    /// endorsements collected for the new chunks of the last blocks.
+    pub fn chunk_validation_status(&self) -> Result<ChunkValidationStatusView, Error> {
+        let head = self.chain.head()?;
+        let shard_layout = self.epoch_manager.get_shard_layout(&head.epoch_id)?;
+        let tries = self.runtime_adapter.get_tries();
+        let mut shards = vec![];
+        for shard_uid in shard_layout.shard_uids() {
+            let shard_id = shard_uid.shard_id();
+            let (in_progress, recent_witnesses) =
+                self.chunk_witness_production_tracker.shard_status(shard_id);
+            let memtrie_stats = tries.get_mem_trie_stats(shard_uid);
+            shards.push(ShardChunkValidationStatusView {
+                shard_id,
+                witness_production_backlog: self.chunk_state_witness_queue.num_pending(shard_id)
+                    + in_progress,
+                recent_witnesses,
+                memtrie_loaded: memtrie_stats.is_some(),
+                memtrie_memory_usage_bytes: memtrie_stats
+                    .map(|stats| stats.arena_memory_usage_bytes as u64),
+            });
+        }
+
+        let mut recent_chunk_endorsements = vec![];
+        let mut block_hash = head.last_block_hash;
+        for _ in 0..DEBUG_CHUNK_ENDORSEMENT_BLOCKS {
+            let header = self.chain.get_block_header(&block_hash)?;
+            // Genesis chunks are never endorsed.
+            if header.prev_hash() == &CryptoHash::default() {
+                break;
+            }
+            block_hash = *header.prev_hash();
+            // Blocks before the sync hash are missing after state sync.
+            let block = match self.chain.get_block(header.hash()) {
+                Ok(block) => block,
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let epoch_id = block.header().epoch_id();
+            for chunk_header in block.chunks().iter() {
+                if chunk_header.height_included() != block.header().height() {
+                    continue;
+                }
+                let num_chunk_validators = self
+                    .epoch_manager
+                    .get_chunk_validators(
+                        epoch_id,
+                        chunk_header.shard_id(),
+                        chunk_header.height_created(),
+                    )?
+                    .len();
+                recent_chunk_endorsements.push(ChunkEndorsementCountView {
+                    chunk_hash: chunk_header.chunk_hash(),
+                    shard_id: chunk_header.shard_id(),
+                    height_created: chunk_header.height_created(),
+                    num_endorsements: self
+                        .chunk_endorsement_tracker
+                        .num_chunk_endorsements(&chunk_header.chunk_hash()),
+                    num_chunk_validators,
+                });
+            }
+        }
+        Ok(ChunkValidationStatusView { shards, recent_chunk_endorsements })
+    }
 }
@@ -0,0 +1,747 @@
+use crate::chunk_state_witness_queue::PendingChunkStateWitness;
+use crate::chunk_validation::get_receipt_source_blocks;
+use crate::metrics;
+use actix::{Actor, Addr, Arbiter, ArbiterHandle, AsyncContext, Context, Handler};
+use lru::LruCache;
+use near_async::messaging::Sender;
+use near_chain::chain::RecordedStateTransition;
+use near_chain::{Chain, ChainGenesis, ChainStore, ChainStoreAccess};
+use near_chain_configs::ClientConfig;
+use near_chain_primitives::Error;
+use near_epoch_manager::EpochManagerAdapter;
+use near_network::state_witness::DistributeStateWitnessRequest;
+use near_o11y::{handler_debug_span, OpenTelemetrySpanExt, WithSpanContext};
+use near_performance_metrics_macros::perf;
+use near_primitives::challenge::PartialState;
+use near_primitives::checked_feature;
+use near_primitives::chunk_validation::{
+    ChunkStateTransition, ChunkStateWitness, StoredChunkStateTransitionData, WitnessTransitionsBase,
+};
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::sharding::{ChunkHash, ReceiptProof, ShardChunk, ShardChunkHeader};
+use near_primitives::types::{AccountId, ShardId};
+use near_primitives::utils::{get_block_shard_id, index_to_bytes};
+use near_primitives::views::ProducedWitnessView;
+use near_store::{DBCol, Store};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Number of recorded state transitions kept until a state witness needs them.
+const RECORDED_STATE_TRANSITIONS_CACHE_SIZE: usize = 100;
+
+/// Number of produced state witnesses per shard reported in the debug status.
+const RECENT_WITNESSES_PER_SHARD: usize = 5;
+
+/// Requests sent by the client to the `ChunkWitnessActor`. They are handled
+/// in order, so transitions recorded while applying a block are available
+/// to the witnesses of chunks produced on top of it.
+#[derive(actix::Message, Debug)]
+#[rtype(result = "()")]
+pub enum ChunkWitnessRequest {
+    /// State transitions recorded while applying the chunks of new blocks.
+    StateTransitionsRecorded(Vec<RecordedStateTransition>),
+    /// Produces the state witnesses of newly produced chunks and sends them
+    /// to the chunk validators of the chunks. The chunks are of different
+    /// shards, so their witnesses are produced in parallel.
+    DistributeChunkStateWitnesses(Vec<PendingChunkStateWitness>),
+}
+
+/// Keeps track of state witness production for the client's debug status:
+/// how many witnesses were requested from the `ChunkWitnessProducer` but not
+/// produced yet, and the last witnesses produced for each shard. Clones share
+/// the same state, so the client and the producer can each hold one.
+#[derive(Clone, Default)]
+pub struct ChunkWitnessProductionTracker(Arc<Mutex<HashMap<ShardId, ShardWitnessProduction>>>);
+
+#[derive(Default)]
+struct ShardWitnessProduction {
+    in_progress: usize,
+    recent_witnesses: VecDeque<ProducedWitnessView>,
+}
+
+impl ChunkWitnessProductionTracker {
+    pub(crate) fn record_requested(&self, shard_id: ShardId) {
+        self.0.lock().unwrap().entry(shard_id).or_default().in_progress += 1;
+    }
+
+    /// Records that production of a requested witness finished, with the
+    /// produced witness unless it failed.
+    pub(crate) fn record_finished(&self, shard_id: ShardId, witness: Option<ProducedWitnessView>) {
+        let mut shards = self.0.lock().unwrap();
+        let shard = shards.entry(shard_id).or_default();
+        shard.in_progress = shard.in_progress.saturating_sub(1);
+        if let Some(witness) = witness {
+            shard.recent_witnesses.push_front(witness);
+            shard.recent_witnesses.truncate(RECENT_WITNESSES_PER_SHARD);
+        }
+    }
+
+    /// Returns the number of witnesses being produced for the shard, and the
+    /// last witnesses produced for it, latest first.
+    pub(crate) fn shard_status(&self, shard_id: ShardId) -> (usize, Vec<ProducedWitnessView>) {
+        match self.0.lock().unwrap().get(&shard_id) {
+            Some(shard) => (shard.in_progress, shard.recent_witnesses.iter().cloned().collect()),
+            None => (0, vec![]),
+        }
+    }
+}
+
+/// Produces state witnesses for the chunks produced by this node and
+/// distributes them to chunk validators. It only reads the chain from the
+/// store, so it can run off the client thread.
+pub struct ChunkWitnessProducer {
+    store: Store,
+    /// One chain store per thread of `thread_pool`, as the caches of a chain
+    /// store can't be shared between threads.
+    chain_stores: Vec<ChainStore>,
+    /// Runs store reads and assembly of the witnesses of different shards in
+    /// parallel.
+    thread_pool: rayon::ThreadPool,
+    epoch_manager: Arc<dyn EpochManagerAdapter>,
+    network_adapter: Sender<DistributeStateWitnessRequest>,
+    /// State transitions recorded while applying chunks, keyed by block hash
+    /// and shard id. Used to produce state witnesses without reading the
+    /// transitions back from the store.
+    recorded_state_transitions: Mutex<LruCache<(CryptoHash, ShardId), RecordedStateTransition>>,
+    save_state_witnesses: bool,
+    /// Whether to send incremental witnesses to the chunk validators which
+    /// were sent the previous witness of the shard.
+    incremental_witnesses: bool,
+    /// The last witness sent for each shard.
+    sent_witnesses: HashMap<ShardId, SentWitness>,
+    production_tracker: ChunkWitnessProductionTracker,
+    adv: crate::adversarial::Controls,
+}
+
+/// The transitions of a witness sent to chunk validators. If the chunk of the
+/// witness doesn't get included, the next witness of the shard has the same
+/// main transition and can build upon these transitions.
+struct SentWitness {
+    main_block_hash: CryptoHash,
+    /// The state after the last transition of the witness.
+    base: WitnessTransitionsBase,
+    /// The chunk validators the witness was sent to.
+    chunk_validators: HashSet<AccountId>,
+}
+
+impl SentWitness {
+    fn new(witness: &ChunkStateWitness, chunk_validators: HashSet<AccountId>) -> Self {
+        let last_transition =
+            witness.implicit_transitions.last().unwrap_or(&witness.main_state_transition);
+        Self {
+            main_block_hash: witness.main_state_transition.block_hash,
+            base: WitnessTransitionsBase {
+                last_block_hash: last_transition.block_hash,
+                post_state_root: last_transition.post_state_root,
+            },
+            chunk_validators,
+        }
+    }
+}
+
+impl ChunkWitnessProducer {
+    pub fn new(
+        config: &ClientConfig,
+        chain_genesis: &ChainGenesis,
+        epoch_manager: Arc<dyn EpochManagerAdapter>,
+        store: Store,
+        network_adapter: Sender<DistributeStateWitnessRequest>,
+        production_tracker: ChunkWitnessProductionTracker,
+        adv: crate::adversarial::Controls,
+    ) -> Self {
+        let num_threads = config.chunk_witness_production_threads.max(1);
+        let chain_stores = (0..num_threads)
+            .map(|_| ChainStore::new(store.clone(), chain_genesis.height, config.save_trie_changes))
+            .collect();
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("chunk_witness_{}", i))
+            .build()
+            .unwrap();
+        Self {
+            store,
+            chain_stores,
+            thread_pool,
+            epoch_manager,
+            network_adapter,
+            recorded_state_transitions: Mutex::new(LruCache::new(
+                RECORDED_STATE_TRANSITIONS_CACHE_SIZE,
+            )),
+            save_state_witnesses: config.save_state_witnesses,
+            incremental_witnesses: config.incremental_chunk_state_witnesses,
+            sent_witnesses: HashMap::new(),
+            production_tracker,
+            adv,
+        }
+    }
+
+    pub fn handle_request(&mut self, request: ChunkWitnessRequest) {
+        match request {
+            ChunkWitnessRequest::StateTransitionsRecorded(state_transitions) => {
+                let mut recorded = self.recorded_state_transitions.lock().unwrap();
+                for state_transition in state_transitions {
+                    recorded.put(
+                        (state_transition.transition.block_hash, state_transition.shard_id),
+                        state_transition,
+                    );
+                }
+            }
+            ChunkWitnessRequest::DistributeChunkStateWitnesses(pending) => {
+                let start = Instant::now();
+                let witnesses = self.create_chunk_state_witnesses(&pending);
+                for (pending, witness) in pending.into_iter().zip(witnesses) {
+                    let chunk_hash = pending.chunk.chunk_hash();
+                    let shard_id = pending.chunk.shard_id();
+                    let result = witness.and_then(|(witness, production_time)| {
+                        self.distribute_chunk_state_witness(pending, witness, production_time)
+                    });
+                    metrics::CHUNK_STATE_WITNESS_LATENCY
+                        .with_label_values(&[&shard_id.to_string()])
+                        .observe(start.elapsed().as_secs_f64());
+                    match result {
+                        Ok(witness) => {
+                            self.production_tracker.record_finished(shard_id, Some(witness))
+                        }
+                        Err(err) => {
+                            self.production_tracker.record_finished(shard_id, None);
+                            tracing::error!(target: "client", ?chunk_hash, ?err, "Failed to send chunk state witness to chunk validators");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Produces the state witnesses of the chunks, along with the time taken
+    /// to produce each of them. Up to one witness per thread of the pool is
+    /// produced at a time.
+    fn create_chunk_state_witnesses(
+        &mut self,
+        pending: &[PendingChunkStateWitness],
+    ) -> Vec<Result<(ChunkStateWitness, f64), Error>> {
+        let epoch_manager = self.epoch_manager.as_ref();
+        let recorded_state_transitions = &self.recorded_state_transitions;
+        let chain_stores = &mut self.chain_stores;
+        let mut witnesses = Vec::with_capacity(pending.len());
+        for batch in pending.chunks(chain_stores.len()) {
+            let batch_witnesses: Vec<_> = self.thread_pool.install(|| {
+                batch
+                    .par_iter()
+                    .zip(chain_stores.par_iter_mut())
+                    .map(|(pending, chain_store)| -> Result<_, Error> {
+                        let timer = metrics::CHUNK_STATE_WITNESS_PRODUCTION_TIME
+                            .with_label_values(&[&pending.chunk.shard_id().to_string()])
+                            .start_timer();
+                        let witness = create_chunk_state_witness(
+                            chain_store,
+                            epoch_manager,
+                            pending.prev_chunk_header.clone(),
+                            &pending.chunk,
+                            Some(recorded_state_transitions),
+                        )?;
+                        Ok((witness, timer.stop_and_record()))
+                    })
+                    .collect()
+            });
+            witnesses.extend(batch_witnesses);
+        }
+        witnesses
+    }
+
+    /// Sends the produced state witness, returning what was produced for the
+    /// debug status.
+    fn distribute_chunk_state_witness(
+        &mut self,
+        pending: PendingChunkStateWitness,
+        mut witness: ChunkStateWitness,
+        production_time: f64,
+    ) -> Result<ProducedWitnessView, Error> {
+        let PendingChunkStateWitness { mut chunk_validators, chunk, .. } = pending;
+        if self.adv.produce_invalid_witnesses() {
+            tracing::debug!(target: "adversary", chunk_hash=?chunk.chunk_hash(), "Producing invalid chunk state witness");
+            witness.applied_receipts_hash = CryptoHash::default();
+        }
+        let chunk_header = chunk.cloned_header();
+        let shard_id_label = chunk_header.shard_id().to_string();
+        let witness_size = borsh::object_length(&witness)?;
+        metrics::CHUNK_STATE_WITNESS_TOTAL_SIZE
+            .with_label_values(&[&shard_id_label])
+            .observe(witness_size as f64);
+        let produced = ProducedWitnessView {
+            chunk_hash: chunk_header.chunk_hash(),
+            height_created: chunk_header.height_created(),
+            size_bytes: witness_size as u64,
+            production_time_ms: (production_time * 1000.0) as u64,
+        };
+        if self.save_state_witnesses {
+            save_chunk_state_witness(&self.store, &witness)?;
+        }
+        tracing::debug!(
+            target: "chunk_validation",
+            "Sending chunk state witness for chunk {:?} to chunk validators {:?}",
+            chunk_header.chunk_hash(),
+            chunk_validators,
+        );
+        let sent = SentWitness::new(&witness, chunk_validators.iter().cloned().collect());
+        let previous = self.sent_witnesses.insert(chunk_header.shard_id(), sent);
+        let incremental_witnesses =
+            self.incremental_witnesses && self.incremental_witnesses_enabled(&chunk_header)?;
+        if let Some(previous) = previous.filter(|_| incremental_witnesses) {
+            if let Some(incremental) = incremental_chunk_state_witness(&witness, &previous) {
+                let (with_base, without_base): (Vec<_>, Vec<_>) = chunk_validators
+                    .into_iter()
+                    .partition(|account_id| previous.chunk_validators.contains(account_id));
+                chunk_validators = without_base;
+                if !with_base.is_empty() {
+                    self.network_adapter.send(DistributeStateWitnessRequest {
+                        chunk_validators: with_base,
+                        state_witness: incremental,
+                    });
+                }
+            }
+        }
+        if !chunk_validators.is_empty() {
+            self.network_adapter
+                .send(DistributeStateWitnessRequest { chunk_validators, state_witness: witness });
+        }
+        Ok(produced)
+    }
+
+    /// Whether the protocol version of the chunk's epoch supports sending
+    /// incremental witnesses, which older nodes can't decode.
+    fn incremental_witnesses_enabled(
+        &self,
+        chunk_header: &ShardChunkHeader,
+    ) -> Result<bool, Error> {
+        let epoch_id =
+            self.epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
+        Ok(checked_feature!("stable", IncrementalChunkStateWitnesses, protocol_version))
+    }
+}
+
+/// Runs the `ChunkWitnessProducer` in its own arbiter, so that reading state
+/// transitions and assembling witnesses never blocks the client.
+pub struct ChunkWitnessActor {
+    producer: ChunkWitnessProducer,
+}
+
+impl Actor for ChunkWitnessActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<WithSpanContext<ChunkWitnessRequest>> for ChunkWitnessActor {
+    type Result = ();
+
+    #[perf]
+    fn handle(&mut self, msg: WithSpanContext<ChunkWitnessRequest>, ctx: &mut Context<Self>) {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        if let ChunkWitnessRequest::DistributeChunkStateWitnesses(_) = &msg {
+            if let Some(delay) = self.producer.adv.witness_distribution_delay() {
+                tracing::debug!(target: "adversary", ?delay, "Delaying chunk state witness distribution");
+                ctx.run_later(delay, move |this, _| this.producer.handle_request(msg));
+                return;
+            }
+        }
+        self.producer.handle_request(msg);
+    }
+}
+
+pub fn start_chunk_witness_actor(
+    config: &ClientConfig,
+    chain_genesis: &ChainGenesis,
+    epoch_manager: Arc<dyn EpochManagerAdapter>,
+    store: Store,
+    network_adapter: Sender<DistributeStateWitnessRequest>,
+    production_tracker: ChunkWitnessProductionTracker,
+    adv: crate::adversarial::Controls,
+) -> (Addr<ChunkWitnessActor>, ArbiterHandle) {
+    let arbiter = Arbiter::new();
+    let arbiter_handle = arbiter.handle();
+    let producer = ChunkWitnessProducer::new(
+        config,
+        chain_genesis,
+        epoch_manager,
+        store,
+        network_adapter,
+        production_tracker,
+        adv,
+    );
+    let addr = ChunkWitnessActor::start_in_arbiter(&arbiter_handle, move |_| ChunkWitnessActor {
+        producer,
+    });
+    (addr, arbiter_handle)
+}
+
+/// Returns the key of the state witness of the chunk in `DBCol::StateWitnesses`.
+fn state_witness_key(chunk_header: &ShardChunkHeader) -> Vec<u8> {
+    [index_to_bytes(chunk_header.height_created()).as_slice(), chunk_header.chunk_hash().as_bytes()]
+        .concat()
+}
+
+/// Persists the state witness in `DBCol::StateWitnesses`.
+pub(crate) fn save_chunk_state_witness(
+    store: &Store,
+    witness: &ChunkStateWitness,
+) -> Result<(), Error> {
+    let key = state_witness_key(&witness.chunk_header);
+    let mut store_update = store.store_update();
+    store_update.set_ser(DBCol::StateWitnesses, &key, witness)?;
+    store_update.commit()?;
+    Ok(())
+}
+
+/// Reads the state witness of the chunk with the given header from
+/// `DBCol::StateWitnesses`, if it was saved.
+pub(crate) fn get_saved_chunk_state_witness(
+    store: &Store,
+    chunk_header: &ShardChunkHeader,
+) -> Result<Option<ChunkStateWitness>, Error> {
+    Ok(store.get_ser(DBCol::StateWitnesses, &state_witness_key(chunk_header))?)
+}
+
+/// Collects the state transitions needed to build the state witness for
+/// `chunk_header`, whose previous new chunk of the shard is `prev_chunk_header`.
+/// Transitions found in `recorded_state_transitions` (recorded while applying
+/// the blocks) are used directly; the rest are read back from the store.
+fn collect_state_transition_data(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    chunk_header: &ShardChunkHeader,
+    prev_chunk_header: ShardChunkHeader,
+    recorded_state_transitions: Option<
+        &Mutex<LruCache<(CryptoHash, ShardId), RecordedStateTransition>>,
+    >,
+) -> Result<(ChunkStateTransition, Vec<ChunkStateTransition>, CryptoHash), Error> {
+    let prev_chunk_height_included = prev_chunk_header.height_included();
+
+    // TODO(#9292): previous chunk is genesis chunk - consider proper
+    // result for this corner case.
+    // let prev_chunk_prev_hash = *prev_chunk_header.prev_block_hash();
+    // if prev_chunk_prev_hash == CryptoHash::default() {
+    //     return Ok(vec![]);
+    // }
+
+    // The transitions of each block are stored under the shard id in the
+    // shard layout of that block, which differs from the shard id of the
+    // chunk if the shard layout changed since the previous chunk.
+    let mut shard_id = chunk_header.shard_id();
+    let mut prev_blocks = Vec::new();
+    for block_hash in chain_store.get_blocks_until_height(
+        *chunk_header.prev_block_hash(),
+        prev_chunk_height_included,
+        true,
+    )? {
+        shard_id = epoch_manager.get_prev_shard_ids(&block_hash, vec![shard_id])?[0];
+        let epoch_id = epoch_manager.get_epoch_id(&block_hash)?;
+        let shard_uid = epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
+        prev_blocks.push((block_hash, shard_id, shard_uid));
+    }
+    prev_blocks.reverse();
+    let (main_block, implicit_blocks) = prev_blocks.split_first().unwrap();
+    let get_state_transition = |block: &(CryptoHash, ShardId, ShardUId)| {
+        let &(block_hash, shard_id, shard_uid) = block;
+        match recorded_state_transitions
+            .and_then(|recorded| recorded.lock().unwrap().pop(&(block_hash, shard_id)))
+        {
+            Some(RecordedStateTransition { transition, applied_receipts_hash, .. }) => {
+                Ok((transition, applied_receipts_hash))
+            }
+            None => get_stored_state_transition(chain_store, &block_hash, shard_id, &shard_uid),
+        }
+    };
+    let (main_transition, receipts_hash) = get_state_transition(main_block)?;
+    let mut implicit_transitions = vec![];
+    for block in implicit_blocks {
+        let (transition, _) = get_state_transition(block)?;
+        implicit_transitions.push(transition);
+    }
+
+    // TODO(#10265): If the previous block does not exist, we should
+    // queue this (similar to orphans) to retry later.
+
+    Ok((main_transition, implicit_transitions, receipts_hash))
+}
+
+/// Reads the state transition of the shard at the given block back from the
+/// state transition data saved when the block was applied.
+fn get_stored_state_transition(
+    chain_store: &ChainStore,
+    block_hash: &CryptoHash,
+    shard_id: ShardId,
+    shard_uid: &ShardUId,
+) -> Result<(ChunkStateTransition, CryptoHash), Error> {
+    let StoredChunkStateTransitionData { base_state, receipts_hash } = chain_store
+        .store()
+        .get_ser(DBCol::StateTransitionData, &get_block_shard_id(block_hash, shard_id))?
+        .ok_or(Error::Other(format!(
+            "Missing state proof for block {block_hash} and shard {shard_id}"
+        )))?;
+    let transition = ChunkStateTransition {
+        block_hash: *block_hash,
+        base_state,
+        post_state_root: *chain_store.get_chunk_extra(block_hash, shard_uid)?.state_root(),
+    };
+    Ok((transition, receipts_hash))
+}
+
+/// Builds the state witness for `chunk` out of the state transition data
+/// stored for the blocks since `prev_chunk_header` (the previous new chunk
+/// of the same shard).
+fn create_chunk_state_witness(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    prev_chunk_header: ShardChunkHeader,
+    chunk: &ShardChunk,
+    recorded_state_transitions: Option<
+        &Mutex<LruCache<(CryptoHash, ShardId), RecordedStateTransition>>,
+    >,
+) -> Result<ChunkStateWitness, Error> {
+    let chunk_header = chunk.cloned_header();
+    let prev_chunk = chain_store.get_chunk(&prev_chunk_header.chunk_hash())?;
+    let prev_chunk_shard_id = prev_chunk_header.shard_id();
+    let (main_state_transition, implicit_transitions, applied_receipts_hash) =
+        collect_state_transition_data(
+            chain_store,
+            epoch_manager,
+            &chunk_header,
+            prev_chunk_header,
+            recorded_state_transitions,
+        )?;
+    let source_receipt_proofs = collect_source_receipt_proofs(
+        chain_store,
+        epoch_manager,
+        main_state_transition.block_hash,
+        prev_chunk_shard_id,
+    )?;
+    Ok(ChunkStateWitness {
+        chunk_header,
+        main_state_transition,
+        source_receipt_proofs,
+        transactions: prev_chunk.transactions().to_vec(),
+        // (Could also be derived from iterating through the receipts, but
+        // that defeats the purpose of this check being a debugging
+        // mechanism.)
+        applied_receipts_hash,
+        implicit_transitions,
+        new_transactions: chunk.transactions().to_vec(),
+        // TODO(#9292): Derive this during chunk production, during
+        // prepare_transactions or the like.
+        new_transactions_validation_state: PartialState::default(),
+        transitions_base: None,
+    })
+}
+
+/// Collects the proofs of the receipts applied by the main transition of the
+/// witness, sent by the new chunks of the blocks returned by
+/// `get_receipt_source_blocks`, keyed by the hash of the sending chunk.
+fn collect_source_receipt_proofs(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    main_block_hash: CryptoHash,
+    shard_id: ShardId,
+) -> Result<HashMap<ChunkHash, ReceiptProof>, Error> {
+    let mut source_receipt_proofs = HashMap::new();
+    for source_block in
+        get_receipt_source_blocks(chain_store, epoch_manager, main_block_hash, shard_id)?
+    {
+        for chunk in source_block.new_chunks {
+            let chunk_hash = chunk.chunk_hash();
+            let partial_chunk = chain_store.get_partial_chunk(&chunk_hash)?;
+            let receipt_proof = partial_chunk
+                .receipts()
+                .iter()
+                .find(|receipt_proof| receipt_proof.1.to_shard_id == source_block.shard_id)
+                .ok_or_else(|| {
+                    Error::Other(format!(
+                        "Missing receipt proof of chunk {:?} for shard {}",
+                        chunk_hash, source_block.shard_id
+                    ))
+                })?;
+            source_receipt_proofs.insert(chunk_hash, receipt_proof.clone());
+        }
+    }
+    Ok(source_receipt_proofs)
+}
+
+/// Builds an incremental version of `witness`, which only carries the
+/// implicit transitions after the ones of the `previous` witness of the shard.
+/// Returns `None` if the witness doesn't build upon the previous one, e.g.
+/// because the previous chunk was included after all.
+fn incremental_chunk_state_witness(
+    witness: &ChunkStateWitness,
+    previous: &SentWitness,
+) -> Option<ChunkStateWitness> {
+    let main_transition = &witness.main_state_transition;
+    if main_transition.block_hash != previous.main_block_hash {
+        return None;
+    }
+    let num_validated = if previous.base.last_block_hash == main_transition.block_hash {
+        0
+    } else {
+        witness
+            .implicit_transitions
+            .iter()
+            .position(|transition| transition.block_hash == previous.base.last_block_hash)?
+            + 1
+    };
+    let post_state_root = match num_validated {
+        0 => main_transition.post_state_root,
+        n => witness.implicit_transitions[n - 1].post_state_root,
+    };
+    if post_state_root != previous.base.post_state_root {
+        return None;
+    }
+    Some(ChunkStateWitness {
+        chunk_header: witness.chunk_header.clone(),
+        main_state_transition: ChunkStateTransition {
+            block_hash: main_transition.block_hash,
+            base_state: PartialState::default(),
+            post_state_root: main_transition.post_state_root,
+        },
+        source_receipt_proofs: witness.source_receipt_proofs.clone(),
+        applied_receipts_hash: witness.applied_receipts_hash,
+        transactions: witness.transactions.clone(),
+        implicit_transitions: witness.implicit_transitions[num_validated..].to_vec(),
+        new_transactions: witness.new_transactions.clone(),
+        new_transactions_validation_state: witness.new_transactions_validation_state.clone(),
+        transitions_base: Some(previous.base.clone()),
+    })
+}
+
+/// Builds the state witness for the chunk with the given hash, if the node has
+/// all the data needed for it.
+pub(crate) fn get_chunk_state_witness(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    chunk_hash: &ChunkHash,
+) -> Result<ChunkStateWitness, Error> {
+    let chunk = chain_store.get_chunk(chunk_hash)?;
+    let prev_block = chain_store.get_block(&chunk.prev_block_hash())?;
+    let prev_chunk_header =
+        Chain::get_prev_chunk_header(epoch_manager, &prev_block, chunk.shard_id())?;
+    if prev_chunk_header.prev_block_hash() == &CryptoHash::default() {
+        return Err(Error::Other(format!(
+            "Previous chunk of chunk {:?} is a genesis chunk, there is no state witness for it",
+            chunk_hash
+        )));
+    }
+    create_chunk_state_witness(chain_store, epoch_manager, prev_chunk_header, &chunk, None)
+}
+
+/// Returns the state witness of the chunk with the given hash, requested by a
+/// chunk validator which missed it. Only the producer of the chunk serves the
+/// witness, taking it from `DBCol::StateWitnesses` if it was saved and
+/// building it again otherwise. Incremental witnesses are never served, as
+/// the requester may not have validated the previous witness of the shard:
+/// only full witnesses are saved.
+pub(crate) fn get_requested_chunk_state_witness(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    me: &AccountId,
+    chunk_hash: &ChunkHash,
+) -> Result<Option<ChunkStateWitness>, Error> {
+    let chunk_header = chain_store.get_partial_chunk(chunk_hash)?.cloned_header();
+    let epoch_id = epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
+    let chunk_producer = epoch_manager.get_chunk_producer(
+        &epoch_id,
+        chunk_header.height_created(),
+        chunk_header.shard_id(),
+    )?;
+    if &chunk_producer != me {
+        return Ok(None);
+    }
+    if let Some(witness) = get_saved_chunk_state_witness(chain_store.store(), &chunk_header)? {
+        return Ok(Some(witness));
+    }
+    get_chunk_state_witness(chain_store, epoch_manager, chunk_hash).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::test_utils::create_test_chunk_header;
+
+    fn transition(block: &[u8]) -> ChunkStateTransition {
+        ChunkStateTransition {
+            block_hash: CryptoHash::hash_bytes(block),
+            base_state: PartialState::TrieValues(vec![block.to_vec().into()]),
+            post_state_root: CryptoHash::hash_bytes(&[block, b"root".as_slice()].concat()),
+        }
+    }
+
+    /// A witness with the main transition of block `a` and an implicit
+    /// transition for each of `implicit_blocks`.
+    fn witness(implicit_blocks: &[&[u8]]) -> ChunkStateWitness {
+        let chunk_header = create_test_chunk_header(implicit_blocks.len() as u64 + 1, 0);
+        ChunkStateWitness {
+            chunk_header,
+            main_state_transition: transition(b"a"),
+            source_receipt_proofs: HashMap::new(),
+            applied_receipts_hash: CryptoHash::default(),
+            transactions: vec![],
+            implicit_transitions: implicit_blocks.iter().map(|block| transition(block)).collect(),
+            new_transactions: vec![],
+            new_transactions_validation_state: PartialState::default(),
+            transitions_base: None,
+        }
+    }
+
+    #[test]
+    fn test_incremental_chunk_state_witness() {
+        let previous = SentWitness::new(&witness(&[b"b"]), HashSet::new());
+        let incremental = incremental_chunk_state_witness(&witness(&[b"b", b"c"]), &previous)
+            .expect("witness should build upon the previous one");
+        assert_eq!(incremental.transitions_base, Some(previous.base.clone()));
+        assert_eq!(incremental.main_state_transition.base_state, PartialState::default());
+        assert_eq!(incremental.implicit_transitions, vec![transition(b"c")]);
+
+        // Without implicit transitions, only the main transition is reused.
+        let previous = SentWitness::new(&witness(&[]), HashSet::new());
+        let incremental = incremental_chunk_state_witness(&witness(&[b"b"]), &previous).unwrap();
+        assert_eq!(incremental.implicit_transitions, vec![transition(b"b")]);
+    }
+
+    #[test]
+    fn test_incremental_chunk_state_witness_unrelated() {
+        // The previous witness has a different main transition.
+        let mut other = witness(&[]);
+        other.main_state_transition = transition(b"x");
+        let previous = SentWitness::new(&other, HashSet::new());
+        assert_eq!(incremental_chunk_state_witness(&witness(&[b"b"]), &previous), None);
+
+        // The previous witness went along a different fork.
+        let previous = SentWitness::new(&witness(&[b"y"]), HashSet::new());
+        assert_eq!(incremental_chunk_state_witness(&witness(&[b"b", b"c"]), &previous), None);
+    }
+
+    #[test]
+    fn test_production_tracker() {
+        let tracker = ChunkWitnessProductionTracker::default();
+        let producer_tracker = tracker.clone();
+        for _ in 0..=RECENT_WITNESSES_PER_SHARD {
+            tracker.record_requested(0);
+        }
+        tracker.record_requested(1);
+        assert_eq!(tracker.shard_status(0).0, RECENT_WITNESSES_PER_SHARD + 1);
+
+        producer_tracker.record_finished(1, None);
+        assert_eq!(tracker.shard_status(1), (0, vec![]));
+        for height in 0..=RECENT_WITNESSES_PER_SHARD as u64 {
+            let witness = ProducedWitnessView {
+                chunk_hash: ChunkHash(CryptoHash::hash_borsh(height)),
+                height_created: height,
+                size_bytes: 100,
+                production_time_ms: 1,
+            };
+            producer_tracker.record_finished(0, Some(witness));
+        }
+        let (in_progress, recent_witnesses) = tracker.shard_status(0);
+        assert_eq!(in_progress, 0);
+        let heights: Vec<_> = recent_witnesses.iter().map(|w| w.height_created).collect();
+        assert_eq!(heights, (1..=RECENT_WITNESSES_PER_SHARD as u64).rev().collect::<Vec<_>>());
+    }
+}
@@ -2,14 +2,25 @@
 //! This client works completely synchronously and must be operated by some async actor outside.
 
 use crate::adapter::ProcessTxResponse;
+use crate::chunk_endorsement_tracker::{ChunkEndorsementPool, ChunkEndorsementTracker};
+use crate::chunk_state_witness_queue::{
+    ChunkStateWitnessQueue, CHUNK_STATE_WITNESS_QUEUE_CAPACITY,
+};
+use crate::chunk_state_witness_requester::{
+    ChunkStateWitnessRequester, CHUNK_STATE_WITNESS_REQUEST_DELAY,
+    CHUNK_STATE_WITNESS_REQUEST_RETRY_PERIOD,
+};
 use crate::chunk_validation::ChunkValidator;
+use crate::chunk_witness_actor::{ChunkWitnessProductionTracker, ChunkWitnessRequest};
 use crate::debug::BlockProductionTracker;
 use crate::debug::PRODUCTION_TIMES_CACHE_SIZE;
 use crate::sync::adapter::SyncShardInfo;
 use crate::sync::block::BlockSync;
+#[cfg(feature = "new_epoch_sync")]
 use crate::sync::epoch::EpochSync;
 use crate::sync::header::HeaderSync;
 use crate::sync::state::{StateSync, StateSyncResult};
+use crate::view_client::ValidatorAccountId;
 use crate::SyncAdapter;
 use crate::SyncMessage;
 use crate::{metrics, SyncStatus};
@@ -18,6 +29,7 @@ use chrono::DateTime;
 use chrono::Utc;
 use itertools::Itertools;
 use lru::LruCache;
+use near_async::futures::AsyncComputationSpawner;
 use near_async::messaging::IntoSender;
 use near_async::messaging::{CanSend, Sender};
 use near_chain::chain::VerifyBlockHashAndSignatureResult;
@@ -26,11 +38,12 @@ use near_chain::chain::{
 };
 use near_chain::flat_storage_creator::FlatStorageCreator;
 use near_chain::orphan::OrphanMissingChunks;
-use near_chain::resharding::ReshardingRequest;
+use near_chain::resharding::{get_resharding_progress, ReshardingRequest};
 use near_chain::state_snapshot_actor::SnapshotCallbacks;
 use near_chain::test_utils::format_hash;
 use near_chain::types::RuntimeAdapter;
-use near_chain::types::{ChainConfig, LatestKnown};
+use near_chain::types::{ChainConfig, LatestKnown, PreparedTransactions};
+use near_chain::validate::validate_challenge;
 use near_chain::{
     BlockProcessingArtifact, BlockStatus, Chain, ChainGenesis, ChainStoreAccess,
     DoneApplyChunkCallback, Doomslug, DoomslugThresholdMode, Provenance,
@@ -45,42 +58,48 @@ use near_chunks::ShardsManager;
 use near_client_primitives::debug::ChunkProduction;
 use near_client_primitives::types::{
     format_shard_sync_phase_per_shard, Error, ShardSyncDownload, ShardSyncStatus,
+    ValidatorSignedHeights,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
-use near_network::types::{AccountKeys, ChainInfo, PeerManagerMessageRequest, SetChainInfo};
 use near_network::types::{
-    HighestHeightPeerInfo, NetworkRequests, PeerManagerAdapter, ReasonForBan,
+    AccountKeys, ChainInfo, PeerManagerMessageRequest, SetChainInfo, SetValidatorSigner,
+};
+use near_network::types::{
+    HighestHeightPeerInfo, NetworkRequests, PeerManagerAdapter, ReasonForBan, SnapshotHostInfo,
 };
 use near_o11y::log_assert;
 use near_o11y::WithSpanContextExt;
-use near_pool::InsertTransactionResult;
+use near_pool::{InsertTransactionResult, PoolOrder, TransactionPoolLimits};
 use near_primitives::block::{Approval, ApprovalInner, ApprovalMessage, Block, BlockHeader, Tip};
 use near_primitives::block_header::ApprovalType;
-use near_primitives::challenge::{Challenge, ChallengeBody};
+use near_primitives::challenge::{Challenge, ChallengeBody, Challenges};
+use near_primitives::checked_feature;
 use near_primitives::epoch_manager::RngSeed;
 use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath, PartialMerkleTree};
 use near_primitives::network::PeerId;
-use near_primitives::receipt::Receipt;
+use near_primitives::receipt::{Receipt, ReceiptEnum};
 use near_primitives::sharding::StateSyncInfo;
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, PartialEncodedChunk, ReedSolomonWrapper, ShardChunk,
     ShardChunkHeader, ShardInfo,
 };
 use near_primitives::static_clock::StaticClock;
-use near_primitives::transaction::SignedTransaction;
+use near_primitives::transaction::{Action, SignedTransaction};
 use near_primitives::types::Gas;
 use near_primitives::types::StateRoot;
-use near_primitives::types::{AccountId, ApprovalStake, BlockHeight, EpochId, NumBlocks, ShardId};
+use near_primitives::types::{
+    AccountId, ApprovalStake, BlockHeight, EpochId, NumBlocks, ProtocolVersion, ShardId,
+};
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{CatchupStatusView, DroppedReason};
+use near_primitives::views::{CatchupStatusView, DroppedReason, TxPoolStatusView};
 use near_store::metadata::DbKind;
-use near_store::ShardUId;
+use near_store::{get_access_key, ShardUId};
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -92,12 +111,9 @@ const NUM_REBROADCAST_BLOCKS: usize = 30;
 const CHUNK_HEADERS_FOR_INCLUSION_CACHE_SIZE: usize = 2048;
 const NUM_EPOCH_CHUNK_PRODUCERS_TO_KEEP_IN_BLOCKLIST: usize = 1000;
 
-/// The time we wait for the response to a Epoch Sync request before retrying
-// TODO #3488 set 30_000
-pub const EPOCH_SYNC_REQUEST_TIMEOUT: Duration = Duration::from_millis(1_000);
-/// How frequently a Epoch Sync response can be sent to a particular peer
-// TODO #3488 set 60_000
-pub const EPOCH_SYNC_PEER_TIMEOUT: Duration = Duration::from_millis(10);
+/// The time we wait for the response to a Epoch Sync request before requesting another peer.
+#[cfg(feature = "new_epoch_sync")]
+pub const EPOCH_SYNC_REQUEST_TIMEOUT: Duration = Duration::from_millis(30_000);
 /// Drop blocks whose height are beyond head + horizon if it is not in the current epoch.
 const BLOCK_HORIZON: u64 = 500;
 
@@ -146,6 +162,18 @@ pub struct Client {
     pub network_adapter: PeerManagerAdapter,
     /// Signer for block producer (if present).
     pub validator_signer: Option<Arc<dyn ValidatorSigner>>,
+    /// Signer of the validator while its key is detached, see
+    /// `detach_validator_signer`.
+    detached_validator_signer: Option<Arc<dyn ValidatorSigner>>,
+    /// Account of the validator, shared with the view client.
+    pub(crate) validator_account_id: ValidatorAccountId,
+    /// Largest heights of the blocks and chunks produced by the node. The
+    /// approval height is only kept up to date while the key is detached,
+    /// see `validator_signed_heights`.
+    signed_heights: ValidatorSignedHeights,
+    /// Blocks and chunks are not produced at heights up to these ones, as
+    /// another node holding the same validator key may have signed them.
+    signing_height_floor: ValidatorSignedHeights,
     /// Approvals for which we do not have the block yet
     pub pending_approvals:
         lru::LruCache<ApprovalInner, HashMap<AccountId, (Approval, ApprovalType)>>,
@@ -154,6 +182,7 @@ pub struct Client {
     pub catchup_state_syncs:
         HashMap<CryptoHash, (StateSync, HashMap<u64, ShardSyncDownload>, BlocksCatchUpState)>,
     /// Keeps track of information needed to perform the initial Epoch Sync
+    #[cfg(feature = "new_epoch_sync")]
     pub epoch_sync: EpochSync,
     /// Keeps track of syncing headers.
     pub header_sync: HeaderSync,
@@ -188,15 +217,183 @@ pub struct Client {
     pub last_time_sync_block_requested: Option<DateTime<Utc>>,
 
     pub chunk_validator: ChunkValidator,
+    pub chunk_endorsement_tracker: ChunkEndorsementTracker,
+    /// Produces state witnesses and sends them to chunk validators, off the
+    /// client thread.
+    chunk_witness_sender: Sender<ChunkWitnessRequest>,
+    /// Shared with the `ChunkWitnessActor` to report witness production in
+    /// the debug status.
+    pub(crate) chunk_witness_production_tracker: ChunkWitnessProductionTracker,
+    /// Produced chunks whose state witnesses are yet to be sent to chunk
+    /// validators. Flushed after each batch of processed blocks.
+    pub(crate) chunk_state_witness_queue: ChunkStateWitnessQueue,
+    /// Chunks this node has to validate whose state witness hasn't arrived
+    /// yet, to be requested from the chunk producers.
+    pub(crate) chunk_state_witness_requester: ChunkStateWitnessRequester,
 }
 
 impl Client {
     pub(crate) fn update_client_config(&self, update_client_config: UpdateableClientConfig) {
         self.config.expected_shutdown.update(update_client_config.expected_shutdown);
+        self.config.maintenance_window.update(update_client_config.maintenance_window);
         self.config.resharding_config.update(update_client_config.resharding_config);
         self.config
             .produce_chunk_add_transactions_time_limit
             .update(update_client_config.produce_chunk_add_transactions_time_limit);
+        self.config
+            .produce_chunk_soft_deadline
+            .update(update_client_config.produce_chunk_soft_deadline);
+        self.chunk_validator.update_config(update_client_config.chunk_validator);
+    }
+
+    /// Switches to a reloaded validator signer, e.g. after the validator key
+    /// files were changed to rotate the key, or drops the signer if the key
+    /// was removed. The ShardsManager, the view client and the network are
+    /// switched along. Changing the validator account requires a restart, so
+    /// signers for another account, or for a node started without one, are
+    /// ignored.
+    pub(crate) fn update_validator_signer(
+        &mut self,
+        validator_signer: Option<Arc<dyn ValidatorSigner>>,
+    ) {
+        let Some(current) = self.any_validator_signer() else {
+            if let Some(validator_signer) = validator_signer {
+                warn!(
+                    target: "client",
+                    new = ?validator_signer.validator_id(),
+                    "Ignoring reloaded validator key, restart the node to start validating");
+            }
+            return;
+        };
+        let Some(validator_signer) = validator_signer else {
+            info!(
+                target: "client",
+                validator = ?current.validator_id(),
+                "Validator key removed, the node stops validating");
+            self.set_validator_signer(None);
+            self.detached_validator_signer = None;
+            self.propagate_validator_signer(None);
+            return;
+        };
+        if current.validator_id() != validator_signer.validator_id() {
+            warn!(
+                target: "client",
+                current = ?current.validator_id(),
+                new = ?validator_signer.validator_id(),
+                "Ignoring reloaded validator key for a different account, restart the node to change the validator account");
+            return;
+        }
+        if current.public_key() == validator_signer.public_key()
+            && current.next_public_key() == validator_signer.next_public_key()
+        {
+            return;
+        }
+        info!(
+            target: "client",
+            validator = ?validator_signer.validator_id(),
+            public_key = ?validator_signer.public_key(),
+            next_public_key = ?validator_signer.next_public_key(),
+            detached = self.detached_validator_signer.is_some(),
+            "Updated validator signer");
+        if self.detached_validator_signer.is_some() {
+            self.detached_validator_signer = Some(validator_signer.clone());
+        } else {
+            self.set_validator_signer(Some(validator_signer.clone()));
+        }
+        self.propagate_validator_signer(Some(validator_signer));
+    }
+
+    /// Lets the other actors of the node know about the reloaded validator
+    /// signer. The network signs its TIER1 data with the new key, while the
+    /// ShardsManager and the view client only care about the account.
+    fn propagate_validator_signer(&self, validator_signer: Option<Arc<dyn ValidatorSigner>>) {
+        let account_id = validator_signer.as_ref().map(|signer| signer.validator_id().clone());
+        self.validator_account_id.set(account_id.clone());
+        self.shards_manager_adapter
+            .send(ShardsManagerRequestFromClient::SetValidatorAccountId(account_id));
+        self.network_adapter.send(SetValidatorSigner(validator_signer));
+    }
+
+    /// Detaches the validator key: the node stops producing blocks and chunks
+    /// and signing approvals and chunk endorsements, but keeps the signer to
+    /// attach it back later, e.g. when a backup node holding the same key
+    /// takes over. Waits for the chunk validations in progress, which still
+    /// endorse the chunks, so that once it returns nothing is signed anymore
+    /// and the heights returned by `validator_signed_heights` are final.
+    /// Returns false if there is no attached key.
+    pub(crate) fn detach_validator_signer(&mut self) -> bool {
+        let Some(validator_signer) = self.validator_signer.clone() else {
+            return false;
+        };
+        self.set_validator_signer(None);
+        self.chunk_validator.wait_for_validations();
+        // Doomslug keeps moving the target height without a signer.
+        self.signed_heights.approval_height = self.doomslug.get_largest_target_height();
+        info!(
+            target: "client",
+            validator = ?validator_signer.validator_id(),
+            signed_heights = ?self.validator_signed_heights(),
+            "Detached validator key");
+        self.detached_validator_signer = Some(validator_signer);
+        true
+    }
+
+    /// Attaches back the validator key detached by `detach_validator_signer`.
+    /// `signed_heights` are the heights last signed at by the node the key is
+    /// taken over from, nothing is signed at or below them. Returns false if
+    /// there is no detached key.
+    pub(crate) fn attach_validator_signer(
+        &mut self,
+        signed_heights: Option<ValidatorSignedHeights>,
+    ) -> Result<bool, Error> {
+        if self.detached_validator_signer.is_none() {
+            return Ok(false);
+        }
+        if let Some(signed_heights) = signed_heights {
+            self.set_signing_height_floor(signed_heights)?;
+        }
+        let validator_signer = self.detached_validator_signer.take().unwrap();
+        info!(
+            target: "client",
+            validator = ?validator_signer.validator_id(),
+            signing_height_floor = ?self.signing_height_floor,
+            "Attached validator key");
+        self.set_validator_signer(Some(validator_signer));
+        Ok(true)
+    }
+
+    /// Raises the heights up to which nothing is signed. The approval height
+    /// is persisted, like the target heights approved by the node, before
+    /// any approval may be signed.
+    fn set_signing_height_floor(&mut self, floor: ValidatorSignedHeights) -> Result<(), Error> {
+        self.signing_height_floor = self.signing_height_floor.max(&floor);
+        self.doomslug.set_approval_height_floor(floor.approval_height);
+        self.chunk_validator.set_endorsement_height_floor(floor.endorsement_height);
+        let mut chain_store_update = self.chain.mut_chain_store().store_update();
+        chain_store_update.save_largest_target_height(self.doomslug.get_largest_target_height());
+        chain_store_update.commit()?;
+        Ok(())
+    }
+
+    /// Returns the largest heights signed at with the validator key.
+    pub(crate) fn validator_signed_heights(&self) -> ValidatorSignedHeights {
+        let mut signed_heights = self.signed_heights;
+        if self.validator_signer.is_some() {
+            signed_heights.approval_height = self.doomslug.get_largest_target_height();
+        }
+        signed_heights.endorsement_height = self.chunk_validator.last_endorsed_height();
+        signed_heights
+    }
+
+    /// Returns the signer of the validator, whether its key is attached or not.
+    pub(crate) fn any_validator_signer(&self) -> Option<&Arc<dyn ValidatorSigner>> {
+        self.validator_signer.as_ref().or(self.detached_validator_signer.as_ref())
+    }
+
+    fn set_validator_signer(&mut self, validator_signer: Option<Arc<dyn ValidatorSigner>>) {
+        self.doomslug.set_signer(validator_signer.clone());
+        self.chunk_validator.set_signer(validator_signer.clone());
+        self.validator_signer = validator_signer;
     }
 }
 
@@ -234,11 +431,20 @@ impl Client {
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         network_adapter: PeerManagerAdapter,
         shards_manager_adapter: Sender<ShardsManagerRequestFromClient>,
+        chunk_witness_sender: Sender<ChunkWitnessRequest>,
+        chunk_witness_production_tracker: ChunkWitnessProductionTracker,
+        chunk_endorsements: ChunkEndorsementPool,
+        validator_account_id: ValidatorAccountId,
         validator_signer: Option<Arc<dyn ValidatorSigner>>,
         enable_doomslug: bool,
         rng_seed: RngSeed,
         snapshot_callbacks: Option<SnapshotCallbacks>,
+        async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
     ) -> Result<Self, Error> {
+        // With failover, the node only signs once it knows that the other node doesn't.
+        let detached = config.validator_key_detached || config.validator_failover.is_some();
+        let (validator_signer, detached_validator_signer) =
+            if detached { (None, validator_signer) } else { (validator_signer, None) };
         let doomslug_threshold_mode = if enable_doomslug {
             DoomslugThresholdMode::TwoThirds
         } else {
@@ -248,6 +454,9 @@ impl Client {
             save_trie_changes: config.save_trie_changes,
             background_migration_threads: config.client_background_migration_threads,
             resharding_config: config.resharding_config.clone(),
+            save_receipt_origins: config.save_receipt_origins,
+            shadow_apply: config.shadow_apply.as_ref().map(|config| config.variant),
+            read_only: config.read_replica.is_some(),
         };
         let chain = Chain::new(
             epoch_manager.clone(),
@@ -258,32 +467,28 @@ impl Client {
             chain_config.clone(),
             snapshot_callbacks,
         )?;
-        // Create flat storage or initiate migration to flat storage.
+        // Create flat storage or initiate migration to flat storage. A read-only
+        // node can't run the migration, so it only opens the flat storages which
+        // are already created.
         let flat_storage_creator = FlatStorageCreator::new(
             epoch_manager.clone(),
             runtime_adapter.clone(),
             chain.chain_store(),
             chain_config.background_migration_threads,
-        )?;
-        let sharded_tx_pool =
-            ShardedTransactionPool::new(rng_seed, config.transaction_pool_size_limit);
-        let sync_status = SyncStatus::AwaitingPeers;
-        let genesis_block = chain.genesis_block();
-        let epoch_sync = EpochSync::new(
-            network_adapter.clone(),
-            genesis_block.header().epoch_id().clone(),
-            genesis_block.header().next_epoch_id().clone(),
-            epoch_manager
-                .get_epoch_block_producers_ordered(
-                    genesis_block.header().epoch_id(),
-                    genesis_block.hash(),
-                )?
-                .iter()
-                .map(|x| x.0.clone())
-                .collect(),
-            EPOCH_SYNC_REQUEST_TIMEOUT,
-            EPOCH_SYNC_PEER_TIMEOUT,
+        )?
+        .filter(|_| !chain_config.read_only);
+        let sharded_tx_pool = ShardedTransactionPool::new(
+            rng_seed,
+            TransactionPoolLimits {
+                total_size: config.transaction_pool_size_limit,
+                account_count: config.transaction_pool_account_count_limit,
+                account_size: config.transaction_pool_account_size_limit,
+                account_future_count: config.transaction_pool_future_nonce_limit,
+            },
         );
+        let sync_status = SyncStatus::AwaitingPeers;
+        #[cfg(feature = "new_epoch_sync")]
+        let epoch_sync = EpochSync::new(network_adapter.clone(), EPOCH_SYNC_REQUEST_TIMEOUT);
         let header_sync = HeaderSync::new(
             network_adapter.clone(),
             config.header_sync_initial_timeout,
@@ -317,18 +522,23 @@ impl Client {
             config.state_sync_timeout,
             &config.chain_id,
             &config.state_sync.sync,
+            config.state_sync_num_concurrent_requests,
             false,
         );
         let num_block_producer_seats = config.num_block_producer_seats as usize;
         let data_parts = epoch_manager.num_data_parts();
         let parity_parts = epoch_manager.num_total_parts() - data_parts;
 
+        let largest_target_height = chain.chain_store().largest_target_height()?;
+        let signed_heights =
+            ValidatorSignedHeights { approval_height: largest_target_height, ..Default::default() };
         let doomslug = Doomslug::new(
-            chain.chain_store().largest_target_height()?,
+            largest_target_height,
             config.min_block_production_delay,
             config.max_block_production_delay,
             config.max_block_production_delay / 10,
             config.max_block_wait_delay,
+            config.doomslug_adaptive_timers.clone(),
             validator_signer.clone(),
             doomslug_threshold_mode,
         );
@@ -337,7 +547,14 @@ impl Client {
             epoch_manager.clone(),
             network_adapter.clone().into_sender(),
             runtime_adapter.clone(),
+            async_computation_spawner,
+            config.chunk_endorsement_num_next_block_producers,
+            config.chunk_validator.clone(),
+            config.chunk_state_witness_analytics_file.as_deref(),
+            config.remote_chunk_validation.as_ref(),
         );
+        let chunk_endorsement_tracker =
+            ChunkEndorsementTracker::new(epoch_manager.clone(), chunk_endorsements);
         Ok(Self {
             #[cfg(feature = "test_features")]
             adv_produce_blocks: None,
@@ -365,8 +582,13 @@ impl Client {
             ),
             network_adapter,
             validator_signer,
+            detached_validator_signer,
+            validator_account_id,
+            signed_heights,
+            signing_height_floor: ValidatorSignedHeights::default(),
             pending_approvals: lru::LruCache::new(num_block_producer_seats),
             catchup_state_syncs: HashMap::new(),
+            #[cfg(feature = "new_epoch_sync")]
             epoch_sync,
             header_sync,
             block_sync,
@@ -381,6 +603,16 @@ impl Client {
             flat_storage_creator,
             last_time_sync_block_requested: None,
             chunk_validator,
+            chunk_endorsement_tracker,
+            chunk_witness_sender,
+            chunk_witness_production_tracker,
+            chunk_state_witness_queue: ChunkStateWitnessQueue::new(
+                CHUNK_STATE_WITNESS_QUEUE_CAPACITY,
+            ),
+            chunk_state_witness_requester: ChunkStateWitnessRequester::new(
+                CHUNK_STATE_WITNESS_REQUEST_DELAY,
+                CHUNK_STATE_WITNESS_REQUEST_RETRY_PERIOD,
+            ),
         })
     }
 
@@ -420,6 +652,7 @@ impl Client {
                     let chunk = self.chain.get_chunk(&chunk_header.chunk_hash()).unwrap();
                     let transactions = chunk.transactions();
                     self.sharded_tx_pool.remove_transactions(shard_uid, transactions);
+                    self.sharded_tx_pool.record_included_chunk(shard_uid, transactions.len());
                 }
             }
         }
@@ -593,6 +826,10 @@ impl Client {
             .as_ref()
             .ok_or_else(|| Error::BlockProducer("Called without block producer info.".to_string()))?
             .clone();
+        if height <= self.signing_height_floor.block_height {
+            debug!(target: "client", height, floor = self.signing_height_floor.block_height, "Not producing block below the signing height floor");
+            return Ok(None);
+        }
 
         // Check that we are were called at the block that we are producer for.
         let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_hash).unwrap();
@@ -762,13 +999,13 @@ impl Client {
             None
         };
 
-        // Get all the current challenges.
-        // TODO(2445): Enable challenges when they are working correctly.
-        // let challenges = self.challenges.drain().map(|(_, challenge)| challenge).collect();
         let this_epoch_protocol_version =
             self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
         let next_epoch_protocol_version =
             self.epoch_manager.get_epoch_protocol_version(&next_epoch_id)?;
+        // Get all the current challenges.
+        let challenges =
+            self.challenges_for_block(&epoch_id, &prev_hash, this_epoch_protocol_version)?;
 
         let block = Block::produce(
             this_epoch_protocol_version,
@@ -786,12 +1023,12 @@ impl Client {
             max_gas_price,
             minted_amount,
             prev_block_extra.challenges_result.clone(),
-            vec![],
+            challenges,
             &*validator_signer,
             next_bp_hash,
             block_merkle_root,
             timestamp_override,
-        );
+        )?;
 
         // Update latest known even before returning block out, to prevent race conditions.
         self.chain
@@ -799,6 +1036,7 @@ impl Client {
             .save_latest_known(LatestKnown { height, seen: block.header().raw_timestamp() })?;
 
         metrics::BLOCK_PRODUCED_TOTAL.inc();
+        self.signed_heights.block_height = max(self.signed_heights.block_height, height);
 
         Ok(Some(block))
     }
@@ -820,6 +1058,10 @@ impl Client {
             .as_ref()
             .ok_or_else(|| Error::ChunkProducer("Called without block producer info.".to_string()))?
             .clone();
+        if next_height <= self.signing_height_floor.chunk_height {
+            debug!(target: "client", next_height, shard_id, floor = self.signing_height_floor.chunk_height, "Not producing chunk below the signing height floor");
+            return Ok(None);
+        }
 
         let chunk_proposer =
             self.epoch_manager.get_chunk_producer(epoch_id, next_height, shard_id).unwrap();
@@ -853,11 +1095,24 @@ impl Client {
             .map_err(|err| Error::ChunkProducer(format!("No chunk extra available: {}", err)))?;
 
         let prev_block_header = self.chain.get_block_header(&prev_block_hash)?;
+        // Stop adding transactions early enough for the chunk to be produced
+        // within the soft deadline, measured from the start of the production.
+        let remaining_time = self
+            .config
+            .produce_chunk_soft_deadline
+            .get()
+            .map(|deadline| deadline.saturating_sub(timer.elapsed()));
+        let time_limit =
+            [self.config.produce_chunk_add_transactions_time_limit.get(), remaining_time]
+                .into_iter()
+                .flatten()
+                .min();
         let transactions = self.prepare_transactions(
             shard_uid,
             chunk_extra.gas_limit(),
             *chunk_extra.state_root(),
             &prev_block_header,
+            time_limit,
         )?;
         #[cfg(feature = "test_features")]
         let transactions = Self::maybe_insert_invalid_transaction(
@@ -906,6 +1161,7 @@ impl Client {
             "Produced chunk");
 
         metrics::CHUNK_PRODUCED_TOTAL.inc();
+        self.signed_heights.chunk_height = max(self.signed_heights.chunk_height, next_height);
         self.chunk_production_info.put(
             (next_height, shard_id),
             ChunkProduction {
@@ -967,14 +1223,29 @@ impl Client {
         gas_limit: Gas,
         state_root: StateRoot,
         prev_block_header: &BlockHeader,
+        time_limit: Option<Duration>,
     ) -> Result<Vec<SignedTransaction>, Error> {
         let Self { chain, sharded_tx_pool, epoch_manager, runtime_adapter: runtime, .. } = self;
 
         let shard_id = shard_uid.shard_id as ShardId;
         let next_epoch_id = epoch_manager.get_epoch_id_from_prev_block(prev_block_header.hash())?;
         let protocol_version = epoch_manager.get_epoch_protocol_version(&next_epoch_id)?;
-
-        let transactions = if let Some(mut iter) = sharded_tx_pool.get_pool_iterator(shard_uid) {
+        // The state read to validate the transactions is part of the state
+        // witness, so stop adding transactions before it alone would make the
+        // witness exceed the size accepted by chunk validators.
+        let storage_proof_size_limit =
+            checked_feature!("stable", ChunkValidation, protocol_version)
+                .then(|| {
+                    epoch_manager.get_epoch_config(&next_epoch_id).map(|config| {
+                        config.chunk_validation_config.max_chunk_state_witness_size as usize
+                    })
+                })
+                .transpose()?;
+
+        let pool_order = pool_order(&self.config, protocol_version);
+        let prepared = if let Some(mut iter) =
+            sharded_tx_pool.get_pool_iterator_with_order(shard_uid, pool_order)
+        {
             let transaction_validity_period = chain.transaction_validity_period;
             runtime.prepare_transactions(
                 prev_block_header.next_gas_price(),
@@ -998,11 +1269,32 @@ impl Client {
                         .is_ok()
                 },
                 protocol_version,
-                self.config.produce_chunk_add_transactions_time_limit.get(),
+                time_limit,
+                storage_proof_size_limit,
             )?
         } else {
-            vec![]
+            PreparedTransactions { transactions: vec![], limited_by: None }
         };
+        let shard_label = shard_id.to_string();
+        if let Some(time_limit) = time_limit {
+            metrics::PRODUCE_CHUNK_TRANSACTIONS_TIME_BUDGET
+                .with_label_values(&[&shard_label])
+                .observe(time_limit.as_secs_f64());
+        }
+        if let Some(limited_by) = prepared.limited_by {
+            debug!(
+                target: "client",
+                shard_id,
+                ?limited_by,
+                ?time_limit,
+                num_tx = prepared.transactions.len(),
+                "Stopped adding transactions to chunk"
+            );
+            metrics::PRODUCE_CHUNK_TRANSACTIONS_LIMITED_TOTAL
+                .with_label_values(&[&shard_label, limited_by.as_ref()])
+                .inc();
+        }
+        let transactions = prepared.transactions;
         // Reintroduce valid transactions back to the pool. They will be removed when the chunk is
         // included into the block.
         let reintroduced_count = sharded_tx_pool.reintroduce_transactions(shard_uid, &transactions);
@@ -1012,10 +1304,27 @@ impl Client {
         Ok(transactions)
     }
 
+    /// Returns the status of the transaction pools and of the transactions of the given account
+    /// in them.
+    pub fn get_tx_pool_status(
+        &self,
+        account_id: Option<&AccountId>,
+    ) -> Result<TxPoolStatusView, Error> {
+        let head = self.chain.head()?;
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(&head.epoch_id)?;
+        Ok(self.sharded_tx_pool.status(account_id, pool_order(&self.config, protocol_version)))
+    }
+
     pub fn send_challenges(&mut self, challenges: Vec<ChallengeBody>) {
         if let Some(validator_signer) = &self.validator_signer {
             for body in challenges {
-                let challenge = Challenge::produce(body, &**validator_signer);
+                let challenge = match Challenge::produce(body, &**validator_signer) {
+                    Ok(challenge) => challenge,
+                    Err(err) => {
+                        warn!(target: "client", %err, "Failed to sign challenge");
+                        continue;
+                    }
+                };
                 self.challenges.insert(challenge.hash, challenge.clone());
                 self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                     NetworkRequests::Challenge(challenge),
@@ -1246,25 +1555,23 @@ impl Client {
 
         // Send out challenge if the block was found to be invalid.
         if let Some(validator_signer) = self.validator_signer.as_ref() {
-            if let Err(e) = &result {
-                match e {
-                    near_chain::Error::InvalidChunkProofs(chunk_proofs) => {
-                        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkProofs(*chunk_proofs.clone()),
-                                &**validator_signer,
-                            )),
-                        ));
-                    }
-                    near_chain::Error::InvalidChunkState(chunk_state) => {
+            let challenge_body = match &result {
+                Err(near_chain::Error::InvalidChunkProofs(chunk_proofs)) => {
+                    Some(ChallengeBody::ChunkProofs(*chunk_proofs.clone()))
+                }
+                Err(near_chain::Error::InvalidChunkState(chunk_state)) => {
+                    Some(ChallengeBody::ChunkState(*chunk_state.clone()))
+                }
+                _ => None,
+            };
+            if let Some(challenge_body) = challenge_body {
+                match Challenge::produce(challenge_body, &**validator_signer) {
+                    Ok(challenge) => {
                         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkState(*chunk_state.clone()),
-                                &**validator_signer,
-                            )),
+                            NetworkRequests::Challenge(challenge),
                         ));
                     }
-                    _ => {}
+                    Err(err) => warn!(target: "client", %err, "Failed to sign challenge"),
                 }
             }
         }
@@ -1308,6 +1615,7 @@ impl Client {
                 !should_produce_chunk,
             );
         }
+        self.send_pending_chunk_state_witnesses();
         self.last_time_head_progress_made =
             max(self.chain.get_last_time_head_updated(), self.last_time_head_progress_made);
         (accepted_blocks_hashes, errors)
@@ -1326,6 +1634,7 @@ impl Client {
             blocks_missing_chunks,
             challenges,
             invalid_chunks,
+            state_transitions,
         } = block_processing_artifacts;
         // Send out challenges that accumulated via on_challenge.
         self.send_challenges(challenges);
@@ -1348,6 +1657,11 @@ impl Client {
                 error!(target: "client", ?err, "Failed to ban chunk producer for producing invalid chunk");
             }
         }
+
+        if !state_transitions.is_empty() {
+            self.chunk_witness_sender
+                .send(ChunkWitnessRequest::StateTransitionsRecorded(state_transitions));
+        }
     }
 
     fn ban_chunk_producer_for_producing_invalid_chunk(
@@ -1392,6 +1706,13 @@ impl Client {
         self.chain.blocks_delay_tracker.mark_chunk_completed(&chunk_header, StaticClock::utc());
         self.block_production_info
             .record_chunk_collected(partial_chunk.height_created(), partial_chunk.shard_id());
+        if let Some(shard_chunk) = &shard_chunk {
+            if self.config.speculative_contract_compilation {
+                if let Err(err) = self.precompile_chunk_contracts(shard_chunk) {
+                    debug!(target: "client", ?err, "Failed to compile the contracts of the chunk");
+                }
+            }
+        }
         persist_chunk(partial_chunk, shard_chunk, self.chain.mut_chain_store())
             .expect("Could not persist chunk");
         // We're marking chunk as accepted.
@@ -1400,6 +1721,57 @@ impl Client {
         self.process_blocks_with_missing_chunks(apply_chunks_done_callback)
     }
 
+    /// Starts compiling in the background the contracts called by the
+    /// transactions and the outgoing receipts of a chunk, which are applied
+    /// with the next blocks, so that the compilation doesn't delay the
+    /// application of the chunks. The contracts are looked up in the state as
+    /// of the head, because the state the chunks are applied to isn't known
+    /// yet, and contracts seldom change.
+    fn precompile_chunk_contracts(&self, chunk: &ShardChunk) -> Result<(), Error> {
+        let is_function_call = |action: &Action| matches!(action, Action::FunctionCall(_));
+        let mut receiver_ids = HashSet::new();
+        for tx in chunk.transactions() {
+            if tx.transaction.actions.iter().any(is_function_call) {
+                receiver_ids.insert(&tx.transaction.receiver_id);
+            }
+        }
+        for receipt in chunk.prev_outgoing_receipts() {
+            if let ReceiptEnum::Action(action_receipt) = &receipt.receipt {
+                if action_receipt.actions.iter().any(is_function_call) {
+                    receiver_ids.insert(&receipt.receiver_id);
+                }
+            }
+        }
+        if receiver_ids.is_empty() {
+            return Ok(());
+        }
+
+        let head = self.chain.head()?;
+        let me = self.validator_signer.as_ref().map(|signer| signer.validator_id());
+        let mut account_ids_by_shard: HashMap<ShardId, Vec<AccountId>> = HashMap::new();
+        for account_id in receiver_ids {
+            let shard_id = self.epoch_manager.account_id_to_shard_id(account_id, &head.epoch_id)?;
+            account_ids_by_shard.entry(shard_id).or_default().push(account_id.clone());
+        }
+        for (shard_id, account_ids) in account_ids_by_shard {
+            if !self.shard_tracker.care_about_shard(me, &head.last_block_hash, shard_id, true) {
+                continue;
+            }
+            let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &head.epoch_id)?;
+            let Ok(chunk_extra) = self.chain.get_chunk_extra(&head.last_block_hash, &shard_uid)
+            else {
+                continue;
+            };
+            self.runtime_adapter.precompile_contracts_speculatively(
+                &head.epoch_id,
+                shard_uid,
+                *chunk_extra.state_root(),
+                account_ids,
+            );
+        }
+        Ok(())
+    }
+
     /// Called asynchronously when the ShardsManager finishes processing a chunk but the chunk
     /// is invalid.
     pub fn on_invalid_chunk(&mut self, encoded_chunk: EncodedShardChunk) {
@@ -1421,7 +1793,13 @@ impl Client {
         self.prev_block_to_chunk_headers_ready_for_inclusion
             .get_mut(prev_block_hash)
             .unwrap()
-            .insert(chunk_header.shard_id(), (chunk_header, chrono::Utc::now(), chunk_producer));
+            .insert(
+                chunk_header.shard_id(),
+                (chunk_header.clone(), chrono::Utc::now(), chunk_producer.clone()),
+            );
+        if let Err(err) = self.await_chunk_state_witness(&chunk_header, chunk_producer) {
+            tracing::debug!(target: "chunk_validation", chunk_hash=?chunk_header.chunk_hash(), ?err, "Failed to check whether to await chunk state witness");
+        }
     }
 
     pub fn sync_block_headers(
@@ -1585,6 +1963,11 @@ impl Client {
                 self.chain.get_block_header(last_final_block).map_or(0, |header| header.height())
             };
             self.chain.blocks_with_missing_chunks.prune_blocks_below_height(last_finalized_height);
+            // Once a challenge is included in the canonical chain its validator is slashed, so
+            // including it again would make the block invalid.
+            for challenge in block.challenges() {
+                self.challenges.remove(&challenge.hash);
+            }
 
             {
                 let _span = tracing::debug_span!(
@@ -2209,7 +2592,25 @@ impl Client {
             } else {
                 // Transactions only need to be recorded if the node is a validator.
                 if me.is_some() {
-                    match self.sharded_tx_pool.insert_transaction(shard_uid, tx.clone()) {
+                    // Lets the pool hold back a transaction which skips nonces after the nonce of
+                    // its access key, even if it's the first transaction of the key in the pool.
+                    let state_update = self
+                        .runtime_adapter
+                        .get_tries()
+                        .new_trie_update_view(shard_uid, state_root);
+                    let access_key_nonce = get_access_key(
+                        &state_update,
+                        &tx.transaction.signer_id,
+                        &tx.transaction.public_key,
+                    )
+                    .ok()
+                    .flatten()
+                    .map(|access_key| access_key.nonce);
+                    match self.sharded_tx_pool.insert_transaction(
+                        shard_uid,
+                        tx.clone(),
+                        access_key_nonce,
+                    ) {
                         InsertTransactionResult::Success => {
                             trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Recorded a transaction.");
                         }
@@ -2224,6 +2625,9 @@ impl Client {
                                 trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Transaction pool is full, trying to forward the transaction.");
                             }
                         }
+                        InsertTransactionResult::AccountLimitReached => {
+                            trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Signer reached its limits in the transaction pool.");
+                        }
                     }
                 }
 
@@ -2289,6 +2693,7 @@ impl Client {
     pub fn run_catchup(
         &mut self,
         highest_height_peers: &[HighestHeightPeerInfo],
+        snapshot_hosts: &[Arc<SnapshotHostInfo>],
         state_parts_task_scheduler: &dyn Fn(ApplyStatePartsRequest),
         block_catch_up_task_scheduler: &dyn Fn(BlockCatchUpRequest),
         resharding_scheduler: &dyn Fn(ReshardingRequest),
@@ -2316,6 +2721,7 @@ impl Client {
                             state_sync_timeout,
                             &self.config.chain_id,
                             &self.config.state_sync.sync,
+                            self.config.state_sync_num_concurrent_requests,
                             true,
                         ),
                         shards_to_split,
@@ -2361,6 +2767,7 @@ impl Client {
                 &mut self.chain,
                 self.epoch_manager.as_ref(),
                 highest_height_peers,
+                snapshot_hosts,
                 tracking_shards,
                 state_parts_task_scheduler,
                 resharding_scheduler,
@@ -2451,32 +2858,74 @@ impl Client {
     }
 
     /// When accepting challenge, we verify that it's valid given signature with current validators.
-    pub fn process_challenge(&mut self, _challenge: Challenge) -> Result<(), Error> {
-        // TODO(2445): Enable challenges when they are working correctly.
-        //        if self.challenges.contains_key(&challenge.hash) {
-        //            return Ok(());
-        //        }
-        //        debug!(target: "client", "Received challenge: {:?}", challenge);
-        //        let head = self.chain.head()?;
-        //        if self.runtime_adapter.verify_validator_or_fisherman_signature(
-        //            &head.epoch_id,
-        //            &head.prev_block_hash,
-        //            &challenge.account_id,
-        //            challenge.hash.as_ref(),
-        //            &challenge.signature,
-        //        )? {
-        //            // If challenge is not double sign, we should process it right away to invalidate the chain.
-        //            match challenge.body {
-        //                ChallengeBody::BlockDoubleSign(_) => {}
-        //                _ => {
-        //                    self.chain.process_challenge(&challenge);
-        //                }
-        //            }
-        //            self.challenges.insert(challenge.hash, challenge);
-        //        }
+    /// Only double signed chunk endorsements are accepted, to be included in the blocks we
+    /// produce, see `ProtocolFeature::ChunkEndorsementSlashing`.
+    pub fn process_challenge(&mut self, challenge: Challenge) -> Result<(), Error> {
+        // TODO(2445): Enable the other challenges when they are working correctly.
+        if !matches!(challenge.body, ChallengeBody::ChunkEndorsementDoubleSign(_))
+            || self.challenges.contains_key(&challenge.hash)
+        {
+            return Ok(());
+        }
+        debug!(
+            target: "client",
+            hash = ?challenge.hash,
+            account_id = %challenge.account_id,
+            "Received challenge",
+        );
+        let head = self.chain.head()?;
+        validate_challenge(
+            self.epoch_manager.as_ref(),
+            self.runtime_adapter.as_ref(),
+            &head.epoch_id,
+            &head.last_block_hash,
+            &challenge,
+        )?;
+        self.challenges.insert(challenge.hash, challenge);
         Ok(())
     }
 
+    /// Returns the challenges to include in a block produced on top of `prev_hash` in
+    /// `epoch_id`. Only double signed chunk endorsements are included, once
+    /// `ChunkEndorsementSlashing` is enabled, and only if they are still valid and their
+    /// validator isn't slashed yet.
+    fn challenges_for_block(
+        &self,
+        epoch_id: &EpochId,
+        prev_hash: &CryptoHash,
+        protocol_version: ProtocolVersion,
+    ) -> Result<Challenges, Error> {
+        // TODO(2445): Enable the other challenges when they are working correctly.
+        if !checked_feature!("stable", ChunkEndorsementSlashing, protocol_version) {
+            return Ok(vec![]);
+        }
+        let prev_block_info = self.epoch_manager.get_block_info(prev_hash)?;
+        let mut slashed = prev_block_info.slashed().keys().cloned().collect::<HashSet<_>>();
+        let mut challenges = vec![];
+        for challenge in self.challenges.values() {
+            if !matches!(challenge.body, ChallengeBody::ChunkEndorsementDoubleSign(_)) {
+                continue;
+            }
+            match validate_challenge(
+                self.epoch_manager.as_ref(),
+                self.runtime_adapter.as_ref(),
+                epoch_id,
+                prev_hash,
+                challenge,
+            ) {
+                Ok((_, account_ids)) => {
+                    if account_ids.into_iter().all(|account_id| slashed.insert(account_id)) {
+                        challenges.push(challenge.clone());
+                    }
+                }
+                Err(err) => {
+                    debug!(target: "client", hash = ?challenge.hash, ?err, "Skipping challenge");
+                }
+            }
+        }
+        Ok(challenges)
+    }
+
     /// Check updates from background flat storage creation processes and possibly update
     /// creation statuses. Returns boolean indicating if all flat storages are created or
     /// creation is not needed.
@@ -2491,10 +2940,11 @@ impl Client {
     }
 
     fn clear_data(&mut self) -> Result<(), near_chain::Error> {
+        let me = self.validator_signer.as_ref().map(|signer| signer.validator_id().clone());
         // A RPC node should do regular garbage collection.
         if !self.config.archive {
             let tries = self.runtime_adapter.get_tries();
-            return self.chain.clear_data(tries, &self.config.gc);
+            return self.chain.clear_data(tries, &self.config.gc, me.as_ref());
         }
 
         // An archival node with split storage should perform garbage collection
@@ -2505,7 +2955,7 @@ impl Client {
         let kind = store.get_db_kind()?;
         if kind == Some(DbKind::Hot) {
             let tries = self.runtime_adapter.get_tries();
-            return self.chain.clear_data(tries, &self.config.gc);
+            return self.chain.clear_data(tries, &self.config.gc, me.as_ref());
         }
 
         // An archival node with legacy storage or in the midst of migration to split
@@ -2542,7 +2992,7 @@ impl Client {
 
 impl Client {
     /// Each epoch defines a set of important accounts: block producers, chunk producers,
-    /// approvers. Low-latency reliable communication between those accounts is critical,
+    /// chunk validators, approvers. Low-latency reliable communication between those accounts is critical,
     /// so that the blocks can be produced on time. This function computes the set of
     /// important accounts (aka TIER1 accounts) so that it can be fed to PeerManager, which
     /// will take care of the traffic prioritization.
@@ -2578,6 +3028,9 @@ impl Client {
         // require some tuning in the future. In particular, if we decide that connecting to
         // block & chunk producers of the next expoch is too expensive, we can postpone it
         // till almost the end of this epoch.
+        //
+        // Chunk validators of this and the next epoch are included as well, so that chunk
+        // state witnesses and chunk endorsements can be sent over direct connections.
         let mut account_keys = AccountKeys::new();
         for epoch_id in [&tip.epoch_id, &tip.next_epoch_id] {
             // We assume here that calls to get_epoch_chunk_producers and get_epoch_block_producers_ordered
@@ -2600,6 +3053,12 @@ impl Client {
                     .or_default()
                     .insert(bp.public_key().clone());
             }
+            for cv in self.epoch_manager.get_epoch_chunk_validators(epoch_id)? {
+                account_keys
+                    .entry(cv.account_id().clone())
+                    .or_default()
+                    .insert(cv.public_key().clone());
+            }
         }
         let account_keys = Arc::new(account_keys);
         self.tier1_accounts_cache = Some((tip.epoch_id.clone(), account_keys.clone()));
@@ -2650,11 +3109,24 @@ impl Client {
         for (sync_hash, (_, shard_sync_state, block_catchup_state)) in
             self.catchup_state_syncs.iter()
         {
-            let sync_block_height = self.chain.get_block_header(sync_hash)?.height();
-            let shard_sync_status: HashMap<_, _> = shard_sync_state
-                .iter()
-                .map(|(shard_id, state)| (*shard_id, state.status.to_string()))
-                .collect();
+            let sync_block_header = self.chain.get_block_header(sync_hash)?;
+            let sync_block_height = sync_block_header.height();
+            let shard_layout = self.epoch_manager.get_shard_layout(sync_block_header.epoch_id())?;
+            let store = self.chain.chain_store().store();
+            let mut shard_sync_status = HashMap::new();
+            for (shard_id, state) in shard_sync_state {
+                let mut status = state.status.to_string();
+                if matches!(state.status, ShardSyncStatus::ReshardingApplying) {
+                    let shard_uid = ShardUId::from_shard_id_and_layout(*shard_id, &shard_layout);
+                    if let Some(progress) = get_resharding_progress(store, shard_uid, sync_hash)? {
+                        status = format!(
+                            "{status} ({} batches, {} bytes)",
+                            progress.num_batches, progress.num_bytes,
+                        );
+                    }
+                }
+                shard_sync_status.insert(*shard_id, status);
+            }
             ret.push(CatchupStatusView {
                 sync_block_hash: *sync_hash,
                 sync_block_height,
@@ -2677,3 +3149,14 @@ impl Drop for Client {
             .stop_all();
     }
 }
+
+/// Returns the order in which chunk producers take transactions from the pool.
+fn pool_order(config: &ClientConfig, protocol_version: ProtocolVersion) -> PoolOrder {
+    if checked_feature!("stable", TransactionPriority, protocol_version) {
+        PoolOrder::Priority {
+            max_transactions_per_account: config.produce_chunk_max_transactions_per_account,
+        }
+    } else {
+        PoolOrder::RoundRobin
+    }
+}
@@ -5,25 +5,34 @@
 //! Unfortunately, this is not the case today. We are in the process of refactoring ClientActor
 //! https://github.com/near/nearcore/issues/7899
 
+#[cfg(feature = "new_epoch_sync")]
+use crate::adapter::EpochSyncResponse;
 use crate::adapter::{
-    BlockApproval, BlockHeadersResponse, BlockResponse, ChunkEndorsementMessage,
-    ChunkStateWitnessMessage, ProcessTxRequest, ProcessTxResponse, RecvChallenge, SetNetworkInfo,
-    StateResponse,
+    BlockApproval, BlockHeadersResponse, BlockResponse, ProcessTxRequest, ProcessTxResponse,
+    RecvChallenge, SetNetworkInfo, StateResponse,
 };
+use crate::chunk_endorsement_tracker::ChunkEndorsementPool;
+use crate::chunk_witness_actor::{ChunkWitnessProductionTracker, ChunkWitnessRequest};
 #[cfg(feature = "test_features")]
 use crate::client::AdvProduceBlocksMode;
 use crate::client::{Client, EPOCH_START_INFO_BLOCKS};
 use crate::config_updater::ConfigUpdater;
+use crate::consensus_log::{ConsensusLogWriter, ConsensusMessage};
 use crate::debug::new_network_info_view;
 use crate::info::{display_sync_status, InfoHelper};
+use crate::maintenance::MaintenanceScheduler;
+use crate::node_status_file::NodeStatusFile;
 use crate::sync::adapter::{SyncMessage, SyncShardInfo};
 use crate::sync::state::{StateSync, StateSyncResult};
 use crate::sync_jobs_actor::{create_sync_job_scheduler, SyncJobsActor};
+use crate::validator_failover::ValidatorFailover;
+use crate::view_client::ValidatorAccountId;
 use crate::{metrics, StatusResponse, SyncAdapter};
 use actix::{Actor, Addr, Arbiter, AsyncContext, Context, Handler};
 use actix_rt::ArbiterHandle;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
+use near_async::futures::RayonAsyncComputationSpawner;
 use near_async::messaging::{CanSend, Sender};
 use near_chain::chain::{
     ApplyStatePartsRequest, ApplyStatePartsResponse, BlockCatchUpRequest, BlockCatchUpResponse,
@@ -44,11 +53,14 @@ use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::logic::cares_about_shard_this_or_next_epoch;
 use near_client_primitives::types::{
-    Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, NetworkInfoResponse,
-    StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus,
+    Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, GetTxPoolStatus,
+    GetTxPoolStatusError, GetValidatorKeyStatus, NetworkInfoResponse, SetValidatorKeyAttached,
+    StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus, ValidatorKeyError,
+    ValidatorKeyStatus,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
+use near_network::state_witness::{ChunkEndorsementMessage, ChunkStateWitnessMessage};
 use near_network::types::ReasonForBan;
 use near_network::types::{
     NetworkInfo, NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest,
@@ -67,7 +79,7 @@ use near_primitives::unwrap_or_return;
 use near_primitives::utils::{from_timestamp, MaybeValidated};
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{DetailedDebugStatus, ValidatorInfo};
+use near_primitives::views::{DetailedDebugStatus, TxPoolStatusView, ValidatorInfo};
 #[cfg(feature = "test_features")]
 use near_store::DBCol;
 use near_store::ShardUId;
@@ -111,6 +123,10 @@ pub struct ClientActor {
     // Last time when log_summary method was called.
     log_summary_timer_next_attempt: DateTime<Utc>,
 
+    /// Writes the status of the node to a file, if configured.
+    node_status_file: Option<NodeStatusFile>,
+    node_status_file_next_attempt: DateTime<Utc>,
+
     block_production_started: bool,
     doomslug_timer_next_attempt: DateTime<Utc>,
     sync_timer_next_attempt: DateTime<Utc>,
@@ -129,6 +145,16 @@ pub struct ClientActor {
 
     /// Manages updating the config.
     config_updater: Option<ConfigUpdater>,
+
+    /// Shuts the node down for the declared maintenance.
+    maintenance_scheduler: MaintenanceScheduler,
+
+    /// Decides whether the node signs, if it shares the validator key with another node.
+    validator_failover: Option<ValidatorFailover>,
+    validator_failover_next_attempt: DateTime<Utc>,
+
+    /// Records the consensus messages received by the node, if configured.
+    consensus_log: Option<ConsensusLogWriter>,
 }
 
 /// Blocks the program until given genesis time arrives.
@@ -153,7 +179,7 @@ fn wait_until_genesis(genesis_time: &DateTime<Utc>) {
 
 impl ClientActor {
     pub fn new(
-        client: Client,
+        mut client: Client,
         address: Addr<ClientActor>,
         config: ClientConfig,
         node_id: PeerId,
@@ -179,6 +205,29 @@ impl ClientActor {
             info!(target: "client", "Starting validator node: {}", vs.validator_id());
         }
         let info_helper = InfoHelper::new(Some(telemetry_actor), &config, validator_signer.clone());
+        let node_status_file = config.node_status_file.clone().map(NodeStatusFile::new);
+        let validator_failover =
+            config.validator_failover.clone().map(ValidatorFailover::new).transpose().map_err(
+                |err| Error::Other(format!("Failed to bind the validator failover socket: {err}")),
+            )?;
+        let consensus_log = config.consensus_log_file.as_deref().and_then(|path| {
+            match ConsensusLogWriter::open(path) {
+                Ok(mut consensus_log) => {
+                    if let Ok(head) = client.chain.head() {
+                        consensus_log.record(ConsensusMessage::Started {
+                            head_height: head.height,
+                            head_hash: head.last_block_hash,
+                        });
+                    }
+                    Some(consensus_log)
+                }
+                Err(err) => {
+                    error!(target: "client", ?path, %err, "Failed to open the consensus log, not recording consensus messages");
+                    None
+                }
+            }
+        });
+        client.chunk_validator.set_adv_controls(adv.clone());
 
         let now = Utc::now();
         Ok(ClientActor {
@@ -198,11 +247,14 @@ impl ClientActor {
                 known_producers: vec![],
                 tier1_accounts_keys: vec![],
                 tier1_accounts_data: vec![],
+                snapshot_hosts: vec![],
             },
             last_validator_announce_time: None,
             info_helper,
             block_production_next_attempt: now,
             log_summary_timer_next_attempt: now,
+            node_status_file,
+            node_status_file_next_attempt: now,
             block_production_started: false,
             doomslug_timer_next_attempt: now,
             sync_timer_next_attempt: now,
@@ -222,6 +274,10 @@ impl ClientActor {
             fastforward_delta: 0,
             shutdown_signal,
             config_updater,
+            maintenance_scheduler: MaintenanceScheduler::new(),
+            validator_failover,
+            validator_failover_next_attempt: now,
+            consensus_log,
         })
     }
 }
@@ -230,6 +286,13 @@ impl Actor for ClientActor {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
+        // A read replica only serves the RPC requests, and never writes to the
+        // database, so none of the jobs is started.
+        if self.client.config.read_replica.is_some() {
+            info!(target: "client", "Running as a read replica");
+            return;
+        }
+
         self.start_flat_storage_creation(ctx);
 
         // Start syncing job.
@@ -268,7 +331,9 @@ impl ClientActor {
         f: impl FnOnce(&mut Self, Req) -> Res,
     ) -> Res {
         let (_span, msg) = handler_debug_span!(target: "client", msg, msg_type);
-        self.check_triggers(ctx);
+        if self.client.config.read_replica.is_none() {
+            self.check_triggers(ctx);
+        }
         let _span_inner = tracing::debug_span!(target: "client", "NetworkClientMessage").entered();
         metrics::CLIENT_MESSAGES_COUNT.with_label_values(&[msg_type]).inc();
         let timer =
@@ -289,6 +354,11 @@ pub enum NetworkAdversarialMessage {
     AdvDisableDoomslug,
     AdvGetSavedBlocks,
     AdvCheckStorageConsistency,
+    AdvProduceInvalidWitnesses(bool),
+    AdvWithholdChunkEndorsements(bool),
+    AdvDoubleEndorseChunks(bool),
+    /// Delay in milliseconds, 0 to distribute witnesses right away again.
+    AdvDelayWitnessDistribution(u64),
 }
 
 #[cfg(feature = "test_features")]
@@ -396,6 +466,26 @@ impl Handler<WithSpanContext<NetworkAdversarialMessage>> for ClientActor {
                     Some(store_validator.tests_done())
                 }
             }
+            NetworkAdversarialMessage::AdvProduceInvalidWitnesses(value) => {
+                info!(target: "adversary", value, "Setting production of invalid chunk state witnesses");
+                this.adv.set_produce_invalid_witnesses(value);
+                None
+            }
+            NetworkAdversarialMessage::AdvWithholdChunkEndorsements(value) => {
+                info!(target: "adversary", value, "Setting withholding of chunk endorsements");
+                this.adv.set_withhold_chunk_endorsements(value);
+                None
+            }
+            NetworkAdversarialMessage::AdvDoubleEndorseChunks(value) => {
+                info!(target: "adversary", value, "Setting double endorsement of chunks");
+                this.adv.set_double_endorse_chunks(value);
+                None
+            }
+            NetworkAdversarialMessage::AdvDelayWitnessDistribution(delay_ms) => {
+                info!(target: "adversary", delay_ms, "Setting delay of chunk state witness distribution");
+                this.adv.set_witness_distribution_delay_ms(delay_ms);
+                None
+            }
         })
     }
 }
@@ -411,6 +501,11 @@ impl Handler<WithSpanContext<ProcessTxRequest>> for ClientActor {
     ) -> Self::Result {
         self.wrap(msg, ctx, "ProcessTxRequest", |this: &mut Self, msg| {
             let ProcessTxRequest { transaction, is_forwarded, check_only } = msg;
+            // A read replica isn't connected to the network, so it can't
+            // forward the transaction to the validators.
+            if this.client.config.read_replica.is_some() {
+                return ProcessTxResponse::DoesNotTrackShard;
+            }
             this.client.process_tx(transaction, is_forwarded, check_only)
         })
     }
@@ -423,6 +518,11 @@ impl Handler<WithSpanContext<BlockResponse>> for ClientActor {
     fn handle(&mut self, msg: WithSpanContext<BlockResponse>, ctx: &mut Context<Self>) {
         self.wrap(msg, ctx, "BlockResponse", |this: &mut Self, msg|{
             let BlockResponse{ block, peer_id, was_requested } = msg;
+            this.record_consensus_message(|| ConsensusMessage::Block {
+                block: block.clone(),
+                peer_id: peer_id.clone(),
+                was_requested,
+            });
             info!(target: "client", block_height = block.header().height(), block_hash = ?block.header().hash(), "BlockResponse");
             let blocks_at_height = this
                 .client
@@ -491,11 +591,33 @@ impl Handler<WithSpanContext<BlockApproval>> for ClientActor {
         self.wrap(msg, ctx, "BlockApproval", |this, msg| {
             let BlockApproval(approval, peer_id) = msg;
             debug!(target: "client", "Receive approval {:?} from peer {:?}", approval, peer_id);
+            this.record_consensus_message(|| ConsensusMessage::Approval {
+                approval: approval.clone(),
+                peer_id: peer_id.clone(),
+            });
             this.client.collect_block_approval(&approval, ApprovalType::PeerApproval(peer_id));
         })
     }
 }
 
+#[cfg(feature = "new_epoch_sync")]
+impl Handler<WithSpanContext<EpochSyncResponse>> for ClientActor {
+    type Result = ();
+
+    #[perf]
+    fn handle(&mut self, msg: WithSpanContext<EpochSyncResponse>, ctx: &mut Context<Self>) {
+        self.wrap(msg, ctx, "EpochSyncResponse", |this, msg| {
+            let EpochSyncResponse { peer_id, epoch_id, epoch_sync_info } = msg;
+            this.client.epoch_sync.on_response(
+                &mut this.client.chain,
+                peer_id,
+                epoch_id,
+                epoch_sync_info,
+            );
+        })
+    }
+}
+
 /// StateResponse is used during StateSync and catchup.
 /// It contains either StateSync header information (that tells us how many parts there are etc) or a single part.
 impl Handler<WithSpanContext<StateResponse>> for ClientActor {
@@ -705,6 +827,7 @@ impl Handler<WithSpanContext<Status>> for ClientActor {
                     .config
                     .min_block_production_delay
                     .as_millis() as u64,
+                chunk_validation_status: self.client.chunk_validation_status()?,
             })
         } else {
             None
@@ -899,13 +1022,18 @@ impl ClientActor {
         // Check client is part of the futures validators
         if self.client.is_validator(&next_epoch_id, &prev_block_hash) {
             debug!(target: "client", "Sending announce account for {}", validator_signer.validator_id());
-            self.last_validator_announce_time = Some(now);
-
-            let signature = validator_signer.sign_account_announce(
+            let signature = match validator_signer.sign_account_announce(
                 validator_signer.validator_id(),
                 &self.node_id,
                 &next_epoch_id,
-            );
+            ) {
+                Ok(signature) => signature,
+                Err(err) => {
+                    warn!(target: "client", %err, "Failed to sign the account announcement");
+                    return;
+                }
+            };
+            self.last_validator_announce_time = Some(now);
             self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::AnnounceAccount(AnnounceAccount {
                     account_id: validator_signer.validator_id().clone(),
@@ -1111,9 +1239,12 @@ impl ClientActor {
     fn check_triggers(&mut self, ctx: &mut Context<ClientActor>) -> Duration {
         let _span = tracing::debug_span!(target: "client", "check_triggers").entered();
         if let Some(config_updater) = &mut self.config_updater {
-            config_updater.try_update(&|updateable_client_config| {
+            let validator_signer = config_updater.try_update(&|updateable_client_config| {
                 self.client.update_client_config(updateable_client_config)
             });
+            if let Some(validator_signer) = validator_signer {
+                self.client.update_validator_signer(validator_signer);
+            }
         }
 
         // Check block height to trigger expected shutdown
@@ -1128,6 +1259,18 @@ impl ClientActor {
             }
         }
 
+        // Check whether the maintenance window starts at the next height
+        match self.maintenance_scheduler.should_shut_down(&self.client) {
+            Ok(true) => {
+                info!(target: "client", "Shutdown for maintenance triggered");
+                if let Some(tx) = self.shutdown_signal.take() {
+                    let _ = tx.send(());
+                }
+            }
+            Ok(false) => {}
+            Err(err) => debug!(target: "client", ?err, "Failed to schedule maintenance"),
+        }
+
         self.try_process_unfinished_blocks();
 
         let mut delay = Duration::from_secs(1);
@@ -1185,6 +1328,13 @@ impl ClientActor {
             )
         }
 
+        if let Some(next_flush) = self.client.chunk_validator.flush_chunk_endorsements() {
+            delay = core::cmp::min(delay, next_flush);
+        }
+        if let Some(next_request) = self.client.request_missing_chunk_state_witnesses() {
+            delay = core::cmp::min(delay, next_request);
+        }
+
         self.log_summary_timer_next_attempt = self.run_timer(
             self.client.config.log_summary_period,
             self.log_summary_timer_next_attempt,
@@ -1199,6 +1349,40 @@ impl ClientActor {
                 .to_std()
                 .unwrap_or(delay),
         );
+        if let Some(period) = self.node_status_file.as_ref().map(NodeStatusFile::period) {
+            self.node_status_file_next_attempt = self.run_timer(
+                period,
+                self.node_status_file_next_attempt,
+                ctx,
+                |act, _ctx| act.write_node_status_file(),
+                "node_status_file",
+            );
+            delay = core::cmp::min(
+                delay,
+                self.node_status_file_next_attempt
+                    .signed_duration_since(now)
+                    .to_std()
+                    .unwrap_or(delay),
+            );
+        }
+        if let Some(period) =
+            self.validator_failover.as_ref().map(ValidatorFailover::heartbeat_period)
+        {
+            self.validator_failover_next_attempt = self.run_timer(
+                period,
+                self.validator_failover_next_attempt,
+                ctx,
+                |act, _ctx| act.run_validator_failover(),
+                "validator_failover",
+            );
+            delay = core::cmp::min(
+                delay,
+                self.validator_failover_next_attempt
+                    .signed_duration_since(now)
+                    .to_std()
+                    .unwrap_or(delay),
+            );
+        }
         timer.observe_duration();
         delay
     }
@@ -1265,6 +1449,7 @@ impl ClientActor {
     fn produce_block(&mut self, next_height: BlockHeight) -> Result<(), Error> {
         let _span = tracing::debug_span!(target: "client", "produce_block", next_height).entered();
         if let Some(block) = self.client.produce_block(next_height)? {
+            self.record_consensus_message(|| ConsensusMessage::ProducedBlock(block.clone()));
             // If we produced the block, send it out before we apply the block.
             self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::Block { block: block.clone() },
@@ -1513,6 +1698,7 @@ impl ClientActor {
             let _span = tracing::debug_span!(target: "client", "catchup").entered();
             if let Err(err) = self.client.run_catchup(
                 &self.network_info.highest_height_peers,
+                &self.network_info.snapshot_hosts,
                 &self.state_parts_task_scheduler,
                 &self.block_catch_up_scheduler,
                 &self.resharding_scheduler,
@@ -1609,6 +1795,18 @@ impl ClientActor {
                 if !currently_syncing {
                     info!(target: "client", ?sync, "enabling sync");
                 }
+                // Headers of finished epochs are synced by epoch sync first.
+                #[cfg(feature = "new_epoch_sync")]
+                if self.client.config.epoch_sync_enabled
+                    && unwrap_and_report!(self.client.epoch_sync.run(
+                        &mut self.client.sync_status,
+                        &self.client.chain,
+                        highest_height,
+                        &self.network_info.highest_height_peers
+                    ))
+                {
+                    return;
+                }
                 // Run each step of syncing separately.
                 unwrap_and_report!(self.client.header_sync.run(
                     &mut self.client.sync_status,
@@ -1747,6 +1945,7 @@ impl ClientActor {
                         &mut self.client.chain,
                         self.client.epoch_manager.as_ref(),
                         &self.network_info.highest_height_peers,
+                        &self.network_info.snapshot_hosts,
                         shards_to_sync,
                         &self.state_parts_task_scheduler,
                         &self.resharding_scheduler,
@@ -1828,6 +2027,57 @@ impl ClientActor {
         )
     }
 
+    fn validator_key_status(&self) -> Result<ValidatorKeyStatus, ValidatorKeyError> {
+        let validator_signer =
+            self.client.any_validator_signer().ok_or(ValidatorKeyError::NoValidatorKey)?;
+        Ok(ValidatorKeyStatus {
+            account_id: validator_signer.validator_id().clone(),
+            public_key: validator_signer.public_key(),
+            attached: self.client.validator_signer.is_some(),
+            signed_heights: self.client.validator_signed_heights(),
+        })
+    }
+
+    fn run_validator_failover(&mut self) {
+        let Some(validator_failover) = &mut self.validator_failover else {
+            return;
+        };
+        let sign = validator_failover.run(&self.client);
+        // The other node must have stopped signing, but may have signed at heights this node
+        // didn't see yet.
+        let peer_signed_heights = validator_failover.peer_signed_heights();
+        match sign {
+            Some(true) => match self.client.attach_validator_signer(peer_signed_heights) {
+                Ok(true) => self.block_production_started = true,
+                Ok(false) => {}
+                Err(err) => {
+                    tracing::error!(target: "client", ?err, "Failed to attach the validator key")
+                }
+            },
+            Some(false) => {
+                self.client.detach_validator_signer();
+            }
+            None => {}
+        }
+    }
+
+    /// Records a consensus message received by the node, if configured.
+    fn record_consensus_message(&mut self, message: impl FnOnce() -> ConsensusMessage) {
+        if let Some(consensus_log) = &mut self.consensus_log {
+            consensus_log.record(message());
+        }
+    }
+
+    /// Writes the status file, with the same head age limit as the health
+    /// check of `Status`.
+    fn write_node_status_file(&mut self) {
+        if let Some(node_status_file) = &self.node_status_file {
+            let max_head_age =
+                self.client.config.max_block_production_delay * STATUS_WAIT_TIME_MULTIPLIER as u32;
+            node_status_file.write(&self.client, max_head_age);
+        }
+    }
+
     /// Checks if the node is syncing its State and applies special logic in that case.
     /// A node usually ignores blocks that are too far ahead, but in case of a node syncing its state it is looking for 2 specific blocks:
     /// * The first block of the new epoch
@@ -1945,6 +2195,10 @@ impl Handler<WithSpanContext<ShardsManagerResponse>> for ClientActor {
         let (_span, msg) = handler_debug_span!(target: "client", msg);
         match msg {
             ShardsManagerResponse::ChunkCompleted { partial_chunk, shard_chunk } => {
+                self.record_consensus_message(|| ConsensusMessage::ChunkCompleted {
+                    partial_chunk: partial_chunk.clone(),
+                    shard_chunk: shard_chunk.clone(),
+                });
                 self.client.on_chunk_completed(
                     partial_chunk,
                     shard_chunk,
@@ -1980,6 +2234,62 @@ impl Handler<WithSpanContext<GetClientConfig>> for ClientActor {
     }
 }
 
+impl Handler<WithSpanContext<GetValidatorKeyStatus>> for ClientActor {
+    type Result = Result<ValidatorKeyStatus, ValidatorKeyError>;
+
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetValidatorKeyStatus>,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        self.validator_key_status()
+    }
+}
+
+impl Handler<WithSpanContext<SetValidatorKeyAttached>> for ClientActor {
+    type Result = Result<ValidatorKeyStatus, ValidatorKeyError>;
+
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<SetValidatorKeyAttached>,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        if msg.attached {
+            match self.client.attach_validator_signer(msg.signed_heights) {
+                // A node started with its key detached hasn't started the block production.
+                Ok(true) => self.block_production_started = true,
+                Ok(false) => {}
+                Err(err) => {
+                    return Err(ValidatorKeyError::InternalError { error_message: err.to_string() })
+                }
+            }
+        } else {
+            self.client.detach_validator_signer();
+        }
+        self.validator_key_status()
+    }
+}
+
+impl Handler<WithSpanContext<GetTxPoolStatus>> for ClientActor {
+    type Result = Result<TxPoolStatusView, GetTxPoolStatusError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetTxPoolStatus>,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+
+        Ok(self.client.get_tx_pool_status(msg.account_id.as_ref())?)
+    }
+}
+
 impl Handler<WithSpanContext<SyncMessage>> for ClientActor {
     type Result = ();
 
@@ -2002,6 +2312,7 @@ impl Handler<WithSpanContext<ChunkStateWitnessMessage>> for ClientActor {
         _: &mut Context<Self>,
     ) -> Self::Result {
         let (_span, msg) = handler_debug_span!(target: "client", msg);
+        self.record_consensus_message(|| ConsensusMessage::chunk_state_witness(msg.0.clone()));
         if let Err(err) = self.client.process_chunk_state_witness(msg.0) {
             tracing::error!(target: "client", ?err, "Error processing chunk state witness");
         }
@@ -2018,6 +2329,7 @@ impl Handler<WithSpanContext<ChunkEndorsementMessage>> for ClientActor {
         _: &mut Context<Self>,
     ) -> Self::Result {
         let (_span, msg) = handler_debug_span!(target: "client", msg);
+        self.record_consensus_message(|| ConsensusMessage::ChunkEndorsement(msg.0.clone()));
         if let Err(err) = self.client.process_chunk_endorsement(msg.0) {
             tracing::error!(target: "client", ?err, "Error processing chunk endorsement");
         }
@@ -2042,6 +2354,10 @@ pub fn start_client(
     state_sync_adapter: Arc<RwLock<SyncAdapter>>,
     network_adapter: PeerManagerAdapter,
     shards_manager_adapter: Sender<ShardsManagerRequestFromClient>,
+    chunk_witness_sender: Sender<ChunkWitnessRequest>,
+    chunk_witness_production_tracker: ChunkWitnessProductionTracker,
+    chunk_endorsements: ChunkEndorsementPool,
+    validator_account_id: ValidatorAccountId,
     validator_signer: Option<Arc<dyn ValidatorSigner>>,
     telemetry_actor: Addr<TelemetryActor>,
     snapshot_callbacks: Option<SnapshotCallbacks>,
@@ -2062,10 +2378,15 @@ pub fn start_client(
         runtime,
         network_adapter.clone(),
         shards_manager_adapter,
+        chunk_witness_sender,
+        chunk_witness_production_tracker,
+        chunk_endorsements,
+        validator_account_id,
         validator_signer.clone(),
         true,
         random_seed_from_thread(),
         snapshot_callbacks,
+        Arc::new(RayonAsyncComputationSpawner),
     )
     .unwrap();
     let resharding_handle = client.chain.resharding_handle.clone();
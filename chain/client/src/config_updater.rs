@@ -1,5 +1,6 @@
 use near_chain_configs::UpdateableClientConfig;
 use near_dyn_configs::{UpdateableConfigLoaderError, UpdateableConfigs};
+use near_primitives::validator_signer::ValidatorSigner;
 use std::sync::Arc;
 use tokio::sync::broadcast::Receiver;
 
@@ -24,7 +25,14 @@ impl ConfigUpdater {
 
     /// Check if any of the configs were updated.
     /// If they did, the receiver (rx_config_update) will contain a clone of the new configs.
-    pub fn try_update(&mut self, update_client_config_fn: &dyn Fn(UpdateableClientConfig)) {
+    /// Returns the most recently loaded validator signer, which is None if the
+    /// validator key was removed, so that the caller can switch to it. Returns
+    /// None if no configs were loaded.
+    pub fn try_update(
+        &mut self,
+        update_client_config_fn: &dyn Fn(UpdateableClientConfig),
+    ) -> Option<Option<Arc<dyn ValidatorSigner>>> {
+        let mut validator_signer = None;
         while let Ok(maybe_updateable_configs) = self.rx_config_update.try_recv() {
             match maybe_updateable_configs {
                 Ok(updateable_configs) => {
@@ -32,6 +40,7 @@ impl ConfigUpdater {
                         update_client_config_fn(client_config);
                         tracing::info!(target: "config", "Updated ClientConfig");
                     }
+                    validator_signer = Some(updateable_configs.validator_signer);
                     self.updateable_configs_error = None;
                 }
                 Err(err) => {
@@ -39,6 +48,7 @@ impl ConfigUpdater {
                 }
             }
         }
+        validator_signer
     }
 
     /// Prints an error if it's present.
@@ -0,0 +1,238 @@
+//! Recording of the consensus messages received by the node, so that a bug seen on a live network
+//! can be reproduced by replaying them into a client started from a copy of the database of the
+//! node, see `neard replay`.
+//!
+//! The log is a sequence of entries, each written as its length as a little-endian `u32`
+//! followed by the borsh-serialized `ConsensusLogEntry`.
+
+use crate::Client;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_chain::test_utils::wait_for_all_blocks_in_processing;
+use near_chain::{DoneApplyChunkCallback, Provenance};
+use near_primitives::block::{Approval, Block};
+use near_primitives::block_header::ApprovalType;
+use near_primitives::chunk_validation::{
+    ChunkEndorsement, ChunkStateWitness, WitnessTransitionsBase,
+};
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::sharding::{PartialEncodedChunk, ShardChunk};
+use near_primitives::static_clock::StaticClock;
+use near_primitives::types::BlockHeight;
+use near_primitives::utils::MaybeValidated;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Message that changed the consensus state of the client.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusMessage {
+    /// Written when the node starts recording, with its head at that time.
+    /// The log should be replayed on a copy of the database taken then.
+    Started {
+        head_height: BlockHeight,
+        head_hash: CryptoHash,
+    },
+    Block {
+        block: Block,
+        peer_id: PeerId,
+        was_requested: bool,
+    },
+    /// Block produced by the node itself, which it never receives from its
+    /// peers.
+    ProducedBlock(Block),
+    Approval {
+        approval: Approval,
+        peer_id: PeerId,
+    },
+    /// Chunk reconstructed by the shards manager.
+    ChunkCompleted {
+        partial_chunk: PartialEncodedChunk,
+        shard_chunk: Option<ShardChunk>,
+    },
+    ChunkStateWitness(ChunkStateWitness),
+    ChunkEndorsement(ChunkEndorsement),
+    /// Incremental witness, whose transitions base isn't part of the borsh
+    /// encoding of the witness.
+    IncrementalChunkStateWitness(ChunkStateWitness, WitnessTransitionsBase),
+}
+
+impl ConsensusMessage {
+    pub fn chunk_state_witness(witness: ChunkStateWitness) -> Self {
+        match witness.transitions_base.clone() {
+            Some(base) => ConsensusMessage::IncrementalChunkStateWitness(witness, base),
+            None => ConsensusMessage::ChunkStateWitness(witness),
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConsensusLogEntry {
+    /// When the message was received, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub message: ConsensusMessage,
+}
+
+/// Appends the consensus messages received by the node to the log.
+pub struct ConsensusLogWriter {
+    file: File,
+}
+
+impl ConsensusLogWriter {
+    /// Opens the log to append entries to, creating it if needed.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    pub fn record(&mut self, message: ConsensusMessage) {
+        let entry = ConsensusLogEntry {
+            timestamp_ms: StaticClock::utc().timestamp_millis() as u64,
+            message,
+        };
+        let data = borsh::to_vec(&entry).expect("entry is serializable");
+        let mut buf = Vec::with_capacity(4 + data.len());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&data);
+        // Each entry is written at once, so that no partial entries are left
+        // behind if the node is killed.
+        if let Err(err) = self.file.write_all(&buf) {
+            tracing::warn!(target: "client", %err, "Failed to record consensus message");
+        }
+    }
+}
+
+/// Reads the entries of the log in the order they were recorded.
+pub struct ConsensusLogReader {
+    reader: BufReader<File>,
+}
+
+impl ConsensusLogReader {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        Ok(Self { reader: BufReader::new(File::open(path)?) })
+    }
+
+    fn read_entry(&mut self) -> std::io::Result<Option<ConsensusLogEntry>> {
+        let mut len = [0; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        let mut data = vec![0; u32::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(ConsensusLogEntry::try_from_slice(&data)?))
+    }
+}
+
+impl Iterator for ConsensusLogReader {
+    type Item = std::io::Result<ConsensusLogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+impl Client {
+    /// Feeds a recorded message to the client the way the client actor does,
+    /// then waits for the blocks whose processing it started and finishes
+    /// processing them, so that every message is handled in the recorded
+    /// order on the same state. Timers of the client actor don't run, so the
+    /// client never produces blocks or chunks.
+    pub fn replay_consensus_message(&mut self, message: ConsensusMessage) {
+        let apply_chunks_done_callback: DoneApplyChunkCallback = Arc::new(|_| {});
+        match message {
+            ConsensusMessage::Started { .. } => {}
+            ConsensusMessage::Block { block, peer_id, was_requested } => self.receive_block(
+                block,
+                peer_id,
+                was_requested,
+                apply_chunks_done_callback.clone(),
+            ),
+            ConsensusMessage::ProducedBlock(block) => {
+                if let Err(err) = self.start_process_block(
+                    MaybeValidated::from_validated(block),
+                    Provenance::PRODUCED,
+                    apply_chunks_done_callback.clone(),
+                ) {
+                    tracing::debug!(target: "client", ?err, "Failed to process produced block");
+                }
+            }
+            ConsensusMessage::Approval { approval, peer_id } => {
+                self.collect_block_approval(&approval, ApprovalType::PeerApproval(peer_id))
+            }
+            ConsensusMessage::ChunkCompleted { partial_chunk, shard_chunk } => self
+                .on_chunk_completed(partial_chunk, shard_chunk, apply_chunks_done_callback.clone()),
+            ConsensusMessage::ChunkStateWitness(witness) => {
+                if let Err(err) = self.process_chunk_state_witness(witness) {
+                    tracing::error!(target: "client", ?err, "Error processing chunk state witness");
+                }
+            }
+            ConsensusMessage::IncrementalChunkStateWitness(mut witness, base) => {
+                witness.transitions_base = Some(base);
+                if let Err(err) = self.process_chunk_state_witness(witness) {
+                    tracing::error!(target: "client", ?err, "Error processing chunk state witness");
+                }
+            }
+            ConsensusMessage::ChunkEndorsement(endorsement) => {
+                if let Err(err) = self.process_chunk_endorsement(endorsement) {
+                    tracing::error!(target: "client", ?err, "Error processing chunk endorsement");
+                }
+            }
+        }
+        while wait_for_all_blocks_in_processing(&self.chain) {
+            let (_, errors) =
+                self.postprocess_ready_blocks(apply_chunks_done_callback.clone(), false);
+            for (block_hash, err) in errors {
+                tracing::error!(target: "client", ?block_hash, ?err, "Error processing block");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::KeyType;
+    use near_primitives::validator_signer::InMemoryValidatorSigner;
+
+    #[test]
+    fn test_record_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("consensus.log");
+        let signer =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let messages: Vec<_> = (1..4)
+            .map(|height| ConsensusMessage::Approval {
+                approval: Approval::new(CryptoHash::default(), height - 1, height, &signer)
+                    .unwrap(),
+                peer_id: PeerId::random(),
+            })
+            .collect();
+        let mut writer = ConsensusLogWriter::open(&path).unwrap();
+        writer
+            .record(ConsensusMessage::Started { head_height: 0, head_hash: CryptoHash::default() });
+        writer.record(messages[0].clone());
+        // Entries are appended to the existing ones when the node restarts.
+        let mut writer = ConsensusLogWriter::open(&path).unwrap();
+        writer.record(messages[1].clone());
+        writer.record(messages[2].clone());
+
+        let read: Vec<_> =
+            ConsensusLogReader::open(&path).unwrap().map(|entry| entry.unwrap().message).collect();
+        assert_eq!(
+            read[0],
+            ConsensusMessage::Started { head_height: 0, head_hash: CryptoHash::default() }
+        );
+        assert_eq!(read[1..], messages[..]);
+
+        // A truncated entry is reported as an error.
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 1).unwrap();
+        let mut reader = ConsensusLogReader::open(&path).unwrap();
+        assert_eq!(reader.by_ref().take(3).filter(|entry| entry.is_ok()).count(), 3);
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -179,6 +179,11 @@ impl Handler<WithSpanContext<DebugStatus>> for ClientActor {
             DebugStatus::ChainProcessingStatus => Ok(DebugStatusResponse::ChainProcessingStatus(
                 self.client.chain.get_chain_processing_info(),
             )),
+            DebugStatus::ChunkEndorsementStatus(chunk_hash) => {
+                Ok(DebugStatusResponse::ChunkEndorsementStatus(
+                    self.client.chunk_endorsement_status(&chunk_hash)?,
+                ))
+            }
         }
     }
 }
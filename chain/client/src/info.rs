@@ -1,4 +1,5 @@
 use crate::config_updater::ConfigUpdater;
+use crate::production_alerts::ProductionAlerts;
 use crate::{metrics, SyncStatus};
 use actix::Addr;
 use itertools::Itertools;
@@ -69,6 +70,8 @@ pub struct InfoHelper {
     enable_multiline_logging: bool,
     // Keeps track of the previous SyncRequirement for updating metrics.
     prev_sync_requirement: Option<String>,
+    /// Alerts when the validator of the node misses blocks or chunks, if configured.
+    production_alerts: Option<ProductionAlerts>,
 }
 
 impl InfoHelper {
@@ -79,6 +82,10 @@ impl InfoHelper {
     ) -> Self {
         set_open_files_limit(0);
         metrics::export_version(&client_config.version);
+        let production_alerts =
+            client_config.production_alerts.clone().zip(validator_signer.as_ref()).map(
+                |(config, signer)| ProductionAlerts::new(config, signer.validator_id().clone()),
+            );
         InfoHelper {
             nearcore_version: client_config.version.clone(),
             sys: System::new(),
@@ -94,6 +101,7 @@ impl InfoHelper {
             epoch_id: None,
             enable_multiline_logging: client_config.enable_multiline_logging,
             prev_sync_requirement: None,
+            production_alerts,
         }
     }
 
@@ -137,7 +145,8 @@ impl InfoHelper {
         }
     }
 
-    /// Count which shards are tracked by the node in the epoch indicated by head parameter.
+    /// Count which shards are tracked by the node in the epoch indicated by head parameter,
+    /// and in the epoch after it.
     fn record_tracked_shards(head: &Tip, client: &crate::client::Client) {
         let me = client.validator_signer.as_ref().map(|x| x.validator_id());
         if let Ok(shard_ids) = client.epoch_manager.shard_ids(&head.epoch_id) {
@@ -153,6 +162,19 @@ impl InfoHelper {
                     .set(if tracked { 1 } else { 0 });
             }
         }
+        if let Ok(shard_ids) = client.epoch_manager.shard_ids(&head.next_epoch_id) {
+            for shard_id in shard_ids {
+                let tracked = client.shard_tracker.will_care_about_shard(
+                    me,
+                    &head.last_block_hash,
+                    shard_id,
+                    true,
+                );
+                metrics::TRACKED_SHARDS_NEXT_EPOCH
+                    .with_label_values(&[&shard_id.to_string()])
+                    .set(if tracked { 1 } else { 0 });
+            }
+        }
     }
 
     fn record_block_producers(head: &Tip, client: &crate::client::Client) {
@@ -435,6 +457,10 @@ impl InfoHelper {
         (metrics::MEMORY_USAGE.set((memory_usage * 1024) as i64));
         (metrics::PROTOCOL_UPGRADE_BLOCK_HEIGHT.set(protocol_upgrade_block_height as i64));
 
+        if let Some(production_alerts) = &mut self.production_alerts {
+            production_alerts.check(head, &validator_epoch_stats);
+        }
+
         // In case we can't get the list of validators for the current and the previous epoch,
         // skip updating the per-validator metrics.
         // Note that the metrics are set to 0 for previous epoch validators who are no longer
@@ -533,10 +559,14 @@ impl InfoHelper {
         };
         // Sign telemetry if there is a signer present.
         if let Some(vs) = self.validator_signer.as_ref() {
-            vs.sign_telemetry(&info)
-        } else {
-            serde_json::to_value(&info).expect("Telemetry must serialize to json")
+            match vs.sign_telemetry(&info) {
+                Ok(signed) => return signed,
+                Err(err) => {
+                    tracing::warn!(target: "stats", %err, "Failed to sign telemetry, sending it unsigned")
+                }
+            }
         }
+        serde_json::to_value(&info).expect("Telemetry must serialize to json")
     }
 
     fn log_chain_processing_info(&mut self, client: &crate::Client, epoch_id: &EpochId) {
@@ -938,6 +968,7 @@ mod tests {
                 tier1_connections: vec![],
                 tier1_accounts_keys: vec![],
                 tier1_accounts_data: vec![],
+                snapshot_hosts: vec![],
             },
             &config,
             0.0,
@@ -1,37 +1,58 @@
 pub use near_client_primitives::types::{
-    Error, GetBlock, GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunk,
-    GetClientConfig, GetExecutionOutcome, GetExecutionOutcomeResponse,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
-    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetSplitStorageInfo, GetStateChanges,
-    GetStateChangesInBlock, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfo, GetValidatorOrdered, Query,
-    QueryError, Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
+    Error, EstimateTransaction, GetAccountOutcomesProof, GetBlock, GetBlockProof,
+    GetBlockProofResponse, GetBlockRange, GetBlockWithMerkleTree, GetChunk, GetChunkExecutionProof,
+    GetChunkStateWitness, GetClientConfig, GetEstimatedValidators, GetExecutionOutcome,
+    GetExecutionOutcomeResponse, GetExecutionOutcomesForBlock, GetGasPrice, GetMaintenanceWindows,
+    GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetReceiptWithOutcome,
+    GetScheduledMaintenanceWindow, GetSplitStorageInfo, GetStateChanges, GetStateChangesInBlock,
+    GetStateChangesWithCauseInBlock, GetStateChangesWithCauseInBlockForTrackedShards,
+    GetTxPoolStatus, GetValidatorInfo, GetValidatorKeyStatus, GetValidatorOrdered, Query,
+    QueryError, SetValidatorKeyAttached, Status, StatusResponse, SyncStatus, TxStatus,
+    TxStatusError,
 };
 
 pub use crate::adapter::{
     BlockApproval, BlockResponse, ProcessTxRequest, ProcessTxResponse, SetNetworkInfo,
 };
+pub use crate::chunk_endorsement_tracker::ChunkEndorsementPool;
+pub use crate::chunk_witness_actor::{
+    start_chunk_witness_actor, ChunkWitnessActor, ChunkWitnessProducer,
+    ChunkWitnessProductionTracker, ChunkWitnessRequest,
+};
 pub use crate::client::Client;
 #[cfg(feature = "test_features")]
 pub use crate::client_actor::NetworkAdversarialMessage;
 pub use crate::client_actor::{start_client, ClientActor};
 pub use crate::config_updater::ConfigUpdater;
 pub use crate::sync::adapter::{SyncAdapter, SyncMessage};
-pub use crate::view_client::{start_view_client, ViewClientActor};
+pub use crate::view_client::{start_view_client, ValidatorAccountId, ViewClientActor};
 pub use near_client_primitives::debug::DebugStatus;
 
 pub mod adapter;
 pub mod adversarial;
+mod chunk_endorsement_batcher;
+mod chunk_endorsement_tracker;
+mod chunk_state_witness_queue;
+mod chunk_state_witness_requester;
 mod chunk_validation;
+mod chunk_witness_actor;
 mod client;
 mod client_actor;
 mod config_updater;
+pub mod consensus_log;
 pub mod debug;
 mod info;
+mod maintenance;
 mod metrics;
+mod node_status_file;
+mod production_alerts;
+#[cfg(feature = "remote_chunk_validation")]
+mod remote_chunk_validation;
 pub mod sync;
 mod sync_jobs_actor;
 pub mod test_utils;
 #[cfg(test)]
 mod tests;
+mod validator_failover;
 mod view_client;
+mod witness_analytics;
@@ -0,0 +1,205 @@
+//! Scheduling of the maintenance of a validator: finding the nearest stretch of
+//! heights in which it has no block or chunk to produce and no chunk to
+//! validate, so that it can be restarted without missing any of its duties.
+
+use crate::client::Client;
+use near_chain::types::Tip;
+use near_chain_configs::MaintenanceWindowConfig;
+use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::checked_feature;
+use near_primitives::epoch_manager::epoch_info::EpochInfo;
+use near_primitives::errors::EpochError;
+use near_primitives::types::{AccountId, BlockHeight, BlockHeightDelta, EpochId, ShardId};
+use std::ops::Range;
+use std::sync::{mpsc, Arc};
+
+/// Returns whether the validator has a block or a chunk to produce, or a chunk
+/// to validate, at the height.
+pub(crate) fn has_duties(
+    epoch_info: &EpochInfo,
+    shard_ids: &[ShardId],
+    account_id: &AccountId,
+    height: BlockHeight,
+) -> bool {
+    let Some(&validator_id) = epoch_info.get_validator_id(account_id) else {
+        return false;
+    };
+    epoch_info.sample_block_producer(height) == validator_id
+        || shard_ids
+            .iter()
+            .any(|&shard_id| epoch_info.sample_chunk_producer(height, shard_id) == validator_id)
+        || (checked_feature!("stable", ChunkValidation, epoch_info.protocol_version())
+            && epoch_info
+                .sample_chunk_validators(height)
+                .iter()
+                .any(|validators| validators.contains_key(&validator_id)))
+}
+
+/// Returns the nearest stretch of at least `num_blocks` heights after the head
+/// in which the validator has no duties, looking at the epoch of the head and
+/// the next one. The stretch extends to the next duty of the validator, or to
+/// the end of the next epoch.
+pub(crate) fn find_maintenance_window(
+    epoch_manager: &dyn EpochManagerAdapter,
+    head: &Tip,
+    account_id: &AccountId,
+    num_blocks: BlockHeightDelta,
+) -> Result<Option<Range<BlockHeight>>, EpochError> {
+    let next_epoch_start = epoch_manager.get_epoch_start_height(&head.last_block_hash)?
+        + epoch_manager.get_epoch_config(&head.epoch_id)?.epoch_length;
+    let next_epoch_end =
+        next_epoch_start + epoch_manager.get_epoch_config(&head.next_epoch_id)?.epoch_length;
+    let epochs = [
+        (&head.epoch_id, head.height + 1..next_epoch_start),
+        (&head.next_epoch_id, next_epoch_start..next_epoch_end),
+    ];
+    let mut start = None;
+    for (epoch_id, heights) in epochs {
+        let epoch_info = epoch_manager.get_epoch_info(epoch_id)?;
+        let shard_ids = epoch_manager.shard_ids(epoch_id)?;
+        for height in heights {
+            if !has_duties(&epoch_info, &shard_ids, account_id, height) {
+                start.get_or_insert(height);
+                continue;
+            }
+            if let Some(start) = start.take() {
+                if height - start >= num_blocks {
+                    return Ok(Some(start..height));
+                }
+            }
+        }
+    }
+    Ok(start
+        .filter(|start| next_epoch_end - start >= num_blocks)
+        .map(|start| start..next_epoch_end))
+}
+
+/// Window found for the maintenance declared in the config.
+struct ScheduledMaintenance {
+    config: MaintenanceWindowConfig,
+    /// Epoch of the head when the window was searched.
+    epoch_id: EpochId,
+    window: Option<Range<BlockHeight>>,
+}
+
+/// Search for a maintenance window running in the background.
+struct MaintenanceSearch {
+    config: MaintenanceWindowConfig,
+    epoch_id: EpochId,
+    result: mpsc::Receiver<Result<Option<Range<BlockHeight>>, EpochError>>,
+}
+
+/// Decides when the node shuts down for the maintenance declared in the
+/// config, if it asks for an automatic restart. Searching for the window goes
+/// through every height of two epochs, so it runs in the background instead of
+/// blocking the client, and is only started again once the head reaches the
+/// window, or once per epoch if there is none.
+pub(crate) struct MaintenanceScheduler {
+    /// Height of the head when the node started. The node never shuts down
+    /// before its head moves past it, so that it doesn't shut down again
+    /// right after the restart.
+    startup_height: Option<BlockHeight>,
+    scheduled: Option<ScheduledMaintenance>,
+    search: Option<MaintenanceSearch>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new() -> Self {
+        Self { startup_height: None, scheduled: None, search: None }
+    }
+
+    /// Returns whether the node should shut down now, i.e. whether the window
+    /// starts at the next height and the validator has a duty at the height
+    /// of the head. If the head skips past the start of the window, the node
+    /// waits for the next one.
+    pub fn should_shut_down(&mut self, client: &Client) -> Result<bool, near_chain::Error> {
+        let Some(config) =
+            client.config.maintenance_window.get().filter(|config| config.auto_restart)
+        else {
+            self.scheduled = None;
+            return Ok(false);
+        };
+        let Some(validator_signer) = &client.validator_signer else {
+            return Ok(false);
+        };
+        let account_id = validator_signer.validator_id();
+        let head = client.chain.head()?;
+        let startup_height = *self.startup_height.get_or_insert(head.height);
+
+        self.poll_search()?;
+        let outdated = match &self.scheduled {
+            Some(scheduled) => {
+                scheduled.config != config
+                    || match &scheduled.window {
+                        Some(window) => head.height >= window.start,
+                        None => scheduled.epoch_id != head.epoch_id,
+                    }
+            }
+            None => true,
+        };
+        if outdated {
+            self.scheduled = None;
+            if self.search.is_none() {
+                self.start_search(client.epoch_manager.clone(), head, account_id.clone(), config);
+            }
+            return Ok(false);
+        }
+
+        let Some(window) = self.scheduled.as_ref().and_then(|scheduled| scheduled.window.as_ref())
+        else {
+            return Ok(false);
+        };
+        if window.start != head.height + 1 || head.height <= startup_height {
+            return Ok(false);
+        }
+        let epoch_info = client.epoch_manager.get_epoch_info(&head.epoch_id)?;
+        let shard_ids = client.epoch_manager.shard_ids(&head.epoch_id)?;
+        Ok(has_duties(&epoch_info, &shard_ids, account_id, head.height))
+    }
+
+    fn start_search(
+        &mut self,
+        epoch_manager: Arc<dyn EpochManagerAdapter>,
+        head: Tip,
+        account_id: AccountId,
+        config: MaintenanceWindowConfig,
+    ) {
+        let (sender, result) = mpsc::channel();
+        let num_blocks = config.num_blocks;
+        self.search = Some(MaintenanceSearch { config, epoch_id: head.epoch_id.clone(), result });
+        rayon::spawn(move || {
+            let window =
+                find_maintenance_window(epoch_manager.as_ref(), &head, &account_id, num_blocks);
+            // The scheduler may be gone if the client stopped in the meantime.
+            let _ = sender.send(window);
+        });
+    }
+
+    /// Schedules the window found by the search in the background, if it is
+    /// done.
+    fn poll_search(&mut self) -> Result<(), near_chain::Error> {
+        let Some(search) = &self.search else {
+            return Ok(());
+        };
+        let window = match search.result.try_recv() {
+            Ok(window) => window,
+            Err(mpsc::TryRecvError::Empty) => return Ok(()),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.search = None;
+                return Err(near_chain::Error::Other(
+                    "Maintenance window search stopped without a result".to_string(),
+                ));
+            }
+        };
+        let MaintenanceSearch { config, epoch_id, .. } = self.search.take().unwrap();
+        let window = window?;
+        tracing::info!(
+            target: "client",
+            ?window,
+            num_blocks = config.num_blocks,
+            "Scheduled maintenance window"
+        );
+        self.scheduled = Some(ScheduledMaintenance { config, epoch_id, window });
+        Ok(())
+    }
+}
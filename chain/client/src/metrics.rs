@@ -172,6 +172,15 @@ pub(crate) static TRACKED_SHARDS: Lazy<IntGaugeVec> = Lazy::new(|| {
     try_create_int_gauge_vec("near_client_tracked_shards", "Tracked shards", &["shard_id"]).unwrap()
 });
 
+pub(crate) static TRACKED_SHARDS_NEXT_EPOCH: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_client_tracked_shards_next_epoch",
+        "Shards tracked in the next epoch",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
 pub(crate) static SYNC_STATUS: Lazy<IntGauge> =
     Lazy::new(|| try_create_int_gauge("near_sync_status", "Node sync status").unwrap());
 
@@ -365,6 +374,25 @@ pub(crate) static PRODUCE_CHUNK_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static PRODUCE_CHUNK_TRANSACTIONS_TIME_BUDGET: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_produce_chunk_transactions_time_budget",
+        "Time budget for adding transactions to a produced chunk, if limited",
+        &["shard_id"],
+        Some(exponential_buckets(0.001, 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static PRODUCE_CHUNK_TRANSACTIONS_LIMITED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_produce_chunk_transactions_limited_total",
+        "Number of produced chunks in which adding transactions stopped at a limit, by the limit",
+        &["shard_id", "limit"],
+    )
+    .unwrap()
+});
+
 pub(crate) static VIEW_CLIENT_MESSAGE_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_view_client_messages_processing_time",
@@ -375,6 +403,14 @@ pub(crate) static VIEW_CLIENT_MESSAGE_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static VIEW_CLIENT_COLD_STATE_PARTS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_view_client_cold_state_parts_total",
+        "Number of requested state parts of epochs behind the tail of the hot storage",
+    )
+    .unwrap()
+});
+
 pub(crate) static PRODUCE_AND_DISTRIBUTE_CHUNK_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_produce_and_distribute_chunk_time",
@@ -413,6 +449,15 @@ pub(crate) static STATE_SYNC_STAGE: Lazy<IntGaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static STATE_SYNC_INVALID_PARTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_invalid_parts_total",
+        "Number of state parts received from peers which failed validation",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_RETRY_PART: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_state_sync_retry_part_total",
@@ -458,6 +503,24 @@ pub(crate) static STATE_SYNC_PARTS_TOTAL: Lazy<IntGaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static STATE_SYNC_PARTS_RESUMED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_parts_resumed_total",
+        "Number of state parts found in the store when starting to download the parts of a shard",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static STATE_SYNC_PARTS_ETA: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_state_sync_parts_eta_seconds",
+        "Estimated time left to download the state parts of the shard",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_DISCARD_PARTS: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_state_sync_discard_parts_total",
@@ -542,3 +605,138 @@ pub(crate) static SYNC_REQUIREMENT_CURRENT: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub(crate) static CHUNK_STATE_WITNESS_VALIDATION_FAILURES_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| {
+        try_create_int_counter_vec(
+            "near_chunk_state_witness_validation_failures_total",
+            "Number of chunk state witnesses which failed validation, by shard and reason",
+            &["shard_id", "reason"],
+        )
+        .unwrap()
+    });
+
+pub(crate) static CHUNK_STATE_WITNESS_FALLBACK_TRIE_NODES_TOTAL: Lazy<IntCounterVec> = Lazy::new(
+    || {
+        try_create_int_counter_vec(
+            "near_chunk_state_witness_fallback_trie_nodes_total",
+            "Number of trie nodes missing from chunk state witnesses which were read from storage instead, by shard",
+            &["shard_id"],
+        )
+        .unwrap()
+    },
+);
+
+pub(crate) static CHUNK_STATE_WITNESS_HEIGHT_REJECTIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(
+    || {
+        try_create_int_counter_vec(
+        "near_chunk_state_witness_height_rejections_total",
+        "Number of chunk state witnesses rejected for being outside of the height horizon, by shard and reason (too_old or too_new)",
+        &["shard_id", "reason"],
+    )
+    .unwrap()
+    },
+);
+
+pub(crate) static CHUNK_EXECUTION_DETERMINISM_CHECKS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_chunk_execution_determinism_checks_total",
+        "Number of chunks executed both from local state and from the state witness, by shard and result (match, divergence or error)",
+        &["shard_id", "result"],
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_VALIDATION_RECORDED_STORAGE_DIVERGENCES_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| {
+        try_create_int_counter_vec(
+            "near_chunk_validation_recorded_storage_divergences_total",
+            "Number of chunks whose validation from the state witness failed, but succeeded from local state, by shard",
+            &["shard_id"],
+        )
+        .unwrap()
+    });
+
+pub(crate) static CHUNK_STATE_WITNESS_QUEUE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_chunk_state_witness_queue_size",
+        "Number of produced chunks waiting for their state witness to be sent",
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESS_QUEUE_DROPPED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_chunk_state_witness_queue_dropped_total",
+        "Number of state witnesses dropped from the production queue, by shard and reason",
+        &["shard_id", "reason"],
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESS_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_chunk_state_witness_requests_total",
+        "Number of state witnesses requested from chunk producers because they didn't arrive in time, by shard",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESS_PRODUCTION_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_chunk_state_witness_production_time",
+        "Time taken to assemble a chunk state witness out of the recorded state transitions, by shard",
+        &["shard_id"],
+        Some(exponential_buckets(0.001, 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESS_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_chunk_state_witness_latency",
+        "Time from the chunk witness producer receiving a batch of chunks to the state witness being sent to chunk validators, by shard",
+        &["shard_id"],
+        Some(exponential_buckets(0.001, 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESS_TOTAL_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_chunk_state_witness_total_size",
+        "Size in bytes of the produced chunk state witnesses, by shard",
+        &["shard_id"],
+        Some(exponential_buckets(10_000., 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static PRODUCTION_ALERTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_production_alerts_total",
+        "Number of alerts raised because the validator of the node missed blocks or chunks, by result of the webhook call",
+        &["result"],
+    )
+    .unwrap()
+});
+
+pub(crate) static VALIDATOR_FAILOVER_SIGNING: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_validator_failover_signing",
+        "Whether the node signs with the validator key it shares with another node, 1 or 0",
+    )
+    .unwrap()
+});
+
+pub(crate) static VALIDATOR_FAILOVER_HEARTBEATS_RECEIVED_TOTAL: Lazy<IntCounterVec> = Lazy::new(
+    || {
+        try_create_int_counter_vec(
+            "near_validator_failover_heartbeats_received_total",
+            "Number of heartbeats received from the other node holding the validator key, by result",
+            &["result"],
+        )
+        .unwrap()
+    },
+);
@@ -0,0 +1,197 @@
+use crate::client::Client;
+use crate::production_alerts::write_status_file;
+use near_chain::ChainStoreAccess;
+use near_chain_configs::NodeStatusFileConfig;
+use near_primitives::hash::CryptoHash;
+use near_primitives::static_clock::StaticClock;
+use near_primitives::types::{AccountId, BlockHeight, ShardId};
+use near_store::flat::{store_helper, FlatStorageStatus};
+use std::time::Duration;
+
+/// Status of the node written to the status file.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NodeStatus {
+    /// When the status was written, in milliseconds since the Unix epoch.
+    pub timestamp_ms: i64,
+    /// Whether the node is synced and its head is recent, like the `/health`
+    /// endpoint of the RPC.
+    pub healthy: bool,
+    /// Name of the sync status, e.g. `NoSync` once the node is synced.
+    pub sync_status: String,
+    pub latest_block_height: BlockHeight,
+    pub latest_block_hash: CryptoHash,
+    /// Timestamp of the head, in milliseconds since the Unix epoch.
+    pub latest_block_timestamp_ms: u64,
+    /// The validator of the node, if it has one.
+    pub validator: Option<ValidatorStatus>,
+    /// The shards of the epoch of the head.
+    pub shards: Vec<ShardStatus>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ValidatorStatus {
+    pub account_id: AccountId,
+    pub is_block_producer: bool,
+    pub is_chunk_producer: bool,
+    pub is_chunk_validator: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ShardStatus {
+    pub shard_id: ShardId,
+    pub tracked: bool,
+    pub memtrie_loaded: bool,
+    /// One of `disabled`, `empty`, `creation` and `ready`, or `unknown` if
+    /// it couldn't be read.
+    pub flat_storage: String,
+}
+
+/// Periodically writes the status of the node as JSON to a file, so that
+/// orchestration systems don't need to query the RPC to check the node.
+pub(crate) struct NodeStatusFile {
+    config: NodeStatusFileConfig,
+}
+
+impl NodeStatusFile {
+    pub fn new(config: NodeStatusFileConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.config.period
+    }
+
+    /// Writes the status of the node. Does nothing if the head is unknown.
+    pub fn write(&self, client: &Client, max_head_age: Duration) {
+        let _span = tracing::debug_span!(target: "client", "write_node_status_file").entered();
+        match node_status(client, max_head_age) {
+            Ok(status) => write_status_file(&self.config.path, &status),
+            Err(err) => {
+                tracing::debug!(target: "client", ?err, "Failed to get the status of the node")
+            }
+        }
+    }
+}
+
+fn node_status(client: &Client, max_head_age: Duration) -> Result<NodeStatus, near_chain::Error> {
+    let head = client.chain.head()?;
+    let head_header = client.chain.get_block_header(&head.last_block_hash)?;
+    let latest_block_timestamp_ms = head_header.raw_timestamp() / 1_000_000;
+    let timestamp_ms = StaticClock::utc().timestamp_millis();
+    let head_age =
+        Duration::from_millis((timestamp_ms as u64).saturating_sub(latest_block_timestamp_ms));
+    let is_syncing = client.sync_status.is_syncing();
+
+    let account_id = client.validator_signer.as_ref().map(|signer| signer.validator_id());
+    let validator = account_id
+        .map(|account_id| -> Result<_, near_chain::Error> {
+            let epoch_manager = &client.epoch_manager;
+            let contains =
+                |validators: Vec<AccountId>| validators.iter().any(|id| id == account_id);
+            Ok(ValidatorStatus {
+                account_id: account_id.clone(),
+                is_block_producer: contains(
+                    epoch_manager
+                        .get_epoch_block_producers_ordered(&head.epoch_id, &head.last_block_hash)?
+                        .into_iter()
+                        .map(|(stake, _)| stake.take_account_id())
+                        .collect(),
+                ),
+                is_chunk_producer: contains(
+                    epoch_manager
+                        .get_epoch_chunk_producers(&head.epoch_id)?
+                        .into_iter()
+                        .map(|stake| stake.take_account_id())
+                        .collect(),
+                ),
+                is_chunk_validator: contains(
+                    epoch_manager
+                        .get_epoch_chunk_validators(&head.epoch_id)?
+                        .into_iter()
+                        .map(|stake| stake.take_account_id())
+                        .collect(),
+                ),
+            })
+        })
+        .transpose()?;
+
+    let tries = client.runtime_adapter.get_tries();
+    let store = client.chain.chain_store().store();
+    let mut shards = vec![];
+    for shard_id in client.epoch_manager.shard_ids(&head.epoch_id)? {
+        let shard_uid = client.epoch_manager.shard_id_to_uid(shard_id, &head.epoch_id)?;
+        let flat_storage = match store_helper::get_flat_storage_status(store, shard_uid) {
+            Ok(FlatStorageStatus::Disabled) => "disabled",
+            Ok(FlatStorageStatus::Empty) => "empty",
+            Ok(FlatStorageStatus::Creation(_)) => "creation",
+            Ok(FlatStorageStatus::Ready(_)) => "ready",
+            Err(_) => "unknown",
+        };
+        shards.push(ShardStatus {
+            shard_id,
+            tracked: client.shard_tracker.care_about_shard(
+                account_id,
+                &head.last_block_hash,
+                shard_id,
+                true,
+            ),
+            memtrie_loaded: tries.get_mem_tries(shard_uid).is_some(),
+            flat_storage: flat_storage.to_string(),
+        });
+    }
+
+    Ok(NodeStatus {
+        timestamp_ms,
+        healthy: !is_syncing && head_age <= max_head_age,
+        sync_status: client.sync_status.as_variant_name().to_string(),
+        latest_block_height: head.height,
+        latest_block_hash: head.last_block_hash,
+        latest_block_timestamp_ms,
+        validator,
+        shards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_client_primitives::types::SyncStatus;
+    use std::path::Path;
+
+    #[test]
+    fn test_write_status_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node_status.json");
+        let mut status = NodeStatus {
+            timestamp_ms: 1,
+            healthy: false,
+            sync_status: SyncStatus::AwaitingPeers.as_variant_name().to_string(),
+            latest_block_height: 10,
+            latest_block_hash: CryptoHash::default(),
+            latest_block_timestamp_ms: 1,
+            validator: None,
+            shards: vec![ShardStatus {
+                shard_id: 0,
+                tracked: true,
+                memtrie_loaded: false,
+                flat_storage: "ready".to_string(),
+            }],
+        };
+        write_status_file(&path, &status);
+        let read = |path: &Path| -> NodeStatus {
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+        };
+        assert_eq!(read(&path), status);
+
+        status.healthy = true;
+        status.validator = Some(ValidatorStatus {
+            account_id: "test0".parse().unwrap(),
+            is_block_producer: true,
+            is_chunk_producer: true,
+            is_chunk_validator: true,
+        });
+        write_status_file(&path, &status);
+        assert_eq!(read(&path), status);
+        assert!(!path.with_extension("tmp").exists());
+    }
+}
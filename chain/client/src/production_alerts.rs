@@ -0,0 +1,221 @@
+use crate::info::ValidatorProductionStats;
+use crate::metrics;
+use near_chain_configs::ProductionAlertsConfig;
+use near_primitives::block::Tip;
+use near_primitives::static_clock::StaticClock;
+use near_primitives::types::{AccountId, BlockHeight, EpochId, NumBlocks};
+use std::path::Path;
+
+/// Production of the validator of the node in the current epoch, written to
+/// the status file and sent to the webhook.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProductionStatus {
+    /// When the production was checked, in milliseconds since the Unix epoch.
+    pub timestamp_ms: i64,
+    pub account_id: AccountId,
+    pub epoch_id: EpochId,
+    pub head_height: BlockHeight,
+    /// Whether the account is a validator in the epoch.
+    pub is_validator: bool,
+    pub num_produced_blocks: NumBlocks,
+    pub num_expected_blocks: NumBlocks,
+    pub num_produced_chunks: NumBlocks,
+    pub num_expected_chunks: NumBlocks,
+    pub missed_blocks: NumBlocks,
+    pub missed_chunks: NumBlocks,
+    /// Whether the missed blocks or chunks exceed the thresholds.
+    pub alerting: bool,
+}
+
+/// Tracks the blocks and chunks the validator of the node produced against
+/// the ones it was expected to produce, and alerts operators through a
+/// webhook or a status file when it misses too many of them, without
+/// requiring external Prometheus alerting rules. An alert is raised at most
+/// once per epoch.
+pub(crate) struct ProductionAlerts {
+    config: ProductionAlertsConfig,
+    account_id: AccountId,
+    http_client: reqwest::Client,
+    /// Epoch in which the last alert was raised.
+    alerted_epoch_id: Option<EpochId>,
+}
+
+impl ProductionAlerts {
+    pub fn new(config: ProductionAlertsConfig, account_id: AccountId) -> Self {
+        Self { config, account_id, http_client: reqwest::Client::new(), alerted_epoch_id: None }
+    }
+
+    /// Checks the production of the validator, given the production stats of
+    /// the validators in the epoch of the head. Does nothing if the stats are
+    /// unknown, e.g. while the node is syncing.
+    pub fn check(&mut self, head: &Tip, validator_epoch_stats: &[ValidatorProductionStats]) {
+        if validator_epoch_stats.is_empty() {
+            return;
+        }
+        let status = self.status(head, validator_epoch_stats);
+        if let Some(path) = &self.config.status_file {
+            write_status_file(path, &status);
+        }
+        if !self.should_alert(&status) {
+            return;
+        }
+        tracing::warn!(
+            target: "client",
+            account_id = %status.account_id,
+            missed_blocks = status.missed_blocks,
+            missed_chunks = status.missed_chunks,
+            "Validator missed too many blocks or chunks in the current epoch",
+        );
+        let Some(webhook_url) = self.config.webhook_url.clone() else {
+            metrics::PRODUCTION_ALERTS_TOTAL.with_label_values(&["no_webhook"]).inc();
+            return;
+        };
+        let request = self.http_client.post(&webhook_url).json(&status).send();
+        near_performance_metrics::actix::spawn("production_alerts", async move {
+            let result = match request.await.and_then(|response| response.error_for_status()) {
+                Ok(_) => "ok",
+                Err(err) => {
+                    tracing::warn!(
+                        target: "client",
+                        ?err,
+                        webhook_url,
+                        "Failed to send production alert",
+                    );
+                    "failed"
+                }
+            };
+            metrics::PRODUCTION_ALERTS_TOTAL.with_label_values(&[result]).inc();
+        });
+    }
+
+    fn status(
+        &self,
+        head: &Tip,
+        validator_epoch_stats: &[ValidatorProductionStats],
+    ) -> ProductionStatus {
+        // Kicked out validators come first with zero stats, so take the last
+        // entry of the account to prefer its stats as a current validator.
+        let stats =
+            validator_epoch_stats.iter().rev().find(|stats| stats.account_id == self.account_id);
+        let num_produced_blocks = stats.map_or(0, |stats| stats.num_produced_blocks);
+        let num_expected_blocks = stats.map_or(0, |stats| stats.num_expected_blocks);
+        let num_produced_chunks = stats.map_or(0, |stats| stats.num_produced_chunks);
+        let num_expected_chunks = stats.map_or(0, |stats| stats.num_expected_chunks);
+        let missed_blocks = num_expected_blocks.saturating_sub(num_produced_blocks);
+        let missed_chunks = num_expected_chunks.saturating_sub(num_produced_chunks);
+        ProductionStatus {
+            timestamp_ms: StaticClock::utc().timestamp_millis(),
+            account_id: self.account_id.clone(),
+            epoch_id: head.epoch_id.clone(),
+            head_height: head.height,
+            is_validator: stats.is_some(),
+            num_produced_blocks,
+            num_expected_blocks,
+            num_produced_chunks,
+            num_expected_chunks,
+            missed_blocks,
+            missed_chunks,
+            alerting: missed_blocks > self.config.missed_blocks_threshold
+                || missed_chunks > self.config.missed_chunks_threshold,
+        }
+    }
+
+    /// Returns whether to raise an alert for the status, i.e. whether it is
+    /// alerting for the first time in its epoch.
+    fn should_alert(&mut self, status: &ProductionStatus) -> bool {
+        if !status.alerting || self.alerted_epoch_id.as_ref() == Some(&status.epoch_id) {
+            return false;
+        }
+        self.alerted_epoch_id = Some(status.epoch_id.clone());
+        true
+    }
+}
+
+/// Replaces the status file, through a temporary file so that readers never
+/// see a partially written status. Also used for the node status file.
+pub(crate) fn write_status_file(path: &Path, status: &impl serde::Serialize) {
+    let tmp_path = path.with_extension("tmp");
+    let result = serde_json::to_vec_pretty(status)
+        .map_err(std::io::Error::from)
+        .and_then(|data| std::fs::write(&tmp_path, data))
+        .and_then(|()| std::fs::rename(&tmp_path, path));
+    if let Err(err) = result {
+        tracing::warn!(target: "client", ?err, ?path, "Failed to write status file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::hash::CryptoHash;
+
+    fn stats(
+        account_id: &str,
+        num_produced_blocks: NumBlocks,
+        num_expected_blocks: NumBlocks,
+    ) -> ValidatorProductionStats {
+        ValidatorProductionStats {
+            account_id: account_id.parse().unwrap(),
+            num_produced_blocks,
+            num_expected_blocks,
+            num_produced_chunks: 0,
+            num_expected_chunks: 0,
+            shards: vec![],
+            num_produced_chunks_per_shard: vec![],
+            num_expected_chunks_per_shard: vec![],
+        }
+    }
+
+    fn head(epoch_id: EpochId, height: BlockHeight) -> Tip {
+        Tip {
+            height,
+            last_block_hash: CryptoHash::default(),
+            prev_block_hash: CryptoHash::default(),
+            epoch_id,
+            next_epoch_id: EpochId::default(),
+        }
+    }
+
+    #[test]
+    fn test_production_alerts() {
+        let dir = tempfile::tempdir().unwrap();
+        let status_file = dir.path().join("production_status.json");
+        let config = ProductionAlertsConfig {
+            webhook_url: None,
+            status_file: Some(status_file.clone()),
+            missed_blocks_threshold: 2,
+            missed_chunks_threshold: 10,
+        };
+        let mut alerts = ProductionAlerts::new(config, "test0".parse().unwrap());
+        let epoch_id = EpochId(CryptoHash::hash_bytes(b"epoch"));
+
+        // Nothing is written while the stats are unknown.
+        alerts.check(&head(epoch_id.clone(), 10), &[]);
+        assert!(!status_file.exists());
+
+        let status = alerts.status(&head(epoch_id.clone(), 10), &[stats("test0", 8, 10)]);
+        assert_eq!(status.missed_blocks, 2);
+        assert!(!status.alerting);
+        assert!(!alerts.should_alert(&status));
+
+        alerts.check(&head(epoch_id.clone(), 11), &[stats("test1", 0, 5), stats("test0", 8, 11)]);
+        let status: ProductionStatus =
+            serde_json::from_slice(&std::fs::read(&status_file).unwrap()).unwrap();
+        assert_eq!(status.head_height, 11);
+        assert_eq!(status.missed_blocks, 3);
+        assert!(status.alerting);
+        // The alert was raised by the check, and isn't raised again in the
+        // same epoch.
+        assert!(!alerts.should_alert(&status));
+
+        let status = alerts.status(
+            &head(EpochId(CryptoHash::hash_bytes(b"next epoch")), 20),
+            &[stats("test0", 0, 3)],
+        );
+        assert!(alerts.should_alert(&status));
+
+        let status = alerts.status(&head(epoch_id, 21), &[stats("test1", 0, 3)]);
+        assert!(!status.is_validator);
+        assert!(!status.alerting);
+    }
+}
@@ -0,0 +1,409 @@
+//! Offloads the validation of chunk state witnesses to remote worker
+//! processes, so that validators validating many shards can spread the load
+//! over several machines.
+//!
+//! Workers are sent the witnesses over gRPC, see
+//! `proto/remote_chunk_validation.proto` for the service they implement. They
+//! return the signature of the chunk endorsement payload made with the
+//! validator key delegated to them, and report whether they are ready to
+//! validate witnesses with health checks.
+//!
+//! Signatures are verified against the validator key registered for the epoch
+//! before the endorsement is sent. If no worker is healthy, or the worker
+//! fails to produce a valid signature in time, the witness is validated
+//! locally.
+
+use borsh::BorshDeserialize;
+use near_chain_configs::RemoteChunkValidationConfig;
+use near_crypto::{PublicKey, Signature};
+use near_o11y::metrics::{
+    try_create_int_counter_vec, try_create_int_gauge, IntCounterVec, IntGauge,
+};
+use near_primitives::chunk_validation::{
+    ChunkEndorsement, ChunkEndorsementPayload, ChunkStateWitness,
+};
+use near_primitives::types::AccountId;
+use once_cell::sync::Lazy;
+use proto::chunk_validation_worker_client::ChunkValidationWorkerClient;
+use proto::health_check_response::ServingStatus;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint};
+
+pub(crate) mod proto {
+    tonic::include_proto!("near.chunk_validation.v1");
+}
+
+static REMOTE_CHUNK_VALIDATION_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_remote_chunk_validation_requests_total",
+        "Number of chunk state witnesses sent to remote validation workers, by result",
+        &["result"],
+    )
+    .unwrap()
+});
+
+static REMOTE_CHUNK_VALIDATION_HEALTHY_WORKERS: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_remote_chunk_validation_healthy_workers",
+        "Number of remote chunk validation workers which passed the last health check",
+    )
+    .unwrap()
+});
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum RemoteChunkValidationError {
+    #[error("no healthy validation worker")]
+    NoHealthyWorker,
+    #[error("request to validation worker {0} failed: {1}")]
+    Request(String, tonic::Status),
+    #[error("invalid response from validation worker {0}: {1}")]
+    InvalidResponse(String, String),
+}
+
+struct Worker {
+    url: String,
+    client: ChunkValidationWorkerClient<Channel>,
+    healthy: AtomicBool,
+}
+
+pub(crate) struct RemoteChunkValidator {
+    workers: Vec<Worker>,
+    /// Index of the worker to try first for the next witness.
+    next_worker: AtomicUsize,
+    /// Runs the connections to the workers. Only blocked on from the
+    /// validation and health check threads, never from within an async
+    /// runtime. Always set, only taken when the validator is dropped.
+    runtime: Option<tokio::runtime::Runtime>,
+    health_check_period: Duration,
+}
+
+impl RemoteChunkValidator {
+    /// Creates the validator. All workers are considered unhealthy until they
+    /// pass a health check, see `start_health_checks`.
+    pub fn new(config: &RemoteChunkValidationConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(!config.worker_urls.is_empty(), "no remote chunk validation workers");
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("remote_chunk_validation")
+            .enable_all()
+            .build()?;
+        // The channels connect on first use, and reconnect after failures, in
+        // the background of the runtime.
+        let workers = {
+            let _guard = runtime.enter();
+            config
+                .worker_urls
+                .iter()
+                .map(|url| {
+                    let channel = Endpoint::from_shared(url.clone())?
+                        .timeout(config.timeout)
+                        .connect_lazy()?;
+                    Ok(Worker {
+                        url: url.clone(),
+                        client: ChunkValidationWorkerClient::new(channel),
+                        healthy: AtomicBool::new(false),
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?
+        };
+        Ok(Self {
+            workers,
+            next_worker: AtomicUsize::new(0),
+            runtime: Some(runtime),
+            health_check_period: config.health_check_period,
+        })
+    }
+
+    fn runtime(&self) -> &tokio::runtime::Runtime {
+        self.runtime.as_ref().unwrap()
+    }
+
+    /// Checks the health of the workers periodically on a dedicated thread,
+    /// until the validator is dropped.
+    pub fn start_health_checks(self: &Arc<Self>) -> std::io::Result<()> {
+        let validator = Arc::downgrade(self);
+        let period = self.health_check_period;
+        std::thread::Builder::new().name("remote_chunk_validation_health".to_string()).spawn(
+            move || {
+                while let Some(validator) = Weak::upgrade(&validator) {
+                    validator.check_health();
+                    drop(validator);
+                    std::thread::sleep(period);
+                }
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Checks the health of all workers once.
+    pub fn check_health(&self) {
+        let statuses =
+            self.runtime().block_on(futures::future::join_all(self.workers.iter().map(|worker| {
+                let mut client = worker.client.clone();
+                async move { client.check(proto::HealthCheckRequest {}).await }
+            })));
+        let mut num_healthy = 0;
+        for (worker, status) in self.workers.iter().zip(statuses) {
+            let healthy = status.is_ok_and(|response| {
+                response.into_inner().status == ServingStatus::Serving as i32
+            });
+            if worker.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+                tracing::info!(target: "chunk_validation", url = %worker.url, healthy, "Remote validation worker health changed");
+            }
+            num_healthy += healthy as i64;
+        }
+        REMOTE_CHUNK_VALIDATION_HEALTHY_WORKERS.set(num_healthy);
+    }
+
+    /// Picks the next healthy worker in round-robin order.
+    fn pick_worker(&self) -> Option<&Worker> {
+        let start = self.next_worker.fetch_add(1, Ordering::Relaxed);
+        (0..self.workers.len())
+            .map(|i| &self.workers[(start + i) % self.workers.len()])
+            .find(|worker| worker.healthy.load(Ordering::Relaxed))
+    }
+
+    /// Sends the witness to a healthy worker to be validated, and returns the
+    /// endorsement of the chunk, with the payload `payload`, signed by the
+    /// worker on behalf of `account_id`.
+    /// Blocks until the worker responds, so must be called from a validation
+    /// thread.
+    pub fn validate(
+        &self,
+        state_witness: &ChunkStateWitness,
+        payload: &ChunkEndorsementPayload,
+        account_id: &AccountId,
+        public_key: &PublicKey,
+    ) -> Result<ChunkEndorsement, RemoteChunkValidationError> {
+        let result = self.validate_impl(state_witness, payload, account_id, public_key);
+        let label = if result.is_ok() { "ok" } else { "error" };
+        REMOTE_CHUNK_VALIDATION_REQUESTS.with_label_values(&[label]).inc();
+        result
+    }
+
+    fn validate_impl(
+        &self,
+        state_witness: &ChunkStateWitness,
+        payload: &ChunkEndorsementPayload,
+        account_id: &AccountId,
+        public_key: &PublicKey,
+    ) -> Result<ChunkEndorsement, RemoteChunkValidationError> {
+        let worker = self.pick_worker().ok_or(RemoteChunkValidationError::NoHealthyWorker)?;
+        let request = proto::ValidateRequest {
+            endorsement_payload: borsh::to_vec(payload).unwrap(),
+            state_witness: borsh::to_vec(state_witness).unwrap(),
+        };
+        let response =
+            self.runtime().block_on(worker.client.clone().validate(request)).map_err(|status| {
+                // Connection failures and timeouts mean that the worker is
+                // likely down, so don't send it anything until it passes the
+                // next health check. Rejected witnesses don't.
+                if status.code() != tonic::Code::InvalidArgument {
+                    worker.healthy.store(false, Ordering::Relaxed);
+                }
+                RemoteChunkValidationError::Request(worker.url.clone(), status)
+            })?;
+        let signature =
+            Signature::try_from_slice(&response.into_inner().signature).map_err(|err| {
+                RemoteChunkValidationError::InvalidResponse(
+                    worker.url.clone(),
+                    format!("invalid signature: {err}"),
+                )
+            })?;
+        let endorsement =
+            ChunkEndorsement { inner: payload.inner(), account_id: account_id.clone(), signature };
+        if !endorsement.verify(public_key, payload) {
+            return Err(RemoteChunkValidationError::InvalidResponse(
+                worker.url.clone(),
+                format!("signature doesn't match public key {}", public_key),
+            ));
+        }
+        Ok(endorsement)
+    }
+}
+
+impl Drop for RemoteChunkValidator {
+    fn drop(&mut self) {
+        // Unlike dropping it, shutting down the runtime in the background is
+        // allowed from within another runtime, e.g. the one of actix.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::challenge::PartialState;
+    use near_primitives::chunk_validation::ChunkStateTransition;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::test_utils::{create_test_chunk_header, create_test_signer};
+    use near_primitives::types::EpochId;
+    use near_primitives::validator_signer::ValidatorSigner;
+    use near_primitives::version::PROTOCOL_VERSION;
+    use proto::chunk_validation_worker_server::{
+        ChunkValidationWorker, ChunkValidationWorkerServer,
+    };
+    use tonic::{Request, Response, Status};
+
+    /// Worker which signs endorsements with the key of `account`, if set, and
+    /// otherwise reports that it isn't serving.
+    struct TestWorker {
+        account: Option<&'static str>,
+    }
+
+    #[tonic::async_trait]
+    impl ChunkValidationWorker for TestWorker {
+        async fn validate(
+            &self,
+            request: Request<proto::ValidateRequest>,
+        ) -> Result<Response<proto::ValidateResponse>, Status> {
+            let proto::ValidateRequest { endorsement_payload, state_witness } =
+                request.into_inner();
+            let payload = ChunkEndorsementPayload::try_from_slice(&endorsement_payload).unwrap();
+            let witness = ChunkStateWitness::try_from_slice(&state_witness).unwrap();
+            if payload.chunk_hash() != &witness.chunk_header.chunk_hash() {
+                return Err(Status::invalid_argument("the witness doesn't match the payload"));
+            }
+            let signer = create_test_signer(self.account.unwrap());
+            let signature = signer.sign_chunk_endorsement(&payload).unwrap();
+            Ok(Response::new(proto::ValidateResponse {
+                signature: borsh::to_vec(&signature).unwrap(),
+            }))
+        }
+
+        async fn check(
+            &self,
+            _request: Request<proto::HealthCheckRequest>,
+        ) -> Result<Response<proto::HealthCheckResponse>, Status> {
+            let status = if self.account.is_some() {
+                ServingStatus::Serving
+            } else {
+                ServingStatus::NotServing
+            };
+            Ok(Response::new(proto::HealthCheckResponse { status: status as i32 }))
+        }
+    }
+
+    /// Serves the worker on a thread of its own, and returns its URL.
+    fn serve(worker: TestWorker) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        std::thread::spawn(move || {
+            let runtime =
+                tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                tonic::transport::Server::builder()
+                    .add_service(ChunkValidationWorkerServer::new(worker))
+                    .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+                    .await
+                    .unwrap();
+            });
+        });
+        url
+    }
+
+    fn witness() -> ChunkStateWitness {
+        let chunk_header = create_test_chunk_header(1, 0);
+        ChunkStateWitness {
+            chunk_header,
+            main_state_transition: ChunkStateTransition {
+                block_hash: CryptoHash::default(),
+                base_state: PartialState::default(),
+                post_state_root: CryptoHash::default(),
+            },
+            source_receipt_proofs: Default::default(),
+            applied_receipts_hash: CryptoHash::default(),
+            transactions: vec![],
+            implicit_transitions: vec![],
+            new_transactions: vec![],
+            new_transactions_validation_state: PartialState::default(),
+            transitions_base: None,
+        }
+    }
+
+    fn payload(witness: &ChunkStateWitness) -> ChunkEndorsementPayload {
+        ChunkEndorsementPayload::new(
+            witness.chunk_header.chunk_hash(),
+            EpochId::default(),
+            witness.chunk_header.height_created(),
+            PROTOCOL_VERSION,
+        )
+    }
+
+    /// Starts a worker which signs endorsements with the key of `account`.
+    fn worker(account: &'static str) -> String {
+        serve(TestWorker { account: Some(account) })
+    }
+
+    fn config(worker_urls: Vec<String>) -> RemoteChunkValidationConfig {
+        RemoteChunkValidationConfig {
+            worker_urls,
+            timeout: Duration::from_secs(5),
+            health_check_period: Duration::from_secs(3600),
+        }
+    }
+
+    #[test]
+    fn test_remote_chunk_validation() {
+        let signer = create_test_signer("validator");
+        let validator = RemoteChunkValidator::new(&config(vec![worker("validator")])).unwrap();
+        let witness = witness();
+        // The worker isn't used until it passes a health check.
+        assert!(matches!(
+            validator.validate(
+                &witness,
+                &payload(&witness),
+                signer.validator_id(),
+                &signer.public_key()
+            ),
+            Err(RemoteChunkValidationError::NoHealthyWorker)
+        ));
+        validator.check_health();
+        let endorsement = validator
+            .validate(&witness, &payload(&witness), signer.validator_id(), &signer.public_key())
+            .unwrap();
+        assert_eq!(endorsement.inner, payload(&witness).inner());
+        assert!(endorsement.verify(&signer.public_key(), &payload(&witness)));
+    }
+
+    #[test]
+    fn test_remote_chunk_validation_wrong_key() {
+        let signer = create_test_signer("validator");
+        let validator = RemoteChunkValidator::new(&config(vec![worker("other")])).unwrap();
+        validator.check_health();
+        assert!(matches!(
+            validator.validate(
+                &witness(),
+                &payload(&witness()),
+                signer.validator_id(),
+                &signer.public_key()
+            ),
+            Err(RemoteChunkValidationError::InvalidResponse(..))
+        ));
+    }
+
+    #[test]
+    fn test_remote_chunk_validation_skips_unhealthy_workers() {
+        let signer = create_test_signer("validator");
+        let unhealthy = serve(TestWorker { account: None });
+        let validator =
+            RemoteChunkValidator::new(&config(vec![unhealthy, worker("validator")])).unwrap();
+        validator.check_health();
+        for _ in 0..3 {
+            validator
+                .validate(
+                    &witness(),
+                    &payload(&witness()),
+                    signer.validator_id(),
+                    &signer.public_key(),
+                )
+                .unwrap();
+        }
+    }
+}
@@ -1,81 +1,205 @@
 use chrono::{DateTime, Duration, Utc};
-use near_network::types::PeerManagerAdapter;
-use near_primitives::hash::CryptoHash;
+use near_async::messaging::CanSend;
+use near_chain::Chain;
+use near_chain_primitives::error::epoch_sync::EpochSyncInfoError;
+use near_client_primitives::types::SyncStatus;
+use near_network::types::{
+    HighestHeightPeerInfo, NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest,
+    ReasonForBan,
+};
+use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 use near_primitives::network::PeerId;
 use near_primitives::static_clock::StaticClock;
-use near_primitives::types::validator_stake::ValidatorStake;
-use near_primitives::types::EpochId;
-use std::collections::{HashMap, HashSet};
+use near_primitives::types::{BlockHeight, EpochId};
+use rand::seq::IteratorRandom;
+use std::collections::HashSet;
 use std::time::Duration as TimeDuration;
 
-/// Helper to keep track of the Epoch Sync
-// TODO #3488
-#[allow(dead_code)]
+/// The last request for an `EpochSyncInfo`.
+struct EpochSyncRequest {
+    epoch_id: EpochId,
+    peer_id: PeerId,
+    when: DateTime<Utc>,
+}
+
+/// Helper to keep track of the Epoch Sync.
+///
+/// Instead of downloading every header since genesis, the node downloads the
+/// `EpochSyncInfo` of every finished epoch, one epoch at a time, starting from
+/// the epoch of the header head. Each `EpochSyncInfo` is validated against the
+/// epoch infos known from the previous one and moves the header head to the
+/// last block of its epoch. Once peers report that the requested epoch isn't
+/// finished yet, the remaining headers are downloaded by the Header Sync.
 pub struct EpochSync {
     network_adapter: PeerManagerAdapter,
-    /// Datastructure to keep track of when the last request to each peer was made.
-    /// Peers do not respond to Epoch Sync requests more frequently than once per a certain time
-    /// interval, thus there's no point in requesting more frequently.
-    peer_to_last_request_time: HashMap<PeerId, DateTime<Utc>>,
-    /// Tracks all the peers who have reported that we are already up to date
-    peers_reporting_up_to_date: HashSet<PeerId>,
-    /// The last epoch we are synced to
-    current_epoch_id: EpochId,
-    /// The next epoch id we need to sync
-    next_epoch_id: EpochId,
-    /// The block producers set to validate the light client block view for the next epoch
-    next_block_producers: Vec<ValidatorStake>,
-    /// The last epoch id that we have requested
-    requested_epoch_id: EpochId,
-    /// When and to whom was the last request made
-    last_request_time: DateTime<Utc>,
-    last_request_peer_id: Option<PeerId>,
-
-    /// How long to wait for a response before re-requesting the same light client block view
+    /// The last request made, if its response is still awaited.
+    last_request: Option<EpochSyncRequest>,
+    /// The next epoch to request. Starts from the epoch of the header head.
+    next_epoch_id: Option<EpochId>,
+    /// Peers which didn't respond in time or served an invalid `EpochSyncInfo`.
+    /// They aren't requested again.
+    failed_peers: HashSet<PeerId>,
+    /// How long to wait for a response before requesting another peer.
     request_timeout: Duration,
-    /// How frequently to send request to the same peer
-    peer_timeout: Duration,
-
-    /// True, if all peers agreed that we're at the last Epoch.
-    /// Only finalization is needed.
-    have_all_epochs: bool,
     /// Whether the Epoch Sync was performed to completion previously.
     /// Current state machine allows for only one Epoch Sync.
     pub done: bool,
-
-    pub sync_hash: CryptoHash,
-
-    received_epoch: bool,
-
-    is_just_started: bool,
 }
 
 impl EpochSync {
-    pub fn new(
-        network_adapter: PeerManagerAdapter,
-        genesis_epoch_id: EpochId,
-        genesis_next_epoch_id: EpochId,
-        first_epoch_block_producers: Vec<ValidatorStake>,
-        request_timeout: TimeDuration,
-        peer_timeout: TimeDuration,
-    ) -> Self {
+    pub fn new(network_adapter: PeerManagerAdapter, request_timeout: TimeDuration) -> Self {
         Self {
             network_adapter,
-            peer_to_last_request_time: HashMap::new(),
-            peers_reporting_up_to_date: HashSet::new(),
-            current_epoch_id: genesis_epoch_id.clone(),
-            next_epoch_id: genesis_next_epoch_id,
-            next_block_producers: first_epoch_block_producers,
-            requested_epoch_id: genesis_epoch_id,
-            last_request_time: StaticClock::utc(),
-            last_request_peer_id: None,
+            last_request: None,
+            next_epoch_id: None,
+            failed_peers: HashSet::new(),
             request_timeout: Duration::from_std(request_timeout).unwrap(),
-            peer_timeout: Duration::from_std(peer_timeout).unwrap(),
-            received_epoch: false,
-            have_all_epochs: false,
             done: false,
-            sync_hash: CryptoHash::default(),
-            is_just_started: true,
         }
     }
+
+    /// Returns true if the Epoch Sync is in progress, and the other kinds of
+    /// sync need to wait for it. Maybe requests an `EpochSyncInfo` from a peer.
+    pub fn run(
+        &mut self,
+        sync_status: &mut SyncStatus,
+        chain: &Chain,
+        highest_height: BlockHeight,
+        highest_height_peers: &[HighestHeightPeerInfo],
+    ) -> Result<bool, near_chain::Error> {
+        let _span = tracing::debug_span!(target: "sync", "run", sync = "EpochSync").entered();
+        if self.done {
+            return Ok(false);
+        }
+        let header_head = chain.header_head()?;
+        if header_head.height >= highest_height {
+            self.finish("header head is at the highest height of peers");
+            return Ok(false);
+        }
+        let epoch_id = self.next_epoch_id.get_or_insert(header_head.epoch_id).clone();
+        let epoch_ord = chain.epoch_manager.get_epoch_info(&epoch_id)?.epoch_height();
+        sync_status.update(SyncStatus::EpochSync { epoch_ord });
+
+        let now = StaticClock::utc();
+        if let Some(request) = &self.last_request {
+            if request.epoch_id == epoch_id && now - request.when < self.request_timeout {
+                return Ok(true);
+            }
+            tracing::debug!(
+                target: "sync",
+                peer_id = %request.peer_id,
+                epoch_id = ?request.epoch_id,
+                "EpochSync: request timed out",
+            );
+            self.failed_peers.insert(request.peer_id.clone());
+        }
+
+        let Some(peer) = highest_height_peers
+            .iter()
+            .filter(|peer| !self.failed_peers.contains(&peer.peer_info.id))
+            .choose(&mut rand::thread_rng())
+        else {
+            self.finish("no peers left to request the EpochSyncInfo from");
+            return Ok(false);
+        };
+        let peer_id = peer.peer_info.id.clone();
+        tracing::debug!(target: "sync", %peer_id, ?epoch_id, epoch_ord, "EpochSync: request");
+        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::EpochSyncRequest {
+                peer_id: peer_id.clone(),
+                epoch_id: epoch_id.clone(),
+            },
+        ));
+        self.last_request = Some(EpochSyncRequest { epoch_id, peer_id, when: now });
+        Ok(true)
+    }
+
+    /// Processes the response to the last request. `None` means the peer
+    /// doesn't have the `EpochSyncInfo` of the epoch, because the epoch isn't
+    /// finished yet, which completes the Epoch Sync.
+    pub fn on_response(
+        &mut self,
+        chain: &mut Chain,
+        peer_id: PeerId,
+        epoch_id: EpochId,
+        epoch_sync_info: Option<Box<EpochSyncInfo>>,
+    ) {
+        let Some(request) = &self.last_request else {
+            return;
+        };
+        if self.done || request.peer_id != peer_id || request.epoch_id != epoch_id {
+            tracing::debug!(
+                target: "sync",
+                %peer_id,
+                ?epoch_id,
+                "EpochSync: unexpected response",
+            );
+            return;
+        }
+        self.last_request = None;
+        let Some(epoch_sync_info) = epoch_sync_info else {
+            self.finish("peer reported that the epoch isn't finished yet");
+            return;
+        };
+
+        if epoch_sync_info.get_epoch_id().ok() != Some(&epoch_id) {
+            tracing::warn!(
+                target: "sync",
+                %peer_id,
+                ?epoch_id,
+                "EpochSync: response for a different epoch",
+            );
+            self.ban_peer(peer_id);
+            return;
+        }
+        match chain.validate_and_record_epoch_sync_info(&epoch_sync_info) {
+            Ok(()) => {
+                tracing::info!(
+                    target: "sync",
+                    ?epoch_id,
+                    epoch_height = epoch_sync_info.epoch_info.epoch_height(),
+                    "EpochSync: synced epoch",
+                );
+                self.next_epoch_id = epoch_sync_info.get_next_epoch_id().ok().cloned();
+            }
+            Err(EpochSyncInfoError::EpochSyncInfoErr(err)) => {
+                tracing::warn!(
+                    target: "sync",
+                    %peer_id,
+                    ?epoch_id,
+                    ?err,
+                    "EpochSync: invalid EpochSyncInfo",
+                );
+                self.ban_peer(peer_id);
+            }
+            Err(err) => {
+                tracing::warn!(
+                    target: "sync",
+                    %peer_id,
+                    ?epoch_id,
+                    ?err,
+                    "EpochSync: failed to record EpochSyncInfo",
+                );
+                self.failed_peers.insert(peer_id);
+            }
+        }
+    }
+
+    fn ban_peer(&mut self, peer_id: PeerId) {
+        self.failed_peers.insert(peer_id.clone());
+        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::BanPeer { peer_id, ban_reason: ReasonForBan::BadEpochSyncInfo },
+        ));
+    }
+
+    fn finish(&mut self, reason: &str) {
+        tracing::info!(
+            target: "sync",
+            next_epoch_id = ?self.next_epoch_id,
+            reason,
+            "EpochSync: done",
+        );
+        self.done = true;
+        self.last_request = None;
+    }
 }
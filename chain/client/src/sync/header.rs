@@ -117,7 +117,6 @@ impl HeaderSync {
                 true
             }
             SyncStatus::NoSync | SyncStatus::AwaitingPeers | SyncStatus::EpochSync { .. } => {
-                // TODO: How can it get to EpochSync if it's hardcoded to go from NoSync to HeaderSync?
                 debug!(target: "sync", "Sync: initial transition to Header sync. Header head {} at {}",
                     header_head.last_block_hash, header_head.height,
                 );
@@ -195,11 +194,10 @@ impl HeaderSync {
 
         // Always enable header sync on initial state transition from
         // * NoSync
-        // * AwaitingPeers.
-        // TODO: Will this remain correct with the introduction of EpochSync?
-        // TODO: Shouldn't a node transition to EpochSync from these states?
+        // * AwaitingPeers
+        // * EpochSync, once the headers of the finished epochs are synced.
         let force_sync = match sync_status {
-            SyncStatus::NoSync | SyncStatus::AwaitingPeers => true,
+            SyncStatus::NoSync | SyncStatus::AwaitingPeers | SyncStatus::EpochSync { .. } => true,
             _ => false,
         };
 
@@ -342,8 +340,12 @@ impl HeaderSync {
         let ordinals = get_locator_ordinals(final_head_ordinal, tip_ordinal);
         let mut locator: Vec<CryptoHash> = vec![];
         for ordinal in &ordinals {
-            let block_hash = store.get_block_hash_from_ordinal(*ordinal)?;
-            locator.push(block_hash);
+            // Epoch sync only saves a few headers at the end of every epoch.
+            match store.get_block_hash_from_ordinal(*ordinal) {
+                Ok(block_hash) => locator.push(block_hash),
+                Err(near_chain::Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            }
         }
         debug!(target: "sync", "Sync: locator: {:?} ordinals: {:?}", locator, ordinals);
         Ok(locator)
@@ -776,6 +778,7 @@ mod test {
                                 this_height,
                                 signer.as_ref(),
                             )
+                            .unwrap()
                             .signature,
                         ))
                     })
@@ -790,7 +793,8 @@ mod test {
                 *last_block.header().next_bp_hash(),
                 block_merkle_tree.root(),
                 None,
-            );
+            )
+            .unwrap();
             block_merkle_tree.insert(*block.hash());
             chain2.process_block_header(block.header(), &mut Vec::new()).unwrap(); // just to validate
             process_block_sync(
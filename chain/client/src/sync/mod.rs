@@ -1,5 +1,6 @@
 pub mod adapter;
 pub mod block;
+#[cfg(feature = "new_epoch_sync")]
 pub mod epoch;
 pub mod external;
 pub mod header;
@@ -13,6 +13,9 @@
 //! (and are high enough) + validators from that epoch that were tracking the shard)
 //! Then for each part that we're missing, we're 'randomly' picking a target from whom we'll request it - but we make
 //! sure to not request more than MAX_STATE_PART_REQUESTS from each.
+//! Parts are requested from the targets which advertise a state snapshot of the shard, if any, as only they can
+//! generate the parts. Every part is validated against the state root, and peers serving too many invalid parts are
+//! banned.
 //!
 //! WARNING: with the current design, we're putting quite a load on the validators - as we request a lot of data from
 //!         them (if you assume that we have 100 validators and 30 peers - we send 100/130 of requests to validators).
@@ -27,12 +30,12 @@ use crate::sync::external::{
 use actix_rt::ArbiterHandle;
 use chrono::{DateTime, Duration, Utc};
 use futures::{future, FutureExt};
-use near_async::messaging::CanSendAsync;
+use near_async::messaging::{CanSend, CanSendAsync};
 use near_chain::chain::ApplyStatePartsRequest;
 use near_chain::near_chain_primitives;
 use near_chain::resharding::ReshardingRequest;
 use near_chain::types::RuntimeAdapter;
-use near_chain::Chain;
+use near_chain::{Chain, ChainStoreAccess};
 use near_chain_configs::{ExternalStorageConfig, ExternalStorageLocation, SyncConfig};
 use near_client_primitives::types::{
     format_shard_sync_phase, DownloadStatus, ShardSyncDownload, ShardSyncStatus,
@@ -40,7 +43,8 @@ use near_client_primitives::types::{
 use near_epoch_manager::EpochManagerAdapter;
 use near_network::types::PeerManagerMessageRequest;
 use near_network::types::{
-    HighestHeightPeerInfo, NetworkRequests, NetworkResponses, PeerManagerAdapter,
+    HighestHeightPeerInfo, NetworkRequests, NetworkResponses, PeerManagerAdapter, ReasonForBan,
+    SnapshotHostInfo,
 };
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
@@ -52,7 +56,7 @@ use near_primitives::types::{AccountId, EpochHeight, EpochId, ShardId, StateRoot
 use near_store::DBCol;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Add;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::{channel, Receiver, Sender};
@@ -66,6 +70,8 @@ pub const MAX_STATE_PART_REQUEST: u64 = 16;
 /// Number of state parts already requested stored as pending.
 /// This number should not exceed MAX_STATE_PART_REQUEST times (number of peers in the network).
 pub const MAX_PENDING_PART: u64 = MAX_STATE_PART_REQUEST * 10000;
+/// Number of invalid state parts a peer can serve before it is banned.
+pub const MAX_INVALID_STATE_PARTS_PER_PEER: u64 = 3;
 /// Time limit per state dump iteration.
 /// A node must check external storage for parts to dump again once time is up.
 pub const STATE_DUMP_ITERATION_TIME_LIMIT_SECS: u64 = 300;
@@ -111,6 +117,13 @@ enum StateSyncInner {
         last_part_id_requested: HashMap<(PeerId, ShardId), PendingRequestStatus>,
         /// Map from which part we requested to whom.
         requested_target: lru::LruCache<(u64, CryptoHash), PeerId>,
+        /// Number of invalid parts served by each peer.
+        invalid_parts: HashMap<PeerId, u64>,
+        /// Peers banned for serving too many invalid parts, not to request anything from them
+        /// again.
+        banned_peers: HashSet<PeerId>,
+        /// Maximum number of parts of a shard requested at the same time.
+        num_concurrent_requests: usize,
     },
     /// Requests the state header from peers but gets the state parts from an
     /// external storage.
@@ -154,12 +167,16 @@ impl StateSync {
         timeout: TimeDuration,
         chain_id: &str,
         sync_config: &SyncConfig,
+        num_concurrent_requests: u32,
         catchup: bool,
     ) -> Self {
         let inner = match sync_config {
             SyncConfig::Peers => StateSyncInner::Peers {
                 last_part_id_requested: Default::default(),
                 requested_target: lru::LruCache::new(MAX_PENDING_PART as usize),
+                invalid_parts: HashMap::new(),
+                banned_peers: HashSet::new(),
+                num_concurrent_requests: num_concurrent_requests as usize,
             },
             SyncConfig::ExternalStorage(ExternalStorageConfig {
                 location,
@@ -218,6 +235,7 @@ impl StateSync {
         chain: &mut Chain,
         epoch_manager: &dyn EpochManagerAdapter,
         highest_height_peers: &[HighestHeightPeerInfo],
+        snapshot_hosts: &[Arc<SnapshotHostInfo>],
         tracking_shards: Vec<ShardId>,
         now: DateTime<Utc>,
         state_parts_task_scheduler: &dyn Fn(ApplyStatePartsRequest),
@@ -247,7 +265,13 @@ impl StateSync {
             let mut run_shard_state_download = false;
             let shard_sync_download = sync_status.entry(shard_id).or_insert_with(|| {
                 run_shard_state_download = true;
-                ShardSyncDownload::new_download_state_header(now)
+                let mut shard_sync_download = ShardSyncDownload::new_download_state_header(now);
+                // The header may already be saved if the node was restarted in the middle of
+                // state sync. Resume the download of the parts then.
+                if chain.get_state_header(shard_id, sync_hash).is_ok() {
+                    shard_sync_download.downloads[0].done = true;
+                }
+                shard_sync_download
             });
 
             let mut shard_sync_done = false;
@@ -348,6 +372,7 @@ impl StateSync {
                     sync_hash,
                     shard_sync_download,
                     highest_height_peers,
+                    snapshot_hosts,
                     runtime_adapter.clone(),
                     state_parts_arbiter_handle,
                 )?;
@@ -445,7 +470,7 @@ impl StateSync {
         sync_hash: CryptoHash,
     ) {
         match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, requested_target } => {
+            StateSyncInner::Peers { last_part_id_requested, requested_target, .. } => {
                 let key = (part_id, sync_hash);
                 // Check that it came from the target that we requested it from.
                 if let Some(target) = requested_target.get(&key) {
@@ -466,7 +491,38 @@ impl StateSync {
         }
     }
 
-    /// Avoids peers that already have outstanding requests for parts.
+    /// Lowers the reputation of the peer the given part was requested from, as it served an
+    /// invalid part, and bans the peer once it served too many of them.
+    fn report_invalid_part(&mut self, part_id: u64, shard_id: ShardId, sync_hash: CryptoHash) {
+        let StateSyncInner::Peers { requested_target, invalid_parts, banned_peers, .. } =
+            &mut self.inner
+        else {
+            return;
+        };
+        let Some(peer_id) = requested_target.get(&(part_id, sync_hash)).cloned() else {
+            return;
+        };
+        metrics::STATE_SYNC_INVALID_PARTS.with_label_values(&[&shard_id.to_string()]).inc();
+        let num_invalid_parts = invalid_parts.entry(peer_id.clone()).or_default();
+        *num_invalid_parts += 1;
+        if *num_invalid_parts < MAX_INVALID_STATE_PARTS_PER_PEER
+            || !banned_peers.insert(peer_id.clone())
+        {
+            return;
+        }
+        tracing::warn!(
+            target: "sync",
+            %peer_id,
+            num_invalid_parts = *num_invalid_parts,
+            "Banning a peer for serving invalid state parts",
+        );
+        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::BanPeer { peer_id, ban_reason: ReasonForBan::BadStatePart },
+        ));
+    }
+
+    /// Avoids peers that already have outstanding requests for parts, and peers banned for serving
+    /// invalid parts.
     fn select_peers(
         &mut self,
         highest_height_peers: &[HighestHeightPeerInfo],
@@ -475,13 +531,14 @@ impl StateSync {
         let peers: Vec<PeerId> =
             highest_height_peers.iter().map(|peer| peer.peer_info.id.clone()).collect();
         let res = match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, .. } => {
+            StateSyncInner::Peers { last_part_id_requested, banned_peers, .. } => {
                 last_part_id_requested.retain(|_, request| !request.expired());
                 peers
                     .into_iter()
                     .filter(|peer| {
                         // If we still have a pending request from this node - don't add another one.
                         !last_part_id_requested.contains_key(&(peer.clone(), shard_id))
+                            && !banned_peers.contains(peer)
                     })
                     .collect::<Vec<_>>()
             }
@@ -498,6 +555,7 @@ impl StateSync {
         sync_hash: CryptoHash,
         shard_sync_download: &mut ShardSyncDownload,
         highest_height_peers: &[HighestHeightPeerInfo],
+        snapshot_hosts: &[Arc<SnapshotHostInfo>],
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         state_parts_arbiter_handle: &ArbiterHandle,
     ) -> Result<(), near_chain::Error> {
@@ -520,6 +578,13 @@ impl StateSync {
                 );
             }
             ShardSyncStatus::StateDownloadParts => {
+                let possible_targets = prefer_snapshot_hosts(
+                    possible_targets,
+                    snapshot_hosts,
+                    shard_id,
+                    sync_hash,
+                    chain,
+                )?;
                 self.request_shard_parts(
                     shard_id,
                     sync_hash,
@@ -583,19 +648,35 @@ impl StateSync {
         // Iterate over all parts that needs to be requested (i.e. download.run_me is true).
         // Parts are ordered such that its index match its part_id.
         match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, requested_target } => {
+            StateSyncInner::Peers {
+                last_part_id_requested,
+                requested_target,
+                num_concurrent_requests,
+                ..
+            } => {
                 // We'll select all the 'highest' peers + validators as candidates (excluding those that gave us timeout in the past).
                 // And for each one of them, we'll ask for up to 16 (MAX_STATE_PART_REQUEST) parts.
                 let possible_targets_sampler =
                     SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
+                // Parts requested but neither received nor timed out yet.
+                let num_in_flight = new_shard_sync_download
+                    .downloads
+                    .iter()
+                    .filter(|download| {
+                        !download.done && !download.error && !download.run_me.load(Ordering::SeqCst)
+                    })
+                    .count();
+                let num_to_request = num_concurrent_requests.saturating_sub(num_in_flight);
 
                 // For every part that needs to be requested it is selected one
                 // peer (target) randomly to request the part from.
                 // IMPORTANT: here we use 'zip' with possible_target_sampler -
                 // which is limited. So at any moment we'll not request more
-                // than possible_targets.len() * MAX_STATE_PART_REQUEST parts.
-                for ((part_id, download), target) in
-                    parts_to_fetch(new_shard_sync_download).zip(possible_targets_sampler)
+                // than possible_targets.len() * MAX_STATE_PART_REQUEST parts,
+                // and never more than num_concurrent_requests parts in flight.
+                for ((part_id, download), target) in parts_to_fetch(new_shard_sync_download)
+                    .zip(possible_targets_sampler)
+                    .take(num_to_request)
                 {
                     sent_request_part(
                         target.clone(),
@@ -663,6 +744,8 @@ impl StateSync {
         chain: &mut Chain,
         epoch_manager: &dyn EpochManagerAdapter,
         highest_height_peers: &[HighestHeightPeerInfo],
+        // Nodes serving state snapshots, preferred to request state parts from.
+        snapshot_hosts: &[Arc<SnapshotHostInfo>],
         // Shards to sync.
         tracking_shards: Vec<ShardId>,
         state_parts_task_scheduler: &dyn Fn(ApplyStatePartsRequest),
@@ -692,6 +775,7 @@ impl StateSync {
             chain,
             epoch_manager,
             highest_height_peers,
+            snapshot_hosts,
             tracking_shards,
             now,
             state_parts_task_scheduler,
@@ -764,6 +848,7 @@ impl StateSync {
                             Err(err) => {
                                 tracing::error!(target: "sync", %shard_id, %hash, part_id, ?err, "State sync set_state_part error");
                                 shard_sync_download.downloads[part_id as usize].error = true;
+                                self.report_invalid_part(part_id, shard_id, hash);
                             }
                         }
                     }
@@ -796,6 +881,14 @@ impl StateSync {
             // Create the vector with entry for each part.
             *shard_sync_download =
                 ShardSyncDownload::new_download_state_parts(now, state_num_parts);
+            let num_parts_resumed =
+                resume_downloaded_parts(shard_id, sync_hash, shard_sync_download, chain)?;
+            if num_parts_resumed > 0 {
+                tracing::info!(target: "sync", %shard_id, %sync_hash, num_parts_resumed, state_num_parts, "Resuming the download of state parts");
+                metrics::STATE_SYNC_PARTS_RESUMED
+                    .with_label_values(&[&shard_id.to_string()])
+                    .inc_by(num_parts_resumed);
+            }
             Ok((false, true))
         } else {
             let download_timeout = now - download.prev_update_time > self.timeout;
@@ -872,6 +965,11 @@ impl StateSync {
         metrics::STATE_SYNC_PARTS_TOTAL
             .with_label_values(&[&shard_id.to_string()])
             .set(num_parts as i64);
+        if let Some(eta) = shard_sync_download.parts_eta(now) {
+            metrics::STATE_SYNC_PARTS_ETA
+                .with_label_values(&[&shard_id.to_string()])
+                .set(eta.as_secs() as i64);
+        }
         // If all parts are done - we can move towards scheduling.
         if parts_done {
             *shard_sync_download = ShardSyncDownload {
@@ -1019,6 +1117,55 @@ impl StateSync {
     }
 }
 
+/// Keeps the targets which advertise a state snapshot of the shard at `sync_hash`, as only they can
+/// generate its state parts. Keeps all the targets if none of them does, e.g. if the snapshot
+/// hosts aren't known yet.
+fn prefer_snapshot_hosts(
+    possible_targets: Vec<PeerId>,
+    snapshot_hosts: &[Arc<SnapshotHostInfo>],
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    chain: &Chain,
+) -> Result<Vec<PeerId>, near_chain::Error> {
+    // Snapshots are identified by the hash of the block before the last block of the previous
+    // epoch.
+    let prev_hash = *chain.get_block_header(&sync_hash)?.prev_hash();
+    let snapshot_hash = *chain.get_block_header(&prev_hash)?.prev_hash();
+    let hosts: HashSet<&PeerId> = snapshot_hosts
+        .iter()
+        .filter(|host| host.sync_hash == snapshot_hash && host.shards.contains(&shard_id))
+        .map(|host| &host.peer_id)
+        .collect();
+    let (preferred_targets, other_targets): (Vec<PeerId>, Vec<PeerId>) =
+        possible_targets.into_iter().partition(|peer_id| hosts.contains(peer_id));
+    if preferred_targets.is_empty() {
+        return Ok(other_targets);
+    }
+    Ok(preferred_targets)
+}
+
+/// Marks the parts already saved to the store as done, so that a download
+/// interrupted by a restart doesn't start over. Parts are saved to the store
+/// only after being validated.
+fn resume_downloaded_parts(
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    shard_sync_download: &mut ShardSyncDownload,
+    chain: &Chain,
+) -> Result<u64, near_chain::Error> {
+    let store = chain.chain_store().store();
+    let mut num_parts_resumed = 0;
+    for (part_id, download) in shard_sync_download.downloads.iter_mut().enumerate() {
+        let key = borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id as u64))?;
+        if store.exists(DBCol::StateParts, &key)? {
+            download.done = true;
+            download.run_me.store(false, Ordering::SeqCst);
+            num_parts_resumed += 1;
+        }
+    }
+    Ok(num_parts_resumed)
+}
+
 /// Returns parts that still need to be fetched.
 fn parts_to_fetch(
     new_shard_sync_download: &mut ShardSyncDownload,
@@ -1281,6 +1428,7 @@ mod test {
             TimeDuration::from_secs(1),
             "chain_id",
             &SyncConfig::Peers,
+            MAX_STATE_PART_REQUEST as u32,
             false,
         );
         let mut new_shard_sync = HashMap::new();
@@ -1341,6 +1489,7 @@ mod test {
                     &mut chain,
                     kv.as_ref(),
                     &[highest_height_peer_info],
+                    &[],
                     vec![0],
                     &apply_parts_fn,
                     &resharding_fn,
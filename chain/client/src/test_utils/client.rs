@@ -208,7 +208,8 @@ pub fn create_chunk(
         *last_block.header().next_bp_hash(),
         block_merkle_tree.root(),
         None,
-    );
+    )
+    .unwrap();
     (chunk, merkle_paths, receipts, block)
 }
 
@@ -236,6 +237,7 @@ pub fn run_catchup(
     loop {
         client.run_catchup(
             highest_height_peers,
+            &[],
             &f,
             &block_catch_up,
             &resharding,
@@ -1,5 +1,6 @@
-use near_network::types::SetChainInfo;
+use near_network::state_witness::{DistributeStateWitnessRequest, SendChunkEndorsementsRequest};
 use near_network::types::{PeerManagerMessageRequest, PeerManagerMessageResponse};
+use near_network::types::{SetChainInfo, SetValidatorSigner};
 
 pub struct PeerManagerMock {
     handle: Box<
@@ -37,3 +38,22 @@ impl actix::Handler<SetChainInfo> for PeerManagerMock {
     type Result = ();
     fn handle(&mut self, _msg: SetChainInfo, _ctx: &mut Self::Context) {}
 }
+
+impl actix::Handler<SetValidatorSigner> for PeerManagerMock {
+    type Result = ();
+    fn handle(&mut self, _msg: SetValidatorSigner, _ctx: &mut Self::Context) {}
+}
+
+impl actix::Handler<DistributeStateWitnessRequest> for PeerManagerMock {
+    type Result = ();
+    fn handle(&mut self, msg: DistributeStateWitnessRequest, ctx: &mut Self::Context) {
+        (self.handle)(PeerManagerMessageRequest::NetworkRequests(msg.into()), ctx);
+    }
+}
+
+impl actix::Handler<SendChunkEndorsementsRequest> for PeerManagerMock {
+    type Result = ();
+    fn handle(&mut self, msg: SendChunkEndorsementsRequest, ctx: &mut Self::Context) {
+        (self.handle)(PeerManagerMessageRequest::NetworkRequests(msg.into()), ctx);
+    }
+}
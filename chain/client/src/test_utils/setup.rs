@@ -8,13 +8,18 @@ use crate::adapter::{
     AnnounceAccountRequest, BlockApproval, BlockHeadersRequest, BlockHeadersResponse, BlockRequest,
     BlockResponse, SetNetworkInfo, StateRequestHeader, StateRequestPart,
 };
-use crate::{start_view_client, Client, ClientActor, SyncAdapter, SyncStatus, ViewClientActor};
+use crate::{
+    start_chunk_witness_actor, start_view_client, ChunkEndorsementPool, ChunkWitnessProducer,
+    ChunkWitnessProductionTracker, ChunkWitnessRequest, Client, ClientActor, SyncAdapter,
+    SyncStatus, ValidatorAccountId, ViewClientActor,
+};
 use actix::{Actor, Addr, AsyncContext, Context};
 use actix_rt::System;
 use chrono::DateTime;
 use chrono::Utc;
 use futures::{future, FutureExt};
 use near_async::actix::AddrWithAutoSpanContextExt;
+use near_async::futures::{AsyncComputationSpawner, RayonAsyncComputationSpawner};
 use near_async::messaging::{CanSend, IntoSender, LateBoundSender, Sender};
 use near_async::time;
 use near_chain::state_snapshot_actor::SnapshotCallbacks;
@@ -56,7 +61,7 @@ use rand::{thread_rng, Rng};
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 pub const TEST_SEED: RngSeed = [3; 32];
@@ -118,6 +123,9 @@ pub fn setup(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_receipt_origins: false,
+            shadow_apply: None,
+            read_only: false,
         },
         None,
     )
@@ -138,15 +146,18 @@ pub fn setup(
     );
 
     let adv = crate::adversarial::Controls::default();
+    let chunk_endorsements = ChunkEndorsementPool::default();
+    let validator_account_id = ValidatorAccountId::new(Some(signer.validator_id().clone()));
 
     let view_client_addr = start_view_client(
-        Some(signer.validator_id().clone()),
+        validator_account_id.clone(),
         chain_genesis.clone(),
         epoch_manager.clone(),
         shard_tracker.clone(),
         runtime.clone(),
         network_adapter.clone(),
         config.clone(),
+        chunk_endorsements.clone(),
         adv.clone(),
     );
 
@@ -160,6 +171,16 @@ pub fn setup(
         config.chunk_request_retry_period,
     );
     let shards_manager_adapter = Arc::new(shards_manager_addr.with_auto_span_context());
+    let chunk_witness_production_tracker = ChunkWitnessProductionTracker::default();
+    let (chunk_witness_addr, _) = start_chunk_witness_actor(
+        &config,
+        &chain_genesis,
+        epoch_manager.clone(),
+        runtime.store().clone(),
+        network_adapter.clone().into_sender(),
+        chunk_witness_production_tracker.clone(),
+        adv.clone(),
+    );
 
     let state_sync_adapter =
         Arc::new(RwLock::new(SyncAdapter::new(Sender::noop(), Sender::noop())));
@@ -172,10 +193,15 @@ pub fn setup(
         runtime,
         network_adapter.clone(),
         shards_manager_adapter.as_sender(),
+        chunk_witness_addr.with_auto_span_context().into_sender(),
+        chunk_witness_production_tracker,
+        chunk_endorsements,
+        validator_account_id,
         Some(signer.clone()),
         enable_doomslug,
         TEST_SEED,
         None,
+        Arc::new(RayonAsyncComputationSpawner),
     )
     .unwrap();
     let client_actor = ClientActor::new(
@@ -246,6 +272,9 @@ pub fn setup_only_view(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_receipt_origins: false,
+            shadow_apply: None,
+            read_only: false,
         },
         None,
     )
@@ -267,13 +296,14 @@ pub fn setup_only_view(
     let adv = crate::adversarial::Controls::default();
 
     start_view_client(
-        Some(signer.validator_id().clone()),
+        ValidatorAccountId::new(Some(signer.validator_id().clone())),
         chain_genesis,
         epoch_manager,
         shard_tracker,
         runtime,
         network_adapter,
         config,
+        ChunkEndorsementPool::default(),
         adv,
     )
 }
@@ -548,6 +578,7 @@ pub fn setup_mock_all_validators(
                             known_producers: vec![],
                             tier1_accounts_keys: vec![],
                             tier1_accounts_data: vec![],
+                            snapshot_hosts: vec![],
                         };
                         client_addr.do_send(SetNetworkInfo(info).with_span_context());
                     }
@@ -834,12 +865,16 @@ pub fn setup_mock_all_validators(
                         | NetworkRequests::TxStatus(_, _, _)
                         | NetworkRequests::SnapshotHostInfo { .. }
                         | NetworkRequests::Challenge(_) => {}
-                        NetworkRequests::ChunkStateWitness(_, _) => {
+                        NetworkRequests::ChunkStateWitness(_, _)
+                        | NetworkRequests::ChunkStateWitnessRequest(_, _) => {
                             // TODO(#10265): Implement for integration tests.
                         },
-                        NetworkRequests::ChunkEndorsement(_, _) => {
+                        NetworkRequests::ChunkEndorsement(_, _)
+                        | NetworkRequests::ChunkEndorsementBatch(_, _) => {
                             // TODO(#10265): Implement for integration tests.
                         },
+                        #[cfg(feature = "new_epoch_sync")]
+                        NetworkRequests::EpochSyncRequest { .. } => {},
                     };
                 }
                 resp
@@ -931,6 +966,7 @@ pub fn setup_client_with_runtime(
     archive: bool,
     save_trie_changes: bool,
     snapshot_callbacks: Option<SnapshotCallbacks>,
+    async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
 ) -> Client {
     let validator_signer =
         account_id.map(|x| Arc::new(create_test_signer(x.as_str())) as Arc<dyn ValidatorSigner>);
@@ -945,6 +981,17 @@ pub fn setup_client_with_runtime(
         true,
     );
     config.epoch_length = chain_genesis.epoch_length;
+    let chunk_witness_production_tracker = ChunkWitnessProductionTracker::default();
+    let chunk_witness_adapter =
+        Arc::new(SynchronousChunkWitnessAdapter::new(ChunkWitnessProducer::new(
+            &config,
+            &chain_genesis,
+            epoch_manager.clone(),
+            runtime.store().clone(),
+            network_adapter.clone().into_sender(),
+            chunk_witness_production_tracker.clone(),
+            crate::adversarial::Controls::default(),
+        )));
     let state_sync_adapter =
         Arc::new(RwLock::new(SyncAdapter::new(Sender::noop(), Sender::noop())));
     let mut client = Client::new(
@@ -956,10 +1003,15 @@ pub fn setup_client_with_runtime(
         runtime,
         network_adapter,
         shards_manager_adapter.client.into(),
+        chunk_witness_adapter.as_sender(),
+        chunk_witness_production_tracker,
+        ChunkEndorsementPool::default(),
+        ValidatorAccountId::new(validator_signer.as_ref().map(|s| s.validator_id().clone())),
         validator_signer,
         enable_doomslug,
         rng_seed,
         snapshot_callbacks,
+        async_computation_spawner,
     )
     .unwrap();
     client.sync_status = SyncStatus::NoSync;
@@ -997,6 +1049,7 @@ pub fn setup_client(
         archive,
         save_trie_changes,
         None,
+        Arc::new(RayonAsyncComputationSpawner),
     )
 }
 
@@ -1027,6 +1080,9 @@ pub fn setup_synchronous_shards_manager(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_receipt_origins: false,
+            shadow_apply: None,
+            read_only: false,
         }, // irrelevant
         None,
     )
@@ -1047,6 +1103,25 @@ pub fn setup_synchronous_shards_manager(
     Arc::new(SynchronousShardsManagerAdapter::new(shards_manager)).into()
 }
 
+/// Produces chunk state witnesses on the calling thread, so that they are
+/// sent right after the chunk is produced in tests.
+pub struct SynchronousChunkWitnessAdapter {
+    // Need a mutex here because CanSend requires Sync.
+    producer: Mutex<ChunkWitnessProducer>,
+}
+
+impl SynchronousChunkWitnessAdapter {
+    pub fn new(producer: ChunkWitnessProducer) -> Self {
+        Self { producer: Mutex::new(producer) }
+    }
+}
+
+impl CanSend<ChunkWitnessRequest> for SynchronousChunkWitnessAdapter {
+    fn send(&self, request: ChunkWitnessRequest) {
+        self.producer.lock().unwrap().handle_request(request);
+    }
+}
+
 pub fn setup_client_with_synchronous_shards_manager(
     store: Store,
     vs: ValidatorSchedule,
@@ -1090,6 +1165,7 @@ pub fn setup_client_with_synchronous_shards_manager(
         archive,
         save_trie_changes,
         None,
+        Arc::new(RayonAsyncComputationSpawner),
     )
 }
 
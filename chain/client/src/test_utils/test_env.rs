@@ -4,6 +4,7 @@ use std::time::Instant;
 
 use crate::adapter::ProcessTxResponse;
 use crate::Client;
+use near_async::futures::AsyncComputationSpawner;
 use near_async::messaging::CanSend;
 use near_chain::test_utils::ValidatorSchedule;
 use near_chain::{ChainGenesis, Provenance};
@@ -19,7 +20,7 @@ use near_o11y::testonly::TracingCapture;
 use near_parameters::RuntimeConfig;
 use near_primitives::action::delegate::{DelegateAction, NonDelegateAction, SignedDelegateAction};
 use near_primitives::block::Block;
-use near_primitives::chunk_validation::ChunkEndorsement;
+use near_primitives::chunk_validation::{ChunkEndorsement, ChunkStateWitness};
 use near_primitives::epoch_manager::RngSeed;
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
@@ -54,6 +55,10 @@ pub struct TestEnv {
     pub(crate) seeds: HashMap<AccountId, RngSeed>,
     pub(crate) archive: bool,
     pub(crate) save_trie_changes: bool,
+    pub(crate) async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
+    /// Protocol versions voted for by the clients in the blocks they produce,
+    /// instead of the latest protocol version.
+    pub(crate) protocol_version_overrides: HashMap<AccountId, ProtocolVersion>,
 }
 
 impl TestEnv {
@@ -70,10 +75,45 @@ impl TestEnv {
     /// Produces block by given client, which may kick off chunk production.
     /// This means that transactions added before this call will be included in the next block produced by this validator.
     pub fn produce_block(&mut self, id: usize, height: BlockHeight) {
-        let block = self.clients[id].produce_block(height).unwrap();
+        let account_id = self.get_client_id(id).clone();
+        let block = self.produce_block_by(&account_id, height);
         self.process_block(id, block.unwrap(), Provenance::PRODUCED);
     }
 
+    /// Produces a block at `height` by the given client without processing
+    /// it. If the client has a protocol version override, the block votes for
+    /// that protocol version.
+    pub fn produce_block_by(
+        &mut self,
+        account_id: &AccountId,
+        height: BlockHeight,
+    ) -> Option<Block> {
+        let client = self.account_indices.lookup_mut(&mut self.clients, account_id);
+        let mut block = client.produce_block(height).unwrap()?;
+        if let Some(protocol_version) = self.protocol_version_overrides.get(account_id) {
+            let signer = client.validator_signer.clone().unwrap();
+            block.mut_header().set_latest_protocol_version(*protocol_version);
+            block.mut_header().resign(signer.as_ref());
+        }
+        Some(block)
+    }
+
+    /// Makes the client vote for `protocol_version` in the blocks produced
+    /// through `produce_block` and `produce_block_by`, as if it was running a
+    /// binary of that protocol version. `None` removes the override.
+    pub fn set_protocol_version_override(
+        &mut self,
+        account_id: &AccountId,
+        protocol_version: Option<ProtocolVersion>,
+    ) {
+        match protocol_version {
+            Some(protocol_version) => {
+                self.protocol_version_overrides.insert(account_id.clone(), protocol_version)
+            }
+            None => self.protocol_version_overrides.remove(account_id),
+        };
+    }
+
     /// Pause processing of the given block, which means that the background
     /// thread which applies the chunks on the block will get blocked until
     /// `resume_block_processing` is called.
@@ -262,6 +302,24 @@ impl TestEnv {
     }
 
     pub fn propagate_chunk_state_witnesses(&mut self) {
+        for (account_id, result) in
+            self.propagate_chunk_state_witnesses_with(|_, witness| Some(witness))
+        {
+            result.unwrap_or_else(|err| {
+                panic!("{account_id} failed to process chunk state witness: {err:?}")
+            });
+        }
+    }
+
+    /// Delivers the chunk state witnesses sent by all clients to their target
+    /// chunk validators. `intercept` is called for each (target, witness) pair
+    /// and can drop the witness by returning `None`, or replace it, e.g. with
+    /// an invalid one. Returns the result of processing each delivered witness.
+    pub fn propagate_chunk_state_witnesses_with(
+        &mut self,
+        mut intercept: impl FnMut(&AccountId, ChunkStateWitness) -> Option<ChunkStateWitness>,
+    ) -> Vec<(AccountId, Result<(), near_chain::Error>)> {
+        let mut results = Vec::new();
         for idx in 0..self.clients.len() {
             let _span =
                 tracing::debug_span!(target: "test", "propagate_chunk_state_witnesses", client=idx)
@@ -273,10 +331,14 @@ impl TestEnv {
                 ) = msg
                 {
                     for account in accounts {
-                        self.account_indices
+                        let Some(witness) = intercept(&account, chunk_state_witness.clone()) else {
+                            continue;
+                        };
+                        let result = self
+                            .account_indices
                             .lookup_mut(&mut self.clients, &account)
-                            .process_chunk_state_witness(chunk_state_witness.clone())
-                            .unwrap();
+                            .process_chunk_state_witness(witness);
+                        results.push((account, result));
                     }
                     None
                 } else {
@@ -284,28 +346,67 @@ impl TestEnv {
                 }
             });
         }
+        results
+    }
+
+    /// Drops all chunk state witnesses sent by the clients so far.
+    pub fn drop_chunk_state_witnesses(&mut self) {
+        self.propagate_chunk_state_witnesses_with(|_, _| None);
     }
 
     pub fn get_all_chunk_endorsements(&mut self) -> Vec<ChunkEndorsement> {
-        let mut approvals = Vec::new();
-        for idx in 0..self.clients.len() {
-            let _span =
-                tracing::debug_span!(target: "test", "get_all_chunk_endorsements", client=idx)
-                    .entered();
+        self.take_chunk_endorsements().into_iter().map(|(_, endorsement)| endorsement).collect()
+    }
 
-            self.network_adapters[idx].handle_filtered(|msg| {
-                if let PeerManagerMessageRequest::NetworkRequests(
-                    NetworkRequests::ChunkEndorsement(_, endorsement),
-                ) = msg
-                {
-                    approvals.push(endorsement);
+    /// Takes the chunk endorsements sent by all clients so far, together with
+    /// the block producer each of them is sent to.
+    pub fn take_chunk_endorsements(&mut self) -> Vec<(AccountId, ChunkEndorsement)> {
+        let mut endorsements = Vec::new();
+        for idx in 0..self.clients.len() {
+            let _span = tracing::debug_span!(target: "test", "take_chunk_endorsements", client=idx)
+                .entered();
+
+            self.clients[idx].chunk_validator.flush_all_chunk_endorsements();
+            self.network_adapters[idx].handle_filtered(|msg| match msg {
+                PeerManagerMessageRequest::NetworkRequests(NetworkRequests::ChunkEndorsement(
+                    target,
+                    endorsement,
+                )) => {
+                    endorsements.push((target, endorsement));
                     None
-                } else {
-                    Some(msg)
                 }
+                PeerManagerMessageRequest::NetworkRequests(
+                    NetworkRequests::ChunkEndorsementBatch(target, batch),
+                ) => {
+                    endorsements
+                        .extend(batch.into_iter().map(|endorsement| (target.clone(), endorsement)));
+                    None
+                }
+                msg => Some(msg),
             });
         }
-        approvals
+        endorsements
+    }
+
+    /// Delivers the chunk endorsements sent by all clients so far to their
+    /// target block producers, except for those rejected by `filter`.
+    /// Returns the delivered endorsements.
+    pub fn propagate_chunk_endorsements_with(
+        &mut self,
+        mut filter: impl FnMut(&AccountId, &ChunkEndorsement) -> bool,
+    ) -> Vec<ChunkEndorsement> {
+        let mut delivered = Vec::new();
+        for (target, endorsement) in self.take_chunk_endorsements() {
+            if !filter(&target, &endorsement) {
+                continue;
+            }
+            self.account_indices
+                .lookup_mut(&mut self.clients, &target)
+                .process_chunk_endorsement(endorsement.clone())
+                .unwrap();
+            delivered.push(endorsement);
+        }
+        delivered
     }
 
     pub fn send_money(&mut self, id: usize) -> ProcessTxResponse {
@@ -440,6 +541,7 @@ impl TestEnv {
             self.archive,
             self.save_trie_changes,
             None,
+            self.async_computation_spawner.clone(),
         )
     }
 
@@ -3,6 +3,7 @@ use super::test_env::TestEnv;
 use super::{AccountIndices, TEST_SEED};
 use actix_rt::System;
 use itertools::{multizip, Itertools};
+use near_async::futures::{AsyncComputationSpawner, RayonAsyncComputationSpawner};
 use near_async::messaging::IntoSender;
 use near_chain::state_snapshot_actor::SnapshotCallbacks;
 use near_chain::test_utils::{KeyValueRuntime, MockEpochManager, ValidatorSchedule};
@@ -43,6 +44,7 @@ pub struct TestEnvBuilder {
     chain_genesis: ChainGenesis,
     clients: Vec<AccountId>,
     validators: Vec<AccountId>,
+    validator_schedule: Option<ValidatorSchedule>,
     home_dirs: Option<Vec<PathBuf>>,
     stores: Option<Vec<Store>>,
     epoch_managers: Option<Vec<EpochManagerKind>>,
@@ -56,6 +58,7 @@ pub struct TestEnvBuilder {
     archive: bool,
     save_trie_changes: bool,
     state_snapshot_enabled: bool,
+    async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
 }
 
 /// Builder for the [`TestEnv`] structure.
@@ -72,6 +75,7 @@ impl TestEnvBuilder {
             chain_genesis,
             clients,
             validators,
+            validator_schedule: None,
             home_dirs: None,
             stores: None,
             epoch_managers: None,
@@ -83,6 +87,7 @@ impl TestEnvBuilder {
             archive: false,
             save_trie_changes: true,
             state_snapshot_enabled: false,
+            async_computation_spawner: Arc::new(RayonAsyncComputationSpawner),
         }
     }
 
@@ -134,6 +139,24 @@ impl TestEnvBuilder {
         self.validators(Self::make_accounts(num))
     }
 
+    /// Sets the schedule used by the mock epoch managers to assign block
+    /// producers, chunk producers and chunk validators. Unlike the real epoch
+    /// manager, the schedule allows these roles to be held by distinct,
+    /// non-overlapping sets of accounts.
+    pub fn validator_schedule(mut self, vs: ValidatorSchedule) -> Self {
+        assert!(
+            self.epoch_managers.is_none(),
+            "Cannot set validator_schedule after epoch_managers"
+        );
+        assert!(
+            self.num_shards.is_none(),
+            "Cannot set both num_shards and validator_schedule at the same time"
+        );
+        self.validators = vs.all_block_producers().cloned().collect();
+        self.validator_schedule = Some(vs);
+        self
+    }
+
     fn ensure_home_dirs(mut self) -> Self {
         if self.home_dirs.is_none() {
             let home_dirs = (0..self.clients.len())
@@ -271,8 +294,10 @@ impl TestEnvBuilder {
         }
         let epoch_managers: Vec<EpochManagerKind> = (0..ret.clients.len())
             .map(|i| {
-                let vs = ValidatorSchedule::new_with_shards(ret.num_shards.unwrap_or(1))
-                    .block_producers_per_epoch(vec![ret.validators.clone()]);
+                let vs = ret.validator_schedule.clone().unwrap_or_else(|| {
+                    ValidatorSchedule::new_with_shards(ret.num_shards.unwrap_or(1))
+                        .block_producers_per_epoch(vec![ret.validators.clone()])
+                });
                 MockEpochManager::new_with_validators(
                     ret.stores.as_ref().unwrap()[i].clone(),
                     vs,
@@ -340,14 +365,21 @@ impl TestEnvBuilder {
     ///
     /// Note that in order to track *NO* shards, just don't override shard_trackers.
     pub fn track_all_shards(self) -> Self {
+        let tracked_configs = vec![TrackedConfig::AllShards; self.clients.len()];
+        self.tracked_configs(tracked_configs)
+    }
+
+    /// Constructs ShardTracker with the given tracked config for each instance.
+    pub fn tracked_configs(self, tracked_configs: Vec<TrackedConfig>) -> Self {
         let ret = self.ensure_epoch_managers();
         let shard_trackers = ret
             .epoch_managers
             .as_ref()
             .unwrap()
             .iter()
-            .map(|epoch_manager| {
-                ShardTracker::new(TrackedConfig::AllShards, epoch_manager.clone().into_adapter())
+            .zip(tracked_configs)
+            .map(|(epoch_manager, tracked_config)| {
+                ShardTracker::new(tracked_config, epoch_manager.clone().into_adapter())
             })
             .collect();
         ret.shard_trackers(shard_trackers)
@@ -445,6 +477,17 @@ impl TestEnvBuilder {
         self
     }
 
+    /// Overrides how the clients spawn async computations, e.g. chunk state
+    /// witness validation. By default they run on the rayon thread pool; tests
+    /// can pass a TestLoop spawner to run them deterministically.
+    pub fn async_computation_spawner(
+        mut self,
+        async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
+    ) -> Self {
+        self.async_computation_spawner = async_computation_spawner;
+        self
+    }
+
     /// Constructs new `TestEnv` structure.
     ///
     /// If no clients were configured (either through count or vector) one
@@ -531,6 +574,7 @@ impl TestEnvBuilder {
                         self.archive,
                         self.save_trie_changes,
                         Some(snapshot_callbacks),
+                        self.async_computation_spawner.clone(),
                     )
                 })
                 .collect();
@@ -553,6 +597,8 @@ impl TestEnvBuilder {
             seeds,
             archive: self.archive,
             save_trie_changes: self.save_trie_changes,
+            async_computation_spawner: self.async_computation_spawner,
+            protocol_version_overrides: HashMap::new(),
         }
     }
 
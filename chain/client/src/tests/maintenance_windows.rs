@@ -2,7 +2,9 @@ use crate::test_utils::setup_no_network;
 use actix::System;
 use futures::{future, FutureExt};
 use near_actix_test_utils::run_actix;
-use near_client_primitives::types::GetMaintenanceWindows;
+use near_client_primitives::types::{
+    GetMaintenanceWindows, GetScheduledMaintenanceWindow, GetScheduledMaintenanceWindowError,
+};
 
 use near_o11y::testonly::init_test_logger;
 use near_o11y::WithSpanContextExt;
@@ -58,3 +60,51 @@ fn test_get_maintenance_windows_for_not_validator() {
         actix::spawn(actor);
     });
 }
+
+#[test]
+fn test_get_scheduled_maintenance_window() {
+    init_test_logger();
+    run_actix(async {
+        let actor_handles =
+            setup_no_network(vec!["test".parse().unwrap()], "other".parse().unwrap(), true, true);
+        let actor = actor_handles.view_client_actor.send(
+            GetScheduledMaintenanceWindow {
+                account_id: Some("alice".parse().unwrap()),
+                num_blocks: Some(5),
+            }
+            .with_span_context(),
+        );
+        let actor = actor.then(|res| {
+            let view = res.unwrap().unwrap();
+            // `alice` has no duties until the end of the next epoch.
+            assert_eq!(view.window, Some(1..20));
+            assert_eq!(view.num_blocks, 5);
+            assert!(!view.auto_restart);
+            System::current().stop();
+            future::ready(())
+        });
+        actix::spawn(actor);
+    });
+}
+
+#[test]
+fn test_get_scheduled_maintenance_window_not_declared() {
+    init_test_logger();
+    run_actix(async {
+        let actor_handles =
+            setup_no_network(vec!["test".parse().unwrap()], "other".parse().unwrap(), true, true);
+        let actor = actor_handles.view_client_actor.send(
+            GetScheduledMaintenanceWindow { account_id: None, num_blocks: None }
+                .with_span_context(),
+        );
+        let actor = actor.then(|res| {
+            assert!(matches!(
+                res.unwrap(),
+                Err(GetScheduledMaintenanceWindowError::NoMaintenanceDeclared)
+            ));
+            System::current().stop();
+            future::ready(())
+        });
+        actix::spawn(actor);
+    });
+}
@@ -10,6 +10,7 @@ use near_primitives::sharding::ShardChunkHeader;
 use near_primitives::sharding::ShardChunkHeaderV3;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::types::{AccountId, EpochId};
 use near_primitives::utils::MaybeValidated;
 use std::sync::Arc;
 
@@ -77,7 +78,8 @@ fn test_bad_shard_id() {
         chunk.tx_root(),
         chunk.prev_validator_proposals().collect(),
         &validator_signer,
-    );
+    )
+    .unwrap();
     modified_chunk.height_included = 2;
     chunks[0] = ShardChunkHeader::V3(modified_chunk);
     block.mut_header().get_mut().inner_rest.chunk_headers_root =
@@ -142,3 +144,35 @@ fn test_bad_block_signature() {
     let _ =
         env.clients[0].process_block_test(MaybeValidated::from(block), Provenance::NONE).unwrap();
 }
+
+/// Test that the validator schedule can assign chunk validation to accounts
+/// which neither produce blocks nor chunks.
+#[test]
+fn test_distinct_chunk_validators() {
+    let accounts: Vec<AccountId> =
+        ["test0", "test1", "test2", "test3"].iter().map(|a| a.parse().unwrap()).collect();
+    let vs = test_utils::ValidatorSchedule::new_with_shards(2)
+        .block_producers_per_epoch(vec![vec![accounts[0].clone(), accounts[1].clone()]])
+        .chunk_validators_per_epoch_per_shard(vec![vec![
+            vec![accounts[2].clone()],
+            vec![accounts[3].clone()],
+        ]]);
+    let env = TestEnv::builder(ChainGenesis::test())
+        .clients(accounts.clone())
+        .validator_schedule(vs)
+        .build();
+    assert_eq!(env.validators, accounts[..2].to_vec());
+
+    let epoch_manager = env.clients[0].epoch_manager.clone();
+    let epoch_id = &EpochId::default();
+    for shard_id in 0..2 {
+        let chunk_validators = epoch_manager.get_chunk_validators(epoch_id, shard_id, 2).unwrap();
+        assert_eq!(
+            chunk_validators.into_keys().collect::<Vec<_>>(),
+            vec![accounts[2 + shard_id as usize].clone()]
+        );
+        let chunk_producer = epoch_manager.get_chunk_producer(epoch_id, 2, shard_id).unwrap();
+        assert!(accounts[..2].contains(&chunk_producer));
+    }
+    assert_eq!(epoch_manager.get_epoch_chunk_validators(epoch_id).unwrap().len(), 2);
+}
@@ -0,0 +1,78 @@
+use crate::test_utils::TestEnv;
+use assert_matches::assert_matches;
+use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
+use near_client_primitives::types::{Error, ValidatorSignedHeights};
+use near_primitives::static_clock::StaticClock;
+use near_primitives::test_utils::create_test_signer;
+use near_primitives::validator_signer::ValidatorSigner;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Test that a client with its validator key detached stops producing blocks
+/// and chunks and signing approvals and chunk endorsements, and that once the
+/// key is attached back it doesn't sign at the heights signed by another node.
+#[test]
+fn test_detached_client_stops_producing_and_signing() {
+    let mut env = TestEnv::builder(ChainGenesis::test()).build();
+    let block = env.clients[0].produce_block(1).unwrap().unwrap();
+    env.process_block(0, block.clone(), Provenance::PRODUCED);
+    let client = &mut env.clients[0];
+    assert_eq!(client.validator_signed_heights().block_height, 1);
+
+    assert!(client.detach_validator_signer());
+    assert!(!client.detach_validator_signer());
+    let signed_heights = client.validator_signed_heights();
+    assert_matches!(client.produce_block(2), Err(Error::BlockProducer(_)));
+    let chunk_header = block.chunks()[0].clone();
+    assert_matches!(
+        client.produce_chunk(*block.hash(), block.header().epoch_id(), chunk_header.clone(), 2, 0),
+        Err(Error::ChunkProducer(_))
+    );
+    assert_matches!(
+        client.chunk_validator.endorse_own_chunk(block.header().epoch_id(), &chunk_header),
+        Err(near_chain::Error::NotAValidator)
+    );
+    let _ = client.check_and_update_doomslug_tip();
+    let approvals = client.doomslug.process_timer(StaticClock::instant() + Duration::from_secs(10));
+    assert!(approvals.is_empty());
+    assert_eq!(client.validator_signed_heights(), signed_heights);
+
+    // The key is attached back after another node signed up to height 3.
+    let floor = ValidatorSignedHeights {
+        block_height: 3,
+        approval_height: 3,
+        chunk_height: 3,
+        endorsement_height: 3,
+    };
+    assert!(client.attach_validator_signer(Some(floor)).unwrap());
+    assert!(!client.attach_validator_signer(None).unwrap());
+    assert!(client.doomslug.get_largest_target_height() >= 3);
+    assert!(client.chain.chain_store().largest_target_height().unwrap() >= 3);
+    assert_matches!(client.produce_block(2), Ok(None));
+    assert_matches!(client.produce_block(3), Ok(None));
+    assert_matches!(client.produce_block(4), Ok(Some(_)));
+    assert_eq!(client.validator_signed_heights().block_height, 4);
+}
+
+/// Test that a reloaded validator key for another account is ignored, and that
+/// once the key is removed the client stops producing blocks and the view
+/// client stops acting as the validator, until the node is restarted.
+#[test]
+fn test_removed_validator_key() {
+    let mut env = TestEnv::builder(ChainGenesis::test()).build();
+    let client = &mut env.clients[0];
+    let account_id = client.validator_signer.as_ref().unwrap().validator_id().clone();
+
+    client.update_validator_signer(Some(Arc::new(create_test_signer("other"))));
+    assert_eq!(client.validator_signer.as_ref().unwrap().validator_id(), &account_id);
+    assert_eq!(client.validator_account_id.get(), Some(account_id.clone()));
+
+    client.update_validator_signer(None);
+    assert!(client.validator_signer.is_none());
+    assert_eq!(client.validator_account_id.get(), None);
+    assert_matches!(client.produce_block(1), Err(Error::BlockProducer(_)));
+
+    client.update_validator_signer(Some(Arc::new(create_test_signer(account_id.as_str()))));
+    assert!(client.validator_signer.is_none());
+    assert_eq!(client.validator_account_id.get(), None);
+}
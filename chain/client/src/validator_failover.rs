@@ -0,0 +1,480 @@
+//! Coordination of two nodes holding the key of the same validator, so that exactly one of them
+//! signs at any time, see `ValidatorFailoverConfig`.
+//!
+//! Both nodes start with the validator key detached and send each other a signed heartbeat every
+//! `heartbeat_period`, telling whether they sign. A node starts signing when:
+//! - the other node is alive and doesn't sign, and this node is the primary, or
+//! - the other node has been silent for `failover_timeout`, this node has been running for that
+//!   long, its head moved in the meantime, it didn't see any new block or chunk of the validator,
+//!   which would mean that the other node still produces them, and it saw the validator miss a
+//!   block or a chunk it was assigned. Silence alone isn't enough, as the link between the nodes
+//!   may be down while the other node still signs, so the timeout must be long enough for the
+//!   validator to be assigned some block or chunk in the meantime.
+//!
+//! A signing node keeps signing unless the other node signs too, in which case the backup stops.
+//! Heartbeats carry the heights the sender signed up to, and a node starting to sign doesn't sign
+//! at or below the last ones it received.
+
+use crate::client::Client;
+use crate::metrics;
+use borsh::BorshDeserialize;
+use near_chain_configs::{FailoverRole, ValidatorFailoverConfig};
+use near_primitives::block::Tip;
+use near_primitives::static_clock::StaticClock;
+use near_primitives::types::{AccountId, BlockHeight};
+use near_primitives::validator_failover::{
+    FailoverHeartbeat, FailoverHeartbeatInner, ValidatorSignedHeights,
+};
+use near_primitives::validator_signer::{ValidatorSigner, ValidatorSignerError};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// Heartbeats are much smaller, larger datagrams are truncated and rejected.
+const MAX_HEARTBEAT_SIZE: usize = 1024;
+
+/// Largest number of new blocks looked at each time the heartbeats are exchanged.
+const MAX_OBSERVED_BLOCKS: usize = 100;
+
+/// What a node knows about the other node and the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Observations {
+    /// Whether the other node signs, according to its last heartbeat, or `None` if it has been
+    /// silent for the failover timeout.
+    peer_signing: Option<bool>,
+    /// Whether the node has been running for the failover timeout, so that it had the time to
+    /// hear from the other node.
+    warmed_up: bool,
+    /// Whether the head of the node moved within the failover timeout.
+    following_chain: bool,
+    /// Whether a block or a chunk of the validator produced by the other node was seen within the
+    /// failover timeout.
+    peer_producing: bool,
+    /// Whether the validator missed a block or a chunk it was assigned within the failover
+    /// timeout, while this node didn't sign.
+    peer_missing: bool,
+}
+
+/// Returns whether the node should sign.
+fn should_sign(role: FailoverRole, signing: bool, observations: &Observations) -> bool {
+    match observations.peer_signing {
+        // Both nodes sign, the backup gives way.
+        Some(true) => signing && role == FailoverRole::Primary,
+        Some(false) => signing || role == FailoverRole::Primary,
+        None => {
+            signing
+                || (observations.warmed_up
+                    && observations.following_chain
+                    && !observations.peer_producing
+                    && observations.peer_missing)
+        }
+    }
+}
+
+pub(crate) struct ValidatorFailover {
+    config: ValidatorFailoverConfig,
+    socket: UdpSocket,
+    started: Instant,
+    /// Last valid heartbeat of the other node, and when it was received.
+    peer: Option<(FailoverHeartbeatInner, Instant)>,
+    /// Height of the head, and when it was first seen.
+    head: Option<(BlockHeight, Instant)>,
+    /// When a block or a chunk of the validator produced by the other node was last seen.
+    last_peer_produced: Option<Instant>,
+    /// When the validator was last seen missing a block or a chunk while this node didn't sign.
+    last_peer_missed: Option<Instant>,
+}
+
+impl ValidatorFailover {
+    pub fn new(config: ValidatorFailoverConfig) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(config.listen_addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            config,
+            socket,
+            started: StaticClock::instant(),
+            peer: None,
+            head: None,
+            last_peer_produced: None,
+            last_peer_missed: None,
+        })
+    }
+
+    pub fn heartbeat_period(&self) -> Duration {
+        self.config.heartbeat_period
+    }
+
+    /// Exchanges heartbeats with the other node and returns whether the node should sign, or
+    /// `None` if it has no validator key.
+    pub fn run(&mut self, client: &Client) -> Option<bool> {
+        let validator_signer = client.any_validator_signer()?.clone();
+        let signing = client.validator_signer.is_some();
+        let now = StaticClock::instant();
+        let now_ms = StaticClock::utc().timestamp_millis() as u64;
+        self.receive_heartbeats(validator_signer.as_ref(), now, now_ms);
+
+        let head = match client.chain.head() {
+            Ok(head) => head,
+            Err(err) => {
+                tracing::debug!(target: "client", ?err, "Failed to get the head for failover");
+                return None;
+            }
+        };
+        if self.head.map_or(true, |(height, _)| height != head.height) {
+            // The node doesn't produce blocks and chunks while it doesn't sign, so new blocks and
+            // chunks of the validator come from the other node.
+            if !signing {
+                if let Err(err) =
+                    self.observe_new_blocks(client, validator_signer.validator_id(), &head, now)
+                {
+                    tracing::debug!(target: "client", ?err, "Failed to observe the new blocks for failover");
+                }
+            }
+            self.head = Some((head.height, now));
+        }
+
+        let sign = should_sign(self.config.role, signing, &self.observations(now));
+        if sign != signing {
+            tracing::info!(
+                target: "client",
+                role = ?self.config.role,
+                sign,
+                peer = ?self.peer.as_ref().map(|(heartbeat, _)| heartbeat),
+                "Validator failover changes whether the node signs"
+            );
+        }
+        metrics::VALIDATOR_FAILOVER_SIGNING.set(sign as i64);
+        match self.heartbeat(
+            validator_signer.as_ref(),
+            sign,
+            head.height,
+            client.validator_signed_heights(),
+            now_ms,
+        ) {
+            Ok(heartbeat) => self.send_heartbeat(&heartbeat),
+            Err(err) => {
+                tracing::warn!(target: "client", %err, "Failed to sign failover heartbeat")
+            }
+        }
+        Some(sign)
+    }
+
+    /// Returns the heights the other node signed up to, according to its last heartbeat.
+    pub fn peer_signed_heights(&self) -> Option<ValidatorSignedHeights> {
+        self.peer.as_ref().map(|(heartbeat, _)| heartbeat.signed_heights)
+    }
+
+    /// Looks at the blocks on top of the previous head for the blocks and chunks the validator
+    /// produced or missed.
+    fn observe_new_blocks(
+        &mut self,
+        client: &Client,
+        validator_id: &AccountId,
+        head: &Tip,
+        now: Instant,
+    ) -> Result<(), near_chain::Error> {
+        let epoch_manager = client.epoch_manager.as_ref();
+        let is_validator = |producer: Result<AccountId, _>| {
+            producer.is_ok_and(|producer| &producer == validator_id)
+        };
+        let prev_head_height = self
+            .head
+            .map_or(head.height.saturating_sub(1), |(height, _)| height)
+            .max(client.chain.genesis().height());
+        let mut block_hash = head.last_block_hash;
+        for _ in 0..MAX_OBSERVED_BLOCKS {
+            let block = client.chain.get_block(&block_hash)?;
+            let header = block.header();
+            if header.height() <= prev_head_height {
+                break;
+            }
+            let epoch_id = header.epoch_id();
+            if is_validator(epoch_manager.get_block_producer(epoch_id, header.height())) {
+                self.last_peer_produced = Some(now);
+            }
+            let prev_height = header.prev_height().unwrap_or(header.height() - 1);
+            for height in prev_height + 1..header.height() {
+                if is_validator(epoch_manager.get_block_producer(epoch_id, height)) {
+                    self.last_peer_missed = Some(now);
+                }
+            }
+            for chunk in block.chunks().iter() {
+                let producer =
+                    epoch_manager.get_chunk_producer(epoch_id, header.height(), chunk.shard_id());
+                if is_validator(producer) {
+                    if chunk.height_included() == header.height() {
+                        self.last_peer_produced = Some(now);
+                    } else {
+                        self.last_peer_missed = Some(now);
+                    }
+                }
+            }
+            block_hash = *header.prev_hash();
+        }
+        Ok(())
+    }
+
+    fn observations(&self, now: Instant) -> Observations {
+        let timeout = self.config.failover_timeout;
+        let recent = |instant: Instant| now.saturating_duration_since(instant) < timeout;
+        Observations {
+            peer_signing: self
+                .peer
+                .as_ref()
+                .filter(|(_, received)| recent(*received))
+                .map(|(heartbeat, _)| heartbeat.signing),
+            warmed_up: !recent(self.started),
+            following_chain: self.head.is_some_and(|(_, seen)| recent(seen)),
+            peer_producing: self.last_peer_produced.is_some_and(recent),
+            peer_missing: self.last_peer_missed.is_some_and(recent),
+        }
+    }
+
+    fn heartbeat(
+        &self,
+        validator_signer: &dyn ValidatorSigner,
+        signing: bool,
+        head_height: BlockHeight,
+        signed_heights: ValidatorSignedHeights,
+        now_ms: u64,
+    ) -> Result<FailoverHeartbeat, ValidatorSignerError> {
+        FailoverHeartbeat::new(
+            FailoverHeartbeatInner {
+                account_id: validator_signer.validator_id().clone(),
+                primary: self.config.role == FailoverRole::Primary,
+                signing,
+                head_height,
+                signed_heights,
+                timestamp_ms: now_ms,
+            },
+            validator_signer,
+        )
+    }
+
+    fn send_heartbeat(&self, heartbeat: &FailoverHeartbeat) {
+        let data = borsh::to_vec(heartbeat).unwrap();
+        if let Err(err) = self.socket.send_to(&data, self.config.peer_addr) {
+            tracing::debug!(target: "client", ?err, peer_addr = ?self.config.peer_addr, "Failed to send failover heartbeat");
+        }
+    }
+
+    fn receive_heartbeats(
+        &mut self,
+        validator_signer: &dyn ValidatorSigner,
+        now: Instant,
+        now_ms: u64,
+    ) {
+        let mut buf = [0; MAX_HEARTBEAT_SIZE];
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    tracing::debug!(target: "client", ?err, "Failed to receive failover heartbeat");
+                    break;
+                }
+            };
+            if let Err(reason) = self.receive_heartbeat(&buf[..len], validator_signer, now, now_ms)
+            {
+                tracing::debug!(target: "client", ?addr, reason, "Rejected failover heartbeat");
+            }
+        }
+    }
+
+    fn receive_heartbeat(
+        &mut self,
+        data: &[u8],
+        validator_signer: &dyn ValidatorSigner,
+        now: Instant,
+        now_ms: u64,
+    ) -> Result<(), &'static str> {
+        let result = FailoverHeartbeat::try_from_slice(data).map_err(|_| "malformed").and_then(
+            |heartbeat| {
+                self.check_heartbeat(&heartbeat, validator_signer, now_ms)?;
+                Ok(heartbeat)
+            },
+        );
+        let label = result.as_ref().map_or_else(|reason| *reason, |_| "ok");
+        metrics::VALIDATOR_FAILOVER_HEARTBEATS_RECEIVED_TOTAL.with_label_values(&[label]).inc();
+        self.peer = Some((result?.inner, now));
+        Ok(())
+    }
+
+    fn check_heartbeat(
+        &self,
+        heartbeat: &FailoverHeartbeat,
+        validator_signer: &dyn ValidatorSigner,
+        now_ms: u64,
+    ) -> Result<(), &'static str> {
+        let inner = &heartbeat.inner;
+        if &inner.account_id != validator_signer.validator_id() {
+            return Err("other_account");
+        }
+        if inner.primary == (self.config.role == FailoverRole::Primary) {
+            return Err("same_role");
+        }
+        let mut public_keys = std::iter::once(validator_signer.public_key())
+            .chain(validator_signer.next_public_key());
+        if !public_keys.any(|public_key| heartbeat.verify(&public_key)) {
+            return Err("invalid_signature");
+        }
+        if now_ms.abs_diff(inner.timestamp_ms) >= self.config.failover_timeout.as_millis() as u64 {
+            return Err("expired");
+        }
+        if self.peer.as_ref().is_some_and(|(last, _)| last.timestamp_ms >= inner.timestamp_ms) {
+            return Err("replayed");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::KeyType;
+    use near_primitives::validator_signer::InMemoryValidatorSigner;
+
+    #[test]
+    fn test_should_sign() {
+        let observations = Observations {
+            peer_signing: None,
+            warmed_up: true,
+            following_chain: true,
+            peer_producing: false,
+            peer_missing: true,
+        };
+        for role in [FailoverRole::Primary, FailoverRole::Backup] {
+            // A silent node is taken over once it misses a block or a chunk, unless its blocks or
+            // chunks are still seen.
+            assert!(should_sign(role, false, &observations));
+            let producing = Observations { peer_producing: true, ..observations };
+            assert!(!should_sign(role, false, &producing));
+            // The heartbeats may be lost while the other node still signs.
+            let partitioned = Observations { peer_missing: false, ..observations };
+            assert!(!should_sign(role, false, &partitioned));
+            let stalled = Observations { following_chain: false, ..observations };
+            assert!(!should_sign(role, false, &stalled));
+            let starting = Observations { warmed_up: false, ..observations };
+            assert!(!should_sign(role, false, &starting));
+            assert!(should_sign(role, true, &stalled));
+        }
+
+        // A signing node keeps signing, unless both sign and it is the backup.
+        let peer_signing = Observations { peer_signing: Some(true), ..observations };
+        assert!(!should_sign(FailoverRole::Primary, false, &peer_signing));
+        assert!(!should_sign(FailoverRole::Backup, false, &peer_signing));
+        assert!(should_sign(FailoverRole::Primary, true, &peer_signing));
+        assert!(!should_sign(FailoverRole::Backup, true, &peer_signing));
+
+        let peer_standby = Observations { peer_signing: Some(false), ..observations };
+        assert!(should_sign(FailoverRole::Primary, false, &peer_standby));
+        assert!(!should_sign(FailoverRole::Backup, false, &peer_standby));
+        assert!(should_sign(FailoverRole::Backup, true, &peer_standby));
+    }
+
+    fn failover(role: FailoverRole) -> ValidatorFailover {
+        let addr = "127.0.0.1:0".parse().unwrap();
+        ValidatorFailover::new(ValidatorFailoverConfig {
+            role,
+            listen_addr: addr,
+            peer_addr: addr,
+            heartbeat_period: Duration::from_secs(1),
+            failover_timeout: Duration::from_secs(10),
+        })
+        .unwrap()
+    }
+
+    fn make_signer(seed: &str) -> InMemoryValidatorSigner {
+        InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, seed)
+    }
+
+    #[test]
+    fn test_observations_expire() {
+        let mut backup = failover(FailoverRole::Backup);
+        let start = Instant::now();
+        let timeout = backup.config.failover_timeout;
+        backup.started = start;
+        backup.head = Some((10, start));
+        backup.last_peer_missed = Some(start);
+        let heartbeat = failover(FailoverRole::Primary)
+            .heartbeat(&make_signer("test"), true, 10, ValidatorSignedHeights::default(), 1_000_000)
+            .unwrap();
+        backup.peer = Some((heartbeat.inner, start));
+
+        let observations = backup.observations(start);
+        assert_eq!(observations.peer_signing, Some(true));
+        assert!(!observations.warmed_up);
+        assert!(observations.following_chain);
+        assert!(observations.peer_missing);
+
+        let observations = backup.observations(start + timeout);
+        assert_eq!(observations.peer_signing, None);
+        assert!(observations.warmed_up);
+        assert!(!observations.following_chain);
+        assert!(!observations.peer_missing);
+    }
+
+    #[test]
+    fn test_heartbeats() {
+        let signer = make_signer("test");
+        let primary = failover(FailoverRole::Primary);
+        let mut backup = failover(FailoverRole::Backup);
+        let now = Instant::now();
+        let signed_heights = ValidatorSignedHeights {
+            block_height: 9,
+            approval_height: 11,
+            chunk_height: 10,
+            endorsement_height: 10,
+        };
+        let heartbeat = |failover: &ValidatorFailover,
+                         signer: &InMemoryValidatorSigner,
+                         signing,
+                         head_height,
+                         now_ms| {
+            let heartbeat =
+                failover.heartbeat(signer, signing, head_height, signed_heights, now_ms).unwrap();
+            borsh::to_vec(&heartbeat).unwrap()
+        };
+
+        let data = heartbeat(&primary, &signer, true, 10, 1_000_000);
+        assert_eq!(backup.receive_heartbeat(&data, &signer, now, 1_000_000), Ok(()));
+        let (received, _) = backup.peer.clone().unwrap();
+        assert!(received.primary);
+        assert!(received.signing);
+        assert_eq!(received.head_height, 10);
+        assert_eq!(backup.peer_signed_heights(), Some(signed_heights));
+        assert_eq!(backup.observations(now).peer_signing, Some(true));
+
+        // Heartbeats of the same role, other keys, and old or replayed ones are rejected.
+        let mut primary_from_backup = failover(FailoverRole::Primary);
+        let data = heartbeat(&backup, &signer, true, 11, 1_000_001);
+        assert_eq!(primary_from_backup.receive_heartbeat(&data, &signer, now, 1_000_001), Ok(()));
+        let data = heartbeat(&primary, &signer, true, 11, 1_000_001);
+        assert_eq!(
+            primary_from_backup.receive_heartbeat(&data, &signer, now, 1_000_001),
+            Err("same_role")
+        );
+        let data = heartbeat(&primary, &make_signer("other"), false, 11, 1_000_001);
+        assert_eq!(
+            backup.receive_heartbeat(&data, &signer, now, 1_000_001),
+            Err("invalid_signature")
+        );
+        let data = heartbeat(&primary, &signer, false, 11, 1_000_000);
+        assert_eq!(backup.receive_heartbeat(&data, &signer, now, 1_000_001), Err("replayed"));
+        let data = heartbeat(&primary, &signer, false, 11, 1_000_001);
+        assert_eq!(
+            backup.receive_heartbeat(&data, &signer, now, 1_000_000 + 10_001),
+            Err("expired")
+        );
+        assert_eq!(
+            backup.receive_heartbeat(&data[..data.len() - 1], &signer, now, 1_000_001),
+            Err("malformed")
+        );
+        assert_eq!(backup.peer.as_ref().unwrap().0, received);
+
+        let data = heartbeat(&primary, &signer, false, 12, 1_000_002);
+        assert_eq!(backup.receive_heartbeat(&data, &signer, now, 1_000_002), Ok(()));
+        let (received, _) = backup.peer.clone().unwrap();
+        assert!(!received.signing);
+        assert_eq!(received.head_height, 12);
+    }
+}
@@ -1,13 +1,17 @@
 //! Readonly view of the chain and state of the database.
 //! Useful for querying from RPC.
 
+#[cfg(feature = "new_epoch_sync")]
+use crate::adapter::EpochSyncRequest;
 use crate::adapter::{
-    AnnounceAccountRequest, BlockHeadersRequest, BlockRequest, StateRequestHeader,
-    StateRequestPart, StateResponse, TxStatusRequest, TxStatusResponse,
+    AnnounceAccountRequest, BlockHeadersRequest, BlockRequest, ChunkStateWitnessRequest,
+    StateRequestHeader, StateRequestPart, StateResponse, TxStatusRequest, TxStatusResponse,
 };
+use crate::chunk_endorsement_tracker::ChunkEndorsementPool;
+use crate::maintenance::find_maintenance_window;
 use crate::{
-    metrics, sync, GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
+    metrics, sync, GetBlockRange, GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock,
+    GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
 };
 use actix::{Actor, Addr, Handler, SyncArbiter, SyncContext};
 use near_async::messaging::CanSend;
@@ -18,11 +22,14 @@ use near_chain::{
 use near_chain_configs::{ClientConfig, ProtocolConfigView};
 use near_chain_primitives::error::EpochErrorResultToChainError;
 use near_client_primitives::types::{
-    Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
-    GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome, GetExecutionOutcomeError,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetMaintenanceWindows,
-    GetMaintenanceWindowsError, GetNextLightClientBlockError, GetProtocolConfig,
-    GetProtocolConfigError, GetReceipt, GetReceiptError, GetSplitStorageInfo,
+    Error, EstimateTransaction, GetAccountOutcomesProof, GetAccountOutcomesProofError, GetBlock,
+    GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse, GetBlockRangeError,
+    GetBlockWithMerkleTree, GetChunkError, GetChunkExecutionProof, GetChunkExecutionProofError,
+    GetChunkStateWitness, GetChunkStateWitnessError, GetEstimatedValidators, GetExecutionOutcome,
+    GetExecutionOutcomeError, GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError,
+    GetMaintenanceWindows, GetMaintenanceWindowsError, GetNextLightClientBlockError,
+    GetProtocolConfig, GetProtocolConfigError, GetReceipt, GetReceiptError, GetReceiptWithOutcome,
+    GetScheduledMaintenanceWindow, GetScheduledMaintenanceWindowError, GetSplitStorageInfo,
     GetSplitStorageInfoError, GetStateChangesError, GetStateChangesWithCauseInBlock,
     GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
     TxStatus, TxStatusError,
@@ -36,31 +43,40 @@ use near_network::types::{
 use near_o11y::{handler_debug_span, OpenTelemetrySpanExt, WithSpanContext, WithSpanContextExt};
 use near_performance_metrics_macros::perf;
 use near_primitives::block::{Block, BlockHeader};
+use near_primitives::chunk_validation::{ChunkExecutionProof, ChunkStateWitness};
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
+#[cfg(feature = "new_epoch_sync")]
+use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, PartialMerkleTree};
 use near_primitives::network::AnnounceAccount;
 use near_primitives::receipt::Receipt;
+use near_primitives::shard_layout::ShardUId;
 use near_primitives::sharding::ShardChunk;
 use near_primitives::state_sync::{
     ShardStateSyncResponse, ShardStateSyncResponseHeader, ShardStateSyncResponseV3,
 };
 use near_primitives::static_clock::StaticClock;
+use near_primitives::transaction::{ExecutionOutcomeWithId, ExecutionOutcomeWithIdAndProof};
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockId, BlockReference, EpochReference, Finality, MaybeBlockId,
-    ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
+    AccountId, BlockHeight, BlockId, BlockReference, EpochHeight, EpochReference, Finality,
+    MaybeBlockId, ShardId, StateRoot, SyncCheckpoint, TransactionOrReceiptId,
+    ValidatorInfoIdentifier,
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ExecutionStatusView,
-    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, GasPriceView, LightClientBlockView,
-    MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView, SplitStorageInfoView,
-    StateChangesKindsView, StateChangesView, TxExecutionStatus, TxStatusView,
+    BlockSummaryView, BlockView, ChunkExecutionProofView, ChunkStateWitnessView, ChunkView,
+    EpochValidatorInfo, EstimatedValidatorsView, ExecutionOutcomeWithIdView, ExecutionStatusView,
+    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, GasPriceView,
+    LightClientBlockOutcomesProofView, LightClientBlockView, LightClientOutcomeProofView,
+    MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView, ReceiptWithOutcomeView,
+    ScheduledMaintenanceWindowView, SplitStorageInfoView, StateChangesKindsView, StateChangesView,
+    TransactionEstimateView, TxExecutionStatus, TxStatusView, ValidatorKickoutView,
 };
 use near_store::flat::{FlatStorageReadyStatus, FlatStorageStatus};
 use near_store::{DBCol, COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY};
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::hash::Hash;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
@@ -73,6 +89,18 @@ const REQUEST_WAIT_TIME: u64 = 1000;
 
 const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 
+/// How many epochs ahead validators can be estimated, i.e. up to the epoch
+/// after next, whose validators are selected from the proposals in the
+/// current epoch.
+const MAX_ESTIMATED_VALIDATORS_EPOCH_OFFSET: EpochHeight = 2;
+
+/// Maximum number of heights whose blocks can be requested with `GetBlockRange`.
+const MAX_BLOCK_RANGE_SIZE: BlockHeight = 1000;
+
+/// Maximum number of heights whose outcomes can be proven with
+/// `GetAccountOutcomesProof`.
+const MAX_ACCOUNT_OUTCOMES_PROOF_RANGE_SIZE: BlockHeight = 100;
+
 /// Request and response manager across all instances of ViewClientActor.
 pub struct ViewClientRequestManager {
     /// Transaction query that needs to be forwarded to other shards
@@ -87,12 +115,31 @@ pub struct ViewClientRequestManager {
     pub receipt_outcome_requests: lru::LruCache<CryptoHash, Instant>,
 }
 
+/// Account of the validator running the node. Clones share the same account,
+/// which the client clears when the validator key is removed.
+#[derive(Clone, Default)]
+pub struct ValidatorAccountId(Arc<RwLock<Option<AccountId>>>);
+
+impl ValidatorAccountId {
+    pub fn new(account_id: Option<AccountId>) -> Self {
+        Self(Arc::new(RwLock::new(account_id)))
+    }
+
+    pub fn get(&self) -> Option<AccountId> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub(crate) fn set(&self, account_id: Option<AccountId>) {
+        *self.0.write().unwrap() = account_id;
+    }
+}
+
 /// View client provides currently committed (to the storage) view of the current chain and state.
 pub struct ViewClientActor {
     pub adv: crate::adversarial::Controls,
 
     /// Validator account (if present).
-    validator_account_id: Option<AccountId>,
+    validator_account_id: ValidatorAccountId,
     chain: Chain,
     epoch_manager: Arc<dyn EpochManagerAdapter>,
     shard_tracker: ShardTracker,
@@ -100,6 +147,9 @@ pub struct ViewClientActor {
     network_adapter: PeerManagerAdapter,
     pub config: ClientConfig,
     request_manager: Arc<RwLock<ViewClientRequestManager>>,
+    /// The chunk endorsements collected by the client, to build chunk
+    /// execution proofs out of.
+    chunk_endorsements: ChunkEndorsementPool,
     state_request_cache: Arc<Mutex<VecDeque<Instant>>>,
 }
 
@@ -120,7 +170,7 @@ impl ViewClientActor {
     const MAX_NUM_STATE_REQUESTS: usize = 30;
 
     pub fn new(
-        validator_account_id: Option<AccountId>,
+        validator_account_id: ValidatorAccountId,
         chain_genesis: &ChainGenesis,
         epoch_manager: Arc<dyn EpochManagerAdapter>,
         shard_tracker: ShardTracker,
@@ -128,6 +178,7 @@ impl ViewClientActor {
         network_adapter: PeerManagerAdapter,
         config: ClientConfig,
         request_manager: Arc<RwLock<ViewClientRequestManager>>,
+        chunk_endorsements: ChunkEndorsementPool,
         adv: crate::adversarial::Controls,
     ) -> Result<Self, Error> {
         // TODO: should we create shared ChainStore that is passed to both Client and ViewClient?
@@ -149,6 +200,7 @@ impl ViewClientActor {
             network_adapter,
             config,
             request_manager,
+            chunk_endorsements,
             state_request_cache: Arc::new(Mutex::new(VecDeque::default())),
         })
     }
@@ -305,28 +357,40 @@ impl ViewClientActor {
         Ok(windows)
     }
 
-    fn handle_query(&mut self, msg: Query) -> Result<QueryResponse, QueryError> {
-        let header = self.get_block_header_by_reference(&msg.block_reference);
-        let header = match header {
+    /// Returns the header of the block a query refers to.
+    fn get_query_block_header(
+        &self,
+        block_reference: &BlockReference,
+    ) -> Result<BlockHeader, QueryError> {
+        match self.get_block_header_by_reference(block_reference) {
             Ok(Some(header)) => Ok(header),
             Ok(None) => Err(QueryError::NoSyncedBlocks),
             Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => {
-                Err(QueryError::UnknownBlock { block_reference: msg.block_reference })
+                // The header of a canonical block may be garbage collected, see
+                // `GCConfig::gc_block_headers`, while its height is still indexed.
+                if let BlockReference::BlockId(BlockId::Height(block_height)) = block_reference {
+                    if let Ok(block_hash) = self.chain.get_block_hash_by_height(*block_height) {
+                        return Err(QueryError::GarbageCollectedBlock {
+                            block_height: *block_height,
+                            block_hash,
+                        });
+                    }
+                }
+                Err(QueryError::UnknownBlock { block_reference: block_reference.clone() })
             }
             Err(near_chain::near_chain_primitives::Error::IOErr(err)) => {
                 Err(QueryError::InternalError { error_message: err.to_string() })
             }
             Err(err) => Err(QueryError::Unreachable { error_message: err.to_string() }),
-        }?;
+        }
+    }
 
-        let account_id = match &msg.request {
-            QueryRequest::ViewAccount { account_id, .. } => account_id,
-            QueryRequest::ViewState { account_id, .. } => account_id,
-            QueryRequest::ViewAccessKey { account_id, .. } => account_id,
-            QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
-            QueryRequest::CallFunction { account_id, .. } => account_id,
-            QueryRequest::ViewCode { account_id, .. } => account_id,
-        };
+    /// Returns the shard of the account and its state root after the block.
+    fn get_query_state_root(
+        &self,
+        header: &BlockHeader,
+        account_id: &AccountId,
+    ) -> Result<(ShardUId, StateRoot), QueryError> {
         let shard_id = self
             .epoch_manager
             .account_id_to_shard_id(account_id, header.epoch_id())
@@ -358,11 +422,25 @@ impl ViewClientActor {
                 }
                 _ => QueryError::Unreachable { error_message: err.to_string() },
             })?;
+        Ok((shard_uid, *chunk_extra.state_root()))
+    }
+
+    fn handle_query(&mut self, msg: Query) -> Result<QueryResponse, QueryError> {
+        let header = self.get_query_block_header(&msg.block_reference)?;
+
+        let account_id = match &msg.request {
+            QueryRequest::ViewAccount { account_id, .. } => account_id,
+            QueryRequest::ViewState { account_id, .. } => account_id,
+            QueryRequest::ViewAccessKey { account_id, .. } => account_id,
+            QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
+            QueryRequest::CallFunction { account_id, .. } => account_id,
+            QueryRequest::ViewCode { account_id, .. } => account_id,
+        };
+        let (shard_uid, state_root) = self.get_query_state_root(&header, account_id)?;
 
-        let state_root = chunk_extra.state_root();
         match self.runtime.query(
             shard_uid,
-            state_root,
+            &state_root,
             header.height(),
             header.raw_timestamp(),
             header.prev_hash(),
@@ -485,7 +563,7 @@ impl ViewClientActor {
             .map_err(|err| TxStatusError::InternalError(err.to_string()))?;
         // Check if we are tracking this shard.
         if self.shard_tracker.care_about_shard(
-            self.validator_account_id.as_ref(),
+            self.validator_account_id.get().as_ref(),
             &head.prev_block_hash,
             target_shard_id,
             true,
@@ -609,6 +687,30 @@ impl ViewClientActor {
             _ => Ok(false),
         }
     }
+
+    /// Returns whether the sync block is behind the tail of the hot storage.
+    /// Split storage archival nodes serve the state of such epochs from the
+    /// cold storage, as no state snapshot is kept for them.
+    fn is_behind_hot_tail(&self, sync_hash: &CryptoHash) -> Result<bool, Error> {
+        let header = self.chain.get_block_header(sync_hash)?;
+        Ok(header.height() < self.chain.tail()?)
+    }
+
+    /// Returns the state sync header, not cached if the sync block is behind
+    /// the tail of the hot storage, not to leave the headers of past epochs in
+    /// the hot storage.
+    fn get_state_response_header(
+        &self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        is_behind_hot_tail: bool,
+    ) -> Result<ShardStateSyncResponseHeader, near_chain::Error> {
+        if is_behind_hot_tail {
+            self.chain.compute_state_response_header(shard_id, sync_hash)
+        } else {
+            self.chain.get_state_response_header(shard_id, sync_hash)
+        }
+    }
 }
 
 impl Actor for ViewClientActor {
@@ -627,6 +729,42 @@ impl Handler<WithSpanContext<Query>> for ViewClientActor {
     }
 }
 
+impl Handler<WithSpanContext<EstimateTransaction>> for ViewClientActor {
+    type Result = Result<TransactionEstimateView, QueryError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<EstimateTransaction>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["EstimateTransaction"])
+            .start_timer();
+        let header = self.get_query_block_header(&msg.block_reference)?;
+        let transaction = &msg.transaction.transaction;
+        let signer_state = self.get_query_state_root(&header, &transaction.signer_id)?;
+        // The receipt is only executed if the node tracks the shard of the
+        // receiver.
+        let receiver_state = match self.get_query_state_root(&header, &transaction.receiver_id) {
+            Ok(receiver_state) => Some(receiver_state),
+            Err(QueryError::UnavailableShard { .. }) => None,
+            Err(err) => return Err(err),
+        };
+        self.runtime
+            .estimate_transaction(
+                &header,
+                signer_state,
+                receiver_state,
+                &msg.transaction,
+                msg.verify_signature,
+            )
+            .map_err(|err| QueryError::InternalError { error_message: err.to_string() })
+    }
+}
+
 /// Handles retrieving block from the chain.
 impl Handler<WithSpanContext<GetBlock>> for ViewClientActor {
     type Result = Result<BlockView, GetBlockError>;
@@ -646,6 +784,44 @@ impl Handler<WithSpanContext<GetBlock>> for ViewClientActor {
     }
 }
 
+impl Handler<WithSpanContext<GetBlockRange>> for ViewClientActor {
+    type Result = Result<Vec<BlockSummaryView>, GetBlockRangeError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetBlockRange>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer =
+            metrics::VIEW_CLIENT_MESSAGE_TIME.with_label_values(&["GetBlockRange"]).start_timer();
+        let GetBlockRange { from_height, to_height, fields } = msg;
+        if to_height < from_height {
+            return Err(GetBlockRangeError::InvalidRange { from_height, to_height });
+        }
+        let num_blocks = (to_height - from_height).saturating_add(1);
+        if num_blocks > MAX_BLOCK_RANGE_SIZE {
+            return Err(GetBlockRangeError::RangeTooLarge {
+                num_blocks,
+                max_num_blocks: MAX_BLOCK_RANGE_SIZE,
+            });
+        }
+        let head = self.chain.head()?;
+        let mut blocks = vec![];
+        for height in from_height..=to_height.min(head.height) {
+            match self.chain.get_block_header_by_height(height) {
+                Ok(header) => blocks.push(BlockSummaryView::new(&header, &fields)),
+                // Skipped height.
+                Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(blocks)
+    }
+}
+
 impl Handler<WithSpanContext<GetBlockWithMerkleTree>> for ViewClientActor {
     type Result = Result<(BlockView, Arc<PartialMerkleTree>), GetBlockError>;
 
@@ -727,6 +903,53 @@ impl Handler<WithSpanContext<GetChunk>> for ViewClientActor {
     }
 }
 
+impl Handler<WithSpanContext<GetChunkStateWitness>> for ViewClientActor {
+    type Result = Result<ChunkStateWitnessView, GetChunkStateWitnessError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetChunkStateWitness>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetChunkStateWitness"])
+            .start_timer();
+        let witness = crate::chunk_witness_actor::get_chunk_state_witness(
+            self.chain.chain_store(),
+            self.epoch_manager.as_ref(),
+            &msg.chunk_hash,
+        )?;
+        Ok(witness.into())
+    }
+}
+
+impl Handler<WithSpanContext<GetChunkExecutionProof>> for ViewClientActor {
+    type Result = Result<ChunkExecutionProofView, GetChunkExecutionProofError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetChunkExecutionProof>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetChunkExecutionProof"])
+            .start_timer();
+        let witness = crate::chunk_witness_actor::get_chunk_state_witness(
+            self.chain.chain_store(),
+            self.epoch_manager.as_ref(),
+            &msg.chunk_hash,
+        )?;
+        let endorsements = self.chunk_endorsements.get_chunk_endorsements(&msg.chunk_hash);
+        Ok(ChunkExecutionProof::new(&witness, endorsements).into())
+    }
+}
+
 impl Handler<WithSpanContext<TxStatus>> for ViewClientActor {
     type Result = Result<TxStatusView, TxStatusError>;
 
@@ -814,6 +1037,57 @@ impl Handler<WithSpanContext<GetValidatorOrdered>> for ViewClientActor {
         })?)
     }
 }
+impl Handler<WithSpanContext<GetEstimatedValidators>> for ViewClientActor {
+    type Result = Result<EstimatedValidatorsView, GetValidatorInfoError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetEstimatedValidators>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetEstimatedValidators"])
+            .start_timer();
+        if msg.epoch_offset > MAX_ESTIMATED_VALIDATORS_EPOCH_OFFSET {
+            return Err(GetValidatorInfoError::EpochOffsetTooLarge {
+                epoch_offset: msg.epoch_offset,
+                max_epoch_offset: MAX_ESTIMATED_VALIDATORS_EPOCH_OFFSET,
+            });
+        }
+        // use header head because this is latest from the perspective of epoch manager
+        let head = self.chain.header_head()?;
+        let epoch_info = self
+            .epoch_manager
+            .get_estimated_validators(&head.last_block_hash, msg.epoch_offset)
+            .into_chain_error()?;
+        // Once the head is the last block of its epoch, the epoch after next
+        // is finalized.
+        let estimated = msg.epoch_offset == MAX_ESTIMATED_VALIDATORS_EPOCH_OFFSET
+            && !self
+                .epoch_manager
+                .is_next_block_epoch_start(&head.last_block_hash)
+                .into_chain_error()?;
+        let kickout = epoch_info
+            .validator_kickout()
+            .clone()
+            .into_iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(account_id, reason)| ValidatorKickoutView { account_id, reason })
+            .collect();
+        Ok(EstimatedValidatorsView {
+            epoch_height: epoch_info.epoch_height(),
+            estimated,
+            seat_price: epoch_info.seat_price(),
+            validators: epoch_info.validators_iter().map(Into::into).collect(),
+            kickout,
+        })
+    }
+}
+
 /// Returns a list of change kinds per account in a store for a given block.
 impl Handler<WithSpanContext<GetStateChangesInBlock>> for ViewClientActor {
     type Result = Result<StateChangesKindsView, GetStateChangesError>;
@@ -1054,7 +1328,7 @@ impl Handler<WithSpanContext<GetExecutionOutcome>> for ViewClientActor {
                     .account_id_to_shard_id(&account_id, &head.epoch_id)
                     .into_chain_error()?;
                 if self.shard_tracker.care_about_shard(
-                    self.validator_account_id.as_ref(),
+                    self.validator_account_id.get().as_ref(),
                     &head.last_block_hash,
                     target_shard_id,
                     true,
@@ -1118,6 +1392,57 @@ impl Handler<WithSpanContext<GetReceipt>> for ViewClientActor {
     }
 }
 
+impl Handler<WithSpanContext<GetReceiptWithOutcome>> for ViewClientActor {
+    type Result = Result<ReceiptWithOutcomeView, GetReceiptError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetReceiptWithOutcome>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetReceiptWithOutcome"])
+            .start_timer();
+        let receipt_id = msg.receipt_id;
+        let receipt = self
+            .chain
+            .chain_store()
+            .get_receipt(&receipt_id)?
+            .map(|receipt| Receipt::clone(&receipt).into());
+        let outcome = match self.chain.get_execution_outcome(&receipt_id) {
+            Ok(outcome) => Some(outcome),
+            Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let originated_from_transaction_hash =
+            self.chain.chain_store().get_receipt_origin(&receipt_id)?;
+        if receipt.is_none() && outcome.is_none() && originated_from_transaction_hash.is_none() {
+            return Err(GetReceiptError::UnknownReceipt(receipt_id));
+        }
+        let (block_hash, shard_id) = match &outcome {
+            Some(outcome) => {
+                let epoch_id = self.chain.get_block_header(&outcome.block_hash)?.epoch_id().clone();
+                let shard_id = self
+                    .epoch_manager
+                    .account_id_to_shard_id(&outcome.outcome_with_id.outcome.executor_id, &epoch_id)
+                    .into_chain_error()?;
+                (Some(outcome.block_hash), Some(shard_id))
+            }
+            None => (None, None),
+        };
+        Ok(ReceiptWithOutcomeView {
+            receipt,
+            outcome: outcome.map(Into::into),
+            block_hash,
+            shard_id,
+            originated_from_transaction_hash,
+        })
+    }
+}
+
 impl Handler<WithSpanContext<GetBlockProof>> for ViewClientActor {
     type Result = Result<GetBlockProofResponse, GetBlockProofError>;
 
@@ -1140,6 +1465,134 @@ impl Handler<WithSpanContext<GetBlockProof>> for ViewClientActor {
     }
 }
 
+impl Handler<WithSpanContext<GetAccountOutcomesProof>> for ViewClientActor {
+    type Result = Result<Vec<LightClientBlockOutcomesProofView>, GetAccountOutcomesProofError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetAccountOutcomesProof>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetAccountOutcomesProof"])
+            .start_timer();
+        let GetAccountOutcomesProof { account_id, from_height, to_height, head_block_hash } = msg;
+        if to_height < from_height {
+            return Err(GetAccountOutcomesProofError::InvalidRange { from_height, to_height });
+        }
+        let num_blocks = (to_height - from_height).saturating_add(1);
+        if num_blocks > MAX_ACCOUNT_OUTCOMES_PROOF_RANGE_SIZE {
+            return Err(GetAccountOutcomesProofError::RangeTooLarge {
+                num_blocks,
+                max_num_blocks: MAX_ACCOUNT_OUTCOMES_PROOF_RANGE_SIZE,
+            });
+        }
+        let head_header = self.chain.get_block_header(&head_block_hash)?;
+        self.chain.check_blocks_final_and_canonical(&[head_header.clone()])?;
+
+        let mut proofs: Vec<LightClientBlockOutcomesProofView> = vec![];
+        for height in from_height..=to_height.min(head_header.height()) {
+            let header = match self.chain.get_block_header_by_height(height) {
+                Ok(header) => header,
+                // Skipped height.
+                Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let block_hash = *header.hash();
+            let shard_id = self
+                .epoch_manager
+                .account_id_to_shard_id(&account_id, header.epoch_id())
+                .into_chain_error()?;
+            if !self.shard_tracker.care_about_shard(
+                self.validator_account_id.get().as_ref(),
+                header.prev_hash(),
+                shard_id,
+                true,
+            ) {
+                return Err(GetAccountOutcomesProofError::UnavailableShard { shard_id });
+            }
+
+            let mut outcomes = vec![];
+            let chain_store = self.chain.chain_store();
+            for id in chain_store.get_outcomes_by_block_hash_and_shard_id(&block_hash, shard_id)? {
+                let Some(outcome_with_proof) =
+                    chain_store.get_outcome_by_id_and_block_hash(&id, &block_hash)?
+                else {
+                    continue;
+                };
+                if outcome_with_proof.outcome.executor_id == account_id {
+                    outcomes.push(ExecutionOutcomeWithIdAndProof {
+                        proof: outcome_with_proof.proof,
+                        block_hash,
+                        outcome_with_id: ExecutionOutcomeWithId {
+                            id,
+                            outcome: outcome_with_proof.outcome,
+                        },
+                    });
+                }
+            }
+            if outcomes.is_empty() {
+                continue;
+            }
+
+            // The outcome root of the chunk is included in the next block
+            // with a new chunk in the shard. The proofs of that block and of
+            // the outcome root are shared by all the outcomes of the chunk.
+            let Some((proof_block_hash, proof_shard_id)) =
+                self.chain.get_next_block_hash_with_new_chunk(&block_hash, shard_id)?
+            else {
+                return Err(GetAccountOutcomesProofError::NotConfirmed { block_height: height });
+            };
+            let proof_block = self.chain.get_block(&proof_block_hash)?;
+            if proof_block.header().height() > head_header.height() {
+                return Err(GetAccountOutcomesProofError::NotConfirmed { block_height: height });
+            }
+            let outcome_roots = proof_block
+                .chunks()
+                .iter()
+                .map(|header| header.prev_outcome_root())
+                .collect::<Vec<_>>();
+            if proof_shard_id >= outcome_roots.len() as u64 {
+                return Err(GetAccountOutcomesProofError::InternalError {
+                    error_message: format!(
+                        "Total number of shards is {} but the execution outcome is in shard {}",
+                        outcome_roots.len(),
+                        proof_shard_id
+                    ),
+                });
+            }
+            let outcome_root_proof = merklize(&outcome_roots).1[proof_shard_id as usize].clone();
+            let outcomes = outcomes.into_iter().map(|outcome| {
+                let mut outcome_proof: ExecutionOutcomeWithIdView = outcome.into();
+                outcome_proof.block_hash = proof_block_hash;
+                LightClientOutcomeProofView {
+                    outcome_proof,
+                    outcome_root_proof: outcome_root_proof.clone(),
+                }
+            });
+            // Chunks of consecutive blocks share the block including their
+            // outcome roots if the chunks in between are missing.
+            match proofs.last_mut() {
+                Some(last)
+                    if last.block_header_lite.inner_lite.height
+                        == proof_block.header().height() =>
+                {
+                    last.outcomes.extend(outcomes)
+                }
+                _ => proofs.push(LightClientBlockOutcomesProofView {
+                    block_header_lite: proof_block.header().clone().into(),
+                    block_proof: self.chain.get_block_proof(&proof_block_hash, &head_block_hash)?,
+                    outcomes: outcomes.collect(),
+                }),
+            }
+        }
+        Ok(proofs)
+    }
+}
+
 impl Handler<WithSpanContext<GetProtocolConfig>> for ViewClientActor {
     type Result = Result<ProtocolConfigView, GetProtocolConfigError>;
 
@@ -1276,6 +1729,64 @@ impl Handler<WithSpanContext<BlockRequest>> for ViewClientActor {
     }
 }
 
+impl Handler<WithSpanContext<ChunkStateWitnessRequest>> for ViewClientActor {
+    type Result = Option<ChunkStateWitness>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<ChunkStateWitnessRequest>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["ChunkStateWitnessRequest"])
+            .start_timer();
+        let ChunkStateWitnessRequest(chunk_hash) = msg;
+        let me = self.validator_account_id.get()?;
+        match crate::chunk_witness_actor::get_requested_chunk_state_witness(
+            self.chain.chain_store(),
+            self.epoch_manager.as_ref(),
+            &me,
+            &chunk_hash,
+        ) {
+            Ok(witness) => witness,
+            Err(err) => {
+                tracing::debug!(target: "client", ?chunk_hash, ?err, "Failed to serve requested chunk state witness");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(feature = "new_epoch_sync")]
+impl Handler<WithSpanContext<EpochSyncRequest>> for ViewClientActor {
+    type Result = Option<Box<EpochSyncInfo>>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<EpochSyncRequest>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["EpochSyncRequest"])
+            .start_timer();
+        let EpochSyncRequest(epoch_id) = msg;
+        match self.chain.chain_store().get_epoch_sync_info(&epoch_id) {
+            Ok(epoch_sync_info) => Some(Box::new(epoch_sync_info)),
+            Err(near_chain::Error::DBNotFoundErr(_)) => None,
+            Err(err) => {
+                tracing::error!(target: "client", ?epoch_id, ?err, "Failed to read EpochSyncInfo");
+                None
+            }
+        }
+    }
+}
+
 impl Handler<WithSpanContext<BlockHeadersRequest>> for ViewClientActor {
     type Result = Option<Vec<BlockHeader>>;
 
@@ -1321,14 +1832,17 @@ impl Handler<WithSpanContext<StateRequestHeader>> for ViewClientActor {
             tracing::debug!(target: "sync", ?sync_hash, "Throttle state sync requests");
             return None;
         }
+        let is_behind_hot_tail = self.is_behind_hot_tail(&sync_hash).unwrap_or(false);
         let header = match self.chain.check_sync_hash_validity(&sync_hash) {
-            Ok(true) => match self.chain.get_state_response_header(shard_id, sync_hash) {
-                Ok(header) => Some(header),
-                Err(err) => {
-                    error!(target: "sync", ?err, "Cannot build state sync header");
-                    None
+            Ok(true) => {
+                match self.get_state_response_header(shard_id, sync_hash, is_behind_hot_tail) {
+                    Ok(header) => Some(header),
+                    Err(err) => {
+                        error!(target: "sync", ?err, "Cannot build state sync header");
+                        None
+                    }
                 }
-            },
+            }
             Ok(false) => {
                 warn!(target: "sync", ?sync_hash, "sync_hash didn't pass validation, possible malicious behavior");
                 // Don't respond to the node, because the request is malformed.
@@ -1366,7 +1880,8 @@ impl Handler<WithSpanContext<StateRequestHeader>> for ViewClientActor {
                     }
                 };
 
-                let can_generate = self.has_state_snapshot(&sync_hash, shard_id).is_ok();
+                let can_generate =
+                    is_behind_hot_tail || self.has_state_snapshot(&sync_hash, shard_id).is_ok();
                 ShardStateSyncResponse::V3(ShardStateSyncResponseV3 {
                     header: Some(header),
                     part: None,
@@ -1406,14 +1921,25 @@ impl Handler<WithSpanContext<StateRequestPart>> for ViewClientActor {
             tracing::debug!(target: "sync", ?sync_hash, "Throttle state sync requests");
             return None;
         }
-        if let Err(err) = self.has_state_snapshot(&sync_hash, shard_id) {
-            tracing::debug!(target: "sync", ?err, ?sync_hash, "Node doesn't have a matching state snapshot");
-            return None;
+        // The state of epochs behind the hot tail is read from the cold storage
+        // instead of a state snapshot.
+        let is_behind_hot_tail = self.is_behind_hot_tail(&sync_hash).unwrap_or(false);
+        if !is_behind_hot_tail {
+            if let Err(err) = self.has_state_snapshot(&sync_hash, shard_id) {
+                tracing::debug!(target: "sync", ?err, ?sync_hash, "Node doesn't have a matching state snapshot");
+                return None;
+            }
         }
-        tracing::debug!(target: "sync", ?shard_id, ?sync_hash, ?part_id, "Computing state request part");
+        tracing::debug!(target: "sync", ?shard_id, ?sync_hash, ?part_id, is_behind_hot_tail, "Computing state request part");
         let part = match self.chain.check_sync_hash_validity(&sync_hash) {
             Ok(true) => {
-                let part = match self.chain.get_state_response_part(shard_id, part_id, sync_hash) {
+                let part = if is_behind_hot_tail {
+                    metrics::VIEW_CLIENT_COLD_STATE_PARTS.inc();
+                    self.chain.compute_state_response_part_from_trie(shard_id, part_id, sync_hash)
+                } else {
+                    self.chain.get_state_response_part(shard_id, part_id, sync_hash)
+                };
+                let part = match part {
                     Ok(part) => Some((part_id, part)),
                     Err(err) => {
                         error!(target: "sync", ?err, ?sync_hash, shard_id, part_id, "Cannot build state part");
@@ -1440,7 +1966,7 @@ impl Handler<WithSpanContext<StateRequestPart>> for ViewClientActor {
                 None
             }
         };
-        let num_parts = part.as_ref().and_then(|_| match self.chain.get_state_response_header(shard_id, sync_hash) {
+        let num_parts = part.as_ref().and_then(|_| match self.get_state_response_header(shard_id, sync_hash, is_behind_hot_tail) {
             Ok(header) => Some(header.num_state_parts()),
             Err(err) => {
                 tracing::error!(target: "sync", ?err, ?sync_hash, shard_id, "Failed to get num state parts");
@@ -1560,6 +2086,38 @@ impl Handler<WithSpanContext<GetMaintenanceWindows>> for ViewClientActor {
     }
 }
 
+impl Handler<WithSpanContext<GetScheduledMaintenanceWindow>> for ViewClientActor {
+    type Result = Result<ScheduledMaintenanceWindowView, GetScheduledMaintenanceWindowError>;
+
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<GetScheduledMaintenanceWindow>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let (_span, msg) = handler_debug_span!(target: "client", msg);
+        tracing::debug!(target: "client", ?msg);
+        let account_id = msg
+            .account_id
+            .or_else(|| self.validator_account_id.get())
+            .ok_or(GetScheduledMaintenanceWindowError::NoValidator)?;
+        let declared = self.config.maintenance_window.get();
+        let (num_blocks, auto_restart) = match (msg.num_blocks, declared) {
+            (Some(num_blocks), _) => (num_blocks, false),
+            (None, Some(declared)) => {
+                let is_own = self.validator_account_id.get().as_ref() == Some(&account_id);
+                (declared.num_blocks, declared.auto_restart && is_own)
+            }
+            (None, None) => return Err(GetScheduledMaintenanceWindowError::NoMaintenanceDeclared),
+        };
+        let head = self.chain.head()?;
+        let window =
+            find_maintenance_window(self.epoch_manager.as_ref(), &head, &account_id, num_blocks)
+                .map_err(near_chain::Error::from)?;
+        Ok(ScheduledMaintenanceWindowView { account_id, num_blocks, auto_restart, window })
+    }
+}
+
 impl Handler<WithSpanContext<GetSplitStorageInfo>> for ViewClientActor {
     type Result = Result<SplitStorageInfoView, GetSplitStorageInfoError>;
 
@@ -1589,13 +2147,14 @@ impl Handler<WithSpanContext<GetSplitStorageInfo>> for ViewClientActor {
 
 /// Starts the View Client in a new arbiter (thread).
 pub fn start_view_client(
-    validator_account_id: Option<AccountId>,
+    validator_account_id: ValidatorAccountId,
     chain_genesis: ChainGenesis,
     epoch_manager: Arc<dyn EpochManagerAdapter>,
     shard_tracker: ShardTracker,
     runtime: Arc<dyn RuntimeAdapter>,
     network_adapter: PeerManagerAdapter,
     config: ClientConfig,
+    chunk_endorsements: ChunkEndorsementPool,
     adv: crate::adversarial::Controls,
 ) -> Addr<ViewClientActor> {
     let request_manager = Arc::new(RwLock::new(ViewClientRequestManager::new()));
@@ -1609,6 +2168,7 @@ pub fn start_view_client(
             network_adapter.clone(),
             config.clone(),
             request_manager.clone(),
+            chunk_endorsements.clone(),
             adv.clone(),
         )
         .unwrap()
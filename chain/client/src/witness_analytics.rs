@@ -0,0 +1,120 @@
+use near_primitives::sharding::ChunkHash;
+use near_primitives::static_clock::StaticClock;
+use near_primitives::types::{BlockHeight, ShardId};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Summary of a chunk state witness processed by this node.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WitnessAnalyticsRecord {
+    /// When the processing of the witness finished, in milliseconds since
+    /// the Unix epoch.
+    pub timestamp_ms: i64,
+    pub chunk_hash: ChunkHash,
+    pub shard_id: ShardId,
+    pub height_created: BlockHeight,
+    /// Size of the borsh-serialized witness.
+    pub size_bytes: u64,
+    /// Time from the start of the validation of the witness until its result
+    /// was known.
+    pub validation_time_ms: u64,
+    /// `valid`, or the reason the witness wasn't endorsed.
+    pub result: String,
+}
+
+impl WitnessAnalyticsRecord {
+    pub fn new(
+        chunk_hash: ChunkHash,
+        shard_id: ShardId,
+        height_created: BlockHeight,
+        size_bytes: u64,
+        validation_time: Duration,
+        result: &str,
+    ) -> Self {
+        Self {
+            timestamp_ms: StaticClock::utc().timestamp_millis(),
+            chunk_hash,
+            shard_id,
+            height_created,
+            size_bytes,
+            validation_time_ms: validation_time.as_millis() as u64,
+            result: result.to_string(),
+        }
+    }
+}
+
+/// Exports a summary record of every state witness processed by the node, to
+/// track how witnesses grow over time. Records are appended to a file as JSON
+/// lines. They are also emitted as `witness_analytics` tracing events, so
+/// they reach the OpenTelemetry collector along with the spans of the
+/// validation if tracing is exported.
+pub(crate) struct WitnessAnalyticsExporter {
+    file: Mutex<File>,
+}
+
+impl WitnessAnalyticsExporter {
+    /// Opens the file to append records to, creating it if needed.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    pub fn export(&self, record: &WitnessAnalyticsRecord) {
+        tracing::debug!(
+            target: "witness_analytics",
+            chunk_hash = ?record.chunk_hash,
+            shard_id = record.shard_id,
+            height_created = record.height_created,
+            size_bytes = record.size_bytes,
+            validation_time_ms = record.validation_time_ms,
+            result = %record.result,
+            "Processed chunk state witness",
+        );
+        let mut line = serde_json::to_vec(record).expect("record is serializable");
+        line.push(b'\n');
+        // Each record is written at once, so that the file can be tailed and
+        // no partial records are left behind if the node is killed.
+        if let Err(err) = self.file.lock().unwrap().write_all(&line) {
+            tracing::warn!(target: "witness_analytics", %err, "Failed to export witness record");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::hash::CryptoHash;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_export_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("witnesses.jsonl");
+        let records: Vec<_> = (0..3)
+            .map(|height| {
+                WitnessAnalyticsRecord::new(
+                    ChunkHash(CryptoHash::hash_borsh(height)),
+                    0,
+                    height,
+                    1000,
+                    Duration::from_millis(20),
+                    "valid",
+                )
+            })
+            .collect();
+        WitnessAnalyticsExporter::open(&path).unwrap().export(&records[0]);
+        // Records are appended to the existing ones when the node restarts.
+        let exporter = WitnessAnalyticsExporter::open(&path).unwrap();
+        exporter.export(&records[1]);
+        exporter.export(&records[2]);
+
+        let exported: Vec<WitnessAnalyticsRecord> = BufReader::new(File::open(&path).unwrap())
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        assert_eq!(exported, records);
+    }
+}
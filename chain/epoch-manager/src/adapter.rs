@@ -172,6 +172,13 @@ pub trait EpochManagerAdapter: Send + Sync {
         epoch_id: &EpochId,
     ) -> Result<Vec<ValidatorStake>, EpochError>;
 
+    /// Returns all the validators which may be assigned to validate chunks
+    /// in a given epoch.
+    fn get_epoch_chunk_validators(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<Vec<ValidatorStake>, EpochError>;
+
     /// Block producers for given height for the main block. Return EpochError if outside of known boundaries.
     fn get_block_producer(
         &self,
@@ -218,6 +225,18 @@ pub trait EpochManagerAdapter: Send + Sync {
         epoch_id: ValidatorInfoIdentifier,
     ) -> Result<EpochValidatorInfo, EpochError>;
 
+    /// Returns the epoch info, with the validators, of the epoch `epoch_offset`
+    /// epochs after the epoch of the given block, up to the epoch after next.
+    /// The epoch info of the epoch after next is estimated from the proposals,
+    /// kickouts and stake so far in the current epoch.
+    ///
+    /// WARNING: this call may be expensive.
+    fn get_estimated_validators(
+        &self,
+        block_hash: &CryptoHash,
+        epoch_offset: EpochHeight,
+    ) -> Result<Arc<EpochInfo>, EpochError>;
+
     fn add_validator_proposals(
         &self,
         block_header_info: BlockHeaderInfo,
@@ -632,6 +651,14 @@ impl EpochManagerAdapter for EpochManagerHandle {
         Ok(epoch_manager.get_all_chunk_producers(epoch_id)?.to_vec())
     }
 
+    fn get_epoch_chunk_validators(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<Vec<ValidatorStake>, EpochError> {
+        let epoch_manager = self.read();
+        epoch_manager.get_all_chunk_validators(epoch_id)
+    }
+
     fn get_block_producer(
         &self,
         epoch_id: &EpochId,
@@ -695,6 +722,15 @@ impl EpochManagerAdapter for EpochManagerHandle {
         epoch_manager.get_validator_info(epoch_id)
     }
 
+    fn get_estimated_validators(
+        &self,
+        block_hash: &CryptoHash,
+        epoch_offset: EpochHeight,
+    ) -> Result<Arc<EpochInfo>, EpochError> {
+        let epoch_manager = self.read();
+        epoch_manager.get_estimated_validators(block_hash, epoch_offset)
+    }
+
     fn add_validator_proposals(
         &self,
         block_header_info: BlockHeaderInfo,
@@ -14,7 +14,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{
-    AccountId, ApprovalStake, Balance, BlockChunkValidatorStats, BlockHeight, EpochId,
+    AccountId, ApprovalStake, Balance, BlockChunkValidatorStats, BlockHeight, EpochHeight, EpochId,
     EpochInfoProvider, NumBlocks, NumSeats, ShardId, ValidatorId, ValidatorInfoIdentifier,
     ValidatorKickoutReason, ValidatorStats,
 };
@@ -22,6 +22,7 @@ use near_primitives::validator_mandates::AssignmentWeight;
 use near_primitives::version::{ProtocolVersion, UPGRADABILITY_FIX_PROTOCOL_VERSION};
 use near_primitives::views::{
     CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, ValidatorKickoutView,
+    ValidatorRoleView,
 };
 use near_store::{DBCol, Store, StoreUpdate};
 use num_rational::Rational64;
@@ -108,6 +109,10 @@ impl EpochInfoProvider for EpochManagerHandle {
         let epoch_manager = self.read();
         epoch_manager.minimum_stake(prev_block_hash)
     }
+
+    fn shard_layout(&self, epoch_id: &EpochId) -> Result<ShardLayout, EpochError> {
+        self.read().get_shard_layout(epoch_id)
+    }
 }
 
 /// Tracks epoch information across different forks, such as validators.
@@ -548,7 +553,7 @@ impl EpochManager {
     }
 
     fn collect_blocks_info(
-        &mut self,
+        &self,
         last_block_info: &BlockInfo,
         last_block_hash: &CryptoHash,
     ) -> Result<EpochSummary, EpochError> {
@@ -917,6 +922,20 @@ impl EpochManager {
         })
     }
 
+    /// Returns all validators which can be sampled as chunk validators in
+    /// the given epoch. With chunk validation enabled, every validator holds
+    /// at least a partial mandate, so this is the whole validator set.
+    pub fn get_all_chunk_validators(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<Vec<ValidatorStake>, EpochError> {
+        let epoch_info = self.get_epoch_info(epoch_id)?;
+        if !checked_feature!("stable", ChunkValidation, epoch_info.protocol_version()) {
+            return Ok(vec![]);
+        }
+        Ok(epoch_info.validators_iter().collect())
+    }
+
     /// Returns the list of chunk validators for the given shard_id and height.
     pub fn get_chunk_validators(
         &self,
@@ -925,20 +944,7 @@ impl EpochManager {
         height: BlockHeight,
     ) -> Result<HashMap<AccountId, AssignmentWeight>, EpochError> {
         let epoch_info = self.get_epoch_info(epoch_id)?;
-        let chunk_validators_per_shard = epoch_info.sample_chunk_validators(height);
-        let chunk_validators =
-            chunk_validators_per_shard.get(shard_id as usize).ok_or_else(|| {
-                EpochError::ChunkValidatorSelectionError(format!(
-                    "Invalid shard ID {} for height {}, epoch {:?} for chunk validation",
-                    shard_id, height, epoch_id,
-                ))
-            })?;
-        Ok(chunk_validators
-            .iter()
-            .map(|(validator_id, seats)| {
-                (epoch_info.get_validator(*validator_id).take_account_id(), seats.clone())
-            })
-            .collect())
+        Self::chunk_validators_from_info(&epoch_info, height, shard_id)
     }
 
     /// get_heuristic_block_approvers_ordered: block producers for epoch
@@ -1287,6 +1293,21 @@ impl EpochManager {
                 validator_to_shard[*validator_id as usize].insert(shard_id as ShardId);
             }
         }
+        let validator_role = |epoch_info: &EpochInfo, validator_id: usize| {
+            let validator_id = validator_id as ValidatorId;
+            let role = if epoch_info.block_producers_settlement().contains(&validator_id) {
+                ValidatorRoleView::BlockProducer
+            } else if epoch_info
+                .chunk_producers_settlement()
+                .iter()
+                .any(|chunk_producers| chunk_producers.contains(&validator_id))
+            {
+                ValidatorRoleView::ChunkOnlyProducer
+            } else {
+                ValidatorRoleView::ChunkValidator
+            };
+            Some(role)
+        };
 
         // This ugly code arises because of the incompatible types between `block_tracker` in `EpochInfoAggregator`
         // and `validator_block_chunk_stats` in `EpochSummary`. Rust currently has no support for Either type
@@ -1320,6 +1341,7 @@ impl EpochManager {
                             num_produced_chunks_per_shard: vec![0; shards.len()],
                             num_expected_chunks_per_shard: vec![0; shards.len()],
                             shards,
+                            role: validator_role(&cur_epoch_info, validator_id),
                             num_produced_blocks: validator_stats.block_stats.produced,
                             num_expected_blocks: validator_stats.block_stats.expected,
                             num_produced_chunks: validator_stats.chunk_stats.produced,
@@ -1374,6 +1396,7 @@ impl EpochManager {
                             public_key,
                             stake,
                             shards: shards.clone(),
+                            role: validator_role(&cur_epoch_info, validator_id),
                             num_produced_blocks: block_stats.produced,
                             num_expected_blocks: block_stats.expected,
                             num_produced_chunks: chunk_stats.produced,
@@ -1417,7 +1440,13 @@ impl EpochManager {
                     .collect::<Vec<ShardId>>();
                 shards.sort();
                 let (account_id, public_key, stake) = info.destructure();
-                NextEpochValidatorInfo { account_id, public_key, stake, shards }
+                NextEpochValidatorInfo {
+                    account_id,
+                    public_key,
+                    stake,
+                    shards,
+                    role: validator_role(&next_epoch_info, validator_id),
+                }
             })
             .collect();
         let prev_epoch_kickout = next_epoch_info
@@ -1441,6 +1470,58 @@ impl EpochManager {
         })
     }
 
+    /// Returns the epoch info, with the validators, of the epoch `epoch_offset` epochs after the
+    /// epoch of the given block. The epoch infos of the current and next epochs are known. The
+    /// epoch info of the epoch after next is estimated the way the current epoch is finalized,
+    /// from the proposals, kickouts and stake up to the given block, but without the rewards of
+    /// the current epoch. The estimate can change until the last block of the current epoch.
+    pub fn get_estimated_validators(
+        &self,
+        block_hash: &CryptoHash,
+        epoch_offset: EpochHeight,
+    ) -> Result<Arc<EpochInfo>, EpochError> {
+        let block_info = self.get_block_info(block_hash)?;
+        let next_epoch_id = self.get_next_epoch_id(block_hash)?;
+        match epoch_offset {
+            0 => return self.get_epoch_info(block_info.epoch_id()),
+            1 => return self.get_epoch_info(&next_epoch_id),
+            2 if self.is_last_block_in_finished_epoch(block_hash)? => {
+                return self.get_epoch_info(&EpochId(*block_hash));
+            }
+            2 => {}
+            _ => return Err(EpochError::EpochOutOfBounds(block_info.epoch_id().clone())),
+        }
+
+        let EpochSummary { all_proposals, validator_kickout, next_version, .. } =
+            self.collect_blocks_info(&block_info, block_hash)?;
+        let epoch_info = self.get_epoch_info(block_info.epoch_id())?;
+        let next_epoch_info = self.get_epoch_info(&next_epoch_id)?;
+        let next_next_epoch_config = self.config.for_protocol_version(next_version);
+        let next_next_epoch_info = match proposals_to_epoch_info(
+            &next_next_epoch_config,
+            // The seed isn't known before the end of the epoch, and only affects the
+            // assignment of the selected validators.
+            RngSeed::default(),
+            &next_epoch_info,
+            all_proposals,
+            validator_kickout,
+            HashMap::new(),
+            0,
+            next_version,
+            epoch_info.protocol_version(),
+        ) {
+            Ok(next_next_epoch_info) => next_next_epoch_info,
+            // Same as in `finalize_epoch`.
+            Err(EpochError::ThresholdError { .. } | EpochError::NotEnoughValidators { .. }) => {
+                let mut epoch_info = EpochInfo::clone(&next_epoch_info);
+                *epoch_info.epoch_height_mut() += 1;
+                epoch_info
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(Arc::new(next_next_epoch_info))
+    }
+
     pub fn add_validator_proposals(
         &mut self,
         block_header_info: BlockHeaderInfo,
@@ -1543,6 +1624,33 @@ impl EpochManager {
         epoch_info.sample_chunk_producer(height, shard_id)
     }
 
+    /// Returns the chunk validators for the given shard_id and height,
+    /// computed from the epoch info alone. Unlike `get_chunk_validators`,
+    /// this doesn't need the epoch info to be stored, so it can be used for
+    /// past epochs known only from epoch sync data.
+    pub fn chunk_validators_from_info(
+        epoch_info: &EpochInfo,
+        height: BlockHeight,
+        shard_id: ShardId,
+    ) -> Result<HashMap<AccountId, AssignmentWeight>, EpochError> {
+        let chunk_validators_per_shard = epoch_info.sample_chunk_validators(height);
+        let chunk_validators =
+            chunk_validators_per_shard.get(shard_id as usize).ok_or_else(|| {
+                EpochError::ChunkValidatorSelectionError(format!(
+                    "Invalid shard ID {} for height {}, epoch height {} for chunk validation",
+                    shard_id,
+                    height,
+                    epoch_info.epoch_height(),
+                ))
+            })?;
+        Ok(chunk_validators
+            .iter()
+            .map(|(validator_id, seats)| {
+                (epoch_info.get_validator(*validator_id).take_account_id(), seats.clone())
+            })
+            .collect())
+    }
+
     /// Returns true, if given current block info, next block supposed to be in the next epoch.
     fn is_next_block_in_next_epoch(&self, block_info: &BlockInfo) -> Result<bool, EpochError> {
         if block_info.prev_hash() == &CryptoHash::default() {
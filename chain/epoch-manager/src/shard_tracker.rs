@@ -236,6 +236,7 @@ mod tests {
             protocol_upgrade_stake_threshold: Ratio::new(80, 100),
             shard_layout: ShardLayout::v0(num_shards, 0),
             validator_selection_config: Default::default(),
+            chunk_validation_config: Default::default(),
             validator_max_kickout_stake_perc: 100,
         };
         let reward_calculator = RewardCalculator {
@@ -162,6 +162,7 @@ pub fn epoch_config_with_production_config(
         protocol_upgrade_stake_threshold: Ratio::new(80, 100),
         minimum_stake_divisor: 1,
         validator_selection_config: Default::default(),
+        chunk_validation_config: Default::default(),
         shard_layout: ShardLayout::v0(num_shards, 0),
         validator_max_kickout_stake_perc: 100,
     };
@@ -173,6 +173,50 @@ fn test_validator_change_of_stake() {
     );
 }
 
+#[test]
+fn test_estimated_validators() {
+    let amount_staked = 1_000_000;
+    let validators =
+        vec![("test1".parse().unwrap(), amount_staked), ("test2".parse().unwrap(), amount_staked)];
+    let mut epoch_manager =
+        setup_epoch_manager(validators, 2, 1, 2, 0, 90, 60, 100, default_reward_calculator());
+
+    let h = hash_range(4);
+    record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+    record_block(&mut epoch_manager, h[0], h[1], 1, vec![stake("test1".parse().unwrap(), 10)]);
+
+    let epoch_id = epoch_manager.get_epoch_id(&h[1]).unwrap();
+    let next_epoch_id = epoch_manager.get_next_epoch_id(&h[1]).unwrap();
+    assert_eq!(
+        epoch_manager.get_estimated_validators(&h[1], 0).unwrap(),
+        epoch_manager.get_epoch_info(&epoch_id).unwrap()
+    );
+    assert_eq!(
+        epoch_manager.get_estimated_validators(&h[1], 1).unwrap(),
+        epoch_manager.get_epoch_info(&next_epoch_id).unwrap()
+    );
+    assert_eq!(
+        epoch_manager.get_estimated_validators(&h[1], 3),
+        Err(EpochError::EpochOutOfBounds(epoch_id))
+    );
+
+    // test1 doesn't have enough stake anymore, so it is estimated to be kicked
+    // out in the epoch after next.
+    let estimated = epoch_manager.get_estimated_validators(&h[1], 2).unwrap();
+    check_validators(&estimated, &[("test2", amount_staked)]);
+    assert!(matches!(
+        estimated.validator_kickout().get(AccountIdRef::new_or_panic("test1")),
+        Some(ValidatorKickoutReason::NotEnoughStake { stake: 10, .. })
+    ));
+
+    record_block(&mut epoch_manager, h[1], h[2], 2, vec![]);
+    record_block(&mut epoch_manager, h[2], h[3], 3, vec![]);
+    let epoch_info =
+        epoch_manager.get_epoch_info(&epoch_manager.get_next_epoch_id(&h[3]).unwrap()).unwrap();
+    check_validators(&epoch_info, &[("test2", amount_staked)]);
+    assert_eq!(epoch_manager.get_estimated_validators(&h[2], 2).unwrap(), epoch_info);
+}
+
 /// Test handling forks across the epoch finalization.
 /// Fork with where one BP produces blocks in one chain and 2 BPs are in another chain.
 ///     |   | /--1---4------|--7---10------|---13---
@@ -2210,6 +2254,7 @@ fn test_protocol_version_switch_with_many_seats() {
         minimum_stake_divisor: 1,
         shard_layout: ShardLayout::v0_single_shard(),
         validator_selection_config: Default::default(),
+        chunk_validation_config: Default::default(),
         validator_max_kickout_stake_perc: 100,
     };
     let config = AllEpochConfig::new(false, epoch_config, "test-chain");
@@ -2653,3 +2698,42 @@ fn test_max_kickout_stake_ratio() {
         ])
     );
 }
+
+/// Nodes doing epoch sync only get the epoch info of past epochs, so the
+/// chunk validator assignments computed from it must match the ones of the
+/// epoch manager which recorded the epoch.
+#[test]
+fn test_chunk_validators_from_epoch_info() {
+    let amount_staked = 1_000_000;
+    let validators = vec![
+        ("test1".parse().unwrap(), amount_staked),
+        ("test2".parse().unwrap(), amount_staked),
+        ("test3".parse().unwrap(), amount_staked),
+    ];
+    let num_shards = 2;
+    let mut epoch_manager = setup_default_epoch_manager(validators, 10, num_shards, 3, 0, 90, 60);
+    let h = hash_range(2);
+    record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+    record_block(&mut epoch_manager, h[0], h[1], 1, vec![]);
+
+    let epoch_id = epoch_manager.get_epoch_id(&h[1]).unwrap();
+    let epoch_info = epoch_manager.get_epoch_info(&epoch_id).unwrap();
+    if !checked_feature!("stable", ChunkValidation, epoch_info.protocol_version()) {
+        return;
+    }
+    let synced_epoch_info: EpochInfo =
+        borsh::from_slice(&borsh::to_vec(epoch_info.as_ref()).unwrap()).unwrap();
+    for height in 1..10 {
+        for shard_id in 0..num_shards {
+            assert_eq!(
+                EpochManager::chunk_validators_from_info(&synced_epoch_info, height, shard_id)
+                    .unwrap(),
+                epoch_manager.get_chunk_validators(&epoch_id, shard_id, height).unwrap(),
+            );
+        }
+    }
+    assert!(matches!(
+        EpochManager::chunk_validators_from_info(&synced_epoch_info, 1, num_shards),
+        Err(EpochError::ChunkValidatorSelectionError(_))
+    ));
+}
@@ -55,7 +55,29 @@ pub fn proposals_to_epoch_info(
         last_version,
     );
     let (chunk_producer_proposals, chunk_producers, cp_stake_threshold) =
-        if checked_feature!("stable", ChunkOnlyProducers, next_version) {
+        if checked_feature!("stable", DedicatedChunkOnlyProducers, next_version) {
+            // Chunk-only producers compete only with the proposals that didn't get a block
+            // producer seat, so their seat price is set by their own stakes rather than by the
+            // stakes of the block producers. Block producer seats left empty can be taken by
+            // chunk-only producers, as before.
+            let num_chunk_only_producer_seats = max_bp_selected
+                + (epoch_config.validator_selection_config.num_chunk_only_producer_seats as usize)
+                - block_producers.len();
+            let (chunk_only_producers, cp_stake_threshold) = if num_chunk_only_producer_seats == 0 {
+                (vec![], bp_stake_threshold)
+            } else {
+                select_chunk_producers(
+                    &mut block_producer_proposals,
+                    num_chunk_only_producer_seats,
+                    min_stake_ratio,
+                    shard_ids.len() as NumShards,
+                    last_version,
+                )
+            };
+            let chunk_producers =
+                block_producers.iter().cloned().chain(chunk_only_producers).collect();
+            (block_producer_proposals, chunk_producers, cp_stake_threshold)
+        } else if checked_feature!("stable", ChunkOnlyProducers, next_version) {
             let mut chunk_producer_proposals = order_proposals(proposals.into_values());
             let max_cp_selected = max_bp_selected
                 + (epoch_config.validator_selection_config.num_chunk_only_producer_seats as usize);
@@ -181,8 +203,8 @@ pub fn proposals_to_epoch_info(
 
     let validator_mandates = if checked_feature!("stable", ChunkValidation, next_version) {
         // TODO(#10014) determine required stake per mandate instead of reusing seat price.
-        // TODO(#10014) determine `min_mandates_per_shard`
-        let min_mandates_per_shard = 0;
+        let min_mandates_per_shard =
+            epoch_config.chunk_validation_config.min_chunk_validator_seats_per_shard as usize;
         let validator_mandates_config =
             ValidatorMandatesConfig::new(threshold, min_mandates_per_shard, shard_ids.len());
         // We can use `all_validators` to construct mandates Since a validator's position in
@@ -392,7 +414,7 @@ mod tests {
     use near_primitives::types::validator_stake::ValidatorStake;
     #[cfg(feature = "nightly")]
     use near_primitives::validator_mandates::{AssignmentWeight, ValidatorMandatesAssignment};
-    use near_primitives::version::PROTOCOL_VERSION;
+    use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
     use num_rational::Ratio;
 
     #[test]
@@ -531,6 +553,58 @@ mod tests {
         );
     }
 
+    /// Chunk-only producers have a seat price of their own, lower than the one of block
+    /// producers, so that validators with a low stake can produce chunks.
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn test_validator_assignment_with_dedicated_chunk_only_producers() {
+        let epoch_config = create_epoch_config(
+            1,
+            2,
+            // purposely set the fishermen threshold high so that none become fishermen
+            1000,
+            ValidatorSelectionConfig {
+                num_chunk_only_producer_seats: 2,
+                minimum_validators_per_shard: 1,
+                minimum_stake_ratio: Ratio::new(1, 10),
+            },
+        );
+        let prev_epoch_info = create_prev_epoch_info(7, &["test5"], &[]);
+        let proposals = create_proposals(&[
+            ("test1", 1000),
+            ("test2", 1000),
+            ("test3", 100), // 100 is < 1/10 of 2100, too small to be a chunk producer before
+            ("test4", 50),
+            ("test5", 10),
+        ]);
+        let epoch_info = proposals_to_epoch_info(
+            &epoch_config,
+            [0; 32],
+            &prev_epoch_info,
+            proposals,
+            Default::default(),
+            Default::default(),
+            0,
+            PROTOCOL_VERSION,
+            PROTOCOL_VERSION,
+        )
+        .unwrap();
+
+        let validators: Vec<_> =
+            epoch_info.validators_iter().map(|v| v.take_account_id()).collect();
+        assert_eq!(validators, vec!["test1", "test2", "test3", "test4"]);
+        assert_eq!(epoch_info.block_producers_settlement(), &[0, 1]);
+        assert_eq!(epoch_info.chunk_producers_settlement(), &[vec![0, 1, 2, 3]]);
+
+        // All chunk-only producer seats are taken, so their seat price is 1 more than the
+        // smallest chunk-only producer stake.
+        assert_eq!(epoch_info.seat_price(), 51);
+        assert_eq!(
+            epoch_info.validator_kickout().get(AccountIdRef::new_or_panic("test5")).unwrap(),
+            &ValidatorKickoutReason::NotEnoughStake { stake: 10, threshold: 51 },
+        );
+    }
+
     #[test]
     fn test_block_producer_sampling() {
         let num_shards = 4;
@@ -689,8 +763,8 @@ mod tests {
             Default::default(),
             Default::default(),
             0,
-            PROTOCOL_VERSION,
-            PROTOCOL_VERSION,
+            protocol_version_before_dedicated_chunk_only_producers(),
+            protocol_version_before_dedicated_chunk_only_producers(),
         )
         .unwrap();
 
@@ -761,8 +835,8 @@ mod tests {
             Default::default(),
             Default::default(),
             0,
-            PROTOCOL_VERSION,
-            PROTOCOL_VERSION,
+            protocol_version_before_dedicated_chunk_only_producers(),
+            protocol_version_before_dedicated_chunk_only_producers(),
         )
         .unwrap();
 
@@ -805,8 +879,8 @@ mod tests {
             Default::default(),
             Default::default(),
             0,
-            PROTOCOL_VERSION,
-            PROTOCOL_VERSION,
+            protocol_version_before_dedicated_chunk_only_producers(),
+            protocol_version_before_dedicated_chunk_only_producers(),
         )
         .unwrap();
         #[cfg(feature = "protocol_feature_fix_staking_threshold")]
@@ -829,8 +903,8 @@ mod tests {
             Default::default(),
             Default::default(),
             0,
-            PROTOCOL_VERSION,
-            PROTOCOL_VERSION,
+            protocol_version_before_dedicated_chunk_only_producers(),
+            protocol_version_before_dedicated_chunk_only_producers(),
         )
         .unwrap();
         assert_eq!(num_validators, epoch_info.validators_iter().len());
@@ -906,6 +980,14 @@ mod tests {
         validator_ids.into_iter().map(|id| epoch_info.get_validator(*id).stake()).sum()
     }
 
+    /// Latest protocol version selecting chunk-only producers among all the proposals.
+    fn protocol_version_before_dedicated_chunk_only_producers() -> ProtocolVersion {
+        cmp::min(
+            PROTOCOL_VERSION,
+            ProtocolFeature::DedicatedChunkOnlyProducers.protocol_version() - 1,
+        )
+    }
+
     /// Create EpochConfig, only filling in the fields important for validator selection.
     fn create_epoch_config(
         num_shards: u64,
@@ -928,6 +1010,7 @@ mod tests {
             protocol_upgrade_stake_threshold: 0.into(),
             shard_layout: ShardLayout::v0(num_shards, 0),
             validator_selection_config,
+            chunk_validation_config: Default::default(),
         }
     }
 
@@ -0,0 +1,4 @@
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=proto/node.proto");
+    tonic_build::compile_protos("proto/node.proto")
+}
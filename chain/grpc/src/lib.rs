@@ -0,0 +1,492 @@
+//! gRPC server of the node, serving the blocks, chunks, transaction statuses and queries of the
+//! JSON RPC to the clients which would rather use protobuf. The schema, generated from the views
+//! of `near_primitives`, is in `proto/node.proto`.
+
+use actix::Addr;
+use borsh::BorshSerialize;
+use near_client::ViewClientActor;
+use near_client_primitives::types::{GetBlockError, GetChunkError, QueryError, TxStatusError};
+use near_o11y::{WithSpanContext, WithSpanContextExt};
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{AccountId, BlockId, BlockReference, Finality};
+use near_primitives::views;
+use std::net::SocketAddr;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("near.node.v1");
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GrpcConfig {
+    pub addr: SocketAddr,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self { addr: "0.0.0.0:3050".parse().unwrap() }
+    }
+}
+
+struct NodeQueryService {
+    view_client_addr: Addr<ViewClientActor>,
+}
+
+impl NodeQueryService {
+    async fn view_client_send<M, T, E>(&self, msg: M) -> Result<T, Status>
+    where
+        ViewClientActor: actix::Handler<WithSpanContext<M>>,
+        M: actix::Message<Result = Result<T, E>> + Send + 'static,
+        M::Result: Send,
+        Status: From<ErrorStatus<E>>,
+    {
+        self.view_client_addr
+            .send(msg.with_span_context())
+            .await
+            .map_err(|err| Status::unavailable(err.to_string()))?
+            .map_err(|err| ErrorStatus(err).into())
+    }
+}
+
+#[tonic::async_trait]
+impl proto::node_query_server::NodeQuery for NodeQueryService {
+    async fn block(
+        &self,
+        request: Request<proto::BlockRequest>,
+    ) -> Result<Response<proto::Block>, Status> {
+        let block_reference = block_reference(request.into_inner().block_reference)?;
+        let block = self.view_client_send(near_client::GetBlock(block_reference)).await?;
+        Ok(Response::new(block.into()))
+    }
+
+    async fn chunk(
+        &self,
+        request: Request<proto::ChunkRequest>,
+    ) -> Result<Response<proto::Chunk>, Status> {
+        use proto::block_shard_id::BlockId as ProtoBlockId;
+        use proto::chunk_request::Reference;
+
+        let msg = match request.into_inner().reference {
+            Some(Reference::ChunkHash(chunk_hash)) => {
+                near_client::GetChunk::ChunkHash(ChunkHash(crypto_hash(&chunk_hash)?))
+            }
+            Some(Reference::BlockShardId(proto::BlockShardId { block_id, shard_id })) => {
+                match block_id {
+                    Some(ProtoBlockId::Height(height)) => {
+                        near_client::GetChunk::Height(height, shard_id)
+                    }
+                    Some(ProtoBlockId::Hash(hash)) => {
+                        near_client::GetChunk::BlockHash(crypto_hash(&hash)?, shard_id)
+                    }
+                    None => return Err(Status::invalid_argument("block_id is required")),
+                }
+            }
+            None => return Err(Status::invalid_argument("reference is required")),
+        };
+        let chunk = self.view_client_send(msg).await?;
+        Ok(Response::new(chunk.into()))
+    }
+
+    async fn tx_status(
+        &self,
+        request: Request<proto::TxStatusRequest>,
+    ) -> Result<Response<proto::TxStatus>, Status> {
+        let proto::TxStatusRequest { tx_hash, sender_account_id } = request.into_inner();
+        let msg = near_client::TxStatus {
+            tx_hash: crypto_hash(&tx_hash)?,
+            signer_account_id: account_id(sender_account_id)?,
+            fetch_receipt: false,
+        };
+        let tx_status = self.view_client_send(msg).await?;
+        Ok(Response::new(tx_status.into()))
+    }
+
+    async fn query(
+        &self,
+        request: Request<proto::QueryRequest>,
+    ) -> Result<Response<proto::QueryResponse>, Status> {
+        let proto::QueryRequest { block_reference: reference, request } = request.into_inner();
+        let msg = near_client::Query::new(block_reference(reference)?, query_request(request)?);
+        let response = self.view_client_send(msg).await?;
+        Ok(Response::new(response.into()))
+    }
+}
+
+/// Starts the gRPC server on the current tokio runtime.
+pub fn start_grpc(config: GrpcConfig, view_client_addr: Addr<ViewClientActor>) {
+    let service = NodeQueryService { view_client_addr };
+    tracing::info!(target: "grpc", addr = %config.addr, "Starting gRPC server");
+    tokio::spawn(async move {
+        let result = tonic::transport::Server::builder()
+            .add_service(proto::node_query_server::NodeQueryServer::new(service))
+            .serve(config.addr)
+            .await;
+        if let Err(err) = result {
+            tracing::error!(target: "grpc", ?err, "gRPC server failed");
+        }
+    });
+}
+
+fn crypto_hash(bytes: &[u8]) -> Result<CryptoHash, Status> {
+    CryptoHash::try_from(bytes)
+        .map_err(|_| Status::invalid_argument("hashes must be exactly 32 bytes long"))
+}
+
+fn account_id(account_id: String) -> Result<AccountId, Status> {
+    account_id.parse().map_err(|err| Status::invalid_argument(format!("{err}")))
+}
+
+fn block_reference(reference: Option<proto::BlockReference>) -> Result<BlockReference, Status> {
+    use proto::block_reference::Reference;
+
+    match reference.and_then(|reference| reference.reference) {
+        Some(Reference::Height(height)) => Ok(BlockId::Height(height).into()),
+        Some(Reference::Hash(hash)) => Ok(BlockId::Hash(crypto_hash(&hash)?).into()),
+        Some(Reference::Finality(finality)) => {
+            let finality = match proto::Finality::from_i32(finality) {
+                Some(proto::Finality::Optimistic) => Finality::None,
+                Some(proto::Finality::Doomslug) => Finality::DoomSlug,
+                Some(proto::Finality::Final) => Finality::Final,
+                None => return Err(Status::invalid_argument("unknown finality")),
+            };
+            Ok(BlockReference::Finality(finality))
+        }
+        None => Err(Status::invalid_argument("block_reference is required")),
+    }
+}
+
+fn query_request(
+    request: Option<proto::query_request::Request>,
+) -> Result<views::QueryRequest, Status> {
+    use proto::query_request::Request;
+
+    Ok(match request {
+        Some(Request::ViewAccount(request)) => {
+            views::QueryRequest::ViewAccount { account_id: account_id(request.account_id)? }
+        }
+        Some(Request::ViewCode(request)) => {
+            views::QueryRequest::ViewCode { account_id: account_id(request.account_id)? }
+        }
+        Some(Request::ViewState(request)) => views::QueryRequest::ViewState {
+            account_id: account_id(request.account_id)?,
+            prefix: request.prefix.into(),
+            include_proof: request.include_proof,
+        },
+        Some(Request::ViewAccessKey(request)) => views::QueryRequest::ViewAccessKey {
+            account_id: account_id(request.account_id)?,
+            public_key: request
+                .public_key
+                .parse()
+                .map_err(|err| Status::invalid_argument(format!("{err}")))?,
+        },
+        Some(Request::ViewAccessKeyList(request)) => {
+            views::QueryRequest::ViewAccessKeyList { account_id: account_id(request.account_id)? }
+        }
+        Some(Request::CallFunction(request)) => views::QueryRequest::CallFunction {
+            account_id: account_id(request.account_id)?,
+            method_name: request.method_name,
+            args: request.args.into(),
+        },
+        None => return Err(Status::invalid_argument("request is required")),
+    })
+}
+
+fn borsh_bytes(value: &impl BorshSerialize) -> Vec<u8> {
+    borsh::to_vec(value).expect("borsh serialization into a vector never fails")
+}
+
+impl From<views::BlockHeaderView> for proto::BlockHeader {
+    fn from(header: views::BlockHeaderView) -> Self {
+        Self {
+            height: header.height,
+            hash: header.hash.as_bytes().to_vec(),
+            prev_hash: header.prev_hash.as_bytes().to_vec(),
+            epoch_id: header.epoch_id.as_bytes().to_vec(),
+            next_epoch_id: header.next_epoch_id.as_bytes().to_vec(),
+            timestamp_nanosec: header.timestamp_nanosec,
+            prev_state_root: header.prev_state_root.as_bytes().to_vec(),
+            chunk_receipts_root: header.chunk_receipts_root.as_bytes().to_vec(),
+            chunk_headers_root: header.chunk_headers_root.as_bytes().to_vec(),
+            chunk_tx_root: header.chunk_tx_root.as_bytes().to_vec(),
+            outcome_root: header.outcome_root.as_bytes().to_vec(),
+            chunks_included: header.chunks_included,
+            chunk_mask: header.chunk_mask,
+            gas_price: header.gas_price.to_string(),
+            total_supply: header.total_supply.to_string(),
+            last_final_block: header.last_final_block.as_bytes().to_vec(),
+            last_ds_final_block: header.last_ds_final_block.as_bytes().to_vec(),
+            next_bp_hash: header.next_bp_hash.as_bytes().to_vec(),
+            block_merkle_root: header.block_merkle_root.as_bytes().to_vec(),
+            latest_protocol_version: header.latest_protocol_version,
+        }
+    }
+}
+
+impl From<views::ChunkHeaderView> for proto::ChunkHeader {
+    fn from(header: views::ChunkHeaderView) -> Self {
+        Self {
+            chunk_hash: header.chunk_hash.as_bytes().to_vec(),
+            prev_block_hash: header.prev_block_hash.as_bytes().to_vec(),
+            outcome_root: header.outcome_root.as_bytes().to_vec(),
+            prev_state_root: header.prev_state_root.as_bytes().to_vec(),
+            height_created: header.height_created,
+            height_included: header.height_included,
+            shard_id: header.shard_id,
+            gas_used: header.gas_used,
+            gas_limit: header.gas_limit,
+            balance_burnt: header.balance_burnt.to_string(),
+            outgoing_receipts_root: header.outgoing_receipts_root.as_bytes().to_vec(),
+            tx_root: header.tx_root.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl From<views::BlockView> for proto::Block {
+    fn from(block: views::BlockView) -> Self {
+        Self {
+            author: block.author.to_string(),
+            header: Some(block.header.into()),
+            chunks: block.chunks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<views::ChunkView> for proto::Chunk {
+    fn from(chunk: views::ChunkView) -> Self {
+        Self {
+            author: chunk.author.to_string(),
+            header: Some(chunk.header.into()),
+            transactions: chunk.transactions.iter().map(borsh_bytes).collect(),
+            receipts: chunk.receipts.iter().map(borsh_bytes).collect(),
+        }
+    }
+}
+
+impl From<views::TxStatusView> for proto::TxStatus {
+    fn from(tx_status: views::TxStatusView) -> Self {
+        let status = match tx_status.status {
+            views::TxExecutionStatus::None => proto::TxExecutionStatus::None,
+            views::TxExecutionStatus::Included => proto::TxExecutionStatus::Included,
+            views::TxExecutionStatus::IncludedFinal => proto::TxExecutionStatus::IncludedFinal,
+            views::TxExecutionStatus::Executed => proto::TxExecutionStatus::Executed,
+            views::TxExecutionStatus::Final => proto::TxExecutionStatus::Final,
+        };
+        Self {
+            status: status as i32,
+            execution_outcome: tx_status
+                .execution_outcome
+                .map(|outcome| borsh_bytes(&outcome.into_outcome()))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<views::QueryResponse> for proto::QueryResponse {
+    fn from(response: views::QueryResponse) -> Self {
+        use proto::query_response::Kind;
+
+        let kind = match response.kind {
+            views::QueryResponseKind::ViewAccount(account) => Kind::Account(proto::Account {
+                amount: account.amount.to_string(),
+                locked: account.locked.to_string(),
+                code_hash: account.code_hash.as_bytes().to_vec(),
+                storage_usage: account.storage_usage,
+            }),
+            views::QueryResponseKind::ViewCode(code) => Kind::Code(proto::ContractCode {
+                code: code.code,
+                hash: code.hash.as_bytes().to_vec(),
+            }),
+            views::QueryResponseKind::ViewState(state) => Kind::State(proto::State {
+                values: state
+                    .values
+                    .into_iter()
+                    .map(|item| proto::StateItem { key: item.key.into(), value: item.value.into() })
+                    .collect(),
+                proof: state.proof.iter().map(|node| node.to_vec()).collect(),
+            }),
+            views::QueryResponseKind::CallResult(result) => {
+                Kind::CallResult(proto::CallResult { result: result.result, logs: result.logs })
+            }
+            views::QueryResponseKind::AccessKey(access_key) => {
+                Kind::AccessKey(borsh_bytes(&access_key))
+            }
+            views::QueryResponseKind::AccessKeyList(list) => {
+                Kind::AccessKeyList(proto::AccessKeyList {
+                    keys: list
+                        .keys
+                        .into_iter()
+                        .map(|key| proto::AccessKeyInfo {
+                            public_key: key.public_key.to_string(),
+                            access_key: borsh_bytes(&key.access_key),
+                        })
+                        .collect(),
+                })
+            }
+        };
+        Self {
+            block_height: response.block_height,
+            block_hash: response.block_hash.as_bytes().to_vec(),
+            kind: Some(kind),
+        }
+    }
+}
+
+/// Wrapper of the errors of the view client, to convert them into statuses.
+struct ErrorStatus<E>(E);
+
+impl From<ErrorStatus<GetBlockError>> for Status {
+    fn from(ErrorStatus(error): ErrorStatus<GetBlockError>) -> Self {
+        let message = error.to_string();
+        match error {
+            GetBlockError::UnknownBlock { .. } => Status::not_found(message),
+            GetBlockError::NotSyncedYet => Status::unavailable(message),
+            GetBlockError::IOError { .. } | GetBlockError::Unreachable { .. } => {
+                Status::internal(message)
+            }
+        }
+    }
+}
+
+impl From<ErrorStatus<GetChunkError>> for Status {
+    fn from(ErrorStatus(error): ErrorStatus<GetChunkError>) -> Self {
+        let message = error.to_string();
+        match error {
+            GetChunkError::UnknownBlock { .. } | GetChunkError::UnknownChunk { .. } => {
+                Status::not_found(message)
+            }
+            GetChunkError::InvalidShardId { .. } => Status::invalid_argument(message),
+            GetChunkError::IOError { .. } | GetChunkError::Unreachable { .. } => {
+                Status::internal(message)
+            }
+        }
+    }
+}
+
+impl From<ErrorStatus<TxStatusError>> for Status {
+    fn from(ErrorStatus(error): ErrorStatus<TxStatusError>) -> Self {
+        match error {
+            TxStatusError::MissingTransaction(tx_hash) => {
+                Status::not_found(format!("transaction {tx_hash} is unknown"))
+            }
+            TxStatusError::TimeoutError => Status::deadline_exceeded("timeout"),
+            TxStatusError::ChainError(err) => Status::internal(format!("{err:?}")),
+            TxStatusError::InternalError(message) => Status::internal(message),
+        }
+    }
+}
+
+impl From<ErrorStatus<QueryError>> for Status {
+    fn from(ErrorStatus(error): ErrorStatus<QueryError>) -> Self {
+        let message = error.to_string();
+        match error {
+            QueryError::UnknownBlock { .. }
+            | QueryError::GarbageCollectedBlock { .. }
+            | QueryError::UnknownAccount { .. }
+            | QueryError::NoContractCode { .. }
+            | QueryError::UnknownAccessKey { .. } => Status::not_found(message),
+            QueryError::InvalidAccount { .. } => Status::invalid_argument(message),
+            QueryError::NoSyncedBlocks | QueryError::UnavailableShard { .. } => {
+                Status::unavailable(message)
+            }
+            QueryError::TooLargeContractState { .. } => Status::resource_exhausted(message),
+            QueryError::ContractExecutionError { .. } => Status::failed_precondition(message),
+            QueryError::InternalError { .. } | QueryError::Unreachable { .. } => {
+                Status::internal(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[test]
+    fn test_block_reference() {
+        use proto::block_reference::Reference;
+
+        let reference = |reference| Some(proto::BlockReference { reference: Some(reference) });
+        assert_eq!(
+            block_reference(reference(Reference::Height(10))).unwrap(),
+            BlockId::Height(10).into()
+        );
+        let hash = CryptoHash::hash_bytes(b"block");
+        assert_eq!(
+            block_reference(reference(Reference::Hash(hash.as_bytes().to_vec()))).unwrap(),
+            BlockId::Hash(hash).into()
+        );
+        assert_eq!(
+            block_reference(reference(Reference::Finality(proto::Finality::Optimistic as i32)))
+                .unwrap(),
+            BlockReference::Finality(Finality::None)
+        );
+
+        let code = |reference| block_reference(reference).unwrap_err().code();
+        assert_eq!(code(reference(Reference::Hash(vec![1, 2, 3]))), Code::InvalidArgument);
+        assert_eq!(code(reference(Reference::Finality(10))), Code::InvalidArgument);
+        assert_eq!(code(Some(proto::BlockReference { reference: None })), Code::InvalidArgument);
+        assert_eq!(code(None), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_query_request() {
+        use proto::query_request::Request;
+
+        let request = Request::ViewState(proto::ViewStateRequest {
+            account_id: "alice.near".to_string(),
+            prefix: b"key".to_vec(),
+            include_proof: true,
+        });
+        assert_eq!(
+            query_request(Some(request)).unwrap(),
+            views::QueryRequest::ViewState {
+                account_id: "alice.near".parse().unwrap(),
+                prefix: b"key".to_vec().into(),
+                include_proof: true,
+            }
+        );
+
+        let request = Request::ViewAccount(proto::ViewAccountRequest {
+            account_id: "Invalid Account".to_string(),
+        });
+        assert_eq!(query_request(Some(request)).unwrap_err().code(), Code::InvalidArgument);
+        let request = Request::ViewAccessKey(proto::ViewAccessKeyRequest {
+            account_id: "alice.near".to_string(),
+            public_key: "not a key".to_string(),
+        });
+        assert_eq!(query_request(Some(request)).unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(query_request(None).unwrap_err().code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_error_status() {
+        let status = |error: QueryError| Status::from(ErrorStatus(error)).code();
+        assert_eq!(
+            status(QueryError::UnknownAccount {
+                requested_account_id: "alice.near".parse().unwrap(),
+                block_height: 1,
+                block_hash: CryptoHash::default(),
+            }),
+            Code::NotFound
+        );
+        assert_eq!(status(QueryError::NoSyncedBlocks), Code::Unavailable);
+        assert_eq!(
+            status(QueryError::InternalError { error_message: String::new() }),
+            Code::Internal
+        );
+        assert_eq!(
+            status(QueryError::Unreachable { error_message: String::new() }),
+            Code::Internal
+        );
+        assert_eq!(
+            Status::from(ErrorStatus(GetBlockError::NotSyncedYet)).code(),
+            Code::Unavailable
+        );
+        assert_eq!(
+            Status::from(ErrorStatus(TxStatusError::TimeoutError)).code(),
+            Code::DeadlineExceeded
+        );
+    }
+}
@@ -8,6 +8,16 @@ pub struct StreamerMessage {
     pub shards: Vec<IndexerShard>,
 }
 
+/// Resulting struct represents a chunk applied by the node, with its execution outcomes and
+/// state changes, streamed before the block including it is final
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct StreamerChunkMessage {
+    /// Header of the block including the chunk. The block may not become final if it ends up
+    /// on a fork
+    pub block_header: views::BlockHeaderView,
+    pub shard: IndexerShard,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct IndexerChunkView {
     pub author: types::AccountId,
@@ -11,7 +11,7 @@ pub use nearcore::{get_default_home, init_configs, NearConfig};
 pub use near_indexer_primitives::{
     IndexerChunkView, IndexerExecutionOutcomeWithOptionalReceipt,
     IndexerExecutionOutcomeWithReceipt, IndexerShard, IndexerTransactionWithOutcome,
-    StreamerMessage,
+    StreamerChunkMessage, StreamerMessage,
 };
 
 mod streamer;
@@ -70,6 +70,18 @@ pub enum AwaitForNodeSyncedEnum {
     StreamWhileSyncing,
 }
 
+/// Enum to define what NEAR Indexer streams
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamerModeEnum {
+    /// Stream a `StreamerMessage` for every final block, see `Indexer::streamer`
+    FinalBlocks,
+    /// Stream a `StreamerChunkMessage` for every chunk as soon as the node applies it, before
+    /// the block including it is final, see `Indexer::chunk_streamer`. Useful to index pending
+    /// receipts with low latency, as long as the indexer reconciles them with the final blocks,
+    /// since the blocks may end up on a fork.
+    Chunks,
+}
+
 /// NEAR Indexer configuration to be provided to `Indexer::new(IndexerConfig)`
 #[derive(Debug, Clone)]
 pub struct IndexerConfig {
@@ -81,6 +93,8 @@ pub struct IndexerConfig {
     pub await_for_node_synced: AwaitForNodeSyncedEnum,
     /// Tells whether to validate the genesis file before starting
     pub validate_genesis: bool,
+    /// What to stream
+    pub streamer_mode: StreamerModeEnum,
 }
 
 /// This is the core component, which handles `nearcore` and internal `streamer`.
@@ -123,17 +137,42 @@ impl Indexer {
     }
 
     /// Boots up `near_indexer::streamer`, so it monitors the new blocks with chunks, transactions, receipts, and execution outcomes inside. The returned stream handler should be drained and handled on the user side.
+    ///
+    /// Requires `StreamerModeEnum::FinalBlocks`.
     pub fn streamer(&self) -> mpsc::Receiver<StreamerMessage> {
+        assert_eq!(
+            self.indexer_config.streamer_mode,
+            StreamerModeEnum::FinalBlocks,
+            "Indexer::streamer requires StreamerModeEnum::FinalBlocks"
+        );
+        let (sender, receiver) = mpsc::channel(100);
+        self.start_streamer(streamer::StreamerSink::FinalBlocks(sender));
+        receiver
+    }
+
+    /// Boots up `near_indexer::streamer`, so it monitors the chunks applied by the node, with their transactions, receipts, execution outcomes and state changes, before their blocks are final. The returned stream handler should be drained and handled on the user side.
+    ///
+    /// Requires `StreamerModeEnum::Chunks`.
+    pub fn chunk_streamer(&self) -> mpsc::Receiver<StreamerChunkMessage> {
+        assert_eq!(
+            self.indexer_config.streamer_mode,
+            StreamerModeEnum::Chunks,
+            "Indexer::chunk_streamer requires StreamerModeEnum::Chunks"
+        );
         let (sender, receiver) = mpsc::channel(100);
+        self.start_streamer(streamer::StreamerSink::Chunks(sender));
+        receiver
+    }
+
+    fn start_streamer(&self, sink: streamer::StreamerSink) {
         actix::spawn(streamer::start(
             self.view_client.clone(),
             self.client.clone(),
             self.indexer_config.clone(),
             self.near_config.config.store.clone(),
             self.near_config.config.archive,
-            sender,
+            sink,
         ));
-        receiver
     }
 
     /// Expose neard config
@@ -12,7 +12,7 @@ use async_recursion::async_recursion;
 use near_indexer_primitives::{
     IndexerChunkView, IndexerExecutionOutcomeWithOptionalReceipt,
     IndexerExecutionOutcomeWithReceipt, IndexerShard, IndexerTransactionWithOutcome,
-    StreamerMessage,
+    StreamerChunkMessage, StreamerMessage,
 };
 use near_parameters::RuntimeConfig;
 use near_primitives::hash::CryptoHash;
@@ -278,6 +278,52 @@ async fn find_local_receipt_by_id_in_block(
     Ok(None)
 }
 
+/// Where the Streamer sends what it fetches, depending on `StreamerModeEnum`.
+pub(crate) enum StreamerSink {
+    FinalBlocks(mpsc::Sender<StreamerMessage>),
+    Chunks(mpsc::Sender<StreamerChunkMessage>),
+}
+
+impl StreamerSink {
+    /// Blocks are fetched at this finality.
+    fn finality(&self) -> near_primitives::types::Finality {
+        match self {
+            Self::FinalBlocks(_) => near_primitives::types::Finality::Final,
+            Self::Chunks(_) => near_primitives::types::Finality::None,
+        }
+    }
+
+    /// Key of the last synced block height in the indexer DB, so that both modes can resume
+    /// from their own interruption.
+    fn last_synced_block_height_key(&self) -> &'static [u8] {
+        match self {
+            Self::FinalBlocks(_) => b"last_synced_block_height",
+            Self::Chunks(_) => b"last_synced_chunk_block_height",
+        }
+    }
+
+    /// Sends the block as a whole or as a message per new chunk. Returns an error if the
+    /// listener is gone.
+    async fn send(&self, streamer_message: StreamerMessage) -> Result<(), ()> {
+        match self {
+            Self::FinalBlocks(sender) => {
+                sender.send(streamer_message).await.map_err(|_| ())?;
+                metrics::NUM_STREAMER_MESSAGES_SENT.inc();
+            }
+            Self::Chunks(sender) => {
+                let StreamerMessage { block, shards } = streamer_message;
+                for shard in shards.into_iter().filter(|shard| shard.chunk.is_some()) {
+                    let chunk_message =
+                        StreamerChunkMessage { block_header: block.header.clone(), shard };
+                    sender.send(chunk_message).await.map_err(|_| ())?;
+                    metrics::NUM_STREAMER_MESSAGES_SENT.inc();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Function that starts Streamer's busy loop. Every half a seconds it fetches the status
 /// compares to already fetched block height and in case it differs fetches new block of given height.
 ///
@@ -288,7 +334,7 @@ pub(crate) async fn start(
     indexer_config: IndexerConfig,
     store_config: near_store::StoreConfig,
     archive: bool,
-    blocks_sink: mpsc::Sender<StreamerMessage>,
+    sink: StreamerSink,
 ) {
     info!(target: INDEXER, "Starting Streamer...");
     let indexer_db_path =
@@ -314,7 +360,7 @@ pub(crate) async fn start(
             AwaitForNodeSyncedEnum::StreamWhileSyncing => {}
         };
 
-        let block = if let Ok(block) = fetch_latest_block(&view_client).await {
+        let block = if let Ok(block) = fetch_latest_block(&view_client, sink.finality()).await {
             block
         } else {
             continue;
@@ -328,7 +374,7 @@ pub(crate) async fn start(
         } else {
             match indexer_config.sync_mode {
                 crate::SyncModeEnum::FromInterruption => {
-                    match db.get(b"last_synced_block_height").unwrap() {
+                    match db.get(sink.last_synced_block_height_key()).unwrap() {
                         Some(value) => String::from_utf8(value).unwrap().parse::<u64>().unwrap(),
                         None => latest_block_height,
                     }
@@ -354,14 +400,12 @@ pub(crate) async fn start(
                 match response {
                     Ok(streamer_message) => {
                         debug!(target: INDEXER, "{:#?}", &streamer_message);
-                        if blocks_sink.send(streamer_message).await.is_err() {
+                        if sink.send(streamer_message).await.is_err() {
                             info!(
                                 target: INDEXER,
                                 "Unable to send StreamerMessage to listener, listener doesn't listen. terminating..."
                             );
                             break 'main;
-                        } else {
-                            metrics::NUM_STREAMER_MESSAGES_SENT.inc();
                         }
                     }
                     Err(err) => {
@@ -373,7 +417,7 @@ pub(crate) async fn start(
                     }
                 }
             }
-            db.put(b"last_synced_block_height", &block_height.to_string()).unwrap();
+            db.put(sink.last_synced_block_height_key(), &block_height.to_string()).unwrap();
             last_synced_block_height = Some(block_height);
         }
     }
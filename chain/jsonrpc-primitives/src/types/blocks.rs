@@ -29,6 +29,47 @@ pub struct RpcBlockResponse {
     pub block_view: near_primitives::views::BlockView,
 }
 
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcBlockRangeError {
+    #[error("to_height {to_height} is lower than from_height {from_height}")]
+    InvalidRange {
+        from_height: near_primitives::types::BlockHeight,
+        to_height: near_primitives::types::BlockHeight,
+    },
+    #[error("The range of {num_blocks} heights exceeds the limit of {max_num_blocks}")]
+    RangeTooLarge {
+        num_blocks: near_primitives::types::BlockHeight,
+        max_num_blocks: near_primitives::types::BlockHeight,
+    },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcBlockRangeRequest {
+    pub from_height: near_primitives::types::BlockHeight,
+    /// Inclusive.
+    pub to_height: near_primitives::types::BlockHeight,
+    /// Optional parts of the block summaries to return, all of them by default.
+    #[serde(default = "all_block_range_fields")]
+    pub fields: Vec<near_primitives::views::BlockRangeField>,
+}
+
+fn all_block_range_fields() -> Vec<near_primitives::views::BlockRangeField> {
+    vec![
+        near_primitives::views::BlockRangeField::Header,
+        near_primitives::views::BlockRangeField::ChunkMask,
+    ]
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcBlockRangeResponse {
+    /// Blocks of the canonical chain in the range, by increasing height.
+    /// Heights without a block are left out.
+    pub blocks: Vec<near_primitives::views::BlockSummaryView>,
+}
+
 impl From<RpcBlockError> for crate::errors::RpcError {
     fn from(error: RpcBlockError) -> Self {
         let error_data = match &error {
@@ -54,3 +95,20 @@ impl From<RpcBlockError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(error_data, error_data_value)
     }
 }
+
+impl From<RpcBlockRangeError> for crate::errors::RpcError {
+    fn from(error: RpcBlockRangeError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcBlockRangeError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -0,0 +1,40 @@
+use serde_json::Value;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcChunkStateWitnessRequest {
+    pub chunk_id: near_primitives::hash::CryptoHash,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcChunkStateWitnessResponse {
+    #[serde(flatten)]
+    pub witness_view: near_primitives::views::ChunkStateWitnessView,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcChunkStateWitnessError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+    #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
+    UnknownChunk { chunk_hash: near_primitives::sharding::ChunkHash },
+    #[error("State witness for the chunk is not available on this node: {error_message}")]
+    WitnessUnavailable { error_message: String },
+}
+
+impl From<RpcChunkStateWitnessError> for crate::errors::RpcError {
+    fn from(error: RpcChunkStateWitnessError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcChunkStateWitnessError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
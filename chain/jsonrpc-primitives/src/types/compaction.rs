@@ -0,0 +1,61 @@
+// This file contains structures for the manual compaction of the database of
+// a running node, triggered through the admin HTTP endpoint.
+use crate::errors::RpcError;
+use serde::{Deserialize, Serialize};
+
+/// Request to compact a column of the database.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompactionRequest {
+    /// Name of the column, e.g. `State`.
+    pub column: String,
+    /// Whether to compact the column of the cold database instead of the hot
+    /// one.
+    #[serde(default)]
+    pub cold: bool,
+    /// Limits the rate at which the column is compacted, so that the
+    /// compaction doesn't starve the node of disk bandwidth. Unlimited if not
+    /// set.
+    #[serde(default)]
+    pub max_bytes_per_second: Option<u64>,
+}
+
+/// Progress of the last compaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompactionStatus {
+    pub request: CompactionRequest,
+    /// When the compaction was started, in milliseconds since the Unix epoch.
+    pub started_at_ms: i64,
+    /// The column is compacted piece by piece, in ranges of its keys.
+    pub num_ranges: usize,
+    pub num_ranges_done: usize,
+    /// Approximate size of the column on disk when the compaction started.
+    pub total_bytes: u64,
+    pub bytes_done: u64,
+    pub finished: bool,
+    /// Set if the compaction failed.
+    pub error: Option<String>,
+}
+
+/// We use a trait for this, because jsonrpc does not have access to the
+/// storage of the node.
+pub trait CompactionHandler: Sync + Send {
+    /// Starts compacting a column in the background. Fails if a compaction is
+    /// already in progress.
+    fn start(&self, request: CompactionRequest) -> Result<CompactionStatus, RpcError>;
+
+    /// Returns the progress of the last compaction, if any.
+    fn status(&self) -> Option<CompactionStatus>;
+}
+
+/// For tests.
+pub struct DummyCompactionHandler {}
+
+impl CompactionHandler for DummyCompactionHandler {
+    fn start(&self, _request: CompactionRequest) -> Result<CompactionStatus, RpcError> {
+        Err(RpcError::new_internal_error(None, "Not implemented".to_string()))
+    }
+
+    fn status(&self) -> Option<CompactionStatus> {
+        None
+    }
+}
@@ -13,6 +13,19 @@ pub struct RpcLightClientNextBlockRequest {
     pub last_block_hash: near_primitives::hash::CryptoHash,
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcLightClientChunkExecutionProofRequest {
+    pub chunk_id: near_primitives::hash::CryptoHash,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcLightClientAccountOutcomesProofRequest {
+    pub account_id: near_primitives::types::AccountId,
+    pub from_height: near_primitives::types::BlockHeight,
+    pub to_height: near_primitives::types::BlockHeight,
+    pub light_client_head: near_primitives::hash::CryptoHash,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct RpcLightClientExecutionProofResponse {
     pub outcome_proof: near_primitives::views::ExecutionOutcomeWithIdView,
@@ -21,6 +34,11 @@ pub struct RpcLightClientExecutionProofResponse {
     pub block_proof: near_primitives::merkle::MerklePath,
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcLightClientAccountOutcomesProofResponse {
+    pub blocks: Vec<near_primitives::views::LightClientBlockOutcomesProofView>,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct RpcLightClientNextBlockResponse {
     #[serde(flatten)]
@@ -53,6 +71,49 @@ pub enum RpcLightClientProofError {
     InternalError { error_message: String },
 }
 
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcLightClientAccountOutcomesProofError {
+    #[error("Block either has never been observed on the node or has been garbage collected: {error_message}")]
+    UnknownBlock {
+        #[serde(skip_serializing)]
+        error_message: String,
+    },
+    #[error("to_height {to_height} is lower than from_height {from_height}")]
+    InvalidRange {
+        from_height: near_primitives::types::BlockHeight,
+        to_height: near_primitives::types::BlockHeight,
+    },
+    #[error("The range of {num_blocks} heights exceeds the limit of {max_num_blocks}")]
+    RangeTooLarge {
+        num_blocks: near_primitives::types::BlockHeight,
+        max_num_blocks: near_primitives::types::BlockHeight,
+    },
+    #[error("Outcomes in the block at height {block_height} have not been confirmed")]
+    NotConfirmed { block_height: near_primitives::types::BlockHeight },
+    #[error("Node doesn't track the shard {shard_id} of the account")]
+    UnavailableShard { shard_id: near_primitives::types::ShardId },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcLightClientChunkExecutionProofResponse {
+    #[serde(flatten)]
+    pub proof: near_primitives::views::ChunkExecutionProofView,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcLightClientChunkExecutionProofError {
+    #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
+    UnknownChunk { chunk_hash: near_primitives::sharding::ChunkHash },
+    #[error("Execution proof for the chunk is not available on this node: {error_message}")]
+    ProofUnavailable { error_message: String },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
 #[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcLightClientNextBlockError {
@@ -90,6 +151,32 @@ impl From<RpcLightClientProofError> for crate::errors::RpcError {
     }
 }
 
+impl From<RpcLightClientAccountOutcomesProofError> for crate::errors::RpcError {
+    fn from(error: RpcLightClientAccountOutcomesProofError) -> Self {
+        let error_data = match &error {
+            RpcLightClientAccountOutcomesProofError::UnknownBlock { error_message } => {
+                Some(Value::String(format!("DB Not Found Error: {}", error_message)))
+            }
+            _ => Some(Value::String(error.to_string())),
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!(
+                        "Failed to serialize RpcLightClientAccountOutcomesProofError: {:?}",
+                        err
+                    ),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
+
 impl From<RpcLightClientNextBlockError> for crate::errors::RpcError {
     fn from(error: RpcLightClientNextBlockError) -> Self {
         let error_data = match serde_json::to_value(error) {
@@ -104,3 +191,23 @@ impl From<RpcLightClientNextBlockError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
     }
 }
+
+impl From<RpcLightClientChunkExecutionProofError> for crate::errors::RpcError {
+    fn from(error: RpcLightClientChunkExecutionProofError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!(
+                        "Failed to serialize RpcLightClientChunkExecutionProofError: {:?}",
+                        err
+                    ),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -36,3 +36,51 @@ impl From<RpcMaintenanceWindowsError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(error_data, error_data_value)
     }
 }
+
+pub type RpcScheduledMaintenanceWindowResponse =
+    near_primitives::views::ScheduledMaintenanceWindowView;
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcScheduledMaintenanceWindowError {
+    #[error("The node has no validator, the account must be given")]
+    NoValidator,
+    #[error("The node has no maintenance declared, the number of blocks must be given")]
+    NoMaintenanceDeclared,
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+/// Both fields default to the validator of the node and the maintenance
+/// declared in its config.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcScheduledMaintenanceWindowRequest {
+    #[serde(default)]
+    pub account_id: Option<near_primitives::types::AccountId>,
+    #[serde(default)]
+    pub num_blocks: Option<near_primitives::types::BlockHeightDelta>,
+}
+
+impl From<RpcScheduledMaintenanceWindowError> for crate::errors::RpcError {
+    fn from(error: RpcScheduledMaintenanceWindowError) -> Self {
+        let error_data = match &error {
+            RpcScheduledMaintenanceWindowError::NoValidator
+            | RpcScheduledMaintenanceWindowError::NoMaintenanceDeclared
+            | RpcScheduledMaintenanceWindowError::InternalError { .. } => {
+                Some(Value::String(error.to_string()))
+            }
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcScheduledMaintenanceWindowError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -1,7 +1,9 @@
 pub mod blocks;
 pub mod changes;
+pub mod chunk_state_witness;
 pub mod chunks;
 pub mod client_config;
+pub mod compaction;
 pub mod config;
 pub mod entity_debug;
 pub mod gas_price;
@@ -13,5 +15,7 @@ pub mod receipts;
 pub mod sandbox;
 pub mod split_storage;
 pub mod status;
+pub mod subscriptions;
 pub mod transactions;
+pub mod tx_pool;
 pub mod validator;
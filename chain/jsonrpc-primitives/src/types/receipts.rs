@@ -15,6 +15,18 @@ pub struct RpcReceiptResponse {
     pub receipt_view: near_primitives::views::ReceiptView,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcReceiptWithOutcomeRequest {
+    #[serde(flatten)]
+    pub receipt_reference: ReceiptReference,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcReceiptWithOutcomeResponse {
+    #[serde(flatten)]
+    pub receipt_with_outcome: near_primitives::views::ReceiptWithOutcomeView,
+}
+
 #[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcReceiptError {
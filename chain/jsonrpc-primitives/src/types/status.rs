@@ -1,6 +1,6 @@
 #[cfg(feature = "debug_types")]
 use near_client_primitives::debug::{
-    DebugBlockStatusData, EpochInfoView, TrackedShardsView, ValidatorStatus,
+    DebugBlockStatusData, EndorsementStatus, EpochInfoView, TrackedShardsView, ValidatorStatus,
 };
 #[cfg(feature = "debug_types")]
 use near_primitives::views::{
@@ -36,6 +36,7 @@ pub enum DebugStatusResponse {
     Routes(NetworkRoutesView),
     SnapshotHosts(SnapshotHostsView),
     SplitStoreStatus(SplitStorageInfoView),
+    ChunkEndorsementStatus(EndorsementStatus),
 }
 
 #[cfg(feature = "debug_types")]
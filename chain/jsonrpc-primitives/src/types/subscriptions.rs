@@ -0,0 +1,175 @@
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{
+    AccountId, Balance, BlockHeight, ShardId, StateChangesFilter, StoreKey,
+};
+use near_primitives::views::{BlockHeaderView, ChunkHeaderView, StateChangesView};
+use serde_json::Value;
+
+/// Id of a subscription, unique within a websocket connection.
+pub type SubscriptionId = u64;
+
+/// Events a websocket connection can subscribe to.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RpcSubscriptionKind {
+    /// Header of every new head of the chain. Heads may be on forks.
+    NewHeads,
+    /// Header of every chunk included in a new head, of all shards or of the given shard.
+    Chunks {
+        #[serde(default)]
+        shard_id: Option<ShardId>,
+    },
+    /// Endorsements collected by the node for every chunk included in a new head, of all shards
+    /// or of the given shard. Experimental: only reported with stateless validation enabled.
+    ExperimentalChunkEndorsements {
+        #[serde(default)]
+        shard_id: Option<ShardId>,
+    },
+    /// State changes of every new head, in the shards tracked by the node, of the accounts whose
+    /// id starts with one of `account_id_prefixes`, or of all the accounts if there are none. If
+    /// `key_prefixes_base64` are given, only the contract data changes whose key starts with one
+    /// of them. Blocks without such changes are not reported.
+    ExperimentalStateChanges {
+        #[serde(default)]
+        account_id_prefixes: Vec<String>,
+        #[serde(default, rename = "key_prefixes_base64")]
+        key_prefixes: Vec<StoreKey>,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcSubscribeRequest {
+    #[serde(flatten)]
+    pub kind: RpcSubscriptionKind,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcSubscribeResponse {
+    pub subscription_id: SubscriptionId,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcUnsubscribeRequest {
+    pub subscription_id: SubscriptionId,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcUnsubscribeResponse {
+    /// Whether the subscription existed.
+    pub unsubscribed: bool,
+}
+
+/// Endorsements collected for a chunk, as reported by `experimental_chunk_endorsements`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ChunkEndorsementsView {
+    pub chunk_hash: ChunkHash,
+    pub shard_id: ShardId,
+    pub endorsed_by: Vec<AccountId>,
+    pub missing: Vec<AccountId>,
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub endorsed_stake: Balance,
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub total_stake: Balance,
+    /// Whether the endorsed stake is above the endorsement quorum.
+    pub has_quorum: bool,
+}
+
+/// Event sent to a subscription.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RpcSubscriptionEvent {
+    NewHead {
+        header: BlockHeaderView,
+    },
+    Chunk {
+        block_hash: CryptoHash,
+        block_height: BlockHeight,
+        chunk: ChunkHeaderView,
+    },
+    ChunkEndorsements {
+        block_hash: CryptoHash,
+        block_height: BlockHeight,
+        endorsements: ChunkEndorsementsView,
+    },
+    StateChanges {
+        block_hash: CryptoHash,
+        block_height: BlockHeight,
+        changes: StateChangesView,
+    },
+}
+
+impl RpcSubscriptionEvent {
+    /// Returns the event as received by a subscription of the given kind, if it receives it.
+    pub fn filter(&self, kind: &RpcSubscriptionKind) -> Option<Self> {
+        let matches = match (self, kind) {
+            (Self::NewHead { .. }, RpcSubscriptionKind::NewHeads) => true,
+            (Self::Chunk { chunk, .. }, RpcSubscriptionKind::Chunks { shard_id }) => {
+                shard_id.map_or(true, |shard_id| shard_id == chunk.shard_id)
+            }
+            (
+                Self::ChunkEndorsements { endorsements, .. },
+                RpcSubscriptionKind::ExperimentalChunkEndorsements { shard_id },
+            ) => shard_id.map_or(true, |shard_id| shard_id == endorsements.shard_id),
+            (
+                Self::StateChanges { block_hash, block_height, changes },
+                RpcSubscriptionKind::ExperimentalStateChanges { account_id_prefixes, key_prefixes },
+            ) => {
+                let filter = StateChangesFilter {
+                    account_id_prefixes: account_id_prefixes.clone(),
+                    key_prefixes: key_prefixes.clone(),
+                };
+                let changes: StateChangesView = changes
+                    .iter()
+                    .filter(|change| {
+                        filter.matches(change.value.affected_account_id(), change.value.data_key())
+                    })
+                    .cloned()
+                    .collect();
+                if changes.is_empty() {
+                    return None;
+                }
+                return Some(Self::StateChanges {
+                    block_hash: *block_hash,
+                    block_height: *block_height,
+                    changes,
+                });
+            }
+            _ => false,
+        };
+        matches.then(|| self.clone())
+    }
+}
+
+/// Params of the `subscription` notifications sent to a websocket connection.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RpcSubscriptionNotification {
+    pub subscription_id: SubscriptionId,
+    pub event: RpcSubscriptionEvent,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSubscriptionError {
+    #[error("Subscriptions limit of {limit} per connection reached")]
+    TooManySubscriptions { limit: usize },
+    #[error("Method {method} is only available over a websocket connection")]
+    WebsocketOnly { method: String },
+}
+
+impl From<RpcSubscriptionError> for crate::errors::RpcError {
+    fn from(error: RpcSubscriptionError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSubscriptionError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
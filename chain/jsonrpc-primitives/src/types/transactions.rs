@@ -31,6 +31,31 @@ pub enum SignedTransaction {
     SignedTransaction(near_primitives::transaction::SignedTransaction),
 }
 
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcEstimateTransactionRequest {
+    #[serde(flatten)]
+    pub transaction: EstimatedTransaction,
+    /// The transaction is executed on the state after this block, or after
+    /// the latest block if not set.
+    #[serde(default)]
+    pub block_id: near_primitives::types::MaybeBlockId,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum EstimatedTransaction {
+    #[serde(rename = "signed_tx_base64")]
+    Signed(near_primitives::transaction::SignedTransaction),
+    /// The signature of an unsigned transaction isn't verified.
+    #[serde(rename = "tx_base64", with = "transaction_base64")]
+    Unsigned(near_primitives::transaction::Transaction),
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcEstimateTransactionResponse {
+    #[serde(flatten)]
+    pub estimate: near_primitives::views::TransactionEstimateView,
+}
+
 #[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize, Clone)]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcTransactionError {
@@ -91,6 +116,23 @@ impl TransactionInfo {
     }
 }
 
+impl EstimatedTransaction {
+    /// Returns the transaction, with an empty signature if it's unsigned, and
+    /// whether its signature should be verified.
+    pub fn into_signed_tx(self) -> (near_primitives::transaction::SignedTransaction, bool) {
+        match self {
+            EstimatedTransaction::Signed(tx) => (tx, true),
+            EstimatedTransaction::Unsigned(tx) => (
+                near_primitives::transaction::SignedTransaction::new(
+                    near_crypto::Signature::empty(tx.public_key.key_type()),
+                    tx,
+                ),
+                false,
+            ),
+        }
+    }
+}
+
 impl From<near_primitives::transaction::SignedTransaction> for TransactionInfo {
     fn from(transaction_info: near_primitives::transaction::SignedTransaction) -> Self {
         Self::Transaction(SignedTransaction::SignedTransaction(transaction_info))
@@ -136,3 +178,25 @@ impl From<RpcTransactionError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(Some(error_data), error_data_value)
     }
 }
+
+/// (De)serializes a `Transaction` as base64 encoded borsh, like
+/// `SignedTransaction`.
+mod transaction_base64 {
+    use near_primitives::borsh;
+    use near_primitives::serialize::{from_base64, to_base64};
+    use near_primitives::transaction::Transaction;
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(tx: &Transaction, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = borsh::to_vec(tx).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&to_base64(&bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Transaction, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = from_base64(&encoded).map_err(de::Error::custom)?;
+        borsh::from_slice(&bytes).map_err(de::Error::custom)
+    }
+}
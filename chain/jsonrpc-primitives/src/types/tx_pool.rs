@@ -0,0 +1,37 @@
+use serde_json::Value;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcTxPoolStatusRequest {
+    #[serde(default)]
+    pub account_id: Option<near_primitives::types::AccountId>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcTxPoolStatusResponse {
+    #[serde(flatten)]
+    pub status_view: near_primitives::views::TxPoolStatusView,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcTxPoolStatusError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcTxPoolStatusError> for crate::errors::RpcError {
+    fn from(error: RpcTxPoolStatusError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcTxPoolStatusError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -10,6 +10,10 @@ pub enum RpcValidatorError {
     UnknownEpoch,
     #[error("Validator info unavailable")]
     ValidatorInfoUnavailable,
+    #[error(
+        "Validators can't be estimated {epoch_offset} epochs ahead, at most {max_epoch_offset}"
+    )]
+    EpochOffsetTooLarge { epoch_offset: u64, max_epoch_offset: u64 },
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
     InternalError { error_message: String },
 }
@@ -25,6 +29,19 @@ pub struct RpcValidatorsOrderedRequest {
     pub block_id: near_primitives::types::MaybeBlockId,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcEstimatedValidatorsRequest {
+    /// Number of epochs after the current one, up to 2 for the epoch after
+    /// next, whose validators are estimated.
+    pub epoch_offset: near_primitives::types::EpochHeight,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcEstimatedValidatorsResponse {
+    #[serde(flatten)]
+    pub validators: near_primitives::views::EstimatedValidatorsView,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct RpcValidatorResponse {
     #[serde(flatten)]
@@ -38,7 +55,8 @@ impl From<RpcValidatorError> for crate::errors::RpcError {
             RpcValidatorError::ValidatorInfoUnavailable => {
                 Some(Value::String("Validator info unavailable".to_string()))
             }
-            RpcValidatorError::InternalError { .. } => Some(Value::String(error.to_string())),
+            RpcValidatorError::EpochOffsetTooLarge { .. }
+            | RpcValidatorError::InternalError { .. } => Some(Value::String(error.to_string())),
         };
 
         let error_data_value = match serde_json::to_value(error) {
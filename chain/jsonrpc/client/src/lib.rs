@@ -220,6 +220,13 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "tx", request)
     }
 
+    pub fn receipt(
+        &self,
+        request: near_jsonrpc_primitives::types::receipts::RpcReceiptWithOutcomeRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::receipts::RpcReceiptWithOutcomeResponse> {
+        call_method(&self.client, &self.server_addr, "receipt", request)
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_changes(
         &self,
@@ -236,6 +243,31 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_validators_ordered", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_estimated_validators(
+        &self,
+        request: near_jsonrpc_primitives::types::validator::RpcEstimatedValidatorsRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::validator::RpcEstimatedValidatorsResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_estimated_validators", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_estimate_tx(
+        &self,
+        request: near_jsonrpc_primitives::types::transactions::RpcEstimateTransactionRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::transactions::RpcEstimateTransactionResponse>
+    {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_estimate_tx", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_block_range(
+        &self,
+        request: near_jsonrpc_primitives::types::blocks::RpcBlockRangeRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::blocks::RpcBlockRangeResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_block_range", request)
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_receipt(
         &self,
@@ -244,6 +276,68 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_receipt", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_chunk_state_witness(
+        &self,
+        request: near_jsonrpc_primitives::types::chunk_state_witness::RpcChunkStateWitnessRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::chunk_state_witness::RpcChunkStateWitnessResponse>
+    {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_chunk_state_witness", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_tx_pool_status(
+        &self,
+        request: near_jsonrpc_primitives::types::tx_pool::RpcTxPoolStatusRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::tx_pool::RpcTxPoolStatusResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_tx_pool_status", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_light_client_account_outcomes_proof(
+        &self,
+        request: near_jsonrpc_primitives::types::light_client::RpcLightClientAccountOutcomesProofRequest,
+    ) -> RpcRequest<
+        near_jsonrpc_primitives::types::light_client::RpcLightClientAccountOutcomesProofResponse,
+    > {
+        call_method(
+            &self.client,
+            &self.server_addr,
+            "EXPERIMENTAL_light_client_account_outcomes_proof",
+            request,
+        )
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_light_client_chunk_execution_proof(
+        &self,
+        request: near_jsonrpc_primitives::types::light_client::RpcLightClientChunkExecutionProofRequest,
+    ) -> RpcRequest<
+        near_jsonrpc_primitives::types::light_client::RpcLightClientChunkExecutionProofResponse,
+    > {
+        call_method(
+            &self.client,
+            &self.server_addr,
+            "EXPERIMENTAL_light_client_chunk_execution_proof",
+            request,
+        )
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_scheduled_maintenance_window(
+        &self,
+        request: near_jsonrpc_primitives::types::maintenance::RpcScheduledMaintenanceWindowRequest,
+    ) -> RpcRequest<
+        near_jsonrpc_primitives::types::maintenance::RpcScheduledMaintenanceWindowResponse,
+    > {
+        call_method(
+            &self.client,
+            &self.server_addr,
+            "EXPERIMENTAL_scheduled_maintenance_window",
+            request,
+        )
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_protocol_config(
         &self,
@@ -5,10 +5,10 @@ use futures::{future, future::LocalBoxFuture, FutureExt, TryFutureExt};
 use near_chain_configs::GenesisConfig;
 use near_client::test_utils::setup_no_network_with_validity_period_and_no_epoch_sync;
 use near_client::ViewClientActor;
-use near_jsonrpc::{start_http, RpcConfig};
+use near_jsonrpc::{start_http, RpcConfig, RpcRateLimitsConfig};
 use near_jsonrpc_primitives::{
     message::{from_slice, Message},
-    types::entity_debug::DummyEntityDebugHandler,
+    types::{compaction::DummyCompactionHandler, entity_debug::DummyEntityDebugHandler},
 };
 use near_network::tcp;
 use near_primitives::types::NumBlocks;
@@ -27,10 +27,31 @@ pub fn start_all(node_type: NodeType) -> (Addr<ViewClientActor>, tcp::ListenerAd
     start_all_with_validity_period_and_no_epoch_sync(node_type, 100, false)
 }
 
+pub fn start_all_with_rate_limits(
+    node_type: NodeType,
+    rate_limits_config: RpcRateLimitsConfig,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
+    start_all_with_config(node_type, 100, false, rate_limits_config)
+}
+
 pub fn start_all_with_validity_period_and_no_epoch_sync(
     node_type: NodeType,
     transaction_validity_period: NumBlocks,
     enable_doomslug: bool,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
+    start_all_with_config(
+        node_type,
+        transaction_validity_period,
+        enable_doomslug,
+        RpcRateLimitsConfig::default(),
+    )
+}
+
+fn start_all_with_config(
+    node_type: NodeType,
+    transaction_validity_period: NumBlocks,
+    enable_doomslug: bool,
+    rate_limits_config: RpcRateLimitsConfig,
 ) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
     let actor_handles = setup_no_network_with_validity_period_and_no_epoch_sync(
         vec!["test1".parse().unwrap()],
@@ -45,13 +66,16 @@ pub fn start_all_with_validity_period_and_no_epoch_sync(
     );
 
     let addr = tcp::ListenerAddr::reserve_for_test();
+    let mut config = RpcConfig::new(addr);
+    config.rate_limits_config = rate_limits_config;
     start_http(
-        RpcConfig::new(addr),
+        config,
         TEST_GENESIS_CONFIG.clone(),
         actor_handles.client_actor,
         actor_handles.view_client_actor.clone(),
         None,
         Arc::new(DummyEntityDebugHandler {}),
+        Arc::new(DummyCompactionHandler {}),
     );
     (actor_handles.view_client_actor, addr)
 }
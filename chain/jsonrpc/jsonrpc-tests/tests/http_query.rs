@@ -1,8 +1,10 @@
 use actix::System;
-use futures::{future, FutureExt};
+use futures::{future, FutureExt, SinkExt, StreamExt};
+use serde_json::json;
 
 use near_actix_test_utils::run_actix;
 use near_jsonrpc::client::new_http_client;
+use near_jsonrpc::{RpcRateLimitsConfig, TokenBucketConfig};
 use near_o11y::testonly::init_test_logger;
 
 use near_jsonrpc_tests as test_utils;
@@ -26,3 +28,96 @@ fn test_status() {
         }));
     });
 }
+
+/// Subscribe to new heads and call a regular method over a websocket connection.
+#[test]
+fn test_websocket() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_view_client_addr, addr) = test_utils::start_all(test_utils::NodeType::NonValidator);
+
+        actix::spawn(async move {
+            let (_, mut connection) =
+                awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "1",
+                "method": "subscribe",
+                "params": {"kind": "chunks", "shard_id": 0},
+            });
+            connection.send(awc::ws::Message::Text(request.to_string().into())).await.unwrap();
+            let Some(Ok(awc::ws::Frame::Text(response))) = connection.next().await else {
+                panic!("expected a text frame");
+            };
+            let response: serde_json::Value = serde_json::from_slice(&response).unwrap();
+            assert_eq!(response["result"]["subscription_id"], 0);
+
+            let params = json!({
+                "kind": "experimental_state_changes",
+                "account_id_prefixes": ["test"],
+                "key_prefixes_base64": ["AA=="],
+            });
+            let request =
+                json!({"jsonrpc": "2.0", "id": "1", "method": "subscribe", "params": params});
+            connection.send(awc::ws::Message::Text(request.to_string().into())).await.unwrap();
+            let Some(Ok(awc::ws::Frame::Text(response))) = connection.next().await else {
+                panic!("expected a text frame");
+            };
+            let response: serde_json::Value = serde_json::from_slice(&response).unwrap();
+            assert_eq!(response["result"]["subscription_id"], 1);
+
+            let request = json!({"jsonrpc": "2.0", "id": "2", "method": "status", "params": []});
+            connection.send(awc::ws::Message::Text(request.to_string().into())).await.unwrap();
+            let Some(Ok(awc::ws::Frame::Text(response))) = connection.next().await else {
+                panic!("expected a text frame");
+            };
+            let response: serde_json::Value = serde_json::from_slice(&response).unwrap();
+            assert_eq!(response["id"], "2");
+            assert_eq!(response["result"]["chain_id"], "unittest");
+            System::current().stop();
+        });
+    });
+}
+
+/// Requests over a websocket connection are rate limited by the IP of the connection.
+#[test]
+fn test_websocket_rate_limit() {
+    init_test_logger();
+
+    run_actix(async {
+        let rate_limits_config = RpcRateLimitsConfig {
+            per_ip: Some(TokenBucketConfig { requests_per_second: 0.001, burst: 1 }),
+            ..Default::default()
+        };
+        let (_view_client_addr, addr) = test_utils::start_all_with_rate_limits(
+            test_utils::NodeType::NonValidator,
+            rate_limits_config,
+        );
+
+        actix::spawn(async move {
+            let (_, mut connection) =
+                awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+
+            let request = json!({"jsonrpc": "2.0", "id": "1", "method": "status", "params": []});
+            connection.send(awc::ws::Message::Text(request.to_string().into())).await.unwrap();
+            let Some(Ok(awc::ws::Frame::Text(response))) = connection.next().await else {
+                panic!("expected a text frame");
+            };
+            let response: serde_json::Value = serde_json::from_slice(&response).unwrap();
+            assert_eq!(response["result"]["chain_id"], "unittest");
+
+            let request = json!({"jsonrpc": "2.0", "id": "2", "method": "status", "params": []});
+            connection.send(awc::ws::Message::Text(request.to_string().into())).await.unwrap();
+            let Some(Ok(awc::ws::Frame::Text(response))) = connection.next().await else {
+                panic!("expected a text frame");
+            };
+            let response: serde_json::Value = serde_json::from_slice(&response).unwrap();
+            assert_eq!(response["id"], "2");
+            assert_eq!(response["error"]["code"], -32_000);
+            assert_eq!(response["error"]["message"], "Too many requests");
+            System::current().stop();
+        });
+    });
+}
@@ -8,14 +8,21 @@ use serde_json::json;
 use near_actix_test_utils::run_actix;
 use near_crypto::{KeyType, PublicKey, Signature};
 use near_jsonrpc::client::{new_client, ChunkId};
+use near_jsonrpc_primitives::types::blocks::RpcBlockRangeRequest;
+use near_jsonrpc_primitives::types::chunk_state_witness::RpcChunkStateWitnessRequest;
+use near_jsonrpc_primitives::types::light_client::RpcLightClientAccountOutcomesProofRequest;
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
-use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
+use near_jsonrpc_primitives::types::receipts::{ReceiptReference, RpcReceiptWithOutcomeRequest};
+use near_jsonrpc_primitives::types::tx_pool::RpcTxPoolStatusRequest;
+use near_jsonrpc_primitives::types::validator::{
+    RpcEstimatedValidatorsRequest, RpcValidatorsOrderedRequest,
+};
 use near_network::test_utils::wait_or_timeout;
 use near_o11y::testonly::init_test_logger;
 use near_primitives::account::{AccessKey, AccessKeyPermission};
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{BlockId, BlockReference, EpochId, SyncCheckpoint};
-use near_primitives::views::QueryRequest;
+use near_primitives::views::{BlockRangeField, QueryRequest};
 
 use near_jsonrpc_tests::{self as test_utils, test_with_client};
 
@@ -84,6 +91,122 @@ fn test_block_query() {
     });
 }
 
+/// Retrieve summaries of a range of blocks via json rpc
+#[test]
+fn test_block_range() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let genesis = client.block_by_id(BlockId::Height(0)).await.unwrap();
+        let response = client
+            .EXPERIMENTAL_block_range(RpcBlockRangeRequest {
+                from_height: 0,
+                to_height: 10,
+                fields: vec![BlockRangeField::Header, BlockRangeField::ChunkMask],
+            })
+            .await
+            .unwrap();
+        // The node doesn't produce blocks, the heights above the head are left out.
+        assert_eq!(response.blocks.len(), 1);
+        let summary = &response.blocks[0];
+        assert_eq!(summary.height, 0);
+        assert_eq!(summary.hash, genesis.header.hash);
+        let header = summary.header.as_ref().unwrap();
+        assert_eq!(header.prev_hash, genesis.header.prev_hash);
+        assert_eq!(header.timestamp_nanosec, genesis.header.timestamp_nanosec);
+        assert_eq!(summary.chunk_mask.as_ref(), Some(&genesis.header.chunk_mask));
+
+        let response = client
+            .EXPERIMENTAL_block_range(RpcBlockRangeRequest {
+                from_height: 0,
+                to_height: 0,
+                fields: vec![BlockRangeField::ChunkMask],
+            })
+            .await
+            .unwrap();
+        assert!(response.blocks[0].header.is_none());
+        assert!(response.blocks[0].chunk_mask.is_some());
+
+        let error = client
+            .EXPERIMENTAL_block_range(RpcBlockRangeRequest {
+                from_height: 5,
+                to_height: 0,
+                fields: vec![],
+            })
+            .await
+            .unwrap_err();
+        let error = serde_json::to_string(&error.data.unwrap()).unwrap();
+        assert!(error.starts_with("\"to_height 0 is lower than from_height 5"));
+
+        let error = client
+            .EXPERIMENTAL_block_range(RpcBlockRangeRequest {
+                from_height: 0,
+                to_height: 100_000,
+                fields: vec![],
+            })
+            .await
+            .unwrap_err();
+        let error = serde_json::to_string(&error.data.unwrap()).unwrap();
+        assert!(error.starts_with("\"The range of 100001 heights exceeds the limit"));
+    });
+}
+
+/// Proofs of the outcomes of an account are only served for bounded ranges
+/// and known light client heads
+#[test]
+fn test_light_client_account_outcomes_proof_errors() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let genesis = client.block_by_id(BlockId::Height(0)).await.unwrap();
+        let request =
+            |from_height, to_height, light_client_head| RpcLightClientAccountOutcomesProofRequest {
+                account_id: "test1".parse().unwrap(),
+                from_height,
+                to_height,
+                light_client_head,
+            };
+
+        let error = client
+            .EXPERIMENTAL_light_client_account_outcomes_proof(request(5, 0, genesis.header.hash))
+            .await
+            .unwrap_err();
+        let error = serde_json::to_string(&error.data.unwrap()).unwrap();
+        assert!(error.starts_with("\"to_height 0 is lower than from_height 5"));
+
+        let error = client
+            .EXPERIMENTAL_light_client_account_outcomes_proof(request(0, 1000, genesis.header.hash))
+            .await
+            .unwrap_err();
+        let error = serde_json::to_string(&error.data.unwrap()).unwrap();
+        assert!(error.starts_with("\"The range of 1001 heights exceeds the limit"));
+
+        let error = client
+            .EXPERIMENTAL_light_client_account_outcomes_proof(request(
+                0,
+                10,
+                CryptoHash::hash_bytes(b"head"),
+            ))
+            .await
+            .unwrap_err();
+        let error = serde_json::to_string(&error.data.unwrap()).unwrap();
+        assert!(error.starts_with("\"DB Not Found Error"));
+    });
+}
+
+/// Receipts the node has never seen are reported as unknown
+#[test]
+fn test_receipt_unknown() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let receipt_id = CryptoHash::hash_bytes(b"receipt");
+        let error = client
+            .receipt(RpcReceiptWithOutcomeRequest {
+                receipt_reference: ReceiptReference { receipt_id },
+            })
+            .await
+            .unwrap_err();
+        let data = error.data.unwrap();
+        assert_eq!(data["name"], "UNKNOWN_RECEIPT");
+        assert_eq!(data["info"]["receipt_id"], receipt_id.to_string());
+    });
+}
+
 /// Retrieve chunk via json rpc
 #[test]
 fn test_chunk_by_hash() {
@@ -127,6 +250,41 @@ fn test_chunk_invalid_shard_id() {
     });
 }
 
+/// Genesis chunks have no state witness, so the node must refuse to build one.
+#[test]
+fn test_chunk_state_witness_for_genesis_chunk() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let chunk = client.chunk(ChunkId::BlockShardId(BlockId::Height(0), 0u64)).await.unwrap();
+        let witness = client
+            .EXPERIMENTAL_chunk_state_witness(RpcChunkStateWitnessRequest {
+                chunk_id: chunk.header.chunk_hash,
+            })
+            .await;
+        match witness {
+            Ok(_) => panic!("should result in an error"),
+            Err(e) => {
+                let s = serde_json::to_string(&e.data.unwrap()).unwrap();
+                assert!(s.starts_with("\"State witness for the chunk is not available"));
+            }
+        }
+    });
+}
+
+/// The account has no transactions in the pool of a node nobody sent transactions to.
+#[test]
+fn test_tx_pool_status() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let status = client
+            .EXPERIMENTAL_tx_pool_status(RpcTxPoolStatusRequest {
+                account_id: Some("test1".parse().unwrap()),
+            })
+            .await
+            .unwrap();
+        assert!(status.status_view.transactions.is_empty());
+        assert!(status.status_view.shards.iter().all(|shard| shard.num_transactions == 0));
+    });
+}
+
 /// Connect to json rpc and query account info with soft-deprecated query API.
 #[test]
 fn test_query_by_path_account() {
@@ -459,6 +617,29 @@ fn test_validators_ordered() {
     });
 }
 
+#[test]
+fn test_estimated_validators() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let response = client
+            .EXPERIMENTAL_estimated_validators(RpcEstimatedValidatorsRequest { epoch_offset: 2 })
+            .await
+            .unwrap();
+        assert_eq!(
+            response
+                .validators
+                .validators
+                .into_iter()
+                .map(|v| v.take_account_id())
+                .collect::<Vec<_>>(),
+            vec!["test1"]
+        );
+        client
+            .EXPERIMENTAL_estimated_validators(RpcEstimatedValidatorsRequest { epoch_offset: 3 })
+            .await
+            .unwrap_err();
+    });
+}
+
 /// Retrieve genesis config via JSON RPC.
 /// WARNING: Be mindful about changing genesis structure as it is part of the public protocol!
 #[test]
@@ -546,6 +727,47 @@ fn test_invalid_methods() {
     });
 }
 
+#[test]
+fn test_batch() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let request = |id: u64, method: &str| {
+            let params = json!({"block_id": 0});
+            json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+        };
+        // Every request of a batch gets its own response, failed or not.
+        let batch = json!([request(1, "block"), request(2, "no_such_method"), json!(3)]);
+        let response = post_json(&client, &batch).await;
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], json!(1));
+        assert_eq!(responses[0]["result"]["header"]["height"], json!(0));
+        assert_eq!(responses[1]["id"], json!(2));
+        assert_eq!(responses[1]["error"]["code"], json!(-32601));
+        assert_eq!(responses[2]["error"]["code"], json!(-32700));
+
+        // Empty and too large batches get a single error.
+        let response = post_json(&client, &json!([])).await;
+        assert_eq!(response["error"]["code"], json!(-32700));
+        let batch: Vec<_> = (0..101).map(|id| request(id, "block")).collect();
+        let response = post_json(&client, &json!(batch)).await;
+        assert_eq!(response["error"]["code"], json!(-32700));
+    });
+}
+
+async fn post_json(
+    client: &near_jsonrpc::client::JsonRpcClient,
+    json: &serde_json::Value,
+) -> serde_json::Value {
+    let mut response = client
+        .client
+        .post(&client.server_addr)
+        .insert_header(("Content-Type", "application/json"))
+        .send_json(json)
+        .await
+        .unwrap();
+    response.json().await.unwrap()
+}
+
 #[test]
 fn test_get_chunk_with_object_in_params() {
     test_with_client!(test_utils::NodeType::NonValidator, client, async move {
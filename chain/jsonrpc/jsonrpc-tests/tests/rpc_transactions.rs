@@ -7,13 +7,16 @@ use futures::{future, FutureExt, TryFutureExt};
 use near_actix_test_utils::run_actix;
 use near_crypto::{InMemorySigner, KeyType};
 use near_jsonrpc::client::new_client;
-use near_jsonrpc_primitives::types::transactions::{RpcTransactionStatusRequest, TransactionInfo};
+use near_jsonrpc_primitives::types::transactions::{
+    EstimatedTransaction, RpcEstimateTransactionRequest, RpcTransactionStatusRequest,
+    TransactionInfo,
+};
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_o11y::testonly::{init_integration_logger, init_test_logger};
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::serialize::to_base64;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::BlockReference;
+use near_primitives::types::{BlockId, BlockReference};
 use near_primitives::views::{FinalExecutionStatus, TxExecutionStatus};
 
 use near_jsonrpc_tests::{self as test_utils, test_with_client};
@@ -249,3 +252,39 @@ fn test_check_invalid_tx() {
         }
     });
 }
+
+#[test]
+fn test_estimate_tx() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::send_money(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer,
+            100,
+            hash(&[1]),
+        );
+        client
+            .EXPERIMENTAL_estimate_tx(RpcEstimateTransactionRequest {
+                transaction: EstimatedTransaction::Signed(tx.clone()),
+                block_id: None,
+            })
+            .await
+            .unwrap();
+        client
+            .EXPERIMENTAL_estimate_tx(RpcEstimateTransactionRequest {
+                transaction: EstimatedTransaction::Unsigned(tx.transaction.clone()),
+                block_id: Some(BlockId::Height(0)),
+            })
+            .await
+            .unwrap();
+        client
+            .EXPERIMENTAL_estimate_tx(RpcEstimateTransactionRequest {
+                transaction: EstimatedTransaction::Signed(tx),
+                block_id: Some(BlockId::Hash(hash(&[1]))),
+            })
+            .await
+            .unwrap_err();
+    });
+}
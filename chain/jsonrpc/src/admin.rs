@@ -0,0 +1,222 @@
+//! Admin endpoints of the JSON RPC server, under `/admin`.
+//!
+//! They are only served when `admin_config` is set, to clients connecting from the loopback
+//! interface and sending the token read from `admin_config.token_file` in an
+//! `Authorization: Bearer <token>` header.
+//!
+//! - `GET /admin/validator_key` returns the account and public key of the validator key, whether
+//!   it is attached and the largest heights signed at with it.
+//! - `POST /admin/validator_key/detach` detaches the validator key: the node keeps following the
+//!   chain but stops producing blocks and chunks and signing approvals and chunk endorsements. The
+//!   response is sent once the chunk validations in progress are done, with the final heights.
+//! - `POST /admin/validator_key/attach` attaches it back. The request body may hold the
+//!   `signed_heights` returned by the detach on another node, nothing is signed at or below them.
+//!
+//! Failing over from a primary to a backup node holding the same validator key is done by
+//! detaching the key on the primary, waiting for the response, and only then attaching it on the
+//! backup with the heights of the response, so that both nodes never sign at the same time nor
+//! sign conflicting messages for the same height.
+
+use crate::JsonRpcHandler;
+use actix_web::http::header;
+use actix_web::{web, Error as HttpError, HttpRequest, HttpResponse};
+use near_client::{GetValidatorKeyStatus, SetValidatorKeyAttached};
+use near_client_primitives::types::{
+    ValidatorKeyError, ValidatorKeyStatus, ValidatorSignedHeights,
+};
+use near_o11y::WithSpanContextExt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcAdminConfig {
+    /// File holding the token of the admin endpoints, relative to the home directory. Should only
+    /// be readable by the user running the node.
+    pub token_file: PathBuf,
+}
+
+/// Reads the token of the admin endpoints. They are disabled if it can't be read or is empty.
+pub(crate) fn read_admin_token(config: &RpcAdminConfig) -> Option<String> {
+    let token_file = &config.token_file;
+    match std::fs::read_to_string(token_file) {
+        Ok(token) if !token.trim().is_empty() => Some(token.trim().to_string()),
+        Ok(_) => {
+            tracing::error!(
+                target: "jsonrpc",
+                ?token_file,
+                "Admin token file is empty, admin endpoints are disabled"
+            );
+            None
+        }
+        Err(err) => {
+            tracing::error!(
+                target: "jsonrpc",
+                ?token_file,
+                ?err,
+                "Failed to read admin token file, admin endpoints are disabled"
+            );
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum AuthError {
+    NotLocal,
+    InvalidToken,
+}
+
+fn authorize(
+    peer_ip: Option<IpAddr>,
+    authorization: Option<&str>,
+    admin_token: &str,
+) -> Result<(), AuthError> {
+    if !peer_ip.is_some_and(|ip| ip.is_loopback()) {
+        return Err(AuthError::NotLocal);
+    }
+    match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(token) if constant_time_eq(token.as_bytes(), admin_token.as_bytes()) => Ok(()),
+        _ => Err(AuthError::InvalidToken),
+    }
+}
+
+/// Compares without returning at the first difference, so that the time taken doesn't tell how
+/// much of the token was guessed.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Returns the response to send instead of serving the request, if it isn't allowed.
+fn check_request(req: &HttpRequest, handler: &JsonRpcHandler) -> Option<HttpResponse> {
+    let Some(admin_token) = &handler.admin_token else {
+        return Some(HttpResponse::NotFound().finish());
+    };
+    let authorization =
+        req.headers().get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+    match authorize(req.peer_addr().map(|addr| addr.ip()), authorization, admin_token) {
+        Ok(()) => None,
+        Err(err) => {
+            tracing::warn!(
+                target: "jsonrpc",
+                peer_addr = ?req.peer_addr(),
+                path = req.path(),
+                ?err,
+                "Rejected admin request"
+            );
+            Some(match err {
+                AuthError::NotLocal => HttpResponse::Forbidden().finish(),
+                AuthError::InvalidToken => HttpResponse::Unauthorized()
+                    .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                    .finish(),
+            })
+        }
+    }
+}
+
+fn validator_key_response(
+    result: Result<Result<ValidatorKeyStatus, ValidatorKeyError>, actix::MailboxError>,
+) -> Result<HttpResponse, HttpError> {
+    match result {
+        Ok(Ok(status)) => Ok(HttpResponse::Ok().json(&status)),
+        Ok(Err(err)) => Ok(HttpResponse::Conflict().body(err.to_string())),
+        Err(_) => Ok(HttpResponse::ServiceUnavailable().finish()),
+    }
+}
+
+pub(crate) async fn validator_key_status_handler(
+    req: HttpRequest,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if let Some(response) = check_request(&req, &handler) {
+        return Ok(response);
+    }
+    validator_key_response(
+        handler.client_addr.send(GetValidatorKeyStatus {}.with_span_context()).await,
+    )
+}
+
+pub(crate) async fn detach_validator_key_handler(
+    req: HttpRequest,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    set_validator_key_attached(req, handler, false, Ok(None)).await
+}
+
+/// Body of the attach request.
+#[derive(serde::Deserialize, Debug)]
+struct AttachRequest {
+    signed_heights: Option<ValidatorSignedHeights>,
+}
+
+pub(crate) async fn attach_validator_key_handler(
+    req: HttpRequest,
+    handler: web::Data<JsonRpcHandler>,
+    body: web::Bytes,
+) -> Result<HttpResponse, HttpError> {
+    let signed_heights = if body.is_empty() {
+        Ok(None)
+    } else {
+        serde_json::from_slice::<AttachRequest>(&body).map(|request| request.signed_heights)
+    };
+    set_validator_key_attached(req, handler, true, signed_heights).await
+}
+
+async fn set_validator_key_attached(
+    req: HttpRequest,
+    handler: web::Data<JsonRpcHandler>,
+    attached: bool,
+    signed_heights: Result<Option<ValidatorSignedHeights>, serde_json::Error>,
+) -> Result<HttpResponse, HttpError> {
+    if let Some(response) = check_request(&req, &handler) {
+        return Ok(response);
+    }
+    let signed_heights = match signed_heights {
+        Ok(signed_heights) => signed_heights,
+        Err(err) => return Ok(HttpResponse::BadRequest().body(err.to_string())),
+    };
+    tracing::info!(
+        target: "jsonrpc",
+        peer_addr = ?req.peer_addr(),
+        attached,
+        ?signed_heights,
+        "Admin request to attach or detach the validator key"
+    );
+    validator_key_response(
+        handler
+            .client_addr
+            .send(SetValidatorKeyAttached { attached, signed_heights }.with_span_context())
+            .await,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorize() {
+        let local = Some("127.0.0.1".parse().unwrap());
+        let remote = Some("10.0.0.1".parse().unwrap());
+        let token = "secret";
+
+        assert_eq!(authorize(local, Some("Bearer secret"), token), Ok(()));
+        assert_eq!(authorize(Some("::1".parse().unwrap()), Some("Bearer secret"), token), Ok(()));
+        assert_eq!(authorize(remote, Some("Bearer secret"), token), Err(AuthError::NotLocal));
+        assert_eq!(authorize(None, Some("Bearer secret"), token), Err(AuthError::NotLocal));
+        assert_eq!(authorize(local, None, token), Err(AuthError::InvalidToken));
+        assert_eq!(authorize(local, Some("secret"), token), Err(AuthError::InvalidToken));
+        assert_eq!(authorize(local, Some("Bearer secre"), token), Err(AuthError::InvalidToken));
+        assert_eq!(authorize(local, Some("Bearer secret2"), token), Err(AuthError::InvalidToken));
+    }
+
+    #[test]
+    fn test_read_admin_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RpcAdminConfig { token_file: dir.path().join("admin_token") };
+        assert_eq!(read_admin_token(&config), None);
+        std::fs::write(&config.token_file, " \n").unwrap();
+        assert_eq!(read_admin_token(&config), None);
+        std::fs::write(&config.token_file, "secret\n").unwrap();
+        assert_eq!(read_admin_token(&config), Some("secret".to_string()));
+    }
+}
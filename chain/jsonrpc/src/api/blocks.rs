@@ -1,8 +1,10 @@
 use serde_json::Value;
 
-use near_client_primitives::types::GetBlockError;
+use near_client_primitives::types::{GetBlockError, GetBlockRangeError};
 use near_jsonrpc_primitives::errors::RpcParseError;
-use near_jsonrpc_primitives::types::blocks::{RpcBlockError, RpcBlockRequest};
+use near_jsonrpc_primitives::types::blocks::{
+    RpcBlockError, RpcBlockRangeError, RpcBlockRangeRequest, RpcBlockRequest,
+};
 use near_primitives::types::BlockReference;
 
 use super::{Params, RpcFrom, RpcRequest};
@@ -38,3 +40,36 @@ impl RpcFrom<GetBlockError> for RpcBlockError {
         }
     }
 }
+
+impl RpcRequest for RpcBlockRangeRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcBlockRangeError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetBlockRangeError> for RpcBlockRangeError {
+    fn rpc_from(error: GetBlockRangeError) -> Self {
+        match error {
+            GetBlockRangeError::InvalidRange { from_height, to_height } => {
+                Self::InvalidRange { from_height, to_height }
+            }
+            GetBlockRangeError::RangeTooLarge { num_blocks, max_num_blocks } => {
+                Self::RangeTooLarge { num_blocks, max_num_blocks }
+            }
+            GetBlockRangeError::IOError { error_message } => Self::InternalError { error_message },
+            GetBlockRangeError::Unreachable { ref error_message } => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcBlockRangeError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
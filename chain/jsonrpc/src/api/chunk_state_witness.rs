@@ -0,0 +1,53 @@
+use serde_json::Value;
+
+use near_client_primitives::types::{GetChunkStateWitness, GetChunkStateWitnessError};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::chunk_state_witness::{
+    RpcChunkStateWitnessError, RpcChunkStateWitnessRequest,
+};
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcChunkStateWitnessRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        // params can be:
+        // - {"chunk_id": chunk_id} (an object) or
+        // - [chunk_id]             (a one-element array with hash element).
+        Params::new(value).try_singleton(|chunk_id| Ok(Self { chunk_id })).unwrap_or_parse()
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcChunkStateWitnessError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<RpcChunkStateWitnessRequest> for GetChunkStateWitness {
+    fn rpc_from(request: RpcChunkStateWitnessRequest) -> Self {
+        Self { chunk_hash: request.chunk_id.into() }
+    }
+}
+
+impl RpcFrom<GetChunkStateWitnessError> for RpcChunkStateWitnessError {
+    fn rpc_from(error: GetChunkStateWitnessError) -> Self {
+        match error {
+            GetChunkStateWitnessError::IOError { error_message } => {
+                Self::InternalError { error_message }
+            }
+            GetChunkStateWitnessError::UnknownChunk { chunk_hash } => {
+                Self::UnknownChunk { chunk_hash }
+            }
+            GetChunkStateWitnessError::WitnessUnavailable { error_message } => {
+                Self::WitnessUnavailable { error_message }
+            }
+            GetChunkStateWitnessError::Unreachable { ref error_message } => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcChunkStateWitnessError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
@@ -3,10 +3,14 @@ use std::sync::Arc;
 use serde_json::Value;
 
 use near_client_primitives::types::{
-    GetBlockProofError, GetExecutionOutcomeError, GetNextLightClientBlockError,
+    GetAccountOutcomesProof, GetAccountOutcomesProofError, GetBlockProofError,
+    GetChunkExecutionProof, GetChunkExecutionProofError, GetExecutionOutcomeError,
+    GetNextLightClientBlockError,
 };
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::light_client::{
+    RpcLightClientAccountOutcomesProofError, RpcLightClientAccountOutcomesProofRequest,
+    RpcLightClientChunkExecutionProofError, RpcLightClientChunkExecutionProofRequest,
     RpcLightClientExecutionProofRequest, RpcLightClientNextBlockError,
     RpcLightClientNextBlockRequest, RpcLightClientNextBlockResponse, RpcLightClientProofError,
 };
@@ -20,6 +24,12 @@ impl RpcRequest for RpcLightClientExecutionProofRequest {
     }
 }
 
+impl RpcRequest for RpcLightClientAccountOutcomesProofRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
 impl RpcRequest for RpcLightClientNextBlockRequest {
     fn parse(value: Value) -> Result<Self, RpcParseError> {
         Params::new(value)
@@ -28,6 +38,47 @@ impl RpcRequest for RpcLightClientNextBlockRequest {
     }
 }
 
+impl RpcRequest for RpcLightClientChunkExecutionProofRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::new(value).try_singleton(|chunk_id| Ok(Self { chunk_id })).unwrap_or_parse()
+    }
+}
+
+impl RpcFrom<RpcLightClientChunkExecutionProofRequest> for GetChunkExecutionProof {
+    fn rpc_from(request: RpcLightClientChunkExecutionProofRequest) -> Self {
+        Self { chunk_hash: request.chunk_id.into() }
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcLightClientChunkExecutionProofError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetChunkExecutionProofError> for RpcLightClientChunkExecutionProofError {
+    fn rpc_from(error: GetChunkExecutionProofError) -> Self {
+        match error {
+            GetChunkExecutionProofError::IOError { error_message } => {
+                Self::InternalError { error_message }
+            }
+            GetChunkExecutionProofError::UnknownChunk { chunk_hash } => {
+                Self::UnknownChunk { chunk_hash }
+            }
+            GetChunkExecutionProofError::ProofUnavailable { error_message } => {
+                Self::ProofUnavailable { error_message }
+            }
+            GetChunkExecutionProofError::Unreachable { ref error_message } => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcLightClientChunkExecutionProofError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
 impl RpcFrom<Option<Arc<LightClientBlockView>>> for RpcLightClientNextBlockResponse {
     fn rpc_from(light_client_block: Option<Arc<LightClientBlockView>>) -> Self {
         Self { light_client_block }
@@ -93,6 +144,55 @@ impl RpcFrom<GetBlockProofError> for RpcLightClientProofError {
     }
 }
 
+impl RpcFrom<RpcLightClientAccountOutcomesProofRequest> for GetAccountOutcomesProof {
+    fn rpc_from(request: RpcLightClientAccountOutcomesProofRequest) -> Self {
+        Self {
+            account_id: request.account_id,
+            from_height: request.from_height,
+            to_height: request.to_height,
+            head_block_hash: request.light_client_head,
+        }
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcLightClientAccountOutcomesProofError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetAccountOutcomesProofError> for RpcLightClientAccountOutcomesProofError {
+    fn rpc_from(error: GetAccountOutcomesProofError) -> Self {
+        match error {
+            GetAccountOutcomesProofError::UnknownBlock { error_message } => {
+                Self::UnknownBlock { error_message }
+            }
+            GetAccountOutcomesProofError::InvalidRange { from_height, to_height } => {
+                Self::InvalidRange { from_height, to_height }
+            }
+            GetAccountOutcomesProofError::RangeTooLarge { num_blocks, max_num_blocks } => {
+                Self::RangeTooLarge { num_blocks, max_num_blocks }
+            }
+            GetAccountOutcomesProofError::NotConfirmed { block_height } => {
+                Self::NotConfirmed { block_height }
+            }
+            GetAccountOutcomesProofError::UnavailableShard { shard_id } => {
+                Self::UnavailableShard { shard_id }
+            }
+            GetAccountOutcomesProofError::InternalError { error_message } => {
+                Self::InternalError { error_message }
+            }
+            GetAccountOutcomesProofError::Unreachable { ref error_message } => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcLightClientAccountOutcomesProofError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
 impl RpcFrom<actix::MailboxError> for RpcLightClientNextBlockError {
     fn rpc_from(error: actix::MailboxError) -> Self {
         Self::InternalError { error_message: error.to_string() }
@@ -1,9 +1,12 @@
 use serde_json::Value;
 
-use near_client_primitives::types::GetMaintenanceWindowsError;
+use near_client_primitives::types::{
+    GetMaintenanceWindowsError, GetScheduledMaintenanceWindowError,
+};
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::maintenance::{
-    RpcMaintenanceWindowsError, RpcMaintenanceWindowsRequest,
+    RpcMaintenanceWindowsError, RpcMaintenanceWindowsRequest, RpcScheduledMaintenanceWindowError,
+    RpcScheduledMaintenanceWindowRequest,
 };
 
 use super::{Params, RpcFrom, RpcRequest};
@@ -33,3 +36,33 @@ impl RpcFrom<GetMaintenanceWindowsError> for RpcMaintenanceWindowsError {
         }
     }
 }
+
+impl RpcRequest for RpcScheduledMaintenanceWindowRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcScheduledMaintenanceWindowError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetScheduledMaintenanceWindowError> for RpcScheduledMaintenanceWindowError {
+    fn rpc_from(error: GetScheduledMaintenanceWindowError) -> Self {
+        match error {
+            GetScheduledMaintenanceWindowError::NoValidator => Self::NoValidator,
+            GetScheduledMaintenanceWindowError::NoMaintenanceDeclared => {
+                Self::NoMaintenanceDeclared
+            }
+            GetScheduledMaintenanceWindowError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            GetScheduledMaintenanceWindowError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
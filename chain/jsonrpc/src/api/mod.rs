@@ -5,6 +5,7 @@ use near_jsonrpc_primitives::errors::{RpcError, ServerError};
 
 mod blocks;
 mod changes;
+mod chunk_state_witness;
 mod chunks;
 mod client_config;
 mod config;
@@ -17,7 +18,9 @@ mod receipts;
 mod sandbox;
 mod split_storage;
 mod status;
+mod subscriptions;
 mod transactions;
+mod tx_pool;
 mod validator;
 
 pub(crate) trait RpcRequest: Sized {
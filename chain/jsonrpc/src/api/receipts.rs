@@ -2,7 +2,7 @@ use super::{Params, RpcFrom, RpcRequest};
 use near_client_primitives::types::{GetReceipt, GetReceiptError};
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::receipts::{
-    ReceiptReference, RpcReceiptError, RpcReceiptRequest,
+    ReceiptReference, RpcReceiptError, RpcReceiptRequest, RpcReceiptWithOutcomeRequest,
 };
 use serde_json::Value;
 
@@ -12,6 +12,12 @@ impl RpcRequest for RpcReceiptRequest {
     }
 }
 
+impl RpcRequest for RpcReceiptWithOutcomeRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Ok(Self { receipt_reference: Params::parse(value)? })
+    }
+}
+
 impl RpcFrom<actix::MailboxError> for RpcReceiptError {
     fn rpc_from(error: actix::MailboxError) -> Self {
         Self::InternalError { error_message: error.to_string() }
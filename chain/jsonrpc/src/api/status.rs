@@ -49,6 +49,11 @@ impl RpcFrom<near_client_primitives::debug::DebugStatusResponse>
                     x,
                 )
             }
+            near_client_primitives::debug::DebugStatusResponse::ChunkEndorsementStatus(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::ChunkEndorsementStatus(
+                    x,
+                )
+            }
         }
     }
 }
@@ -0,0 +1,23 @@
+use serde_json::Value;
+
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::subscriptions::{RpcSubscribeRequest, RpcUnsubscribeRequest};
+
+use super::{Params, RpcRequest};
+
+impl RpcRequest for RpcSubscribeRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcUnsubscribeRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        // params can be:
+        // - {"subscription_id": subscription_id} (an object) or
+        // - [subscription_id]                    (a one-element array).
+        Params::new(value)
+            .try_singleton(|subscription_id| Ok(Self { subscription_id }))
+            .unwrap_or_parse()
+    }
+}
@@ -3,7 +3,8 @@ use serde_json::Value;
 use near_client_primitives::types::TxStatusError;
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::transactions::{
-    RpcSendTransactionRequest, RpcTransactionError, RpcTransactionStatusRequest, TransactionInfo,
+    RpcEstimateTransactionRequest, RpcSendTransactionRequest, RpcTransactionError,
+    RpcTransactionStatusRequest, TransactionInfo,
 };
 use near_primitives::borsh::BorshDeserialize;
 use near_primitives::transaction::SignedTransaction;
@@ -52,6 +53,12 @@ impl RpcRequest for RpcTransactionStatusRequest {
     }
 }
 
+impl RpcRequest for RpcEstimateTransactionRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
 impl RpcFrom<actix::MailboxError> for RpcTransactionError {
     fn rpc_from(error: actix::MailboxError) -> Self {
         Self::InternalError { debug_info: error.to_string() }
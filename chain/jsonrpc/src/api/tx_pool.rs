@@ -0,0 +1,49 @@
+use serde_json::Value;
+
+use near_client_primitives::types::{GetTxPoolStatus, GetTxPoolStatusError};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::tx_pool::{RpcTxPoolStatusError, RpcTxPoolStatusRequest};
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcTxPoolStatusRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        // params can be:
+        // - omitted or null               (status of the pools only),
+        // - {"account_id": account_id} (an object) or
+        // - [account_id]               (a one-element array with account id element).
+        if value.is_null() {
+            return Ok(Self { account_id: None });
+        }
+        Params::new(value).try_singleton(|account_id| Ok(Self { account_id })).unwrap_or_parse()
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcTxPoolStatusError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<RpcTxPoolStatusRequest> for GetTxPoolStatus {
+    fn rpc_from(request: RpcTxPoolStatusRequest) -> Self {
+        Self { account_id: request.account_id }
+    }
+}
+
+impl RpcFrom<GetTxPoolStatusError> for RpcTxPoolStatusError {
+    fn rpc_from(error: GetTxPoolStatusError) -> Self {
+        match error {
+            GetTxPoolStatusError::IOError { error_message } => {
+                Self::InternalError { error_message }
+            }
+            GetTxPoolStatusError::Unreachable { ref error_message } => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcTxPoolStatusError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
@@ -3,7 +3,8 @@ use serde_json::Value;
 use near_client_primitives::types::GetValidatorInfoError;
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::validator::{
-    RpcValidatorError, RpcValidatorRequest, RpcValidatorsOrderedRequest,
+    RpcEstimatedValidatorsRequest, RpcValidatorError, RpcValidatorRequest,
+    RpcValidatorsOrderedRequest,
 };
 use near_primitives::types::EpochReference;
 
@@ -27,6 +28,12 @@ impl RpcRequest for RpcValidatorsOrderedRequest {
     }
 }
 
+impl RpcRequest for RpcEstimatedValidatorsRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
 impl RpcFrom<actix::MailboxError> for RpcValidatorError {
     fn rpc_from(error: actix::MailboxError) -> Self {
         Self::InternalError { error_message: error.to_string() }
@@ -38,6 +45,9 @@ impl RpcFrom<GetValidatorInfoError> for RpcValidatorError {
         match error {
             GetValidatorInfoError::UnknownEpoch => Self::UnknownEpoch,
             GetValidatorInfoError::ValidatorInfoUnavailable => Self::ValidatorInfoUnavailable,
+            GetValidatorInfoError::EpochOffsetTooLarge { epoch_offset, max_epoch_offset } => {
+                Self::EpochOffsetTooLarge { epoch_offset, max_epoch_offset }
+            }
             GetValidatorInfoError::IOError(error_message) => Self::InternalError { error_message },
             GetValidatorInfoError::Unreachable(ref error_message) => {
                 tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
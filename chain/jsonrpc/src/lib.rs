@@ -11,22 +11,26 @@ use futures::Future;
 use futures::FutureExt;
 use near_chain_configs::GenesisConfig;
 use near_client::{
-    ClientActor, DebugStatus, GetBlock, GetBlockProof, GetChunk, GetClientConfig,
-    GetExecutionOutcome, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
-    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, ProcessTxRequest,
-    ProcessTxResponse, Query, Status, TxStatus, ViewClientActor,
+    ClientActor, DebugStatus, EstimateTransaction, GetAccountOutcomesProof, GetBlock,
+    GetBlockProof, GetBlockRange, GetChunk, GetChunkExecutionProof, GetChunkStateWitness,
+    GetClientConfig, GetEstimatedValidators, GetExecutionOutcome, GetGasPrice,
+    GetMaintenanceWindows, GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt,
+    GetReceiptWithOutcome, GetScheduledMaintenanceWindow, GetStateChanges, GetStateChangesInBlock,
+    GetTxPoolStatus, GetValidatorInfo, GetValidatorOrdered, ProcessTxRequest, ProcessTxResponse,
+    Query, Status, TxStatus, ViewClientActor,
 };
 use near_client_primitives::types::GetSplitStorageInfo;
 pub use near_jsonrpc_client as client;
-use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::errors::{RpcError, RpcErrorKind, RpcRequestValidationErrorKind};
 use near_jsonrpc_primitives::message::{Message, Request};
+use near_jsonrpc_primitives::types::compaction::{CompactionHandler, CompactionRequest};
 use near_jsonrpc_primitives::types::config::RpcProtocolConfigResponse;
 use near_jsonrpc_primitives::types::entity_debug::{EntityDebugHandler, EntityQuery};
 use near_jsonrpc_primitives::types::query::RpcQueryRequest;
 use near_jsonrpc_primitives::types::split_storage::{
     RpcSplitStorageInfoRequest, RpcSplitStorageInfoResponse,
 };
+use near_jsonrpc_primitives::types::subscriptions::RpcSubscriptionError;
 use near_jsonrpc_primitives::types::transactions::{
     RpcSendTransactionRequest, RpcTransactionResponse,
 };
@@ -35,6 +39,7 @@ use near_network::PeerManagerActor;
 use near_o11y::metrics::{prometheus, Encoder, TextEncoder};
 use near_o11y::{WithSpanContext, WithSpanContextExt};
 use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, BlockHeight};
 use near_primitives::views::{QueryRequest, TxExecutionStatus};
@@ -45,8 +50,20 @@ use std::time::{Duration, Instant};
 use tokio::time::{sleep, timeout};
 use tracing::{error, info};
 
+mod admin;
 mod api;
 mod metrics;
+mod rate_limiter;
+mod subscriptions;
+
+pub use admin::RpcAdminConfig;
+use rate_limiter::RateLimiter;
+pub use rate_limiter::{RpcRateLimitsConfig, TokenBucketConfig};
+pub use subscriptions::RpcSubscriptionsConfig;
+use subscriptions::{ws_handler, SubscriptionHub};
+
+/// Parameters of slow requests are only logged up to that many bytes.
+const MAX_LOGGED_PARAMS_LEN: usize = 1024;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
@@ -67,11 +84,18 @@ impl Default for RpcPollingConfig {
 pub struct RpcLimitsConfig {
     /// Maximum byte size of the json payload.
     pub json_payload_max_size: usize,
+    /// Maximum number of requests in a JSON RPC batch.
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+}
+
+fn default_max_batch_size() -> usize {
+    100
 }
 
 impl Default for RpcLimitsConfig {
     fn default() -> Self {
-        Self { json_payload_max_size: 10 * 1024 * 1024 }
+        Self { json_payload_max_size: 10 * 1024 * 1024, max_batch_size: default_max_batch_size() }
     }
 }
 
@@ -79,6 +103,10 @@ fn default_enable_debug_rpc() -> bool {
     false
 }
 
+fn default_slow_request_threshold() -> Option<Duration> {
+    Some(Duration::from_secs(1))
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct RpcConfig {
     pub addr: tcp::ListenerAddr,
@@ -88,10 +116,22 @@ pub struct RpcConfig {
     pub polling_config: RpcPollingConfig,
     #[serde(default)]
     pub limits_config: RpcLimitsConfig,
+    #[serde(default)]
+    pub subscriptions_config: RpcSubscriptionsConfig,
+    #[serde(default)]
+    pub rate_limits_config: RpcRateLimitsConfig,
+    // Requests taking longer than that are logged, with their parameters. If not set, no requests
+    // are logged.
+    #[serde(default = "default_slow_request_threshold")]
+    pub slow_request_threshold: Option<Duration>,
     // If true, enable some debug RPC endpoints (like one to get the latest block).
     // We disable it by default, as some of those endpoints might be quite CPU heavy.
     #[serde(default = "default_enable_debug_rpc")]
     pub enable_debug_rpc: bool,
+    // If provided, the admin endpoints under `/admin` are served to local clients presenting the
+    // token, see the `admin` module.
+    #[serde(default)]
+    pub admin_config: Option<RpcAdminConfig>,
     // For node developers only: if specified, the HTML files used to serve the debug pages will
     // be read from this directory, instead of the contents compiled into the binary. This allows
     // for quick iterative development.
@@ -106,7 +146,11 @@ impl Default for RpcConfig {
             cors_allowed_origins: vec!["*".to_owned()],
             polling_config: Default::default(),
             limits_config: Default::default(),
+            subscriptions_config: Default::default(),
+            rate_limits_config: Default::default(),
+            slow_request_threshold: default_slow_request_threshold(),
             enable_debug_rpc: false,
+            admin_config: None,
             experimental_debug_pages_src_path: None,
         }
     }
@@ -228,36 +272,103 @@ struct JsonRpcHandler {
     enable_debug_rpc: bool,
     debug_pages_src_path: Option<PathBuf>,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
+    compaction_handler: Arc<dyn CompactionHandler>,
+    max_batch_size: usize,
+    rate_limiter: Arc<RateLimiter>,
+    slow_request_threshold: Option<Duration>,
+    /// Token of the admin endpoints, which are disabled if it's not set.
+    admin_token: Option<String>,
 }
 
 impl JsonRpcHandler {
     pub async fn process(&self, message: Message) -> Result<Message, HttpError> {
+        match message {
+            Message::Batch(messages) => Ok(self.process_batch(messages).await),
+            message => Ok(self.process_message(message).await),
+        }
+    }
+
+    async fn process_message(&self, message: Message) -> Message {
         let id = message.id();
         match message {
-            Message::Request(request) => {
-                Ok(Message::response(id, self.process_request(request).await))
-            }
-            _ => Ok(Message::error(RpcError::parse_error(
+            Message::Request(request) => Message::response(id, self.process_request(request).await),
+            _ => Message::error(RpcError::parse_error(
                 "JSON RPC Request format was expected".to_owned(),
-            ))),
+            )),
+        }
+    }
+
+    /// Processes the messages of a batch concurrently. Each message gets its
+    /// own response, in order, so that a failed request doesn't affect the
+    /// others. An empty or too large batch gets a single error response.
+    async fn process_batch(&self, messages: Vec<Message>) -> Message {
+        if messages.is_empty() {
+            return Message::error(RpcError::parse_error(
+                "JSON RPC batch should not be empty".to_owned(),
+            ));
+        }
+        if messages.len() > self.max_batch_size {
+            metrics::RPC_REJECTED_BATCHES_TOTAL.inc();
+            return Message::error(RpcError::parse_error(format!(
+                "JSON RPC batch of {} messages exceeds the limit of {}",
+                messages.len(),
+                self.max_batch_size
+            )));
         }
+        metrics::RPC_BATCH_SIZE.observe(messages.len() as f64);
+        // Batches in a batch are rejected like any other message which isn't a request.
+        let responses = futures::future::join_all(
+            messages.into_iter().map(|message| self.process_message(message)),
+        )
+        .await;
+        Message::Batch(responses)
     }
 
     // `process_request` increments affected metrics but the request processing is done by
     // `process_request_internal`.
     async fn process_request(&self, request: Request) -> Result<Value, RpcError> {
         let timer = Instant::now();
+        let params = request.params.to_string();
         let (metrics_name, response) = self.process_request_internal(request).await;
+        let elapsed = timer.elapsed();
 
         metrics::HTTP_RPC_REQUEST_COUNT.with_label_values(&[&metrics_name]).inc();
         metrics::RPC_PROCESSING_TIME
             .with_label_values(&[&metrics_name])
-            .observe(timer.elapsed().as_secs_f64());
+            .observe(elapsed.as_secs_f64());
+        metrics::RPC_REQUEST_SIZE_BYTES
+            .with_label_values(&[&metrics_name])
+            .observe(params.len() as f64);
 
-        if let Err(err) = &response {
-            metrics::RPC_ERROR_COUNT
-                .with_label_values(&[&metrics_name, &err.code.to_string()])
-                .inc();
+        match &response {
+            Ok(result) => {
+                metrics::RPC_RESPONSE_SIZE_BYTES
+                    .with_label_values(&[&metrics_name])
+                    .observe(json_size(result) as f64);
+            }
+            Err(err) => {
+                metrics::RPC_ERROR_COUNT
+                    .with_label_values(&[&metrics_name, &err.code.to_string()])
+                    .inc();
+                metrics::RPC_ERRORS_BY_TYPE_TOTAL
+                    .with_label_values(&[&metrics_name, error_type(err)])
+                    .inc();
+            }
+        }
+
+        if self.slow_request_threshold.is_some_and(|threshold| elapsed >= threshold) {
+            let mut params_len = params.len().min(MAX_LOGGED_PARAMS_LEN);
+            while !params.is_char_boundary(params_len) {
+                params_len -= 1;
+            }
+            tracing::warn!(
+                target: "jsonrpc",
+                method = %metrics_name,
+                ?elapsed,
+                params = &params[..params_len],
+                is_error = response.is_err(),
+                "Slow JSON RPC request",
+            );
         }
 
         response
@@ -271,6 +382,12 @@ impl JsonRpcHandler {
         request: Request,
     ) -> (String, Result<Value, RpcError>) {
         let method_name = request.method.to_string();
+        if !self.rate_limiter.is_method_allowed(&method_name) {
+            return (
+                "UNSUPPORTED_METHOD".to_string(),
+                Err(RpcError::method_not_found(method_name)),
+            );
+        }
         let request = match self.process_adversarial_request_internal(request).await {
             Ok(response) => return (method_name, response),
             Err(request) => request,
@@ -339,6 +456,9 @@ impl JsonRpcHandler {
                 process_method_call(request, |params| self.next_light_client_block(params)).await
             }
             "network_info" => process_method_call(request, |_params: ()| self.network_info()).await,
+            "receipt" => {
+                process_method_call(request, |params| self.receipt_with_outcome(params)).await
+            }
             "send_tx" => process_method_call(request, |params| self.send_tx(params)).await,
             "status" => process_method_call(request, |_params: ()| self.status()).await,
             "tx" => {
@@ -354,12 +474,27 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_changes_in_block" => {
                 process_method_call(request, |params| self.changes_in_block(params)).await
             }
+            "EXPERIMENTAL_chunk_state_witness" => {
+                process_method_call(request, |params| self.chunk_state_witness(params)).await
+            }
             "EXPERIMENTAL_genesis_config" => {
                 process_method_call(request, |_params: ()| async {
                     Result::<_, std::convert::Infallible>::Ok(&self.genesis_config)
                 })
                 .await
             }
+            "EXPERIMENTAL_light_client_account_outcomes_proof" => {
+                process_method_call(request, |params| {
+                    self.light_client_account_outcomes_proof(params)
+                })
+                .await
+            }
+            "EXPERIMENTAL_light_client_chunk_execution_proof" => {
+                process_method_call(request, |params| {
+                    self.light_client_chunk_execution_proof(params)
+                })
+                .await
+            }
             "EXPERIMENTAL_light_client_proof" => {
                 process_method_call(request, |params| {
                     self.light_client_execution_outcome_proof(params)
@@ -375,15 +510,34 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_tx_status" => {
                 process_method_call(request, |params| self.tx_status_common(params, true)).await
             }
+            "EXPERIMENTAL_estimate_tx" => {
+                process_method_call(request, |params| self.estimate_tx(params)).await
+            }
             "EXPERIMENTAL_validators_ordered" => {
                 process_method_call(request, |params| self.validators_ordered(params)).await
             }
+            "EXPERIMENTAL_block_range" => {
+                process_method_call(request, |params| self.block_range(params)).await
+            }
+            "EXPERIMENTAL_estimated_validators" => {
+                process_method_call(request, |params| self.estimated_validators(params)).await
+            }
             "EXPERIMENTAL_maintenance_windows" => {
                 process_method_call(request, |params| self.maintenance_windows(params)).await
             }
+            "EXPERIMENTAL_scheduled_maintenance_window" => {
+                process_method_call(request, |params| self.scheduled_maintenance_window(params))
+                    .await
+            }
             "EXPERIMENTAL_split_storage_info" => {
                 process_method_call(request, |params| self.split_storage_info(params)).await
             }
+            "EXPERIMENTAL_tx_pool_status" => {
+                process_method_call(request, |params| self.tx_pool_status(params)).await
+            }
+            "subscribe" | "unsubscribe" => {
+                Err(RpcSubscriptionError::WebsocketOnly { method: request.method }.into())
+            }
             #[cfg(feature = "sandbox")]
             "sandbox_patch_state" => {
                 process_method_call(request, |params| self.sandbox_patch_state(params)).await
@@ -423,6 +577,16 @@ impl JsonRpcHandler {
             "adv_switch_to_height" => self.adv_switch_to_height(request.params).await,
             "adv_get_saved_blocks" => self.adv_get_saved_blocks(request.params).await,
             "adv_check_store" => self.adv_check_store(request.params).await,
+            "adv_produce_invalid_witnesses" => {
+                self.adv_produce_invalid_witnesses(request.params).await
+            }
+            "adv_withhold_chunk_endorsements" => {
+                self.adv_withhold_chunk_endorsements(request.params).await
+            }
+            "adv_double_endorse_chunks" => self.adv_double_endorse_chunks(request.params).await,
+            "adv_delay_witness_distribution" => {
+                self.adv_delay_witness_distribution(request.params).await
+            }
             _ => return Err(request),
         })
     }
@@ -808,6 +972,24 @@ impl JsonRpcHandler {
         }
     }
 
+    pub async fn debug_chunk_endorsement_status(
+        &self,
+        chunk_hash: ChunkHash,
+    ) -> Result<
+        Option<near_jsonrpc_primitives::types::status::RpcDebugStatusResponse>,
+        near_jsonrpc_primitives::types::status::RpcStatusError,
+    > {
+        if self.enable_debug_rpc {
+            let debug_status =
+                self.client_send(DebugStatus::ChunkEndorsementStatus(chunk_hash)).await?.rpc_into();
+            Ok(Some(near_jsonrpc_primitives::types::status::RpcDebugStatusResponse {
+                status_response: debug_status,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub async fn protocol_config(
         &self,
         request_data: near_jsonrpc_primitives::types::config::RpcProtocolConfigRequest,
@@ -833,6 +1015,32 @@ impl JsonRpcHandler {
         Ok(query_response.rpc_into())
     }
 
+    /// Executes the transaction on the state after the requested block without
+    /// committing anything, to estimate its cost and outcome.
+    async fn estimate_tx(
+        &self,
+        request_data: near_jsonrpc_primitives::types::transactions::RpcEstimateTransactionRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::transactions::RpcEstimateTransactionResponse,
+        near_jsonrpc_primitives::types::query::RpcQueryError,
+    > {
+        let (transaction, verify_signature) = request_data.transaction.into_signed_tx();
+        let block_reference = request_data.block_id.map_or_else(
+            near_primitives::types::BlockReference::latest,
+            near_primitives::types::BlockReference::BlockId,
+        );
+        let estimate = self
+            .view_client_send(EstimateTransaction {
+                block_reference,
+                transaction,
+                verify_signature,
+            })
+            .await?;
+        Ok(near_jsonrpc_primitives::types::transactions::RpcEstimateTransactionResponse {
+            estimate,
+        })
+    }
+
     async fn tx_status_common(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcTransactionStatusRequest,
@@ -858,6 +1066,23 @@ impl JsonRpcHandler {
         Ok(near_jsonrpc_primitives::types::blocks::RpcBlockResponse { block_view })
     }
 
+    async fn block_range(
+        &self,
+        request_data: near_jsonrpc_primitives::types::blocks::RpcBlockRangeRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::blocks::RpcBlockRangeResponse,
+        near_jsonrpc_primitives::types::blocks::RpcBlockRangeError,
+    > {
+        let near_jsonrpc_primitives::types::blocks::RpcBlockRangeRequest {
+            from_height,
+            to_height,
+            fields,
+        } = request_data;
+        let blocks =
+            self.view_client_send(GetBlockRange { from_height, to_height, fields }).await?;
+        Ok(near_jsonrpc_primitives::types::blocks::RpcBlockRangeResponse { blocks })
+    }
+
     async fn chunk(
         &self,
         request_data: near_jsonrpc_primitives::types::chunks::RpcChunkRequest,
@@ -870,6 +1095,20 @@ impl JsonRpcHandler {
         Ok(near_jsonrpc_primitives::types::chunks::RpcChunkResponse { chunk_view })
     }
 
+    async fn chunk_state_witness(
+        &self,
+        request_data: near_jsonrpc_primitives::types::chunk_state_witness::RpcChunkStateWitnessRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::chunk_state_witness::RpcChunkStateWitnessResponse,
+        near_jsonrpc_primitives::types::chunk_state_witness::RpcChunkStateWitnessError,
+    > {
+        let witness_view =
+            self.view_client_send(GetChunkStateWitness::rpc_from(request_data)).await?;
+        Ok(near_jsonrpc_primitives::types::chunk_state_witness::RpcChunkStateWitnessResponse {
+            witness_view,
+        })
+    }
+
     async fn receipt(
         &self,
         request_data: near_jsonrpc_primitives::types::receipts::RpcReceiptRequest,
@@ -892,6 +1131,23 @@ impl JsonRpcHandler {
         }
     }
 
+    async fn receipt_with_outcome(
+        &self,
+        request_data: near_jsonrpc_primitives::types::receipts::RpcReceiptWithOutcomeRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::receipts::RpcReceiptWithOutcomeResponse,
+        near_jsonrpc_primitives::types::receipts::RpcReceiptError,
+    > {
+        let receipt_with_outcome = self
+            .view_client_send(GetReceiptWithOutcome {
+                receipt_id: request_data.receipt_reference.receipt_id,
+            })
+            .await?;
+        Ok(near_jsonrpc_primitives::types::receipts::RpcReceiptWithOutcomeResponse {
+            receipt_with_outcome,
+        })
+    }
+
     async fn changes_in_block(
         &self,
         request: near_jsonrpc_primitives::types::changes::RpcStateChangesInBlockRequest,
@@ -935,6 +1191,19 @@ impl JsonRpcHandler {
         })
     }
 
+    async fn light_client_chunk_execution_proof(
+        &self,
+        request_data: near_jsonrpc_primitives::types::light_client::RpcLightClientChunkExecutionProofRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::light_client::RpcLightClientChunkExecutionProofResponse,
+        near_jsonrpc_primitives::types::light_client::RpcLightClientChunkExecutionProofError,
+    > {
+        let proof = self.view_client_send(GetChunkExecutionProof::rpc_from(request_data)).await?;
+        Ok(near_jsonrpc_primitives::types::light_client::RpcLightClientChunkExecutionProofResponse {
+            proof,
+        })
+    }
+
     async fn next_light_client_block(
         &self,
         request: near_jsonrpc_primitives::types::light_client::RpcLightClientNextBlockRequest,
@@ -978,6 +1247,19 @@ impl JsonRpcHandler {
         })
     }
 
+    async fn light_client_account_outcomes_proof(
+        &self,
+        request: near_jsonrpc_primitives::types::light_client::RpcLightClientAccountOutcomesProofRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::light_client::RpcLightClientAccountOutcomesProofResponse,
+        near_jsonrpc_primitives::types::light_client::RpcLightClientAccountOutcomesProofError,
+    > {
+        let blocks = self.view_client_send(GetAccountOutcomesProof::rpc_from(request)).await?;
+        Ok(near_jsonrpc_primitives::types::light_client::RpcLightClientAccountOutcomesProofResponse {
+            blocks,
+        })
+    }
+
     async fn network_info(
         &self,
     ) -> Result<
@@ -1029,6 +1311,23 @@ impl JsonRpcHandler {
         Ok(validators)
     }
 
+    /// Returns the validators of the current, next or next next epoch. The
+    /// validators of the next next epoch are estimated from the proposals,
+    /// kickouts and stake so far in the current epoch.
+    async fn estimated_validators(
+        &self,
+        request: near_jsonrpc_primitives::types::validator::RpcEstimatedValidatorsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcEstimatedValidatorsResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let near_jsonrpc_primitives::types::validator::RpcEstimatedValidatorsRequest {
+            epoch_offset,
+        } = request;
+        let validators = self.view_client_send(GetEstimatedValidators { epoch_offset }).await?;
+        Ok(near_jsonrpc_primitives::types::validator::RpcEstimatedValidatorsResponse { validators })
+    }
+
     /// If experimental_debug_pages_src_path config is set, reads the html file from that
     /// directory. Otherwise, returns None.
     fn read_html_file_override(&self, html_file: &'static str) -> Option<String> {
@@ -1057,6 +1356,22 @@ impl JsonRpcHandler {
         Ok(windows.iter().map(|r| (r.start, r.end)).collect())
     }
 
+    /// Returns the nearest window in the current or the next epoch in which the account has no
+    /// block or chunk to produce and no chunk to validate for the given number of blocks.
+    async fn scheduled_maintenance_window(
+        &self,
+        request: near_jsonrpc_primitives::types::maintenance::RpcScheduledMaintenanceWindowRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::maintenance::RpcScheduledMaintenanceWindowResponse,
+        near_jsonrpc_primitives::types::maintenance::RpcScheduledMaintenanceWindowError,
+    > {
+        let near_jsonrpc_primitives::types::maintenance::RpcScheduledMaintenanceWindowRequest {
+            account_id,
+            num_blocks,
+        } = request;
+        self.view_client_send(GetScheduledMaintenanceWindow { account_id, num_blocks }).await
+    }
+
     async fn client_config(
         &self,
     ) -> Result<
@@ -1067,6 +1382,17 @@ impl JsonRpcHandler {
         Ok(near_jsonrpc_primitives::types::client_config::RpcClientConfigResponse { client_config })
     }
 
+    async fn tx_pool_status(
+        &self,
+        request_data: near_jsonrpc_primitives::types::tx_pool::RpcTxPoolStatusRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::tx_pool::RpcTxPoolStatusResponse,
+        near_jsonrpc_primitives::types::tx_pool::RpcTxPoolStatusError,
+    > {
+        let status_view = self.client_send(GetTxPoolStatus::rpc_from(request_data)).await?;
+        Ok(near_jsonrpc_primitives::types::tx_pool::RpcTxPoolStatusResponse { status_view })
+    }
+
     pub async fn split_storage_info(
         &self,
         _request_data: near_jsonrpc_primitives::types::split_storage::RpcSplitStorageInfoRequest,
@@ -1286,13 +1612,105 @@ impl JsonRpcHandler {
             _ => Err(RpcError::server_error::<String>(None)),
         }
     }
+
+    async fn adv_produce_invalid_witnesses(&self, params: Value) -> Result<Value, RpcError> {
+        let (value,) = crate::api::Params::parse(params)?;
+        self.adv_send_to_client(near_client::NetworkAdversarialMessage::AdvProduceInvalidWitnesses(
+            value,
+        ))
+        .await
+    }
+
+    async fn adv_withhold_chunk_endorsements(&self, params: Value) -> Result<Value, RpcError> {
+        let (value,) = crate::api::Params::parse(params)?;
+        self.adv_send_to_client(
+            near_client::NetworkAdversarialMessage::AdvWithholdChunkEndorsements(value),
+        )
+        .await
+    }
+
+    async fn adv_double_endorse_chunks(&self, params: Value) -> Result<Value, RpcError> {
+        let (value,) = crate::api::Params::parse(params)?;
+        self.adv_send_to_client(near_client::NetworkAdversarialMessage::AdvDoubleEndorseChunks(
+            value,
+        ))
+        .await
+    }
+
+    async fn adv_delay_witness_distribution(&self, params: Value) -> Result<Value, RpcError> {
+        let (delay_ms,) = crate::api::Params::parse(params)?;
+        self.adv_send_to_client(
+            near_client::NetworkAdversarialMessage::AdvDelayWitnessDistribution(delay_ms),
+        )
+        .await
+    }
+
+    /// Sends the message to the client, returning once it has been handled so
+    /// that the behaviour has changed when the request returns.
+    async fn adv_send_to_client(
+        &self,
+        msg: near_client::NetworkAdversarialMessage,
+    ) -> Result<Value, RpcError> {
+        self.client_addr
+            .send(msg.with_span_context())
+            .await
+            .map_err(|err| RpcError::server_error(Some(err.to_string())))?;
+        Ok(Value::String(String::new()))
+    }
+}
+
+/// Returns the size of the value serialized to JSON, without allocating it.
+fn json_size(value: &Value) -> usize {
+    struct ByteCounter(usize);
+
+    impl std::io::Write for ByteCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).map_or(0, |()| counter.0)
+}
+
+/// Name of the error, as in the `name` field of the error returned to the client.
+fn error_type(error: &RpcError) -> &str {
+    match &error.error_struct {
+        Some(RpcErrorKind::RequestValidationError(
+            RpcRequestValidationErrorKind::MethodNotFound { .. },
+        )) => "METHOD_NOT_FOUND",
+        Some(RpcErrorKind::RequestValidationError(RpcRequestValidationErrorKind::ParseError {
+            ..
+        })) => "PARSE_ERROR",
+        Some(RpcErrorKind::HandlerError(error)) => {
+            error["name"].as_str().unwrap_or("HANDLER_ERROR")
+        }
+        Some(RpcErrorKind::InternalError(_)) => "INTERNAL_ERROR",
+        None => "UNKNOWN_ERROR",
+    }
 }
 
 fn rpc_handler(
+    req: HttpRequest,
     message: web::Json<Message>,
     handler: web::Data<JsonRpcHandler>,
 ) -> impl Future<Output = Result<HttpResponse, HttpError>> {
     let response = async move {
+        let limiter = &handler.rate_limiter;
+        let ip = rate_limiter::client_ip(&req, limiter.use_forwarded_ip());
+        let methods = rate_limiter::request_methods(&message);
+        if let Err(retry_after) = limiter.check(ip, &methods, Instant::now()) {
+            let retry_after_secs = rate_limiter::retry_after_secs(retry_after);
+            let error = rate_limiter::too_many_requests_error(retry_after_secs);
+            return Ok(HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after_secs))
+                .json(&Message::error(error)));
+        }
         let message = handler.process(message.0).await?;
         Ok(HttpResponse::Ok().json(&message))
     };
@@ -1342,6 +1760,28 @@ async fn handle_entity_debug(
     }
 }
 
+async fn start_compaction_handler(
+    req: web::Json<CompactionRequest>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.enable_debug_rpc {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+    match handler.compaction_handler.start(req.0) {
+        Ok(status) => Ok(HttpResponse::Ok().json(&status)),
+        Err(err) => Ok(HttpResponse::BadRequest().body(format!("{:?}", err))),
+    }
+}
+
+async fn compaction_status_handler(
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.enable_debug_rpc {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+    Ok(HttpResponse::Ok().json(&handler.compaction_handler.status()))
+}
+
 async fn debug_block_status_handler(
     path: web::Path<u64>,
     handler: web::Data<JsonRpcHandler>,
@@ -1353,6 +1793,20 @@ async fn debug_block_status_handler(
     }
 }
 
+async fn debug_chunk_endorsement_status_handler(
+    path: web::Path<String>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    let Ok(chunk_hash) = path.parse::<CryptoHash>() else {
+        return Ok(HttpResponse::BadRequest().finish());
+    };
+    match handler.debug_chunk_endorsement_status(ChunkHash(chunk_hash)).await {
+        Ok(Some(value)) => Ok(HttpResponse::Ok().json(&value)),
+        Ok(None) => Ok(HttpResponse::MethodNotAllowed().finish()),
+        Err(_) => Ok(HttpResponse::ServiceUnavailable().finish()),
+    }
+}
+
 fn health_handler(
     handler: web::Data<JsonRpcHandler>,
 ) -> impl Future<Output = Result<HttpResponse, HttpError>> {
@@ -1483,6 +1937,7 @@ pub fn start_http(
     view_client_addr: Addr<ViewClientActor>,
     peer_manager_addr: Option<Addr<PeerManagerActor>>,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
+    compaction_handler: Arc<dyn CompactionHandler>,
 ) -> Vec<(&'static str, actix_web::dev::ServerHandle)> {
     let RpcConfig {
         addr,
@@ -1490,13 +1945,26 @@ pub fn start_http(
         cors_allowed_origins,
         polling_config,
         limits_config,
+        subscriptions_config,
+        rate_limits_config,
+        slow_request_threshold,
         enable_debug_rpc,
+        admin_config,
         experimental_debug_pages_src_path: debug_pages_src_path,
     } = config;
+    let admin_token = admin_config.as_ref().and_then(admin::read_admin_token);
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr.to_string());
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
     info!(target:"network", "Starting http server at {}", addr);
     let mut servers = Vec::new();
+    let subscription_hub =
+        SubscriptionHub::new(subscriptions_config, limits_config.json_payload_max_size);
+    subscription_hub.start_poller(
+        client_addr.clone(),
+        view_client_addr.clone(),
+        polling_config.polling_interval,
+    );
+    let rate_limiter = Arc::new(RateLimiter::new(rate_limits_config));
     let listener = HttpServer::new(move || {
         App::new()
             .wrap(get_cors(&cors_allowed_origins))
@@ -1509,10 +1977,17 @@ pub fn start_http(
                 enable_debug_rpc,
                 debug_pages_src_path: debug_pages_src_path.clone().map(Into::into),
                 entity_debug_handler: entity_debug_handler.clone(),
+                compaction_handler: compaction_handler.clone(),
+                max_batch_size: limits_config.max_batch_size,
+                rate_limiter: rate_limiter.clone(),
+                slow_request_threshold,
+                admin_token: admin_token.clone(),
             }))
+            .app_data(web::Data::new(subscription_hub.clone()))
             .app_data(web::JsonConfig::default().limit(limits_config.json_payload_max_size))
             .wrap(middleware::Logger::default())
             .service(web::resource("/").route(web::post().to(rpc_handler)))
+            .service(web::resource("/ws").route(web::get().to(ws_handler)))
             .service(
                 web::resource("/status")
                     .route(web::get().to(status_handler))
@@ -1526,14 +2001,35 @@ pub fn start_http(
             .service(web::resource("/network_info").route(web::get().to(network_info_handler)))
             .service(web::resource("/metrics").route(web::get().to(prometheus_handler)))
             .service(web::resource("/debug/api/entity").route(web::post().to(handle_entity_debug)))
+            .service(
+                web::resource("/debug/api/compaction")
+                    .route(web::get().to(compaction_status_handler))
+                    .route(web::post().to(start_compaction_handler)),
+            )
             .service(web::resource("/debug/api/{api}").route(web::get().to(debug_handler)))
             .service(
                 web::resource("/debug/api/block_status/{starting_height}")
                     .route(web::get().to(debug_block_status_handler)),
             )
+            .service(
+                web::resource("/debug/api/chunk_endorsement_status/{chunk_hash}")
+                    .route(web::get().to(debug_chunk_endorsement_status_handler)),
+            )
             .service(
                 web::resource("/debug/client_config").route(web::get().to(client_config_handler)),
             )
+            .service(
+                web::resource("/admin/validator_key")
+                    .route(web::get().to(admin::validator_key_status_handler)),
+            )
+            .service(
+                web::resource("/admin/validator_key/detach")
+                    .route(web::post().to(admin::detach_validator_key_handler)),
+            )
+            .service(
+                web::resource("/admin/validator_key/attach")
+                    .route(web::post().to(admin::attach_validator_key_handler)),
+            )
             .service(debug_html)
             .service(display_debug_html)
     });
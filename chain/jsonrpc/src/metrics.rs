@@ -1,4 +1,6 @@
-use near_o11y::metrics::{exponential_buckets, HistogramVec, IntCounter, IntCounterVec};
+use near_o11y::metrics::{
+    exponential_buckets, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+};
 use once_cell::sync::Lazy;
 
 pub static RPC_PROCESSING_TIME: Lazy<HistogramVec> = Lazy::new(|| {
@@ -55,3 +57,66 @@ pub static RPC_UNREACHABLE_ERROR_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static WEBSOCKET_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_gauge(
+        "near_rpc_websocket_connections",
+        "Number of open websocket connections",
+    )
+    .unwrap()
+});
+pub static WEBSOCKET_LAGGED_CONNECTIONS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter(
+        "near_rpc_websocket_lagged_connections_total",
+        "Total count of websocket connections closed for falling behind the subscription events",
+    )
+    .unwrap()
+});
+pub static RPC_BATCH_SIZE: Lazy<Histogram> = Lazy::new(|| {
+    near_o11y::metrics::try_create_histogram_with_buckets(
+        "near_rpc_batch_size",
+        "Number of messages in the JSON RPC batches received",
+        exponential_buckets(1.0, 2.0, 12).unwrap(),
+    )
+    .unwrap()
+});
+pub static RPC_REJECTED_BATCHES_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter(
+        "near_rpc_rejected_batches_total",
+        "Total count of JSON RPC batches rejected for exceeding the maximum batch size",
+    )
+    .unwrap()
+});
+pub static RPC_RATE_LIMITER_DECISIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter_vec(
+        "near_rpc_rate_limiter_decisions_total",
+        "Total count of JSON RPC requests by method and decision of the rate limiter",
+        &["method", "decision"],
+    )
+    .unwrap()
+});
+pub static RPC_REQUEST_SIZE_BYTES: Lazy<HistogramVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_histogram_vec(
+        "near_rpc_request_size_bytes",
+        "Size of the JSON parameters of the rpc queries, by method",
+        &["method"],
+        Some(exponential_buckets(64.0, 4.0, 10).unwrap()),
+    )
+    .unwrap()
+});
+pub static RPC_RESPONSE_SIZE_BYTES: Lazy<HistogramVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_histogram_vec(
+        "near_rpc_response_size_bytes",
+        "Size of the JSON results of the successful rpc queries, by method",
+        &["method"],
+        Some(exponential_buckets(64.0, 4.0, 10).unwrap()),
+    )
+    .unwrap()
+});
+pub static RPC_ERRORS_BY_TYPE_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    near_o11y::metrics::try_create_int_counter_vec(
+        "near_rpc_errors_by_type_total",
+        "Total count of errors returned by rpc queries, by method and error name",
+        &["method", "error_type"],
+    )
+    .unwrap()
+});
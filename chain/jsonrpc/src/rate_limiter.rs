@@ -0,0 +1,372 @@
+//! Rate limiting of the requests of the JSON RPC server.
+//!
+//! Requests are limited with token buckets: a bucket holds up to `burst` tokens and is refilled
+//! with `requests_per_second` tokens per second. Every request of an HTTP message, including every
+//! request of a batch, takes a token from the bucket of the client IP and from the bucket of its
+//! method, when they are configured. IPv6 clients are grouped by /64 network, as a single host
+//! usually gets a whole one. A message finding a bucket without enough tokens is rejected as a
+//! whole, without taking tokens from any bucket, with `429 Too Many Requests` and a `Retry-After`
+//! header telling when the bucket will have enough tokens again.
+
+use crate::metrics;
+use actix_web::HttpRequest;
+use lru::LruCache;
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::message::Message;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Number of clients whose buckets are kept. The buckets of the least recently seen clients are
+/// dropped first.
+const MAX_TRACKED_IPS: usize = 10_000;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TokenBucketConfig {
+    /// Rate at which the bucket is refilled.
+    pub requests_per_second: f64,
+    /// Maximum number of requests in a burst, i.e. the size of the bucket. Batches of more
+    /// requests than that are always rejected.
+    pub burst: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct RpcRateLimitsConfig {
+    /// Limit of the requests of every client IP, across all methods.
+    pub per_ip: Option<TokenBucketConfig>,
+    /// Limits of the requests of the methods, across all clients.
+    pub per_method: BTreeMap<String, TokenBucketConfig>,
+    /// If not empty, only these methods are served.
+    pub allowed_methods: Vec<String>,
+    /// Methods which are not served.
+    pub denied_methods: Vec<String>,
+    /// Whether to identify the clients by the `Forwarded` or `X-Forwarded-For` header rather than
+    /// by the address of the connection. Only enable it behind a proxy which sets the header, as
+    /// clients can set it to anything otherwise.
+    pub use_forwarded_ip: bool,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: &TokenBucketConfig, now: Instant) -> Self {
+        Self { tokens: config.burst as f64, updated: now }
+    }
+
+    fn refill(&mut self, config: &TokenBucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.requests_per_second).min(config.burst as f64);
+        self.updated = now;
+    }
+
+    /// Checks that the bucket has `num_tokens` tokens, or returns how long it takes until it has
+    /// enough of them.
+    fn check(
+        &mut self,
+        config: &TokenBucketConfig,
+        num_tokens: usize,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        self.refill(config, now);
+        let num_tokens = num_tokens as f64;
+        if self.tokens >= num_tokens {
+            return Ok(());
+        }
+        let missing = num_tokens.min(config.burst as f64) - self.tokens;
+        Err(Duration::try_from_secs_f64(missing / config.requests_per_second)
+            .unwrap_or(Duration::MAX))
+    }
+
+    /// Takes `num_tokens` tokens from the bucket, or returns how long it takes until the bucket
+    /// has enough of them.
+    fn take(
+        &mut self,
+        config: &TokenBucketConfig,
+        num_tokens: usize,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        self.check(config, num_tokens, now)?;
+        self.tokens -= num_tokens as f64;
+        Ok(())
+    }
+}
+
+/// Limits shared by all the workers of a server.
+pub(crate) struct RateLimiter {
+    config: RpcRateLimitsConfig,
+    ip_buckets: Mutex<LruCache<IpAddr, TokenBucket>>,
+    method_buckets: HashMap<String, Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RpcRateLimitsConfig) -> Self {
+        let now = Instant::now();
+        let method_buckets = config
+            .per_method
+            .iter()
+            .map(|(method, bucket_config)| {
+                (method.clone(), Mutex::new(TokenBucket::new(bucket_config, now)))
+            })
+            .collect();
+        Self { config, ip_buckets: Mutex::new(LruCache::new(MAX_TRACKED_IPS)), method_buckets }
+    }
+
+    pub fn use_forwarded_ip(&self) -> bool {
+        self.config.use_forwarded_ip
+    }
+
+    pub fn is_method_allowed(&self, method: &str) -> bool {
+        let allowed = (self.config.allowed_methods.is_empty()
+            || self.config.allowed_methods.iter().any(|allowed| allowed == method))
+            && !self.config.denied_methods.iter().any(|denied| denied == method);
+        if !allowed {
+            metrics::RPC_RATE_LIMITER_DECISIONS_TOTAL
+                .with_label_values(&[self.metrics_label(method), "denied"])
+                .inc();
+        }
+        allowed
+    }
+
+    /// Takes the tokens of the requests of a message, given by their methods, from the buckets
+    /// of the client and of the methods. Returns how long the client should wait before retrying
+    /// if any of the buckets doesn't have enough tokens.
+    pub fn check(
+        &self,
+        ip: Option<IpAddr>,
+        methods: &[&str],
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let result = self.take_tokens(ip, methods, now);
+        let decision = match result {
+            Ok(()) => "allowed",
+            Err((decision, _)) => decision,
+        };
+        for method in methods {
+            metrics::RPC_RATE_LIMITER_DECISIONS_TOTAL
+                .with_label_values(&[self.metrics_label(method), decision])
+                .inc();
+        }
+        result.map_err(|(_, retry_after)| retry_after)
+    }
+
+    /// Takes the tokens only if all the buckets have enough of them, so that a rejected message
+    /// doesn't use up the tokens of the buckets which would have allowed it. The bucket of the
+    /// client is locked first, then the buckets of the methods in the order of their names.
+    fn take_tokens(
+        &self,
+        ip: Option<IpAddr>,
+        methods: &[&str],
+        now: Instant,
+    ) -> Result<(), (&'static str, Duration)> {
+        let mut ip_buckets = self.ip_buckets.lock().unwrap();
+        let ip_bucket = match (&self.config.per_ip, ip) {
+            (Some(config), Some(ip)) => {
+                let key = client_key(ip);
+                if !ip_buckets.contains(&key) {
+                    ip_buckets.put(key, TokenBucket::new(config, now));
+                }
+                let bucket = ip_buckets.get_mut(&key).unwrap();
+                bucket
+                    .check(config, methods.len(), now)
+                    .map_err(|retry_after| ("limited_ip", retry_after))?;
+                Some((config, bucket))
+            }
+            _ => None,
+        };
+
+        let mut num_requests = BTreeMap::<&str, usize>::new();
+        for method in methods {
+            if self.method_buckets.contains_key(*method) {
+                *num_requests.entry(*method).or_default() += 1;
+            }
+        }
+        let mut method_buckets = num_requests
+            .into_iter()
+            .map(|(method, num_requests)| {
+                let config = &self.config.per_method[method];
+                (config, num_requests, self.method_buckets[method].lock().unwrap())
+            })
+            .collect::<Vec<_>>();
+        let mut retry_after = None;
+        for (config, num_requests, bucket) in &mut method_buckets {
+            if let Err(wait) = bucket.check(config, *num_requests, now) {
+                retry_after = retry_after.max(Some(wait));
+            }
+        }
+        if let Some(retry_after) = retry_after {
+            return Err(("limited_method", retry_after));
+        }
+
+        if let Some((config, bucket)) = ip_bucket {
+            bucket.take(config, methods.len(), now).expect("checked above");
+        }
+        for (config, num_requests, bucket) in &mut method_buckets {
+            bucket.take(config, *num_requests, now).expect("checked above");
+        }
+        Ok(())
+    }
+
+    /// Methods are reported by name only if they are configured, to bound the number of series
+    /// since the names come from the clients.
+    fn metrics_label<'a>(&self, method: &'a str) -> &'a str {
+        let configured = self.config.per_method.contains_key(method)
+            || self.config.allowed_methods.iter().any(|allowed| allowed == method)
+            || self.config.denied_methods.iter().any(|denied| denied == method);
+        if configured {
+            method
+        } else {
+            "other"
+        }
+    }
+}
+
+/// Returns the methods of the requests of a message.
+pub(crate) fn request_methods(message: &Message) -> Vec<&str> {
+    match message {
+        Message::Request(request) => vec![request.method.as_str()],
+        Message::Batch(messages) => messages.iter().flat_map(request_methods).collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns the number of seconds to wait before retrying, rounded up so that the client doesn't
+/// retry before the tokens are available.
+pub(crate) fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs().saturating_add(u64::from(retry_after.subsec_nanos() > 0))
+}
+
+/// Returns the error replied to the requests rejected by the limiter.
+pub(crate) fn too_many_requests_error(retry_after_secs: u64) -> RpcError {
+    RpcError::new(
+        -32_000,
+        "Too many requests".to_owned(),
+        Some(json!({ "retry_after_secs": retry_after_secs })),
+    )
+}
+
+/// Returns the key of the bucket of a client: its IPv4 address, or the /64 network of its IPv6
+/// address.
+fn client_key(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !u128::from(u64::MAX))),
+        },
+    }
+}
+
+pub(crate) fn client_ip(req: &HttpRequest, use_forwarded_ip: bool) -> Option<IpAddr> {
+    if !use_forwarded_ip {
+        return req.peer_addr().map(|addr| addr.ip());
+    }
+    let connection_info = req.connection_info();
+    let addr = connection_info.realip_remote_addr()?;
+    addr.parse::<IpAddr>().ok().or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket_config(requests_per_second: f64, burst: u32) -> TokenBucketConfig {
+        TokenBucketConfig { requests_per_second, burst }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let config = bucket_config(2.0, 4);
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(&config, now);
+        assert_eq!(bucket.take(&config, 3, now), Ok(()));
+        assert_eq!(bucket.take(&config, 2, now), Err(Duration::from_millis(500)));
+        assert_eq!(bucket.take(&config, 1, now), Ok(()));
+        let now = now + Duration::from_secs(1);
+        assert_eq!(bucket.take(&config, 2, now), Ok(()));
+        // The bucket never holds more than `burst` tokens, so larger batches are never allowed.
+        let now = now + Duration::from_secs(60);
+        assert_eq!(bucket.take(&config, 5, now), Err(Duration::ZERO));
+        assert_eq!(bucket.take(&config, 4, now), Ok(()));
+
+        let config = bucket_config(0.0, 1);
+        let mut bucket = TokenBucket::new(&config, now);
+        assert_eq!(bucket.take(&config, 1, now), Ok(()));
+        assert_eq!(bucket.take(&config, 1, now), Err(Duration::MAX));
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(RpcRateLimitsConfig {
+            per_ip: Some(bucket_config(1.0, 3)),
+            per_method: [("query".to_string(), bucket_config(1.0, 2))].into_iter().collect(),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let alice = Some("10.0.0.1".parse().unwrap());
+        let bob = Some("10.0.0.2".parse().unwrap());
+
+        assert_eq!(limiter.check(alice, &["block", "block", "block"], now), Ok(()));
+        assert_eq!(limiter.check(alice, &["block"], now), Err(Duration::from_secs(1)));
+        // Other clients have their own bucket, but share the buckets of the methods.
+        assert_eq!(limiter.check(bob, &["query", "query"], now), Ok(()));
+        assert_eq!(limiter.check(bob, &["query"], now), Err(Duration::from_secs(1)));
+        // The rejected message didn't take a token from the bucket of the client.
+        assert_eq!(limiter.check(bob, &["block"], now), Ok(()));
+        assert_eq!(limiter.check(bob, &["block"], now), Err(Duration::from_secs(1)));
+        // Clients without an IP are only limited per method.
+        assert_eq!(limiter.check(None, &["block", "block", "block", "block"], now), Ok(()));
+        assert_eq!(limiter.check(None, &["query"], now), Err(Duration::from_secs(1)));
+
+        let now = now + Duration::from_secs(1);
+        assert_eq!(limiter.check(alice, &["query"], now), Ok(()));
+    }
+
+    #[test]
+    fn test_rate_limiter_ip_buckets() {
+        let limiter = RateLimiter::new(RpcRateLimitsConfig {
+            per_ip: Some(bucket_config(1.0, 1)),
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        // IPv6 clients of the same /64 network share a bucket.
+        assert_eq!(limiter.check(Some("2001:db8::1".parse().unwrap()), &["block"], now), Ok(()));
+        let same_network = Some("2001:db8::ffff:1".parse().unwrap());
+        assert_eq!(limiter.check(same_network, &["block"], now), Err(Duration::from_secs(1)));
+        let other_network = Some("2001:db8:0:1::1".parse().unwrap());
+        assert_eq!(limiter.check(other_network, &["block"], now), Ok(()));
+
+        // IPv4-mapped addresses share the bucket of the IPv4 address.
+        assert_eq!(limiter.check(Some("10.0.0.1".parse().unwrap()), &["block"], now), Ok(()));
+        let mapped = Some("::ffff:10.0.0.1".parse().unwrap());
+        assert_eq!(limiter.check(mapped, &["block"], now), Err(Duration::from_secs(1)));
+
+        // Only the buckets of the most recently seen clients are kept.
+        for i in 0..MAX_TRACKED_IPS as u32 + 10 {
+            let ip = IpAddr::V4(std::net::Ipv4Addr::from(i));
+            limiter.check(Some(ip), &["block"], now).unwrap();
+        }
+        assert_eq!(limiter.ip_buckets.lock().unwrap().len(), MAX_TRACKED_IPS);
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let limiter = RateLimiter::new(RpcRateLimitsConfig::default());
+        assert!(limiter.is_method_allowed("block"));
+
+        let limiter = RateLimiter::new(RpcRateLimitsConfig {
+            allowed_methods: vec!["block".to_string(), "query".to_string()],
+            denied_methods: vec!["query".to_string()],
+            ..Default::default()
+        });
+        assert!(limiter.is_method_allowed("block"));
+        assert!(!limiter.is_method_allowed("query"));
+        assert!(!limiter.is_method_allowed("chunk"));
+    }
+}
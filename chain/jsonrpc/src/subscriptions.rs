@@ -0,0 +1,489 @@
+//! Websocket pub/sub interface of the JSON RPC server.
+//!
+//! A websocket connection to `/ws` accepts the same requests as the HTTP endpoint, plus
+//! `subscribe` and `unsubscribe`. The events of the subscriptions are sent as `subscription`
+//! notifications.
+//!
+//! A single poller per server follows the head of the chain and broadcasts the events to all the
+//! connections, so that subscribers don't need to poll the node themselves. A connection falling
+//! more than `max_pending_events` events behind is closed, instead of buffering its events without
+//! bound.
+//!
+//! The requests of a connection go through the same rate limits as the HTTP requests, with the
+//! IP the connection was opened from.
+
+use crate::api::RpcRequest;
+use crate::rate_limiter::{self, RateLimiter};
+use crate::{metrics, serialize_response, JsonRpcHandler};
+use actix::Addr;
+use actix_http::ws::{self, CloseCode, CloseReason, Frame};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{web, Error as HttpError, HttpRequest, HttpResponse};
+use bytes::{Bytes, BytesMut};
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use near_client::{
+    ClientActor, DebugStatus, GetBlock, GetStateChangesWithCauseInBlock, ViewClientActor,
+};
+use near_client_primitives::debug::DebugStatusResponse;
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::message::{self, Message, Request};
+use near_jsonrpc_primitives::types::subscriptions::{
+    ChunkEndorsementsView, RpcSubscribeRequest, RpcSubscribeResponse, RpcSubscriptionError,
+    RpcSubscriptionEvent, RpcSubscriptionKind, RpcSubscriptionNotification, RpcUnsubscribeRequest,
+    RpcUnsubscribeResponse, SubscriptionId,
+};
+use near_o11y::WithSpanContextExt;
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{BlockHeight, BlockId, BlockReference};
+use near_primitives::views::BlockView;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Maximum number of blocks the poller walks back from a new head to report the heads it
+/// skipped since the previous poll.
+const MAX_SKIPPED_HEADS: usize = 16;
+
+/// Number of encoded frames buffered for a connection before its socket accepts them.
+const OUTGOING_FRAMES_BUFFER: usize = 16;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
+pub struct RpcSubscriptionsConfig {
+    /// Maximum number of websocket connections open at the same time.
+    pub max_connections: usize,
+    /// Maximum number of subscriptions per websocket connection.
+    pub max_subscriptions_per_connection: usize,
+    /// Maximum number of events a websocket connection can fall behind before it is closed.
+    pub max_pending_events: usize,
+}
+
+impl Default for RpcSubscriptionsConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 100,
+            max_subscriptions_per_connection: 16,
+            max_pending_events: 1024,
+        }
+    }
+}
+
+/// State shared by the websocket connections of a server.
+#[derive(Clone)]
+pub(crate) struct SubscriptionHub {
+    config: RpcSubscriptionsConfig,
+    /// Maximum size of the frames received from a connection.
+    max_frame_size: usize,
+    events: broadcast::Sender<Arc<RpcSubscriptionEvent>>,
+    num_connections: Arc<AtomicUsize>,
+    /// Endorsements are requested from the client only while someone subscribes to them.
+    num_endorsement_subscriptions: Arc<AtomicUsize>,
+    /// Same for the state changes, requested from the view client.
+    num_state_changes_subscriptions: Arc<AtomicUsize>,
+}
+
+impl SubscriptionHub {
+    pub fn new(config: RpcSubscriptionsConfig, max_frame_size: usize) -> Self {
+        let (events, _) = broadcast::channel(config.max_pending_events.max(1));
+        Self {
+            config,
+            max_frame_size,
+            events,
+            num_connections: Default::default(),
+            num_endorsement_subscriptions: Default::default(),
+            num_state_changes_subscriptions: Default::default(),
+        }
+    }
+
+    /// Returns the number of subscriptions of the kind, for the kinds whose events are only
+    /// fetched while someone subscribes to them.
+    fn num_subscriptions(&self, kind: &RpcSubscriptionKind) -> Option<&AtomicUsize> {
+        match kind {
+            RpcSubscriptionKind::ExperimentalChunkEndorsements { .. } => {
+                Some(&self.num_endorsement_subscriptions)
+            }
+            RpcSubscriptionKind::ExperimentalStateChanges { .. } => {
+                Some(&self.num_state_changes_subscriptions)
+            }
+            RpcSubscriptionKind::NewHeads | RpcSubscriptionKind::Chunks { .. } => None,
+        }
+    }
+
+    /// Starts the poller broadcasting the events to the connections.
+    pub fn start_poller(
+        &self,
+        client_addr: Addr<ClientActor>,
+        view_client_addr: Addr<ViewClientActor>,
+        polling_interval: Duration,
+    ) {
+        tokio::spawn(self.clone().poll_events(client_addr, view_client_addr, polling_interval));
+    }
+
+    async fn poll_events(
+        self,
+        client_addr: Addr<ClientActor>,
+        view_client_addr: Addr<ViewClientActor>,
+        polling_interval: Duration,
+    ) {
+        let mut last_head: Option<(CryptoHash, BlockHeight)> = None;
+        let mut interval = tokio::time::interval(polling_interval);
+        loop {
+            interval.tick().await;
+            if self.events.receiver_count() == 0 {
+                last_head = None;
+                continue;
+            }
+            let Some(head) = fetch_block(&view_client_addr, BlockReference::latest()).await else {
+                continue;
+            };
+            if last_head.map_or(false, |(hash, _)| hash == head.header.hash) {
+                continue;
+            }
+            let head_hash_and_height = (head.header.hash, head.header.height);
+            let mut new_heads = vec![head];
+            if let Some((last_hash, last_height)) = last_head {
+                while new_heads.len() < MAX_SKIPPED_HEADS {
+                    let header = &new_heads.last().unwrap().header;
+                    if header.prev_hash == last_hash || header.height <= last_height + 1 {
+                        break;
+                    }
+                    let prev_block_reference = BlockId::Hash(header.prev_hash).into();
+                    match fetch_block(&view_client_addr, prev_block_reference).await {
+                        Some(block) => new_heads.push(block),
+                        None => break,
+                    }
+                }
+            }
+            last_head = Some(head_hash_and_height);
+            for block in new_heads.into_iter().rev() {
+                self.broadcast_block_events(&client_addr, &view_client_addr, block).await;
+            }
+        }
+    }
+
+    async fn broadcast_block_events(
+        &self,
+        client_addr: &Addr<ClientActor>,
+        view_client_addr: &Addr<ViewClientActor>,
+        block: BlockView,
+    ) {
+        let block_hash = block.header.hash;
+        let block_height = block.header.height;
+        let with_endorsements = self.num_endorsement_subscriptions.load(Ordering::Relaxed) > 0;
+        let with_state_changes = self.num_state_changes_subscriptions.load(Ordering::Relaxed) > 0;
+        // Sending fails only if there are no connections left, the events are then dropped.
+        let _ = self.events.send(Arc::new(RpcSubscriptionEvent::NewHead { header: block.header }));
+        for chunk in block.chunks {
+            if chunk.height_included != block_height {
+                continue;
+            }
+            let chunk_hash = ChunkHash(chunk.chunk_hash);
+            let _ = self.events.send(Arc::new(RpcSubscriptionEvent::Chunk {
+                block_hash,
+                block_height,
+                chunk,
+            }));
+            if !with_endorsements {
+                continue;
+            }
+            let response = client_addr
+                .send(DebugStatus::ChunkEndorsementStatus(chunk_hash).with_span_context())
+                .await;
+            let Ok(Ok(DebugStatusResponse::ChunkEndorsementStatus(status))) = response else {
+                continue;
+            };
+            let endorsements = ChunkEndorsementsView {
+                chunk_hash: status.chunk_hash,
+                shard_id: status.shard_id,
+                endorsed_by: status.endorsed_by,
+                missing: status.missing,
+                endorsed_stake: status.endorsed_stake,
+                total_stake: status.total_stake,
+                has_quorum: status.has_quorum,
+            };
+            let _ = self.events.send(Arc::new(RpcSubscriptionEvent::ChunkEndorsements {
+                block_hash,
+                block_height,
+                endorsements,
+            }));
+        }
+        if !with_state_changes {
+            return;
+        }
+        // All the changes of the block are broadcast, each connection filters them for its
+        // subscriptions.
+        let response = view_client_addr
+            .send(GetStateChangesWithCauseInBlock { block_hash }.with_span_context())
+            .await;
+        if let Ok(Ok(changes)) = response {
+            if !changes.is_empty() {
+                let _ = self.events.send(Arc::new(RpcSubscriptionEvent::StateChanges {
+                    block_hash,
+                    block_height,
+                    changes,
+                }));
+            }
+        }
+    }
+}
+
+async fn fetch_block(
+    view_client_addr: &Addr<ViewClientActor>,
+    block_reference: BlockReference,
+) -> Option<BlockView> {
+    match view_client_addr.send(GetBlock(block_reference).with_span_context()).await {
+        Ok(Ok(block)) => Some(block),
+        _ => None,
+    }
+}
+
+/// Upgrades the request to a websocket connection, unless the server reached its limit of
+/// connections.
+pub(crate) async fn ws_handler(
+    req: HttpRequest,
+    payload: web::Payload,
+    handler: web::Data<JsonRpcHandler>,
+    hub: web::Data<SubscriptionHub>,
+) -> Result<HttpResponse, HttpError> {
+    ws::verify_handshake(req.head())?;
+    let ip = rate_limiter::client_ip(&req, handler.rate_limiter.use_forwarded_ip());
+    let (sender, receiver) = mpsc::channel(OUTGOING_FRAMES_BUFFER);
+    let Some(connection) = Connection::new(hub.get_ref().clone(), handler, ip, sender) else {
+        return Ok(HttpResponse::ServiceUnavailable().finish());
+    };
+    let key = req.headers().get(header::SEC_WEBSOCKET_KEY).expect("checked by the handshake");
+    let accept = ws::hash_key(key.as_bytes());
+    actix_web::rt::spawn(connection.run(payload));
+    Ok(HttpResponse::build(StatusCode::SWITCHING_PROTOCOLS)
+        .upgrade("websocket")
+        .insert_header((header::SEC_WEBSOCKET_ACCEPT, HeaderValue::from_bytes(&accept).unwrap()))
+        .streaming(receiver))
+}
+
+struct Connection {
+    hub: SubscriptionHub,
+    handler: web::Data<JsonRpcHandler>,
+    rate_limiter: Arc<RateLimiter>,
+    /// IP of the client, which its requests are rate limited by.
+    ip: Option<IpAddr>,
+    codec: ws::Codec,
+    /// Encoded frames to write to the socket.
+    sender: mpsc::Sender<Result<Bytes, Infallible>>,
+    subscriptions: HashMap<SubscriptionId, RpcSubscriptionKind>,
+    next_subscription_id: SubscriptionId,
+}
+
+impl Connection {
+    fn new(
+        hub: SubscriptionHub,
+        handler: web::Data<JsonRpcHandler>,
+        ip: Option<IpAddr>,
+        sender: mpsc::Sender<Result<Bytes, Infallible>>,
+    ) -> Option<Self> {
+        if hub.num_connections.fetch_add(1, Ordering::Relaxed) >= hub.config.max_connections {
+            hub.num_connections.fetch_sub(1, Ordering::Relaxed);
+            return None;
+        }
+        metrics::WEBSOCKET_CONNECTIONS.inc();
+        let codec = ws::Codec::new().max_size(hub.max_frame_size);
+        let rate_limiter = handler.rate_limiter.clone();
+        Some(Self {
+            hub,
+            handler,
+            rate_limiter,
+            ip,
+            codec,
+            sender,
+            subscriptions: HashMap::new(),
+            next_subscription_id: 0,
+        })
+    }
+
+    async fn run(mut self, mut payload: web::Payload) {
+        let mut events = self.hub.events.subscribe();
+        let mut buffer = BytesMut::new();
+        loop {
+            tokio::select! {
+                bytes = payload.next() => {
+                    let Some(Ok(bytes)) = bytes else {
+                        return;
+                    };
+                    buffer.extend_from_slice(&bytes);
+                    loop {
+                        match self.codec.decode(&mut buffer) {
+                            Ok(Some(frame)) => {
+                                if !self.handle_frame(frame).await {
+                                    return;
+                                }
+                            }
+                            Ok(None) => break,
+                            Err(err) => {
+                                tracing::debug!(target: "jsonrpc", ?err, "Invalid websocket frame");
+                                self.close(CloseCode::Protocol, err.to_string()).await;
+                                return;
+                            }
+                        }
+                    }
+                }
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        if !self.send_event(&event).await {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(num_events)) => {
+                        metrics::WEBSOCKET_LAGGED_CONNECTIONS_TOTAL.inc();
+                        let description = format!("Fell behind by {} events", num_events);
+                        self.close(CloseCode::Policy, description).await;
+                        return;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        self.close(CloseCode::Away, "Server is shutting down".to_owned()).await;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles a frame received from the client. Returns whether the connection stays open.
+    async fn handle_frame(&mut self, frame: Frame) -> bool {
+        match frame {
+            Frame::Text(bytes) | Frame::Binary(bytes) => {
+                let response = match message::from_slice(&bytes) {
+                    Ok(Message::Request(request)) => self.process_request(request).await,
+                    Ok(_) => Message::error(RpcError::parse_error(
+                        "JSON RPC Request format was expected".to_owned(),
+                    )),
+                    Err(broken) => broken.reply(),
+                };
+                self.send_message(response).await
+            }
+            Frame::Ping(bytes) => self.send(ws::Message::Pong(bytes)).await,
+            Frame::Pong(_) => true,
+            Frame::Continuation(_) => {
+                self.close(CloseCode::Unsupported, "Fragmented messages are not supported".into())
+                    .await;
+                false
+            }
+            Frame::Close(reason) => {
+                self.send(ws::Message::Close(reason)).await;
+                false
+            }
+        }
+    }
+
+    async fn process_request(&mut self, request: Request) -> Message {
+        let methods = [request.method.as_str()];
+        if let Err(retry_after) = self.rate_limiter.check(self.ip, &methods, Instant::now()) {
+            let retry_after_secs = rate_limiter::retry_after_secs(retry_after);
+            return request.error(rate_limiter::too_many_requests_error(retry_after_secs));
+        }
+        let result = match request.method.as_str() {
+            "subscribe" => match RpcSubscribeRequest::parse(request.params.clone()) {
+                Ok(params) => {
+                    self.subscribe(params).map_err(RpcError::from).and_then(serialize_response)
+                }
+                Err(err) => Err(RpcError::from(err)),
+            },
+            "unsubscribe" => match RpcUnsubscribeRequest::parse(request.params.clone()) {
+                Ok(params) => serialize_response(self.unsubscribe(params)),
+                Err(err) => Err(RpcError::from(err)),
+            },
+            _ => {
+                let id = request.id.clone();
+                return Message::response(id, self.handler.process_request(request).await);
+            }
+        };
+        match result {
+            Ok(value) => request.reply(value),
+            Err(err) => request.error(err),
+        }
+    }
+
+    fn subscribe(
+        &mut self,
+        request: RpcSubscribeRequest,
+    ) -> Result<RpcSubscribeResponse, RpcSubscriptionError> {
+        let limit = self.hub.config.max_subscriptions_per_connection;
+        if self.subscriptions.len() >= limit {
+            return Err(RpcSubscriptionError::TooManySubscriptions { limit });
+        }
+        if let Some(num_subscriptions) = self.hub.num_subscriptions(&request.kind) {
+            num_subscriptions.fetch_add(1, Ordering::Relaxed);
+        }
+        let subscription_id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        self.subscriptions.insert(subscription_id, request.kind);
+        Ok(RpcSubscribeResponse { subscription_id })
+    }
+
+    fn unsubscribe(&mut self, request: RpcUnsubscribeRequest) -> RpcUnsubscribeResponse {
+        let kind = self.subscriptions.remove(&request.subscription_id);
+        if let Some(num_subscriptions) =
+            kind.as_ref().and_then(|kind| self.hub.num_subscriptions(kind))
+        {
+            num_subscriptions.fetch_sub(1, Ordering::Relaxed);
+        }
+        RpcUnsubscribeResponse { unsubscribed: kind.is_some() }
+    }
+
+    /// Sends the event to the matching subscriptions. Returns whether the connection stays open.
+    async fn send_event(&mut self, event: &RpcSubscriptionEvent) -> bool {
+        let notifications: Vec<RpcSubscriptionNotification> = self
+            .subscriptions
+            .iter()
+            .filter_map(|(&subscription_id, kind)| {
+                Some(RpcSubscriptionNotification { subscription_id, event: event.filter(kind)? })
+            })
+            .collect();
+        for notification in notifications {
+            let Ok(params) = serde_json::to_value(notification) else {
+                continue;
+            };
+            if !self.send_message(Message::notification("subscription".to_owned(), params)).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn send_message(&mut self, message: Message) -> bool {
+        let text: String = message.into();
+        self.send(ws::Message::Text(text.into())).await
+    }
+
+    async fn close(&mut self, code: CloseCode, description: String) {
+        self.send(ws::Message::Close(Some(CloseReason { code, description: Some(description) })))
+            .await;
+    }
+
+    /// Returns whether the frame was sent, i.e. whether the client is still connected.
+    async fn send(&mut self, message: ws::Message) -> bool {
+        let mut buffer = BytesMut::new();
+        if let Err(err) = self.codec.encode(message, &mut buffer) {
+            tracing::debug!(target: "jsonrpc", ?err, "Failed to encode websocket frame");
+            return false;
+        }
+        self.sender.send(Ok(buffer.freeze())).await.is_ok()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        for kind in self.subscriptions.values() {
+            if let Some(num_subscriptions) = self.hub.num_subscriptions(kind) {
+                num_subscriptions.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        self.hub.num_connections.fetch_sub(1, Ordering::Relaxed);
+        metrics::WEBSOCKET_CONNECTIONS.dec();
+    }
+}
@@ -134,16 +134,22 @@ impl AccountDataCacheSnapshot {
             return None;
         }
         let d = match &self.local {
-            Some(local) if d.account_key == local.signer.public_key() => Arc::new(
-                VersionedAccountData {
+            Some(local) if d.account_key == local.signer.public_key() => {
+                let signed = VersionedAccountData {
                     data: local.data.as_ref().clone(),
                     account_key: local.signer.public_key(),
                     version: d.version + 1,
                     timestamp: clock.now_utc(),
                 }
-                .sign(local.signer.as_ref())
-                .unwrap(),
-            ),
+                .sign(local.signer.as_ref());
+                match signed {
+                    Ok(signed) => Arc::new(signed),
+                    Err(err) => {
+                        tracing::warn!(target: "network", %err, "Failed to sign the account data");
+                        return None;
+                    }
+                }
+            }
             _ => d,
         };
         self.data.insert(d.account_key.clone(), d.clone());
@@ -172,18 +178,24 @@ impl AccountDataCacheSnapshot {
         let result = match self.keys.contains(&account_key) {
             false => None,
             true => {
-                let d = Arc::new(
-                    VersionedAccountData {
-                        data: local.data.as_ref().clone(),
-                        account_key: account_key.clone(),
-                        version: self.data.get(&account_key).map_or(0, |d| d.version) + 1,
-                        timestamp: clock.now_utc(),
+                let signed = VersionedAccountData {
+                    data: local.data.as_ref().clone(),
+                    account_key: account_key.clone(),
+                    version: self.data.get(&account_key).map_or(0, |d| d.version) + 1,
+                    timestamp: clock.now_utc(),
+                }
+                .sign(local.signer.as_ref());
+                match signed {
+                    Ok(signed) => {
+                        let d = Arc::new(signed);
+                        self.data.insert(account_key, d.clone());
+                        Some(d)
                     }
-                    .sign(local.signer.as_ref())
-                    .unwrap(),
-                );
-                self.data.insert(account_key, d.clone());
-                Some(d)
+                    Err(err) => {
+                        tracing::warn!(target: "network", %err, "Failed to sign the account data");
+                        None
+                    }
+                }
             }
         };
         self.local = Some(local);
@@ -5,8 +5,11 @@ use crate::types::{NetworkInfo, ReasonForBan};
 use near_primitives::block::{Approval, Block, BlockHeader};
 use near_primitives::challenge::Challenge;
 use near_primitives::chunk_validation::{ChunkEndorsement, ChunkStateWitness};
+#[cfg(feature = "new_epoch_sync")]
+use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
+use near_primitives::sharding::ChunkHash;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, EpochId, ShardId};
 use near_primitives::views::FinalExecutionOutcomeView;
@@ -66,7 +69,26 @@ pub trait Client: Send + Sync + 'static {
 
     async fn chunk_state_witness(&self, witness: ChunkStateWitness);
 
+    async fn chunk_state_witness_request(&self, chunk_hash: ChunkHash)
+        -> Option<ChunkStateWitness>;
+
     async fn chunk_endorsement(&self, endorsement: ChunkEndorsement);
+
+    /// The epoch sync methods do nothing by default, so that clients which
+    /// don't serve epoch sync build with the feature enabled.
+    #[cfg(feature = "new_epoch_sync")]
+    async fn epoch_sync_request(&self, _epoch_id: EpochId) -> Option<Box<EpochSyncInfo>> {
+        None
+    }
+
+    #[cfg(feature = "new_epoch_sync")]
+    async fn epoch_sync_response(
+        &self,
+        _peer_id: PeerId,
+        _epoch_id: EpochId,
+        _epoch_sync_info: Option<Box<EpochSyncInfo>>,
+    ) {
+    }
 }
 
 /// Implementation of Client which doesn't do anything and never returns errors.
@@ -137,5 +159,12 @@ impl Client for Noop {
 
     async fn chunk_state_witness(&self, _witness: ChunkStateWitness) {}
 
+    async fn chunk_state_witness_request(
+        &self,
+        _chunk_hash: ChunkHash,
+    ) -> Option<ChunkStateWitness> {
+        None
+    }
+
     async fn chunk_endorsement(&self, _endorsement: ChunkEndorsement) {}
 }
@@ -165,6 +165,11 @@ pub struct NetworkConfig {
     //   * ignoring received deleted edges as well
     pub skip_tombstones: Option<time::Duration>,
 
+    /// Upper bound on the size of a chunk state witness. It is checked on the
+    /// raw messages, so that an oversized witness is dropped before it is
+    /// decoded. `None` disables the check.
+    pub max_chunk_state_witness_size: Option<usize>,
+
     /// TEST-ONLY
     /// TODO(gprusak): make it pub(crate), once all integration tests
     /// are merged into near_network.
@@ -340,6 +345,7 @@ impl NetworkConfig {
             } else {
                 None
             },
+            max_chunk_state_witness_size: None,
             event_sink: Sink::null(),
         };
         this.override_config(cfg.experimental.network_config_overrides);
@@ -407,6 +413,7 @@ impl NetworkConfig {
                 enable_outbound: true,
             }),
             skip_tombstones: None,
+            max_chunk_state_witness_size: None,
             event_sink: Sink::null(),
         }
     }
@@ -6,6 +6,7 @@ mod network_protocol;
 mod peer;
 mod peer_manager;
 mod private_actix;
+mod seen_validation_messages;
 mod snapshot_hosts;
 mod stats;
 mod store;
@@ -22,6 +23,7 @@ pub mod raw;
 pub mod routing;
 pub mod shards_manager;
 pub mod state_sync;
+pub mod state_witness;
 pub mod tcp;
 pub mod test_loop;
 pub mod test_utils;
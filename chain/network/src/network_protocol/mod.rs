@@ -8,7 +8,7 @@ mod proto_conv;
 mod state_sync;
 pub use edge::*;
 use near_primitives::chunk_validation::ChunkEndorsement;
-use near_primitives::chunk_validation::ChunkStateWitness;
+use near_primitives::chunk_validation::{ChunkStateWitness, WitnessTransitionsBase};
 pub use peer::*;
 pub use state_sync::*;
 
@@ -33,6 +33,8 @@ use near_crypto::Signature;
 use near_o11y::OpenTelemetrySpanExt;
 use near_primitives::block::{Approval, Block, BlockHeader, GenesisId};
 use near_primitives::challenge::Challenge;
+#[cfg(feature = "new_epoch_sync")]
+use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::combine_hash;
 use near_primitives::network::{AnnounceAccount, PeerId};
@@ -42,8 +44,10 @@ use near_primitives::sharding::{
 use near_primitives::state_sync::{ShardStateSyncResponse, ShardStateSyncResponseV1};
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::AccountId;
+#[cfg(feature = "new_epoch_sync")]
+use near_primitives::types::EpochId;
 use near_primitives::types::{BlockHeight, ShardId};
-use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::validator_signer::{ValidatorSigner, ValidatorSignerError};
 use near_primitives::views::FinalExecutionOutcomeView;
 use protobuf::Message as _;
 use std::collections::HashSet;
@@ -179,7 +183,7 @@ impl VersionedAccountData {
                 MAX_ACCOUNT_DATA_SIZE_BYTES
             );
         }
-        let signature = signer.sign_account_key_payload(&payload);
+        let signature = signer.sign_account_key_payload(&payload)?;
         Ok(SignedAccountData {
             account_data: self,
             payload: AccountKeySignedPayload { payload, signature },
@@ -251,18 +255,21 @@ impl OwnedAccount {
     /// Serializes OwnedAccount to proto and signs it using `signer`.
     /// Panics if OwnedAccount.account_key doesn't match signer.public_key(),
     /// as this would likely be a bug.
-    pub fn sign(self, signer: &dyn ValidatorSigner) -> SignedOwnedAccount {
+    pub fn sign(
+        self,
+        signer: &dyn ValidatorSigner,
+    ) -> Result<SignedOwnedAccount, ValidatorSignerError> {
         assert_eq!(
             self.account_key,
             signer.public_key(),
             "OwnedAccount.account_key doesn't match the signer's account_key"
         );
         let payload = proto::AccountKeyPayload::from(&self).write_to_bytes().unwrap();
-        let signature = signer.sign_account_key_payload(&payload);
-        SignedOwnedAccount {
+        let signature = signer.sign_account_key_payload(&payload)?;
+        Ok(SignedOwnedAccount {
             owned_account: self,
             payload: AccountKeySignedPayload { payload, signature },
-        }
+        })
     }
 }
 
@@ -491,12 +498,60 @@ impl PeerMessage {
         })
     }
 
+    /// Returns the size of the chunk state witness carried by the encoded
+    /// message, or `None` if the message doesn't carry one. The witness itself
+    /// isn't decoded, so that an oversized one can be dropped cheaply.
+    pub(crate) fn chunk_state_witness_size(enc: Encoding, data: &[u8]) -> Option<usize> {
+        match enc {
+            Encoding::Borsh => {
+                let (&tag, routed) = data.split_first()?;
+                if tag != BORSH_ROUTED_MESSAGE_TAG {
+                    return None;
+                }
+                RoutedMessage::chunk_state_witness_size(routed)
+            }
+            Encoding::Proto => {
+                let proto_msg = proto::PeerMessage::parse_from_bytes(data).ok()?;
+                match proto_msg.message_type? {
+                    proto::peer_message::Message_type::Routed(routed) => {
+                        RoutedMessage::chunk_state_witness_size(&routed.borsh)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
     pub(crate) fn msg_variant(&self) -> &'static str {
         match self {
             PeerMessage::Routed(routed_msg) => routed_msg.body_variant(),
             _ => self.into(),
         }
     }
+
+    /// Priority class of the message in the outbound queue of a connection.
+    pub(crate) fn priority(&self) -> MessagePriority {
+        match self {
+            PeerMessage::Routed(routed_msg) => routed_msg.body.priority(),
+            _ => MessagePriority::Normal,
+        }
+    }
+}
+
+/// Priority class of an outbound message. Messages of the `High` class are
+/// written to the connection before any queued messages of the `Normal`
+/// class, so that latency-critical messages are not delayed by bulky ones
+/// (e.g. state sync parts).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::IntoStaticStr)]
+pub(crate) enum MessagePriority {
+    High,
+    Normal,
+}
+
+impl MessagePriority {
+    pub(crate) fn as_str(&self) -> &'static str {
+        self.into()
+    }
 }
 
 // TODO(#1313): Use Box
@@ -531,9 +586,36 @@ pub enum RoutedMessageBody {
     PartialEncodedChunkForward(PartialEncodedChunkForwardMsg),
     ChunkStateWitness(ChunkStateWitness),
     ChunkEndorsement(ChunkEndorsement),
+    ChunkEndorsementBatch(Vec<ChunkEndorsement>),
+    /// Request for the state witness of a chunk, sent by a chunk validator
+    /// which missed it to the chunk producer. The witness is sent back as a
+    /// `ChunkStateWitness` message.
+    ChunkStateWitnessRequest(ChunkHash),
+    /// A state witness which builds upon the transitions of the previous
+    /// witness of the shard, along with the state those transitions ended at.
+    /// Only sent since `ProtocolFeature::IncrementalChunkStateWitnesses`.
+    IncrementalChunkStateWitness(ChunkStateWitness, WitnessTransitionsBase),
+    /// Request for the `EpochSyncInfo` of a finished epoch, sent by a node
+    /// bootstrapping with Epoch Sync.
+    #[cfg(feature = "new_epoch_sync")]
+    EpochSyncRequest(EpochId),
+    /// Response to `EpochSyncRequest`. `None` if the epoch isn't finished yet,
+    /// or the node doesn't have its `EpochSyncInfo`.
+    #[cfg(feature = "new_epoch_sync")]
+    EpochSyncResponse(EpochId, Option<Box<EpochSyncInfo>>),
 }
 
 impl RoutedMessageBody {
+    /// Wraps the state witness into the message carrying it: incremental
+    /// witnesses are sent along with their transitions base, which isn't part
+    /// of the borsh encoding of the witness.
+    pub fn from_chunk_state_witness(witness: ChunkStateWitness) -> Self {
+        match witness.transitions_base.clone() {
+            Some(base) => RoutedMessageBody::IncrementalChunkStateWitness(witness, base),
+            None => RoutedMessageBody::ChunkStateWitness(witness),
+        }
+    }
+
     // Return whether this message is important.
     // In routing logics, we send important messages multiple times to minimize the risk that they are
     // lost
@@ -543,11 +625,26 @@ impl RoutedMessageBody {
             // and lost messages cannot be requested again.
             RoutedMessageBody::BlockApproval(_)
             | RoutedMessageBody::ChunkEndorsement(_)
+            | RoutedMessageBody::ChunkEndorsementBatch(_)
             | RoutedMessageBody::ChunkStateWitness(_)
+            | RoutedMessageBody::IncrementalChunkStateWitness(..)
             | RoutedMessageBody::VersionedPartialEncodedChunk(_) => true,
             _ => false,
         }
     }
+
+    /// Stateless validation messages are latency-critical: block production
+    /// waits for the chunk endorsements, which in turn wait for the witnesses.
+    pub(crate) fn priority(&self) -> MessagePriority {
+        match self {
+            RoutedMessageBody::ChunkStateWitness(_)
+            | RoutedMessageBody::IncrementalChunkStateWitness(..)
+            | RoutedMessageBody::ChunkStateWitnessRequest(_)
+            | RoutedMessageBody::ChunkEndorsement(_)
+            | RoutedMessageBody::ChunkEndorsementBatch(_) => MessagePriority::High,
+            _ => MessagePriority::Normal,
+        }
+    }
 }
 
 impl fmt::Debug for RoutedMessageBody {
@@ -598,6 +695,23 @@ impl fmt::Debug for RoutedMessageBody {
             RoutedMessageBody::_UnusedVersionedStateResponse => write!(f, "VersionedStateResponse"),
             RoutedMessageBody::ChunkStateWitness(_) => write!(f, "ChunkStateWitness"),
             RoutedMessageBody::ChunkEndorsement(_) => write!(f, "ChunkEndorsement"),
+            RoutedMessageBody::ChunkEndorsementBatch(endorsements) => {
+                write!(f, "ChunkEndorsementBatch({})", endorsements.len())
+            }
+            RoutedMessageBody::ChunkStateWitnessRequest(chunk_hash) => {
+                write!(f, "ChunkStateWitnessRequest({:?})", chunk_hash)
+            }
+            RoutedMessageBody::IncrementalChunkStateWitness(..) => {
+                write!(f, "IncrementalChunkStateWitness")
+            }
+            #[cfg(feature = "new_epoch_sync")]
+            RoutedMessageBody::EpochSyncRequest(epoch_id) => {
+                write!(f, "EpochSyncRequest({:?})", epoch_id)
+            }
+            #[cfg(feature = "new_epoch_sync")]
+            RoutedMessageBody::EpochSyncResponse(epoch_id, epoch_sync_info) => {
+                write!(f, "EpochSyncResponse({:?}, {})", epoch_id, epoch_sync_info.is_some())
+            }
         }
     }
 }
@@ -626,6 +740,28 @@ pub struct RoutedMessage {
     pub body: RoutedMessageBody,
 }
 
+/// Borsh tag of `PeerMessage::Routed` in the borsh encoding of peer messages.
+const BORSH_ROUTED_MESSAGE_TAG: u8 = 13;
+/// Borsh tag of `RoutedMessageBody::ChunkStateWitness`.
+const BORSH_CHUNK_STATE_WITNESS_TAG: u8 = 19;
+/// Borsh tag of `RoutedMessageBody::IncrementalChunkStateWitness`.
+const BORSH_INCREMENTAL_CHUNK_STATE_WITNESS_TAG: u8 = 23;
+
+impl RoutedMessage {
+    /// Returns the size of the chunk state witness carried by the borsh
+    /// encoded routed message, or `None` if it carries another message body.
+    /// Only the fields preceding the body are decoded.
+    fn chunk_state_witness_size(mut data: &[u8]) -> Option<usize> {
+        PeerIdOrHash::deserialize_reader(&mut data).ok()?;
+        PeerId::deserialize_reader(&mut data).ok()?;
+        Signature::deserialize_reader(&mut data).ok()?;
+        let _ttl = u8::deserialize_reader(&mut data).ok()?;
+        let (&tag, body) = data.split_first()?;
+        matches!(tag, BORSH_CHUNK_STATE_WITNESS_TAG | BORSH_INCREMENTAL_CHUNK_STATE_WITNESS_TAG)
+            .then_some(body.len())
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct RoutedMessageV2 {
     /// Message
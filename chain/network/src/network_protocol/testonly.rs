@@ -70,6 +70,7 @@ pub fn make_block(
         // TODO: migrate to clock.now()
         Some(chrono::Utc::now()), // timestamp_override
     )
+    .unwrap()
 }
 
 pub fn make_account_id<R: Rng>(rng: &mut R) -> AccountId {
@@ -106,11 +107,9 @@ pub fn make_peer_info<R: Rng>(rng: &mut R) -> PeerInfo {
 pub fn make_announce_account<R: Rng>(rng: &mut R) -> AnnounceAccount {
     let peer_id = make_peer_id(rng);
     let validator_signer = make_validator_signer(rng);
-    let signature = validator_signer.sign_account_announce(
-        validator_signer.validator_id(),
-        &peer_id,
-        &EpochId::default(),
-    );
+    let signature = validator_signer
+        .sign_account_announce(validator_signer.validator_id(), &peer_id, &EpochId::default())
+        .unwrap();
     AnnounceAccount {
         account_id: validator_signer.validator_id().clone(),
         peer_id: peer_id,
@@ -179,6 +178,7 @@ pub fn make_challenge<R: Rng>(rng: &mut R) -> Challenge {
         }),
         &make_validator_signer(rng),
     )
+    .unwrap()
 }
 
 // Based on ShardsManager::prepare_partial_encoded_chunk_response_from_chunk.
@@ -7,6 +7,7 @@ use crate::types::{PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg
 use anyhow::{bail, Context as _};
 use itertools::Itertools as _;
 use near_async::time;
+use near_primitives::test_utils::{create_test_chunk_endorsement, create_test_chunk_header};
 use rand::Rng as _;
 
 #[test]
@@ -170,3 +171,101 @@ fn serialize_deserialize() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn message_priority() {
+    let mut rng = make_rng(19385389);
+    let inner = near_primitives::chunk_validation::ChunkEndorsementInner::new(
+        near_primitives::sharding::ChunkHash(data::make_hash(&mut rng)),
+    );
+    let payload = near_primitives::chunk_validation::ChunkEndorsementPayload::V1(inner);
+    let endorsement = create_test_chunk_endorsement("validator", &payload);
+    let ping = RoutedMessageBody::Ping(Ping { nonce: 1, source: data::make_peer_id(&mut rng) });
+    for (body, want) in [
+        (RoutedMessageBody::ChunkEndorsement(endorsement.clone()), MessagePriority::High),
+        (RoutedMessageBody::ChunkEndorsementBatch(vec![endorsement]), MessagePriority::High),
+        (
+            RoutedMessageBody::ChunkStateWitnessRequest(near_primitives::sharding::ChunkHash(
+                data::make_hash(&mut rng),
+            )),
+            MessagePriority::High,
+        ),
+        (ping, MessagePriority::Normal),
+    ] {
+        let msg = PeerMessage::Routed(Box::new(data::make_routed_message(&mut rng, body)));
+        assert_eq!(msg.priority(), want);
+    }
+    let clock = time::FakeClock::default();
+    let chain = data::Chain::make(&clock, &mut rng, 1);
+    assert_eq!(PeerMessage::Block(chain.blocks[0].clone()).priority(), MessagePriority::Normal);
+}
+
+#[test]
+fn chunk_state_witness_size() {
+    use near_primitives::challenge::PartialState;
+    use near_primitives::chunk_validation::{ChunkStateTransition, WitnessTransitionsBase};
+
+    let mut rng = make_rng(19385389);
+    let transition = ChunkStateTransition {
+        block_hash: data::make_hash(&mut rng),
+        base_state: PartialState::TrieValues(vec![vec![7; 1000].into()]),
+        post_state_root: data::make_hash(&mut rng),
+    };
+    let chunk_header = create_test_chunk_header(1, 0);
+    let witness = ChunkStateWitness {
+        chunk_header,
+        main_state_transition: transition.clone(),
+        source_receipt_proofs: Default::default(),
+        applied_receipts_hash: CryptoHash::default(),
+        transactions: vec![],
+        implicit_transitions: vec![transition],
+        new_transactions: vec![],
+        new_transactions_validation_state: PartialState::default(),
+        transitions_base: None,
+    };
+    let witness_size = borsh::object_length(&witness).unwrap();
+    let msg = PeerMessage::Routed(Box::new(data::make_routed_message(
+        &mut rng,
+        RoutedMessageBody::from_chunk_state_witness(witness.clone()),
+    )));
+    // The transitions base of an incremental witness is sent next to it,
+    // leaving the encoding of the witness itself unchanged.
+    let base = WitnessTransitionsBase {
+        last_block_hash: data::make_hash(&mut rng),
+        post_state_root: data::make_hash(&mut rng),
+    };
+    let incremental_witness = ChunkStateWitness { transitions_base: Some(base.clone()), ..witness };
+    assert_eq!(borsh::object_length(&incremental_witness).unwrap(), witness_size);
+    let incremental_msg = PeerMessage::Routed(Box::new(data::make_routed_message(
+        &mut rng,
+        RoutedMessageBody::from_chunk_state_witness(incremental_witness),
+    )));
+    let PeerMessage::Routed(routed) =
+        PeerMessage::deserialize(Encoding::Borsh, &incremental_msg.serialize(Encoding::Borsh))
+            .unwrap()
+    else {
+        panic!("expected a routed message");
+    };
+    let RoutedMessageBody::IncrementalChunkStateWitness(decoded, decoded_base) = routed.msg.body
+    else {
+        panic!("expected an incremental witness");
+    };
+    assert_eq!(decoded.transitions_base, None);
+    assert_eq!(decoded_base, base);
+    let ping = RoutedMessageBody::Ping(Ping { nonce: 1, source: data::make_peer_id(&mut rng) });
+    let ping = PeerMessage::Routed(Box::new(data::make_routed_message(&mut rng, ping)));
+    for enc in [Encoding::Borsh, Encoding::Proto] {
+        let data = msg.serialize(enc);
+        assert_eq!(PeerMessage::chunk_state_witness_size(enc, &data), Some(witness_size));
+        let data = incremental_msg.serialize(enc);
+        assert_eq!(
+            PeerMessage::chunk_state_witness_size(enc, &data),
+            Some(witness_size + borsh::object_length(&base).unwrap())
+        );
+        let data = ping.serialize(enc);
+        assert_eq!(PeerMessage::chunk_state_witness_size(enc, &data), None);
+        let data = PeerMessage::Disconnect(Disconnect { remove_from_connection_store: false })
+            .serialize(enc);
+        assert_eq!(PeerMessage::chunk_state_witness_size(enc, &data), None);
+    }
+}
@@ -6,8 +6,8 @@ use crate::network_protocol::SnapshotHostInfoVerificationError;
 use crate::network_protocol::{
     DistanceVector, Edge, EdgeState, Encoding, OwnedAccount, ParsePeerMessageError,
     PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, PeersRequest, PeersResponse,
-    RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate, StateResponseInfo, SyncAccountsData,
-    SyncSnapshotHosts,
+    RawRoutedMessage, RoutedMessage, RoutedMessageBody, RoutingTableUpdate, StateResponseInfo,
+    SyncAccountsData, SyncSnapshotHosts,
 };
 use crate::peer::stream;
 use crate::peer::tracker::Tracker;
@@ -43,6 +43,7 @@ use parking_lot::Mutex;
 use rand::seq::IteratorRandom;
 use rand::thread_rng;
 use std::cmp::min;
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::io;
 use std::net::SocketAddr;
@@ -68,6 +69,11 @@ pub(crate) const DROP_DUPLICATED_MESSAGES_PERIOD: time::Duration = time::Duratio
 const SYNC_LATEST_BLOCK_INTERVAL: time::Duration = time::Duration::seconds(60);
 /// How often to perform a full sync of AccountsData with the peer.
 const ACCOUNTS_DATA_FULL_SYNC_INTERVAL: time::Duration = time::Duration::minutes(10);
+/// Maximum number of state sync requests served to a single peer within
+/// `STATE_REQUESTS_PERIOD`. Requests above the limit are dropped, and the peer
+/// retries them with other snapshot hosts once they time out.
+const MAX_STATE_REQUESTS_PER_PERIOD: usize = 32;
+const STATE_REQUESTS_PERIOD: time::Duration = time::Duration::seconds(1);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConnectionClosedEvent {
@@ -164,6 +170,8 @@ pub(crate) struct PeerActor {
     stats: Arc<connection::Stats>,
     /// Cache of recently routed messages, this allows us to drop duplicates
     routed_message_cache: LruCache<(PeerId, PeerIdOrHash, Signature), time::Instant>,
+    /// When the state sync requests of the last `STATE_REQUESTS_PERIOD` were received.
+    state_requests: Mutex<VecDeque<time::Instant>>,
     /// Whether we detected support for protocol buffers during handshake.
     protocol_buffers_supported: bool,
     /// Whether the PeerActor should skip protobuf support detection and use
@@ -291,7 +299,7 @@ impl PeerActor {
         let my_node_info = PeerInfo {
             id: network_state.config.node_id(),
             addr: network_state.config.node_addr.as_ref().map(|a| **a),
-            account_id: network_state.config.validator.as_ref().map(|v| v.account_id()),
+            account_id: network_state.validator.load().as_ref().as_ref().map(|v| v.account_id()),
         };
         // recv is the HandshakeSignal returned by this spawn_inner() call.
         let (send, recv): (HandshakeSignalSender, HandshakeSignal) =
@@ -319,6 +327,7 @@ impl PeerActor {
                     tracker: Default::default(),
                     stats,
                     routed_message_cache: LruCache::new(ROUTED_MESSAGE_CACHE_SIZE),
+                    state_requests: Default::default(),
                     protocol_buffers_supported: false,
                     force_encoding,
                     peer_info: match &stream_type {
@@ -412,7 +421,7 @@ impl PeerActor {
         self.tracker.lock().increment_sent(&self.clock, bytes.len() as u64);
         let bytes_len = bytes.len();
         tracing::trace!(target: "network", msg_len = bytes_len);
-        self.framed.send(stream::Frame(bytes));
+        self.framed.send(stream::Frame(bytes), msg.priority());
         metrics::PEER_DATA_SENT_BYTES.inc_by(bytes_len as u64);
         metrics::PEER_MESSAGE_SENT_BY_TYPE_TOTAL.with_label_values(&[msg_type]).inc();
         metrics::PEER_MESSAGE_SENT_BY_TYPE_BYTES
@@ -441,13 +450,17 @@ impl PeerActor {
                 archival: self.network_state.config.archive,
             },
             partial_edge_info: spec.partial_edge_info,
-            owned_account: self.network_state.config.validator.as_ref().map(|vc| {
+            owned_account: self.network_state.validator.load().as_ref().as_ref().and_then(|vc| {
                 OwnedAccount {
                     account_key: vc.signer.public_key(),
                     peer_id: self.network_state.config.node_id(),
                     timestamp: self.clock.now_utc(),
                 }
                 .sign(vc.signer.as_ref())
+                .map_err(|err| {
+                    tracing::warn!(target: "network", %err, "Failed to sign the owned account")
+                })
+                .ok()
             }),
         };
         let msg = match spec.tier {
@@ -921,14 +934,18 @@ impl PeerActor {
         network_state: &NetworkState,
         peer_id: PeerId,
         msg_hash: CryptoHash,
-        body: RoutedMessageBody,
+        msg: RoutedMessage,
     ) -> Result<Option<RoutedMessageBody>, ReasonForBan> {
         let _span = tracing::trace_span!(
             target: "network",
             "receive_routed_message",
-            "type" = <&RoutedMessageBody as Into<&'static str>>::into(&body)
+            "type" = <&RoutedMessageBody as Into<&'static str>>::into(&msg.body)
         )
         .entered();
+        let seen_validation_messages = &network_state.seen_validation_messages;
+        let Some(body) = seen_validation_messages.filter(clock, &msg_hash, msg.body) else {
+            return Ok(None);
+        };
         Ok(match body {
             RoutedMessageBody::TxStatusRequest(account_id, tx_hash) => network_state
                 .client
@@ -985,10 +1002,38 @@ impl PeerActor {
                 network_state.client.chunk_state_witness(witness).await;
                 None
             }
+            RoutedMessageBody::IncrementalChunkStateWitness(mut witness, base) => {
+                witness.transitions_base = Some(base);
+                network_state.client.chunk_state_witness(witness).await;
+                None
+            }
             RoutedMessageBody::ChunkEndorsement(endorsement) => {
                 network_state.client.chunk_endorsement(endorsement).await;
                 None
             }
+            RoutedMessageBody::ChunkEndorsementBatch(endorsements) => {
+                for endorsement in endorsements {
+                    network_state.client.chunk_endorsement(endorsement).await;
+                }
+                None
+            }
+            RoutedMessageBody::ChunkStateWitnessRequest(chunk_hash) => network_state
+                .client
+                .chunk_state_witness_request(chunk_hash)
+                .await
+                .map(RoutedMessageBody::ChunkStateWitness),
+            #[cfg(feature = "new_epoch_sync")]
+            RoutedMessageBody::EpochSyncRequest(epoch_id) => {
+                let epoch_sync_info =
+                    network_state.client.epoch_sync_request(epoch_id.clone()).await;
+                Some(RoutedMessageBody::EpochSyncResponse(epoch_id, epoch_sync_info))
+            }
+            #[cfg(feature = "new_epoch_sync")]
+            RoutedMessageBody::EpochSyncResponse(epoch_id, epoch_sync_info) => {
+                let client = &network_state.client;
+                client.epoch_sync_response(msg.author, epoch_id, epoch_sync_info).await;
+                None
+            }
             body => {
                 tracing::error!(target: "network", "Peer received unexpected message type: {:?}", body);
                 None
@@ -996,6 +1041,25 @@ impl PeerActor {
         })
     }
 
+    /// Returns true if a state sync request of the peer needs to be dropped,
+    /// because the peer exceeded its rate limit of state sync requests.
+    fn throttle_state_request(&self) -> bool {
+        let now = self.clock.now();
+        let mut state_requests = self.state_requests.lock();
+        while let Some(&instant) = state_requests.front() {
+            if now > instant + STATE_REQUESTS_PERIOD {
+                state_requests.pop_front();
+            } else {
+                break;
+            }
+        }
+        if state_requests.len() >= MAX_STATE_REQUESTS_PER_PERIOD {
+            return true;
+        }
+        state_requests.push_back(now);
+        false
+    }
+
     fn receive_message(
         &self,
         ctx: &mut actix::Context<Self>,
@@ -1028,6 +1092,18 @@ impl PeerActor {
             }
             _ => false,
         };
+        if matches!(msg, PeerMessage::StateRequestHeader(..) | PeerMessage::StateRequestPart(..))
+            && self.throttle_state_request()
+        {
+            tracing::debug!(
+                target: "network",
+                peer_id = %conn.peer_info.id,
+                "Throttled a state sync request",
+            );
+            metrics::PEER_STATE_REQUESTS_THROTTLED.inc();
+            message_processed_event();
+            return;
+        }
         let clock = self.clock.clone();
         let network_state = self.network_state.clone();
         let peer_id = conn.peer_info.id.clone();
@@ -1035,7 +1111,7 @@ impl PeerActor {
             Ok(match msg {
                 PeerMessage::Routed(msg) => {
                     let msg_hash = msg.hash();
-                    Self::receive_routed_message(&clock, &network_state, peer_id, msg_hash, msg.msg.body).await?.map(
+                    Self::receive_routed_message(&clock, &network_state, peer_id, msg_hash, msg.msg).await?.map(
                         |body| {
                             PeerMessage::Routed(network_state.sign_message(
                                 &clock,
@@ -1602,6 +1678,20 @@ impl actix::Handler<stream::Frame> for PeerActor {
             self.tracker.lock().increment_received(&self.clock, msg.len() as u64);
         }
 
+        if let Some(limit) = self.network_state.config.max_chunk_state_witness_size {
+            // A witness can't exceed the limit if the whole message doesn't.
+            if msg.len() > limit {
+                let size = self
+                    .encoding()
+                    .and_then(|enc| PeerMessage::chunk_state_witness_size(enc, &msg));
+                if let Some(size) = size.filter(|size| *size > limit) {
+                    tracing::debug!(target: "network", size, limit, peer = %self.peer_info, "Dropping oversized chunk state witness");
+                    metrics::MessageDropped::InputTooLong.inc_msg_type("ChunkStateWitness");
+                    return;
+                }
+            }
+        }
+
         let mut peer_msg = match self.parse_message(&msg) {
             Ok(msg) => msg,
             Err(err) => {
@@ -1,3 +1,4 @@
+use crate::network_protocol::MessagePriority;
 use crate::peer_manager::connection;
 use crate::stats::metrics;
 use crate::tcp;
@@ -8,6 +9,7 @@ use std::io;
 use std::net::SocketAddr;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::io::AsyncReadExt as _;
 use tokio::io::AsyncWriteExt as _;
 
@@ -40,6 +42,46 @@ pub(crate) enum RecvError {
 #[rtype(result = "()")]
 pub(crate) struct Frame(pub Vec<u8>);
 
+/// Frame waiting in the send queue.
+struct QueuedFrame {
+    frame: Frame,
+    priority: MessagePriority,
+    enqueued_at: Instant,
+}
+
+impl QueuedFrame {
+    fn observe_queue_delay(&self) {
+        metrics::PEER_MESSAGE_QUEUE_DELAY
+            .with_label_values(&[self.priority.as_str()])
+            .observe(self.enqueued_at.elapsed().as_secs_f64());
+    }
+}
+
+/// Send queues of the connection, one per message priority class.
+struct SendQueues {
+    high: tokio::sync::mpsc::UnboundedReceiver<QueuedFrame>,
+    normal: tokio::sync::mpsc::UnboundedReceiver<QueuedFrame>,
+}
+
+impl SendQueues {
+    /// Waits for the next frame to send, preferring the high priority queue.
+    /// Returns None once both queues are closed.
+    async fn recv(&mut self) -> Option<QueuedFrame> {
+        tokio::select! {
+            biased;
+            Some(frame) = self.high.recv() => Some(frame),
+            Some(frame) = self.normal.recv() => Some(frame),
+            else => None,
+        }
+    }
+
+    /// Returns the next frame to send without waiting, preferring the high
+    /// priority queue.
+    fn try_recv(&mut self) -> Option<QueuedFrame> {
+        self.high.try_recv().or_else(|_| self.normal.try_recv()).ok()
+    }
+}
+
 /// Stream critical error.
 /// Actor is responsible for calling ctx.stop() after receiving stream::Error.
 /// Actor might receive more than 1 stream::Error, but should call ctx.stop() just after the
@@ -57,7 +99,8 @@ pub(crate) enum Error {
 }
 
 pub(crate) struct FramedStream<Actor: actix::Actor> {
-    queue_send: tokio::sync::mpsc::UnboundedSender<Frame>,
+    queue_send_high: tokio::sync::mpsc::UnboundedSender<QueuedFrame>,
+    queue_send_normal: tokio::sync::mpsc::UnboundedSender<QueuedFrame>,
     stats: Arc<connection::Stats>,
     send_buf_size_metric: Arc<metrics::IntGaugeGuard>,
     addr: actix::Addr<Actor>,
@@ -75,7 +118,9 @@ where
         stats: Arc<connection::Stats>,
     ) -> Self {
         let (tcp_recv, tcp_send) = tokio::io::split(stream.stream);
-        let (queue_send, queue_recv) = tokio::sync::mpsc::unbounded_channel();
+        let (queue_send_high, high) = tokio::sync::mpsc::unbounded_channel();
+        let (queue_send_normal, normal) = tokio::sync::mpsc::unbounded_channel();
+        let queues = SendQueues { high, normal };
         let send_buf_size_metric = Arc::new(metrics::MetricGuard::new(
             &*metrics::PEER_DATA_WRITE_BUFFER_SIZE,
             vec![stream.peer_addr.to_string()],
@@ -85,7 +130,7 @@ where
             let stats = stats.clone();
             let m = send_buf_size_metric.clone();
             async move {
-                if let Err(err) = Self::run_send_loop(tcp_send, queue_recv, stats, m).await {
+                if let Err(err) = Self::run_send_loop(tcp_send, queues, stats, m).await {
                     addr.do_send(Error::Send(SendError::IO(err)));
                 }
             }
@@ -101,14 +146,22 @@ where
                 }
             }
         }));
-        Self { queue_send, stats, send_buf_size_metric, addr: ctx.address() }
+        Self {
+            queue_send_high,
+            queue_send_normal,
+            stats,
+            send_buf_size_metric,
+            addr: ctx.address(),
+        }
     }
 
-    /// Pushes `msg` to the send queue.
+    /// Pushes `msg` to the send queue of the given priority class.
+    /// Messages from the high priority queue are sent before any queued
+    /// messages from the normal priority queue.
     /// Silently drops message if the connection has been closed.
     /// If the message is too large, it will be silently dropped inside run_send_loop.
     /// Emits a critical error to Actor if send queue is full.
-    pub fn send(&self, frame: Frame) {
+    pub fn send(&self, frame: Frame, priority: MessagePriority) {
         let msg = &frame.0;
         let mut buf_size =
             self.stats.bytes_to_send.fetch_add(msg.len() as u64, Ordering::Acquire) as usize;
@@ -125,7 +178,11 @@ where
                 want_max_bytes: MAX_WRITE_BUFFER_CAPACITY_BYTES,
             }));
         }
-        let _ = self.queue_send.send(frame);
+        let queue = match priority {
+            MessagePriority::High => &self.queue_send_high,
+            MessagePriority::Normal => &self.queue_send_normal,
+        };
+        let _ = queue.send(QueuedFrame { frame, priority, enqueued_at: Instant::now() });
     }
 
     /// Event loop receiving and processing messages.
@@ -177,15 +234,17 @@ where
     }
     async fn run_send_loop(
         tcp_send: WriteHalf,
-        mut queue_recv: tokio::sync::mpsc::UnboundedReceiver<Frame>,
+        mut queues: SendQueues,
         stats: Arc<connection::Stats>,
         buf_size_metric: Arc<metrics::IntGaugeGuard>,
     ) -> io::Result<()> {
         const WRITE_BUFFER_CAPACITY: usize = 8 * 1024;
         let mut writer = tokio::io::BufWriter::with_capacity(WRITE_BUFFER_CAPACITY, tcp_send);
-        while let Some(Frame(mut msg)) = queue_recv.recv().await {
+        while let Some(mut queued) = queues.recv().await {
             // Try writing a batch of messages and flush once at the end.
             loop {
+                queued.observe_queue_delay();
+                let msg = queued.frame.0;
                 // TODO(gprusak): sending a too large message should probably be treated as a bug,
                 // since dropping messages may lead to hard-to-debug high-level issues.
                 if msg.len() > NETWORK_MESSAGE_MAX_SIZE_BYTES {
@@ -197,16 +256,16 @@ where
                 stats.messages_to_send.fetch_sub(1, Ordering::Release);
                 stats.bytes_to_send.fetch_sub(msg.len() as u64, Ordering::Release);
                 buf_size_metric.sub(msg.len() as i64);
-                msg = match queue_recv.try_recv() {
-                    Ok(Frame(it)) => it,
-                    Err(_) => break,
+                queued = match queues.try_recv() {
+                    Some(it) => it,
+                    None => break,
                 };
             }
             // This is an unconditional flush, which means that even if new messages
             // will be added to the queue in the meantime, we will wait for the buffer
             // to be flushed before sending them. This is suboptimal in case messages are small
             // and added to the queue at a rate similar to flush latency. To fix that
-            // we would need to put writer.flush() and queue_recv.recv() into a tokio::select
+            // we would need to put writer.flush() and queues.recv() into a tokio::select
             // and make sure that both are cancellation-safe.
             writer.flush().await?;
         }
@@ -1,5 +1,6 @@
 use crate::actix::ActixSystem;
 use crate::network_protocol::testonly as data;
+use crate::network_protocol::MessagePriority;
 use crate::peer::stream;
 use crate::tcp;
 use crate::testonly::make_rng;
@@ -20,12 +21,12 @@ impl actix::Actor for Actor {
 
 #[derive(actix::Message)]
 #[rtype("()")]
-struct SendFrame(stream::Frame);
+struct SendFrame(stream::Frame, MessagePriority);
 
 impl actix::Handler<SendFrame> for Actor {
     type Result = ();
-    fn handle(&mut self, SendFrame(frame): SendFrame, _ctx: &mut Self::Context) {
-        self.stream.send(frame);
+    fn handle(&mut self, SendFrame(frame, priority): SendFrame, _ctx: &mut Self::Context) {
+        self.stream.send(frame, priority);
     }
 }
 
@@ -82,7 +83,7 @@ async fn send_recv() {
             })
             .collect();
         for msg in &msgs {
-            a1.system.addr.send(SendFrame(msg.clone())).await.unwrap();
+            a1.system.addr.send(SendFrame(msg.clone(), MessagePriority::Normal)).await.unwrap();
         }
         for want in &msgs {
             let got = a2.queue_recv.recv().await.unwrap();
@@ -29,7 +29,7 @@ mod tests;
 
 impl tcp::Tier {
     /// Checks if the given message type is allowed on a connection of the given Tier.
-    /// TIER1 is reserved exclusively for BFT consensus messages.
+    /// TIER1 is reserved exclusively for BFT consensus and chunk validation messages.
     /// Each validator establishes a lot of TIER1 connections, so bandwidth shouldn't be
     /// wasted on broadcasting or periodic state syncs on TIER1 connections.
     pub(crate) fn is_allowed(self, msg: &PeerMessage) -> bool {
@@ -47,6 +47,10 @@ impl tcp::Tier {
         match body {
             RoutedMessageBody::BlockApproval(..) => true,
             RoutedMessageBody::VersionedPartialEncodedChunk(..) => true,
+            RoutedMessageBody::ChunkStateWitness(..) => true,
+            RoutedMessageBody::IncrementalChunkStateWitness(..) => true,
+            RoutedMessageBody::ChunkEndorsement(..) => true,
+            RoutedMessageBody::ChunkEndorsementBatch(..) => true,
             _ => self == tcp::Tier::T2,
         }
     }
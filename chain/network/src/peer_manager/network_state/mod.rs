@@ -17,6 +17,7 @@ use crate::peer_manager::peer_store;
 use crate::private_actix::RegisterPeerError;
 use crate::routing::route_back_cache::RouteBackCache;
 use crate::routing::NetworkTopologyChange;
+use crate::seen_validation_messages::SeenValidationMessages;
 use crate::shards_manager::ShardsManagerRequestFromNetwork;
 use crate::snapshot_hosts::{SnapshotHostInfoError, SnapshotHostsCache};
 use crate::stats::metrics;
@@ -31,6 +32,7 @@ use near_primitives::block::GenesisId;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
 use near_primitives::types::AccountId;
+use near_primitives::validator_signer::ValidatorSigner;
 use parking_lot::Mutex;
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicUsize;
@@ -103,6 +105,9 @@ pub(crate) struct NetworkState {
 
     /// Network-related info about the chain.
     pub chain_info: ArcSwap<Option<ChainInfo>>,
+    /// Validator of this node. Starts as `config.validator`, the signer is
+    /// replaced when the validator key is rotated or removed.
+    pub validator: ArcSwap<Option<config::ValidatorConfig>>,
     /// AccountsData for TIER1 accounts.
     pub accounts_data: Arc<AccountDataCache>,
     /// AnnounceAccounts mapping TIER1 account ids to peer ids.
@@ -129,6 +134,9 @@ pub(crate) struct NetworkState {
     /// Hashes of the body of recently received routed messages.
     /// It allows us to determine whether messages arrived faster over TIER1 or TIER2 network.
     pub recent_routed_messages: Mutex<lru::LruCache<CryptoHash, ()>>,
+    /// Recently received chunk state witnesses and chunk endorsements.
+    /// Duplicates are dropped before they reach the client.
+    pub seen_validation_messages: SeenValidationMessages,
 
     /// Hash of messages that requires routing back to respective previous hop.
     pub tier2_route_back: Mutex<RouteBackCache>,
@@ -188,6 +196,7 @@ impl NetworkState {
             client,
             shards_manager_adapter,
             chain_info: Default::default(),
+            validator: ArcSwap::new(Arc::new(config.validator.clone())),
             tier2: connection::Pool::new(config.node_id()),
             tier1: connection::Pool::new(config.node_id()),
             inbound_handshake_permits: Arc::new(tokio::sync::Semaphore::new(LIMIT_PENDING_PEERS)),
@@ -202,6 +211,7 @@ impl NetworkState {
             recent_routed_messages: Mutex::new(lru::LruCache::new(
                 RECENT_ROUTED_MESSAGES_CACHE_SIZE,
             )),
+            seen_validation_messages: SeenValidationMessages::default(),
             txns_since_last_block: AtomicUsize::new(0),
             whitelist_nodes,
             add_edges_demux: demux::Demux::new(config.routing_table_update_rate_limit),
@@ -499,7 +509,7 @@ impl NetworkState {
         // Check if the message is for myself and don't try to send it in that case.
         if let PeerIdOrHash::PeerId(target) = &msg.target {
             if target == &my_peer_id {
-                tracing::debug!(target: "network", account_id = ?self.config.validator.as_ref().map(|v|v.account_id()), ?my_peer_id, ?msg, "Drop signed message to myself");
+                tracing::debug!(target: "network", account_id = ?self.validator.load().as_ref().as_ref().map(|v|v.account_id()), ?my_peer_id, ?msg, "Drop signed message to myself");
                 metrics::CONNECTED_TO_MYSELF.inc();
                 return false;
             }
@@ -536,7 +546,7 @@ impl NetworkState {
                         metrics::MessageDropped::NoRouteFound.inc(&msg.body);
 
                         tracing::debug!(target: "network",
-                              account_id = ?self.config.validator.as_ref().map(|v|v.account_id()),
+                              account_id = ?self.validator.load().as_ref().as_ref().map(|v|v.account_id()),
                               to = ?msg.target,
                               reason = ?find_route_error,
                               known_peers = ?self.graph.routing_table.reachable_peers(),
@@ -611,7 +621,7 @@ impl NetworkState {
             // TODO(MarX, #1369): Message is dropped here. Define policy for this case.
             metrics::MessageDropped::UnknownAccount.inc(&msg);
             tracing::debug!(target: "network",
-                   account_id = ?self.config.validator.as_ref().map(|v|v.account_id()),
+                   account_id = ?self.validator.load().as_ref().as_ref().map(|v|v.account_id()),
                    to = ?account_id,
                    ?msg,"Drop message: unknown account",
             );
@@ -824,4 +834,14 @@ impl NetworkState {
         }
         has_changed
     }
+
+    /// Switches to a new signer of the validator of this node, or to none if
+    /// its key was removed. The proxies of the validator are kept.
+    pub fn set_validator_signer(&self, signer: Option<Arc<dyn ValidatorSigner>>) {
+        let validator = signer.and_then(|signer| {
+            let proxies = self.config.validator.as_ref()?.proxies.clone();
+            Some(config::ValidatorConfig { signer, proxies })
+        });
+        self.validator.store(Arc::new(validator));
+    }
 }
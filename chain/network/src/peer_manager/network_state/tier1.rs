@@ -22,13 +22,14 @@ impl super::NetworkState {
     pub fn tier1_validator_config(
         &self,
         accounts_data: &AccountDataCacheSnapshot,
-    ) -> Option<&config::ValidatorConfig> {
+    ) -> Option<config::ValidatorConfig> {
         if self.config.tier1.is_none() {
             return None;
         }
-        self.config
-            .validator
+        self.validator
+            .load()
             .as_ref()
+            .clone()
             .filter(|cfg| accounts_data.keys.contains(&cfg.signer.public_key()))
     }
 
@@ -271,7 +272,7 @@ impl super::NetworkState {
         // Construct a safe set of connections.
         let mut safe_set: HashSet<PeerId> = safe.values().map(|v| (*v).clone()).collect();
         // Add proxies of our node to the safe set.
-        if let Some(vc) = validator_cfg {
+        if let Some(vc) = &validator_cfg {
             match &vc.proxies {
                 config::ValidatorProxies::Dynamic(_) => {
                     safe_set.insert(self.config.node_id());
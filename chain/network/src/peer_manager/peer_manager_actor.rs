@@ -9,13 +9,14 @@ use crate::peer_manager::connection;
 use crate::peer_manager::network_state::{NetworkState, WhitelistNode};
 use crate::peer_manager::peer_store;
 use crate::shards_manager::ShardsManagerRequestFromNetwork;
+use crate::state_witness::{DistributeStateWitnessRequest, SendChunkEndorsementsRequest};
 use crate::stats::metrics;
 use crate::store;
 use crate::tcp;
 use crate::types::{
     ConnectedPeerInfo, HighestHeightPeerInfo, KnownProducer, NetworkInfo, NetworkRequests,
     NetworkResponses, PeerInfo, PeerManagerMessageRequest, PeerManagerMessageResponse, PeerType,
-    SetChainInfo, SnapshotHostInfo,
+    SetChainInfo, SetValidatorSigner, SnapshotHostInfo,
 };
 use crate::{client, network_protocol};
 use actix::fut::future::wrap_future;
@@ -696,6 +697,7 @@ impl PeerManagerActor {
                 .collect(),
             tier1_accounts_keys: self.state.accounts_data.load().keys.iter().cloned().collect(),
             tier1_accounts_data: self.state.accounts_data.load().data.values().cloned().collect(),
+            snapshot_hosts: self.state.snapshot_hosts.get_hosts(),
         }
     }
 
@@ -963,7 +965,7 @@ impl PeerManagerActor {
                     self.state.send_message_to_account(
                         &self.clock,
                         &chunk_validator,
-                        RoutedMessageBody::ChunkStateWitness(state_witness.clone()),
+                        RoutedMessageBody::from_chunk_state_witness(state_witness.clone()),
                     );
                 }
                 NetworkResponses::NoResponse
@@ -976,6 +978,40 @@ impl PeerManagerActor {
                 );
                 NetworkResponses::NoResponse
             }
+            NetworkRequests::ChunkEndorsementBatch(target, endorsements) => {
+                self.state.send_message_to_account(
+                    &self.clock,
+                    &target,
+                    RoutedMessageBody::ChunkEndorsementBatch(endorsements),
+                );
+                NetworkResponses::NoResponse
+            }
+            NetworkRequests::ChunkStateWitnessRequest(target, chunk_hash) => {
+                self.state.send_message_to_account(
+                    &self.clock,
+                    &target,
+                    RoutedMessageBody::ChunkStateWitnessRequest(chunk_hash),
+                );
+                NetworkResponses::NoResponse
+            }
+            #[cfg(feature = "new_epoch_sync")]
+            NetworkRequests::EpochSyncRequest { peer_id, epoch_id } => {
+                if self.state.send_message_to_peer(
+                    &self.clock,
+                    tcp::Tier::T2,
+                    self.state.sign_message(
+                        &self.clock,
+                        RawRoutedMessage {
+                            target: PeerIdOrHash::PeerId(peer_id),
+                            body: RoutedMessageBody::EpochSyncRequest(epoch_id),
+                        },
+                    ),
+                ) {
+                    NetworkResponses::NoResponse
+                } else {
+                    NetworkResponses::RouteNotFound
+                }
+            }
         }
     }
 
@@ -1041,6 +1077,74 @@ impl actix::Handler<WithSpanContext<SetChainInfo>> for PeerManagerActor {
     }
 }
 
+impl actix::Handler<WithSpanContext<SetValidatorSigner>> for PeerManagerActor {
+    type Result = ();
+    #[perf]
+    fn handle(&mut self, msg: WithSpanContext<SetValidatorSigner>, ctx: &mut Self::Context) {
+        let (_span, SetValidatorSigner(signer)) = handler_trace_span!(target: "network", msg);
+        let _timer = metrics::PEER_MANAGER_MESSAGES_TIME
+            .with_label_values(&["SetValidatorSigner"])
+            .start_timer();
+        self.state.set_validator_signer(signer);
+
+        let state = self.state.clone();
+        let clock = self.clock.clone();
+        ctx.spawn(wrap_future(
+            async move {
+                // Readvertise the proxies signed with the new key right away, instead of waiting
+                // for the next periodic advertisement.
+                state.tier1_advertise_proxies(&clock).await;
+            }
+            .in_current_span(),
+        ));
+    }
+}
+
+impl actix::Handler<WithSpanContext<DistributeStateWitnessRequest>> for PeerManagerActor {
+    type Result = ();
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<DistributeStateWitnessRequest>,
+        _ctx: &mut Self::Context,
+    ) {
+        let (_span, DistributeStateWitnessRequest { chunk_validators, state_witness }) =
+            handler_trace_span!(target: "network", msg);
+        let _timer = metrics::PEER_MANAGER_MESSAGES_TIME
+            .with_label_values(&["DistributeStateWitnessRequest"])
+            .start_timer();
+        for chunk_validator in chunk_validators {
+            self.state.send_message_to_account(
+                &self.clock,
+                &chunk_validator,
+                RoutedMessageBody::from_chunk_state_witness(state_witness.clone()),
+            );
+        }
+    }
+}
+
+impl actix::Handler<WithSpanContext<SendChunkEndorsementsRequest>> for PeerManagerActor {
+    type Result = ();
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: WithSpanContext<SendChunkEndorsementsRequest>,
+        _ctx: &mut Self::Context,
+    ) {
+        let (_span, SendChunkEndorsementsRequest { block_producer, mut endorsements }) =
+            handler_trace_span!(target: "network", msg);
+        let _timer = metrics::PEER_MANAGER_MESSAGES_TIME
+            .with_label_values(&["SendChunkEndorsementsRequest"])
+            .start_timer();
+        let body = if endorsements.len() == 1 {
+            RoutedMessageBody::ChunkEndorsement(endorsements.pop().unwrap())
+        } else {
+            RoutedMessageBody::ChunkEndorsementBatch(endorsements)
+        };
+        self.state.send_message_to_account(&self.clock, &block_producer, body);
+    }
+}
+
 impl actix::Handler<WithSpanContext<PeerManagerMessageRequest>> for PeerManagerActor {
     type Result = PeerManagerMessageResponse;
     #[perf]
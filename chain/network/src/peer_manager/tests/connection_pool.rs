@@ -169,7 +169,8 @@ async fn owned_account_mismatch() {
                     peer_id: data::make_peer_id(rng),
                     timestamp: clock.now_utc(),
                 }
-                .sign(vc.signer.as_ref()),
+                .sign(vc.signer.as_ref())
+                .unwrap(),
             ),
         }))
         .await;
@@ -282,7 +283,8 @@ async fn invalid_edge() {
                         peer_id: cfg.node_id(),
                         timestamp: clock.now_utc(),
                     }
-                    .sign(vc.signer.as_ref()),
+                    .sign(vc.signer.as_ref())
+                    .unwrap(),
                 ),
             };
             let handshake = match tier {
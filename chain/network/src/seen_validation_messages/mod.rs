@@ -0,0 +1,121 @@
+//! Cache of recently received stateless validation messages.
+//!
+//! Chunk state witnesses and chunk endorsements are sent multiple times over
+//! both TIER1 and TIER2 connections, to minimize the risk of them being lost.
+//! The cache allows the node to drop the duplicate copies before they reach
+//! the client.
+//!
+//! The messages are cached before the client verifies them, so a message is
+//! identified by everything its signature covers and the signature itself.
+//! A copy with a forged signature or content doesn't match the genuine message
+//! and can't get it dropped.
+
+use crate::network_protocol::RoutedMessageBody;
+use crate::stats::metrics;
+use lru::LruCache;
+use near_async::time;
+use near_crypto::Signature;
+use near_primitives::chunk_validation::ChunkEndorsement;
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::AccountId;
+use parking_lot::Mutex;
+
+#[cfg(test)]
+mod tests;
+
+/// Default value for `capacity`.
+const DEFAULT_CAPACITY: usize = 10_000;
+/// Default value for `ttl`.
+const DEFAULT_TTL: time::Duration = time::Duration::seconds(60);
+
+/// Identity of a stateless validation message.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum MessageId {
+    /// Hash of the routed message carrying the witness. It covers the author
+    /// and the whole witness, and the signature of the author over it has
+    /// already been verified.
+    ChunkStateWitness(CryptoHash),
+    /// Endorsement of the chunk by the chunk validator, with its signature.
+    ChunkEndorsement(ChunkHash, AccountId, Signature),
+}
+
+pub(crate) struct SeenValidationMessages {
+    /// How long a message is considered a duplicate after it has been seen.
+    ttl: time::Duration,
+    /// Time at which each recently received message was first seen.
+    seen: Mutex<LruCache<MessageId, time::Instant>>,
+}
+
+impl Default for SeenValidationMessages {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_TTL)
+    }
+}
+
+impl SeenValidationMessages {
+    pub fn new(capacity: usize, ttl: time::Duration) -> Self {
+        Self { ttl, seen: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    /// Records the message as seen. Returns false if the same message has
+    /// already been seen within the ttl, in which case it should be dropped.
+    fn insert(&self, now: time::Instant, id: MessageId) -> bool {
+        let mut seen = self.seen.lock();
+        if let Some(first_seen) = seen.get(&id) {
+            if now - *first_seen < self.ttl {
+                return false;
+            }
+        }
+        seen.put(id, now);
+        true
+    }
+
+    /// Filters out the duplicate stateless validation messages.
+    /// Returns None if the whole message is a duplicate, otherwise the
+    /// message with the duplicates removed (only batches can be partially
+    /// duplicated). Other messages are returned unchanged.
+    pub fn filter(
+        &self,
+        clock: &time::Clock,
+        msg_hash: &CryptoHash,
+        body: RoutedMessageBody,
+    ) -> Option<RoutedMessageBody> {
+        let now = clock.now();
+        let body = match body {
+            RoutedMessageBody::ChunkEndorsementBatch(endorsements) => {
+                RoutedMessageBody::ChunkEndorsementBatch(
+                    endorsements
+                        .into_iter()
+                        .filter(|endorsement| self.insert(now, endorsement_id(endorsement)))
+                        .collect(),
+                )
+            }
+            body => body,
+        };
+        let is_new = match &body {
+            RoutedMessageBody::ChunkStateWitness(_)
+            | RoutedMessageBody::IncrementalChunkStateWitness(..) => {
+                self.insert(now, MessageId::ChunkStateWitness(*msg_hash))
+            }
+            RoutedMessageBody::ChunkEndorsement(endorsement) => {
+                self.insert(now, endorsement_id(endorsement))
+            }
+            RoutedMessageBody::ChunkEndorsementBatch(endorsements) => !endorsements.is_empty(),
+            _ => true,
+        };
+        if !is_new {
+            metrics::MessageDropped::Duplicate.inc(&body);
+            return None;
+        }
+        Some(body)
+    }
+}
+
+fn endorsement_id(endorsement: &ChunkEndorsement) -> MessageId {
+    MessageId::ChunkEndorsement(
+        endorsement.inner.chunk_hash.clone(),
+        endorsement.account_id.clone(),
+        endorsement.signature.clone(),
+    )
+}
@@ -0,0 +1,83 @@
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::RoutedMessageBody;
+use crate::seen_validation_messages::SeenValidationMessages;
+use crate::testonly::make_rng;
+use near_async::time;
+use near_crypto::{KeyType, Signature};
+use near_primitives::chunk_validation::{
+    ChunkEndorsement, ChunkEndorsementInner, ChunkEndorsementPayload,
+};
+use near_primitives::sharding::ChunkHash;
+use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+use rand::Rng;
+
+fn make_endorsement<R: Rng>(rng: &mut R, signer: &InMemoryValidatorSigner) -> ChunkEndorsement {
+    let payload =
+        ChunkEndorsementPayload::V1(ChunkEndorsementInner::new(ChunkHash(data::make_hash(rng))));
+    ChunkEndorsement {
+        account_id: signer.validator_id().clone(),
+        signature: signer.sign_chunk_endorsement(&payload).unwrap(),
+        inner: payload.inner(),
+    }
+}
+
+#[test]
+fn drop_duplicate_endorsements() {
+    let mut rng = make_rng(2947294234);
+    let rng = &mut rng;
+    let clock = time::FakeClock::default();
+    let msg_hash = data::make_hash(rng);
+    let signer = data::make_validator_signer(rng);
+    let seen = SeenValidationMessages::new(100, time::Duration::seconds(10));
+
+    let e1 = make_endorsement(rng, &signer);
+    let msg = RoutedMessageBody::ChunkEndorsement(e1.clone());
+    assert_eq!(seen.filter(&clock.clock(), &msg_hash, msg.clone()), Some(msg.clone()));
+    assert_eq!(seen.filter(&clock.clock(), &msg_hash, msg.clone()), None);
+
+    // Only the new endorsements of a batch are kept.
+    let e2 = make_endorsement(rng, &signer);
+    let batch = RoutedMessageBody::ChunkEndorsementBatch(vec![e1.clone(), e2.clone()]);
+    assert_eq!(
+        seen.filter(&clock.clock(), &msg_hash, batch.clone()),
+        Some(RoutedMessageBody::ChunkEndorsementBatch(vec![e2]))
+    );
+    assert_eq!(seen.filter(&clock.clock(), &msg_hash, batch), None);
+
+    // After the ttl, the message is not considered a duplicate anymore.
+    clock.advance(time::Duration::seconds(11));
+    assert_eq!(seen.filter(&clock.clock(), &msg_hash, msg.clone()), Some(msg));
+}
+
+#[test]
+fn forged_endorsement_does_not_shadow_genuine_one() {
+    let mut rng = make_rng(3049187532);
+    let rng = &mut rng;
+    let clock = time::FakeClock::default();
+    let msg_hash = data::make_hash(rng);
+    let signer = data::make_validator_signer(rng);
+    let seen = SeenValidationMessages::default();
+
+    let endorsement = make_endorsement(rng, &signer);
+    let mut forged = endorsement.clone();
+    forged.signature = Signature::empty(KeyType::ED25519);
+    let forged = RoutedMessageBody::ChunkEndorsement(forged);
+    assert_eq!(seen.filter(&clock.clock(), &msg_hash, forged.clone()), Some(forged));
+    let msg = RoutedMessageBody::ChunkEndorsement(endorsement);
+    assert_eq!(seen.filter(&clock.clock(), &msg_hash, msg.clone()), Some(msg.clone()));
+    assert_eq!(seen.filter(&clock.clock(), &msg_hash, msg), None);
+}
+
+#[test]
+fn other_messages_are_not_filtered() {
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let clock = time::FakeClock::default();
+    let msg_hash = data::make_hash(rng);
+    let seen = SeenValidationMessages::default();
+    let source = data::make_peer_id(rng);
+    let msg = RoutedMessageBody::Ping(crate::network_protocol::Ping { nonce: 0, source });
+    for _ in 0..3 {
+        assert_eq!(seen.filter(&clock.clock(), &msg_hash, msg.clone()), Some(msg.clone()));
+    }
+}
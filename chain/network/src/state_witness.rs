@@ -0,0 +1,46 @@
+use crate::types::NetworkRequests;
+use near_primitives::chunk_validation::{ChunkEndorsement, ChunkStateWitness};
+use near_primitives::types::AccountId;
+
+/// A chunk state witness received from the network, to be validated by the client.
+#[derive(actix::Message, Clone, Debug, PartialEq, Eq)]
+#[rtype(result = "()")]
+pub struct ChunkStateWitnessMessage(pub ChunkStateWitness);
+
+/// A chunk endorsement received from the network, to be tracked by the client.
+#[derive(actix::Message, Clone, Debug)]
+#[rtype(result = "()")]
+pub struct ChunkEndorsementMessage(pub ChunkEndorsement);
+
+/// Request to send the state witness of a chunk to the chunk validators.
+#[derive(actix::Message, Clone, Debug, PartialEq, Eq)]
+#[rtype(result = "()")]
+pub struct DistributeStateWitnessRequest {
+    pub chunk_validators: Vec<AccountId>,
+    pub state_witness: ChunkStateWitness,
+}
+
+/// Request to send chunk endorsements to a block producer. A single
+/// endorsement is sent on its own, several are sent as one batch.
+#[derive(actix::Message, Clone, Debug)]
+#[rtype(result = "()")]
+pub struct SendChunkEndorsementsRequest {
+    pub block_producer: AccountId,
+    pub endorsements: Vec<ChunkEndorsement>,
+}
+
+impl From<DistributeStateWitnessRequest> for NetworkRequests {
+    fn from(msg: DistributeStateWitnessRequest) -> Self {
+        NetworkRequests::ChunkStateWitness(msg.chunk_validators, msg.state_witness)
+    }
+}
+
+impl From<SendChunkEndorsementsRequest> for NetworkRequests {
+    fn from(mut msg: SendChunkEndorsementsRequest) -> Self {
+        if msg.endorsements.len() == 1 {
+            NetworkRequests::ChunkEndorsement(msg.block_producer, msg.endorsements.pop().unwrap())
+        } else {
+            NetworkRequests::ChunkEndorsementBatch(msg.block_producer, msg.endorsements)
+        }
+    }
+}
@@ -152,6 +152,16 @@ pub(crate) static PEER_DATA_READ_BUFFER_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub(crate) static PEER_MESSAGE_QUEUE_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_peer_message_queue_delay",
+        "Time that outbound messages spend in the send queue of a connection, by message priority class",
+        &["priority"],
+        Some(exponential_buckets(0.0001, 2., 20).unwrap()),
+    )
+    .unwrap()
+});
+
 pub(crate) static PEER_DATA_WRITE_BUFFER_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     try_create_int_gauge_vec(
         "near_peer_write_buffer_size",
@@ -262,6 +272,14 @@ pub(crate) static EDGE_TOMBSTONE_RECEIVING_SKIPPED: Lazy<IntCounter> = Lazy::new
     .unwrap()
 });
 
+pub(crate) static PEER_STATE_REQUESTS_THROTTLED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_peer_state_requests_throttled_total",
+        "Number of state sync requests dropped because the peer exceeded its rate limit.",
+    )
+    .unwrap()
+});
+
 pub(crate) static PEER_UNRELIABLE: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge(
         "near_peer_unreliable",
@@ -457,7 +475,7 @@ impl MessageDropped {
         self.inc_msg_type("unknown")
     }
 
-    fn inc_msg_type(self, msg_type: &str) {
+    pub fn inc_msg_type(self, msg_type: &str) {
         let reason = self.as_ref();
         DROPPED_MESSAGE_COUNT.with_label_values(&[msg_type, reason]).inc();
     }
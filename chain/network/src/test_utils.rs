@@ -1,7 +1,8 @@
 use crate::network_protocol::PeerInfo;
+use crate::state_witness::{DistributeStateWitnessRequest, SendChunkEndorsementsRequest};
 use crate::types::{
     NetworkInfo, NetworkResponses, PeerManagerMessageRequest, PeerManagerMessageResponse,
-    SetChainInfo,
+    SetChainInfo, SetValidatorSigner,
 };
 use crate::PeerManagerActor;
 use actix::{Actor, ActorContext, Context, Handler};
@@ -259,6 +260,22 @@ impl CanSend<SetChainInfo> for MockPeerManagerAdapter {
     fn send(&self, _msg: SetChainInfo) {}
 }
 
+impl CanSend<SetValidatorSigner> for MockPeerManagerAdapter {
+    fn send(&self, _msg: SetValidatorSigner) {}
+}
+
+impl CanSend<DistributeStateWitnessRequest> for MockPeerManagerAdapter {
+    fn send(&self, msg: DistributeStateWitnessRequest) {
+        self.send(PeerManagerMessageRequest::NetworkRequests(msg.into()));
+    }
+}
+
+impl CanSend<SendChunkEndorsementsRequest> for MockPeerManagerAdapter {
+    fn send(&self, msg: SendChunkEndorsementsRequest) {
+        self.send(PeerManagerMessageRequest::NetworkRequests(msg.into()));
+    }
+}
+
 impl MockPeerManagerAdapter {
     pub fn pop(&self) -> Option<PeerManagerMessageRequest> {
         self.requests.write().unwrap().pop_front()
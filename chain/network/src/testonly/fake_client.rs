@@ -7,6 +7,8 @@ use near_async::messaging;
 use near_primitives::block::{Approval, Block, BlockHeader};
 use near_primitives::challenge::Challenge;
 use near_primitives::chunk_validation::{ChunkEndorsement, ChunkStateWitness};
+#[cfg(feature = "new_epoch_sync")]
+use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::sharding::{ChunkHash, PartialEncodedChunkPart};
@@ -29,6 +31,9 @@ pub enum Event {
     ChunkEndorsement(ChunkEndorsement),
     ChunkRequest(ChunkHash),
     ChunkStateWitness(ChunkStateWitness),
+    ChunkStateWitnessRequest(ChunkHash),
+    #[cfg(feature = "new_epoch_sync")]
+    EpochSyncRequest(EpochId),
     Transaction(SignedTransaction),
 }
 
@@ -126,9 +131,32 @@ impl client::Client for Fake {
         self.event_sink.push(Event::ChunkStateWitness(witness));
     }
 
+    async fn chunk_state_witness_request(
+        &self,
+        chunk_hash: ChunkHash,
+    ) -> Option<ChunkStateWitness> {
+        self.event_sink.push(Event::ChunkStateWitnessRequest(chunk_hash));
+        None
+    }
+
     async fn chunk_endorsement(&self, endorsement: ChunkEndorsement) {
         self.event_sink.push(Event::ChunkEndorsement(endorsement));
     }
+
+    #[cfg(feature = "new_epoch_sync")]
+    async fn epoch_sync_request(&self, epoch_id: EpochId) -> Option<Box<EpochSyncInfo>> {
+        self.event_sink.push(Event::EpochSyncRequest(epoch_id));
+        None
+    }
+
+    #[cfg(feature = "new_epoch_sync")]
+    async fn epoch_sync_response(
+        &self,
+        _peer_id: PeerId,
+        _epoch_id: EpochId,
+        _epoch_sync_info: Option<Box<EpochSyncInfo>>,
+    ) {
+    }
 }
 
 impl messaging::CanSend<ShardsManagerRequestFromNetwork> for Fake {
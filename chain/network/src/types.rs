@@ -11,6 +11,7 @@ pub use crate::network_protocol::{
 };
 use crate::routing::routing_table_view::RoutingTableInfo;
 pub use crate::state_sync::{StateSync, StateSyncResponse};
+use crate::state_witness::{DistributeStateWitnessRequest, SendChunkEndorsementsRequest};
 use near_async::messaging::{
     AsyncSender, CanSend, CanSendAsync, IntoAsyncSender, IntoSender, Sender,
 };
@@ -21,9 +22,12 @@ use near_primitives::challenge::Challenge;
 use near_primitives::chunk_validation::{ChunkEndorsement, ChunkStateWitness};
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
-use near_primitives::sharding::PartialEncodedChunkWithArcReceipts;
+use near_primitives::sharding::{ChunkHash, PartialEncodedChunkWithArcReceipts};
 use near_primitives::transaction::SignedTransaction;
+#[cfg(feature = "new_epoch_sync")]
+use near_primitives::types::EpochId;
 use near_primitives::types::{AccountId, BlockHeight, EpochHeight, ShardId};
+use near_primitives::validator_signer::ValidatorSigner;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::net::SocketAddr;
@@ -69,6 +73,8 @@ pub enum ReasonForBan {
     InvalidDistanceVector = 11,
     Blacklisted = 14,
     ProvidedNotEnoughHeaders = 15,
+    BadStatePart = 16,
+    BadEpochSyncInfo = 17,
 }
 
 /// Banning signal sent from Peer instance to PeerManager
@@ -156,6 +162,12 @@ pub struct ChainInfo {
 #[rtype(result = "()")]
 pub struct SetChainInfo(pub ChainInfo);
 
+/// Switches to a reloaded signer of the validator of this node, or to none if
+/// the validator key was removed.
+#[derive(actix::Message)]
+#[rtype(result = "()")]
+pub struct SetValidatorSigner(pub Option<Arc<dyn ValidatorSigner>>);
+
 /// Public actix interface of `PeerManagerActor`.
 #[derive(actix::Message, Debug, strum::IntoStaticStr)]
 #[rtype(result = "PeerManagerMessageResponse")]
@@ -261,6 +273,16 @@ pub enum NetworkRequests {
     ChunkStateWitness(Vec<AccountId>, ChunkStateWitness),
     /// Message for a chunk endorsement, sent by a chunk validator to the block producer.
     ChunkEndorsement(AccountId, ChunkEndorsement),
+    /// Several chunk endorsements sent by a chunk validator to the same block
+    /// producer in a single message.
+    ChunkEndorsementBatch(AccountId, Vec<ChunkEndorsement>),
+    /// Request for the state witness of a chunk, sent by a chunk validator to
+    /// the chunk producer if the witness didn't arrive in time.
+    ChunkStateWitnessRequest(AccountId, ChunkHash),
+    /// Request for the `EpochSyncInfo` of an epoch, sent by Epoch Sync to a
+    /// peer at the highest height.
+    #[cfg(feature = "new_epoch_sync")]
+    EpochSyncRequest { peer_id: PeerId, epoch_id: EpochId },
 }
 
 /// Combines peer address info, chain.
@@ -361,6 +383,8 @@ pub struct NetworkInfo {
     pub tier1_accounts_data: Vec<Arc<SignedAccountData>>,
     /// TIER1 connections.
     pub tier1_connections: Vec<ConnectedPeerInfo>,
+    /// Known nodes serving state snapshots, to request state parts from.
+    pub snapshot_hosts: Vec<Arc<SnapshotHostInfo>>,
 }
 
 #[derive(Debug, actix::MessageResponse, PartialEq, Eq)]
@@ -375,12 +399,18 @@ pub struct PeerManagerAdapter {
         AsyncSender<PeerManagerMessageRequest, Result<PeerManagerMessageResponse, ()>>,
     pub request_sender: Sender<PeerManagerMessageRequest>,
     pub set_chain_info_sender: Sender<SetChainInfo>,
+    pub set_validator_signer_sender: Sender<SetValidatorSigner>,
+    pub distribute_state_witness_sender: Sender<DistributeStateWitnessRequest>,
+    pub send_chunk_endorsements_sender: Sender<SendChunkEndorsementsRequest>,
 }
 
 impl<
         A: CanSendAsync<PeerManagerMessageRequest, Result<PeerManagerMessageResponse, ()>>
             + CanSend<PeerManagerMessageRequest>
-            + CanSend<SetChainInfo>,
+            + CanSend<SetChainInfo>
+            + CanSend<SetValidatorSigner>
+            + CanSend<DistributeStateWitnessRequest>
+            + CanSend<SendChunkEndorsementsRequest>,
     > From<Arc<A>> for PeerManagerAdapter
 {
     fn from(arc: Arc<A>) -> Self {
@@ -388,6 +418,9 @@ impl<
             async_request_sender: arc.as_async_sender(),
             request_sender: arc.as_sender(),
             set_chain_info_sender: arc.as_sender(),
+            set_validator_signer_sender: arc.as_sender(),
+            distribute_state_witness_sender: arc.as_sender(),
+            send_chunk_endorsements_sender: arc.as_sender(),
         }
     }
 }
@@ -1,14 +1,15 @@
+use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{hash_map, BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
 
 use crate::types::{PoolIterator, PoolKey, TransactionGroup};
 
 use near_crypto::PublicKey;
-use near_o11y::metrics::prometheus::core::{AtomicI64, GenericGauge};
+use near_o11y::metrics::prometheus::core::{AtomicI64, AtomicU64, GenericCounter, GenericGauge};
 use near_primitives::epoch_manager::RngSeed;
 use near_primitives::hash::{hash, CryptoHash};
-use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::AccountId;
+use near_primitives::transaction::{Action, SignedTransaction};
+use near_primitives::types::{AccountId, Gas, Nonce};
 use std::ops::Bound;
 
 mod metrics;
@@ -22,6 +23,75 @@ pub enum InsertTransactionResult {
     Duplicate,
     /// Not enough space to fit the transaction.
     NoSpaceLeft,
+    /// The signer of the transaction reached its limits in the pool.
+    AccountLimitReached,
+}
+
+/// Limits of the transactions kept in a transaction pool.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TransactionPoolLimits {
+    /// Total size of the transactions in bytes. When a new transaction doesn't fit, the
+    /// transactions with a lower priority than it are evicted to make room for it, see
+    /// `transaction_priority`. If that's not enough, it is rejected.
+    pub total_size: Option<u64>,
+    /// Number of transactions of a single signer account.
+    pub account_count: Option<usize>,
+    /// Total size of the transactions of a single signer account in bytes.
+    pub account_size: Option<u64>,
+    /// Number of transactions of a single signer account held back until the transactions with
+    /// the nonces before them arrive, see `TransactionPool::future_transactions`. If not set,
+    /// transactions are never held back.
+    pub account_future_count: Option<usize>,
+}
+
+/// Number of pool iterations, i.e. of chunks produced from the pool, after which a transaction
+/// held back for missing nonces is released anyway, as the nonces may never arrive.
+const MAX_FUTURE_TRANSACTION_ITERATIONS: u64 = 3;
+
+/// What the pool remembers about each of its transactions to evict them and to enforce the
+/// limits of their signers.
+struct TransactionInfo {
+    key: PoolKey,
+    signer_id: AccountId,
+    nonce: Nonce,
+    priority: Gas,
+    size: u64,
+    /// Whether the transaction is held back in `future_transactions`.
+    future: bool,
+}
+
+/// A transaction held back until the transactions with the nonces before it arrive.
+struct FutureTransaction {
+    transaction: SignedTransaction,
+    /// Value of `TransactionPool::num_iterations` when the transaction was held back.
+    held_at: u64,
+}
+
+/// Number and total size of the transactions of an account in the pool.
+#[derive(Clone, Copy, Default)]
+struct AccountUsage {
+    count: usize,
+    size: u64,
+    /// Number of the transactions which are held back for missing nonces.
+    future_count: usize,
+}
+
+/// Order in which the pool iterator returns the transaction groups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolOrder {
+    /// Round robin over the groups, in an order randomized by the key seed of the pool, starting
+    /// after the group used last.
+    RoundRobin,
+    /// Groups whose next transaction has the highest priority first, see `transaction_priority`.
+    /// The groups of a signer are returned at most `max_transactions_per_account` times, so that
+    /// a single account can't fill a chunk with high priority transactions.
+    Priority { max_transactions_per_account: usize },
+}
+
+/// Returns the priority of a transaction for `PoolOrder::Priority`: the gas attached to its
+/// function calls, which the signer pays for upfront.
+pub fn transaction_priority(transaction: &SignedTransaction) -> Gas {
+    transaction.transaction.actions.iter().map(Action::get_prepaid_gas).fold(0, Gas::saturating_add)
 }
 
 /// Transaction pool: keeps track of transactions that were not yet accepted into the block chain.
@@ -30,44 +100,63 @@ pub struct TransactionPool {
     /// NOTE: It's more efficient on average to keep transactions unsorted and with potentially
     /// conflicting nonce than to create a BTreeMap for every transaction.
     transactions: BTreeMap<PoolKey, Vec<SignedTransaction>>,
-    /// Set of all hashes to quickly check if the given transaction is in the pool.
-    unique_transactions: HashSet<CryptoHash>,
+    /// All hashes to quickly check if the given transaction is in the pool.
+    unique_transactions: HashMap<CryptoHash, TransactionInfo>,
+    /// Transactions by increasing priority, to find the ones to evict.
+    priorities: BTreeSet<(Gas, CryptoHash)>,
+    /// Number and size of the transactions of each signer.
+    account_usage: HashMap<AccountId, AccountUsage>,
+    /// Transactions whose nonce is beyond the next nonce of their group, by group and nonce.
+    /// They are not returned by the pool iterator, as including them first would make the
+    /// transactions with the missing nonces invalid, until the missing nonces arrive or get
+    /// included in a chunk, or `MAX_FUTURE_TRANSACTION_ITERATIONS` pass.
+    future_transactions: HashMap<PoolKey, BTreeMap<Nonce, FutureTransaction>>,
+    /// The nonce following the highest nonce of the transactions of each group which are in the
+    /// pool or were included in a chunk while the group was in the pool.
+    next_nonces: HashMap<PoolKey, Nonce>,
+    /// Number of pool iterators created, to release the transactions held back for too long.
+    num_iterations: u64,
     /// A uniquely generated key seed to randomize PoolKey order.
     key_seed: RngSeed,
     /// The key after which the pool iterator starts. Doesn't have to be present in the pool.
     last_used_key: PoolKey,
-    /// If set, new transactions that bring the size of the pool over this limit will be rejected.
-    total_transaction_size_limit: Option<u64>,
+    limits: TransactionPoolLimits,
     /// Total size of transactions in the pool measured in bytes.
     total_transaction_size: u64,
     /// Metrics tracked for transaction pool.
     transaction_pool_count_metric: GenericGauge<AtomicI64>,
     transaction_pool_size_metric: GenericGauge<AtomicI64>,
+    transaction_pool_evictions_metric: GenericCounter<AtomicU64>,
 }
 
 impl TransactionPool {
-    pub fn new(
-        key_seed: RngSeed,
-        total_transaction_size_limit: Option<u64>,
-        metrics_label: &str,
-    ) -> Self {
+    pub fn new(key_seed: RngSeed, limits: TransactionPoolLimits, metrics_label: &str) -> Self {
         let transaction_pool_count_metric =
             metrics::TRANSACTION_POOL_COUNT.with_label_values(&[metrics_label]);
         let transaction_pool_size_metric =
             metrics::TRANSACTION_POOL_SIZE.with_label_values(&[metrics_label]);
+        let transaction_pool_evictions_metric =
+            metrics::TRANSACTION_POOL_EVICTIONS.with_label_values(&[metrics_label]);
         // A `get()` call initializes a metric even if its value is zero.
         transaction_pool_count_metric.get();
         transaction_pool_size_metric.get();
+        transaction_pool_evictions_metric.get();
 
         Self {
             key_seed,
             transactions: BTreeMap::new(),
-            unique_transactions: HashSet::new(),
+            unique_transactions: HashMap::new(),
+            priorities: BTreeSet::new(),
+            account_usage: HashMap::new(),
+            future_transactions: HashMap::new(),
+            next_nonces: HashMap::new(),
+            num_iterations: 0,
             last_used_key: CryptoHash::default(),
-            total_transaction_size_limit,
+            limits,
             total_transaction_size: 0,
             transaction_pool_count_metric,
             transaction_pool_size_metric,
+            transaction_pool_evictions_metric,
         }
     }
 
@@ -78,37 +167,82 @@ impl TransactionPool {
         hash(&v)
     }
 
-    /// Inserts a signed transaction that passed validation into the pool.
+    /// Inserts a signed transaction that passed validation into the pool. `access_key_nonce` is
+    /// the nonce of the access key of the transaction in the state, if known, so that the first
+    /// transaction of a group which skips nonces is held back as well.
     #[must_use]
     pub fn insert_transaction(
         &mut self,
         signed_transaction: SignedTransaction,
+        access_key_nonce: Option<Nonce>,
     ) -> InsertTransactionResult {
-        if !self.unique_transactions.insert(signed_transaction.get_hash()) {
+        let hash = signed_transaction.get_hash();
+        if self.unique_transactions.contains_key(&hash) {
             // The hash of this transaction was already seen, skip it.
             return InsertTransactionResult::Duplicate;
         }
+        let size = signed_transaction.get_size();
+        let signer_id = &signed_transaction.transaction.signer_id;
+        let key = self.key(signer_id, &signed_transaction.transaction.public_key);
+        let nonce = signed_transaction.transaction.nonce;
+        let next_nonce = match (self.next_nonces.get(&key), access_key_nonce) {
+            (Some(next_nonce), Some(access_key_nonce)) => {
+                Some((*next_nonce).max(access_key_nonce.saturating_add(1)))
+            }
+            (Some(next_nonce), None) => Some(*next_nonce),
+            (None, access_key_nonce) => access_key_nonce.map(|nonce| nonce.saturating_add(1)),
+        };
+        // Only one transaction per nonce is held back, the others are left to fail validation.
+        let future = self.limits.account_future_count.is_some()
+            && next_nonce.is_some_and(|next_nonce| nonce > next_nonce)
+            && !self.future_transactions.get(&key).is_some_and(|held| held.contains_key(&nonce));
+        let usage = self.account_usage.get(signer_id).copied().unwrap_or_default();
+        if self.limits.account_count.is_some_and(|limit| usage.count >= limit)
+            || self.limits.account_size.is_some_and(|limit| usage.size + size > limit)
+            || (future
+                && self
+                    .limits
+                    .account_future_count
+                    .is_some_and(|limit| usage.future_count >= limit))
+        {
+            return InsertTransactionResult::AccountLimitReached;
+        }
+        let priority = transaction_priority(&signed_transaction);
+        let Some(evicted) = self.transactions_to_evict(size, priority) else {
+            return InsertTransactionResult::NoSpaceLeft;
+        };
+        for hash in evicted {
+            self.evict_transaction(&hash);
+        }
+
+        // At this point transaction is accepted to the pool.
         // We never expect the total size to go over `u64` during real operation as that would
         // be more than 10^9 GiB of RAM consumed for transaction pool, so panicing here is intended
         // to catch a logic error in estimation of transaction size.
-        let new_total_transaction_size = self
+        self.total_transaction_size = self
             .total_transaction_size
-            .checked_add(signed_transaction.get_size())
+            .checked_add(size)
             .expect("Total transaction size is too large");
-        if let Some(limit) = self.total_transaction_size_limit {
-            if new_total_transaction_size > limit {
-                return InsertTransactionResult::NoSpaceLeft;
-            }
+        let usage = self.account_usage.entry(signer_id.clone()).or_default();
+        usage.count += 1;
+        usage.size += size;
+        if future {
+            usage.future_count += 1;
+        }
+        self.priorities.insert((priority, hash));
+        self.unique_transactions.insert(
+            hash,
+            TransactionInfo { key, signer_id: signer_id.clone(), nonce, priority, size, future },
+        );
+        if future {
+            self.future_transactions.entry(key).or_default().insert(
+                nonce,
+                FutureTransaction { transaction: signed_transaction, held_at: self.num_iterations },
+            );
+        } else {
+            self.transactions.entry(key).or_insert_with(Vec::new).push(signed_transaction);
+            self.update_next_nonce(key, nonce);
         }
-
-        // At this point transaction is accepted to the pool.
-        self.total_transaction_size = new_total_transaction_size;
-        let signer_id = &signed_transaction.transaction.signer_id;
-        let signer_public_key = &signed_transaction.transaction.public_key;
-        self.transactions
-            .entry(self.key(signer_id, signer_public_key))
-            .or_insert_with(Vec::new)
-            .push(signed_transaction);
 
         self.transaction_pool_count_metric.inc();
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
@@ -122,6 +256,11 @@ impl TransactionPool {
         PoolIteratorWrapper::new(self)
     }
 
+    /// Same as `pool_iterator`, but iterates over transaction groups in the given order.
+    pub fn pool_iterator_with_order(&mut self, order: PoolOrder) -> PoolIteratorWrapper<'_> {
+        PoolIteratorWrapper::with_order(self, order)
+    }
+
     /// Removes given transactions from the pool.
     ///
     /// In practice, used to evict transactions that have already been included into the block or
@@ -129,17 +268,21 @@ impl TransactionPool {
     pub fn remove_transactions(&mut self, transactions: &[SignedTransaction]) {
         let mut grouped_transactions = HashMap::new();
         for tx in transactions {
+            let key = self.key(&tx.transaction.signer_id, &tx.transaction.public_key);
+            // The transactions held back for the nonce of an included transaction don't have to
+            // wait for it anymore, even if this node never received it.
+            if self.next_nonces.contains_key(&key) {
+                self.update_next_nonce(key, tx.transaction.nonce);
+            }
             // If transaction is not present in the pool, skip it.
-            if !self.unique_transactions.remove(&tx.get_hash()) {
+            let Some(info) = self.forget_transaction(&tx.get_hash()) else {
+                continue;
+            };
+            if info.future {
+                self.remove_future_transaction(&info);
                 continue;
             }
-
-            let signer_id = &tx.transaction.signer_id;
-            let signer_public_key = &tx.transaction.public_key;
-            grouped_transactions
-                .entry(self.key(signer_id, signer_public_key))
-                .or_insert_with(HashSet::new)
-                .insert(tx.get_hash());
+            grouped_transactions.entry(key).or_insert_with(HashSet::new).insert(tx.get_hash());
         }
         for (key, hashes) in grouped_transactions {
             if let Entry::Occupied(mut entry) = self.transactions.entry(key) {
@@ -166,6 +309,168 @@ impl TransactionPool {
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
     }
 
+    /// Returns the lowest priority transactions to evict to fit a new transaction of the given
+    /// size and priority in the total size limit, or `None` if evicting the transactions with a
+    /// lower priority isn't enough.
+    fn transactions_to_evict(&self, size: u64, priority: Gas) -> Option<Vec<CryptoHash>> {
+        let Some(limit) = self.limits.total_size else {
+            return Some(vec![]);
+        };
+        let mut evicted = vec![];
+        let mut total_size = self.total_transaction_size;
+        let mut candidates = self.priorities.iter();
+        while total_size.saturating_add(size) > limit {
+            let (evicted_priority, hash) = candidates.next()?;
+            if *evicted_priority >= priority {
+                return None;
+            }
+            total_size -= self.unique_transactions[hash].size;
+            evicted.push(*hash);
+        }
+        Some(evicted)
+    }
+
+    fn evict_transaction(&mut self, hash: &CryptoHash) {
+        let Some(info) = self.forget_transaction(hash) else {
+            return;
+        };
+        if info.future {
+            self.remove_future_transaction(&info);
+        } else {
+            if let Entry::Occupied(mut entry) = self.transactions.entry(info.key) {
+                entry.get_mut().retain(|tx| tx.get_hash() != *hash);
+                if entry.get().is_empty() {
+                    entry.remove_entry();
+                }
+            }
+            // See the comment in `insert_transaction` where we increase the size for reasoning
+            // why panicing here catches a logic error.
+            self.total_transaction_size = self
+                .total_transaction_size
+                .checked_sub(info.size)
+                .expect("Total transaction size dropped below zero");
+        }
+        self.transaction_pool_evictions_metric.inc();
+    }
+
+    /// Removes a forgotten transaction from `future_transactions`.
+    fn remove_future_transaction(&mut self, info: &TransactionInfo) {
+        if let hash_map::Entry::Occupied(mut entry) = self.future_transactions.entry(info.key) {
+            entry.get_mut().remove(&info.nonce);
+            if entry.get().is_empty() {
+                entry.remove_entry();
+            }
+        }
+        // See the comment in `insert_transaction` where we increase the size for reasoning why
+        // panicing here catches a logic error.
+        self.total_transaction_size = self
+            .total_transaction_size
+            .checked_sub(info.size)
+            .expect("Total transaction size dropped below zero");
+    }
+
+    /// Records that the group has a transaction with the given nonce in the pool or included in
+    /// a chunk, and releases the transactions of the group which no longer miss nonces.
+    fn update_next_nonce(&mut self, key: PoolKey, nonce: Nonce) {
+        let next_nonce = self.next_nonces.entry(key).or_default();
+        *next_nonce = (*next_nonce).max(nonce.saturating_add(1));
+        let mut next_nonce = *next_nonce;
+        let mut released = vec![];
+        if let hash_map::Entry::Occupied(mut entry) = self.future_transactions.entry(key) {
+            while let Some(first) = entry.get_mut().first_entry() {
+                if *first.key() > next_nonce {
+                    break;
+                }
+                next_nonce = next_nonce.max(first.key().saturating_add(1));
+                released.push(first.remove().transaction);
+            }
+            if entry.get().is_empty() {
+                entry.remove_entry();
+            }
+        }
+        self.next_nonces.insert(key, next_nonce);
+        self.release_future_transactions(key, released);
+    }
+
+    /// Moves transactions of the group from `future_transactions` to the transactions returned
+    /// by the pool iterator.
+    fn release_future_transactions(&mut self, key: PoolKey, transactions: Vec<SignedTransaction>) {
+        for tx in transactions {
+            if let Some(info) = self.unique_transactions.get_mut(&tx.get_hash()) {
+                info.future = false;
+                if let Some(usage) = self.account_usage.get_mut(&info.signer_id) {
+                    usage.future_count -= 1;
+                }
+            }
+            self.transactions.entry(key).or_insert_with(Vec::new).push(tx);
+        }
+    }
+
+    /// Releases the transactions held back for `MAX_FUTURE_TRANSACTION_ITERATIONS`, and forgets
+    /// the next nonces of the groups which have no transactions in the pool anymore.
+    fn release_stale_future_transactions(&mut self) {
+        if let Some(held_at) = self.num_iterations.checked_sub(MAX_FUTURE_TRANSACTION_ITERATIONS) {
+            self.release_future_transactions_held_since(held_at);
+        }
+        let Self { next_nonces, transactions, future_transactions, .. } = self;
+        next_nonces.retain(|key, _| {
+            transactions.contains_key(key) || future_transactions.contains_key(key)
+        });
+    }
+
+    /// Releases all transactions held back for missing nonces, e.g. to move them to another pool.
+    pub fn release_all_future_transactions(&mut self) {
+        self.release_future_transactions_held_since(u64::MAX);
+    }
+
+    /// Releases the transactions held back at or before the given iteration.
+    fn release_future_transactions_held_since(&mut self, held_at: u64) {
+        let stale: Vec<(PoolKey, Nonce)> = self
+            .future_transactions
+            .iter()
+            .flat_map(|(key, transactions)| {
+                transactions
+                    .iter()
+                    .filter(|(_, tx)| tx.held_at <= held_at)
+                    .map(|(nonce, _)| (*key, *nonce))
+            })
+            .collect();
+        for (key, nonce) in stale {
+            let hash_map::Entry::Occupied(mut entry) = self.future_transactions.entry(key) else {
+                continue;
+            };
+            // Releasing a transaction may have released the following ones already.
+            let Some(tx) = entry.get_mut().remove(&nonce) else {
+                continue;
+            };
+            if entry.get().is_empty() {
+                entry.remove_entry();
+            }
+            self.release_future_transactions(key, vec![tx.transaction]);
+            self.update_next_nonce(key, nonce);
+        }
+    }
+
+    /// Removes the transaction from the indices of the pool, but not from its group, and returns
+    /// what the pool knew about it.
+    fn forget_transaction(&mut self, hash: &CryptoHash) -> Option<TransactionInfo> {
+        let info = self.unique_transactions.remove(hash)?;
+        self.priorities.remove(&(info.priority, *hash));
+        if let hash_map::Entry::Occupied(mut usage) =
+            self.account_usage.entry(info.signer_id.clone())
+        {
+            usage.get_mut().count -= 1;
+            usage.get_mut().size -= info.size;
+            if info.future {
+                usage.get_mut().future_count -= 1;
+            }
+            if usage.get().count == 0 {
+                usage.remove_entry();
+            }
+        }
+        Some(info)
+    }
+
     /// Returns the number of unique transactions in the pool.
     pub fn len(&self) -> usize {
         self.unique_transactions.len()
@@ -175,6 +480,37 @@ impl TransactionPool {
     pub fn transaction_size(&self) -> u64 {
         self.total_transaction_size
     }
+
+    /// Returns the number of transaction groups in the pool, i.e. the number of transactions
+    /// `PoolOrder::RoundRobin` takes in one round over the pool.
+    pub fn num_groups(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Returns the transactions of the given signer in the pool, except the ones held back for
+    /// missing nonces, by increasing nonce.
+    pub fn account_transactions(&self, account_id: &AccountId) -> Vec<&SignedTransaction> {
+        if !self.account_usage.contains_key(account_id) {
+            return vec![];
+        }
+        let keys: BTreeSet<PoolKey> = self
+            .unique_transactions
+            .values()
+            .filter(|info| &info.signer_id == account_id)
+            .map(|info| info.key)
+            .collect();
+        let mut transactions: Vec<_> =
+            keys.iter().filter_map(|key| self.transactions.get(key)).flatten().collect();
+        transactions.sort_by_key(|tx| tx.transaction.nonce);
+        transactions
+    }
+
+    /// Returns the number of transactions in the pool which `PoolOrder::Priority` prefers over
+    /// the given one.
+    pub fn num_transactions_with_higher_priority(&self, transaction: &SignedTransaction) -> usize {
+        let key = (transaction_priority(transaction), transaction.get_hash());
+        self.priorities.range((Bound::Excluded(key), Bound::Unbounded)).count()
+    }
 }
 
 /// PoolIterator is a structure to pull transactions from the pool.
@@ -186,11 +522,90 @@ pub struct PoolIteratorWrapper<'a> {
 
     /// Queue of transaction groups. Each group there is sorted by nonce.
     sorted_groups: VecDeque<TransactionGroup>,
+
+    /// Order in which the groups are returned.
+    order: PoolOrder,
+    /// For `PoolOrder::Priority`, the non-empty groups of `sorted_groups` by decreasing priority
+    /// of their next transaction, identified by their index. Groups with equal priorities are
+    /// ordered by key. Initialized on the first `next()` call.
+    priority_queue: Option<BinaryHeap<(Gas, Reverse<PoolKey>, usize)>>,
+    /// For `PoolOrder::Priority`, the index of the group returned last, which is queued again on
+    /// the next `next()` call as the priority of its next transaction is only known then.
+    last_prioritized_group: Option<usize>,
+    /// For `PoolOrder::Priority`, the number of times groups of each signer were returned.
+    transactions_per_account: HashMap<AccountId, usize>,
 }
 
 impl<'a> PoolIteratorWrapper<'a> {
     pub fn new(pool: &'a mut TransactionPool) -> Self {
-        Self { pool, sorted_groups: Default::default() }
+        Self::with_order(pool, PoolOrder::RoundRobin)
+    }
+
+    pub fn with_order(pool: &'a mut TransactionPool, order: PoolOrder) -> Self {
+        pool.num_iterations += 1;
+        pool.release_stale_future_transactions();
+        Self {
+            pool,
+            sorted_groups: Default::default(),
+            order,
+            priority_queue: None,
+            last_prioritized_group: None,
+            transactions_per_account: HashMap::new(),
+        }
+    }
+
+    /// Returns the group whose next transaction has the highest priority, skipping the groups
+    /// of signers which reached the limit.
+    ///
+    /// All groups are taken out of the pool on the first call to compare their priorities. They
+    /// are returned to the pool when the iterator is dropped, like for the round robin order.
+    fn next_by_priority(
+        &mut self,
+        max_transactions_per_account: usize,
+    ) -> Option<&mut TransactionGroup> {
+        if self.priority_queue.is_none() {
+            let mut queue = BinaryHeap::new();
+            for (key, mut transactions) in std::mem::take(&mut self.pool.transactions) {
+                transactions.sort_by_key(|st| Reverse(st.transaction.nonce));
+                let next_transaction =
+                    transactions.last().expect("groups in the pool are not empty");
+                queue.push((
+                    transaction_priority(next_transaction),
+                    Reverse(key),
+                    self.sorted_groups.len(),
+                ));
+                self.sorted_groups.push_back(TransactionGroup {
+                    key,
+                    transactions,
+                    removed_transaction_hashes: vec![],
+                    removed_transaction_size: 0,
+                });
+            }
+            self.priority_queue = Some(queue);
+        }
+        let queue = self.priority_queue.as_mut().expect("just initialized");
+        if let Some(index) = self.last_prioritized_group.take() {
+            let group = &self.sorted_groups[index];
+            if let Some(next_transaction) = group.transactions.last() {
+                queue.push((transaction_priority(next_transaction), Reverse(group.key), index));
+            }
+        }
+        while let Some((_, _, index)) = queue.pop() {
+            let signer_id = &self.sorted_groups[index]
+                .transactions
+                .last()
+                .expect("only non-empty groups are queued")
+                .transaction
+                .signer_id;
+            let count = self.transactions_per_account.entry(signer_id.clone()).or_default();
+            if *count >= max_transactions_per_account {
+                continue;
+            }
+            *count += 1;
+            self.last_prioritized_group = Some(index);
+            return Some(&mut self.sorted_groups[index]);
+        }
+        None
     }
 }
 
@@ -213,6 +628,9 @@ impl<'a> PoolIteratorWrapper<'a> {
 /// And all non-empty group from the sorted groups queue are inserted back into the pool.
 impl<'a> PoolIterator for PoolIteratorWrapper<'a> {
     fn next(&mut self) -> Option<&mut TransactionGroup> {
+        if let PoolOrder::Priority { max_transactions_per_account } = self.order {
+            return self.next_by_priority(max_transactions_per_account);
+        }
         if !self.pool.transactions.is_empty() {
             let key = *self
                 .pool
@@ -242,7 +660,7 @@ impl<'a> PoolIterator for PoolIteratorWrapper<'a> {
             while let Some(sorted_group) = self.sorted_groups.pop_front() {
                 if sorted_group.transactions.is_empty() {
                     for hash in sorted_group.removed_transaction_hashes {
-                        self.pool.unique_transactions.remove(&hash);
+                        self.pool.forget_transaction(&hash);
                     }
                     // See the comment in `insert_transaction` where we increase the size for reasoning
                     // why panicing here catches a logic error.
@@ -273,7 +691,7 @@ impl<'a> Drop for PoolIteratorWrapper<'a> {
     fn drop(&mut self) {
         for group in self.sorted_groups.drain(..) {
             for hash in group.removed_transaction_hashes {
-                self.pool.unique_transactions.remove(&hash);
+                self.pool.forget_transaction(&hash);
             }
             // See the comment in `insert_transaction` where we increase the size for reasoning
             // why panicing here catches a logic error.
@@ -335,11 +753,11 @@ mod tests {
         mut transactions: Vec<SignedTransaction>,
         expected_weight: u32,
     ) -> (Vec<u64>, TransactionPool) {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, TransactionPoolLimits::default(), "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions {
-            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+            assert_eq!(pool.insert_transaction(tx, None), InsertTransactionResult::Success);
         }
         (
             prepare_transactions(&mut pool, expected_weight)
@@ -446,12 +864,12 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, TransactionPoolLimits::default(), "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions.clone() {
             println!("{:?}", tx);
-            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+            assert_eq!(pool.insert_transaction(tx, None), InsertTransactionResult::Success);
         }
         assert_eq!(pool.len(), n as usize);
 
@@ -507,7 +925,7 @@ mod tests {
 
         for tx in transactions {
             assert!(matches!(
-                pool.insert_transaction(tx),
+                pool.insert_transaction(tx, None),
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate
             ));
         }
@@ -546,7 +964,7 @@ mod tests {
 
         for tx in transactions {
             assert!(matches!(
-                pool.insert_transaction(tx),
+                pool.insert_transaction(tx, None),
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate
             ));
         }
@@ -559,15 +977,91 @@ mod tests {
         assert_ne!(nonces, new_nonces);
     }
 
+    fn call(signer_id: &str, nonce: u64, gas: Gas) -> SignedTransaction {
+        let signer_id: AccountId = signer_id.parse().unwrap();
+        let signer = InMemorySigner::from_seed(signer_id.clone(), KeyType::ED25519, "seed");
+        SignedTransaction::call(
+            nonce,
+            signer_id,
+            "bob.near".parse().unwrap(),
+            &signer,
+            0,
+            "method".to_string(),
+            vec![],
+            gas,
+            CryptoHash::default(),
+        )
+    }
+
+    /// Transactions are pulled by decreasing attached gas, in nonce order within a group, and at
+    /// most `max_transactions_per_account` times per signer.
+    #[test]
+    fn test_pool_iterator_priority_order() {
+        let mut pool = TransactionPool::new(TEST_SEED, TransactionPoolLimits::default(), "");
+        for tx in [
+            call("alice.near", 1, 10),
+            call("alice.near", 2, 40),
+            call("alice.near", 3, 50),
+            call("carol.near", 1, 30),
+            call("dave.near", 1, 20),
+            call("dave.near", 2, 100),
+        ] {
+            assert_eq!(pool.insert_transaction(tx, None), InsertTransactionResult::Success);
+        }
+
+        let mut res = vec![];
+        let mut pool_iter =
+            pool.pool_iterator_with_order(PoolOrder::Priority { max_transactions_per_account: 2 });
+        while let Some(iter) = pool_iter.next() {
+            res.push(iter.next().unwrap());
+        }
+        drop(pool_iter);
+        let res: Vec<_> = res
+            .iter()
+            .map(|tx| (tx.transaction.signer_id.as_str(), tx.transaction.nonce))
+            .collect();
+        assert_eq!(
+            res,
+            vec![
+                ("carol.near", 1),
+                ("dave.near", 1),
+                ("dave.near", 2),
+                ("alice.near", 1),
+                ("alice.near", 2),
+            ]
+        );
+        // The transaction over the limit stays in the pool.
+        assert_eq!(pool.len(), 1);
+        assert_eq!(prepare_transactions(&mut pool, 10)[0].transaction.nonce, 3);
+    }
+
+    #[test]
+    fn test_account_transactions() {
+        let mut pool = TransactionPool::new(TEST_SEED, TransactionPoolLimits::default(), "");
+        for tx in [call("alice.near", 3, 10), call("alice.near", 1, 40), call("carol.near", 1, 30)]
+        {
+            assert_eq!(pool.insert_transaction(tx, None), InsertTransactionResult::Success);
+        }
+        assert_eq!(pool.num_groups(), 2);
+
+        let transactions = pool.account_transactions(&"alice.near".parse().unwrap());
+        let nonces: Vec<_> = transactions.iter().map(|tx| tx.transaction.nonce).collect();
+        assert_eq!(nonces, vec![1, 3]);
+        let ahead: Vec<_> =
+            transactions.iter().map(|tx| pool.num_transactions_with_higher_priority(tx)).collect();
+        assert_eq!(ahead, vec![0, 2]);
+        assert!(pool.account_transactions(&"bob.near".parse().unwrap()).is_empty());
+    }
+
     #[test]
     fn test_transaction_pool_size() {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, TransactionPoolLimits::default(), "");
         let transactions = generate_transactions("alice.near", "alice.near", 1, 100);
         let mut total_transaction_size = 0;
         // Adding transactions increases the size.
         for tx in transactions.clone() {
             total_transaction_size += tx.get_size();
-            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+            assert_eq!(pool.insert_transaction(tx, None), InsertTransactionResult::Success);
             assert_eq!(pool.transaction_size(), total_transaction_size);
         }
         // Removing transactions decreases the size.
@@ -585,13 +1079,167 @@ mod tests {
         // Each transaction is at least 1 byte in size, so the last transaction will not fit.
         let pool_size_limit =
             transactions.iter().map(|tx| tx.get_size()).sum::<u64>().checked_sub(1).unwrap();
-        let mut pool = TransactionPool::new(TEST_SEED, Some(pool_size_limit), "");
+        let limits =
+            TransactionPoolLimits { total_size: Some(pool_size_limit), ..Default::default() };
+        let mut pool = TransactionPool::new(TEST_SEED, limits, "");
         for (i, tx) in transactions.iter().cloned().enumerate() {
             if i + 1 < transactions.len() {
-                assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+                assert_eq!(pool.insert_transaction(tx, None), InsertTransactionResult::Success);
             } else {
-                assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::NoSpaceLeft);
+                assert_eq!(pool.insert_transaction(tx, None), InsertTransactionResult::NoSpaceLeft);
             }
         }
     }
+
+    /// When the pool is full, transactions with a lower priority are evicted for new ones.
+    #[test]
+    fn test_transaction_pool_eviction() {
+        let size = call("aaa.near", 1, 10).get_size();
+        let limits = TransactionPoolLimits { total_size: Some(2 * size), ..Default::default() };
+        let mut pool = TransactionPool::new(TEST_SEED, limits, "");
+        assert_eq!(
+            pool.insert_transaction(call("aaa.near", 1, 10), None),
+            InsertTransactionResult::Success
+        );
+        assert_eq!(
+            pool.insert_transaction(call("bbb.near", 1, 20), None),
+            InsertTransactionResult::Success
+        );
+        assert_eq!(
+            pool.insert_transaction(call("ccc.near", 1, 10), None),
+            InsertTransactionResult::NoSpaceLeft
+        );
+        assert_eq!(
+            pool.insert_transaction(call("ddd.near", 1, 30), None),
+            InsertTransactionResult::Success
+        );
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.transaction_size(), 2 * size);
+        let mut signers: Vec<_> = prepare_transactions(&mut pool, 10)
+            .into_iter()
+            .map(|tx| tx.transaction.signer_id.to_string())
+            .collect();
+        signers.sort();
+        assert_eq!(signers, vec!["bbb.near", "ddd.near"]);
+    }
+
+    /// Transactions whose nonce is beyond the next nonce of their group are held back until the
+    /// missing nonces arrive, get included, or the pool is iterated enough times.
+    #[test]
+    fn test_future_transactions() {
+        let limits = TransactionPoolLimits { account_future_count: Some(2), ..Default::default() };
+        let mut pool = TransactionPool::new(TEST_SEED, limits, "");
+        let transactions = generate_transactions("alice.near", "alice.near", 1, 8);
+        let nonces = |transactions: Vec<SignedTransaction>| -> Vec<u64> {
+            transactions.iter().map(|tx| tx.transaction.nonce).collect()
+        };
+        for tx in [&transactions[0], &transactions[2], &transactions[3]] {
+            assert_eq!(pool.insert_transaction(tx.clone(), None), InsertTransactionResult::Success);
+        }
+        assert_eq!(
+            pool.insert_transaction(transactions[5].clone(), None),
+            InsertTransactionResult::AccountLimitReached
+        );
+        assert_eq!(pool.len(), 3);
+        assert_eq!(nonces(prepare_transactions(&mut pool, 10)), vec![1]);
+
+        // The missing nonce releases the transactions after it.
+        assert_eq!(
+            pool.insert_transaction(transactions[1].clone(), None),
+            InsertTransactionResult::Success
+        );
+        assert_eq!(nonces(prepare_transactions(&mut pool, 10)), vec![2, 3, 4]);
+
+        // So does the missing nonce included in a chunk without going through this pool.
+        assert_eq!(
+            pool.insert_transaction(transactions[5].clone(), None),
+            InsertTransactionResult::Success
+        );
+        pool.remove_transactions(&transactions[4..5]);
+        assert_eq!(nonces(prepare_transactions(&mut pool, 10)), vec![6]);
+
+        // Transactions aren't held back forever if the missing nonces never arrive.
+        assert_eq!(
+            pool.insert_transaction(transactions[7].clone(), None),
+            InsertTransactionResult::Success
+        );
+        for _ in 1..MAX_FUTURE_TRANSACTION_ITERATIONS {
+            assert!(prepare_transactions(&mut pool, 10).is_empty());
+        }
+        assert_eq!(nonces(prepare_transactions(&mut pool, 10)), vec![8]);
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.transaction_size(), 0);
+    }
+
+    /// The first transaction of a group is held back if it skips nonces after the nonce of its
+    /// access key.
+    #[test]
+    fn test_future_transactions_access_key_nonce() {
+        let limits = TransactionPoolLimits { account_future_count: Some(2), ..Default::default() };
+        let mut pool = TransactionPool::new(TEST_SEED, limits, "");
+        let transactions = generate_transactions("alice.near", "alice.near", 1, 5);
+        assert_eq!(
+            pool.insert_transaction(transactions[2].clone(), Some(1)),
+            InsertTransactionResult::Success
+        );
+        assert!(prepare_transactions(&mut pool, 10).is_empty());
+        assert_eq!(
+            pool.insert_transaction(transactions[1].clone(), Some(1)),
+            InsertTransactionResult::Success
+        );
+        let nonces: Vec<_> =
+            prepare_transactions(&mut pool, 10).iter().map(|tx| tx.transaction.nonce).collect();
+        assert_eq!(nonces, vec![2, 3]);
+
+        // The nonce of the access key may be ahead of the transactions of this pool, if another
+        // chunk producer included the transactions with the nonces in between.
+        assert_eq!(
+            pool.insert_transaction(transactions[4].clone(), Some(4)),
+            InsertTransactionResult::Success
+        );
+        assert_eq!(prepare_transactions(&mut pool, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_transaction_pool_account_limits() {
+        let limits = TransactionPoolLimits { account_count: Some(2), ..Default::default() };
+        let mut pool = TransactionPool::new(TEST_SEED, limits, "");
+        let transactions = generate_transactions("alice.near", "alice.near", 1, 3);
+        assert_eq!(
+            pool.insert_transaction(transactions[0].clone(), None),
+            InsertTransactionResult::Success
+        );
+        assert_eq!(
+            pool.insert_transaction(transactions[1].clone(), None),
+            InsertTransactionResult::Success
+        );
+        assert_eq!(
+            pool.insert_transaction(transactions[2].clone(), None),
+            InsertTransactionResult::AccountLimitReached
+        );
+        // Other accounts are not limited.
+        let other = generate_transactions("bob.near", "bob.near", 1, 1);
+        assert_eq!(
+            pool.insert_transaction(other[0].clone(), None),
+            InsertTransactionResult::Success
+        );
+        // Removed transactions don't count towards the limit.
+        pool.remove_transactions(&transactions[..1]);
+        assert_eq!(
+            pool.insert_transaction(transactions[2].clone(), None),
+            InsertTransactionResult::Success
+        );
+
+        let size = transactions[0].get_size();
+        let limits = TransactionPoolLimits { account_size: Some(2 * size), ..Default::default() };
+        let mut pool = TransactionPool::new(TEST_SEED, limits, "");
+        for (i, tx) in transactions.into_iter().enumerate() {
+            let expected = if i < 2 {
+                InsertTransactionResult::Success
+            } else {
+                InsertTransactionResult::AccountLimitReached
+            };
+            assert_eq!(pool.insert_transaction(tx, None), expected);
+        }
+    }
 }
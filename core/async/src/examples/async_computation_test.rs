@@ -0,0 +1,43 @@
+use derive_enum_from_into::{EnumFrom, EnumTryInto};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    futures::AsyncComputationSpawner,
+    test_loop::{
+        futures::{drive_async_computations, TestLoopAsyncComputationEvent},
+        TestLoopBuilder,
+    },
+    time,
+};
+
+#[derive(derive_more::AsMut, derive_more::AsRef)]
+struct TestData {
+    dummy: (), // needed for the drive_async_computations handler
+}
+
+#[derive(Debug, EnumTryInto, EnumFrom)]
+enum TestEvent {
+    AsyncComputation(TestLoopAsyncComputationEvent),
+}
+
+#[test]
+fn test_async_computation() {
+    let builder = TestLoopBuilder::<TestEvent>::new();
+    let spawner = builder.async_computation_spawner(|name| match name {
+        "slow" => time::Duration::milliseconds(100),
+        _ => time::Duration::milliseconds(10),
+    });
+    let mut test = builder.build(TestData { dummy: () });
+    test.register_handler(drive_async_computations().widen());
+
+    let completed = Arc::new(Mutex::new(vec![]));
+    let spawner: &dyn AsyncComputationSpawner = &spawner;
+    for name in ["slow", "fast"] {
+        let completed = completed.clone();
+        spawner.spawn(name, move || completed.lock().unwrap().push(name));
+    }
+    test.run_for(time::Duration::milliseconds(50));
+    assert_eq!(*completed.lock().unwrap(), vec!["fast"]);
+    test.run_for(time::Duration::milliseconds(100));
+    assert_eq!(*completed.lock().unwrap(), vec!["fast", "slow"]);
+}
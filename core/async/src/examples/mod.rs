@@ -1,5 +1,6 @@
 mod async_component;
 mod async_component_test;
+mod async_computation_test;
 mod multi_instance_test;
 mod sum_numbers;
 mod sum_numbers_test;
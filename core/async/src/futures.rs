@@ -31,3 +31,39 @@ impl FutureSpawner for ActixFutureSpawner {
         near_performance_metrics::actix::spawn(description, f);
     }
 }
+
+/// Abstraction for something that can run CPU-bound computations off the
+/// calling thread, e.g. a thread pool.
+///
+/// Like `FutureSpawner`, this exists so that the computations can be driven
+/// by the TestLoop in tests, which makes them deterministic.
+pub trait AsyncComputationSpawner: Send + Sync {
+    fn spawn_boxed(&self, name: &str, f: Box<dyn FnOnce() + Send>);
+}
+
+impl<'a> dyn AsyncComputationSpawner + 'a {
+    /// Spawns a computation by automatically boxing it.
+    pub fn spawn(&self, name: &str, f: impl FnOnce() + Send + 'static) {
+        self.spawn_boxed(name, Box::new(f))
+    }
+}
+
+/// An AsyncComputationSpawner that runs the computations on the global rayon
+/// thread pool.
+pub struct RayonAsyncComputationSpawner;
+
+impl AsyncComputationSpawner for RayonAsyncComputationSpawner {
+    fn spawn_boxed(&self, _name: &str, f: Box<dyn FnOnce() + Send>) {
+        rayon::spawn(f);
+    }
+}
+
+/// An AsyncComputationSpawner that runs the computations right away on the
+/// calling thread, so that tests see their results without waiting.
+pub struct InlineAsyncComputationSpawner;
+
+impl AsyncComputationSpawner for InlineAsyncComputationSpawner {
+    fn spawn_boxed(&self, _name: &str, f: Box<dyn FnOnce() + Send>) {
+        f();
+    }
+}
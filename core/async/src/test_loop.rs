@@ -68,7 +68,10 @@ pub mod multi_instance;
 use self::{
     delay_sender::DelaySender,
     event_handler::LoopEventHandler,
-    futures::{TestLoopFutureSpawner, TestLoopTask},
+    futures::{
+        TestLoopAsyncComputationEvent, TestLoopAsyncComputationSpawner, TestLoopFutureSpawner,
+        TestLoopTask,
+    },
 };
 use crate::test_loop::event_handler::LoopHandlerContext;
 use crate::time;
@@ -199,6 +202,18 @@ impl<Event: Debug + Send + 'static> TestLoopBuilder<Event> {
         self.sender().narrow()
     }
 
+    /// Returns an AsyncComputationSpawner that runs computations in the loop,
+    /// each after the artificial delay returned for its name.
+    pub fn async_computation_spawner(
+        &self,
+        artificial_delay: impl Fn(&str) -> time::Duration + Send + Sync + 'static,
+    ) -> TestLoopAsyncComputationSpawner
+    where
+        Event: From<TestLoopAsyncComputationEvent>,
+    {
+        TestLoopAsyncComputationSpawner::new(self.sender().narrow(), artificial_delay)
+    }
+
     pub fn build<Data>(self, data: Data) -> TestLoop<Data, Event> {
         TestLoop::new(self.pending_events, self.pending_events_sender, self.clock, data)
     }
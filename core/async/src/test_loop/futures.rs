@@ -8,7 +8,7 @@ use std::task::Context;
 use tokio::sync::oneshot;
 
 use crate::{
-    futures::FutureSpawner,
+    futures::{AsyncComputationSpawner, FutureSpawner},
     messaging::{self, CanSend},
 };
 
@@ -43,6 +43,12 @@ use super::{delay_sender::DelaySender, event_handler::LoopEventHandler};
 //      this is implemented is by implementing a conversion from
 //      DelaySender<MessageExpectingResponse<Request, Response>> to
 //      AsyncSender<Request, Response>.
+//
+//   3. A way to run CPU-bound computations in the TestLoop. To support this,
+//      add () to the Data, add TestLoopAsyncComputationEvent as an Event,
+//      and add drive_async_computations as a handler. Then pass a
+//      TestLoopAsyncComputationSpawner as the &dyn AsyncComputationSpawner
+//      to any component that spawns computations.
 
 /// A message, plus a response callback. This should be used as the event type
 /// when testing an Actix component that's expected to return a result.
@@ -131,3 +137,50 @@ impl FutureSpawner for TestLoopFutureSpawner {
         self.send(task);
     }
 }
+
+/// A computation spawned by our implementation of AsyncComputationSpawner.
+pub struct TestLoopAsyncComputationEvent {
+    name: String,
+    f: Box<dyn FnOnce() + Send>,
+}
+
+impl Debug for TestLoopAsyncComputationEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AsyncComputation").field(&self.name).finish()
+    }
+}
+
+/// Runs any TestLoopAsyncComputationEvent events (computations spawned by our
+/// implementation of AsyncComputationSpawner) that are remaining in the loop.
+pub fn drive_async_computations() -> LoopEventHandler<(), TestLoopAsyncComputationEvent> {
+    LoopEventHandler::new_simple(|event: TestLoopAsyncComputationEvent, _| {
+        (event.f)();
+    })
+}
+
+/// An AsyncComputationSpawner that sends the computations to the test loop.
+/// Each computation is delayed by the artificial delay returned for its name,
+/// so that tests can simulate slow computations, or have them complete in a
+/// different order than they were spawned.
+pub struct TestLoopAsyncComputationSpawner {
+    sender: DelaySender<TestLoopAsyncComputationEvent>,
+    artificial_delay: Box<dyn Fn(&str) -> time::Duration + Send + Sync>,
+}
+
+impl TestLoopAsyncComputationSpawner {
+    pub fn new(
+        sender: DelaySender<TestLoopAsyncComputationEvent>,
+        artificial_delay: impl Fn(&str) -> time::Duration + Send + Sync + 'static,
+    ) -> Self {
+        Self { sender, artificial_delay: Box::new(artificial_delay) }
+    }
+}
+
+impl AsyncComputationSpawner for TestLoopAsyncComputationSpawner {
+    fn spawn_boxed(&self, name: &str, f: Box<dyn FnOnce() + Send>) {
+        self.sender.send_with_delay(
+            TestLoopAsyncComputationEvent { name: name.to_string(), f },
+            (self.artificial_delay)(name),
+        );
+    }
+}
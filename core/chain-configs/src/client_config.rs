@@ -6,6 +6,7 @@ use near_primitives::types::{
 };
 use near_primitives::version::Version;
 use std::cmp::{max, min};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
@@ -31,6 +32,9 @@ pub const DEFAULT_GC_NUM_EPOCHS_TO_KEEP: u64 = 5;
 pub const DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL: u32 = 25;
 pub const DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL: u32 = 5;
 
+/// Default number of state parts of a shard requested from peers at the same time.
+pub const DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_PEERS: u32 = 512;
+
 /// Configuration for garbage collection.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
 #[serde(default)]
@@ -45,6 +49,20 @@ pub struct GCConfig {
 
     /// Number of epochs for which we keep store data.
     pub gc_num_epochs_to_keep: u64,
+
+    /// Whether to garbage collect the block headers together with the blocks,
+    /// keeping only the headers of the first and the last block of every
+    /// epoch, which light client proofs need. Such a node can't serve the
+    /// headers of old blocks to the peers doing header sync.
+    /// Not supported by archival nodes.
+    pub gc_block_headers: bool,
+
+    /// Whether to garbage collect the trie state, the flat state and the
+    /// in-memory tries of the shards the node no longer tracks, once no block
+    /// left after garbage collection belongs to an epoch in which the node
+    /// tracks them. Shards the validator of the node is assigned to are never
+    /// garbage collected.
+    pub gc_untracked_shards: bool,
 }
 
 impl Default for GCConfig {
@@ -53,6 +71,8 @@ impl Default for GCConfig {
             gc_blocks_limit: 2,
             gc_fork_clean_step: 100,
             gc_num_epochs_to_keep: DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+            gc_block_headers: false,
+            gc_untracked_shards: false,
         }
     }
 }
@@ -217,6 +237,223 @@ impl Default for ReshardingConfig {
     }
 }
 
+/// Options of chunk validators which can be changed while the node is
+/// running, by editing `config.json` and sending `SIGHUP` to `neard`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct ChunkValidatorConfig {
+    /// Chunk state witnesses larger than this are rejected without being
+    /// validated. Only lowers the limit of the protocol, never raises it.
+    pub max_witness_size: Option<bytesize::ByteSize>,
+    /// Number of threads validating chunk state witnesses. If not set, they
+    /// are validated on the shared rayon thread pool.
+    pub validation_threads: Option<usize>,
+    /// How long chunk endorsements for the same block producer are held back,
+    /// waiting for other endorsements to be sent along with them.
+    pub endorsement_batch_window: Duration,
+}
+
+impl Default for ChunkValidatorConfig {
+    fn default() -> Self {
+        Self {
+            max_witness_size: None,
+            validation_threads: None,
+            endorsement_batch_window: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Configuration for offloading chunk state witness validation to remote
+/// worker processes. Only used if the node is built with the
+/// `remote_chunk_validation` feature.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RemoteChunkValidationConfig {
+    /// gRPC endpoints of the validation workers, e.g. `http://10.0.0.2:3060`.
+    /// Witnesses are sent to the healthy workers in round-robin order.
+    pub worker_urls: Vec<String>,
+    /// How long to wait for a worker to validate a witness before validating
+    /// it locally instead.
+    #[serde(default = "default_remote_chunk_validation_timeout")]
+    pub timeout: Duration,
+    /// How often to check the health of the workers.
+    #[serde(default = "default_remote_chunk_validation_health_check_period")]
+    pub health_check_period: Duration,
+}
+
+/// Configuration of a read replica: a node which opens the database of
+/// another node running on the same machine in secondary mode and serves
+/// read-only RPC from it. A read replica doesn't connect to the network and
+/// never writes to the database.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ReadReplicaConfig {
+    /// How often to catch up with the changes written by the primary node.
+    #[serde(default = "default_read_replica_catch_up_period")]
+    pub catch_up_period: Duration,
+}
+
+pub fn default_read_replica_catch_up_period() -> Duration {
+    Duration::from_millis(500)
+}
+
+impl Default for ReadReplicaConfig {
+    fn default() -> Self {
+        Self { catch_up_period: default_read_replica_catch_up_period() }
+    }
+}
+
+/// Alerts raised when the validator of the node misses too many of the blocks
+/// or chunks it is expected to produce in an epoch. The production is checked
+/// every `log_summary_period`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ProductionAlertsConfig {
+    /// If set, alerts are POSTed as JSON to this URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// If set, the production status of the validator is written as JSON to
+    /// this file on every check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_file: Option<PathBuf>,
+    /// Number of blocks the validator can miss in an epoch without alerting.
+    #[serde(default = "default_production_alerts_missed_blocks_threshold")]
+    pub missed_blocks_threshold: NumBlocks,
+    /// Number of chunks the validator can miss in an epoch without alerting.
+    #[serde(default = "default_production_alerts_missed_chunks_threshold")]
+    pub missed_chunks_threshold: NumBlocks,
+}
+
+pub fn default_production_alerts_missed_blocks_threshold() -> NumBlocks {
+    3
+}
+
+pub fn default_production_alerts_missed_chunks_threshold() -> NumBlocks {
+    10
+}
+
+/// Maintenance of the node declared by its operator. The node looks for the
+/// nearest stretch of heights in which its validator has no block or chunk to
+/// produce and no chunk to validate.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaintenanceWindowConfig {
+    /// Number of consecutive heights the maintenance needs.
+    pub num_blocks: BlockHeightDelta,
+    /// Whether the node shuts down at the start of the window, to be
+    /// restarted by its supervisor.
+    #[serde(default)]
+    pub auto_restart: bool,
+}
+
+/// Machine-readable status of the node, written to a file so that
+/// orchestration systems such as Kubernetes probes and systemd watchdogs can
+/// check the node without querying the RPC.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct NodeStatusFileConfig {
+    /// Path of the status file.
+    pub path: PathBuf,
+    /// How often the status file is written.
+    #[serde(default = "default_node_status_file_period")]
+    pub period: Duration,
+}
+
+pub fn default_node_status_file_period() -> Duration {
+    Duration::from_secs(10)
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverRole {
+    Primary,
+    Backup,
+}
+
+/// Coordinates two nodes holding the key of the same validator, so that
+/// exactly one of them signs at any time. The nodes exchange signed
+/// heartbeats over UDP. The standby node takes over once the heartbeats of
+/// the other node stop for `failover_timeout`, provided that it follows the
+/// chain itself and sees no new blocks of the validator in the meantime.
+/// When both nodes sign, the backup gives way to the primary.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ValidatorFailoverConfig {
+    pub role: FailoverRole,
+    /// Address the heartbeats of the other node are received at.
+    pub listen_addr: SocketAddr,
+    /// Address of the other node, where heartbeats are sent.
+    pub peer_addr: SocketAddr,
+    /// How often heartbeats are sent.
+    #[serde(default = "default_failover_heartbeat_period")]
+    pub heartbeat_period: Duration,
+    /// How long the other node must be silent before the standby node
+    /// starts signing.
+    #[serde(default = "default_failover_timeout")]
+    pub failover_timeout: Duration,
+}
+
+pub fn default_failover_heartbeat_period() -> Duration {
+    Duration::from_secs(1)
+}
+
+pub fn default_failover_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+/// Alternate configuration chunks are applied with a second time, see
+/// `ShadowApplyConfig`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShadowApplyVariant {
+    /// Reads the state from disk and flat storage instead of the in-memory
+    /// tries. Only makes a difference on shards with memtries loaded.
+    NoMemtrie,
+}
+
+impl ShadowApplyVariant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShadowApplyVariant::NoMemtrie => "no_memtrie",
+        }
+    }
+}
+
+/// Applies every new chunk a second time with an alternate configuration,
+/// discarding the result, and reports the time taken by both applications,
+/// to compare them on the traffic of a canary node. This roughly doubles
+/// the time to apply blocks, so it must not be enabled on validators.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ShadowApplyConfig {
+    pub variant: ShadowApplyVariant,
+}
+
+/// Adapts the doomslug skip delay to the observed latency of approvals, so
+/// that well-connected networks skip missing block producers sooner. The
+/// delay stays between twice the `min_block_production_delay`, which paces
+/// the endorsements, and the configured `max_block_production_delay`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct DoomslugAdaptiveTimersConfig {
+    /// Number of recent observations of the time for approvals to reach the
+    /// threshold that the delay adapts to.
+    #[serde(default = "default_doomslug_adaptive_timers_window_size")]
+    pub window_size: usize,
+    /// The skip delay is this multiple of the 90th percentile of the observed
+    /// times, within the bounds.
+    #[serde(default = "default_doomslug_adaptive_timers_latency_multiplier")]
+    pub latency_multiplier: u32,
+}
+
+pub fn default_doomslug_adaptive_timers_window_size() -> usize {
+    100
+}
+
+pub fn default_doomslug_adaptive_timers_latency_multiplier() -> u32 {
+    3
+}
+
+pub fn default_remote_chunk_validation_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+pub fn default_remote_chunk_validation_health_check_period() -> Duration {
+    Duration::from_secs(5)
+}
+
 pub fn default_header_sync_initial_timeout() -> Duration {
     Duration::from_secs(10)
 }
@@ -233,6 +470,10 @@ pub fn default_state_sync_timeout() -> Duration {
     Duration::from_secs(60)
 }
 
+pub fn default_state_sync_num_concurrent_requests() -> u32 {
+    DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_PEERS
+}
+
 pub fn default_header_sync_expected_height_per_second() -> u64 {
     10
 }
@@ -289,10 +530,30 @@ pub fn default_transaction_pool_size_limit() -> Option<u64> {
     Some(100_000_000) // 100 MB.
 }
 
+pub fn default_transaction_pool_future_nonce_limit() -> Option<usize> {
+    Some(16)
+}
+
 pub fn default_tx_routing_height_horizon() -> BlockHeightDelta {
     4
 }
 
+pub fn default_chunk_witness_height_horizon() -> BlockHeightDelta {
+    5
+}
+
+pub fn default_chunk_witness_production_threads() -> usize {
+    4
+}
+
+pub fn default_produce_chunk_max_transactions_per_account() -> usize {
+    100
+}
+
+pub fn default_chunk_endorsement_num_next_block_producers() -> u64 {
+    1
+}
+
 pub fn default_enable_multiline_logging() -> Option<bool> {
     Some(true)
 }
@@ -312,6 +573,8 @@ pub struct ClientConfig {
     pub rpc_addr: Option<String>,
     /// Graceful shutdown at expected block height.
     pub expected_shutdown: MutableConfigValue<Option<BlockHeight>>,
+    /// Upcoming maintenance of the node, if any.
+    pub maintenance_window: MutableConfigValue<Option<MaintenanceWindowConfig>>,
     /// Duration to check for producing / skipping block.
     pub block_production_tracking_delay: Duration,
     /// Minimum duration before producing block.
@@ -338,6 +601,8 @@ pub struct ClientConfig {
     pub header_sync_expected_height_per_second: u64,
     /// How long to wait for a response during state sync
     pub state_sync_timeout: Duration,
+    /// Maximum number of state parts of a shard requested from peers at the same time.
+    pub state_sync_num_concurrent_requests: u32,
     /// Minimum number of peers to start syncing.
     pub min_num_peers: usize,
     /// Period between logging summary information.
@@ -367,8 +632,13 @@ pub struct ClientConfig {
     /// Accounts that this client tracks.
     pub tracked_accounts: Vec<AccountId>,
     /// Shards that this client tracks.
+    /// If non-empty, the client tracks all shards, whichever ids are listed.
     pub tracked_shards: Vec<ShardId>,
-    /// Rotate between these sets of tracked shards.
+    /// Rotate between these sets of tracked shards: the shards tracked in an
+    /// epoch are the set at index `epoch_height % tracked_shard_schedule.len()`.
+    /// The state of the shards tracked in the next epoch but not in the
+    /// current one is downloaded by catchup during the current epoch, so that
+    /// the client switches the tracked shards without interruption.
     /// Used to simulate the behavior of chunk only producers without staking tokens.
     /// This field is only used if `tracked_shards` is empty.
     pub tracked_shard_schedule: Vec<Vec<ShardId>>,
@@ -405,8 +675,24 @@ pub struct ClientConfig {
     /// Options for syncing state.
     pub state_sync: StateSyncConfig,
     /// Limit of the size of per-shard transaction pool measured in bytes. If not set, the size
-    /// will be unbounded.
+    /// will be unbounded. Transactions with the lowest priority are evicted to make room for new
+    /// transactions with a higher priority.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Limit of the number of transactions of a single signer account in the
+    /// per-shard transaction pool. If not set, the number is unbounded.
+    pub transaction_pool_account_count_limit: Option<usize>,
+    /// Limit of the size of the transactions of a single signer account in
+    /// the per-shard transaction pool, in bytes. If not set, the size is
+    /// unbounded.
+    pub transaction_pool_account_size_limit: Option<u64>,
+    /// Limit of the number of transactions of a single signer account held
+    /// back in the per-shard transaction pool until the transactions with
+    /// the nonces before them arrive. If not set, transactions are never held
+    /// back.
+    pub transaction_pool_future_nonce_limit: Option<usize>,
+    /// Maximum number of transactions of a single account in a chunk, once
+    /// chunk producers pick transactions by priority.
+    pub produce_chunk_max_transactions_per_account: usize,
     // Allows more detailed logging, for example a list of orphaned blocks.
     pub enable_multiline_logging: bool,
     // Configuration for resharding.
@@ -419,6 +705,79 @@ pub struct ClientConfig {
     /// some limit is reached. This time limit ensures that adding transactions won't take
     /// longer than the specified duration, which helps to produce the chunk quickly.
     pub produce_chunk_add_transactions_time_limit: MutableConfigValue<Option<Duration>>,
+    /// Soft deadline for producing a chunk, measured from the start of its
+    /// production. Adding transactions stops early enough to meet it, so a
+    /// slow start leaves a smaller time budget for transactions and the
+    /// chunk includes only part of the transactions in the pool.
+    pub produce_chunk_soft_deadline: MutableConfigValue<Option<Duration>>,
+    /// Whether to persist received and produced chunk state witnesses.
+    pub save_state_witnesses: bool,
+    /// Whether to record the transaction each executed receipt originates from.
+    pub save_receipt_origins: bool,
+    /// Whether to collect and persist a gas and IO profile of every executed
+    /// receipt.
+    pub save_receipt_profiles: bool,
+    /// Whether to read trie nodes missing from a chunk state witness from
+    /// storage when validating witnesses of tracked shards.
+    pub chunk_validation_db_fallback: bool,
+    /// Whether to also execute chunks of tracked shards from local state when
+    /// validating their witnesses, and report if the results differ.
+    pub chunk_validation_determinism_check: bool,
+    /// Whether to validate witnesses of tracked shards once more from local
+    /// state when validation from the witness fails, to tell a corrupted
+    /// witness apart from an invalid chunk.
+    pub chunk_validation_stateful_fallback: bool,
+    /// Number of block producers of the heights after the chunk's height
+    /// which chunk endorsements are also sent to, so that the chunk can be
+    /// included if the block producer of its height misses its slot.
+    pub chunk_endorsement_num_next_block_producers: u64,
+    /// Chunk state witnesses for chunks more than that many blocks below or
+    /// above the head are rejected without being processed.
+    pub chunk_witness_height_horizon: BlockHeightDelta,
+    /// Witness size limit, validation threads and endorsement batching of
+    /// chunk validators.
+    pub chunk_validator: MutableConfigValue<ChunkValidatorConfig>,
+    /// Maximum number of state witnesses of different shards produced in
+    /// parallel by a chunk producer tracking several shards.
+    pub chunk_witness_production_threads: usize,
+    /// Whether to send incremental state witnesses, which build upon the
+    /// transitions of the previous witness of the shard, to the chunk
+    /// validators which were sent the previous witness.
+    pub incremental_chunk_state_witnesses: bool,
+    /// If set, a summary record of every validated chunk state witness is
+    /// appended to this file as a JSON line.
+    pub chunk_state_witness_analytics_file: Option<PathBuf>,
+    /// If set, chunk state witnesses are validated by remote workers, falling
+    /// back to local validation if no worker is available.
+    pub remote_chunk_validation: Option<RemoteChunkValidationConfig>,
+    /// If set, the node alerts when its validator misses producing blocks or
+    /// chunks.
+    pub production_alerts: Option<ProductionAlertsConfig>,
+    /// If set, the status of the node is periodically written to a file.
+    pub node_status_file: Option<NodeStatusFileConfig>,
+    /// If set, the doomslug skip delay adapts to the observed latency of
+    /// approvals.
+    pub doomslug_adaptive_timers: Option<DoomslugAdaptiveTimersConfig>,
+    /// If set, the node is a read replica of another node and only serves
+    /// read-only RPC.
+    pub read_replica: Option<ReadReplicaConfig>,
+    /// Whether to compile the contracts called by the transactions and
+    /// receipts of newly received chunks in the background, before the chunks
+    /// are applied.
+    pub speculative_contract_compilation: bool,
+    /// Whether the node starts with its validator key detached, i.e. without
+    /// signing anything until the key is attached through the admin endpoint
+    /// of the RPC.
+    pub validator_key_detached: bool,
+    /// If set, the node coordinates with another node holding the same
+    /// validator key, and starts with its validator key detached.
+    pub validator_failover: Option<ValidatorFailoverConfig>,
+    /// If set, every new chunk is applied a second time with an alternate
+    /// configuration to compare the timings.
+    pub shadow_apply: Option<ShadowApplyConfig>,
+    /// If set, the consensus messages received by the node are appended to
+    /// this file, to be replayed with `neard replay`.
+    pub consensus_log_file: Option<PathBuf>,
 }
 
 impl ClientConfig {
@@ -443,6 +802,7 @@ impl ClientConfig {
             chain_id: "unittest".to_string(),
             rpc_addr: Some("0.0.0.0:3030".to_string()),
             expected_shutdown: MutableConfigValue::new(None, "expected_shutdown"),
+            maintenance_window: MutableConfigValue::new(None, "maintenance_window"),
             block_production_tracking_delay: Duration::from_millis(std::cmp::max(
                 10,
                 min_block_prod_time / 5,
@@ -458,6 +818,7 @@ impl ClientConfig {
             header_sync_progress_timeout: Duration::from_secs(2),
             header_sync_stall_ban_timeout: Duration::from_secs(30),
             state_sync_timeout: Duration::from_secs(TEST_STATE_SYNC_TIMEOUT),
+            state_sync_num_concurrent_requests: DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_PEERS,
             header_sync_expected_height_per_second: 1,
             min_num_peers: 1,
             log_summary_period: Duration::from_secs(10),
@@ -492,6 +853,11 @@ impl ClientConfig {
             state_sync_enabled,
             state_sync: StateSyncConfig::default(),
             transaction_pool_size_limit: None,
+            transaction_pool_account_count_limit: None,
+            transaction_pool_account_size_limit: None,
+            transaction_pool_future_nonce_limit: None,
+            produce_chunk_max_transactions_per_account:
+                default_produce_chunk_max_transactions_per_account(),
             enable_multiline_logging: false,
             resharding_config: MutableConfigValue::new(
                 ReshardingConfig::default(),
@@ -502,6 +868,37 @@ impl ClientConfig {
                 default_produce_chunk_add_transactions_time_limit(),
                 "produce_chunk_add_transactions_time_limit",
             ),
+            produce_chunk_soft_deadline: MutableConfigValue::new(
+                None,
+                "produce_chunk_soft_deadline",
+            ),
+            save_state_witnesses: false,
+            save_receipt_origins: archive,
+            save_receipt_profiles: false,
+            chunk_validation_db_fallback: false,
+            chunk_validation_determinism_check: false,
+            chunk_validation_stateful_fallback: false,
+            // Tests deliver chunk endorsements explicitly, only to the block
+            // producer of the chunk's height.
+            chunk_endorsement_num_next_block_producers: 0,
+            chunk_witness_height_horizon: default_chunk_witness_height_horizon(),
+            chunk_validator: MutableConfigValue::new(
+                ChunkValidatorConfig::default(),
+                "chunk_validator",
+            ),
+            chunk_witness_production_threads: default_chunk_witness_production_threads(),
+            incremental_chunk_state_witnesses: false,
+            chunk_state_witness_analytics_file: None,
+            remote_chunk_validation: None,
+            production_alerts: None,
+            node_status_file: None,
+            doomslug_adaptive_timers: None,
+            read_replica: None,
+            speculative_contract_compilation: false,
+            validator_key_detached: false,
+            validator_failover: None,
+            shadow_apply: None,
+            consensus_log_file: None,
         }
     }
 }
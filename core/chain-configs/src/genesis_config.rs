@@ -68,6 +68,18 @@ fn default_num_chunk_only_producer_seats() -> u64 {
     300
 }
 
+fn default_min_chunk_validator_seats_per_shard() -> u64 {
+    0
+}
+
+fn default_chunk_endorsement_quorum() -> Rational32 {
+    Rational32::new(2, 3)
+}
+
+fn default_max_chunk_state_witness_size() -> u64 {
+    64 * 1024 * 1024
+}
+
 fn default_use_production_config() -> bool {
     false
 }
@@ -172,6 +184,18 @@ pub struct GenesisConfig {
     #[serde(default = "default_minimum_stake_ratio")]
     #[default(Rational32::new(160, 1_000_000))]
     pub minimum_stake_ratio: Rational32,
+    /// The minimum number of chunk validator seats (mandates) each shard must have.
+    #[serde(default = "default_min_chunk_validator_seats_per_shard")]
+    #[default(0)]
+    pub min_chunk_validator_seats_per_shard: NumSeats,
+    /// Fraction of the chunk validators' stake which must endorse a chunk.
+    #[serde(default = "default_chunk_endorsement_quorum")]
+    #[default(Rational32::new(2, 3))]
+    pub chunk_endorsement_quorum: Rational32,
+    /// Maximum size in bytes of a chunk state witness accepted by chunk validators.
+    #[serde(default = "default_max_chunk_state_witness_size")]
+    #[default(64 * 1024 * 1024)]
+    pub max_chunk_state_witness_size: u64,
     #[serde(default = "default_use_production_config")]
     #[default(false)]
     /// This is only for test purposes. We hard code some configs for mainnet and testnet
@@ -211,6 +235,11 @@ impl From<&GenesisConfig> for EpochConfig {
                 minimum_stake_ratio: config.minimum_stake_ratio,
             },
             validator_max_kickout_stake_perc: config.max_kickout_stake_perc,
+            chunk_validation_config: near_primitives::epoch_manager::ChunkValidationConfig {
+                min_chunk_validator_seats_per_shard: config.min_chunk_validator_seats_per_shard,
+                chunk_endorsement_quorum: config.chunk_endorsement_quorum,
+                max_chunk_state_witness_size: config.max_chunk_state_witness_size,
+            },
         }
     }
 }
@@ -785,6 +814,12 @@ pub struct ProtocolConfigView {
     pub num_chunk_only_producer_seats: NumSeats,
     /// Layout information regarding how to split accounts to shards
     pub shard_layout: ShardLayout,
+    /// The minimum number of chunk validator seats (mandates) each shard must have.
+    pub min_chunk_validator_seats_per_shard: NumSeats,
+    /// Fraction of the chunk validators' stake which must endorse a chunk.
+    pub chunk_endorsement_quorum: Rational32,
+    /// Maximum size in bytes of a chunk state witness accepted by chunk validators.
+    pub max_chunk_state_witness_size: u64,
 }
 
 pub struct ProtocolConfig {
@@ -829,6 +864,9 @@ impl From<ProtocolConfig> for ProtocolConfigView {
             minimum_validators_per_shard: genesis_config.minimum_validators_per_shard,
             num_chunk_only_producer_seats: genesis_config.num_chunk_only_producer_seats,
             shard_layout: genesis_config.shard_layout,
+            min_chunk_validator_seats_per_shard: genesis_config.min_chunk_validator_seats_per_shard,
+            chunk_endorsement_quorum: genesis_config.chunk_endorsement_quorum,
+            max_chunk_state_witness_size: genesis_config.max_chunk_state_witness_size,
         }
     }
 }
@@ -180,6 +180,23 @@ impl<'a> GenesisValidator<'a> {
             let error_message = format!("Epoch Length must be greater than 0");
             self.validation_errors.push_genesis_semantics_error(error_message)
         }
+
+        let chunk_endorsement_quorum = self.genesis_config.chunk_endorsement_quorum;
+        if *chunk_endorsement_quorum.denom() <= 0 {
+            let error_message = format!(
+                "Chunk endorsement quorum must have a positive denominator, value in config is {}",
+                chunk_endorsement_quorum
+            );
+            self.validation_errors.push_genesis_semantics_error(error_message)
+        } else if chunk_endorsement_quorum <= Rational32::from_integer(0)
+            || chunk_endorsement_quorum > Rational32::from_integer(1)
+        {
+            let error_message = format!(
+                "Chunk endorsement quorum must be greater than 0 and less or equal than 1, value in config is {}",
+                chunk_endorsement_quorum
+            );
+            self.validation_errors.push_genesis_semantics_error(error_message)
+        }
     }
 
     fn result_with_full_error(&self) -> Result<(), ValidationError> {
@@ -314,4 +331,39 @@ mod test {
         let genesis = &Genesis::new(config, records).unwrap();
         validate_genesis(genesis).unwrap();
     }
+
+    fn genesis_with_chunk_endorsement_quorum(quorum: Rational32) -> Genesis {
+        let mut config = GenesisConfig::default();
+        config.validators = vec![AccountInfo {
+            account_id: "test".parse().unwrap(),
+            public_key: VALID_ED25519_RISTRETTO_KEY.parse().unwrap(),
+            amount: 10,
+        }];
+        config.total_supply = 110;
+        config.chunk_endorsement_quorum = quorum;
+        let records = GenesisRecords(vec![StateRecord::Account {
+            account_id: "test".parse().unwrap(),
+            account: create_account(),
+        }]);
+        Genesis::new(config, records).unwrap()
+    }
+
+    #[test]
+    #[should_panic(expected = "Chunk endorsement quorum must be greater than 0")]
+    fn test_zero_chunk_endorsement_quorum() {
+        validate_genesis(&genesis_with_chunk_endorsement_quorum(Rational32::new(0, 1))).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Chunk endorsement quorum must be greater than 0")]
+    fn test_too_large_chunk_endorsement_quorum() {
+        validate_genesis(&genesis_with_chunk_endorsement_quorum(Rational32::new(3, 2))).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Chunk endorsement quorum must have a positive denominator")]
+    fn test_negative_chunk_endorsement_quorum_denominator() {
+        let quorum = Rational32::new_raw(-2, -3);
+        validate_genesis(&genesis_with_chunk_endorsement_quorum(quorum)).unwrap();
+    }
 }
@@ -6,17 +6,23 @@ mod metrics;
 mod updateable_config;
 
 pub use client_config::{
-    default_enable_multiline_logging, default_epoch_sync_enabled,
-    default_header_sync_expected_height_per_second, default_header_sync_initial_timeout,
-    default_header_sync_progress_timeout, default_header_sync_stall_ban_timeout,
-    default_log_summary_period, default_produce_chunk_add_transactions_time_limit,
-    default_state_sync, default_state_sync_enabled, default_state_sync_timeout,
-    default_sync_check_period, default_sync_height_threshold, default_sync_step_period,
-    default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
-    default_tx_routing_height_horizon, default_view_client_threads,
-    default_view_client_throttle_period, ClientConfig, DumpConfig, ExternalStorageConfig,
-    ExternalStorageLocation, GCConfig, LogSummaryStyle, ReshardingConfig, ReshardingHandle,
-    StateSyncConfig, SyncConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+    default_chunk_endorsement_num_next_block_producers, default_chunk_witness_height_horizon,
+    default_chunk_witness_production_threads, default_enable_multiline_logging,
+    default_epoch_sync_enabled, default_header_sync_expected_height_per_second,
+    default_header_sync_initial_timeout, default_header_sync_progress_timeout,
+    default_header_sync_stall_ban_timeout, default_log_summary_period,
+    default_produce_chunk_add_transactions_time_limit,
+    default_produce_chunk_max_transactions_per_account, default_state_sync,
+    default_state_sync_enabled, default_state_sync_timeout, default_sync_check_period,
+    default_sync_height_threshold, default_sync_step_period,
+    default_transaction_pool_future_nonce_limit, default_transaction_pool_size_limit,
+    default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
+    default_view_client_threads, default_view_client_throttle_period, ChunkValidatorConfig,
+    ClientConfig, DoomslugAdaptiveTimersConfig, DumpConfig, ExternalStorageConfig,
+    ExternalStorageLocation, FailoverRole, GCConfig, LogSummaryStyle, MaintenanceWindowConfig,
+    NodeStatusFileConfig, ProductionAlertsConfig, ReadReplicaConfig, RemoteChunkValidationConfig,
+    ReshardingConfig, ReshardingHandle, ShadowApplyConfig, ShadowApplyVariant, StateSyncConfig,
+    SyncConfig, ValidatorFailoverConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL, MIN_GC_NUM_EPOCHS_TO_KEEP,
     TEST_STATE_SYNC_TIMEOUT,
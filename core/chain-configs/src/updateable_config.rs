@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize, Serializer};
 use std::sync::{Arc, Mutex};
 use std::{fmt::Debug, time::Duration};
 
-use crate::ReshardingConfig;
+use crate::{ChunkValidatorConfig, MaintenanceWindowConfig, ReshardingConfig};
 
 /// A wrapper for a config value that can be updated while the node is running.
 /// When initializing sub-objects (e.g. `ShardsManager`), please make sure to
@@ -91,9 +91,19 @@ pub struct UpdateableClientConfig {
     /// Graceful shutdown at expected block height.
     pub expected_shutdown: Option<BlockHeight>,
 
+    pub maintenance_window: Option<MaintenanceWindowConfig>,
+
     // Configuration for resharding.
     pub resharding_config: ReshardingConfig,
 
     /// Time limit for adding transactions in produce_chunk()
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+
+    /// Soft deadline for producing a chunk, which bounds the time budget for
+    /// adding transactions in produce_chunk()
+    pub produce_chunk_soft_deadline: Option<Duration>,
+
+    /// Witness size limit, validation threads and endorsement batching of
+    /// chunk validators.
+    pub chunk_validator: ChunkValidatorConfig,
 }
@@ -3,6 +3,7 @@
 use near_chain_configs::UpdateableClientConfig;
 use near_o11y::log_config::LogConfig;
 use near_primitives::static_clock::StaticClock;
+use near_primitives::validator_signer::ValidatorSigner;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -17,6 +18,11 @@ pub struct UpdateableConfigs {
     pub log_config: Option<LogConfig>,
     /// Contents of the `config.json` corresponding to the mutable fields of `ClientConfig`.
     pub client_config: Option<UpdateableClientConfig>,
+    /// Validator signer loaded from the validator key files referenced by
+    /// `config.json`, None if there is no validator key. Allows rotating the
+    /// validator key without a restart.
+    #[serde(skip)]
+    pub validator_signer: Option<Arc<dyn ValidatorSigner>>,
 }
 
 /// Pushes the updates to listeners.
@@ -144,6 +144,7 @@ pub enum Parameter {
     Wasmer2StackLimit,
     MaxLocalsPerContract,
     AccountIdValidityRulesVersion,
+    MaxOutgoingReceiptsSizePerShard,
 
     // Contract runtime features
     #[strum(serialize = "disable_9393_fix")]
@@ -222,6 +223,7 @@ impl Parameter {
             Parameter::Wasmer2StackLimit,
             Parameter::MaxLocalsPerContract,
             Parameter::AccountIdValidityRulesVersion,
+            Parameter::MaxOutgoingReceiptsSizePerShard,
         ]
         .iter()
     }
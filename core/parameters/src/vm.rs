@@ -132,6 +132,11 @@ pub struct LimitConfig {
     /// historically.
     #[serde(default = "AccountIdValidityRulesVersion::v0")]
     pub account_id_validity_rules_version: AccountIdValidityRulesVersion,
+    /// If present, limits the total size of the receipts a chunk sends to
+    /// each other shard. Receipts over the limit are forwarded by the next
+    /// chunks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_outgoing_receipts_size_per_shard: Option<u64>,
 }
 
 /// Dynamic configuration parameters required for the WASM runtime to
@@ -130,6 +130,31 @@ pub enum ProtocolFeature {
     /// NEP: https://github.com/near/NEPs/pull/509
     ChunkValidation,
     EthImplicitAccounts,
+    /// Chunk endorsements sign the epoch id and height of the endorsed chunk
+    /// next to its hash, so that they can't be replayed in another context.
+    ChunkEndorsementV2,
+    /// Limits the size of the receipts a chunk sends to each other shard.
+    /// Receipts over the limit are buffered in the state of the sending shard
+    /// and forwarded by the next chunks.
+    OutgoingReceiptsSizeLimit,
+    /// Chunk producers pick the transactions with the most attached gas
+    /// first, up to a number of transactions per account, instead of picking
+    /// them round robin.
+    TransactionPriority,
+    /// Chunk-only producers are selected among the validators that didn't get
+    /// a block producer seat, with a seat price of their own, so that
+    /// validators with a lower stake can produce chunks.
+    DedicatedChunkOnlyProducers,
+    /// Chunk validators which endorse different chunks of the same shard and
+    /// height are slashed, with the evidence included in blocks as challenges.
+    ChunkEndorsementSlashing,
+    /// Incoming receipts of different receivers are executed in parallel, each
+    /// group of receipts with its own deterministic trie accounting cache.
+    ParallelReceiptExecution,
+    /// Chunk producers send incremental state witnesses, which build upon the
+    /// transitions of the previous witness of the shard, as a separate
+    /// routed message.
+    IncrementalChunkStateWitnesses,
 }
 
 impl ProtocolFeature {
@@ -186,6 +211,13 @@ impl ProtocolFeature {
             ProtocolFeature::RejectBlocksWithOutdatedProtocolVersions => 132,
             ProtocolFeature::ChunkValidation => 137,
             ProtocolFeature::EthImplicitAccounts => 138,
+            ProtocolFeature::ChunkEndorsementV2 => 139,
+            ProtocolFeature::OutgoingReceiptsSizeLimit => 140,
+            ProtocolFeature::TransactionPriority => 141,
+            ProtocolFeature::DedicatedChunkOnlyProducers => 142,
+            ProtocolFeature::ChunkEndorsementSlashing => 143,
+            ProtocolFeature::ParallelReceiptExecution => 144,
+            ProtocolFeature::IncrementalChunkStateWitnesses => 145,
         }
     }
 }
@@ -198,7 +230,7 @@ const STABLE_PROTOCOL_VERSION: ProtocolVersion = 64;
 /// Largest protocol version supported by the current binary.
 pub const PROTOCOL_VERSION: ProtocolVersion = if cfg!(feature = "nightly_protocol") {
     // On nightly, pick big enough version to support all features.
-    139
+    146
 } else {
     // Enable all stable features.
     STABLE_PROTOCOL_VERSION
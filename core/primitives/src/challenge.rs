@@ -1,8 +1,9 @@
+use crate::chunk_validation::ChunkEndorsement;
 use crate::hash::CryptoHash;
 use crate::merkle::MerklePath;
 use crate::sharding::{EncodedShardChunk, ShardChunk, ShardChunkHeader};
-use crate::types::AccountId;
-use crate::validator_signer::ValidatorSigner;
+use crate::types::{AccountId, EpochId};
+use crate::validator_signer::{ValidatorSigner, ValidatorSignerError};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::Signature;
 
@@ -84,6 +85,48 @@ pub struct ChunkState {
     pub partial_state: PartialState,
 }
 
+/// Endorsements by the same chunk validator of two different chunks of the
+/// same shard and height. The chunks are ordered by hash, so that the same
+/// evidence has the same hash regardless of who reports it.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
+pub struct ChunkEndorsementDoubleSign {
+    /// Epoch of the chunks. The endorsements sign it since
+    /// `ChunkEndorsementV2`, so it can't be forged by the reporter.
+    pub epoch_id: EpochId,
+    pub left_chunk_header: ShardChunkHeader,
+    pub left_endorsement: ChunkEndorsement,
+    pub right_chunk_header: ShardChunkHeader,
+    pub right_endorsement: ChunkEndorsement,
+}
+
+impl ChunkEndorsementDoubleSign {
+    pub fn new(
+        epoch_id: EpochId,
+        chunk_header: ShardChunkHeader,
+        endorsement: ChunkEndorsement,
+        other_chunk_header: ShardChunkHeader,
+        other_endorsement: ChunkEndorsement,
+    ) -> Self {
+        if chunk_header.chunk_hash() < other_chunk_header.chunk_hash() {
+            Self {
+                epoch_id,
+                left_chunk_header: chunk_header,
+                left_endorsement: endorsement,
+                right_chunk_header: other_chunk_header,
+                right_endorsement: other_endorsement,
+            }
+        } else {
+            Self {
+                epoch_id,
+                left_chunk_header: other_chunk_header,
+                left_endorsement: other_endorsement,
+                right_chunk_header: chunk_header,
+                right_endorsement: endorsement,
+            }
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
 // TODO(#1313): Use Box
 #[allow(clippy::large_enum_variant)]
@@ -91,6 +134,8 @@ pub enum ChallengeBody {
     BlockDoubleSign(BlockDoubleSign),
     ChunkProofs(ChunkProofs),
     ChunkState(ChunkState),
+    /// Used since `ProtocolFeature::ChunkEndorsementSlashing`.
+    ChunkEndorsementDoubleSign(ChunkEndorsementDoubleSign),
 }
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
@@ -109,9 +154,12 @@ impl Challenge {
         self.hash = CryptoHash::hash_borsh(&self.body);
     }
 
-    pub fn produce(body: ChallengeBody, signer: &dyn ValidatorSigner) -> Self {
-        let (hash, signature) = signer.sign_challenge(&body);
-        Self { body, account_id: signer.validator_id().clone(), signature, hash }
+    pub fn produce(
+        body: ChallengeBody,
+        signer: &dyn ValidatorSigner,
+    ) -> Result<Self, ValidatorSignerError> {
+        let (hash, signature) = signer.sign_challenge(&body)?;
+        Ok(Self { body, account_id: signer.validator_id().clone(), signature, hash })
     }
 }
 
@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 
 use crate::challenge::PartialState;
+use crate::num_rational::Rational32;
 use crate::sharding::{ChunkHash, ReceiptProof, ShardChunkHeader};
 use crate::transaction::SignedTransaction;
+use crate::types::EpochId;
 use borsh::{BorshDeserialize, BorshSerialize};
-use near_crypto::Signature;
+use near_crypto::{PublicKey, Signature};
 use near_primitives_core::hash::CryptoHash;
-use near_primitives_core::types::AccountId;
+use near_primitives_core::types::{AccountId, Balance, BlockHeight, ProtocolVersion};
 
 /// The state witness for a chunk; proves the state transition that the
 /// chunk attests to.
@@ -74,6 +76,32 @@ pub struct ChunkStateWitness {
     /// accounts have appropriate balances, access keys, nonces, etc.
     pub new_transactions: Vec<SignedTransaction>,
     pub new_transactions_validation_state: PartialState,
+    /// If set, this is an incremental witness: the main transition and the
+    /// implicit transitions up to `WitnessTransitionsBase::last_block_hash`
+    /// were already sent in a previous witness of the same shard, which the
+    /// chunk validator is expected to have validated. The base state of the
+    /// main transition is then left empty, and `implicit_transitions` only
+    /// contains the transitions after that block.
+    ///
+    /// Not part of the borsh encoding of the witness, so that it stays the
+    /// same for full witnesses. Incremental witnesses are sent along with
+    /// their base in a separate network message.
+    #[borsh(skip)]
+    pub transitions_base: Option<WitnessTransitionsBase>,
+}
+
+/// Refers to the state reached after applying the transitions of a previous
+/// witness. When several chunks of a shard in a row are not included in
+/// blocks, consecutive witnesses share the same main transition and differ
+/// only by the implicit transitions of the blocks without a new chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, BorshSerialize, BorshDeserialize)]
+pub struct WitnessTransitionsBase {
+    /// The block of the last transition of the previous witness, either its
+    /// main transition or its last implicit transition.
+    pub last_block_hash: CryptoHash,
+    /// The state root after applying the last transition of the previous
+    /// witness.
+    pub post_state_root: CryptoHash,
 }
 
 /// Represents the base state and the expected post-state-root of a chunk's state
@@ -103,7 +131,20 @@ pub struct ChunkEndorsement {
     pub signature: Signature,
 }
 
-/// This is the part of the chunk endorsement that is actually being signed.
+impl ChunkEndorsement {
+    /// Verifies that the endorsement was signed by the owner of `public_key`
+    /// and that the signed payload is `payload`. The payload is not sent
+    /// along with the endorsement, as since `ChunkEndorsementV2` it includes
+    /// the epoch id and height of the chunk, which the receiver knows from
+    /// the chunk header.
+    pub fn verify(&self, public_key: &PublicKey, payload: &ChunkEndorsementPayload) -> bool {
+        payload.chunk_hash() == &self.inner.chunk_hash
+            && self.signature.verify(&payload.signed_data(), public_key)
+    }
+}
+
+/// This is the part of the chunk endorsement that is sent over the network.
+/// Before `ChunkEndorsementV2` it is also what is signed.
 #[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
 pub struct ChunkEndorsementInner {
     pub chunk_hash: ChunkHash,
@@ -122,6 +163,76 @@ impl ChunkEndorsementInner {
     }
 }
 
+/// What is signed instead of `ChunkEndorsementInner` since
+/// `ChunkEndorsementV2`. Binds the endorsement to the epoch and height of the
+/// chunk, so that a signature can't be replayed for the same chunk hash in
+/// another context.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ChunkEndorsementInnerV2 {
+    /// Static tag of the signed message type; see
+    /// `ChunkEndorsementInner::signature_differentiator`.
+    message_type: String,
+    pub chunk_hash: ChunkHash,
+    pub epoch_id: EpochId,
+    pub height_created: BlockHeight,
+}
+
+impl ChunkEndorsementInnerV2 {
+    pub fn new(chunk_hash: ChunkHash, epoch_id: EpochId, height_created: BlockHeight) -> Self {
+        Self { message_type: "ChunkEndorsementV2".to_owned(), chunk_hash, epoch_id, height_created }
+    }
+}
+
+/// The payload signed by a chunk validator to endorse a chunk, in the format
+/// used by the protocol version of the chunk's epoch. Only the
+/// `ChunkEndorsementInner` derived from it is part of `ChunkEndorsement`.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum ChunkEndorsementPayload {
+    V1(ChunkEndorsementInner),
+    V2(ChunkEndorsementInnerV2),
+}
+
+impl ChunkEndorsementPayload {
+    /// Creates the payload signed to endorse the chunk with the given hash,
+    /// created at `height_created` in the epoch `epoch_id`, in the format
+    /// used by `protocol_version`.
+    pub fn new(
+        chunk_hash: ChunkHash,
+        epoch_id: EpochId,
+        height_created: BlockHeight,
+        protocol_version: ProtocolVersion,
+    ) -> Self {
+        if crate::checked_feature!("stable", ChunkEndorsementV2, protocol_version) {
+            Self::V2(ChunkEndorsementInnerV2::new(chunk_hash, epoch_id, height_created))
+        } else {
+            Self::V1(ChunkEndorsementInner::new(chunk_hash))
+        }
+    }
+
+    pub fn chunk_hash(&self) -> &ChunkHash {
+        match self {
+            Self::V1(inner) => &inner.chunk_hash,
+            Self::V2(inner) => &inner.chunk_hash,
+        }
+    }
+
+    /// The part of the endorsement sent over the network along with the
+    /// signature.
+    pub fn inner(&self) -> ChunkEndorsementInner {
+        ChunkEndorsementInner::new(self.chunk_hash().clone())
+    }
+
+    /// The bytes that are signed. Only the versioned struct is serialized,
+    /// without the enum tag, so that V1 signatures are the same as before
+    /// the payload was versioned.
+    pub fn signed_data(&self) -> Vec<u8> {
+        match self {
+            Self::V1(inner) => borsh::to_vec(inner).unwrap(),
+            Self::V2(inner) => borsh::to_vec(inner).unwrap(),
+        }
+    }
+}
+
 /// Stored on disk for each chunk, including missing chunks, in order to
 /// produce a chunk state witness when needed.
 #[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
@@ -136,3 +247,272 @@ pub struct StoredChunkStateTransitionData {
     /// to ease debugging of why a state witness may be incorrect.
     pub receipts_hash: CryptoHash,
 }
+
+/// A compact proof that the state transition of a chunk has been validated by
+/// its chunk validators. It is derived from a validated state witness and the
+/// endorsements collected for the chunk, and can be checked without access to
+/// the chain given the chunk validator assignment for the chunk (e.g. by a
+/// light client which already tracks the validator set).
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ChunkExecutionProof {
+    /// The header of the endorsed chunk. Its `prev_outgoing_receipts_root`,
+    /// `prev_state_root` and `prev_outcome_root` are the results of executing
+    /// the previous chunk of the shard, which the endorsements vouch for.
+    pub chunk_header: ShardChunkHeader,
+    /// Hash of the receipts applied in the main state transition; see
+    /// `ChunkStateWitness::applied_receipts_hash`.
+    pub applied_receipts_hash: CryptoHash,
+    /// Post state root of the main state transition of the witness.
+    pub main_transition_post_state_root: CryptoHash,
+    /// Endorsements of the chunk, at most one per chunk validator.
+    pub endorsements: Vec<ChunkEndorsement>,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ChunkExecutionProofError {
+    #[error("Endorsement is for chunk {actual:?}, expected chunk {expected:?}")]
+    ChunkHashMismatch { expected: ChunkHash, actual: ChunkHash },
+    #[error("{0} is not a chunk validator of the chunk")]
+    NotAChunkValidator(AccountId),
+    #[error("Duplicate endorsement from {0}")]
+    DuplicateEndorsement(AccountId),
+    #[error("Invalid endorsement signature from {0}")]
+    InvalidSignature(AccountId),
+    #[error(
+        "Endorsed stake {endorsed_stake} is not more than {quorum} of total stake {total_stake}"
+    )]
+    NotEnoughStake { endorsed_stake: Balance, total_stake: Balance, quorum: Rational32 },
+}
+
+impl ChunkExecutionProof {
+    pub fn new(witness: &ChunkStateWitness, endorsements: Vec<ChunkEndorsement>) -> Self {
+        Self {
+            chunk_header: witness.chunk_header.clone(),
+            applied_receipts_hash: witness.applied_receipts_hash,
+            main_transition_post_state_root: witness.main_state_transition.post_state_root,
+            endorsements,
+        }
+    }
+
+    /// Checks that the endorsements are valid signatures of the chunk
+    /// validators in `chunk_validators` (mapping account id to the public key
+    /// and stake of the validator) and that they represent more than `quorum`
+    /// of the total stake assigned to validate the chunk. `epoch_id` and
+    /// `protocol_version` are those of the epoch the chunk belongs to.
+    pub fn verify(
+        &self,
+        epoch_id: &EpochId,
+        protocol_version: ProtocolVersion,
+        chunk_validators: &HashMap<AccountId, (PublicKey, Balance)>,
+        quorum: Rational32,
+    ) -> Result<(), ChunkExecutionProofError> {
+        let chunk_hash = self.chunk_header.chunk_hash();
+        let payload = ChunkEndorsementPayload::new(
+            chunk_hash.clone(),
+            epoch_id.clone(),
+            self.chunk_header.height_created(),
+            protocol_version,
+        );
+        let mut endorsed_by = HashMap::new();
+        for endorsement in &self.endorsements {
+            if endorsement.inner.chunk_hash != chunk_hash {
+                return Err(ChunkExecutionProofError::ChunkHashMismatch {
+                    expected: chunk_hash,
+                    actual: endorsement.inner.chunk_hash.clone(),
+                });
+            }
+            let Some((public_key, stake)) = chunk_validators.get(&endorsement.account_id) else {
+                return Err(ChunkExecutionProofError::NotAChunkValidator(
+                    endorsement.account_id.clone(),
+                ));
+            };
+            if endorsed_by.insert(&endorsement.account_id, *stake).is_some() {
+                return Err(ChunkExecutionProofError::DuplicateEndorsement(
+                    endorsement.account_id.clone(),
+                ));
+            }
+            if !endorsement.verify(public_key, &payload) {
+                return Err(ChunkExecutionProofError::InvalidSignature(
+                    endorsement.account_id.clone(),
+                ));
+            }
+        }
+        let endorsed_stake: Balance = endorsed_by.values().sum();
+        let total_stake: Balance = chunk_validators.values().map(|(_, stake)| stake).sum();
+        if endorsed_stake * (*quorum.denom() as Balance)
+            <= total_stake * (*quorum.numer() as Balance)
+        {
+            return Err(ChunkExecutionProofError::NotEnoughStake {
+                endorsed_stake,
+                total_stake,
+                quorum,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        create_test_chunk_endorsement, create_test_chunk_header, create_test_signer,
+    };
+    use crate::validator_signer::ValidatorSigner;
+    use crate::version::{ProtocolFeature, PROTOCOL_VERSION};
+
+    fn endorse(chunk_header: &ShardChunkHeader, account: &str) -> ChunkEndorsement {
+        let payload = ChunkEndorsementPayload::new(
+            chunk_header.chunk_hash(),
+            EpochId::default(),
+            chunk_header.height_created(),
+            PROTOCOL_VERSION,
+        );
+        create_test_chunk_endorsement(account, &payload)
+    }
+
+    fn proof(
+        chunk_header: &ShardChunkHeader,
+        endorsements: Vec<ChunkEndorsement>,
+    ) -> ChunkExecutionProof {
+        ChunkExecutionProof {
+            chunk_header: chunk_header.clone(),
+            applied_receipts_hash: CryptoHash::default(),
+            main_transition_post_state_root: CryptoHash::default(),
+            endorsements,
+        }
+    }
+
+    #[test]
+    fn test_verify_chunk_execution_proof() {
+        let chunk_header = create_test_chunk_header(1, 0);
+        let epoch_id = EpochId::default();
+        let chunk_validators: HashMap<_, _> = ["test0", "test1", "test2"]
+            .into_iter()
+            .map(|account| {
+                let signer = create_test_signer(account);
+                (signer.validator_id().clone(), (signer.public_key(), 100))
+            })
+            .collect();
+
+        let endorsements = vec![
+            endorse(&chunk_header, "test0"),
+            endorse(&chunk_header, "test1"),
+            endorse(&chunk_header, "test2"),
+        ];
+        let quorum = Rational32::new(2, 3);
+        assert_eq!(
+            proof(&chunk_header, endorsements.clone()).verify(
+                &epoch_id,
+                PROTOCOL_VERSION,
+                &chunk_validators,
+                quorum
+            ),
+            Ok(())
+        );
+
+        // Exactly 2/3 of the stake is not enough.
+        assert_eq!(
+            proof(&chunk_header, endorsements[..2].to_vec()).verify(
+                &epoch_id,
+                PROTOCOL_VERSION,
+                &chunk_validators,
+                quorum
+            ),
+            Err(ChunkExecutionProofError::NotEnoughStake {
+                endorsed_stake: 200,
+                total_stake: 300,
+                quorum
+            })
+        );
+        // With a lower quorum it is.
+        assert_eq!(
+            proof(&chunk_header, endorsements[..2].to_vec()).verify(
+                &epoch_id,
+                PROTOCOL_VERSION,
+                &chunk_validators,
+                Rational32::new(1, 2)
+            ),
+            Ok(())
+        );
+
+        let mut duplicated = endorsements.clone();
+        duplicated.push(endorse(&chunk_header, "test0"));
+        assert_eq!(
+            proof(&chunk_header, duplicated).verify(
+                &epoch_id,
+                PROTOCOL_VERSION,
+                &chunk_validators,
+                quorum
+            ),
+            Err(ChunkExecutionProofError::DuplicateEndorsement("test0".parse().unwrap()))
+        );
+
+        let mut with_outsider = endorsements.clone();
+        with_outsider.push(endorse(&chunk_header, "test3"));
+        assert_eq!(
+            proof(&chunk_header, with_outsider).verify(
+                &epoch_id,
+                PROTOCOL_VERSION,
+                &chunk_validators,
+                quorum
+            ),
+            Err(ChunkExecutionProofError::NotAChunkValidator("test3".parse().unwrap()))
+        );
+
+        let mut forged = endorsements;
+        forged[0].signature = endorse(&chunk_header, "test3").signature;
+        assert_eq!(
+            proof(&chunk_header, forged).verify(
+                &epoch_id,
+                PROTOCOL_VERSION,
+                &chunk_validators,
+                quorum
+            ),
+            Err(ChunkExecutionProofError::InvalidSignature("test0".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_chunk_endorsement_replay() {
+        let signer = create_test_signer("test0");
+        let chunk_hash = create_test_chunk_header(1, 0).chunk_hash();
+        let epoch_id = EpochId(CryptoHash::hash_bytes(&[1]));
+        let version = ProtocolFeature::ChunkEndorsementV2.protocol_version();
+        let payload = |epoch_id: &EpochId, height_created, protocol_version| {
+            ChunkEndorsementPayload::new(
+                chunk_hash.clone(),
+                epoch_id.clone(),
+                height_created,
+                protocol_version,
+            )
+        };
+        let endorse = |payload| create_test_chunk_endorsement("test0", payload);
+
+        // Before the protocol upgrade, only the chunk hash is signed, in the
+        // same format as before the payload was versioned.
+        let v1 = payload(&epoch_id, 1, version - 1);
+        let endorsement = endorse(&v1);
+        assert_eq!(
+            v1.signed_data(),
+            borsh::to_vec(&ChunkEndorsementInner::new(chunk_hash.clone())).unwrap()
+        );
+        assert!(
+            endorsement.verify(&signer.public_key(), &payload(&EpochId::default(), 2, version - 1))
+        );
+
+        // After it, the signed payload differs per epoch and height, so a
+        // signature is only valid in the context it was made for. What is
+        // sent over the network doesn't change.
+        let v2 = payload(&epoch_id, 1, version);
+        let endorsement = endorse(&v2);
+        assert_eq!(endorsement.inner, ChunkEndorsementInner::new(chunk_hash.clone()));
+        assert!(endorsement.verify(&signer.public_key(), &v2));
+        for (other_epoch_id, other_height) in [(EpochId::default(), 1), (epoch_id.clone(), 2)] {
+            let other = payload(&other_epoch_id, other_height, version);
+            assert!(!endorsement.verify(&signer.public_key(), &other));
+        }
+        // Nor can a signature of the old format be reused in the new one.
+        assert!(!endorse(&v1).verify(&signer.public_key(), &v2));
+    }
+}
@@ -50,6 +50,8 @@ pub struct EpochConfig {
     pub shard_layout: ShardLayout,
     /// Additional config for validator selection algorithm
     pub validator_selection_config: ValidatorSelectionConfig,
+    /// Parameters of stateless chunk validation.
+    pub chunk_validation_config: ChunkValidationConfig,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -79,6 +81,9 @@ impl ShardConfig {
 pub struct AllEpochConfigTestOverrides {
     pub block_producer_kickout_threshold: Option<u8>,
     pub chunk_producer_kickout_threshold: Option<u8>,
+    /// Shard layout to switch to from the given protocol version on, to test
+    /// resharding at protocol versions which don't change the shard layout.
+    pub shard_layout_upgrade: Option<(ProtocolVersion, ShardLayout)>,
 }
 
 /// AllEpochConfig manages protocol configs that might be changing throughout epochs (hence EpochConfig).
@@ -138,7 +143,7 @@ impl AllEpochConfig {
 
         Self::config_max_kickout_stake(&mut config, protocol_version);
 
-        Self::config_test_overrides(&mut config, &self.test_overrides);
+        Self::config_test_overrides(&mut config, &self.test_overrides, protocol_version);
 
         config
     }
@@ -205,6 +210,7 @@ impl AllEpochConfig {
     fn config_test_overrides(
         config: &mut EpochConfig,
         test_overrides: &AllEpochConfigTestOverrides,
+        protocol_version: ProtocolVersion,
     ) {
         if let Some(block_producer_kickout_threshold) =
             test_overrides.block_producer_kickout_threshold
@@ -217,6 +223,13 @@ impl AllEpochConfig {
         {
             config.chunk_producer_kickout_threshold = chunk_producer_kickout_threshold;
         }
+
+        if let Some((upgrade_protocol_version, shard_layout)) = &test_overrides.shard_layout_upgrade
+        {
+            if protocol_version >= *upgrade_protocol_version {
+                Self::config_nightshade_impl(config, shard_layout.clone());
+            }
+        }
     }
 }
 
@@ -232,6 +245,23 @@ pub struct ValidatorSelectionConfig {
     pub minimum_stake_ratio: Rational32,
 }
 
+/// Parameters of stateless chunk validation, configurable per chain so that
+/// they can be tuned on testnets without code changes.
+#[derive(Debug, Clone, SmartDefault, PartialEq, Eq)]
+pub struct ChunkValidationConfig {
+    /// Minimum number of chunk validator seats (mandates) assigned to each shard.
+    #[default(0)]
+    pub min_chunk_validator_seats_per_shard: NumSeats,
+    /// Fraction of the stake assigned to validate a chunk which needs to
+    /// endorse the chunk for it to be included in a block.
+    #[default(Rational32::new(2, 3))]
+    pub chunk_endorsement_quorum: Rational32,
+    /// Maximum size in bytes of a chunk state witness which chunk validators
+    /// accept.
+    #[default(64 * 1024 * 1024)]
+    pub max_chunk_state_witness_size: u64,
+}
+
 pub mod block_info {
     use super::SlashState;
     use crate::challenge::SlashedValidator;
@@ -1216,18 +1246,18 @@ pub enum SlashState {
 
 #[cfg(feature = "new_epoch_sync")]
 pub mod epoch_sync {
-    use crate::block_header::BlockHeader;
+    use crate::block_header::{Approval, ApprovalInner, BlockHeader};
     use crate::epoch_manager::block_info::BlockInfo;
     use crate::epoch_manager::epoch_info::EpochInfo;
     use crate::errors::epoch_sync::{EpochSyncHashType, EpochSyncInfoError};
-    use crate::types::EpochId;
+    use crate::types::{Balance, EpochId};
     use borsh::{BorshDeserialize, BorshSerialize};
     use near_o11y::log_assert;
     use near_primitives_core::hash::CryptoHash;
     use std::collections::{HashMap, HashSet};
 
     /// Struct to keep all the info that is transferred for one epoch during Epoch Sync.
-    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug)]
+    #[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Eq, Debug)]
     pub struct EpochSyncInfo {
         /// All block hashes of this epoch. In order of production.
         pub all_block_hashes: Vec<CryptoHash>,
@@ -1362,6 +1392,117 @@ pub mod epoch_sync {
             Ok(next_epoch_first_header.epoch_sync_data_hash())
         }
 
+        /// Checks that the headers are consistent with each other and that the
+        /// last block of the epoch is approved by block producers holding more
+        /// than 2/3 of the stake of `next_epoch_info`, as seen in the approvals
+        /// of the first header of the next epoch. The headers to save must be
+        /// ancestors of that block: either linked to it by `prev_hash` or
+        /// listed in `all_block_hashes`, which the caller checks against the
+        /// block merkle root of the last header. `epoch_sync_data_hash` of the
+        /// first header of the next epoch has to commit to the epoch infos.
+        /// `epoch_info` and `next_epoch_info` themselves have to be checked
+        /// against the ones the caller already trusts.
+        pub fn validate_headers(&self) -> Result<(), EpochSyncInfoError> {
+            let epoch_height = self.epoch_info.epoch_height();
+            for (hash, header) in &self.headers {
+                if header.hash() != hash {
+                    return Err(EpochSyncInfoError::InvalidHeaderChain {
+                        hash: *hash,
+                        hash_type: EpochSyncHashType::Other,
+                        epoch_height,
+                    });
+                }
+            }
+
+            let next_epoch_first_header = self
+                .get_header(self.next_epoch_first_hash, EpochSyncHashType::NextEpochFirstBlock)?;
+            let epoch_last_header = self.get_epoch_last_header()?;
+            if next_epoch_first_header.prev_hash() != epoch_last_header.hash() {
+                return Err(EpochSyncInfoError::InvalidHeaderChain {
+                    hash: self.next_epoch_first_hash,
+                    hash_type: EpochSyncHashType::NextEpochFirstBlock,
+                    epoch_height,
+                });
+            }
+            self.validate_approvals(next_epoch_first_header, epoch_last_header)?;
+
+            let mut ancestors: HashSet<&CryptoHash> = self.all_block_hashes.iter().collect();
+            let mut header = Some(epoch_last_header);
+            while let Some(current) = header {
+                ancestors.insert(current.hash());
+                header = self.headers.get(current.prev_hash());
+            }
+            for hash in &self.headers_to_save {
+                if !ancestors.contains(hash) {
+                    return Err(EpochSyncInfoError::InvalidHeaderChain {
+                        hash: *hash,
+                        hash_type: EpochSyncHashType::BlockToSave,
+                        epoch_height,
+                    });
+                }
+            }
+
+            if self.get_epoch_sync_data_hash()? != Some(self.calculate_epoch_sync_data_hash()?) {
+                return Err(EpochSyncInfoError::InvalidEpochSyncDataHash { epoch_height });
+            }
+            Ok(())
+        }
+
+        /// Checks the approvals of `header` for its parent `prev_header`, the
+        /// last block of the epoch, the same way Doomslug does before producing
+        /// a block: the approvers are the block producers of the next epoch,
+        /// and the valid approvals must be from more than 2/3 of their stake.
+        fn validate_approvals(
+            &self,
+            header: &BlockHeader,
+            prev_header: &BlockHeader,
+        ) -> Result<(), EpochSyncInfoError> {
+            let epoch_height = self.epoch_info.epoch_height();
+            let epoch_info = &self.next_epoch_info;
+            let mut approvers = vec![];
+            let mut seen = HashSet::new();
+            for validator_id in epoch_info.block_producers_settlement() {
+                let validator = epoch_info.get_validator(*validator_id);
+                if seen.insert(validator.account_id().clone()) {
+                    approvers.push(validator);
+                }
+            }
+            if header.approvals().len() > approvers.len() {
+                return Err(EpochSyncInfoError::InvalidSignature {
+                    hash: *header.hash(),
+                    hash_type: EpochSyncHashType::NextEpochFirstBlock,
+                    epoch_height,
+                });
+            }
+            let inner =
+                ApprovalInner::new(prev_header.hash(), prev_header.height(), header.height());
+            let data = Approval::get_data_for_sig(&inner, header.height());
+            let total_stake: Balance = approvers.iter().map(|approver| approver.stake()).sum();
+            let mut approved_stake: Balance = 0;
+            for (approval, approver) in header.approvals().iter().zip(approvers.iter()) {
+                let Some(signature) = approval else {
+                    continue;
+                };
+                if !signature.verify(&data, approver.public_key()) {
+                    return Err(EpochSyncInfoError::InvalidSignature {
+                        hash: *header.hash(),
+                        hash_type: EpochSyncHashType::NextEpochFirstBlock,
+                        epoch_height,
+                    });
+                }
+                approved_stake += approver.stake();
+            }
+            if approved_stake <= total_stake * 2 / 3 {
+                return Err(EpochSyncInfoError::NotEnoughApprovals {
+                    hash: *header.hash(),
+                    approved_stake,
+                    total_stake,
+                    epoch_height,
+                });
+            }
+            Ok(())
+        }
+
         pub fn get_header(
             &self,
             hash: CryptoHash,
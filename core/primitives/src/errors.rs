@@ -1218,7 +1218,7 @@ impl From<near_vm_runner::logic::errors::FunctionCallError> for FunctionCallErro
 #[cfg(feature = "new_epoch_sync")]
 pub mod epoch_sync {
     use near_primitives_core::hash::CryptoHash;
-    use near_primitives_core::types::EpochHeight;
+    use near_primitives_core::types::{Balance, EpochHeight, ShardId};
     use std::fmt::Debug;
 
     #[derive(Eq, PartialEq, Clone, strum::Display, Debug)]
@@ -1237,5 +1237,36 @@ pub mod epoch_sync {
         HashNotFound { hash: CryptoHash, hash_type: EpochSyncHashType, epoch_height: EpochHeight },
         #[error("all_block_hashes.len() < 2 for epoch {epoch_height}")]
         ShortEpoch { epoch_height: EpochHeight },
+        #[error("{hash_type} header {hash:?} has an invalid signature for epoch {epoch_height}")]
+        InvalidSignature {
+            hash: CryptoHash,
+            hash_type: EpochSyncHashType,
+            epoch_height: EpochHeight,
+        },
+        #[error(
+            "{hash_type} header {hash:?} does not link to EpochSyncInfo for epoch {epoch_height}"
+        )]
+        InvalidHeaderChain {
+            hash: CryptoHash,
+            hash_type: EpochSyncHashType,
+            epoch_height: EpochHeight,
+        },
+        #[error(
+            "header {hash:?} approved by {approved_stake} of {total_stake} stake, epoch {epoch_height}"
+        )]
+        NotEnoughApprovals {
+            hash: CryptoHash,
+            approved_stake: Balance,
+            total_stake: Balance,
+            epoch_height: EpochHeight,
+        },
+        #[error("epoch info of epoch {epoch_height} does not match the known one")]
+        InvalidEpochInfo { epoch_height: EpochHeight },
+        #[error("epoch_sync_data_hash does not match EpochSyncInfo for epoch {epoch_height}")]
+        InvalidEpochSyncDataHash { epoch_height: EpochHeight },
+        #[error(
+            "chunk validators of shard {shard_id} in header {hash:?} do not match the epoch info of epoch {epoch_height}"
+        )]
+        InvalidChunkValidators { hash: CryptoHash, shard_id: ShardId, epoch_height: EpochHeight },
     }
 }
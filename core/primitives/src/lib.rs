@@ -35,6 +35,7 @@ pub mod trie_key;
 pub mod types;
 mod upgrade_schedule;
 pub mod utils;
+pub mod validator_failover;
 pub mod validator_mandates;
 pub mod validator_signer;
 pub mod version;
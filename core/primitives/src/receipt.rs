@@ -1,5 +1,6 @@
 use crate::hash::CryptoHash;
 use crate::serialize::dec_format;
+use crate::shard_layout::ShardVersion;
 use crate::transaction::{Action, TransferAction};
 use crate::types::{AccountId, Balance, ShardId};
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -8,7 +9,7 @@ use near_fmt::AbbrBytes;
 use serde_with::base64::Base64;
 use serde_with::serde_as;
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 pub use near_vm_runner::logic::DataReceiver;
@@ -204,5 +205,17 @@ impl DelayedReceiptIndices {
     }
 }
 
+/// Stores the indices of the persistent queues of outgoing receipts which exceeded the size
+/// limit of their receiving shard, one queue per receiving shard. Only non-empty queues are
+/// stored.
+#[derive(Default, BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct BufferedReceiptIndices {
+    /// Version of the shard layout the receiving shards belong to. The queues
+    /// of a shard are moved to one of its children on resharding, and are
+    /// regrouped by receiving shard of the new layout when they are next used.
+    pub shard_layout_version: ShardVersion,
+    pub shard_buffers: BTreeMap<ShardId, DelayedReceiptIndices>,
+}
+
 /// Map of shard to list of receipts to send to it.
 pub type ReceiptResult = HashMap<ShardId, Vec<Receipt>>;
@@ -4,11 +4,12 @@ use crate::receipt::{Receipt, ReceivedData};
 use crate::trie_key::trie_key_parsers::{
     parse_account_id_from_access_key_key, parse_account_id_from_account_key,
     parse_account_id_from_contract_code_key, parse_account_id_from_contract_data_key,
-    parse_account_id_from_received_data_key, parse_data_id_from_received_data_key,
-    parse_data_key_from_contract_data_key, parse_public_key_from_access_key_key,
+    parse_account_id_from_received_data_key, parse_buffered_receipt_key,
+    parse_data_id_from_received_data_key, parse_data_key_from_contract_data_key,
+    parse_public_key_from_access_key_key,
 };
 use crate::trie_key::{col, TrieKey};
-use crate::types::{AccountId, StoreKey, StoreValue};
+use crate::types::{AccountId, ShardId, StoreKey, StoreValue};
 use borsh::BorshDeserialize;
 use near_crypto::PublicKey;
 use serde_with::base64::Base64;
@@ -43,6 +44,9 @@ pub enum StateRecord {
     /// Delayed Receipt.
     /// The receipt was delayed because the shard was overwhelmed.
     DelayedReceipt(Box<Receipt>),
+    /// Outgoing receipt buffered because it exceeded the size limit of its
+    /// receiving shard, at the given index of the buffer of that shard.
+    BufferedReceipt { receiving_shard: ShardId, index: u64, receipt: Box<Receipt> },
 }
 
 impl StateRecord {
@@ -103,6 +107,20 @@ impl StateRecord {
                 let receipt = Receipt::try_from_slice(&value)?;
                 Some(StateRecord::DelayedReceipt(Box::new(receipt)))
             }
+            col::BUFFERED_RECEIPT_OR_INDICES
+                if key.len() == TrieKey::BufferedReceiptIndices.len() =>
+            {
+                None
+            }
+            col::BUFFERED_RECEIPT_OR_INDICES => {
+                let (receiving_shard, index) = parse_buffered_receipt_key(&key)?;
+                let receipt = Receipt::try_from_slice(&value)?;
+                Some(StateRecord::BufferedReceipt {
+                    receiving_shard,
+                    index,
+                    receipt: Box::new(receipt),
+                })
+            }
             _ => {
                 println!("key[0]: {} is unreachable", key[0]);
                 None
@@ -119,6 +137,7 @@ impl StateRecord {
             StateRecord::PostponedReceipt { .. } => "PostponedReceipt",
             StateRecord::ReceivedData { .. } => "ReceivedData",
             StateRecord::DelayedReceipt { .. } => "DelayedReceipt",
+            StateRecord::BufferedReceipt { .. } => "BufferedReceipt",
         }
         .to_string()
     }
@@ -152,6 +171,9 @@ impl Display for StateRecord {
             ),
             StateRecord::PostponedReceipt(receipt) => write!(f, "Postponed receipt {:?}", receipt),
             StateRecord::DelayedReceipt(receipt) => write!(f, "Delayed receipt {:?}", receipt),
+            StateRecord::BufferedReceipt { receiving_shard, index, receipt } => {
+                write!(f, "Buffered receipt #{} to shard {}: {:?}", index, receiving_shard, receipt)
+            }
         }
     }
 }
@@ -178,9 +200,9 @@ pub fn state_record_to_account_id(state_record: &StateRecord) -> &AccountId {
         | StateRecord::Contract { account_id, .. }
         | StateRecord::ReceivedData { account_id, .. }
         | StateRecord::Data { account_id, .. } => account_id,
-        StateRecord::PostponedReceipt(receipt) | StateRecord::DelayedReceipt(receipt) => {
-            &receipt.receiver_id
-        }
+        StateRecord::PostponedReceipt(receipt)
+        | StateRecord::DelayedReceipt(receipt)
+        | StateRecord::BufferedReceipt { receipt, .. } => &receipt.receiver_id,
     }
 }
 
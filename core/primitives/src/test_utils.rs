@@ -11,17 +11,21 @@ use crate::block::Block;
 use crate::block_body::BlockBody;
 use crate::block_header::BlockHeader;
 use crate::challenge::Challenges;
+use crate::chunk_validation::{ChunkEndorsement, ChunkEndorsementPayload};
 use crate::errors::EpochError;
 use crate::hash::CryptoHash;
 use crate::merkle::PartialMerkleTree;
 use crate::num_rational::Ratio;
+use crate::shard_layout::ShardLayout;
 use crate::sharding::{ShardChunkHeader, ShardChunkHeaderV3};
 use crate::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
     DeployContractAction, FunctionCallAction, SignedTransaction, StakeAction, Transaction,
     TransferAction,
 };
-use crate::types::{AccountId, Balance, EpochId, EpochInfoProvider, Gas, Nonce};
+use crate::types::{
+    AccountId, Balance, BlockHeight, EpochId, EpochInfoProvider, Gas, Nonce, ShardId,
+};
 use crate::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
 use crate::version::PROTOCOL_VERSION;
 use crate::views::{ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionStatus};
@@ -297,11 +301,13 @@ impl BlockHeader {
     }
 
     pub fn resign(&mut self, signer: &dyn ValidatorSigner) {
-        let (hash, signature) = signer.sign_block_header_parts(
-            *self.prev_hash(),
-            &self.inner_lite_bytes(),
-            &self.inner_rest_bytes(),
-        );
+        let (hash, signature) = signer
+            .sign_block_header_parts(
+                *self.prev_hash(),
+                &self.inner_lite_bytes(),
+                &self.inner_rest_bytes(),
+            )
+            .unwrap();
         match self {
             BlockHeader::BlockHeaderV1(header) => {
                 let header = Arc::make_mut(header);
@@ -453,6 +459,7 @@ impl TestBlockBuilder {
             self.block_merkle_root,
             None,
         )
+        .unwrap()
     }
 }
 
@@ -598,6 +605,10 @@ impl EpochInfoProvider for MockEpochInfoProvider {
     fn minimum_stake(&self, _prev_block_hash: &CryptoHash) -> Result<Balance, EpochError> {
         Ok(0)
     }
+
+    fn shard_layout(&self, _epoch_id: &EpochId) -> Result<ShardLayout, EpochError> {
+        Ok(ShardLayout::v0_single_shard())
+    }
 }
 
 /// Encode array of `u64` to be passed as a smart contract argument.
@@ -644,6 +655,47 @@ pub fn eth_implicit_test_account() -> AccountId {
     "0x96791e923f8cf697ad9c3290f2c9059f0231b24c".parse().unwrap()
 }
 
+/// Creates a chunk header at the given height and shard signed by the test
+/// signer of the `producer` account, with default values for everything else.
+pub fn create_test_chunk_header(
+    height_created: BlockHeight,
+    shard_id: ShardId,
+) -> ShardChunkHeader {
+    let signer = create_test_signer("producer");
+    ShardChunkHeader::V3(
+        ShardChunkHeaderV3::new(
+            CryptoHash::default(),
+            CryptoHash::default(),
+            CryptoHash::default(),
+            CryptoHash::default(),
+            0,
+            height_created,
+            shard_id,
+            0,
+            0,
+            0,
+            CryptoHash::default(),
+            CryptoHash::default(),
+            vec![],
+            &signer,
+        )
+        .unwrap(),
+    )
+}
+
+/// Creates the endorsement of `payload` signed by the test signer of `account`.
+pub fn create_test_chunk_endorsement(
+    account: &str,
+    payload: &ChunkEndorsementPayload,
+) -> ChunkEndorsement {
+    let signer = create_test_signer(account);
+    ChunkEndorsement {
+        account_id: signer.validator_id().clone(),
+        signature: signer.sign_chunk_endorsement(payload).unwrap(),
+        inner: payload.inner(),
+    }
+}
+
 impl FinalExecutionOutcomeView {
     #[track_caller]
     /// Check transaction and all transitive receipts for success status.
@@ -1,10 +1,11 @@
 use crate::errors::TxExecutionError;
 use crate::hash::{hash, CryptoHash};
 use crate::merkle::MerklePath;
-use crate::types::{AccountId, Balance, Gas, Nonce};
+use crate::types::{AccountId, Balance, Gas, Nonce, TrieNodesCount};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::{PublicKey, Signature};
 use near_fmt::{AbbrBytes, Slice};
+use near_parameters::ExtCosts;
 use near_primitives_core::serialize::{from_base64, to_base64};
 use near_primitives_core::types::Compute;
 use near_vm_runner::{ProfileDataV2, ProfileDataV3};
@@ -13,6 +14,7 @@ use serde::ser::Error as EncodeError;
 use std::borrow::Borrow;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use strum::IntoEnumIterator;
 
 pub use crate::action::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
@@ -292,6 +294,65 @@ impl ExecutionOutcomeWithIdAndProof {
     }
 }
 
+/// Detailed gas and IO profile of the execution of a receipt. Profiles are not
+/// part of the chain, a node only collects them for the receipts it executes
+/// when `save_receipt_profiles` is enabled.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Clone, Eq, Debug, Default)]
+pub struct ReceiptProfile {
+    /// Number of wasm instructions executed, in units of `regular_op_cost`.
+    pub wasm_ops: u64,
+    /// Gas spent on the execution of wasm instructions.
+    pub wasm_gas: Gas,
+    /// Usage of the host function costs charged to the receipt, in the order
+    /// of `ExtCosts`. Costs which weren't charged are omitted.
+    pub host_functions: Vec<HostFunctionUsage>,
+    /// Gas spent on sending or executing actions.
+    pub action_gas: Gas,
+    /// Trie nodes read from the database while executing the receipt.
+    pub trie_db_reads: u64,
+    /// Trie nodes read from the in-memory cache of the trie while executing
+    /// the receipt.
+    pub trie_mem_reads: u64,
+}
+
+/// Usage of a single host function cost, e.g. `storage_write_base`.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Clone, Eq, Debug)]
+pub struct HostFunctionUsage {
+    pub cost: String,
+    /// Number of units of the cost charged: calls for the base costs and
+    /// bytes for the per-byte costs.
+    pub units: u64,
+    pub gas: Gas,
+}
+
+impl ReceiptProfile {
+    pub fn new(
+        profile: &ProfileDataV3,
+        wasm_config: &near_parameters::vm::Config,
+        trie_nodes: &TrieNodesCount,
+    ) -> Self {
+        let host_functions = ExtCosts::iter()
+            .filter_map(|cost| {
+                let gas = profile.get_ext_cost(cost);
+                if gas == 0 {
+                    return None;
+                }
+                let units = gas.checked_div(cost.gas(&wasm_config.ext_costs)).unwrap_or_default();
+                Some(HostFunctionUsage { cost: format!("{:?}", cost), units, gas })
+            })
+            .collect();
+        let wasm_gas = profile.get_wasm_cost();
+        Self {
+            wasm_ops: wasm_gas.checked_div(wasm_config.regular_op_cost.into()).unwrap_or_default(),
+            wasm_gas,
+            host_functions,
+            action_gas: profile.action_gas(),
+            trie_db_reads: trie_nodes.db_reads,
+            trie_mem_reads: trie_nodes.mem_reads,
+        }
+    }
+}
+
 pub fn verify_transaction_signature(
     transaction: &SignedTransaction,
     public_keys: &[PublicKey],
@@ -414,4 +475,46 @@ mod tests {
             outcome.to_hashes()
         );
     }
+
+    #[test]
+    fn test_receipt_profile() {
+        let wasm_config = near_parameters::RuntimeConfig::test().wasm_config;
+        let sha256_base = ExtCosts::sha256_base.gas(&wasm_config.ext_costs);
+        let sha256_byte = ExtCosts::sha256_byte.gas(&wasm_config.ext_costs);
+        let mut profile_data = ProfileDataV3::default();
+        profile_data.add_ext_cost(ExtCosts::sha256_base, 2 * sha256_base);
+        profile_data.add_ext_cost(ExtCosts::sha256_byte, 64 * sha256_byte);
+        profile_data.add_action_cost(near_parameters::ActionCosts::function_call_base, 1000);
+        let host_gas = 2 * sha256_base + 64 * sha256_byte;
+        let wasm_gas = 10 * u64::from(wasm_config.regular_op_cost);
+        profile_data.compute_wasm_instruction_cost(1000 + host_gas + wasm_gas);
+
+        let profile = ReceiptProfile::new(
+            &profile_data,
+            &wasm_config,
+            &TrieNodesCount { db_reads: 3, mem_reads: 5 },
+        );
+        assert_eq!(
+            profile,
+            ReceiptProfile {
+                wasm_ops: 10,
+                wasm_gas,
+                host_functions: vec![
+                    HostFunctionUsage {
+                        cost: "sha256_base".to_string(),
+                        units: 2,
+                        gas: 2 * sha256_base
+                    },
+                    HostFunctionUsage {
+                        cost: "sha256_byte".to_string(),
+                        units: 64,
+                        gas: 64 * sha256_byte
+                    },
+                ],
+                action_gas: 1000,
+                trie_db_reads: 3,
+                trie_mem_reads: 5,
+            }
+        );
+    }
 }
@@ -5,7 +5,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::PublicKey;
 
 use crate::hash::CryptoHash;
-use crate::types::AccountId;
+use crate::types::{AccountId, ShardId};
 
 pub(crate) const ACCOUNT_DATA_SEPARATOR: u8 = b',';
 // The use of `ACCESS_KEY` as a separator is a historical artefact.
@@ -43,6 +43,12 @@ pub mod col {
     pub const DELAYED_RECEIPT_OR_INDICES: u8 = 7;
     /// This column id is used when storing Key-Value data from a contract on an `account_id`.
     pub const CONTRACT_DATA: u8 = 9;
+    /// This column id is used when storing:
+    /// * the indices of the outgoing receipts buffers (a singleton per shard)
+    /// * the buffered outgoing receipts themselves
+    /// Like `DELAYED_RECEIPT_OR_INDICES`, it is shared between two key types, which is valid
+    /// because `TrieKey::BufferedReceipt` is always longer than `TrieKey::BufferedReceiptIndices`.
+    pub const BUFFERED_RECEIPT_OR_INDICES: u8 = 10;
     /// All columns
     pub const NON_DELAYED_RECEIPT_COLUMNS: [(u8, &str); 8] = [
         (ACCOUNT, "Account"),
@@ -91,6 +97,13 @@ pub enum TrieKey {
     /// Used to store a key-value record `Vec<u8>` within a contract deployed on a given `AccountId`
     /// and a given key.
     ContractData { account_id: AccountId, key: Vec<u8> },
+    /// Used to store the indices of the buffers of outgoing receipts which exceeded the size
+    /// limit of their receiving shard (`primitives::receipt::BufferedReceiptIndices`).
+    /// NOTE: It is a singleton per shard.
+    BufferedReceiptIndices,
+    /// Used to store a buffered outgoing receipt `primitives::receipt::Receipt` for a given
+    /// receiving shard and a given index `u64` in the buffer of that shard.
+    BufferedReceipt { receiving_shard: ShardId, index: u64 },
 }
 
 /// Provides `len` function.
@@ -150,6 +163,10 @@ impl TrieKey {
                     + ACCOUNT_DATA_SEPARATOR.len()
                     + key.len()
             }
+            TrieKey::BufferedReceiptIndices => col::BUFFERED_RECEIPT_OR_INDICES.len(),
+            TrieKey::BufferedReceipt { .. } => {
+                col::BUFFERED_RECEIPT_OR_INDICES.len() + size_of::<ShardId>() + size_of::<u64>()
+            }
         }
     }
 
@@ -209,6 +226,14 @@ impl TrieKey {
                 buf.push(ACCOUNT_DATA_SEPARATOR);
                 buf.extend(key);
             }
+            TrieKey::BufferedReceiptIndices => {
+                buf.push(col::BUFFERED_RECEIPT_OR_INDICES);
+            }
+            TrieKey::BufferedReceipt { receiving_shard, index } => {
+                buf.push(col::BUFFERED_RECEIPT_OR_INDICES);
+                buf.extend(&receiving_shard.to_le_bytes());
+                buf.extend(&index.to_le_bytes());
+            }
         };
         debug_assert_eq!(expected_len, buf.len() - start_len);
     }
@@ -232,6 +257,8 @@ impl TrieKey {
             TrieKey::DelayedReceiptIndices => None,
             TrieKey::DelayedReceipt { .. } => None,
             TrieKey::ContractData { account_id, .. } => Some(account_id.clone()),
+            TrieKey::BufferedReceiptIndices => None,
+            TrieKey::BufferedReceipt { .. } => None,
         }
     }
 }
@@ -403,6 +430,20 @@ pub mod trie_key_parsers {
         parse_account_id_from_trie_key_with_separator(col::RECEIVED_DATA, raw_key, "ReceivedData")
     }
 
+    /// Returns the receiving shard and the index of a `TrieKey::BufferedReceipt`.
+    pub fn parse_buffered_receipt_key(raw_key: &[u8]) -> Result<(ShardId, u64), std::io::Error> {
+        let key_len = TrieKey::BufferedReceipt { receiving_shard: 0, index: 0 }.len();
+        if raw_key.len() != key_len || raw_key[0] != col::BUFFERED_RECEIPT_OR_INDICES {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "raw key is not a TrieKey::BufferedReceipt",
+            ));
+        }
+        let receiving_shard = ShardId::from_le_bytes(raw_key[1..9].try_into().unwrap());
+        let index = u64::from_le_bytes(raw_key[9..17].try_into().unwrap());
+        Ok((receiving_shard, index))
+    }
+
     pub fn parse_data_id_from_received_data_key(
         raw_key: &[u8],
         account_id: &AccountId,
@@ -650,6 +691,21 @@ mod tests {
         assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
     }
 
+    #[test]
+    fn test_key_for_buffered_receipts_consistency() {
+        let key = TrieKey::BufferedReceiptIndices;
+        let raw_key = key.to_vec();
+        assert_eq!(raw_key.len(), key.len());
+        assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
+        let key = TrieKey::BufferedReceipt { receiving_shard: 3, index: 258 };
+        let raw_key = key.to_vec();
+        assert_eq!(raw_key.len(), key.len());
+        assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
+        assert_eq!(trie_key_parsers::parse_buffered_receipt_key(&raw_key).unwrap(), (3, 258));
+        let raw_key = TrieKey::BufferedReceiptIndices.to_vec();
+        assert!(trie_key_parsers::parse_buffered_receipt_key(&raw_key).is_err());
+    }
+
     #[test]
     fn test_account_id_from_trie_key() {
         for account_id_str in OK_ACCOUNT_IDS {
@@ -4,7 +4,8 @@ use crate::errors::EpochError;
 use crate::hash::CryptoHash;
 use crate::receipt::Receipt;
 use crate::serialize::dec_format;
-use crate::trie_key::TrieKey;
+use crate::shard_layout::ShardLayout;
+use crate::trie_key::{col, TrieKey};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::PublicKey;
 /// Reexport primitive types
@@ -246,6 +247,64 @@ pub enum StateChangesRequest {
     AllAccessKeyChanges { account_ids: Vec<AccountId> },
     ContractCodeChanges { account_ids: Vec<AccountId> },
     DataChanges { account_ids: Vec<AccountId>, key_prefix: StoreKey },
+    FilteredChanges { filter: StateChangesFilter },
+}
+
+/// Selects the state changes of the accounts whose id starts with one of `account_id_prefixes`,
+/// or of all the accounts if there are none. If `key_prefixes` are given, only the contract data
+/// changes whose key starts with one of them are selected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateChangesFilter {
+    pub account_id_prefixes: Vec<String>,
+    pub key_prefixes: Vec<StoreKey>,
+}
+
+impl StateChangesFilter {
+    /// Returns whether a change of the account, with the given key for contract data changes,
+    /// is selected.
+    pub fn matches(&self, account_id: &AccountId, data_key: Option<&[u8]>) -> bool {
+        let account_matches = self.account_id_prefixes.is_empty()
+            || self
+                .account_id_prefixes
+                .iter()
+                .any(|prefix| account_id.as_str().starts_with(prefix));
+        let key_matches = self.key_prefixes.is_empty()
+            || data_key.map_or(false, |key| {
+                self.key_prefixes.iter().any(|prefix| key.starts_with(prefix))
+            });
+        account_matches && key_matches
+    }
+
+    /// Returns the prefixes of the raw trie keys which cover all the selected changes, or None if
+    /// all the accounts are selected. The changes under the prefixes still need to be checked
+    /// with `matches` for the key prefixes.
+    pub fn raw_trie_key_prefixes(&self) -> Option<Vec<Vec<u8>>> {
+        if self.account_id_prefixes.is_empty() {
+            return None;
+        }
+        let mut account_id_prefixes: Vec<&str> =
+            self.account_id_prefixes.iter().map(String::as_str).collect();
+        // Prefixes extending a shorter prefix are covered by the shorter one.
+        account_id_prefixes.sort_unstable();
+        account_id_prefixes.dedup_by(|prefix, shorter| prefix.starts_with(*shorter));
+        let cols: &[u8] = if self.key_prefixes.is_empty() {
+            &[col::ACCOUNT, col::CONTRACT_CODE, col::ACCESS_KEY, col::CONTRACT_DATA]
+        } else {
+            &[col::CONTRACT_DATA]
+        };
+        Some(
+            cols.iter()
+                .flat_map(|&col| {
+                    account_id_prefixes.iter().map(move |prefix| {
+                        let mut raw_prefix = Vec::with_capacity(1 + prefix.len());
+                        raw_prefix.push(col);
+                        raw_prefix.extend_from_slice(prefix.as_bytes());
+                        raw_prefix
+                    })
+                })
+                .collect(),
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -273,6 +332,15 @@ impl StateChangeValue {
             | StateChangeValue::ContractCodeDeletion { account_id } => account_id,
         }
     }
+
+    /// Key of the contract data changes.
+    pub fn data_key(&self) -> Option<&[u8]> {
+        match &self {
+            StateChangeValue::DataUpdate { key, .. }
+            | StateChangeValue::DataDeletion { key, .. } => Some(key.as_slice()),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -371,6 +439,8 @@ impl StateChanges {
                 TrieKey::PostponedReceipt { .. } => {}
                 TrieKey::DelayedReceiptIndices => {}
                 TrieKey::DelayedReceipt { .. } => {}
+                TrieKey::BufferedReceiptIndices => {}
+                TrieKey::BufferedReceipt { .. } => {}
             }
         }
 
@@ -992,7 +1062,7 @@ pub enum TransactionOrReceiptId {
 
 /// Provides information about current epoch validators.
 /// Used to break dependency between epoch manager and runtime.
-pub trait EpochInfoProvider {
+pub trait EpochInfoProvider: Send + Sync {
     /// Get current stake of a validator in the given epoch.
     /// If the account is not a validator, returns `None`.
     fn validator_stake(
@@ -1010,6 +1080,9 @@ pub trait EpochInfoProvider {
     ) -> Result<Balance, EpochError>;
 
     fn minimum_stake(&self, prev_block_hash: &CryptoHash) -> Result<Balance, EpochError>;
+
+    /// Get the shard layout of the given epoch.
+    fn shard_layout(&self, epoch_id: &EpochId) -> Result<ShardLayout, EpochError>;
 }
 
 /// Mode of the trie cache.
@@ -1053,6 +1126,7 @@ mod tests {
     use near_primitives_core::types::Balance;
 
     use super::validator_stake::ValidatorStake;
+    use super::StateChangesFilter;
 
     fn new_validator_stake(stake: Balance) -> ValidatorStake {
         ValidatorStake::new(
@@ -1075,4 +1149,45 @@ mod tests {
         assert_eq!(new_validator_stake(10).partial_mandate_weight(5), 0);
         assert_eq!(new_validator_stake(12).partial_mandate_weight(5), 2);
     }
+
+    #[test]
+    fn test_state_changes_filter() {
+        let app = "app.near".parse().unwrap();
+        let other = "other.near".parse().unwrap();
+
+        let filter = StateChangesFilter::default();
+        assert!(filter.matches(&app, None));
+        assert!(filter.matches(&other, Some(b"key")));
+        assert_eq!(filter.raw_trie_key_prefixes(), None);
+
+        let filter = StateChangesFilter {
+            account_id_prefixes: vec!["app".to_string()],
+            key_prefixes: vec![b"k".to_vec().into()],
+        };
+        assert!(filter.matches(&app, Some(b"key")));
+        assert!(!filter.matches(&app, Some(b"value")));
+        assert!(!filter.matches(&app, None));
+        assert!(!filter.matches(&other, Some(b"key")));
+        assert_eq!(filter.raw_trie_key_prefixes(), Some(vec![b"\x09app".to_vec()]));
+
+        let filter = StateChangesFilter {
+            account_id_prefixes: vec!["app.".to_string(), "other".to_string(), "app".to_string()],
+            key_prefixes: vec![],
+        };
+        assert!(filter.matches(&app, None));
+        assert!(filter.matches(&other, Some(b"key")));
+        assert_eq!(
+            filter.raw_trie_key_prefixes(),
+            Some(vec![
+                b"\x00app".to_vec(),
+                b"\x00other".to_vec(),
+                b"\x01app".to_vec(),
+                b"\x01other".to_vec(),
+                b"\x02app".to_vec(),
+                b"\x02other".to_vec(),
+                b"\x09app".to_vec(),
+                b"\x09other".to_vec(),
+            ])
+        );
+    }
 }
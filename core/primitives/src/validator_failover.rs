@@ -0,0 +1,83 @@
+use crate::types::{AccountId, BlockHeight};
+use crate::validator_signer::{ValidatorSigner, ValidatorSignerError};
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::{PublicKey, Signature};
+
+/// Heartbeat exchanged by two nodes holding the key of the same validator, so
+/// that only one of them signs at a time. Signed with the validator key, which
+/// both nodes hold.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct FailoverHeartbeat {
+    pub inner: FailoverHeartbeatInner,
+    pub signature: Signature,
+}
+
+impl FailoverHeartbeat {
+    pub fn new(
+        inner: FailoverHeartbeatInner,
+        signer: &dyn ValidatorSigner,
+    ) -> Result<Self, ValidatorSignerError> {
+        let signature = signer.sign_failover_heartbeat(&inner)?;
+        Ok(Self { inner, signature })
+    }
+
+    /// Verifies that the heartbeat was signed by the owner of `public_key`.
+    pub fn verify(&self, public_key: &PublicKey) -> bool {
+        let data = borsh::to_vec(&self.inner).unwrap();
+        self.signature.verify(&data, public_key)
+    }
+}
+
+/// This is the part of the heartbeat that is actually being signed.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct FailoverHeartbeatInner {
+    pub account_id: AccountId,
+    /// Whether the sender is the primary node, which wins when both nodes
+    /// sign at the same time.
+    pub primary: bool,
+    /// Whether the sender signs with the validator key.
+    pub signing: bool,
+    /// Height of the head of the sender.
+    pub head_height: BlockHeight,
+    /// Heights the sender signed up to, which the other node must not sign
+    /// at when it starts signing.
+    pub signed_heights: ValidatorSignedHeights,
+    /// When the heartbeat was sent, in milliseconds since the Unix epoch, so
+    /// that replayed heartbeats are rejected.
+    pub timestamp_ms: u64,
+}
+
+/// Largest heights of the blocks, approvals, chunks and chunk endorsements
+/// signed with a validator key.
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+)]
+pub struct ValidatorSignedHeights {
+    pub block_height: BlockHeight,
+    /// Target height of the approvals.
+    pub approval_height: BlockHeight,
+    pub chunk_height: BlockHeight,
+    /// Height created of the endorsed chunks.
+    pub endorsement_height: BlockHeight,
+}
+
+impl ValidatorSignedHeights {
+    /// Returns the largest of each height.
+    pub fn max(&self, other: &Self) -> Self {
+        Self {
+            block_height: self.block_height.max(other.block_height),
+            approval_height: self.approval_height.max(other.approval_height),
+            chunk_height: self.chunk_height.max(other.chunk_height),
+            endorsement_height: self.endorsement_height.max(other.endorsement_height),
+        }
+    }
+}
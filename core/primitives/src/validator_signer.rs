@@ -5,12 +5,19 @@ use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
 
 use crate::block::{Approval, ApprovalInner, BlockHeader};
 use crate::challenge::ChallengeBody;
-use crate::chunk_validation::ChunkEndorsementInner;
+use crate::chunk_validation::ChunkEndorsementPayload;
 use crate::hash::CryptoHash;
 use crate::network::{AnnounceAccount, PeerId};
 use crate::sharding::ChunkHash;
 use crate::telemetry::TelemetryInfo;
 use crate::types::{AccountId, BlockHeight, EpochId};
+use crate::validator_failover::FailoverHeartbeatInner;
+
+/// Error of a validator signer which failed to sign, e.g. because its remote
+/// signing service didn't respond. Whatever was being signed must be skipped.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("validator signer failed: {0}")]
+pub struct ValidatorSignerError(pub String);
 
 /// Validator signer that is used to sign blocks and approvals.
 pub trait ValidatorSigner: Sync + Send {
@@ -20,8 +27,20 @@ pub trait ValidatorSigner: Sync + Send {
     /// Public key that identifies this validator.
     fn public_key(&self) -> PublicKey;
 
+    /// Key the validator is rotating to, if any. During the rotation the
+    /// validator may need to sign with either key, depending on which one is
+    /// registered for the epoch it is signing for.
+    fn next_public_key(&self) -> Option<PublicKey>;
+
+    /// Returns a signer for the same validator which signs with the given
+    /// key, or `None` if the validator doesn't hold the key.
+    fn signer_for_public_key(&self, public_key: &PublicKey) -> Option<Arc<dyn ValidatorSigner>>;
+
     /// Serializes telemetry info to JSON and signs it, returning JSON with "signature" field.
-    fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value;
+    fn sign_telemetry(
+        &self,
+        info: &TelemetryInfo,
+    ) -> Result<serde_json::Value, ValidatorSignerError>;
 
     /// Signs given parts of the header.
     fn sign_block_header_parts(
@@ -29,19 +48,35 @@ pub trait ValidatorSigner: Sync + Send {
         prev_hash: CryptoHash,
         inner_lite: &[u8],
         inner_rest: &[u8],
-    ) -> (CryptoHash, Signature);
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError>;
 
     /// Signs given inner of the chunk header.
-    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Signature;
+    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Result<Signature, ValidatorSignerError>;
 
     /// Signs approval of given parent hash and reference hash.
-    fn sign_approval(&self, inner: &ApprovalInner, target_height: BlockHeight) -> Signature;
+    fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, ValidatorSignerError>;
 
     /// Signs approval of the given chunk.
-    fn sign_chunk_endorsement(&self, inner: &ChunkEndorsementInner) -> Signature;
+    fn sign_chunk_endorsement(
+        &self,
+        payload: &ChunkEndorsementPayload,
+    ) -> Result<Signature, ValidatorSignerError>;
 
     /// Signs challenge body.
-    fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature);
+    fn sign_challenge(
+        &self,
+        challenge_body: &ChallengeBody,
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError>;
+
+    /// Signs a heartbeat sent to the other node holding the validator key.
+    fn sign_failover_heartbeat(
+        &self,
+        inner: &FailoverHeartbeatInner,
+    ) -> Result<Signature, ValidatorSignerError>;
 
     /// Signs account announce.
     fn sign_account_announce(
@@ -49,7 +84,7 @@ pub trait ValidatorSigner: Sync + Send {
         account_id: &AccountId,
         peer_id: &PeerId,
         epoch_id: &EpochId,
-    ) -> Signature;
+    ) -> Result<Signature, ValidatorSignerError>;
 
     /// Signs a proto-serialized AccountKeyPayload (see
     /// chain/network/src/network_protocol/network.proto).
@@ -62,12 +97,15 @@ pub trait ValidatorSigner: Sync + Send {
     /// used only for networking purposes and are not persisted on chain.
     /// Moving to proto serialization for stuff stored on chain would be way
     /// harder.
-    fn sign_account_key_payload(&self, proto_bytes: &[u8]) -> Signature;
+    fn sign_account_key_payload(
+        &self,
+        proto_bytes: &[u8],
+    ) -> Result<Signature, ValidatorSignerError>;
 
     fn compute_vrf_with_proof(
         &self,
         data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof);
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), ValidatorSignerError>;
 
     /// Used by test infrastructure, only implement if make sense for testing otherwise raise `unimplemented`.
     fn write_to_file(&self, path: &Path) -> std::io::Result<()>;
@@ -90,8 +128,23 @@ impl ValidatorSigner for EmptyValidatorSigner {
         PublicKey::empty(KeyType::ED25519)
     }
 
-    fn sign_telemetry(&self, _info: &TelemetryInfo) -> serde_json::Value {
-        serde_json::Value::default()
+    fn next_public_key(&self) -> Option<PublicKey> {
+        None
+    }
+
+    fn signer_for_public_key(&self, public_key: &PublicKey) -> Option<Arc<dyn ValidatorSigner>> {
+        if public_key == &self.public_key() {
+            Some(Arc::new(Self { account_id: self.account_id.clone() }))
+        } else {
+            None
+        }
+    }
+
+    fn sign_telemetry(
+        &self,
+        _info: &TelemetryInfo,
+    ) -> Result<serde_json::Value, ValidatorSignerError> {
+        Ok(serde_json::Value::default())
     }
 
     fn sign_block_header_parts(
@@ -99,25 +152,42 @@ impl ValidatorSigner for EmptyValidatorSigner {
         prev_hash: CryptoHash,
         inner_lite: &[u8],
         inner_rest: &[u8],
-    ) -> (CryptoHash, Signature) {
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
         let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
-        (hash, Signature::default())
+        Ok((hash, Signature::default()))
+    }
+
+    fn sign_chunk_hash(&self, _chunk_hash: &ChunkHash) -> Result<Signature, ValidatorSignerError> {
+        Ok(Signature::default())
     }
 
-    fn sign_chunk_hash(&self, _chunk_hash: &ChunkHash) -> Signature {
-        Signature::default()
+    fn sign_approval(
+        &self,
+        _inner: &ApprovalInner,
+        _target_height: BlockHeight,
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(Signature::default())
     }
 
-    fn sign_approval(&self, _inner: &ApprovalInner, _target_height: BlockHeight) -> Signature {
-        Signature::default()
+    fn sign_chunk_endorsement(
+        &self,
+        _payload: &ChunkEndorsementPayload,
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(Signature::default())
     }
 
-    fn sign_chunk_endorsement(&self, _inner: &ChunkEndorsementInner) -> Signature {
-        Signature::default()
+    fn sign_challenge(
+        &self,
+        challenge_body: &ChallengeBody,
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
+        Ok((CryptoHash::hash_borsh(challenge_body), Signature::default()))
     }
 
-    fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature) {
-        (CryptoHash::hash_borsh(challenge_body), Signature::default())
+    fn sign_failover_heartbeat(
+        &self,
+        _inner: &FailoverHeartbeatInner,
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(Signature::default())
     }
 
     fn sign_account_announce(
@@ -125,18 +195,21 @@ impl ValidatorSigner for EmptyValidatorSigner {
         _account_id: &AccountId,
         _peer_id: &PeerId,
         _epoch_id: &EpochId,
-    ) -> Signature {
-        Signature::default()
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(Signature::default())
     }
 
-    fn sign_account_key_payload(&self, _proto_bytes: &[u8]) -> Signature {
-        Signature::default()
+    fn sign_account_key_payload(
+        &self,
+        _proto_bytes: &[u8],
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(Signature::default())
     }
 
     fn compute_vrf_with_proof(
         &self,
         _data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), ValidatorSignerError> {
         unimplemented!()
     }
 
@@ -150,17 +223,20 @@ impl ValidatorSigner for EmptyValidatorSigner {
 pub struct InMemoryValidatorSigner {
     account_id: AccountId,
     signer: Arc<dyn Signer>,
+    /// Key the validator is rotating to. Signing methods always use `signer`;
+    /// use `signer_for_public_key` to sign with this key instead.
+    next_signer: Option<Arc<dyn Signer>>,
 }
 
 impl InMemoryValidatorSigner {
     pub fn from_random(account_id: AccountId, key_type: KeyType) -> Self {
         let signer = Arc::new(InMemorySigner::from_random(account_id.clone(), key_type));
-        Self { account_id, signer }
+        Self { account_id, signer, next_signer: None }
     }
 
     pub fn from_seed(account_id: AccountId, key_type: KeyType, seed: &str) -> Self {
         let signer = Arc::new(InMemorySigner::from_seed(account_id.clone(), key_type, seed));
-        Self { account_id, signer }
+        Self { account_id, signer, next_signer: None }
     }
 
     pub fn public_key(&self) -> PublicKey {
@@ -169,7 +245,38 @@ impl InMemoryValidatorSigner {
 
     pub fn from_file(path: &Path) -> std::io::Result<Self> {
         let signer = InMemorySigner::from_file(path)?;
-        Ok(Self { account_id: signer.account_id.clone(), signer: Arc::new(signer) })
+        Ok(Self {
+            account_id: signer.account_id.clone(),
+            signer: Arc::new(signer),
+            next_signer: None,
+        })
+    }
+
+    /// Loads the validator key from `path` and, if given, the key the
+    /// validator is rotating to from `next_key_path`. Both keys must belong to
+    /// the same account.
+    pub fn from_files(path: &Path, next_key_path: Option<&Path>) -> std::io::Result<Self> {
+        let mut validator_signer = Self::from_file(path)?;
+        if let Some(next_key_path) = next_key_path {
+            let next_signer = InMemorySigner::from_file(next_key_path)?;
+            if next_signer.account_id != validator_signer.account_id {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "next validator key is for account {}, expected {}",
+                        next_signer.account_id, validator_signer.account_id
+                    ),
+                ));
+            }
+            validator_signer.next_signer = Some(Arc::new(next_signer));
+        }
+        Ok(validator_signer)
+    }
+
+    /// Sets the key the validator is rotating to.
+    pub fn with_next_signer(mut self, next_signer: Arc<dyn Signer>) -> Self {
+        self.next_signer = Some(next_signer);
+        self
     }
 }
 
@@ -182,11 +289,32 @@ impl ValidatorSigner for InMemoryValidatorSigner {
         self.signer.public_key()
     }
 
-    fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value {
+    fn next_public_key(&self) -> Option<PublicKey> {
+        self.next_signer.as_ref().map(|signer| signer.public_key())
+    }
+
+    fn signer_for_public_key(&self, public_key: &PublicKey) -> Option<Arc<dyn ValidatorSigner>> {
+        if public_key == &self.signer.public_key() {
+            return Some(Arc::new(self.clone()));
+        }
+        match &self.next_signer {
+            Some(next_signer) if public_key == &next_signer.public_key() => Some(Arc::new(Self {
+                account_id: self.account_id.clone(),
+                signer: next_signer.clone(),
+                next_signer: None,
+            })),
+            _ => None,
+        }
+    }
+
+    fn sign_telemetry(
+        &self,
+        info: &TelemetryInfo,
+    ) -> Result<serde_json::Value, ValidatorSignerError> {
         let mut value = serde_json::to_value(info).expect("Telemetry must serialize to JSON");
         let content = serde_json::to_string(&value).expect("Telemetry must serialize to JSON");
         value["signature"] = self.signer.sign(content.as_bytes()).to_string().into();
-        value
+        Ok(value)
     }
 
     fn sign_block_header_parts(
@@ -194,27 +322,44 @@ impl ValidatorSigner for InMemoryValidatorSigner {
         prev_hash: CryptoHash,
         inner_lite: &[u8],
         inner_rest: &[u8],
-    ) -> (CryptoHash, Signature) {
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
         let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
-        (hash, self.signer.sign(hash.as_ref()))
+        Ok((hash, self.signer.sign(hash.as_ref())))
     }
 
-    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Signature {
-        self.signer.sign(chunk_hash.as_ref())
+    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Result<Signature, ValidatorSignerError> {
+        Ok(self.signer.sign(chunk_hash.as_ref()))
     }
 
-    fn sign_approval(&self, inner: &ApprovalInner, target_height: BlockHeight) -> Signature {
-        self.signer.sign(&Approval::get_data_for_sig(inner, target_height))
+    fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(self.signer.sign(&Approval::get_data_for_sig(inner, target_height)))
     }
 
-    fn sign_chunk_endorsement(&self, inner: &ChunkEndorsementInner) -> Signature {
-        self.signer.sign(&borsh::to_vec(inner).unwrap())
+    fn sign_chunk_endorsement(
+        &self,
+        payload: &ChunkEndorsementPayload,
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(self.signer.sign(&payload.signed_data()))
     }
 
-    fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature) {
+    fn sign_challenge(
+        &self,
+        challenge_body: &ChallengeBody,
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
         let hash = CryptoHash::hash_borsh(challenge_body);
         let signature = self.signer.sign(hash.as_ref());
-        (hash, signature)
+        Ok((hash, signature))
+    }
+
+    fn sign_failover_heartbeat(
+        &self,
+        inner: &FailoverHeartbeatInner,
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(self.signer.sign(&borsh::to_vec(inner).unwrap()))
     }
 
     fn sign_account_announce(
@@ -222,23 +367,60 @@ impl ValidatorSigner for InMemoryValidatorSigner {
         account_id: &AccountId,
         peer_id: &PeerId,
         epoch_id: &EpochId,
-    ) -> Signature {
+    ) -> Result<Signature, ValidatorSignerError> {
         let hash = AnnounceAccount::build_header_hash(account_id, peer_id, epoch_id);
-        self.signer.sign(hash.as_ref())
+        Ok(self.signer.sign(hash.as_ref()))
     }
 
-    fn sign_account_key_payload(&self, proto_bytes: &[u8]) -> Signature {
-        self.signer.sign(proto_bytes)
+    fn sign_account_key_payload(
+        &self,
+        proto_bytes: &[u8],
+    ) -> Result<Signature, ValidatorSignerError> {
+        Ok(self.signer.sign(proto_bytes))
     }
 
     fn compute_vrf_with_proof(
         &self,
         data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
-        self.signer.compute_vrf_with_proof(data)
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), ValidatorSignerError> {
+        Ok(self.signer.compute_vrf_with_proof(data))
     }
 
     fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
         self.signer.write_to_file(path)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::PROTOCOL_VERSION;
+
+    #[test]
+    fn test_signer_for_public_key() {
+        let account_id: AccountId = "test".parse().unwrap();
+        let next_signer =
+            Arc::new(InMemorySigner::from_seed(account_id.clone(), KeyType::ED25519, "next"));
+        let signer = InMemoryValidatorSigner::from_seed(account_id, KeyType::ED25519, "current")
+            .with_next_signer(next_signer.clone());
+        assert_eq!(signer.next_public_key(), Some(next_signer.public_key()));
+
+        let current = signer.signer_for_public_key(&signer.public_key()).unwrap();
+        assert_eq!(current.public_key(), signer.public_key());
+        let next = signer.signer_for_public_key(&next_signer.public_key()).unwrap();
+        assert_eq!(next.public_key(), next_signer.public_key());
+        assert_eq!(next.validator_id(), signer.validator_id());
+
+        let payload = ChunkEndorsementPayload::new(
+            ChunkHash::default(),
+            EpochId::default(),
+            0,
+            PROTOCOL_VERSION,
+        );
+        let signature = next.sign_chunk_endorsement(&payload).unwrap();
+        assert!(signature.verify(&payload.signed_data(), &next_signer.public_key()));
+
+        let other = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "other");
+        assert!(signer.signer_for_public_key(&other.public_key()).is_none());
+    }
+}
@@ -27,13 +27,13 @@ use crate::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
     DeployContractAction, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithIdAndProof,
     ExecutionStatus, FunctionCallAction, PartialExecutionOutcome, PartialExecutionStatus,
-    SignedTransaction, StakeAction, TransferAction,
+    ReceiptProfile, SignedTransaction, StakeAction, TransferAction,
 };
 use crate::types::{
-    AccountId, AccountWithPublicKey, Balance, BlockHeight, EpochHeight, EpochId, FunctionArgs, Gas,
-    Nonce, NumBlocks, ShardId, StateChangeCause, StateChangeKind, StateChangeValue,
-    StateChangeWithCause, StateChangesRequest, StateRoot, StorageUsage, StoreKey, StoreValue,
-    ValidatorKickoutReason,
+    AccountId, AccountWithPublicKey, Balance, BlockHeight, BlockHeightDelta, EpochHeight, EpochId,
+    FunctionArgs, Gas, Nonce, NumBlocks, ShardId, StateChangeCause, StateChangeKind,
+    StateChangeValue, StateChangeWithCause, StateChangesFilter, StateChangesRequest, StateRoot,
+    StorageUsage, StoreKey, StoreValue, ValidatorKickoutReason,
 };
 use crate::version::{ProtocolVersion, Version};
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -236,6 +236,9 @@ pub struct ViewStateResult {
 pub struct CallResult {
     pub result: Vec<u8>,
     pub logs: Vec<String>,
+    /// Gas burnt by the execution of the view call.
+    #[serde(default)]
+    pub gas_burnt: Gas,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -505,6 +508,8 @@ pub struct NetworkRoutesView {
 pub struct ShardSyncDownloadView {
     pub downloads: Vec<DownloadStatusView>,
     pub status: String,
+    /// Estimated time left to download the state parts, if known.
+    pub eta_seconds: Option<u64>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
@@ -686,6 +691,48 @@ pub struct DetailedDebugStatus {
     pub current_head_status: BlockStatusView,
     pub current_header_head_status: BlockStatusView,
     pub block_production_delay_millis: u64,
+    #[serde(default)]
+    pub chunk_validation_status: ChunkValidationStatusView,
+}
+
+/// State of chunk validation on this node: witness production for the chunks
+/// it produces, the in-memory tries, and the endorsements it collected.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct ChunkValidationStatusView {
+    pub shards: Vec<ShardChunkValidationStatusView>,
+    /// Endorsements collected for the new chunks of the last blocks, latest
+    /// first.
+    pub recent_chunk_endorsements: Vec<ChunkEndorsementCountView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ShardChunkValidationStatusView {
+    pub shard_id: ShardId,
+    /// Number of produced chunks whose state witness is yet to be produced.
+    pub witness_production_backlog: usize,
+    /// The last state witnesses produced for the shard, latest first.
+    pub recent_witnesses: Vec<ProducedWitnessView>,
+    /// Whether the in-memory trie of the shard is loaded.
+    pub memtrie_loaded: bool,
+    /// Bytes reserved by the arena of the in-memory trie, if loaded.
+    pub memtrie_memory_usage_bytes: Option<u64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProducedWitnessView {
+    pub chunk_hash: ChunkHash,
+    pub height_created: BlockHeight,
+    pub size_bytes: u64,
+    pub production_time_ms: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ChunkEndorsementCountView {
+    pub chunk_hash: ChunkHash,
+    pub shard_id: ShardId,
+    pub height_created: BlockHeight,
+    pub num_endorsements: usize,
+    pub num_chunk_validators: usize,
 }
 
 // TODO: add more information to status.
@@ -1130,6 +1177,61 @@ impl BlockView {
     }
 }
 
+/// Optional parts of the block summaries returned by the
+/// `EXPERIMENTAL_block_range` RPC method.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockRangeField {
+    /// The most used fields of the header, see `BlockHeaderSummaryView`.
+    Header,
+    /// Whether the block includes a new chunk for each shard.
+    ChunkMask,
+}
+
+/// Summary of a block, see `BlockRangeField` for the optional parts.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockSummaryView {
+    pub height: BlockHeight,
+    pub hash: CryptoHash,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<BlockHeaderSummaryView>,
+    /// Whether the block includes a new chunk, by shard id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_mask: Option<Vec<bool>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeaderSummaryView {
+    pub prev_hash: CryptoHash,
+    pub epoch_id: CryptoHash,
+    #[serde(with = "dec_format")]
+    pub timestamp_nanosec: u64,
+    #[serde(with = "dec_format")]
+    pub gas_price: Balance,
+    pub chunks_included: u64,
+    pub latest_protocol_version: ProtocolVersion,
+}
+
+impl BlockSummaryView {
+    pub fn new(header: &BlockHeader, fields: &[BlockRangeField]) -> Self {
+        Self {
+            height: header.height(),
+            hash: *header.hash(),
+            header: fields.contains(&BlockRangeField::Header).then(|| BlockHeaderSummaryView {
+                prev_hash: *header.prev_hash(),
+                epoch_id: header.epoch_id().0,
+                timestamp_nanosec: header.raw_timestamp(),
+                gas_price: header.next_gas_price(),
+                chunks_included: header.chunks_included(),
+                latest_protocol_version: header.latest_protocol_version(),
+            }),
+            chunk_mask: fields
+                .contains(&BlockRangeField::ChunkMask)
+                .then(|| header.chunk_mask().to_vec()),
+        }
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct ChunkView {
     pub author: AccountId,
@@ -1780,6 +1882,101 @@ pub struct FinalExecutionOutcomeWithReceiptView {
     pub final_outcome: FinalExecutionOutcomeView,
     /// Receipts generated from the transaction
     pub receipts: Vec<ReceiptView>,
+    /// Gas and IO profiles of the receipts, for the receipts whose profiles
+    /// were saved by the node. See `save_receipt_profiles`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub receipt_profiles: Vec<ReceiptProfileView>,
+}
+
+/// Gas and IO profile of the execution of a receipt.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct ReceiptProfileView {
+    pub receipt_id: CryptoHash,
+    /// The block in which the receipt was executed.
+    pub block_hash: CryptoHash,
+    /// Number of wasm instructions executed, in units of `regular_op_cost`.
+    pub wasm_ops: u64,
+    #[serde(with = "dec_format")]
+    pub wasm_gas: Gas,
+    /// Host function costs charged to the receipt.
+    pub host_functions: Vec<HostFunctionUsageView>,
+    #[serde(with = "dec_format")]
+    pub action_gas: Gas,
+    /// Trie nodes read from the database.
+    pub trie_db_reads: u64,
+    /// Trie nodes read from the in-memory cache of the trie.
+    pub trie_mem_reads: u64,
+}
+
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct HostFunctionUsageView {
+    pub cost: String,
+    /// Number of units of the cost charged: calls for the base costs, or
+    /// bytes for the per-byte costs.
+    pub units: u64,
+    #[serde(with = "dec_format")]
+    pub gas_used: Gas,
+}
+
+impl ReceiptProfileView {
+    pub fn new(receipt_id: CryptoHash, block_hash: CryptoHash, profile: ReceiptProfile) -> Self {
+        Self {
+            receipt_id,
+            block_hash,
+            wasm_ops: profile.wasm_ops,
+            wasm_gas: profile.wasm_gas,
+            host_functions: profile
+                .host_functions
+                .into_iter()
+                .map(|usage| HostFunctionUsageView {
+                    cost: usage.cost.to_ascii_uppercase(),
+                    units: usage.units,
+                    gas_used: usage.gas,
+                })
+                .collect(),
+            action_gas: profile.action_gas,
+            trie_db_reads: profile.trie_db_reads,
+            trie_mem_reads: profile.trie_mem_reads,
+        }
+    }
+}
+
+/// Result of the dry run of a transaction on the state of a block, see the
+/// `EXPERIMENTAL_estimate_tx` RPC method. Nothing is committed by the dry run.
+#[derive(PartialEq, Eq, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TransactionEstimateView {
+    /// Gas burnt by the transaction and its receipt.
+    pub gas_burnt: Gas,
+    /// Tokens burnt by the transaction and its receipt.
+    #[serde(with = "dec_format")]
+    pub tokens_burnt: Balance,
+    /// None if the transaction is invalid.
+    pub transaction_outcome: Option<ExecutionOutcomeView>,
+    /// None if the transaction is invalid or if the node doesn't track the
+    /// shard of the receiver, in which case the receipt isn't executed.
+    pub receipt_outcome: Option<ExecutionOutcomeView>,
+    /// Receipts which would be generated by the receipt of the transaction,
+    /// or the receipt of the transaction itself if it wasn't executed.
+    pub receipts: Vec<ReceiptView>,
+    pub failure: Option<TxExecutionError>,
 }
 
 pub mod validator_stake_view {
@@ -1873,6 +2070,21 @@ pub struct ReceiptView {
     pub receipt: ReceiptEnumView,
 }
 
+/// A receipt with its execution, as returned by the `receipt` RPC method.
+#[derive(PartialEq, Eq, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ReceiptWithOutcomeView {
+    /// None if the receipt wasn't persisted by the node, e.g. for local receipts.
+    pub receipt: Option<ReceiptView>,
+    /// Outcome of the execution of the receipt on the canonical chain, if executed.
+    pub outcome: Option<ExecutionOutcomeWithIdView>,
+    /// Block and shard the receipt was executed in.
+    pub block_hash: Option<CryptoHash>,
+    pub shard_id: Option<ShardId>,
+    /// Hash of the transaction the receipt originates from, only known if the node records
+    /// the origins of the receipts.
+    pub originated_from_transaction_hash: Option<CryptoHash>,
+}
+
 #[derive(
     BorshSerialize,
     BorshDeserialize,
@@ -2012,6 +2224,24 @@ pub struct EpochValidatorInfo {
     pub epoch_height: EpochHeight,
 }
 
+/// Validators of the current, next or next next epoch. The validators of the
+/// next next epoch are estimated from the proposals, kickouts and stake so far
+/// in the current epoch, and can change until the end of the current epoch.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct EstimatedValidatorsView {
+    /// Epoch height
+    pub epoch_height: EpochHeight,
+    /// Whether the validators are an estimate
+    pub estimated: bool,
+    /// Stake required for a seat in the epoch
+    #[serde(with = "dec_format")]
+    pub seat_price: Balance,
+    /// Validators of the epoch
+    pub validators: Vec<ValidatorStakeView>,
+    /// Validators kicked out in the epoch before
+    pub kickout: Vec<ValidatorKickoutView>,
+}
+
 #[derive(
     BorshSerialize,
     BorshDeserialize,
@@ -2027,6 +2257,28 @@ pub struct ValidatorKickoutView {
     pub reason: ValidatorKickoutReason,
 }
 
+/// Role of a validator in an epoch.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorRoleView {
+    /// Produces blocks, and chunks of the shards it is assigned to.
+    BlockProducer,
+    /// Only produces chunks of the shards it is assigned to.
+    ChunkOnlyProducer,
+    /// Neither produces blocks nor chunks, only validates chunks.
+    ChunkValidator,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct CurrentEpochValidatorInfo {
     pub account_id: AccountId,
@@ -2035,6 +2287,9 @@ pub struct CurrentEpochValidatorInfo {
     #[serde(with = "dec_format")]
     pub stake: Balance,
     pub shards: Vec<ShardId>,
+    /// None if reported by a node which doesn't report the roles of the validators.
+    #[serde(default)]
+    pub role: Option<ValidatorRoleView>,
     pub num_produced_blocks: NumBlocks,
     pub num_expected_blocks: NumBlocks,
     #[serde(default)]
@@ -2064,6 +2319,9 @@ pub struct NextEpochValidatorInfo {
     #[serde(with = "dec_format")]
     pub stake: Balance,
     pub shards: Vec<ShardId>,
+    /// None if reported by a node which doesn't report the roles of the validators.
+    #[serde(default)]
+    pub role: Option<ValidatorRoleView>,
 }
 
 #[derive(
@@ -2144,6 +2402,15 @@ pub enum StateChangesRequestView {
         #[serde(rename = "key_prefix_base64")]
         key_prefix: StoreKey,
     },
+    /// Changes of the accounts whose id starts with one of `account_id_prefixes`, or of all the
+    /// accounts if there are none. If `key_prefixes_base64` are given, only the contract data
+    /// changes whose key starts with one of them.
+    FilteredChanges {
+        #[serde(default)]
+        account_id_prefixes: Vec<String>,
+        #[serde(default, rename = "key_prefixes_base64")]
+        key_prefixes: Vec<StoreKey>,
+    },
 }
 
 impl From<StateChangesRequestView> for StateChangesRequest {
@@ -2164,6 +2431,11 @@ impl From<StateChangesRequestView> for StateChangesRequest {
             StateChangesRequestView::DataChanges { account_ids, key_prefix } => {
                 Self::DataChanges { account_ids, key_prefix }
             }
+            StateChangesRequestView::FilteredChanges { account_id_prefixes, key_prefixes } => {
+                Self::FilteredChanges {
+                    filter: StateChangesFilter { account_id_prefixes, key_prefixes },
+                }
+            }
         }
     }
 }
@@ -2199,7 +2471,7 @@ impl From<StateChangeKind> for StateChangeKindView {
 pub type StateChangesKindsView = Vec<StateChangeKindView>;
 
 /// See crate::types::StateChangeCause for details.
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum StateChangeCauseView {
     NotWritableToDisk,
@@ -2244,7 +2516,7 @@ impl From<StateChangeCause> for StateChangeCauseView {
 }
 
 #[serde_as]
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type", content = "change")]
 pub enum StateChangeValueView {
     AccountUpdate {
@@ -2287,6 +2559,29 @@ pub enum StateChangeValueView {
     },
 }
 
+impl StateChangeValueView {
+    pub fn affected_account_id(&self) -> &AccountId {
+        match &self {
+            Self::AccountUpdate { account_id, .. }
+            | Self::AccountDeletion { account_id }
+            | Self::AccessKeyUpdate { account_id, .. }
+            | Self::AccessKeyDeletion { account_id, .. }
+            | Self::DataUpdate { account_id, .. }
+            | Self::DataDeletion { account_id, .. }
+            | Self::ContractCodeUpdate { account_id, .. }
+            | Self::ContractCodeDeletion { account_id } => account_id,
+        }
+    }
+
+    /// Key of the contract data changes.
+    pub fn data_key(&self) -> Option<&[u8]> {
+        match &self {
+            Self::DataUpdate { key, .. } | Self::DataDeletion { key, .. } => Some(key.as_slice()),
+            _ => None,
+        }
+    }
+}
+
 impl From<StateChangeValue> for StateChangeValueView {
     fn from(state_change: StateChangeValue) -> Self {
         match state_change {
@@ -2318,7 +2613,7 @@ impl From<StateChangeValue> for StateChangeValueView {
     }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct StateChangeWithCauseView {
     pub cause: StateChangeCauseView,
     #[serde(flatten)]
@@ -2337,6 +2632,19 @@ pub type StateChangesView = Vec<StateChangeWithCauseView>;
 /// Maintenance windows view are a vector of maintenance window.
 pub type MaintenanceWindowsView = Vec<Range<BlockHeight>>;
 
+/// The nearest window for a maintenance of the account, in which it has no
+/// block or chunk to produce and no chunk to validate.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledMaintenanceWindowView {
+    pub account_id: AccountId,
+    /// Number of blocks the maintenance needs.
+    pub num_blocks: BlockHeightDelta,
+    /// Whether the node shuts down itself at the start of the window.
+    pub auto_restart: bool,
+    /// The window, or `None` if there is none until the end of the next epoch.
+    pub window: Option<Range<BlockHeight>>,
+}
+
 /// Contains the split storage information.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct SplitStorageInfoView {
@@ -2347,6 +2655,117 @@ pub struct SplitStorageInfoView {
     pub hot_db_kind: Option<String>,
 }
 
+/// A chunk state witness, as served by the `EXPERIMENTAL_chunk_state_witness`
+/// RPC method. The witness itself is returned in its borsh encoding so that it
+/// can be re-verified by the caller bit-for-bit.
+#[serde_as]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkStateWitnessView {
+    pub chunk_hash: CryptoHash,
+    pub shard_id: ShardId,
+    pub height_created: BlockHeight,
+    pub prev_block_hash: CryptoHash,
+    /// Borsh-serialized `ChunkStateWitness`.
+    #[serde(rename = "witness_base64")]
+    #[serde_as(as = "Base64")]
+    pub witness: Vec<u8>,
+}
+
+/// A proof that a chunk's state transition has been endorsed by its chunk
+/// validators, as served by the light client RPC.
+#[serde_as]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkExecutionProofView {
+    pub chunk_hash: CryptoHash,
+    pub shard_id: ShardId,
+    pub height_created: BlockHeight,
+    /// Accounts of the chunk validators whose endorsements are in the proof.
+    pub endorsed_by: Vec<AccountId>,
+    /// Borsh-serialized `ChunkExecutionProof`.
+    #[serde(rename = "proof_base64")]
+    #[serde_as(as = "Base64")]
+    pub proof: Vec<u8>,
+}
+
+impl From<crate::chunk_validation::ChunkExecutionProof> for ChunkExecutionProofView {
+    fn from(proof: crate::chunk_validation::ChunkExecutionProof) -> Self {
+        let header = &proof.chunk_header;
+        Self {
+            chunk_hash: header.chunk_hash().0,
+            shard_id: header.shard_id(),
+            height_created: header.height_created(),
+            endorsed_by: proof
+                .endorsements
+                .iter()
+                .map(|endorsement| endorsement.account_id.clone())
+                .collect(),
+            proof: borsh::to_vec(&proof).unwrap(),
+        }
+    }
+}
+
+impl From<crate::chunk_validation::ChunkStateWitness> for ChunkStateWitnessView {
+    fn from(witness: crate::chunk_validation::ChunkStateWitness) -> Self {
+        let header = &witness.chunk_header;
+        Self {
+            chunk_hash: header.chunk_hash().0,
+            shard_id: header.shard_id(),
+            height_created: header.height_created(),
+            prev_block_hash: *header.prev_block_hash(),
+            witness: borsh::to_vec(&witness).unwrap(),
+        }
+    }
+}
+
+/// Proofs of execution outcomes whose outcome root is included in a block, as
+/// served by the light client RPC.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct LightClientBlockOutcomesProofView {
+    pub block_header_lite: LightClientBlockLiteView,
+    /// Proof of the block against the head of the light client.
+    pub block_proof: MerklePath,
+    pub outcomes: Vec<LightClientOutcomeProofView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct LightClientOutcomeProofView {
+    pub outcome_proof: ExecutionOutcomeWithIdView,
+    /// Proof of the outcome root of the chunk against the outcome root of
+    /// the block.
+    pub outcome_root_proof: MerklePath,
+}
+
+/// Status of the transaction pools of the node, as served by the
+/// `EXPERIMENTAL_tx_pool_status` RPC method.
+///
+/// Inclusion delays are estimated in chunks of the shard, `1` meaning the
+/// next chunk, from the number of transactions recent chunks of the shard
+/// included. They are `None` until the node has seen a chunk of the shard
+/// with transactions.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TxPoolStatusView {
+    pub shards: Vec<ShardTxPoolStatusView>,
+    /// Transactions of the requested account in the pools, by shard and nonce.
+    pub transactions: Vec<PendingTransactionView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShardTxPoolStatusView {
+    pub shard_id: ShardId,
+    pub num_transactions: u64,
+    pub size_bytes: u64,
+    /// Estimated delay before a new transaction with no priority is included.
+    pub estimated_inclusion_delay: Option<u64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PendingTransactionView {
+    pub hash: CryptoHash,
+    pub shard_id: ShardId,
+    pub nonce: Nonce,
+    pub estimated_inclusion_delay: Option<u64>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::ExecutionMetadataView;
@@ -284,6 +284,30 @@ pub enum DBCol {
     /// - *Rows*: BlockShardId (BlockHash || ShardId) - 40 bytes
     /// - *Column type*: `StoredChunkStateTransitionData`
     StateTransitionData,
+    /// Chunk state witnesses received or produced by the node. Only written
+    /// when `store.save_state_witnesses` is enabled. Witnesses received from
+    /// other nodes are only written once they are validated.
+    /// The height is the height the chunk was created at.
+    /// - *Rows*: BlockHeight || ChunkHash - 40 bytes
+    /// - *Column type*: `ChunkStateWitness`
+    StateWitnesses,
+    /// Gas and IO profiles of the receipts executed by the node. Only written
+    /// when `save_receipt_profiles` is enabled.
+    /// - *Rows*: receipt id (CryptoHash) || block hash (CryptoHash)
+    /// - *Column type*: `ReceiptProfile`
+    ReceiptProfiles,
+    /// Hash of the transaction each receipt originates from. Only written when
+    /// `store.save_receipt_origins` is enabled or on archival nodes, and only
+    /// for the receipts generated by the outcomes of the tracked shards.
+    /// - *Rows*: receipt id (CryptoHash)
+    /// - *Column type*: transaction hash (CryptoHash)
+    ReceiptOrigins,
+    /// Chunks endorsed by the node as a chunk validator, so that it never
+    /// endorses two different chunks of the same shard and height, even
+    /// across restarts.
+    /// - *Rows*: height created (u64)
+    /// - *Column type*: `HashMap<ShardId, ChunkHash>`
+    EndorsedChunks,
     /// Column to store data for Epoch Sync.
     /// Does not contain data for genesis epoch.
     /// - *Rows*: `epoch_id`
@@ -434,6 +458,12 @@ impl DBCol {
             // TODO StateChangesForSplitStates is not GC-ed, why is it here?
             | DBCol::StateChangesForSplitStates
             | DBCol::StateHeaders
+            // Data needed to produce state witnesses and the saved state
+            // witnesses are kept by archival nodes.
+            | DBCol::StateTransitionData
+            | DBCol::StateWitnesses
+            | DBCol::ReceiptProfiles
+            | DBCol::ReceiptOrigins
             | DBCol::TransactionResultForBlock
             | DBCol::Transactions => true,
 
@@ -456,6 +486,8 @@ impl DBCol {
             DBCol::ProcessedBlockHeights => false,
             // HeaderHashesByHeight is only needed for GC.
             DBCol::HeaderHashesByHeight => false,
+            // EndorsedChunks is only needed by the chunk validator at head.
+            DBCol::EndorsedChunks => false,
 
             // Columns that are not GC-ed need not be copied to the cold storage.
             DBCol::BlockHeader
@@ -483,8 +515,7 @@ impl DBCol {
             | DBCol::FlatState
             | DBCol::FlatStateChanges
             | DBCol::FlatStateDeltaMetadata
-            | DBCol::FlatStorageStatus
-            | DBCol::StateTransitionData => false,
+            | DBCol::FlatStorageStatus => false,
             #[cfg(feature = "new_epoch_sync")]
             DBCol::EpochSyncInfo => false
         }
@@ -556,6 +587,12 @@ impl DBCol {
             DBCol::FlatStateDeltaMetadata => &[DBKeyType::ShardUId, DBKeyType::BlockHash],
             DBCol::FlatStorageStatus => &[DBKeyType::ShardUId],
             DBCol::StateTransitionData => &[DBKeyType::BlockHash, DBKeyType::ShardId],
+            DBCol::StateWitnesses => &[DBKeyType::BlockHeight, DBKeyType::ChunkHash],
+            DBCol::ReceiptProfiles => &[DBKeyType::OutcomeId, DBKeyType::BlockHash],
+            // The origin of a receipt is copied to the cold storage with the
+            // block the receipt is executed in.
+            DBCol::ReceiptOrigins => &[DBKeyType::OutcomeId],
+            DBCol::EndorsedChunks => &[DBKeyType::BlockHeight],
             #[cfg(feature = "new_epoch_sync")]
             DBCol::EpochSyncInfo => &[DBKeyType::EpochId],
         }
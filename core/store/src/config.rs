@@ -4,7 +4,10 @@ use crate::trie::{
 use crate::DBCol;
 use near_primitives::shard_layout::ShardUId;
 use std::time::Duration;
-use std::{collections::HashMap, iter::FromIterator};
+use std::{
+    collections::{BTreeMap, HashMap},
+    iter::FromIterator,
+};
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
@@ -14,6 +17,11 @@ pub struct StoreConfig {
     /// database.
     pub path: Option<std::path::PathBuf>,
 
+    /// Key-value engine backing the database.
+    /// Default value: `RocksDb`.
+    #[serde(skip_serializing_if = "StoreBackend::is_default")]
+    pub backend: StoreBackend,
+
     /// Collect internal storage layer statistics.
     /// Minor performance impact is expected.
     pub enable_statistics: bool,
@@ -45,6 +53,18 @@ pub struct StoreConfig {
     /// the performance of the storage
     pub block_size: bytesize::ByteSize,
 
+    /// RocksDB settings of individual columns, keyed by the column name, e.g.
+    /// `State`, `FlatState` or `BlockHeader`. They take precedence over
+    /// `col_state_cache_size` and `col_flat_state_cache_size`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_configs: BTreeMap<String, ColumnConfig>,
+
+    /// Memory budget of the compiled contracts cache shared by all shards.
+    /// The most recently used compiled contracts are kept in memory up to
+    /// this size, on top of the compiled contracts stored in the database.
+    /// 0 disables keeping compiled contracts in memory.
+    pub compiled_contract_cache_size: bytesize::ByteSize,
+
     /// Trie cache configuration per shard for normal (non-view) caches.
     pub trie_cache: TrieCacheConfig,
     /// Trie cache configuration per shard for view caches.
@@ -53,6 +73,13 @@ pub struct StoreConfig {
     /// Enable fetching account and access key data ahead of time to avoid IO latency.
     pub enable_receipt_prefetching: bool,
 
+    /// Maximum number of trie keys prefetched for the actions of the receipts
+    /// of a chunk: contract code of function calls, access keys of added and
+    /// deleted keys and accounts of delegate actions. Each key takes at least
+    /// one read per trie node on its path. 0 disables the prefetching of these
+    /// keys; it requires `enable_receipt_prefetching`.
+    pub receipt_prefetch_io_budget: usize,
+
     /// Configured accounts will be prefetched as SWEAT token account, if predecessor is listed as receiver.
     /// This config option is temporary and will be removed once flat storage is implemented.
     pub sweat_prefetch_receivers: Vec<String>,
@@ -66,6 +93,13 @@ pub struct StoreConfig {
     pub load_mem_tries_for_shards: Vec<ShardUId>,
     /// If true, load mem tries for all shards; this has priority over `load_mem_tries_for_shards`.
     pub load_mem_tries_for_all_shards: bool,
+    /// Number of heights, before the height memtrie garbage collection would
+    /// otherwise delete roots up to, for which the roots are retained.
+    pub mem_tries_gc_retained_heights: u64,
+    /// Fraction of the memtrie arena memory not used by active nodes above
+    /// which the arena is compacted after garbage collection. Compaction is
+    /// disabled if not set.
+    pub mem_tries_compaction_fragmentation_threshold: Option<f64>,
 
     /// Path where to create RocksDB checkpoints during database migrations or
     /// `false` to disable that feature.
@@ -115,10 +149,65 @@ pub struct StoreConfig {
 
     // TODO (#9989): To be phased out in favor of state_snapshot_config
     pub state_snapshot_compaction_enabled: bool,
+
+    /// Persist the chunk state witnesses produced by the node, and those
+    /// received and successfully validated, in `DBCol::StateWitnesses`, so
+    /// that they can be re-validated or analysed later. Witnesses are garbage
+    /// collected together with the chunks of their height, except on archival
+    /// nodes which keep them in cold storage.
+    pub save_state_witnesses: bool,
+
+    /// Record the transaction each receipt executed by the node originates
+    /// from in `DBCol::ReceiptOrigins`, for the `receipt` RPC method. Always
+    /// enabled on archival nodes. The records are garbage collected together
+    /// with the outcomes of the receipts.
+    pub save_receipt_origins: bool,
+
+    /// When migrating the database to version 39, re-apply the chunks of the
+    /// tracked shards since their last new chunk to backfill
+    /// `DBCol::StateTransitionData`, so that a chunk producer upgraded in the
+    /// middle of an epoch can produce state witnesses right away.
+    pub backfill_state_transition_data: bool,
+}
+
+/// Key-value engine backing a database. Every engine implements
+/// [`crate::db::Database`], and the rest of the node only accesses the
+/// database through it.
+#[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum StoreBackend {
+    #[default]
+    RocksDb,
+    /// An in-memory database, for tests only. The data is kept for the
+    /// lifetime of the process, so the database can be reopened at the same
+    /// path, but it is lost when the process exits. Migration and state
+    /// snapshots aren't supported.
+    InMemory,
+}
+
+impl StoreBackend {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// RocksDB settings of a column. The settings which aren't set use the
+/// defaults of the column.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ColumnConfig {
+    /// Size of the block cache of the column.
+    pub block_cache_size: Option<bytesize::ByteSize>,
+    /// Memory budget of the memtables of the column, from which the write
+    /// buffer size and the sizes of the compaction levels are derived.
+    /// Default value: 128MiB.
+    pub memtable_memory_budget: Option<bytesize::ByteSize>,
+    /// Target size of the SST files of the column produced by compaction.
+    /// Default value: 64MiB.
+    pub target_file_size_base: Option<bytesize::ByteSize>,
 }
 
 /// Config used to control state snapshot creation. This is used for state sync and resharding.
-#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct StateSnapshotConfig {
     pub state_snapshot_type: StateSnapshotType,
@@ -127,6 +216,20 @@ pub struct StateSnapshotConfig {
     /// It makes state snapshots tiny (10GB) over the course of an epoch.
     /// We may want to disable it for archival nodes during resharding
     pub compaction_enabled: bool,
+    /// Number of the most recent snapshots to keep, so that state sync can
+    /// still be served for the previous epochs. The oldest snapshots are
+    /// deleted when a new one is made.
+    pub num_retained_snapshots: usize,
+}
+
+impl Default for StateSnapshotConfig {
+    fn default() -> Self {
+        Self {
+            state_snapshot_type: StateSnapshotType::default(),
+            compaction_enabled: false,
+            num_retained_snapshots: 1,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -158,11 +261,16 @@ pub enum Mode {
     ReadWrite,
     /// Creates a new database in read-write mode.  Fails if it exists.
     Create,
+    /// Open an existing database, which another process keeps open in
+    /// read-write mode, as a read-only secondary instance which can catch up
+    /// with the changes written by the primary process.  Fail if it doesn’t
+    /// exist.
+    Secondary,
 }
 
 impl Mode {
     pub const fn read_only(self) -> bool {
-        matches!(self, Mode::ReadOnly)
+        matches!(self, Mode::ReadOnly | Mode::Secondary)
     }
     pub const fn read_write(self) -> bool {
         !self.read_only()
@@ -173,12 +281,15 @@ impl Mode {
     pub const fn must_create(self) -> bool {
         matches!(self, Mode::Create)
     }
+    pub const fn secondary(self) -> bool {
+        matches!(self, Mode::Secondary)
+    }
 
     /// Returns variant of the mode which prohibits creation of the database or
     /// `None` if the mode requires creation of a new database.
     pub const fn but_cannot_create(self) -> Option<Self> {
         match self {
-            Self::ReadOnly | Self::ReadWriteExisting => Some(self),
+            Self::ReadOnly | Self::ReadWriteExisting | Self::Secondary => Some(self),
             Self::ReadWrite => Some(Self::ReadWriteExisting),
             Self::Create => None,
         }
@@ -196,8 +307,16 @@ impl StoreConfig {
         Self { max_open_files: 512, ..Self::default() }
     }
 
+    /// Returns the settings of the column from `column_configs`, if any.
+    pub fn column_config(&self, col: DBCol) -> Option<&ColumnConfig> {
+        self.column_configs.get(<&str>::from(col))
+    }
+
     /// Returns cache size for given column.
-    pub const fn col_cache_size(&self, col: DBCol) -> bytesize::ByteSize {
+    pub fn col_cache_size(&self, col: DBCol) -> bytesize::ByteSize {
+        if let Some(size) = self.column_config(col).and_then(|config| config.block_cache_size) {
+            return size;
+        }
         match col {
             DBCol::State => self.col_state_cache_size,
             DBCol::FlatState => self.col_flat_state_cache_size,
@@ -210,6 +329,7 @@ impl Default for StoreConfig {
     fn default() -> Self {
         Self {
             path: None,
+            backend: StoreBackend::default(),
             enable_statistics: false,
             enable_statistics_export: true,
 
@@ -239,6 +359,10 @@ impl Default for StoreConfig {
             // we use it since then.
             block_size: bytesize::ByteSize::kib(16),
 
+            column_configs: BTreeMap::new(),
+
+            compiled_contract_cache_size: bytesize::ByteSize::mib(256),
+
             trie_cache: TrieCacheConfig {
                 default_max_bytes: bytesize::ByteSize::mb(500),
                 // TODO(resharding) The cache size needs to adjusted for every resharding.
@@ -265,6 +389,7 @@ impl Default for StoreConfig {
             view_trie_cache: TrieCacheConfig::default(),
 
             enable_receipt_prefetching: true,
+            receipt_prefetch_io_budget: 4096,
             sweat_prefetch_receivers: vec![
                 "token.sweat".to_owned(),
                 "vfinal.token.sweat.testnet".to_owned(),
@@ -281,6 +406,8 @@ impl Default for StoreConfig {
             // requires more RAM and takes several minutes on startup.
             load_mem_tries_for_shards: Default::default(),
             load_mem_tries_for_all_shards: false,
+            mem_tries_gc_retained_heights: 0,
+            mem_tries_compaction_fragmentation_threshold: None,
 
             migration_snapshot: Default::default(),
 
@@ -303,6 +430,12 @@ impl Default for StoreConfig {
 
             // TODO: To be phased out in favor of state_snapshot_config
             state_snapshot_compaction_enabled: false,
+
+            save_state_witnesses: false,
+
+            save_receipt_origins: false,
+
+            backfill_state_transition_data: false,
         }
     }
 }
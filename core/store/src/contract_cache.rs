@@ -0,0 +1,248 @@
+use crate::db::COMPILED_CONTRACT_CACHE_HOT_KEYS_KEY;
+use crate::{metrics, DBCol, Store, StoreCompiledContractCache};
+use lru::LruCache;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::ShardId;
+use near_vm_runner::logic::{CompiledContract, CompiledContractCache};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Number of the most called contracts which compiled code is loaded into
+/// memory at startup.
+const NUM_HOT_CONTRACTS: usize = 256;
+/// The most called contracts are saved to the database every this many
+/// requests to the cache.
+const SAVE_HOT_CONTRACTS_PERIOD: u64 = 10_000;
+
+/// Compiled contracts cache shared by all shards of the node.
+///
+/// Keeps the most recently used compiled contracts in memory, within a byte
+/// budget, on top of `DBCol::CachedContractCode`. The most called contracts
+/// are saved to the database from time to time, and loaded into memory at
+/// startup by [`SharedCompiledContractCache::warm_up`].
+#[derive(Clone)]
+pub struct SharedCompiledContractCache {
+    inner: Arc<Inner>,
+    /// Label of the shard the hits and misses are counted for. They aren't
+    /// counted if not set.
+    shard_id_label: Option<String>,
+}
+
+struct Inner {
+    store: Store,
+    db_cache: StoreCompiledContractCache,
+    /// Upper bound for the total size of the compiled contracts in memory.
+    /// The memory cache is disabled if 0.
+    capacity: u64,
+    memory: Mutex<MemoryCache>,
+}
+
+struct MemoryCache {
+    lru: LruCache<CryptoHash, CompiledContract>,
+    /// Current total size of the compiled contracts in `lru`.
+    size: u64,
+    /// Number of requests for each contract since the start of the node.
+    num_calls: HashMap<CryptoHash, u64>,
+    num_requests_since_save: u64,
+}
+
+fn entry_size(value: &CompiledContract) -> u64 {
+    match value {
+        CompiledContract::Code(code) => code.len() as u64,
+        CompiledContract::CompileModuleError(_) => std::mem::size_of::<CompiledContract>() as u64,
+    }
+}
+
+impl MemoryCache {
+    fn put(&mut self, key: CryptoHash, value: CompiledContract, capacity: u64) {
+        let size = entry_size(&value);
+        if size > capacity {
+            return;
+        }
+        if let Some(old_value) = self.lru.put(key, value) {
+            self.size -= entry_size(&old_value);
+        }
+        self.size += size;
+        while self.size > capacity {
+            let Some((_, evicted)) = self.lru.pop_lru() else { break };
+            self.size -= entry_size(&evicted);
+            metrics::COMPILED_CONTRACT_CACHE_EVICTIONS.inc();
+        }
+        metrics::COMPILED_CONTRACT_CACHE_SIZE.set(self.size as i64);
+    }
+
+    /// Counts a request for `key` and returns the most called contracts if
+    /// they are due to be saved.
+    fn record_request(&mut self, key: &CryptoHash) -> Option<Vec<CryptoHash>> {
+        *self.num_calls.entry(*key).or_default() += 1;
+        self.num_requests_since_save += 1;
+        if self.num_requests_since_save < SAVE_HOT_CONTRACTS_PERIOD {
+            return None;
+        }
+        self.num_requests_since_save = 0;
+        let mut num_calls: Vec<_> = self.num_calls.iter().collect();
+        num_calls.sort_by_key(|(_, num_calls)| std::cmp::Reverse(**num_calls));
+        Some(num_calls.into_iter().take(NUM_HOT_CONTRACTS).map(|(key, _)| *key).collect())
+    }
+}
+
+impl SharedCompiledContractCache {
+    pub fn new(store: &Store, capacity: u64) -> Self {
+        let memory = MemoryCache {
+            lru: LruCache::unbounded(),
+            size: 0,
+            num_calls: HashMap::new(),
+            num_requests_since_save: 0,
+        };
+        let inner = Inner {
+            store: store.clone(),
+            db_cache: StoreCompiledContractCache::new(store),
+            capacity,
+            memory: Mutex::new(memory),
+        };
+        Self { inner: Arc::new(inner), shard_id_label: None }
+    }
+
+    /// Returns a handle to the same cache which counts the hits and misses
+    /// for the given shard.
+    pub fn for_shard(&self, shard_id: ShardId) -> Self {
+        Self { inner: self.inner.clone(), shard_id_label: Some(shard_id.to_string()) }
+    }
+
+    /// Loads the compiled code of the most called contracts, saved by the
+    /// previous run of the node, into memory, within the byte budget.
+    pub fn warm_up(&self) -> io::Result<()> {
+        if self.inner.capacity == 0 {
+            return Ok(());
+        }
+        let Some(hot_keys) = self
+            .inner
+            .store
+            .get_ser::<Vec<CryptoHash>>(DBCol::Misc, COMPILED_CONTRACT_CACHE_HOT_KEYS_KEY)?
+        else {
+            return Ok(());
+        };
+        let mut loaded = vec![];
+        let mut size = 0;
+        for key in hot_keys {
+            let Some(value) = self.inner.db_cache.get(&key)? else { continue };
+            size += entry_size(&value);
+            if size > self.inner.capacity {
+                break;
+            }
+            loaded.push((key, value));
+        }
+        let num_loaded = loaded.len();
+        let mut memory = self.inner.memory.lock().unwrap();
+        // The most called contracts are put last to be evicted last.
+        for (key, value) in loaded.into_iter().rev() {
+            memory.put(key, value, self.inner.capacity);
+        }
+        tracing::info!(
+            target: "store",
+            num_loaded,
+            size = memory.size,
+            "Loaded the most called contracts into the compiled contract cache",
+        );
+        Ok(())
+    }
+
+    fn count_request(&self, result: &str) {
+        if let Some(shard_id_label) = &self.shard_id_label {
+            metrics::COMPILED_CONTRACT_CACHE_REQUESTS
+                .with_label_values(&[shard_id_label, result])
+                .inc();
+        }
+    }
+
+    fn save_hot_contracts(&self, hot_keys: Vec<CryptoHash>) {
+        let mut store_update = self.inner.store.store_update();
+        let result = store_update
+            .set_ser(DBCol::Misc, COMPILED_CONTRACT_CACHE_HOT_KEYS_KEY, &hot_keys)
+            .and_then(|()| store_update.commit());
+        if let Err(err) = result {
+            tracing::warn!(target: "store", ?err, "Failed to save the most called contracts");
+        }
+    }
+}
+
+impl CompiledContractCache for SharedCompiledContractCache {
+    fn put(&self, key: &CryptoHash, value: CompiledContract) -> io::Result<()> {
+        if self.inner.capacity > 0 {
+            self.inner.memory.lock().unwrap().put(*key, value.clone(), self.inner.capacity);
+        }
+        self.inner.db_cache.put(key, value)
+    }
+
+    fn get(&self, key: &CryptoHash) -> io::Result<Option<CompiledContract>> {
+        let (cached, hot_keys) = {
+            let mut memory = self.inner.memory.lock().unwrap();
+            let hot_keys = memory.record_request(key);
+            (memory.lru.get(key).cloned(), hot_keys)
+        };
+        if let Some(hot_keys) = hot_keys {
+            self.save_hot_contracts(hot_keys);
+        }
+        if let Some(value) = cached {
+            self.count_request("memory_hit");
+            return Ok(Some(value));
+        }
+        let value = self.inner.db_cache.get(key)?;
+        match &value {
+            Some(value) => {
+                self.count_request("db_hit");
+                if self.inner.capacity > 0 {
+                    let mut memory = self.inner.memory.lock().unwrap();
+                    memory.put(*key, value.clone(), self.inner.capacity);
+                }
+            }
+            None => self.count_request("miss"),
+        }
+        Ok(value)
+    }
+
+    fn has(&self, key: &CryptoHash) -> io::Result<bool> {
+        if self.inner.memory.lock().unwrap().lru.contains(key) {
+            return Ok(true);
+        }
+        self.inner.db_cache.has(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SharedCompiledContractCache;
+    use crate::test_utils::create_test_store;
+    use near_primitives::hash::CryptoHash;
+    use near_vm_runner::logic::{CompiledContract, CompiledContractCache};
+
+    #[test]
+    fn test_eviction_and_warm_up() {
+        let store = create_test_store();
+        let cache = SharedCompiledContractCache::new(&store, 10).for_shard(0);
+        let keys: Vec<_> = (0..3u8).map(|i| CryptoHash::hash_bytes(&[i])).collect();
+        for key in &keys {
+            cache.put(key, CompiledContract::Code(vec![1; 4])).unwrap();
+        }
+        // Only the two most recently used contracts fit into memory, but all
+        // of them are still in the database.
+        {
+            let memory = cache.inner.memory.lock().unwrap();
+            assert_eq!(memory.size, 8);
+            assert!(!memory.lru.contains(&keys[0]));
+        }
+        for key in &keys {
+            assert_eq!(cache.get(key).unwrap(), Some(CompiledContract::Code(vec![1; 4])));
+        }
+
+        // The most called contracts are loaded by a new cache at startup.
+        cache.save_hot_contracts(vec![keys[0], keys[2]]);
+        let cache = SharedCompiledContractCache::new(&store, 10);
+        cache.warm_up().unwrap();
+        let memory = cache.inner.memory.lock().unwrap();
+        assert!(memory.lru.contains(&keys[0]));
+        assert!(!memory.lru.contains(&keys[1]));
+        assert!(memory.lru.contains(&keys[2]));
+    }
+}
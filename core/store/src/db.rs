@@ -34,10 +34,16 @@ pub const GENESIS_STATE_ROOTS_KEY: &[u8; 19] = b"GENESIS_STATE_ROOTS";
 pub const COLD_HEAD_KEY: &[u8; 9] = b"COLD_HEAD";
 pub const STATE_SYNC_DUMP_KEY: &[u8; 15] = b"STATE_SYNC_DUMP";
 pub const STATE_SNAPSHOT_KEY: &[u8; 18] = b"STATE_SNAPSHOT_KEY";
+pub const STATE_SNAPSHOTS_KEY: &[u8; 15] = b"STATE_SNAPSHOTS";
 
 // `DBCol::Misc` keys
 pub const FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS_KEY: &[u8] =
     b"FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS";
+/// Prefix of the keys of the resharding progress of parent shards, followed by
+/// the bytes of the `ShardUId` of the parent shard.
+pub const RESHARDING_PROGRESS_KEY_PREFIX: &[u8] = b"RESHARDING_PROGRESS";
+/// Keys of the most called compiled contracts, loaded into memory at startup.
+pub const COMPILED_CONTRACT_CACHE_HOT_KEYS_KEY: &[u8] = b"COMPILED_CONTRACT_CACHE_HOT_KEYS";
 
 #[derive(Default, Debug)]
 pub struct DBTransaction {
@@ -225,6 +231,23 @@ pub trait Database: Sync + Send {
     /// is blocking until compaction finishes. Otherwise, this is a no-op.
     fn compact(&self) -> io::Result<()>;
 
+    /// Catches up with the changes written by the primary instance if the
+    /// database is opened as a secondary instance, see
+    /// [`crate::Mode::Secondary`].  Otherwise, this is a no-op.
+    fn catch_up_with_primary(&self) -> io::Result<()>;
+
+    /// Splits the keys of the column into ranges of at least `range_size`
+    /// bytes on disk, which can be compacted one at a time with
+    /// `compact_range`. Databases which don't support compaction return a
+    /// single range covering the whole column.
+    fn column_ranges(&self, col: DBCol, range_size: u64) -> io::Result<Vec<ColumnRange>>;
+
+    /// Compacts the keys of the column in the given range.
+    ///
+    /// If the database supports compaction, calling this function is blocking
+    /// until the compaction finishes. Otherwise, this is a no-op.
+    fn compact_range(&self, col: DBCol, range: &ColumnRange) -> io::Result<()>;
+
     /// Returns statistics about the database if available.
     fn get_store_statistics(&self) -> Option<StoreStatistics>;
 
@@ -255,3 +278,19 @@ pub enum StatsValue {
 pub struct StoreStatistics {
     pub data: Vec<(String, Vec<StatsValue>)>,
 }
+
+/// A range of the keys of a column. `None` bounds are unbounded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnRange {
+    pub start: Option<Vec<u8>>,
+    pub end: Option<Vec<u8>>,
+    /// Approximate size of the range on disk, in bytes.
+    pub size: u64,
+}
+
+impl ColumnRange {
+    /// The range covering all keys of a column.
+    pub fn full(size: u64) -> Self {
+        Self { start: None, end: None, size }
+    }
+}
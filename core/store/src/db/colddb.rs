@@ -1,7 +1,7 @@
 use near_o11y::{log_assert, log_assert_fail};
 
 use crate::db::refcount::set_refcount;
-use crate::db::{DBIterator, DBOp, DBSlice, DBTransaction, Database};
+use crate::db::{ColumnRange, DBIterator, DBOp, DBSlice, DBTransaction, Database};
 use crate::DBCol;
 
 /// A database which provides access to the cold storage.
@@ -103,6 +103,18 @@ impl Database for ColdDB {
         self.cold.compact()
     }
 
+    fn catch_up_with_primary(&self) -> std::io::Result<()> {
+        self.cold.catch_up_with_primary()
+    }
+
+    fn column_ranges(&self, col: DBCol, range_size: u64) -> std::io::Result<Vec<ColumnRange>> {
+        self.cold.column_ranges(col, range_size)
+    }
+
+    fn compact_range(&self, col: DBCol, range: &ColumnRange) -> std::io::Result<()> {
+        self.cold.compact_range(col, range)
+    }
+
     fn flush(&self) -> std::io::Result<()> {
         self.cold.flush()
     }
@@ -1,5 +1,7 @@
 use crate::config::Mode;
-use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, StatsValue};
+use crate::db::{
+    refcount, ColumnRange, DBIterator, DBOp, DBSlice, DBTransaction, Database, StatsValue,
+};
 use crate::{metadata, metrics, DBCol, StoreConfig, StoreStatistics, Temperature};
 use ::rocksdb::{
     BlockBasedOptions, Cache, ColumnFamily, Env, IteratorMode, Options, ReadOptions, WriteBatch, DB,
@@ -30,6 +32,8 @@ static CF_PROPERTY_NAMES: Lazy<Vec<std::ffi::CString>> = Lazy::new(|| {
             properties::ESTIMATE_TABLE_READERS_MEM,
             properties::BLOCK_CACHE_CAPACITY,
             properties::BLOCK_CACHE_USAGE,
+            properties::BLOCK_CACHE_PINNED_USAGE,
+            properties::ESTIMATE_NUM_KEYS,
             properties::CUR_SIZE_ACTIVE_MEM_TABLE,
             properties::SIZE_ALL_MEM_TABLES,
         ]
@@ -52,6 +56,10 @@ pub struct RocksDB {
     /// want.
     cf_handles: enum_map::EnumMap<DBCol, Option<std::ptr::NonNull<ColumnFamily>>>,
 
+    /// Whether the database is opened as a secondary instance, see
+    /// [`Mode::Secondary`].
+    secondary: bool,
+
     // RAII-style of keeping track of the number of instances of RocksDB and
     // counting total sum of max_open_files.
     _instance_tracker: instance_tracker::InstanceTracker,
@@ -117,7 +125,7 @@ impl RocksDB {
             .map_err(io::Error::other)?;
         let (db, db_opt) = Self::open_db(path, store_config, mode, temp, columns)?;
         let cf_handles = Self::get_cf_handles(&db, columns);
-        Ok(Self { db, db_opt, cf_handles, _instance_tracker: counter })
+        Ok(Self { db, db_opt, cf_handles, secondary: mode.secondary(), _instance_tracker: counter })
     }
 
     /// Opens the database with given column families configured.
@@ -139,7 +147,12 @@ impl RocksDB {
                 )
             })
             .collect::<Vec<_>>();
-        let db = if mode.read_only() {
+        let db = if mode.secondary() {
+            // The secondary instance keeps its info logs in a directory of its
+            // own.
+            let secondary_path = path.join("secondary");
+            DB::open_cf_descriptors_as_secondary(&options, path, &secondary_path, cf_descriptors)
+        } else if mode.read_only() {
             DB::open_cf_descriptors_read_only(&options, path, cf_descriptors, false)
         } else {
             DB::open_cf_descriptors(&options, path, cf_descriptors)
@@ -344,6 +357,9 @@ impl Database for RocksDB {
     }
 
     fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        if self.secondary {
+            return Err(io::Error::other("the database is opened in secondary mode"));
+        }
         let mut batch = WriteBatch::default();
         for op in transaction.ops {
             match op {
@@ -388,6 +404,44 @@ impl Database for RocksDB {
         Ok(())
     }
 
+    fn catch_up_with_primary(&self) -> io::Result<()> {
+        if self.secondary {
+            self.db.try_catch_up_with_primary().map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn column_ranges(&self, col: DBCol, range_size: u64) -> io::Result<Vec<ColumnRange>> {
+        let name = col_name(col);
+        let mut files = self.db.live_files().map_err(io::Error::other)?;
+        files.retain(|file| file.column_family_name == name);
+        files.sort_by(|a, b| a.start_key.cmp(&b.start_key));
+        // The ranges are delimited by the smallest keys of the SST files. The
+        // files of different levels overlap, so the sizes are approximate.
+        let mut ranges = vec![];
+        let mut start = None;
+        let mut size = 0;
+        for file in files {
+            if size >= range_size && file.start_key.is_some() && file.start_key != start {
+                ranges.push(ColumnRange { start, end: file.start_key.clone(), size });
+                start = file.start_key;
+                size = 0;
+            }
+            size += file.size as u64;
+        }
+        ranges.push(ColumnRange { start, end: None, size });
+        Ok(ranges)
+    }
+
+    fn compact_range(&self, col: DBCol, range: &ColumnRange) -> io::Result<()> {
+        self.db.compact_range_cf(
+            self.cf_handle(col)?,
+            range.start.as_deref(),
+            range.end.as_deref(),
+        );
+        Ok(())
+    }
+
     fn flush(&self) -> io::Result<()> {
         // Need to iterator over all CFs because the normal `flush()` only
         // flushes the default column family.
@@ -432,7 +486,12 @@ fn rocksdb_options(store_config: &StoreConfig, mode: Mode) -> Options {
     opts.create_missing_column_families(mode.read_write());
     opts.create_if_missing(mode.can_create());
     opts.set_use_fsync(false);
-    opts.set_max_open_files(store_config.max_open_files.try_into().unwrap_or(i32::MAX));
+    if mode.secondary() {
+        // Required by RocksDB for secondary instances.
+        opts.set_max_open_files(-1);
+    } else {
+        opts.set_max_open_files(store_config.max_open_files.try_into().unwrap_or(i32::MAX));
+    }
     opts.set_keep_log_file_num(1);
     opts.set_bytes_per_sync(bytesize::MIB);
     opts.set_write_buffer_size(256 * bytesize::MIB as usize);
@@ -517,10 +576,16 @@ fn rocksdb_column_options(col: DBCol, store_config: &StoreConfig, temp: Temperat
     // the rest use LZ4 compression.
     // See the implementation here:
     //      https://github.com/facebook/rocksdb/blob/c18c4a081c74251798ad2a1abf83bad417518481/options/options.cc#L588.
-    let memtable_memory_budget = 128 * bytesize::MIB as usize;
-    opts.optimize_level_style_compaction(memtable_memory_budget);
-
-    opts.set_target_file_size_base(64 * bytesize::MIB);
+    let column_config = store_config.column_config(col);
+    let memtable_memory_budget = column_config
+        .and_then(|config| config.memtable_memory_budget)
+        .map_or(128 * bytesize::MIB, |size| size.as_u64());
+    opts.optimize_level_style_compaction(memtable_memory_budget as usize);
+
+    let target_file_size_base = column_config
+        .and_then(|config| config.target_file_size_base)
+        .map_or(64 * bytesize::MIB, |size| size.as_u64());
+    opts.set_target_file_size_base(target_file_size_base);
     if temp == Temperature::Hot && col.is_rc() {
         opts.set_merge_operator("refcount merge", RocksDB::refcount_merge, RocksDB::refcount_merge);
         opts.set_compaction_filter("empty value filter", RocksDB::empty_value_compaction_filter);
@@ -826,4 +891,32 @@ mod tests {
         assert_matches!(store.exists(column, &keys[2]), Ok(false));
         assert_matches!(store.exists(column, &keys[3]), Ok(true));
     }
+
+    #[test]
+    fn test_compact_column_ranges() {
+        let store = NodeStorage::test_opener().1.open().unwrap().get_hot_store();
+        let column = DBCol::Block;
+        // Every flush writes a separate SST file.
+        for key in 0..4u8 {
+            let mut store_update = store.store_update();
+            store_update.insert(column, vec![key], vec![42; 1000]);
+            store_update.commit().unwrap();
+            store.flush().unwrap();
+        }
+
+        let ranges = store.column_ranges(column, 1).unwrap();
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges[0].start, None);
+        assert_eq!(ranges[0].end, Some(vec![1]));
+        assert_eq!(ranges[3].start, Some(vec![3]));
+        assert_eq!(ranges[3].end, None);
+        assert_eq!(store.column_ranges(column, u64::MAX).unwrap().len(), 1);
+
+        for range in &ranges {
+            store.compact_range(column, range).unwrap();
+        }
+        for key in 0..4u8 {
+            assert_matches!(store.exists(column, &[key]), Ok(true));
+        }
+    }
 }
@@ -5,7 +5,9 @@ use std::sync::Arc;
 
 use near_o11y::log_assert_fail;
 
-use crate::db::{DBIterator, DBIteratorItem, DBSlice, DBTransaction, Database, StoreStatistics};
+use crate::db::{
+    ColumnRange, DBIterator, DBIteratorItem, DBSlice, DBTransaction, Database, StoreStatistics,
+};
 use crate::DBCol;
 
 /// A database that provides access to the hot and cold databases.
@@ -193,6 +195,21 @@ impl Database for SplitDB {
         Ok(())
     }
 
+    fn catch_up_with_primary(&self) -> io::Result<()> {
+        self.hot.catch_up_with_primary()?;
+        self.cold.catch_up_with_primary()
+    }
+
+    fn column_ranges(&self, col: DBCol, range_size: u64) -> io::Result<Vec<ColumnRange>> {
+        self.hot.column_ranges(col, range_size)
+    }
+
+    fn compact_range(&self, col: DBCol, range: &ColumnRange) -> io::Result<()> {
+        let msg = "compact_range is not allowed - the split storage is read only.";
+        log_assert_fail!("{}", msg);
+        self.hot.compact_range(col, range)
+    }
+
     fn get_store_statistics(&self) -> Option<StoreStatistics> {
         log_assert_fail!("get_store_statistics is not allowed - the split storage has two stores");
         None
@@ -1,10 +1,13 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io;
 use std::ops::Bound;
-use std::sync::{Arc, RwLock};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database};
-use crate::{DBCol, StoreStatistics};
+use once_cell::sync::Lazy;
+
+use crate::db::{refcount, ColumnRange, DBIterator, DBOp, DBSlice, DBTransaction, Database};
+use crate::{DBCol, Mode, StoreStatistics};
 
 /// An in-memory database intended for tests and IO-agnostic estimations.
 #[derive(Default)]
@@ -20,10 +23,38 @@ pub struct TestDB {
     stats: RwLock<Option<StoreStatistics>>,
 }
 
+/// Databases of the in-memory backend, by path.
+static IN_MEMORY_DBS: Lazy<Mutex<HashMap<PathBuf, Arc<TestDB>>>> = Lazy::new(Default::default);
+
 impl TestDB {
     pub fn new() -> Arc<TestDB> {
         Arc::new(Self::default())
     }
+
+    /// Opens the in-memory database at given path, as the
+    /// [`crate::config::StoreBackend::InMemory`] backend. The database isn't
+    /// written to disk, the path only identifies it, so that it can be opened
+    /// again while the process is running.
+    pub(crate) fn open_in_memory(path: &Path, mode: Mode) -> io::Result<Arc<TestDB>> {
+        let mut dbs = IN_MEMORY_DBS.lock().unwrap();
+        match dbs.get(path) {
+            Some(_) if mode.must_create() => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("in-memory database {} already exists", path.display()),
+            )),
+            Some(db) => Ok(db.clone()),
+            None if mode.can_create() => Ok(dbs.entry(path.to_path_buf()).or_default().clone()),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("in-memory database {} doesn't exist", path.display()),
+            )),
+        }
+    }
+
+    /// Returns the in-memory database at given path if it was created.
+    pub(crate) fn get_in_memory(path: &Path) -> Option<Arc<TestDB>> {
+        IN_MEMORY_DBS.lock().unwrap().get(path).cloned()
+    }
 }
 
 impl TestDB {
@@ -124,6 +155,18 @@ impl Database for TestDB {
         Ok(())
     }
 
+    fn catch_up_with_primary(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn column_ranges(&self, _col: DBCol, _range_size: u64) -> io::Result<Vec<ColumnRange>> {
+        Ok(vec![ColumnRange::full(0)])
+    }
+
+    fn compact_range(&self, _col: DBCol, _range: &ColumnRange) -> io::Result<()> {
+        Ok(())
+    }
+
     fn get_store_statistics(&self) -> Option<StoreStatistics> {
         self.stats.read().unwrap().clone()
     }
@@ -128,12 +128,26 @@ impl FlatStateChanges {
 
 /// `FlatStateChanges` which uses hash of raw `TrieKey`s instead of keys themselves.
 /// Used to reduce memory used by deltas and serves read queries.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct CachedFlatStateChanges(HashMap<CryptoHash, Option<ValueRef>>);
 
 #[derive(Debug)]
 pub struct CachedFlatStateDelta {
     pub metadata: FlatStateDeltaMetadata,
+    /// `None` if the changes were spilled to disk to bound the memory used by
+    /// the deltas. They are read from disk on demand then.
+    pub changes: Option<Arc<CachedFlatStateChanges>>,
+}
+
+/// Changes of a run of consecutive blocks with flat state changes, merged
+/// together so that lookups on a long linear chain read a single delta for
+/// the whole run.
+#[derive(Debug)]
+pub struct MergedFlatStateDelta {
+    /// The oldest block of the run.
+    pub first_block: BlockInfo,
+    /// Number of blocks with flat state changes in the run.
+    pub num_blocks: usize,
     pub changes: Arc<CachedFlatStateChanges>,
 }
 
@@ -164,6 +178,11 @@ impl CachedFlatStateChanges {
         self.0.len()
     }
 
+    /// Merge two deltas. Values from `other` should override values from `self`.
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.0.extend(other.0.iter().map(|(key, value)| (*key, value.clone())))
+    }
+
     /// Total size in bytes consumed by delta. May be changed if we implement inlining of `ValueRef`s.
     pub(crate) fn total_size(&self) -> u64 {
         (self.0.capacity() as u64) * (Self::ENTRY_SIZE as u64)
@@ -11,6 +11,9 @@ pub(crate) struct FlatStorageMetrics {
     cached_deltas: IntGauge,
     cached_changes_num_items: IntGauge,
     cached_changes_size: IntGauge,
+    spilled_deltas: IntGauge,
+    merged_deltas: IntGauge,
+    merged_changes_size: IntGauge,
 }
 
 impl FlatStorageMetrics {
@@ -29,6 +32,12 @@ impl FlatStorageMetrics {
                 .with_label_values(&[&shard_id_label]),
             cached_changes_size: flat_state_metrics::FLAT_STORAGE_CACHED_CHANGES_SIZE
                 .with_label_values(&[&shard_id_label]),
+            spilled_deltas: flat_state_metrics::FLAT_STORAGE_SPILLED_DELTAS
+                .with_label_values(&[&shard_id_label]),
+            merged_deltas: flat_state_metrics::FLAT_STORAGE_MERGED_DELTAS
+                .with_label_values(&[&shard_id_label]),
+            merged_changes_size: flat_state_metrics::FLAT_STORAGE_MERGED_CHANGES_SIZE
+                .with_label_values(&[&shard_id_label]),
         }
     }
 
@@ -51,6 +60,17 @@ impl FlatStorageMetrics {
         self.cached_changes_num_items.set(cached_changes_num_items as i64);
         self.cached_changes_size.set(cached_changes_size as i64);
     }
+
+    pub(crate) fn set_compacted_deltas(
+        &self,
+        spilled_deltas: usize,
+        merged_deltas: usize,
+        merged_changes_size: u64,
+    ) {
+        self.spilled_deltas.set(spilled_deltas as i64);
+        self.merged_deltas.set(merged_deltas as i64);
+        self.merged_changes_size.set(merged_changes_size as i64);
+    }
 }
 
 /// Metrics reporting about flat storage creation progress on each status update.
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+
+use lru::LruCache;
 
 use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
@@ -13,7 +15,7 @@ use crate::flat::BlockInfo;
 use crate::flat::{FlatStorageReadyStatus, FlatStorageStatus};
 use crate::{Store, StoreUpdate};
 
-use super::delta::{CachedFlatStateDelta, FlatStateDelta};
+use super::delta::{CachedFlatStateDelta, FlatStateDelta, MergedFlatStateDelta};
 use super::metrics::FlatStorageMetrics;
 use super::store_helper;
 use super::types::FlatStorageError;
@@ -43,6 +45,15 @@ pub(crate) struct FlatStorageInner {
     flat_head: BlockInfo,
     /// Cached deltas for all blocks supported by this flat storage.
     deltas: HashMap<CryptoHash, CachedFlatStateDelta>,
+    /// Changes of runs of blocks on a linear chain merged together, by the
+    /// newest block of the run.
+    merged_deltas: HashMap<CryptoHash, MergedFlatStateDelta>,
+    /// Changes of the most recently used spilled deltas read back from disk,
+    /// so that lookups don't read them again for every key.
+    spilled_changes: Mutex<LruCache<CryptoHash, Arc<CachedFlatStateChanges>>>,
+    /// Once the total size of the cached changes exceeds this limit, the
+    /// changes of the oldest deltas are spilled to disk.
+    cached_changes_size_limit: u64,
     /// This flag enables skipping flat head moves, needed temporarily for FlatState
     /// values inlining migration.
     /// The flag has a numerical value and not a bool, to let us detect attempts
@@ -59,8 +70,14 @@ impl FlatStorageInner {
     /// https://github.com/near/nearcore/issues/8006#issuecomment-1473621334
     const HOPS_LIMIT: usize = 100;
     /// Limit for total size of cached changes. We allocate 600 MiB for cached deltas, which
-    /// means 150 MiB per shards.
+    /// means 150 MiB per shards. Past this limit the changes of the oldest deltas are spilled
+    /// to disk, and only the merged changes are kept in memory for them.
     const CACHED_CHANGES_SIZE_LIMIT: bytesize::ByteSize = bytesize::ByteSize(150 * bytesize::MIB);
+    /// Number of consecutive blocks with changes merged into a single delta, once that many
+    /// blocks with unmerged changes are between the chain head and the flat head.
+    const DELTAS_MERGE_BATCH: usize = 16;
+    /// Number of spilled deltas whose changes are kept in memory after being read back.
+    const SPILLED_CHANGES_CACHE_SIZE: usize = 16;
 
     const BLOCKS_WITH_CHANGES_FLAT_HEAD_GAP: BlockHeight = 2;
 
@@ -71,16 +88,188 @@ impl FlatStorageInner {
     }
 
     /// Gets changes for the given block and shard `self.shard_uid`, assuming that they must exist.
+    /// Changes spilled to disk are read from there, and cached in `spilled_changes`.
     fn get_block_changes(
         &self,
         block_hash: &CryptoHash,
     ) -> Result<Arc<CachedFlatStateChanges>, FlatStorageError> {
-        // TODO (#7327): add limitation on cached changes number to limit RAM usage
-        // and read single `ValueRef` from delta if it is not cached.
-        self.deltas
-            .get(block_hash)
-            .ok_or_else(|| missing_delta_error(block_hash))
-            .map(|delta| delta.changes.clone())
+        let delta = self.deltas.get(block_hash).ok_or_else(|| missing_delta_error(block_hash))?;
+        if let Some(changes) = &delta.changes {
+            return Ok(changes.clone());
+        }
+        let mut spilled_changes = self.spilled_changes.lock().expect(super::POISONED_LOCK_ERR);
+        if let Some(changes) = spilled_changes.get(block_hash) {
+            return Ok(changes.clone());
+        }
+        let changes = store_helper::get_delta_changes(&self.store, self.shard_uid, *block_hash)?
+            .ok_or_else(|| missing_delta_error(block_hash))?;
+        let changes: Arc<CachedFlatStateChanges> = Arc::new(changes.into());
+        spilled_changes.put(*block_hash, changes.clone());
+        Ok(changes)
+    }
+
+    /// Get the changes to look up a key in, from `target_block_hash` to flat head, in backwards
+    /// chain order. A run of blocks with merged changes is looked up in a single delta.
+    fn get_changes_to_head(
+        &self,
+        target_block_hash: &CryptoHash,
+    ) -> Result<Vec<Arc<CachedFlatStateChanges>>, FlatStorageError> {
+        let blocks = self.get_blocks_to_head(target_block_hash)?;
+        let mut changes = vec![];
+        let mut i = 0;
+        while i < blocks.len() {
+            if let Some(merged) = self.merged_deltas.get(&blocks[i]) {
+                changes.push(merged.changes.clone());
+                i += merged.num_blocks;
+            } else {
+                changes.push(self.get_block_changes(&blocks[i])?);
+                i += 1;
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Merges the changes of the oldest `DELTAS_MERGE_BATCH` blocks of the run of blocks with
+    /// unmerged changes right before `block_hash`, if the run is long enough. The block itself
+    /// isn't merged, as forks usually happen near the chain head, and the run is skipped if
+    /// another block forks off it, to not keep merged changes of forks in memory. The changes of
+    /// the merged blocks are then only kept on disk, as lookups read the merged changes instead.
+    fn maybe_merge_deltas(&mut self, block_hash: &CryptoHash) -> Result<(), FlatStorageError> {
+        let blocks = self.get_blocks_to_head(block_hash)?;
+        let mut run = vec![];
+        for block_hash in blocks.iter().skip(1) {
+            if self.merged_deltas.contains_key(block_hash) {
+                break;
+            }
+            run.push(*block_hash);
+        }
+        if run.len() < Self::DELTAS_MERGE_BATCH {
+            return Ok(());
+        }
+        let run = &run[run.len() - Self::DELTAS_MERGE_BATCH..];
+        let get_block = |block_hash: &CryptoHash| {
+            self.deltas
+                .get(block_hash)
+                .map(|delta| delta.metadata.block)
+                .ok_or_else(|| missing_delta_error(block_hash))
+        };
+        let newest_block = get_block(&run[0])?;
+        let first_block = get_block(&run[run.len() - 1])?;
+
+        // The run is linear if all the blocks at its heights are on the path from its newest
+        // block to its oldest one.
+        let mut num_blocks_on_path = 1;
+        let mut block = newest_block;
+        while block.hash != first_block.hash {
+            block = get_block(&block.prev_hash)?;
+            num_blocks_on_path += 1;
+        }
+        let heights = first_block.height..=newest_block.height;
+        let num_blocks_at_heights = self
+            .deltas
+            .values()
+            .filter(|delta| heights.contains(&delta.metadata.block.height))
+            .count();
+        if num_blocks_at_heights != num_blocks_on_path {
+            return Ok(());
+        }
+
+        let mut changes = CachedFlatStateChanges::default();
+        for block_hash in run.iter().rev() {
+            changes.merge(&self.get_block_changes(block_hash)?);
+        }
+        let spilled_changes = self.spilled_changes.get_mut().expect(super::POISONED_LOCK_ERR);
+        for block_hash in run {
+            spilled_changes.pop(block_hash);
+            if let Some(delta) = self.deltas.get_mut(block_hash) {
+                delta.changes = None;
+            }
+        }
+        debug!(
+            target: "store",
+            shard_id = self.shard_uid.shard_id(),
+            first_block_height = first_block.height,
+            newest_block_height = newest_block.height,
+            "Merged flat storage deltas");
+        self.merged_deltas.insert(
+            newest_block.hash,
+            MergedFlatStateDelta { first_block, num_blocks: run.len(), changes: Arc::new(changes) },
+        );
+        Ok(())
+    }
+
+    /// Total size in bytes of the changes kept in memory, including the merged ones and the ones
+    /// read back from disk.
+    fn cached_changes_size(&self) -> u64 {
+        let deltas_size: u64 = self
+            .deltas
+            .values()
+            .filter_map(|delta| delta.changes.as_ref())
+            .map(|changes| changes.total_size())
+            .sum();
+        let merged_size: u64 =
+            self.merged_deltas.values().map(|merged| merged.changes.total_size()).sum();
+        deltas_size + merged_size + self.spilled_changes_size()
+    }
+
+    /// Total size in bytes of the spilled changes read back from disk.
+    fn spilled_changes_size(&self) -> u64 {
+        let spilled_changes = self.spilled_changes.lock().expect(super::POISONED_LOCK_ERR);
+        spilled_changes.iter().map(|(_, changes)| changes.total_size()).sum()
+    }
+
+    /// Spills the changes of the oldest deltas to disk until the total size of the cached
+    /// changes is under `cached_changes_size_limit`. All deltas are stored on disk anyway, so
+    /// their changes are just dropped from memory. The changes of `new_block_hash` are kept, as
+    /// its delta may not be committed to disk yet. If that isn't enough, the oldest merged
+    /// changes are dropped too, and then the changes read back from disk.
+    fn maybe_spill_deltas(&mut self, new_block_hash: &CryptoHash) {
+        let mut cached_changes_size = self.cached_changes_size();
+        if cached_changes_size <= self.cached_changes_size_limit {
+            return;
+        }
+        let mut cached_deltas: Vec<_> = self
+            .deltas
+            .iter()
+            .filter(|(block_hash, delta)| block_hash != &new_block_hash && delta.changes.is_some())
+            .map(|(block_hash, delta)| (delta.metadata.block.height, *block_hash))
+            .collect();
+        cached_deltas.sort();
+        let mut num_spilled = 0;
+        for (_, block_hash) in cached_deltas {
+            if cached_changes_size <= self.cached_changes_size_limit {
+                break;
+            }
+            let Some(delta) = self.deltas.get_mut(&block_hash) else { continue };
+            if let Some(changes) = delta.changes.take() {
+                cached_changes_size -= changes.total_size();
+                num_spilled += 1;
+            }
+        }
+        let mut merged_deltas: Vec<_> = self
+            .merged_deltas
+            .iter()
+            .map(|(block_hash, merged)| (merged.first_block.height, *block_hash))
+            .collect();
+        merged_deltas.sort();
+        for (_, block_hash) in merged_deltas {
+            if cached_changes_size <= self.cached_changes_size_limit {
+                break;
+            }
+            if let Some(merged) = self.merged_deltas.remove(&block_hash) {
+                cached_changes_size -= merged.changes.total_size();
+            }
+        }
+        if cached_changes_size > self.cached_changes_size_limit {
+            cached_changes_size -= self.spilled_changes_size();
+            self.spilled_changes.lock().expect(super::POISONED_LOCK_ERR).clear();
+        }
+        debug!(
+            target: "store",
+            shard_id = self.shard_uid.shard_id(),
+            num_spilled,
+            cached_changes_size,
+            "Spilled flat storage deltas to disk");
     }
 
     /// Get sequence of blocks `target_block_hash` (inclusive) to flat head (exclusive)
@@ -146,9 +335,21 @@ impl FlatStorageInner {
         let cached_deltas = self.deltas.len();
         let mut cached_changes_num_items = 0;
         let mut cached_changes_size = 0;
-        for changes in self.deltas.values() {
-            cached_changes_num_items += changes.changes.len();
-            cached_changes_size += changes.changes.total_size();
+        let mut spilled_deltas = 0;
+        for delta in self.deltas.values() {
+            match &delta.changes {
+                Some(changes) => {
+                    cached_changes_num_items += changes.len();
+                    cached_changes_size += changes.total_size();
+                }
+                None => spilled_deltas += 1,
+            }
+        }
+        let mut merged_deltas = 0;
+        let mut merged_changes_size = 0;
+        for merged in self.merged_deltas.values() {
+            merged_deltas += merged.num_blocks;
+            merged_changes_size += merged.changes.total_size();
         }
 
         self.metrics.set_cached_deltas(
@@ -156,6 +357,9 @@ impl FlatStorageInner {
             cached_changes_num_items,
             cached_changes_size,
         );
+        self.metrics.set_compacted_deltas(spilled_deltas, merged_deltas, merged_changes_size);
+        let cached_changes_size =
+            cached_changes_size + merged_changes_size + self.spilled_changes_size();
 
         let cached_changes_size_bytes = bytesize::ByteSize(cached_changes_size);
         if cached_changes_size_bytes >= Self::CACHED_CHANGES_SIZE_LIMIT {
@@ -248,24 +452,35 @@ impl FlatStorage {
         let metrics = FlatStorageMetrics::new(shard_id);
         metrics.set_flat_head_height(flat_head.height);
 
-        let deltas_metadata = store_helper::get_all_deltas_metadata(&store, shard_uid)
+        let mut deltas_metadata = store_helper::get_all_deltas_metadata(&store, shard_uid)
             .unwrap_or_else(|_| {
                 panic!("Cannot read flat state deltas metadata for shard {shard_id} from storage")
             });
+        // Load the changes of the newest deltas, and leave the oldest ones on
+        // disk once the cached changes exceed the limit.
+        deltas_metadata
+            .sort_by_key(|delta_metadata| std::cmp::Reverse(delta_metadata.block.height));
+        let cached_changes_size_limit = FlatStorageInner::CACHED_CHANGES_SIZE_LIMIT.as_u64();
+        let mut cached_changes_size = 0;
         let mut deltas = HashMap::new();
         for delta_metadata in deltas_metadata {
             let block_hash = delta_metadata.block.hash;
-            let changes: CachedFlatStateChanges =
-                store_helper::get_delta_changes(&store, shard_uid, block_hash)
-                    .expect("failed to read flat state delta changes")
-                    .unwrap_or_else(|| {
-                        panic!("cannot find block delta for block {block_hash:?} shard {shard_id}")
-                    })
-                    .into();
-            deltas.insert(
-                block_hash,
-                CachedFlatStateDelta { metadata: delta_metadata, changes: Arc::new(changes) },
-            );
+            let changes = if cached_changes_size <= cached_changes_size_limit {
+                let changes: CachedFlatStateChanges =
+                    store_helper::get_delta_changes(&store, shard_uid, block_hash)
+                        .expect("failed to read flat state delta changes")
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "cannot find block delta for block {block_hash:?} shard {shard_id}"
+                            )
+                        })
+                        .into();
+                cached_changes_size += changes.total_size();
+                Some(Arc::new(changes))
+            } else {
+                None
+            };
+            deltas.insert(block_hash, CachedFlatStateDelta { metadata: delta_metadata, changes });
         }
 
         let inner = FlatStorageInner {
@@ -273,6 +488,11 @@ impl FlatStorage {
             shard_uid,
             flat_head,
             deltas,
+            merged_deltas: HashMap::new(),
+            spilled_changes: Mutex::new(LruCache::new(
+                FlatStorageInner::SPILLED_CHANGES_CACHE_SIZE,
+            )),
+            cached_changes_size_limit,
             move_head_enabled: true,
             metrics,
         };
@@ -297,11 +517,10 @@ impl FlatStorage {
         key: &[u8],
     ) -> Result<Option<FlatStateValue>, crate::StorageError> {
         let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
-        let blocks_to_head =
-            guard.get_blocks_to_head(block_hash).map_err(|e| StorageError::from(e))?;
-        for block_hash in blocks_to_head.iter() {
+        let changes_to_head =
+            guard.get_changes_to_head(block_hash).map_err(|e| StorageError::from(e))?;
+        for changes in changes_to_head.iter() {
             // If we found a key in changes, we can return a value because it is the most recent key update.
-            let changes = guard.get_block_changes(block_hash)?;
             match changes.get(key) {
                 Some(value_ref) => {
                     return Ok(value_ref.map(|value_ref| FlatStateValue::Ref(value_ref)));
@@ -397,7 +616,11 @@ impl FlatStorage {
             for hash in hashes_to_remove {
                 store_helper::remove_delta(&mut store_update, shard_uid, hash);
                 guard.deltas.remove(&hash);
+                guard.spilled_changes.lock().expect(super::POISONED_LOCK_ERR).pop(&hash);
             }
+            // Merged changes can't be partially applied, so drop the ones
+            // which aren't fully above the new flat head.
+            guard.merged_deltas.retain(|_, merged| merged.first_block.height > gc_height);
 
             store_update.commit().unwrap();
             debug!(target: "store", %shard_id, %block_hash, %block_height, "Moved flat storage head");
@@ -428,8 +651,13 @@ impl FlatStorage {
         let cached_changes: CachedFlatStateChanges = delta.changes.into();
         guard.deltas.insert(
             block_hash,
-            CachedFlatStateDelta { metadata: delta.metadata, changes: Arc::new(cached_changes) },
+            CachedFlatStateDelta {
+                metadata: delta.metadata,
+                changes: Some(Arc::new(cached_changes)),
+            },
         );
+        guard.maybe_merge_deltas(&block_hash)?;
+        guard.maybe_spill_deltas(&block_hash);
         guard.update_delta_metrics();
 
         Ok(store_update)
@@ -882,6 +1110,86 @@ mod tests {
         }
     }
 
+    /// Checks that lookups return the same values when the deltas are merged on a long linear
+    /// chain, when the flat head moves past merged changes, and when the changes of the deltas
+    /// and the merged changes are spilled to disk.
+    #[test]
+    fn flat_storage_delta_compaction() {
+        init_test_logger();
+        let mut chain = MockChain::linear_chain(1);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_update.commit().unwrap();
+
+        let flat_storage_manager = FlatStorageManager::new(store.clone());
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+
+        // Block i sets key &[1] to &[i], and key &[2] to &[i] if i is even.
+        let add_block = |chain: &mut MockChain, i: BlockHeight| {
+            let hash = chain.create_block();
+            let mut changes =
+                FlatStateChanges::from([(vec![1], Some(FlatStateValue::value_ref(&[i as u8])))]);
+            if i % 2 == 0 {
+                changes.insert(vec![2], Some(FlatStateValue::value_ref(&[i as u8])));
+            }
+            let delta = FlatStateDelta {
+                changes,
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block_info(&hash),
+                    prev_block_with_changes: None,
+                },
+            };
+            flat_storage.add_delta(delta).unwrap().commit().unwrap();
+        };
+        let num_blocks = 40;
+        for i in 1..=num_blocks {
+            add_block(&mut chain, i);
+        }
+        let num_spilled = |flat_storage: &FlatStorage| {
+            let guard = flat_storage.0.read().unwrap();
+            guard.deltas.values().filter(|delta| delta.changes.is_none()).count()
+        };
+        // The changes of the merged blocks are only kept on disk.
+        assert_eq!(flat_storage.0.read().unwrap().merged_deltas.len(), 2);
+        assert_eq!(num_spilled(&flat_storage), 32);
+
+        let check_values = |chain: &MockChain, from_height: BlockHeight, to_height: BlockHeight| {
+            for i in from_height..=to_height {
+                let chunk_view =
+                    flat_storage_manager.chunk_view(shard_uid, chain.get_block_hash(i)).unwrap();
+                assert_eq!(
+                    chunk_view.get_value(&[1]).unwrap(),
+                    Some(FlatStateValue::value_ref(&[i as u8]))
+                );
+                let value_2 = (i >= 2).then(|| FlatStateValue::value_ref(&[(i / 2 * 2) as u8]));
+                assert_eq!(chunk_view.get_value(&[2]).unwrap(), value_2);
+            }
+        };
+        check_values(&chain, 1, num_blocks);
+
+        // The merged changes below the new flat head are dropped.
+        flat_storage.update_flat_head(&chain.get_block_hash(10), true).unwrap();
+        assert_eq!(flat_storage.0.read().unwrap().merged_deltas.len(), 1);
+        check_values(&chain, 10, num_blocks);
+
+        // Once over the limit, the merged changes are spilled too, and the spilled changes
+        // read back from disk are cached.
+        flat_storage.0.write().unwrap().cached_changes_size_limit = 0;
+        add_block(&mut chain, num_blocks + 1);
+        assert!(flat_storage.0.read().unwrap().merged_deltas.is_empty());
+        assert_eq!(num_spilled(&flat_storage), num_blocks as usize - 10);
+        check_values(&chain, 10, num_blocks + 1);
+        let num_read_back = flat_storage.0.read().unwrap().spilled_changes.lock().unwrap().len();
+        assert_eq!(num_read_back, FlatStorageInner::SPILLED_CHANGES_CACHE_SIZE);
+    }
+
     #[test]
     /// Move flat storage to an exact height when flat storage has no changes.
     fn flat_storage_with_no_changes() {
@@ -59,6 +59,7 @@ impl<'a> StorageComputer<'a> {
             StateRecord::PostponedReceipt(_) => None,
             StateRecord::ReceivedData { .. } => None,
             StateRecord::DelayedReceipt(_) => None,
+            StateRecord::BufferedReceipt { .. } => None,
         };
         if let Some((account_id, storage_usage)) = account_and_storage {
             *self.result.entry(account_id).or_default() += storage_usage;
@@ -243,6 +244,12 @@ impl GenesisStateApplier {
                 StateRecord::DelayedReceipt(receipt) => storage.modify(|state_update| {
                     set_delayed_receipt(state_update, delayed_receipts_indices, &*receipt);
                 }),
+                // The records are split by the receiver of the receipt, so instead of buffering
+                // it in the shard it was sent from, it's delayed in the shard of its receiver,
+                // which is where it would end up once sent.
+                StateRecord::BufferedReceipt { receipt, .. } => storage.modify(|state_update| {
+                    set_delayed_receipt(state_update, delayed_receipts_indices, &*receipt);
+                }),
             }
         });
 
@@ -15,7 +15,8 @@ pub use columns::DBCol;
 pub use db::{
     CHUNK_TAIL_KEY, COLD_HEAD_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, GENESIS_JSON_HASH_KEY,
     GENESIS_STATE_ROOTS_KEY, HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY,
-    LATEST_KNOWN_KEY, STATE_SNAPSHOT_KEY, STATE_SYNC_DUMP_KEY, TAIL_KEY,
+    LATEST_KNOWN_KEY, RESHARDING_PROGRESS_KEY_PREFIX, STATE_SNAPSHOTS_KEY, STATE_SNAPSHOT_KEY,
+    STATE_SYNC_DUMP_KEY, TAIL_KEY,
 };
 use near_crypto::PublicKey;
 use near_fmt::{AbbrBytes, StorageKey};
@@ -29,7 +30,9 @@ use near_primitives::types::{AccountId, StateRoot};
 use near_vm_runner::logic::{CompiledContract, CompiledContractCache};
 use near_vm_runner::ContractCode;
 
-use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, StoreStatistics};
+use crate::db::{
+    refcount, ColumnRange, DBIterator, DBOp, DBSlice, DBTransaction, Database, StoreStatistics,
+};
 pub use crate::trie::iterator::{TrieIterator, TrieTraversalItem};
 pub use crate::trie::update::{TrieUpdate, TrieUpdateIterator, TrieUpdateValuePtr};
 pub use crate::trie::{
@@ -42,6 +45,7 @@ pub use crate::trie::{
 pub mod cold_storage;
 mod columns;
 pub mod config;
+mod contract_cache;
 pub mod db;
 pub mod flat;
 pub mod genesis;
@@ -54,7 +58,8 @@ mod sync_utils;
 pub mod test_utils;
 pub mod trie;
 
-pub use crate::config::{Mode, StoreConfig};
+pub use crate::config::{ColumnConfig, Mode, StoreConfig};
+pub use crate::contract_cache::SharedCompiledContractCache;
 pub use crate::opener::{
     checkpoint_hot_storage_and_cleanup_columns, StoreMigrator, StoreOpener, StoreOpenerError,
 };
@@ -119,14 +124,11 @@ impl NodeStorage {
         StoreOpener::new(home_dir, archive, config, cold_config)
     }
 
-    /// Constructs new object backed by given database.
-    fn from_rocksdb(
-        hot_storage: crate::db::RocksDB,
-        cold_storage: Option<crate::db::RocksDB>,
+    /// Constructs new object backed by given databases.
+    fn from_databases(
+        hot_storage: Arc<dyn Database>,
+        cold_storage: Option<Arc<dyn Database>>,
     ) -> Self {
-        let hot_storage = Arc::new(hot_storage);
-        let cold_storage = cold_storage.map(|storage| Arc::new(storage));
-
         let cold_db = if let Some(cold_storage) = cold_storage {
             Some(Arc::new(crate::db::ColdDB::new(cold_storage)))
         } else {
@@ -385,6 +387,24 @@ impl Store {
         self.storage.compact()
     }
 
+    /// Catches up with the changes written by the primary process if the
+    /// storage is opened in [`Mode::Secondary`].
+    pub fn catch_up_with_primary(&self) -> io::Result<()> {
+        self.storage.catch_up_with_primary()
+    }
+
+    /// Splits the column into ranges of at least `range_size` bytes which can
+    /// be compacted one at a time with `compact_range`.
+    pub fn column_ranges(&self, col: DBCol, range_size: u64) -> io::Result<Vec<ColumnRange>> {
+        self.storage.column_ranges(col, range_size)
+    }
+
+    /// Blocking compaction request of a range of the column if supported by
+    /// storage.
+    pub fn compact_range(&self, col: DBCol, range: &ColumnRange) -> io::Result<()> {
+        self.storage.compact_range(col, range)
+    }
+
     pub fn get_store_statistics(&self) -> Option<StoreStatistics> {
         self.storage.get_store_statistics()
     }
@@ -3,8 +3,9 @@ use crate::{NodeStorage, Store, Temperature};
 use actix_rt::ArbiterHandle;
 use near_o11y::metrics::{
     exponential_buckets, try_create_histogram, try_create_histogram_vec,
-    try_create_histogram_with_buckets, try_create_int_counter_vec, try_create_int_gauge,
-    try_create_int_gauge_vec, Histogram, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
+    try_create_int_gauge, try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec,
 };
 use once_cell::sync::Lazy;
 
@@ -18,6 +19,31 @@ pub(crate) static DATABASE_OP_LATENCY_HIST: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static COMPILED_CONTRACT_CACHE_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_compiled_contract_cache_requests_total",
+        "Requests to the compiled contract cache by shard and result: memory_hit, db_hit or miss",
+        &["shard_id", "result"],
+    )
+    .unwrap()
+});
+
+pub(crate) static COMPILED_CONTRACT_CACHE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_compiled_contract_cache_size_bytes",
+        "Total size of the compiled contracts kept in memory",
+    )
+    .unwrap()
+});
+
+pub(crate) static COMPILED_CONTRACT_CACHE_EVICTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_compiled_contract_cache_evictions_total",
+        "Compiled contracts evicted from memory to stay within the byte budget",
+    )
+    .unwrap()
+});
+
 // TODO(#9054): Rename the metric to be consistent with "accounting cache".
 pub static CHUNK_CACHE_HITS: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
@@ -149,6 +175,14 @@ pub static REVERTED_TRIE_INSERTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static VIEW_TRIE_MEMTRIE_MISSES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_view_trie_memtrie_misses",
+        "View tries read from disk because their state root is not loaded in memtries",
+        &["shard_id"],
+    )
+    .unwrap()
+});
 pub static PREFETCH_SENT: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec("near_prefetch_sent", "Prefetch requests sent to DB", &["shard_id"])
         .unwrap()
@@ -461,6 +495,30 @@ pub mod flat_state_metrics {
         )
         .unwrap()
     });
+    pub static FLAT_STORAGE_SPILLED_DELTAS: Lazy<IntGaugeVec> = Lazy::new(|| {
+        try_create_int_gauge_vec(
+            "flat_storage_spilled_deltas",
+            "Number of deltas in flat storage which changes are read from disk",
+            &["shard_id"],
+        )
+        .unwrap()
+    });
+    pub static FLAT_STORAGE_MERGED_DELTAS: Lazy<IntGaugeVec> = Lazy::new(|| {
+        try_create_int_gauge_vec(
+            "flat_storage_merged_deltas",
+            "Number of deltas in flat storage covered by merged changes",
+            &["shard_id"],
+        )
+        .unwrap()
+    });
+    pub static FLAT_STORAGE_MERGED_CHANGES_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
+        try_create_int_gauge_vec(
+            "flat_storage_merged_changes_size",
+            "Total size of merged changes in flat storage",
+            &["shard_id"],
+        )
+        .unwrap()
+    });
     pub static FLAT_STORAGE_DISTANCE_TO_HEAD: Lazy<IntGaugeVec> = Lazy::new(|| {
         try_create_int_gauge_vec(
             "flat_storage_distance_to_head",
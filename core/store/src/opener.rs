@@ -1,5 +1,7 @@
+use crate::config::StoreBackend;
 use crate::db::rocksdb::snapshot::{Snapshot, SnapshotError, SnapshotRemoveError};
 use crate::db::rocksdb::RocksDB;
+use crate::db::{Database, TestDB};
 use crate::metadata::{DbKind, DbMetadata, DbVersion, DB_VERSION};
 use crate::{DBCol, DBTransaction, Mode, NodeStorage, Store, StoreConfig, Temperature};
 use std::sync::Arc;
@@ -177,7 +179,8 @@ pub struct StoreOpener<'a> {
     migrator: Option<&'a dyn StoreMigrator>,
 }
 
-/// Opener for a single RocksDB instance.
+/// Opener for a single database, backed by the engine selected in its
+/// configuration.
 struct DBOpener<'a> {
     /// Path to the database.
     ///
@@ -257,6 +260,9 @@ impl<'a> StoreOpener<'a> {
             };
             tracing::info!(target: "db_opener", path=hot_path, cold_path=cold_path, "Opening NodeStorage");
         }
+        if self.hot.config.backend == StoreBackend::InMemory {
+            tracing::warn!(target: "db_opener", "Using the in-memory database backend, the data will be lost on exit");
+        }
 
         let hot_snapshot = {
             Self::ensure_created(mode, &self.hot)?;
@@ -280,7 +286,7 @@ impl<'a> StoreOpener<'a> {
             .transpose()?
             .map(|(db, _)| db);
 
-        let storage = NodeStorage::from_rocksdb(hot_db, cold_db);
+        let storage = NodeStorage::from_databases(hot_db, cold_db);
 
         hot_snapshot.remove()?;
         cold_snapshot.remove()?;
@@ -339,8 +345,7 @@ impl<'a> StoreOpener<'a> {
             None if mode.can_create() => {
                 tracing::info!(target: "db_opener", path=%opener.path.display(), "The database doesn't exist, creating it.");
 
-                let db = opener.create()?;
-                let store = Store { storage: Arc::new(db) };
+                let store = Store { storage: opener.create()? };
                 store.set_db_version(DB_VERSION)?;
                 return Ok(());
             }
@@ -467,15 +472,13 @@ impl<'a> StoreOpener<'a> {
         opener: &DBOpener,
         version: DbVersion,
     ) -> Result<Store, StoreOpenerError> {
-        let (db, _) = opener.open(mode, version)?;
-        let store = Store { storage: Arc::new(db) };
-        Ok(store)
+        let (storage, _) = opener.open(mode, version)?;
+        Ok(Store { storage })
     }
 
     fn open_store_unsafe(mode: Mode, opener: &DBOpener) -> Result<Store, StoreOpenerError> {
-        let db = opener.open_unsafe(mode)?;
-        let store = Store { storage: Arc::new(db) };
-        Ok(store)
+        let storage = opener.open_unsafe(mode)?;
+        Ok(Store { storage })
     }
 }
 
@@ -500,7 +503,13 @@ impl<'a> DBOpener<'a> {
     /// introduced, the kind is returned as `None`.  Otherwise, it’s also
     /// fetched and if it’s not there error is returned.
     fn get_metadata(&self) -> std::io::Result<Option<DbMetadata>> {
-        RocksDB::get_metadata(&self.path, self.config)
+        match self.config.backend {
+            StoreBackend::RocksDb => RocksDB::get_metadata(&self.path, self.config),
+            StoreBackend::InMemory => match TestDB::get_in_memory(&self.path) {
+                Some(db) => Some(DbMetadata::read(db.as_ref())).transpose(),
+                None => Ok(None),
+            },
+        }
     }
 
     /// Opens the database in given mode checking expected version and kind.
@@ -519,9 +528,13 @@ impl<'a> DBOpener<'a> {
     /// new version.
     ///
     /// Use [`Self::create`] to create a new database.
-    fn open(&self, mode: Mode, want_version: DbVersion) -> std::io::Result<(RocksDB, DbMetadata)> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        let metadata = DbMetadata::read(&db)?;
+    fn open(
+        &self,
+        mode: Mode,
+        want_version: DbVersion,
+    ) -> std::io::Result<(Arc<dyn Database>, DbMetadata)> {
+        let db = self.open_unsafe(mode)?;
+        let metadata = DbMetadata::read(db.as_ref())?;
         if want_version != metadata.version {
             let msg = format!("unexpected DbVersion {}; expected {want_version}", metadata.version);
             Err(std::io::Error::other(msg))
@@ -534,19 +547,28 @@ impl<'a> DBOpener<'a> {
     ///
     /// This is only suitable when creating the database or setting the version
     /// and kind for the first time.
-    fn open_unsafe(&self, mode: Mode) -> std::io::Result<RocksDB> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        Ok(db)
+    fn open_unsafe(&self, mode: Mode) -> std::io::Result<Arc<dyn Database>> {
+        match self.config.backend {
+            StoreBackend::RocksDb => {
+                Ok(Arc::new(RocksDB::open(&self.path, &self.config, mode, self.temp)?))
+            }
+            StoreBackend::InMemory => Ok(TestDB::open_in_memory(&self.path, mode)?),
+        }
     }
 
     /// Creates a new database.
-    fn create(&self) -> std::io::Result<RocksDB> {
-        RocksDB::open(&self.path, &self.config, Mode::Create, self.temp)
+    fn create(&self) -> std::io::Result<Arc<dyn Database>> {
+        self.open_unsafe(Mode::Create)
     }
 
     /// Creates a new snapshot for the database.
+    ///
+    /// The in-memory backend doesn't support snapshots.
     fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
-        Snapshot::new(&self.path, &self.config, self.temp)
+        match self.config.backend {
+            StoreBackend::RocksDb => Snapshot::new(&self.path, &self.config, self.temp),
+            StoreBackend::InMemory => Ok(Snapshot::none()),
+        }
     }
 }
 
@@ -699,4 +721,31 @@ mod tests {
         check_keys_existence(&store.get_hot_store(), &DBCol::Chunks, &keys, false);
         check_keys_existence(&store.get_hot_store(), &DBCol::BlockHeader, &keys, false);
     }
+
+    #[test]
+    fn test_in_memory_backend() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let config = StoreConfig { backend: StoreBackend::InMemory, ..StoreConfig::test_config() };
+        let opener = StoreOpener::new(home_dir.path(), false, &config, None);
+        assert!(matches!(
+            opener.open_in_mode(Mode::ReadWriteExisting),
+            Err(StoreOpenerError::DbDoesNotExist)
+        ));
+
+        let store = opener.open().unwrap().get_hot_store();
+        assert_eq!(store.get_db_version().unwrap(), Some(DB_VERSION));
+        assert_eq!(store.get_db_kind().unwrap(), Some(DbKind::RPC));
+        let mut store_update = store.store_update();
+        store_update.insert(DBCol::Block, vec![1], vec![42]);
+        store_update.commit().unwrap();
+        // Nothing is written to disk.
+        assert!(!home_dir.path().join("data").exists());
+
+        let store = opener.open_in_mode(Mode::ReadOnly).unwrap().get_hot_store();
+        check_keys_existence(&store, &DBCol::Block, &vec![vec![1]], true);
+        assert!(matches!(
+            opener.open_in_mode(Mode::Create),
+            Err(StoreOpenerError::DbAlreadyExists)
+        ));
+    }
 }
@@ -26,6 +26,8 @@ pub struct TrieConfig {
     pub shard_cache_config: TrieCacheConfig,
     pub view_shard_cache_config: TrieCacheConfig,
     pub enable_receipt_prefetching: bool,
+    /// Maximum number of trie keys prefetched for the actions of the receipts of a chunk.
+    pub receipt_prefetch_io_budget: usize,
 
     /// Configured accounts will be prefetched as SWEAT token account, if predecessor is listed as sender.
     pub sweat_prefetch_receivers: Vec<AccountId>,
@@ -34,6 +36,12 @@ pub struct TrieConfig {
     /// List of shards we will load into memory.
     pub load_mem_tries_for_shards: Vec<ShardUId>,
     pub load_mem_tries_for_all_shards: bool,
+    /// Number of heights for which memtrie roots are retained beyond the
+    /// garbage collection height.
+    pub mem_tries_gc_retained_heights: u64,
+    /// Arena fragmentation above which memtries are compacted after garbage
+    /// collection.
+    pub mem_tries_compaction_fragmentation_threshold: Option<f64>,
 }
 
 impl TrieConfig {
@@ -45,6 +53,7 @@ impl TrieConfig {
         this.view_shard_cache_config = config.view_trie_cache.clone();
 
         this.enable_receipt_prefetching = config.enable_receipt_prefetching;
+        this.receipt_prefetch_io_budget = config.receipt_prefetch_io_budget;
         for account in &config.sweat_prefetch_receivers {
             match AccountId::from_str(account) {
                 Ok(account_id) => this.sweat_prefetch_receivers.push(account_id),
@@ -59,6 +68,9 @@ impl TrieConfig {
         }
         this.load_mem_tries_for_shards = config.load_mem_tries_for_shards.clone();
         this.load_mem_tries_for_all_shards = config.load_mem_tries_for_all_shards;
+        this.mem_tries_gc_retained_heights = config.mem_tries_gc_retained_heights;
+        this.mem_tries_compaction_fragmentation_threshold =
+            config.mem_tries_compaction_fragmentation_threshold;
 
         this
     }
@@ -6,6 +6,7 @@ use crate::trie::mem::arena::metrics::{
     MEM_TRIE_ARENA_ACTIVE_ALLOCS_BYTES, MEM_TRIE_ARENA_MEMORY_USAGE_BYTES,
 };
 use crate::trie::mem::flexible_data::encoding::BorshFixedSize;
+use std::sync::Arc;
 
 /// Simple bump allocator with freelists.
 ///
@@ -92,7 +93,7 @@ impl Allocator {
     /// Adds a new chunk to the arena, and updates the next_alloc_pos to the beginning of
     /// the new chunk.
     fn new_chunk(&mut self, arena: &mut ArenaMemory) {
-        arena.chunks.push(vec![0; CHUNK_SIZE]);
+        arena.chunks.push(Arc::new(vec![0; CHUNK_SIZE]));
         self.next_alloc_pos =
             ArenaPos { chunk: u32::try_from(arena.chunks.len() - 1).unwrap(), pos: 0 };
         self.memory_usage_gauge.set(arena.chunks.len() as i64 * CHUNK_SIZE as i64);
@@ -136,10 +137,27 @@ impl Allocator {
         self.freelists[size_class] = pos;
     }
 
-    #[cfg(test)]
     pub fn num_active_allocs(&self) -> usize {
         self.active_allocs_count
     }
+
+    pub fn active_allocs_bytes(&self) -> usize {
+        self.active_allocs_bytes
+    }
+
+    /// Total memory reserved by the arena, including freed allocations.
+    pub fn memory_usage_bytes(&self, arena: &ArenaMemory) -> usize {
+        arena.chunks.len() * CHUNK_SIZE
+    }
+
+    /// Memory handed out by the bump allocator so far, i.e. the memory of
+    /// both active and freed allocations.
+    pub fn allocated_bytes(&self) -> usize {
+        if self.next_alloc_pos.is_invalid() {
+            return 0;
+        }
+        self.next_alloc_pos.chunk() * CHUNK_SIZE + self.next_alloc_pos.pos()
+    }
 }
 
 #[cfg(test)]
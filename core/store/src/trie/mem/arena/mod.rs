@@ -5,6 +5,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::Hash;
 use std::mem::size_of;
+use std::sync::Arc;
 
 use super::flexible_data::encoding::BorshFixedSize;
 
@@ -25,8 +26,12 @@ pub struct Arena {
 /// From an `ArenaMemory` one can obtain an `ArenaPtr` (single location)
 /// or `ArenaSlice` (range of bytes) to read the actual memory, and the
 /// mutable versions `ArenaPtrMut` and `ArenaSliceMut` to write memory.
+///
+/// Chunks of memory are reference counted, so that a frozen copy of the
+/// memory can be made cheaply (see `freeze`). A chunk shared with a frozen
+/// copy is copied before being written to, so the frozen copy never changes.
 pub struct ArenaMemory {
-    chunks: Vec<Vec<u8>>,
+    chunks: Vec<Arc<Vec<u8>>>,
 }
 
 #[derive(
@@ -89,7 +94,14 @@ impl ArenaMemory {
     }
 
     fn raw_slice_mut(&mut self, pos: ArenaPos, len: usize) -> &mut [u8] {
-        &mut self.chunks[pos.chunk()][pos.pos()..pos.pos() + len]
+        &mut Arc::make_mut(&mut self.chunks[pos.chunk()])[pos.pos()..pos.pos() + len]
+    }
+
+    /// Makes a read-only copy of the memory, sharing the chunks with it. The
+    /// copy is not affected by later writes to this memory, so it can be read
+    /// from other threads while the arena keeps being updated.
+    pub fn freeze(&self) -> ArenaMemory {
+        ArenaMemory { chunks: self.chunks.clone() }
     }
 
     /// Provides read access to a region of memory in the arena.
@@ -134,11 +146,32 @@ impl Arena {
     }
 
     /// Number of active allocations (alloc calls minus dealloc calls).
-    #[cfg(test)]
     pub fn num_active_allocs(&self) -> usize {
         self.allocator.num_active_allocs()
     }
 
+    /// Total size in bytes of the active allocations.
+    pub fn active_allocs_bytes(&self) -> usize {
+        self.allocator.active_allocs_bytes()
+    }
+
+    /// Memory reserved by the arena. This never decreases, as deallocated
+    /// memory is only reused by later allocations.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.allocator.memory_usage_bytes(&self.memory)
+    }
+
+    /// Fraction of the memory handed out by the allocator which is not used
+    /// by active allocations: memory of freed allocations which hasn't been
+    /// reused, and memory lost to rounding up allocation sizes.
+    pub fn fragmentation(&self) -> f64 {
+        let allocated_bytes = self.allocator.allocated_bytes();
+        if allocated_bytes == 0 {
+            return 0.0;
+        }
+        1.0 - self.allocator.active_allocs_bytes() as f64 / allocated_bytes as f64
+    }
+
     pub fn memory(&self) -> &ArenaMemory {
         &self.memory
     }
@@ -321,12 +354,13 @@ impl<'a> ArenaSliceMut<'a> {
 #[cfg(test)]
 mod tests {
     use crate::trie::mem::arena::ArenaPos;
+    use std::sync::Arc;
 
     #[test]
     fn test_arena_ptr_and_slice() {
         let mut arena = super::ArenaMemory::new();
-        arena.chunks.push(vec![0; 1000]);
-        arena.chunks.push(vec![0; 1000]);
+        arena.chunks.push(Arc::new(vec![0; 1000]));
+        arena.chunks.push(Arc::new(vec![0; 1000]));
 
         let chunk1 = ArenaPos { chunk: 1, pos: 0 };
 
@@ -353,4 +387,17 @@ mod tests {
             chunk1.offset_by(234)
         );
     }
+
+    #[test]
+    fn test_frozen_memory_unaffected_by_writes() {
+        let mut arena = super::ArenaMemory::new();
+        arena.chunks.push(Arc::new(vec![0; 1000]));
+        let pos = ArenaPos { chunk: 0, pos: 0 };
+
+        arena.slice_mut(pos, 8).write_pos_at(0, pos.offset_by(123));
+        let frozen = arena.freeze();
+        arena.slice_mut(pos, 8).write_pos_at(0, pos.offset_by(234));
+        assert_eq!(frozen.slice(pos, 8).read_ptr_at(0).raw_pos(), pos.offset_by(123));
+        assert_eq!(arena.slice(pos, 8).read_ptr_at(0).raw_pos(), pos.offset_by(234));
+    }
 }
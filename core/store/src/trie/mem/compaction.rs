@@ -0,0 +1,149 @@
+use super::arena::{Arena, ArenaMemory};
+use super::flexible_data::children::ChildrenView;
+use super::metrics::{MEM_TRIE_ARENA_FRAGMENTATION, MEM_TRIE_COMPACTIONS};
+use super::node::{InputMemTrieNode, MemTrieNodeId, MemTrieNodeView};
+use super::MemTries;
+use std::collections::HashMap;
+
+impl MemTries {
+    /// Fraction of the memory allocated by the arena which is not used by
+    /// the nodes of the loaded tries.
+    pub fn arena_fragmentation(&self) -> f64 {
+        self.arena.fragmentation()
+    }
+
+    /// Copies the tries of all loaded roots into a new arena and drops the
+    /// old one, so that the memory of nodes freed by garbage collection
+    /// which the allocator couldn't reuse is released.
+    ///
+    /// Nodes shared between roots stay shared. Duplicate nodes of the same
+    /// state root are deduplicated. This takes time proportional to the
+    /// number of nodes of the loaded tries, and doubles the memory usage
+    /// while running.
+    pub fn compact(&mut self) {
+        let shard_uid = self.shard_uid.to_string();
+        let mut arena = Arena::new(shard_uid.clone());
+        let mut copied = HashMap::new();
+        let mut roots = HashMap::new();
+        for (state_root, ids) in &self.roots {
+            let new_root = copy_node(self.arena.memory(), &mut arena, ids[0], &mut copied);
+            roots.insert(*state_root, vec![new_root]);
+        }
+        // Roots are referenced once per height they are present at.
+        for state_root in self.heights.values().flatten() {
+            roots[state_root][0].add_ref(&mut arena);
+        }
+        self.arena = arena;
+        self.roots = roots;
+
+        MEM_TRIE_COMPACTIONS.with_label_values(&[&shard_uid]).inc();
+        MEM_TRIE_ARENA_FRAGMENTATION
+            .with_label_values(&[&shard_uid])
+            .set(self.arena.fragmentation());
+    }
+}
+
+/// Copies the subtree of the given node into the new arena, reusing the nodes
+/// already copied.
+fn copy_node(
+    memory: &ArenaMemory,
+    arena: &mut Arena,
+    node_id: MemTrieNodeId,
+    copied: &mut HashMap<MemTrieNodeId, MemTrieNodeId>,
+) -> MemTrieNodeId {
+    if let Some(new_id) = copied.get(&node_id) {
+        return *new_id;
+    }
+    let view = node_id.as_ptr(memory).view();
+    let input = match &view {
+        MemTrieNodeView::Leaf { extension, value } => InputMemTrieNode::Leaf {
+            value: value.clone().to_flat_value(),
+            extension: extension.raw_slice().into(),
+        },
+        MemTrieNodeView::Extension { extension, child, .. } => InputMemTrieNode::Extension {
+            extension: extension.raw_slice().into(),
+            child: copy_node(memory, arena, child.id(), copied),
+        },
+        MemTrieNodeView::Branch { children, .. } => {
+            InputMemTrieNode::Branch { children: copy_children(memory, arena, children, copied) }
+        }
+        MemTrieNodeView::BranchWithValue { children, value, .. } => {
+            InputMemTrieNode::BranchWithValue {
+                children: copy_children(memory, arena, children, copied),
+                value: value.clone().to_flat_value(),
+            }
+        }
+    };
+    let new_id = MemTrieNodeId::new_with_hash(arena, input, view.node_hash());
+    copied.insert(node_id, new_id);
+    new_id
+}
+
+fn copy_children(
+    memory: &ArenaMemory,
+    arena: &mut Arena,
+    children: &ChildrenView<'_>,
+    copied: &mut HashMap<MemTrieNodeId, MemTrieNodeId>,
+) -> [Option<MemTrieNodeId>; 16] {
+    let mut new_children = [None; 16];
+    for (i, new_child) in new_children.iter_mut().enumerate() {
+        *new_child = children.get(i).map(|child| copy_node(memory, arena, child.id(), copied));
+    }
+    new_children
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::mem::lookup::memtrie_lookup;
+    use crate::trie::mem::updating::apply_memtrie_changes;
+    use crate::trie::mem::MemTries;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::state::FlatStateValue;
+
+    #[test]
+    fn test_compact() {
+        let mut tries = MemTries::new(ShardUId::single_shard());
+        let mut state_root = CryptoHash::default();
+        let mut state_roots = vec![];
+        for height in 0..20u64 {
+            let mut update = tries.update(state_root, false).unwrap();
+            for i in 0..50u64 {
+                let key = (height * 7 + i).to_le_bytes();
+                update.insert_memtrie_only(
+                    &key,
+                    FlatStateValue::Inlined(height.to_le_bytes().to_vec()),
+                );
+            }
+            let changes = update.to_mem_trie_changes_only();
+            state_root = apply_memtrie_changes(&mut tries, &changes, height);
+            state_roots.push((height, state_root));
+        }
+        // The same root at another height.
+        let root_id = tries.get_root(&state_root).unwrap().id();
+        tries.insert_root(state_root, root_id, 20);
+
+        tries.delete_until_height(15);
+        let num_nodes = tries.arena.num_active_allocs();
+        let roots = tries.roots();
+        let fragmentation = tries.arena_fragmentation();
+
+        tries.compact();
+        assert_eq!(tries.roots(), roots);
+        assert_eq!(tries.arena.num_active_allocs(), num_nodes);
+        assert!(tries.arena_fragmentation() < fragmentation);
+        for (height, state_root) in &state_roots[15..] {
+            let root = tries.get_root(state_root).unwrap();
+            assert_eq!(root.view().node_hash(), *state_root);
+            let key = (height * 7).to_le_bytes();
+            assert_eq!(
+                memtrie_lookup(root, &key, None),
+                Some(FlatStateValue::Inlined(height.to_le_bytes().to_vec()))
+            );
+        }
+
+        // Refcounts are preserved, so the tries can be garbage collected.
+        tries.delete_until_height(21);
+        assert_eq!(tries.arena.num_active_allocs(), 0);
+    }
+}
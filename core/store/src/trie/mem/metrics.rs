@@ -1,5 +1,6 @@
 use near_o11y::metrics::{
-    try_create_int_counter, try_create_int_counter_vec, try_create_int_gauge_vec, IntCounter,
+    exponential_buckets, try_create_gauge_vec, try_create_histogram_vec, try_create_int_counter,
+    try_create_int_counter_vec, try_create_int_gauge_vec, GaugeVec, HistogramVec, IntCounter,
     IntCounterVec, IntGaugeVec,
 };
 use once_cell::sync::Lazy;
@@ -29,3 +30,31 @@ pub static MEM_TRIE_NUM_LOOKUPS: Lazy<IntCounter> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub static MEM_TRIE_GC_FREED_NODES: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_mem_trie_gc_freed_nodes",
+        "Number of in-memory trie nodes freed by a garbage collection pass",
+        &["shard_uid"],
+        Some(exponential_buckets(1.0, 2.0, 24).unwrap()),
+    )
+    .unwrap()
+});
+
+pub static MEM_TRIE_ARENA_FRAGMENTATION: Lazy<GaugeVec> = Lazy::new(|| {
+    try_create_gauge_vec(
+        "near_mem_trie_arena_fragmentation",
+        "Fraction of the memory allocated by the in-memory trie arena not used by active nodes",
+        &["shard_uid"],
+    )
+    .unwrap()
+});
+
+pub static MEM_TRIE_COMPACTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_mem_trie_compactions_total",
+        "Number of times the in-memory trie arena was compacted",
+        &["shard_uid"],
+    )
+    .unwrap()
+});
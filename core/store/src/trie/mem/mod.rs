@@ -1,5 +1,6 @@
 use self::arena::Arena;
-use self::metrics::MEM_TRIE_NUM_ROOTS;
+use self::arena::ArenaMemory;
+use self::metrics::{MEM_TRIE_ARENA_FRAGMENTATION, MEM_TRIE_GC_FREED_NODES, MEM_TRIE_NUM_ROOTS};
 use self::node::{MemTrieNodeId, MemTrieNodePtr};
 use self::updating::MemTrieUpdate;
 use near_primitives::errors::StorageError;
@@ -7,14 +8,17 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::{BlockHeight, StateRoot};
 use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 mod arena;
+mod compaction;
 mod construction;
 mod flexible_data;
 pub mod loading;
 pub mod lookup;
 pub mod metrics;
 pub mod node;
+pub mod nodes_storage;
 pub mod updating;
 
 /// Check this, because in the code we conveniently assume usize is 8 bytes.
@@ -44,6 +48,66 @@ pub struct MemTries {
     shard_uid: ShardUId,
 }
 
+/// A read-only snapshot of the in-memory trie of a single state root. It can be
+/// shared between threads, e.g. those validating witnesses, serving view
+/// queries or applying chunks, and read without holding the lock of the
+/// `MemTries` it was taken from, which can keep being updated meanwhile.
+///
+/// Chunks of the arena written to by updates of the `MemTries` while a
+/// snapshot is alive are copied, so snapshots should be short lived.
+#[derive(Clone)]
+pub struct FrozenMemTrie {
+    memory: Arc<ArenaMemory>,
+    /// None iff the trie has no keys.
+    root: Option<MemTrieNodeId>,
+    state_root: StateRoot,
+    shard_uid: ShardUId,
+}
+
+impl FrozenMemTrie {
+    pub fn state_root(&self) -> StateRoot {
+        self.state_root
+    }
+
+    /// Returns the root node of the trie, or None if the trie is empty.
+    pub fn get_root(&self) -> Option<MemTrieNodePtr<'_>> {
+        self.root.map(|root| root.as_ptr(&self.memory))
+    }
+
+    pub(crate) fn get_node(&self, node_id: MemTrieNodeId) -> MemTrieNodePtr<'_> {
+        node_id.as_ptr(&self.memory)
+    }
+
+    /// Starts an update of the trie. The resulting changes can be applied to
+    /// the `MemTries` the snapshot was taken from, as long as the state root
+    /// is still loaded there.
+    pub fn update(&self, track_disk_changes: bool) -> MemTrieUpdate {
+        MemTrieUpdate::new(self.root, &self.memory, self.shard_uid.to_string(), track_disk_changes)
+    }
+}
+
+/// A state root loaded in the in-memory tries, along with the height it was
+/// inserted at. The same state root may be present at several heights.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemTrieRootInfo {
+    pub height: BlockHeight,
+    pub state_root: StateRoot,
+}
+
+/// Statistics of the in-memory tries of a shard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemTrieStats {
+    pub shard_uid: ShardUId,
+    /// Roots currently loaded, ordered by height.
+    pub roots: Vec<MemTrieRootInfo>,
+    /// Number of trie nodes stored in the arena, shared between all roots.
+    pub num_nodes: usize,
+    /// Bytes used by the trie nodes stored in the arena.
+    pub arena_active_bytes: usize,
+    /// Bytes reserved by the arena, including memory of freed nodes.
+    pub arena_memory_usage_bytes: usize,
+}
+
 impl MemTries {
     pub fn new(shard_uid: ShardUId) -> Self {
         Self {
@@ -96,11 +160,28 @@ impl MemTries {
         self.roots.get(state_root).map(|ids| ids[0].as_ptr(self.arena.memory()))
     }
 
+    /// Takes a snapshot of the trie of the given state root, or returns None
+    /// if the state root is not loaded.
+    pub fn freeze(&self, state_root: &StateRoot) -> Option<FrozenMemTrie> {
+        let root = if state_root == &CryptoHash::default() {
+            None
+        } else {
+            Some(self.get_root(state_root)?.id())
+        };
+        Some(FrozenMemTrie {
+            memory: Arc::new(self.arena.memory().freeze()),
+            root,
+            state_root: *state_root,
+            shard_uid: self.shard_uid,
+        })
+    }
+
     /// Expires all trie roots corresponding to a height smaller than
     /// `block_height`. This internally manages refcounts. If a trie root
     /// is expired but is still used at a higher height, it will still be
     /// valid until all references to that root expires.
     pub fn delete_until_height(&mut self, block_height: BlockHeight) {
+        let num_nodes_before = self.arena.num_active_allocs();
         let mut to_delete = vec![];
         self.heights.retain(|height, state_roots| {
             if *height < block_height {
@@ -115,6 +196,13 @@ impl MemTries {
         for state_root in to_delete {
             self.delete_root(&state_root);
         }
+        let shard_uid = self.shard_uid.to_string();
+        MEM_TRIE_GC_FREED_NODES
+            .with_label_values(&[&shard_uid])
+            .observe((num_nodes_before - self.arena.num_active_allocs()) as f64);
+        MEM_TRIE_ARENA_FRAGMENTATION
+            .with_label_values(&[&shard_uid])
+            .set(self.arena.fragmentation());
     }
 
     fn delete_root(&mut self, state_root: &CryptoHash) {
@@ -135,11 +223,35 @@ impl MemTries {
             .set(self.roots.len() as i64);
     }
 
-    /// Used for unit testing and integration testing.
+    /// Number of roots currently loaded, counting a root once per height it
+    /// is present at.
     pub fn num_roots(&self) -> usize {
         self.heights.iter().map(|(_, v)| v.len()).sum()
     }
 
+    /// Lists the roots currently loaded, ordered by height.
+    pub fn roots(&self) -> Vec<MemTrieRootInfo> {
+        self.heights
+            .iter()
+            .flat_map(|(height, state_roots)| {
+                state_roots
+                    .iter()
+                    .map(|state_root| MemTrieRootInfo { height: *height, state_root: *state_root })
+            })
+            .collect()
+    }
+
+    /// Returns the loaded roots and the node and memory usage of the arena.
+    pub fn stats(&self) -> MemTrieStats {
+        MemTrieStats {
+            shard_uid: self.shard_uid,
+            roots: self.roots(),
+            num_nodes: self.arena.num_active_allocs(),
+            arena_active_bytes: self.arena.active_allocs_bytes(),
+            arena_memory_usage_bytes: self.arena.memory_usage_bytes(),
+        }
+    }
+
     pub fn update(
         &self,
         root: CryptoHash,
@@ -171,7 +283,7 @@ impl MemTries {
 #[cfg(test)]
 mod tests {
     use super::node::{InputMemTrieNode, MemTrieNodeId};
-    use super::MemTries;
+    use super::{MemTrieRootInfo, MemTries};
     use crate::NibbleSlice;
     use near_primitives::hash::CryptoHash;
     use near_primitives::shard_layout::ShardUId;
@@ -254,4 +366,92 @@ mod tests {
         assert_eq!(tries.arena.num_active_allocs(), 0);
         assert_eq!(tries.num_roots(), 0);
     }
+
+    #[test]
+    fn test_stats() {
+        let mut tries = MemTries::new(ShardUId::single_shard());
+        let stats = tries.stats();
+        assert!(stats.roots.is_empty());
+        assert_eq!(stats.num_nodes, 0);
+        assert_eq!(stats.arena_memory_usage_bytes, 0);
+
+        let mut state_roots = Vec::new();
+        for height in [10, 11, 11] {
+            let state_root = tries
+                .construct_root(height, |arena| -> Result<Option<MemTrieNodeId>, ()> {
+                    let root = MemTrieNodeId::new(
+                        arena,
+                        InputMemTrieNode::Leaf {
+                            value: FlatStateValue::Inlined(
+                                format!("{}", state_roots.len()).into_bytes(),
+                            ),
+                            extension: NibbleSlice::new(&[])
+                                .encoded(true)
+                                .to_vec()
+                                .into_boxed_slice(),
+                        },
+                    );
+                    root.as_ptr_mut(arena.memory_mut()).compute_hash_recursively();
+                    Ok(Some(root))
+                })
+                .unwrap();
+            state_roots.push((height, state_root));
+        }
+        let stats = tries.stats();
+        assert_eq!(
+            stats.roots,
+            state_roots
+                .iter()
+                .map(|(height, state_root)| MemTrieRootInfo {
+                    height: *height,
+                    state_root: *state_root
+                })
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(stats.num_nodes, 3);
+        assert!(stats.arena_active_bytes > 0);
+        assert!(stats.arena_memory_usage_bytes >= stats.arena_active_bytes);
+
+        tries.delete_until_height(11);
+        let stats = tries.stats();
+        assert_eq!(stats.roots.len(), 2);
+        assert_eq!(stats.num_nodes, 2);
+    }
+
+    fn construct_leaf_root(tries: &mut MemTries, height: BlockHeight, value: &[u8]) -> CryptoHash {
+        tries
+            .construct_root(height, |arena| -> Result<Option<MemTrieNodeId>, ()> {
+                let root = MemTrieNodeId::new(
+                    arena,
+                    InputMemTrieNode::Leaf {
+                        value: FlatStateValue::Inlined(value.to_vec()),
+                        extension: NibbleSlice::new(&[]).encoded(true).to_vec().into_boxed_slice(),
+                    },
+                );
+                root.as_ptr_mut(arena.memory_mut()).compute_hash_recursively();
+                Ok(Some(root))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_frozen_trie() {
+        let mut tries = MemTries::new(ShardUId::single_shard());
+        let state_root = construct_leaf_root(&mut tries, 10, b"frozen");
+        let frozen = tries.freeze(&state_root).unwrap();
+        assert!(tries.freeze(&CryptoHash::hash_bytes(b"unknown")).is_none());
+        assert!(tries.freeze(&CryptoHash::default()).unwrap().get_root().is_none());
+
+        // The memory of the root is freed and reused by another root, which
+        // doesn't affect the snapshot.
+        tries.delete_until_height(11);
+        assert!(tries.get_root(&state_root).is_none());
+        let new_state_root = construct_leaf_root(&mut tries, 11, b"updated");
+        assert_eq!(tries.arena.num_active_allocs(), 1);
+
+        let root = frozen.get_root().unwrap();
+        assert_eq!(frozen.state_root(), state_root);
+        assert_eq!(root.view().node_hash(), state_root);
+        assert_eq!(tries.get_root(&new_state_root).unwrap().view().node_hash(), new_state_root);
+    }
 }
@@ -0,0 +1,65 @@
+use super::node::{MemTrieNodeId, MemTrieNodeView};
+use super::FrozenMemTrie;
+use crate::trie::trie_storage::TrieStorage;
+use crate::StorageError;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::state::FlatStateValue;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Serves the nodes of a frozen in-memory trie by hash, serialized as they
+/// are stored on disk, so that traversals of the trie such as iteration or
+/// state part generation don't need to read the disk.
+///
+/// In-memory trie nodes can't be looked up by hash, so the storage remembers
+/// the children of every node it returns: traversals always read a node
+/// before its children. Values which are not inlined in the in-memory trie,
+/// and nodes which weren't reached from the root this way, are read from the
+/// fallback storage.
+pub struct MemTrieNodesStorage {
+    frozen_memtrie: FrozenMemTrie,
+    nodes: Mutex<HashMap<CryptoHash, MemTrieNodeId>>,
+    values: Mutex<HashMap<CryptoHash, Arc<[u8]>>>,
+    fallback: Arc<dyn TrieStorage>,
+}
+
+impl MemTrieNodesStorage {
+    pub fn new(frozen_memtrie: FrozenMemTrie, fallback: Arc<dyn TrieStorage>) -> Self {
+        let mut nodes = HashMap::new();
+        if let Some(root) = frozen_memtrie.get_root() {
+            nodes.insert(root.view().node_hash(), root.id());
+        }
+        Self { frozen_memtrie, nodes: Mutex::new(nodes), values: Default::default(), fallback }
+    }
+
+    /// Remembers the children and the inlined value of the node, which the
+    /// traversal may read next.
+    fn remember_reachable(&self, view: &MemTrieNodeView<'_>) {
+        let mut nodes = self.nodes.lock().unwrap();
+        for child in view.iter_children() {
+            nodes.insert(child.view().node_hash(), child.id());
+        }
+        let value = match view {
+            MemTrieNodeView::Leaf { value, .. }
+            | MemTrieNodeView::BranchWithValue { value, .. } => value.to_flat_value(),
+            MemTrieNodeView::Extension { .. } | MemTrieNodeView::Branch { .. } => return,
+        };
+        if let FlatStateValue::Inlined(value) = value {
+            self.values.lock().unwrap().insert(hash(&value), value.into());
+        }
+    }
+}
+
+impl TrieStorage for MemTrieNodesStorage {
+    fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
+        if let Some(value) = self.values.lock().unwrap().get(hash) {
+            return Ok(value.clone());
+        }
+        let Some(node_id) = self.nodes.lock().unwrap().get(hash).copied() else {
+            return self.fallback.retrieve_raw_bytes(hash);
+        };
+        let view = self.frozen_memtrie.get_node(node_id).view();
+        self.remember_reachable(&view);
+        Ok(borsh::to_vec(&view.to_raw_trie_node_with_size()).unwrap().into())
+    }
+}
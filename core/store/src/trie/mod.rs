@@ -1,9 +1,9 @@
 use self::accounting_cache::TrieAccountingCache;
 use self::mem::lookup::memtrie_lookup;
-use self::mem::updating::{UpdatedMemTrieNode, UpdatedMemTrieNodeId};
-use self::mem::MemTries;
+use self::mem::nodes_storage::MemTrieNodesStorage;
+use self::mem::updating::{MemTrieUpdate, UpdatedMemTrieNode, UpdatedMemTrieNodeId};
+use self::mem::{FrozenMemTrie, MemTries};
 use self::trie_recording::TrieRecorder;
-use self::trie_storage::TrieMemoryPartialStorage;
 use crate::flat::{FlatStateChanges, FlatStorageChunkView};
 pub use crate::trie::config::TrieConfig;
 pub(crate) use crate::trie::config::{
@@ -15,7 +15,9 @@ pub use crate::trie::nibble_slice::NibbleSlice;
 pub use crate::trie::prefetching_trie_storage::{PrefetchApi, PrefetchError};
 pub use crate::trie::shard_tries::{KeyForStateChanges, ShardTries, WrappedTrieChanges};
 pub use crate::trie::state_snapshot::{SnapshotError, StateSnapshot, StateSnapshotConfig};
-pub use crate::trie::trie_storage::{TrieCache, TrieCachingStorage, TrieDBStorage, TrieStorage};
+pub use crate::trie::trie_storage::{
+    TrieCache, TrieCachingStorage, TrieDBStorage, TrieMemoryPartialStorage, TrieStorage,
+};
 use crate::StorageError;
 use borsh::{BorshDeserialize, BorshSerialize};
 pub use from_flat::construct_trie_from_flat;
@@ -34,7 +36,6 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Write;
 use std::hash::Hash;
-use std::rc::Rc;
 use std::str;
 use std::sync::{Arc, RwLock};
 
@@ -331,8 +332,11 @@ impl std::fmt::Debug for TrieNode {
 }
 
 pub struct Trie {
-    storage: Rc<dyn TrieStorage>,
+    storage: Arc<dyn TrieStorage>,
     memtries: Option<Arc<RwLock<MemTries>>>,
+    /// If present, lookups and updates are done on this snapshot of the
+    /// in-memory trie instead, without locking `memtries`.
+    frozen_memtrie: Option<FrozenMemTrie>,
     root: StateRoot,
     /// If present, flat storage is used to look up keys (if asked for).
     /// Otherwise, we would crawl through the trie.
@@ -618,7 +622,7 @@ impl Trie {
     /// By default, the accounting cache is not enabled. To enable or disable it
     /// (only in this crate), call self.accounting_cache.borrow_mut().set_enabled().
     pub fn new(
-        storage: Rc<dyn TrieStorage>,
+        storage: Arc<dyn TrieStorage>,
         root: StateRoot,
         flat_storage_chunk_view: Option<FlatStorageChunkView>,
     ) -> Self {
@@ -626,7 +630,7 @@ impl Trie {
     }
 
     pub fn new_with_memtries(
-        storage: Rc<dyn TrieStorage>,
+        storage: Arc<dyn TrieStorage>,
         memtries: Option<Arc<RwLock<MemTries>>>,
         root: StateRoot,
         flat_storage_chunk_view: Option<FlatStorageChunkView>,
@@ -641,6 +645,7 @@ impl Trie {
         Trie {
             storage,
             memtries,
+            frozen_memtrie: None,
             root,
             charge_gas_for_trie_node_access: flat_storage_chunk_view.is_none(),
             flat_storage_chunk_view,
@@ -649,6 +654,28 @@ impl Trie {
         }
     }
 
+    /// Makes a trie which reads from a snapshot of the in-memory trie, so that
+    /// it doesn't contend for the lock of the in-memory tries with the threads
+    /// updating them.
+    pub fn new_with_frozen_memtrie(
+        storage: Arc<dyn TrieStorage>,
+        frozen_memtrie: FrozenMemTrie,
+        flat_storage_chunk_view: Option<FlatStorageChunkView>,
+    ) -> Self {
+        let mut trie = Self::new(storage, frozen_memtrie.state_root(), flat_storage_chunk_view);
+        trie.frozen_memtrie = Some(frozen_memtrie);
+        trie
+    }
+
+    /// If the trie reads from a snapshot of the in-memory trie, makes a trie of
+    /// the same state root which reads trie nodes from the snapshot instead of
+    /// the storage. Lookups use the in-memory trie anyway, but traversals of
+    /// the trie, like iteration or state part generation, read the nodes.
+    pub fn with_memtrie_nodes_storage(&self) -> Option<Trie> {
+        let storage = MemTrieNodesStorage::new(self.frozen_memtrie.clone()?, self.storage.clone());
+        Some(Trie::new(Arc::new(storage), self.root, None))
+    }
+
     /// Temporary helper, must be removed after stateless validation release.
     pub fn dont_charge_gas_for_trie_node_access(&mut self) {
         self.charge_gas_for_trie_node_access = false;
@@ -663,15 +690,51 @@ impl Trie {
             self.root,
             self.flat_storage_chunk_view.clone(),
         );
+        trie.frozen_memtrie = self.frozen_memtrie.clone();
         trie.recorder = Some(RefCell::new(TrieRecorder::new()));
         trie
     }
 
+    /// Makes a new trie of the same state which can be used from another
+    /// thread. The fork has its own accounting cache, so reads through it
+    /// aren't affected by the nodes cached through this trie, and records its
+    /// reads separately if this trie records reads. The recorded reads can be
+    /// moved back with `merge_recorded_storage`.
+    pub fn fork(&self) -> Self {
+        let mut trie = Self::new_with_memtries(
+            self.storage.clone(),
+            self.memtries.clone(),
+            self.root,
+            self.flat_storage_chunk_view.clone(),
+        );
+        trie.frozen_memtrie = self.frozen_memtrie.clone();
+        trie.charge_gas_for_trie_node_access = self.charge_gas_for_trie_node_access;
+        if self.recorder.is_some() {
+            trie.recorder = Some(RefCell::new(TrieRecorder::new()));
+        }
+        trie
+    }
+
+    /// Moves the reads recorded through `other`, usually a fork of this trie,
+    /// into the state proof recorded by this trie. Does nothing if this trie
+    /// doesn't record reads.
+    pub fn merge_recorded_storage(&self, other: &Trie) {
+        if let (Some(recorder), Some(other_recorder)) = (&self.recorder, &other.recorder) {
+            recorder.borrow_mut().merge(&mut other_recorder.borrow_mut());
+        }
+    }
+
     /// Takes the recorded state proof out of the trie.
     pub fn recorded_storage(&self) -> Option<PartialStorage> {
         self.recorder.as_ref().map(|recorder| recorder.borrow_mut().recorded_storage())
     }
 
+    /// Size of the state proof recorded so far, or None if the trie doesn't
+    /// record reads.
+    pub fn recorded_storage_size(&self) -> Option<usize> {
+        self.recorder.as_ref().map(|recorder| recorder.borrow().recorded_storage_size())
+    }
+
     /// Constructs a Trie from the partial storage (i.e. state proof) that
     /// was returned from recorded_storage(). If used to access the same trie
     /// nodes as when the partial storage was generated, this trie will behave
@@ -687,12 +750,32 @@ impl Trie {
     ) -> Self {
         let PartialState::TrieValues(nodes) = partial_storage.nodes;
         let recorded_storage = nodes.into_iter().map(|value| (hash(&value), value)).collect();
-        let storage = Rc::new(TrieMemoryPartialStorage::new(recorded_storage));
+        let storage = Arc::new(TrieMemoryPartialStorage::new(recorded_storage));
         let mut trie = Self::new(storage, root, None);
         trie.charge_gas_for_trie_node_access = !flat_storage_used;
         trie
     }
 
+    /// Like `from_recorded_storage`, but nodes missing from the recorded
+    /// storage are read from the storage of `fallback` instead of failing.
+    /// The returned partial storage reports which nodes were missing.
+    pub fn from_recorded_storage_with_fallback(
+        partial_storage: PartialStorage,
+        root: StateRoot,
+        flat_storage_used: bool,
+        fallback: &Trie,
+    ) -> (Self, Arc<TrieMemoryPartialStorage>) {
+        let PartialState::TrieValues(nodes) = partial_storage.nodes;
+        let recorded_storage = nodes.into_iter().map(|value| (hash(&value), value)).collect();
+        let storage = Arc::new(TrieMemoryPartialStorage::with_fallback(
+            recorded_storage,
+            fallback.storage.clone(),
+        ));
+        let mut trie = Self::new(storage.clone(), root, None);
+        trie.charge_gas_for_trie_node_access = !flat_storage_used;
+        (trie, storage)
+    }
+
     pub fn get_root(&self) -> &StateRoot {
         &self.root
     }
@@ -1261,16 +1344,20 @@ impl Trie {
         if self.root == Self::EMPTY_ROOT {
             return Ok(None);
         }
-        let lock = self.memtries.as_ref().unwrap().read().unwrap();
-        let root = lock.get_root(&self.root).ok_or_else(|| {
-            StorageError::StorageInconsistentState(format!(
-                "Failed to find root node {} in memtrie",
-                self.root
-            ))
-        })?;
-
         let mut accessed_nodes = Vec::new();
-        let flat_value = memtrie_lookup(root, key, Some(&mut accessed_nodes));
+        let flat_value = if let Some(frozen_memtrie) = &self.frozen_memtrie {
+            let root = frozen_memtrie.get_root().expect("Non-empty trie must have a root node");
+            memtrie_lookup(root, key, Some(&mut accessed_nodes))
+        } else {
+            let lock = self.memtries.as_ref().unwrap().read().unwrap();
+            let root = lock.get_root(&self.root).ok_or_else(|| {
+                StorageError::StorageInconsistentState(format!(
+                    "Failed to find root node {} in memtrie",
+                    self.root
+                ))
+            })?;
+            memtrie_lookup(root, key, Some(&mut accessed_nodes))
+        };
         if charge_gas_for_trie_node_access {
             for (node_hash, serialized_node) in &accessed_nodes {
                 self.accounting_cache
@@ -1378,7 +1465,7 @@ impl Trie {
     ) -> Result<Option<OptimizedValueRef>, StorageError> {
         let charge_gas_for_trie_node_access =
             mode == KeyLookupMode::Trie || self.charge_gas_for_trie_node_access;
-        if self.memtries.is_some() {
+        if self.memtries.is_some() || self.frozen_memtrie.is_some() {
             self.lookup_from_memory(key, charge_gas_for_trie_node_access)
         } else if mode == KeyLookupMode::FlatStorage && self.flat_storage_chunk_view.is_some() {
             self.lookup_from_flat_storage(key)
@@ -1425,23 +1512,17 @@ impl Trie {
     where
         I: IntoIterator<Item = (Vec<u8>, Option<Vec<u8>>)>,
     {
-        match &self.memtries {
-            Some(memtries) => {
+        match (&self.frozen_memtrie, &self.memtries) {
+            (Some(frozen_memtrie), _) => {
+                Ok(Self::update_memtrie(frozen_memtrie.update(true), changes))
+            }
+            (None, Some(memtries)) => {
                 // If we have in-memory tries, use it to construct the the changes entirely (for
                 // both in-memory and on-disk updates) because it's much faster.
                 let guard = memtries.read().unwrap();
-                let mut trie_update = guard.update(self.root, true)?;
-                for (key, value) in changes {
-                    match value {
-                        Some(arr) => {
-                            trie_update.insert(&key, arr);
-                        }
-                        None => trie_update.delete(&key),
-                    }
-                }
-                Ok(trie_update.to_trie_changes())
+                Ok(Self::update_memtrie(guard.update(self.root, true)?, changes))
             }
-            None => {
+            (None, None) => {
                 let mut memory = NodesStorage::new();
                 let mut root_node = self.move_node_to_mutable(&mut memory, &self.root)?;
                 for (key, value) in changes {
@@ -1461,6 +1542,21 @@ impl Trie {
         }
     }
 
+    fn update_memtrie<I>(mut trie_update: MemTrieUpdate, changes: I) -> TrieChanges
+    where
+        I: IntoIterator<Item = (Vec<u8>, Option<Vec<u8>>)>,
+    {
+        for (key, value) in changes {
+            match value {
+                Some(arr) => {
+                    trie_update.insert(&key, arr);
+                }
+                None => trie_update.delete(&key),
+            }
+        }
+        trie_update.to_trie_changes()
+    }
+
     pub fn iter<'a>(&'a self) -> Result<TrieIterator<'a>, StorageError> {
         TrieIterator::new(self, None)
     }
@@ -1854,7 +1950,11 @@ mod tests {
         let trie2 = tries.get_trie_for_shard(ShardUId::single_shard(), root).recording_reads();
         trie2.get(b"dog").unwrap();
         trie2.get(b"horse").unwrap();
+        let recorded_size = trie2.recorded_storage_size().unwrap();
         let partial_storage = trie2.recorded_storage();
+        let PartialState::TrieValues(nodes) = &partial_storage.as_ref().unwrap().nodes;
+        assert_eq!(recorded_size, nodes.iter().map(|node| node.len()).sum::<usize>());
+        assert_eq!(trie2.recorded_storage_size(), Some(0));
 
         let trie3 = Trie::from_recorded_storage(partial_storage.unwrap(), root, false);
 
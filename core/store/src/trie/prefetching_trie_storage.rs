@@ -12,7 +12,6 @@ use near_primitives::shard_layout::ShardUId;
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{AccountId, ShardId, StateRoot};
 use std::collections::HashMap;
-use std::rc::Rc;
 use std::sync::Arc;
 use std::thread;
 
@@ -78,6 +77,8 @@ pub struct PrefetchApi {
     pub(crate) prefetching: PrefetchStagingArea,
 
     pub enable_receipt_prefetching: bool,
+    /// Maximum number of trie keys prefetched for the actions of the receipts of a chunk.
+    pub receipt_prefetch_io_budget: usize,
     /// Configured accounts will be prefetched as SWEAT token account, if predecessor is listed as receiver.
     pub sweat_prefetch_receivers: Vec<AccountId>,
     /// List of allowed predecessor accounts for SWEAT prefetching.
@@ -406,12 +407,14 @@ impl PrefetchApi {
         let sweat_prefetch_receivers = trie_config.sweat_prefetch_receivers.clone();
         let sweat_prefetch_senders = trie_config.sweat_prefetch_senders.clone();
         let enable_receipt_prefetching = trie_config.enable_receipt_prefetching;
+        let receipt_prefetch_io_budget = trie_config.receipt_prefetch_io_budget;
 
         let this = Self {
             work_queue_tx,
             work_queue_rx,
             prefetching: PrefetchStagingArea::new(shard_uid.shard_id()),
             enable_receipt_prefetching,
+            receipt_prefetch_io_budget,
             sweat_prefetch_receivers,
             sweat_prefetch_senders,
             shard_uid,
@@ -473,7 +476,7 @@ impl PrefetchApi {
                         // the clone only clones a few `Arc`s, so the performance
                         // hit is small.
                         let prefetcher_trie =
-                            Trie::new(Rc::new(prefetcher_storage.clone()), trie_root, None);
+                            Trie::new(Arc::new(prefetcher_storage.clone()), trie_root, None);
                         let storage_key = trie_key.to_vec();
                         metric_prefetch_sent.inc();
                         if let Ok(_maybe_value) = prefetcher_trie.get(&storage_key) {
@@ -8,7 +8,7 @@ use near_primitives::receipt::Receipt;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_part::PartId;
 use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
-use near_primitives::trie_key::TrieKey;
+use near_primitives::trie_key::{col, TrieKey};
 use near_primitives::types::{
     ConsolidatedStateChange, StateChangeCause, StateChangesForResharding, StateRoot,
 };
@@ -39,6 +39,7 @@ impl ShardTries {
         account_id_to_shard_uid: &dyn Fn(&AccountId) -> ShardUId,
     ) -> Result<HashMap<ShardUId, TrieUpdate>, StorageError> {
         let mut trie_updates: HashMap<_, _> = self.get_trie_updates(state_roots);
+        let buffered_receipts_shard_uid = buffered_receipts_shard_uid(state_roots)?;
         let mut insert_receipts = Vec::new();
         for ConsolidatedStateChange { trie_key, value } in changes.changes {
             match &trie_key {
@@ -55,6 +56,13 @@ impl ShardTries {
                     }
                     None => {}
                 },
+                TrieKey::BufferedReceiptIndices | TrieKey::BufferedReceipt { .. } => {
+                    let trie_update = trie_updates.get_mut(&buffered_receipts_shard_uid).unwrap();
+                    match value {
+                        Some(value) => trie_update.set(trie_key, value),
+                        None => trie_update.remove(trie_key),
+                    }
+                }
                 TrieKey::Account { account_id }
                 | TrieKey::ContractCode { account_id }
                 | TrieKey::AccessKey { account_id, .. }
@@ -101,6 +109,8 @@ impl ShardTries {
     /// The caller must guarantee that `state_roots` contains all shard_ids
     /// that `key_to_shard_id` that may return
     /// Ignore changes on DelayedReceipts or DelayedReceiptsIndices
+    /// The buffered outgoing receipts are all moved to one of the children, see
+    /// `buffered_receipts_shard_uid`
     /// Returns `store_update` and the new state_roots for children shards
     pub fn add_values_to_children_states(
         &self,
@@ -108,7 +118,11 @@ impl ShardTries {
         values: Vec<(Vec<u8>, Option<Vec<u8>>)>,
         account_id_to_shard_id: &dyn Fn(&AccountId) -> ShardUId,
     ) -> Result<(StoreUpdate, HashMap<ShardUId, StateRoot>), StorageError> {
+        let buffered_receipts_shard_uid = buffered_receipts_shard_uid(state_roots)?;
         self.add_values_to_children_states_impl(state_roots, values, &|raw_key| {
+            if raw_key.first() == Some(&col::BUFFERED_RECEIPT_OR_INDICES) {
+                return Ok(Some(buffered_receipts_shard_uid));
+            }
             // Here changes on DelayedReceipts or DelayedReceiptsIndices will be excluded
             // This is because we cannot migrate delayed receipts part by part. They have to be
             // reconstructed in the new states after all DelayedReceipts are ready in the original
@@ -196,6 +210,19 @@ impl ShardTries {
     }
 }
 
+/// Returns the child shard which takes over the buffered outgoing receipts of
+/// the parent shard. The receipts and their indices are copied as they are, so
+/// that the changes to them can be applied the same way during catchup, and
+/// are regrouped by the receiving shards of the new shard layout by the first
+/// chunk of the child which forwards receipts.
+fn buffered_receipts_shard_uid(
+    state_roots: &HashMap<ShardUId, StateRoot>,
+) -> Result<ShardUId, StorageError> {
+    state_roots.keys().min().copied().ok_or_else(|| {
+        StorageError::StorageInconsistentState("no children shards to reshard into".to_string())
+    })
+}
+
 fn apply_delayed_receipts_to_children_states_impl(
     trie_updates: &mut HashMap<ShardUId, TrieUpdate>,
     insert_receipts: &[Receipt],
@@ -321,13 +348,15 @@ mod tests {
         gen_changes, gen_receipts, get_all_delayed_receipts, test_populate_trie, TestTriesBuilder,
     };
 
-    use crate::{set, ShardTries, ShardUId, Trie};
+    use crate::{set, ShardTries, ShardUId, Trie, TrieAccess};
     use near_primitives::account::id::AccountId;
 
     use near_primitives::hash::hash;
     use near_primitives::receipt::{DelayedReceiptIndices, Receipt};
     use near_primitives::trie_key::TrieKey;
-    use near_primitives::types::{NumShards, StateChangeCause, StateRoot};
+    use near_primitives::types::{
+        ConsolidatedStateChange, NumShards, StateChangeCause, StateChangesForResharding, StateRoot,
+    };
     use rand::Rng;
     use std::collections::HashMap;
 
@@ -376,6 +405,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_buffered_receipts_go_to_first_child() {
+        let tries = TestTriesBuilder::new().build();
+        let children = [ShardUId { version: 2, shard_id: 0 }, ShardUId { version: 2, shard_id: 1 }];
+        let state_roots: HashMap<_, _> =
+            children.iter().map(|shard_uid| (*shard_uid, Trie::EMPTY_ROOT)).collect();
+        let account_id_to_shard_uid = |account_id: &AccountId| {
+            if account_id.as_str() < "b" {
+                children[0]
+            } else {
+                children[1]
+            }
+        };
+        let receipt = Receipt::new_balance_refund(&"bob".parse().unwrap(), 1);
+        let buffered_receipt_key = TrieKey::BufferedReceipt { receiving_shard: 1, index: 0 };
+        let indices_value = vec![1, 2, 3];
+        let receipt_value = borsh::to_vec(&receipt).unwrap();
+
+        let (store_update, state_roots) = tries
+            .add_values_to_children_states(
+                &state_roots,
+                vec![
+                    (TrieKey::BufferedReceiptIndices.to_vec(), Some(indices_value.clone())),
+                    (buffered_receipt_key.to_vec(), Some(receipt_value.clone())),
+                ],
+                &account_id_to_shard_uid,
+            )
+            .unwrap();
+        store_update.commit().unwrap();
+        let trie = tries.get_trie_for_shard(children[0], state_roots[&children[0]]);
+        assert_eq!(
+            trie.get(&TrieKey::BufferedReceiptIndices.to_vec()).unwrap(),
+            Some(indices_value)
+        );
+        assert_eq!(trie.get(&buffered_receipt_key.to_vec()).unwrap(), Some(receipt_value));
+        assert_eq!(state_roots[&children[1]], Trie::EMPTY_ROOT);
+
+        // The receipt is forwarded by the parent shard before the children are caught up.
+        let changes = StateChangesForResharding {
+            changes: vec![
+                ConsolidatedStateChange { trie_key: TrieKey::BufferedReceiptIndices, value: None },
+                ConsolidatedStateChange { trie_key: buffered_receipt_key.clone(), value: None },
+            ],
+            processed_delayed_receipts: vec![],
+        };
+        let trie_updates = tries
+            .apply_state_changes_to_children_states(&state_roots, changes, &account_id_to_shard_uid)
+            .unwrap();
+        let trie_update = &trie_updates[&children[0]];
+        assert_eq!(trie_update.get(&TrieKey::BufferedReceiptIndices).unwrap(), None);
+        assert_eq!(trie_update.get(&buffered_receipt_key).unwrap(), None);
+    }
+
     #[test]
     fn test_get_delayed_receipts() {
         let mut rng = rand::thread_rng();
@@ -1,4 +1,4 @@
-use super::mem::MemTries;
+use super::mem::{FrozenMemTrie, MemTrieStats, MemTries};
 use super::state_snapshot::{StateSnapshot, StateSnapshotConfig};
 use super::TrieRefcountSubtraction;
 use crate::flat::store_helper::remove_all_state_values;
@@ -20,7 +20,6 @@ use near_primitives::types::{
 };
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::collections::HashMap;
-use std::rc::Rc;
 use std::sync::{Arc, RwLock};
 use tracing::info;
 
@@ -35,11 +34,12 @@ struct ShardTriesInner {
     flat_storage_manager: FlatStorageManager,
     /// Prefetcher state, such as IO threads, per shard.
     prefetchers: RwLock<HashMap<ShardUId, (PrefetchApi, PrefetchingThreadsHandle)>>,
-    /// Provides access to the snapshot of the DB at the beginning of an epoch.
+    /// Provides access to the retained snapshots of the DB at the beginning of an epoch,
+    /// oldest first.
     // Needs a synchronization primitive because it can be concurrently accessed:
     // * writes by StateSnapshotActor
     // * reads by ViewClientActor
-    state_snapshot: Arc<RwLock<Option<StateSnapshot>>>,
+    state_snapshot: Arc<RwLock<Vec<StateSnapshot>>>,
     /// Configures how to make state snapshots.
     state_snapshot_config: StateSnapshotConfig,
 }
@@ -66,7 +66,7 @@ impl ShardTries {
             view_caches: RwLock::new(view_caches),
             flat_storage_manager,
             prefetchers: Default::default(),
-            state_snapshot: Arc::new(RwLock::new(None)),
+            state_snapshot: Arc::new(RwLock::new(Vec::new())),
             state_snapshot_config,
         }))
     }
@@ -97,6 +97,7 @@ impl ShardTries {
         state_root: StateRoot,
         is_view: bool,
         block_hash: Option<CryptoHash>,
+        use_mem_tries: bool,
     ) -> Trie {
         let caches_to_use = if is_view { &self.0.view_caches } else { &self.0.caches };
         let cache = {
@@ -133,7 +134,7 @@ impl ShardTries {
                 .clone()
         });
 
-        let storage = Rc::new(TrieCachingStorage::new(
+        let storage = Arc::new(TrieCachingStorage::new(
             self.0.store.clone(),
             cache,
             shard_uid,
@@ -142,16 +143,52 @@ impl ShardTries {
         ));
         let flat_storage_chunk_view = block_hash
             .and_then(|block_hash| self.0.flat_storage_manager.chunk_view(shard_uid, block_hash));
-        Trie::new_with_memtries(
-            storage,
-            self.get_mem_tries(shard_uid),
-            state_root,
-            flat_storage_chunk_view,
-        )
+        if !use_mem_tries {
+            return Trie::new(storage, state_root, flat_storage_chunk_view);
+        }
+        if !is_view {
+            return Trie::new_with_memtries(
+                storage,
+                self.get_mem_tries(shard_uid),
+                state_root,
+                flat_storage_chunk_view,
+            );
+        }
+        // View queries read a snapshot of the memtrie so that they don't
+        // contend for its lock with chunk application. Unlike the tries used
+        // to apply chunks, view tries may be requested for the state of older
+        // blocks, which memtries don't keep; those are read from disk instead.
+        let Some(mem_tries) = self.get_mem_tries(shard_uid) else {
+            return Trie::new(storage, state_root, flat_storage_chunk_view);
+        };
+        match mem_tries.read().expect(POISONED_LOCK_ERR).freeze(&state_root) {
+            Some(frozen_mem_trie) => {
+                Trie::new_with_frozen_memtrie(storage, frozen_mem_trie, flat_storage_chunk_view)
+            }
+            None => {
+                let mut buffer = itoa::Buffer::new();
+                metrics::VIEW_TRIE_MEMTRIE_MISSES
+                    .with_label_values(&[buffer.format(shard_uid.shard_id)])
+                    .inc();
+                Trie::new(storage, state_root, flat_storage_chunk_view)
+            }
+        }
+    }
+
+    /// Takes a snapshot of the in-memory trie of the given state root, which
+    /// can be read from any thread without locking the in-memory tries.
+    /// Returns None if the shard has no in-memory tries loaded, or the state
+    /// root is not loaded in them.
+    pub fn get_frozen_mem_trie(
+        &self,
+        shard_uid: ShardUId,
+        state_root: &StateRoot,
+    ) -> Option<FrozenMemTrie> {
+        self.get_mem_tries(shard_uid)?.read().expect(POISONED_LOCK_ERR).freeze(state_root)
     }
 
     pub fn get_trie_for_shard(&self, shard_uid: ShardUId, state_root: StateRoot) -> Trie {
-        self.get_trie_for_shard_internal(shard_uid, state_root, false, None)
+        self.get_trie_for_shard_internal(shard_uid, state_root, false, None, true)
     }
 
     pub fn get_trie_with_block_hash_for_shard_from_snapshot(
@@ -168,7 +205,7 @@ impl ShardTries {
                 .or_insert_with(|| TrieCache::new(&self.0.trie_config, shard_uid, true))
                 .clone()
         };
-        let storage = Rc::new(TrieCachingStorage::new(store, cache, shard_uid, true, None));
+        let storage = Arc::new(TrieCachingStorage::new(store, cache, shard_uid, true, None));
         let flat_storage_chunk_view = flat_storage_manager.chunk_view(shard_uid, *block_hash);
 
         Ok(Trie::new(storage, state_root, flat_storage_chunk_view))
@@ -181,11 +218,22 @@ impl ShardTries {
         block_hash: &CryptoHash,
         is_view: bool,
     ) -> Trie {
-        self.get_trie_for_shard_internal(shard_uid, state_root, is_view, Some(*block_hash))
+        self.get_trie_for_shard_internal(shard_uid, state_root, is_view, Some(*block_hash), true)
+    }
+
+    /// Like `get_trie_with_block_hash_for_shard`, but reads the state from
+    /// disk and flat storage even if the shard has in-memory tries loaded.
+    pub fn get_trie_without_mem_tries_for_shard(
+        &self,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+        block_hash: &CryptoHash,
+    ) -> Trie {
+        self.get_trie_for_shard_internal(shard_uid, state_root, false, Some(*block_hash), false)
     }
 
     pub fn get_view_trie_for_shard(&self, shard_uid: ShardUId, state_root: StateRoot) -> Trie {
-        self.get_trie_for_shard_internal(shard_uid, state_root, true, None)
+        self.get_trie_for_shard_internal(shard_uid, state_root, true, None, true)
     }
 
     pub fn store_update(&self) -> StoreUpdate {
@@ -208,7 +256,7 @@ impl ShardTries {
         &self.0.state_snapshot_config
     }
 
-    pub(crate) fn state_snapshot(&self) -> &Arc<RwLock<Option<StateSnapshot>>> {
+    pub(crate) fn state_snapshot(&self) -> &Arc<RwLock<Vec<StateSnapshot>>> {
         &self.0.state_snapshot
     }
 
@@ -414,17 +462,54 @@ impl ShardTries {
         Ok(())
     }
 
+    /// Unloads the in-memory tries for the shard, if they are loaded.
+    pub fn unload_mem_tries_for_shard(&self, shard_uid: ShardUId) {
+        if self.0.mem_tries.write().unwrap().remove(&shard_uid).is_some() {
+            info!(target: "memtrie", ?shard_uid, "Unloaded memtries");
+        }
+    }
+
     /// Retrieves the in-memory tries for the shard.
     pub fn get_mem_tries(&self, shard_uid: ShardUId) -> Option<Arc<RwLock<MemTries>>> {
         let guard = self.0.mem_tries.write().unwrap();
         guard.get(&shard_uid).cloned()
     }
 
+    /// Returns the loaded roots and memory usage of the in-memory tries for
+    /// the shard, or None if the shard has no in-memory tries loaded.
+    pub fn get_mem_trie_stats(&self, shard_uid: ShardUId) -> Option<MemTrieStats> {
+        Some(self.get_mem_tries(shard_uid)?.read().unwrap().stats())
+    }
+
+    /// Returns the stats of the in-memory tries of all shards which have them
+    /// loaded.
+    pub fn mem_trie_stats(&self) -> Vec<MemTrieStats> {
+        let mem_tries: Vec<_> = self.0.mem_tries.read().unwrap().values().cloned().collect();
+        mem_tries.iter().map(|mem_tries| mem_tries.read().unwrap().stats()).collect()
+    }
+
     /// Garbage collects the in-memory tries for the shard up to (and including) the given
-    /// height.
+    /// height, minus the configured number of retained heights. Compacts the tries afterwards
+    /// if the arena is fragmented beyond the configured threshold.
     pub fn delete_memtrie_roots_up_to_height(&self, shard_uid: ShardUId, height: BlockHeight) {
-        if let Some(memtries) = self.get_mem_tries(shard_uid) {
-            memtries.write().unwrap().delete_until_height(height);
+        let Some(memtries) = self.get_mem_tries(shard_uid) else {
+            return;
+        };
+        let mut memtries = memtries.write().unwrap();
+        memtries.delete_until_height(
+            height.saturating_sub(self.0.trie_config.mem_tries_gc_retained_heights),
+        );
+        if let Some(threshold) = self.0.trie_config.mem_tries_compaction_fragmentation_threshold {
+            let fragmentation = memtries.arena_fragmentation();
+            if fragmentation > threshold {
+                info!(
+                    target: "memtrie",
+                    %shard_uid,
+                    fragmentation,
+                    "Compacting in-memory trie"
+                );
+                memtries.compact();
+            }
         }
     }
 }
@@ -689,21 +774,31 @@ mod test {
     use std::{assert_eq, str::FromStr};
 
     fn create_trie() -> ShardTries {
-        let store = create_test_store();
+        create_trie_with_config(test_trie_config())
+    }
+
+    fn test_trie_config() -> TrieConfig {
         let trie_cache_config = TrieCacheConfig {
             default_max_bytes: DEFAULT_SHARD_CACHE_TOTAL_SIZE_LIMIT,
             per_shard_max_bytes: Default::default(),
             shard_cache_deletions_queue_capacity: 0,
         };
-        let trie_config = TrieConfig {
+        TrieConfig {
             shard_cache_config: trie_cache_config.clone(),
             view_shard_cache_config: trie_cache_config,
             enable_receipt_prefetching: false,
+            receipt_prefetch_io_budget: 0,
             sweat_prefetch_receivers: Vec::new(),
             sweat_prefetch_senders: Vec::new(),
             load_mem_tries_for_shards: Vec::new(),
             load_mem_tries_for_all_shards: false,
-        };
+            mem_tries_gc_retained_heights: 0,
+            mem_tries_compaction_fragmentation_threshold: None,
+        }
+    }
+
+    fn create_trie_with_config(trie_config: TrieConfig) -> ShardTries {
+        let store = create_test_store();
         let shard_uids = Vec::from([ShardUId::single_shard()]);
         ShardTries::new(
             store.clone(),
@@ -819,10 +914,13 @@ mod test {
             shard_cache_config: trie_cache_config.clone(),
             view_shard_cache_config: trie_cache_config,
             enable_receipt_prefetching: false,
+            receipt_prefetch_io_budget: 0,
             sweat_prefetch_receivers: Vec::new(),
             sweat_prefetch_senders: Vec::new(),
             load_mem_tries_for_shards: Vec::new(),
             load_mem_tries_for_all_shards: false,
+            mem_tries_gc_retained_heights: 0,
+            mem_tries_compaction_fragmentation_threshold: None,
         };
         let shard_uids = Vec::from([ShardUId { shard_id: 0, version: 0 }]);
         let shard_uid = *shard_uids.first().unwrap();
@@ -855,6 +953,134 @@ mod test {
         assert!(trie_caches.read().unwrap().get(&shard_uid).unwrap().get(&key).is_none());
     }
 
+    #[test]
+    fn test_view_trie_falls_back_to_disk() {
+        let shard_uid = ShardUId::single_shard();
+        let tries = create_trie();
+        let key = CryptoHash::hash_borsh("alice").as_bytes().to_vec();
+        let val: Vec<u8> = Vec::from([0, 1, 2, 3, 4]);
+
+        // The state is written to disk only, as for blocks whose roots have
+        // been garbage collected from memtries.
+        let trie = tries.get_trie_for_shard(shard_uid, Trie::EMPTY_ROOT);
+        let trie_changes = trie.update(vec![(key.clone(), Some(val.clone()))]).unwrap();
+        let mut store_update = tries.store_update();
+        let state_root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+        store_update.commit().unwrap();
+        tries
+            .0
+            .mem_tries
+            .write()
+            .unwrap()
+            .insert(shard_uid, Arc::new(RwLock::new(MemTries::new(shard_uid))));
+
+        let view_trie = tries.get_view_trie_for_shard(shard_uid, state_root);
+        assert_eq!(view_trie.get(&key).unwrap(), Some(val));
+        // Tries used to apply chunks must never miss the memtrie root.
+        let trie = tries.get_trie_for_shard(shard_uid, state_root);
+        assert!(trie.get(&key).is_err());
+    }
+
+    #[test]
+    fn test_trie_without_mem_tries_reads_disk() {
+        let shard_uid = ShardUId::single_shard();
+        let tries = create_trie();
+        let key = CryptoHash::hash_borsh("alice").as_bytes().to_vec();
+        let val: Vec<u8> = Vec::from([0, 1, 2, 3, 4]);
+
+        let trie = tries.get_trie_for_shard(shard_uid, Trie::EMPTY_ROOT);
+        let trie_changes = trie.update(vec![(key.clone(), Some(val.clone()))]).unwrap();
+        let mut store_update = tries.store_update();
+        let state_root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+        store_update.commit().unwrap();
+        tries
+            .0
+            .mem_tries
+            .write()
+            .unwrap()
+            .insert(shard_uid, Arc::new(RwLock::new(MemTries::new(shard_uid))));
+
+        // The memtries don't have the root, so only the trie ignoring them
+        // finds the value.
+        let trie = tries.get_trie_for_shard(shard_uid, state_root);
+        assert!(trie.get(&key).is_err());
+        let trie = tries.get_trie_without_mem_tries_for_shard(
+            shard_uid,
+            state_root,
+            &CryptoHash::default(),
+        );
+        assert_eq!(trie.get(&key).unwrap(), Some(val));
+    }
+
+    #[test]
+    fn test_view_trie_reads_frozen_memtrie() {
+        let shard_uid = ShardUId::single_shard();
+        let tries = create_trie();
+        let mem_tries = Arc::new(RwLock::new(MemTries::new(shard_uid)));
+        tries.0.mem_tries.write().unwrap().insert(shard_uid, mem_tries.clone());
+        let key = CryptoHash::hash_borsh("alice").as_bytes().to_vec();
+        let val: Vec<u8> = Vec::from([0, 1, 2, 3, 4]);
+
+        let trie = tries.get_trie_for_shard(shard_uid, Trie::EMPTY_ROOT);
+        let trie_changes = trie.update(vec![(key.clone(), Some(val.clone()))]).unwrap();
+        let mut store_update = tries.store_update();
+        let state_root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+        store_update.commit().unwrap();
+        tries.apply_memtrie_changes(&trie_changes, shard_uid, 1);
+
+        let view_trie = tries.get_view_trie_for_shard(shard_uid, state_root);
+        // The view trie keeps reading its snapshot while the memtries are
+        // locked for writing, and after its root is garbage collected.
+        let mut guard = mem_tries.write().unwrap();
+        assert_eq!(view_trie.get(&key).unwrap(), Some(val.clone()));
+        guard.delete_until_height(2);
+        drop(guard);
+        assert_eq!(view_trie.get(&key).unwrap(), Some(val));
+        assert!(tries.get_frozen_mem_trie(shard_uid, &state_root).is_none());
+    }
+
+    #[test]
+    fn test_memtrie_gc_retention_and_compaction() {
+        let shard_uid = ShardUId::single_shard();
+        let tries = create_trie_with_config(TrieConfig {
+            mem_tries_gc_retained_heights: 2,
+            mem_tries_compaction_fragmentation_threshold: Some(0.0),
+            ..test_trie_config()
+        });
+        tries
+            .0
+            .mem_tries
+            .write()
+            .unwrap()
+            .insert(shard_uid, Arc::new(RwLock::new(MemTries::new(shard_uid))));
+
+        let mut state_root = Trie::EMPTY_ROOT;
+        let mut state_roots = vec![];
+        for height in 1..=5u64 {
+            let key = CryptoHash::hash_borsh(height).as_bytes().to_vec();
+            let trie = tries.get_trie_for_shard(shard_uid, state_root);
+            let trie_changes = trie.update(vec![(key, Some(vec![1, 2, 3]))]).unwrap();
+            let mut store_update = tries.store_update();
+            state_root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+            store_update.commit().unwrap();
+            tries.apply_memtrie_changes(&trie_changes, shard_uid, height);
+            state_roots.push(state_root);
+        }
+
+        // Roots below height 5 - 2 are deleted, and the rest survive the
+        // compaction.
+        tries.delete_memtrie_roots_up_to_height(shard_uid, 5);
+        let mem_tries = tries.get_mem_tries(shard_uid).unwrap();
+        let mem_tries = mem_tries.read().unwrap();
+        assert_eq!(mem_tries.num_roots(), 3);
+        for state_root in &state_roots[..2] {
+            assert!(mem_tries.get_root(state_root).is_none());
+        }
+        for state_root in &state_roots[2..] {
+            assert_eq!(mem_tries.get_root(state_root).unwrap().view().node_hash(), *state_root);
+        }
+    }
+
     #[test]
     fn test_delete_trie_for_shard() {
         let shard_uid = ShardUId::single_shard();
@@ -22,6 +22,7 @@ use crate::trie::nibble_slice::NibbleSlice;
 use crate::trie::trie_storage::TrieMemoryPartialStorage;
 use crate::trie::{
     ApplyStatePartResult, NodeHandle, RawTrieNodeWithSize, TrieNode, TrieNodeWithSize,
+    POISONED_LOCK_ERR,
 };
 use crate::{metrics, PartialStorage, StorageError, Trie, TrieChanges};
 use borsh::BorshDeserialize;
@@ -33,7 +34,6 @@ use near_primitives::state_record::is_contract_code_key;
 use near_primitives::types::{ShardId, StateRoot};
 use near_vm_runner::ContractCode;
 use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
 use std::sync::Arc;
 
 use super::TrieRefcountDeltaMap;
@@ -243,7 +243,7 @@ impl Trie {
             .with_label_values(&[&shard_id.to_string()])
             .start_timer();
         let local_state_part_trie =
-            Trie::new(Rc::new(TrieMemoryPartialStorage::default()), StateRoot::new(), None);
+            Trie::new(Arc::new(TrieMemoryPartialStorage::default()), StateRoot::new(), None);
         let local_state_part_nodes =
             local_state_part_trie.update(all_state_part_items.into_iter())?.insertions;
         let local_trie_creation_duration = local_trie_creation_timer.stop_and_record();
@@ -264,7 +264,7 @@ impl Trie {
                 .map(|entry| (*entry.hash(), entry.payload().to_vec().into())),
         );
         let final_trie =
-            Trie::new(Rc::new(TrieMemoryPartialStorage::new(all_nodes)), self.root, None);
+            Trie::new(Arc::new(TrieMemoryPartialStorage::new(all_nodes)), self.root, None);
 
         final_trie.visit_nodes_for_state_part(part_id)?;
         let final_trie_storage = final_trie.storage.as_partial_storage().unwrap();
@@ -434,7 +434,7 @@ impl Trie {
         trie.visit_nodes_for_state_part(part_id)?;
         let storage = trie.storage.as_partial_storage().unwrap();
 
-        if storage.visited_nodes.borrow().len() != num_nodes {
+        if storage.visited_nodes.lock().expect(POISONED_LOCK_ERR).len() != num_nodes {
             // As all nodes belonging to state part were visited, there is some
             // unexpected data in downloaded state part.
             return Err(StorageError::UnexpectedTrieValue);
@@ -519,8 +519,11 @@ mod tests {
 
     use near_primitives::hash::{hash, CryptoHash};
 
-    use crate::test_utils::{gen_changes, test_populate_trie, TestTriesBuilder};
+    use crate::test_utils::{
+        gen_changes, gen_larger_changes, test_populate_trie, TestTriesBuilder,
+    };
     use crate::trie::iterator::CrumbStatus;
+    use crate::trie::mem::nodes_storage::MemTrieNodesStorage;
     use crate::trie::{
         TrieRefcountAddition, TrieRefcountDeltaMap, TrieRefcountSubtraction, ValueHandle,
     };
@@ -1142,6 +1145,52 @@ mod tests {
         }
     }
 
+    /// Checks that state parts generated from in-memory tries are the same as
+    /// the ones generated from the trie on disk, without reading the disk.
+    #[test]
+    fn test_get_trie_nodes_for_part_from_memtrie() {
+        let mut rng = rand::thread_rng();
+        let shard_uid = ShardUId::single_shard();
+        for _ in 0..10 {
+            let tries = TestTriesBuilder::new().with_flat_storage().with_in_memory_tries().build();
+            let trie = tries.get_trie_for_shard(shard_uid, Trie::EMPTY_ROOT);
+            let trie_changes = trie.update(gen_larger_changes(&mut rng, 50)).unwrap();
+            let mut store_update = tries.store_update();
+            let root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+            store_update.commit().unwrap();
+            tries.apply_memtrie_changes(&trie_changes, shard_uid, 1);
+
+            let disk_trie = tries.get_trie_for_shard(shard_uid, root);
+            let memtrie = tries
+                .get_view_trie_for_shard(shard_uid, root)
+                .with_memtrie_nodes_storage()
+                .unwrap();
+            // All values are small enough to be inlined in the memtrie.
+            let memtrie_only = Trie::new(
+                Arc::new(MemTrieNodesStorage::new(
+                    tries.get_frozen_mem_trie(shard_uid, &root).unwrap(),
+                    Arc::new(TrieMemoryPartialStorage::default()),
+                )),
+                root,
+                None,
+            );
+            for _ in 0..10 {
+                let num_parts: u64 = rng.gen_range(1..10);
+                let part_id = PartId::new(rng.gen_range(0..num_parts), num_parts);
+                let state_part =
+                    disk_trie.get_trie_nodes_for_part_without_flat_storage(part_id).unwrap();
+                assert_eq!(
+                    memtrie.get_trie_nodes_for_part_without_flat_storage(part_id),
+                    Ok(state_part.clone())
+                );
+                assert_eq!(
+                    memtrie_only.get_trie_nodes_for_part_without_flat_storage(part_id),
+                    Ok(state_part)
+                );
+            }
+        }
+    }
+
     /// Checks sanity of generating state part using flat storage.
     #[test]
     fn get_trie_nodes_for_part_with_flat_storage() {
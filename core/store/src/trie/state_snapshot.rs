@@ -1,5 +1,5 @@
 use crate::config::StateSnapshotType;
-use crate::db::STATE_SNAPSHOT_KEY;
+use crate::db::{STATE_SNAPSHOTS_KEY, STATE_SNAPSHOT_KEY};
 use crate::flat::{FlatStorageManager, FlatStorageStatus};
 use crate::Mode;
 use crate::{checkpoint_hot_storage_and_cleanup_columns, metrics, DBCol, NodeStorage};
@@ -10,6 +10,7 @@ use near_primitives::errors::EpochError;
 use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
+use std::collections::HashSet;
 use std::error::Error;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -138,6 +139,8 @@ pub struct StateSnapshotConfig {
     pub hot_store_path: PathBuf,
     pub state_snapshot_subdir: PathBuf,
     pub compaction_enabled: bool,
+    /// Number of the most recent snapshots to keep. Values below 1 are treated as 1.
+    pub num_retained_snapshots: usize,
 }
 
 impl ShardTries {
@@ -147,17 +150,22 @@ impl ShardTries {
     ) -> Result<(Store, FlatStorageManager), SnapshotError> {
         // Taking this lock can last up to 10 seconds, if the snapshot happens to be re-created.
         let guard = self.state_snapshot().try_read()?;
-        let data = guard.as_ref().ok_or(SnapshotError::SnapshotNotFound(*block_hash))?;
-        if &data.prev_block_hash != block_hash {
-            return Err(SnapshotError::IncorrectSnapshotRequested(
+        let latest = guard.last().ok_or(SnapshotError::SnapshotNotFound(*block_hash))?;
+        // The requested snapshot isn't necessarily the latest one, e.g. when
+        // serving state sync for an older epoch.
+        let data = guard
+            .iter()
+            .rev()
+            .find(|state_snapshot| &state_snapshot.prev_block_hash == block_hash)
+            .ok_or(SnapshotError::IncorrectSnapshotRequested(
                 *block_hash,
-                data.prev_block_hash,
-            ));
-        }
+                latest.prev_block_hash,
+            ))?;
         Ok((data.store.clone(), data.flat_storage_manager.clone()))
     }
 
     /// Makes a snapshot of the current state of the DB, if one is not already available.
+    /// The oldest snapshots beyond `num_retained_snapshots` are deleted.
     /// If a new snapshot is created, returns the ids of the included shards.
     pub fn create_state_snapshot(
         &self,
@@ -175,7 +183,7 @@ impl ShardTries {
         // `write()` lock is held for the whole duration of this function.
         let mut state_snapshot_lock = self.state_snapshot().write().unwrap();
         let db_snapshot_hash = self.get_state_snapshot_hash();
-        if let Some(state_snapshot) = &*state_snapshot_lock {
+        if let Some(state_snapshot) = state_snapshot_lock.last() {
             // only return Ok() when the hash stored in STATE_SNAPSHOT_KEY and in state_snapshot_lock and prev_block_hash are the same
             if db_snapshot_hash.is_ok_and(|hash| hash == prev_block_hash)
                 && state_snapshot.prev_block_hash == prev_block_hash
@@ -188,14 +196,21 @@ impl ShardTries {
 
         let StateSnapshotConfig { home_dir, hot_store_path, state_snapshot_subdir, .. } =
             self.state_snapshot_config();
+        let snapshot_path = Self::get_state_snapshot_base_dir(
+            &prev_block_hash,
+            home_dir,
+            hot_store_path,
+            state_snapshot_subdir,
+        );
+        // A checkpoint can't be made over an existing one, drop any stale snapshot of that block.
+        state_snapshot_lock
+            .retain(|state_snapshot| state_snapshot.prev_block_hash != prev_block_hash);
+        if snapshot_path.exists() {
+            std::fs::remove_dir_all(&snapshot_path)?;
+        }
         let storage = checkpoint_hot_storage_and_cleanup_columns(
             &self.get_store(),
-            &Self::get_state_snapshot_base_dir(
-                &prev_block_hash,
-                home_dir,
-                hot_store_path,
-                state_snapshot_subdir,
-            ),
+            &snapshot_path,
             // TODO: Cleanup Changes and DeltaMetadata to avoid extra memory usage.
             // Can't be cleaned up now because these columns are needed to `update_flat_head()`.
             Some(vec![
@@ -214,18 +229,25 @@ impl ShardTries {
         // it is used only for reading flat storage in the snapshot a
         // doesn't introduce memory overhead.
         let flat_storage_manager = FlatStorageManager::new(store.clone());
-        *state_snapshot_lock = Some(StateSnapshot::new(
+        state_snapshot_lock.push(StateSnapshot::new(
             store,
             prev_block_hash,
             flat_storage_manager,
             shard_uids,
             Some(block),
         ));
+        if let Err(err) = self.prune_state_snapshots(&mut state_snapshot_lock) {
+            tracing::error!(target: "state_snapshot", ?err, "Failed to delete the state snapshots that aren't retained");
+        }
+        let snapshot_hashes: Vec<CryptoHash> = state_snapshot_lock
+            .iter()
+            .map(|state_snapshot| state_snapshot.prev_block_hash)
+            .collect();
 
-        // this will set the new hash for state snapshot in rocksdb. will retry until success.
+        // this will set the new hashes for state snapshots in rocksdb. will retry until success.
         let mut set_state_snapshot_in_db = false;
         while !set_state_snapshot_in_db {
-            set_state_snapshot_in_db = match self.set_state_snapshot_hash(Some(prev_block_hash)) {
+            set_state_snapshot_in_db = match self.set_state_snapshot_hashes(&snapshot_hashes) {
                 Ok(_) => true,
                 Err(err) => {
                     // This will be retried.
@@ -237,16 +259,48 @@ impl ShardTries {
 
         metrics::HAS_STATE_SNAPSHOT.set(1);
         tracing::info!(target: "state_snapshot", ?prev_block_hash, "Made a checkpoint");
-        Ok(Some(state_snapshot_lock.as_ref().unwrap().get_shard_uids()))
+        Ok(Some(state_snapshot_lock.last().unwrap().get_shard_uids()))
     }
 
-    /// Runs compaction on the snapshot.
+    /// Drops the oldest snapshots beyond `num_retained_snapshots`, and deletes
+    /// from the file system every snapshot that isn't retained.
+    fn prune_state_snapshots(
+        &self,
+        state_snapshots: &mut Vec<StateSnapshot>,
+    ) -> Result<(), io::Error> {
+        let StateSnapshotConfig {
+            home_dir,
+            hot_store_path,
+            state_snapshot_subdir,
+            num_retained_snapshots,
+            ..
+        } = self.state_snapshot_config();
+        let num_pruned = state_snapshots.len().saturating_sub((*num_retained_snapshots).max(1));
+        // Drop Store before deleting the underlying data.
+        state_snapshots.drain(..num_pruned);
+        let retained: HashSet<String> = state_snapshots
+            .iter()
+            .map(|state_snapshot| state_snapshot.prev_block_hash.to_string())
+            .collect();
+        let path = home_dir.join(hot_store_path).join(state_snapshot_subdir);
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            if retained.contains(entry.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+            tracing::info!(target: "state_snapshot", path = ?entry.path(), "Deleting a state snapshot that isn't retained");
+            std::fs::remove_dir_all(entry.path())?;
+        }
+        Ok(())
+    }
+
+    /// Runs compaction on the latest snapshot.
     pub fn compact_state_snapshot(&self) -> Result<(), anyhow::Error> {
         let _span =
             tracing::info_span!(target: "state_snapshot", "compact_state_snapshot").entered();
         // It's fine if the access to state snapshot blocks.
         let state_snapshot_lock = self.state_snapshot().read().unwrap();
-        if let Some(state_snapshot) = &*state_snapshot_lock {
+        if let Some(state_snapshot) = state_snapshot_lock.last() {
             let _timer = metrics::COMPACT_STATE_SNAPSHOT_ELAPSED.start_timer();
             state_snapshot.store.compact()?;
         } else {
@@ -255,7 +309,7 @@ impl ShardTries {
         Ok(())
     }
 
-    /// Deletes all snapshots and unsets the STATE_SNAPSHOT_KEY.
+    /// Deletes all snapshots and unsets the STATE_SNAPSHOT_KEY and STATE_SNAPSHOTS_KEY.
     pub fn delete_state_snapshot(&self) {
         let _span =
             tracing::info_span!(target: "state_snapshot", "delete_state_snapshot").entered();
@@ -263,10 +317,8 @@ impl ShardTries {
 
         // get snapshot_hash after acquiring write lock
         let mut state_snapshot_lock = self.state_snapshot().write().unwrap();
-        if state_snapshot_lock.is_some() {
-            // Drop Store before deleting the underlying data.
-            *state_snapshot_lock = None;
-        }
+        // Drop Store before deleting the underlying data.
+        state_snapshot_lock.clear();
         let StateSnapshotConfig { home_dir, hot_store_path, state_snapshot_subdir, .. } =
             self.state_snapshot_config();
 
@@ -282,10 +334,10 @@ impl ShardTries {
 
         // this will delete the STATE_SNAPSHOT_KEY-value pair from db. Will retry 3 times
         for _ in 0..3 {
-            match self.set_state_snapshot_hash(None) {
+            match self.set_state_snapshot_hashes(&[]) {
                 Ok(_) => break,
                 Err(err) => {
-                    tracing::error!(target: "state_snapshot", ?err, "Failed to delete the old state snapshot for BlockMisc::STATE_SNAPSHOT_KEY in rocksdb")
+                    tracing::error!(target: "state_snapshot", ?err, "Failed to delete the old state snapshots for BlockMisc::STATE_SNAPSHOT_KEY in rocksdb")
                 }
             }
         }
@@ -329,22 +381,42 @@ impl ShardTries {
         )
     }
 
-    /// Updates STATE_SNAPSHOT_KEY.
+    /// Retrieves STATE_SNAPSHOTS_KEY, the hashes of the retained snapshots, oldest first.
+    pub fn get_state_snapshot_hashes(&self) -> Result<Vec<CryptoHash>, io::Error> {
+        match self.get_store().get_ser(DBCol::BlockMisc, STATE_SNAPSHOTS_KEY)? {
+            Some(hashes) => Ok(hashes),
+            // Snapshots made before retention was supported are only recorded in
+            // STATE_SNAPSHOT_KEY.
+            None => Ok(vec![self.get_state_snapshot_hash()?]),
+        }
+    }
+
+    /// Updates STATE_SNAPSHOT_KEY, leaving only the given snapshot retained.
     pub fn set_state_snapshot_hash(&self, value: Option<CryptoHash>) -> Result<(), io::Error> {
+        self.set_state_snapshot_hashes(value.as_slice())
+    }
+
+    /// Updates STATE_SNAPSHOTS_KEY, and STATE_SNAPSHOT_KEY to the last of the given hashes.
+    fn set_state_snapshot_hashes(&self, hashes: &[CryptoHash]) -> Result<(), io::Error> {
         let mut store_update = self.store_update();
-        let key = STATE_SNAPSHOT_KEY;
-        match value {
-            None => store_update.delete(DBCol::BlockMisc, key),
-            Some(value) => store_update.set_ser(DBCol::BlockMisc, key, &value)?,
+        match hashes.last() {
+            None => {
+                store_update.delete(DBCol::BlockMisc, STATE_SNAPSHOT_KEY);
+                store_update.delete(DBCol::BlockMisc, STATE_SNAPSHOTS_KEY);
+            }
+            Some(latest) => {
+                store_update.set_ser(DBCol::BlockMisc, STATE_SNAPSHOT_KEY, latest)?;
+                store_update.set_ser(DBCol::BlockMisc, STATE_SNAPSHOTS_KEY, &hashes.to_vec())?;
+            }
         }
         store_update.commit().into()
     }
 
-    /// Read RocksDB for the latest available snapshot hash, if available, open base_path+snapshot_hash for the state snapshot
-    /// we don't deal with multiple snapshots here because we will deal with it whenever a new snapshot is created and saved to file system
+    /// Read RocksDB for the retained snapshot hashes, if available, open base_path+snapshot_hash for each state snapshot
+    /// we don't deal with snapshots beyond retention here because we will deal with them whenever a new snapshot is created and saved to file system
     pub fn maybe_open_state_snapshot(
         &self,
-        get_shard_uids_fn: impl FnOnce(CryptoHash) -> Result<Vec<ShardUId>, EpochError>,
+        get_shard_uids_fn: impl Fn(CryptoHash) -> Result<Vec<ShardUId>, EpochError>,
     ) -> Result<(), anyhow::Error> {
         let _span =
             tracing::info_span!(target: "state_snapshot", "maybe_open_state_snapshot").entered();
@@ -353,32 +425,40 @@ impl ShardTries {
             self.state_snapshot_config();
 
         // directly return error if no snapshot is found
-        let snapshot_hash = self.get_state_snapshot_hash()?;
-
-        let snapshot_path = Self::get_state_snapshot_base_dir(
-            &snapshot_hash,
-            &home_dir,
-            &hot_store_path,
-            &state_snapshot_subdir,
-        );
-        let parent_path = snapshot_path
-            .parent()
-            .ok_or(anyhow::anyhow!("{snapshot_path:?} needs to have a parent dir"))?;
-        tracing::debug!(target: "state_snapshot", ?snapshot_path, ?parent_path);
+        let snapshot_hashes = self.get_state_snapshot_hashes()?;
 
         let store_config = StoreConfig::default();
-
-        let opener = NodeStorage::opener(&snapshot_path, false, &store_config, None);
-        let storage = opener.open_in_mode(Mode::ReadOnly)?;
-        let store = storage.get_hot_store();
-        let flat_storage_manager = FlatStorageManager::new(store.clone());
-
-        let shard_uids = get_shard_uids_fn(snapshot_hash)?;
+        let mut state_snapshots = Vec::with_capacity(snapshot_hashes.len());
+        for snapshot_hash in snapshot_hashes {
+            let snapshot_path = Self::get_state_snapshot_base_dir(
+                &snapshot_hash,
+                &home_dir,
+                &hot_store_path,
+                &state_snapshot_subdir,
+            );
+            let parent_path = snapshot_path
+                .parent()
+                .ok_or(anyhow::anyhow!("{snapshot_path:?} needs to have a parent dir"))?;
+            tracing::debug!(target: "state_snapshot", ?snapshot_path, ?parent_path);
+
+            let opener = NodeStorage::opener(&snapshot_path, false, &store_config, None);
+            let storage = opener.open_in_mode(Mode::ReadOnly)?;
+            let store = storage.get_hot_store();
+            let flat_storage_manager = FlatStorageManager::new(store.clone());
+
+            let shard_uids = get_shard_uids_fn(snapshot_hash)?;
+            state_snapshots.push(StateSnapshot::new(
+                store,
+                snapshot_hash,
+                flat_storage_manager,
+                &shard_uids,
+                None,
+            ));
+            tracing::info!(target: "runtime", ?snapshot_hash, ?snapshot_path, "Detected and opened a state snapshot.");
+        }
         let mut guard = self.state_snapshot().write().unwrap();
-        *guard =
-            Some(StateSnapshot::new(store, snapshot_hash, flat_storage_manager, &shard_uids, None));
+        *guard = state_snapshots;
         metrics::HAS_STATE_SNAPSHOT.set(1);
-        tracing::info!(target: "runtime", ?snapshot_hash, ?snapshot_path, "Detected and opened a state snapshot.");
         Ok(())
     }
 }
@@ -7,20 +7,40 @@ use std::sync::Arc;
 /// A simple struct to capture a state proof as it's being accumulated.
 pub struct TrieRecorder {
     recorded: HashMap<CryptoHash, Arc<[u8]>>,
+    /// Total size of the recorded nodes and values.
+    size: usize,
 }
 
 impl TrieRecorder {
     pub fn new() -> Self {
-        Self { recorded: HashMap::new() }
+        Self { recorded: HashMap::new(), size: 0 }
     }
 
     pub fn record(&mut self, hash: &CryptoHash, node: Arc<[u8]>) {
-        self.recorded.insert(*hash, node);
+        let size = node.len();
+        if self.recorded.insert(*hash, node).is_none() {
+            self.size += size;
+        }
+    }
+
+    /// Size of the state proof accumulated so far, which lets callers bound
+    /// the size of the proof while recording it.
+    pub fn recorded_storage_size(&self) -> usize {
+        self.size
+    }
+
+    /// Moves the nodes recorded by `other` into this recorder.
+    pub fn merge(&mut self, other: &mut TrieRecorder) {
+        for (hash, node) in other.recorded.drain() {
+            self.record(&hash, node);
+        }
+        other.size = 0;
     }
 
     pub fn recorded_storage(&mut self) -> PartialStorage {
         let mut nodes: Vec<_> = self.recorded.drain().map(|(_key, value)| value).collect();
         nodes.sort();
+        self.size = 0;
         PartialStorage { nodes: PartialState::TrieValues(nodes) }
     }
 }
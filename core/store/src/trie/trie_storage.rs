@@ -10,7 +10,6 @@ use near_primitives::challenge::PartialState;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::ShardId;
-use std::cell::RefCell;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 
@@ -277,7 +276,7 @@ impl TrieCache {
     }
 }
 
-pub trait TrieStorage {
+pub trait TrieStorage: Send + Sync {
     /// Get bytes of a serialized `TrieNode`.
     ///
     /// # Errors
@@ -302,20 +301,29 @@ pub trait TrieStorage {
 #[derive(Default)]
 pub struct TrieMemoryPartialStorage {
     pub(crate) recorded_storage: HashMap<CryptoHash, Arc<[u8]>>,
-    pub(crate) visited_nodes: RefCell<HashSet<CryptoHash>>,
+    pub(crate) visited_nodes: Mutex<HashSet<CryptoHash>>,
+    /// Storage to read nodes missing from `recorded_storage` from, if any.
+    fallback: Option<Arc<dyn TrieStorage>>,
+    /// Nodes which were missing from `recorded_storage` and were read from
+    /// the fallback storage.
+    fallback_nodes: Mutex<HashSet<CryptoHash>>,
 }
 
 impl TrieStorage for TrieMemoryPartialStorage {
     fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
-        let result =
-            self.recorded_storage.get(hash).cloned().ok_or(StorageError::MissingTrieValue(
+        if let Some(value) = self.recorded_storage.get(hash) {
+            self.visited_nodes.lock().expect(POISONED_LOCK_ERR).insert(*hash);
+            return Ok(value.clone());
+        }
+        let Some(fallback) = &self.fallback else {
+            return Err(StorageError::MissingTrieValue(
                 MissingTrieValueContext::TrieMemoryPartialStorage,
                 *hash,
             ));
-        if result.is_ok() {
-            self.visited_nodes.borrow_mut().insert(*hash);
-        }
-        result
+        };
+        let value = fallback.retrieve_raw_bytes(hash)?;
+        self.fallback_nodes.lock().expect(POISONED_LOCK_ERR).insert(*hash);
+        Ok(value)
     }
 
     fn as_partial_storage(&self) -> Option<&TrieMemoryPartialStorage> {
@@ -325,11 +333,29 @@ impl TrieStorage for TrieMemoryPartialStorage {
 
 impl TrieMemoryPartialStorage {
     pub fn new(recorded_storage: HashMap<CryptoHash, Arc<[u8]>>) -> Self {
-        Self { recorded_storage, visited_nodes: Default::default() }
+        Self { recorded_storage, ..Default::default() }
+    }
+
+    /// Creates the storage which reads nodes missing from `recorded_storage`
+    /// from `fallback` instead of failing.
+    pub fn with_fallback(
+        recorded_storage: HashMap<CryptoHash, Arc<[u8]>>,
+        fallback: Arc<dyn TrieStorage>,
+    ) -> Self {
+        Self { recorded_storage, fallback: Some(fallback), ..Default::default() }
+    }
+
+    /// Returns the nodes which were missing from the recorded storage and
+    /// were read from the fallback storage.
+    pub fn fallback_nodes(&self) -> Vec<CryptoHash> {
+        let mut nodes: Vec<_> =
+            self.fallback_nodes.lock().expect(POISONED_LOCK_ERR).iter().copied().collect();
+        nodes.sort();
+        nodes
     }
 
     pub fn partial_state(&self) -> PartialState {
-        let touched_nodes = self.visited_nodes.borrow();
+        let touched_nodes = self.visited_nodes.lock().expect(POISONED_LOCK_ERR);
         let mut nodes: Vec<_> =
             self.recorded_storage
                 .iter()
@@ -9,16 +9,14 @@ use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::TrieNodesCount;
 use rand::seq::SliceRandom;
 use rand::Rng;
-use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
-use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// TrieMemoryPartialStorage, but contains only the first n requested nodes.
 pub struct IncompletePartialStorage {
     pub(crate) recorded_storage: HashMap<CryptoHash, Arc<[u8]>>,
-    pub(crate) visited_nodes: RefCell<HashSet<CryptoHash>>,
+    pub(crate) visited_nodes: Mutex<HashSet<CryptoHash>>,
     pub node_count_to_fail_after: usize,
 }
 
@@ -42,9 +40,10 @@ impl TrieStorage for IncompletePartialStorage {
             .cloned()
             .expect("Recorded storage is missing the given hash");
 
-        self.visited_nodes.borrow_mut().insert(*hash);
+        let mut visited_nodes = self.visited_nodes.lock().unwrap();
+        visited_nodes.insert(*hash);
 
-        if self.visited_nodes.borrow().len() > self.node_count_to_fail_after {
+        if visited_nodes.len() > self.node_count_to_fail_after {
             Err(StorageError::MissingTrieValue(
                 MissingTrieValueContext::TrieMemoryPartialStorage,
                 *hash,
@@ -80,7 +79,7 @@ where
     println!("Test touches {} nodes, expected result {:?}...", size, expected);
     for i in 0..(size + 1) {
         let storage = IncompletePartialStorage::new(storage.clone(), i);
-        let new_trie = Trie::new(Rc::new(storage), *trie.get_root(), None);
+        let new_trie = Trie::new(Arc::new(storage), *trie.get_root(), None);
         let result = test(new_trie).map(|v| v.1);
         if i < size {
             assert_matches!(
@@ -141,10 +140,44 @@ fn test_reads_with_incomplete_storage() {
     }
 }
 
+#[test]
+fn test_reads_with_fallback_storage() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..20 {
+        let tries = TestTriesBuilder::new().with_shard_layout(1, 2).build();
+        let shard_uid = ShardUId { version: 1, shard_id: 0 };
+        let trie_changes = simplify_changes(&gen_changes(&mut rng, 20));
+        if trie_changes.is_empty() {
+            continue;
+        }
+        let state_root =
+            test_populate_trie(&tries, &Trie::EMPTY_ROOT, shard_uid, trie_changes.clone());
+        let get_trie = || tries.get_trie_for_shard(shard_uid, state_root);
+        let (key, _) = trie_changes.choose(&mut rng).unwrap();
+        let mut lookup =
+            |trie: Trie| -> Result<_, StorageError> { trie.get(key).map(move |v| (trie, v)) };
+        let (storage, _, expected) = setup_storage(get_trie(), &mut lookup);
+
+        // Drop one of the recorded nodes.
+        let PartialState::TrieValues(mut nodes) = storage.nodes;
+        let missing = nodes.swap_remove(rng.gen_range(0..nodes.len()));
+        let storage = PartialStorage { nodes: PartialState::TrieValues(nodes) };
+
+        let trie = Trie::from_recorded_storage(storage.clone(), state_root, false);
+        assert_matches!(lookup(trie).map(|v| v.1), Err(StorageError::MissingTrieValue(..)));
+
+        let (trie, partial_storage) =
+            Trie::from_recorded_storage_with_fallback(storage, state_root, false, &get_trie());
+        assert_eq!(lookup(trie).map(|v| v.1), Ok(expected));
+        assert_eq!(partial_storage.fallback_nodes(), vec![hash(&missing)]);
+    }
+}
+
 #[cfg(test)]
 mod nodes_counter_tests {
     use super::*;
     use crate::trie::nibble_slice::NibbleSlice;
+    use std::rc::Rc;
 
     fn create_trie_key(nibbles: &[u8]) -> Vec<u8> {
         NibbleSlice::encode_nibbles(&nibbles, false).into_vec()
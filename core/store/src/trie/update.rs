@@ -8,6 +8,7 @@ use near_primitives::types::{
     TrieCacheMode,
 };
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 mod iterator;
 
@@ -24,8 +25,14 @@ pub type TrieUpdates = BTreeMap<Vec<u8>, TrieKeyValueUpdate>;
 /// TODO (#7327): rename to StateUpdate
 pub struct TrieUpdate {
     pub trie: Trie,
+    /// Changes committed to the update this one was forked from, shared by
+    /// all the forks. Read before the trie and after `committed`.
+    base: Option<Arc<RawStateChanges>>,
     committed: RawStateChanges,
     prospective: TrieUpdates,
+    /// Log of the changes committed to a fork, in the order of the commits,
+    /// so that they can be replayed on the update the fork was made from.
+    forked_changes: Option<Vec<(TrieKey, RawStateChange)>>,
 }
 
 pub enum TrieUpdateValuePtr<'a> {
@@ -51,7 +58,59 @@ impl<'a> TrieUpdateValuePtr<'a> {
 
 impl TrieUpdate {
     pub fn new(trie: Trie) -> Self {
-        TrieUpdate { trie, committed: Default::default(), prospective: Default::default() }
+        TrieUpdate {
+            trie,
+            base: None,
+            committed: Default::default(),
+            prospective: Default::default(),
+            forked_changes: None,
+        }
+    }
+
+    /// Makes updates of the current state which can be modified independently
+    /// from this one and from each other, e.g. from other threads. Changes
+    /// committed to a fork can be taken with `take_forked_changes` and applied
+    /// to this update with `commit_forked_changes`.
+    pub fn fork(&self, num_forks: usize) -> Vec<TrieUpdate> {
+        assert!(self.prospective.is_empty(), "Fork cannot be called with uncommitted changes.");
+        assert!(self.base.is_none(), "Fork of a fork is not supported.");
+        let base = Arc::new(self.committed.clone());
+        (0..num_forks)
+            .map(|_| TrieUpdate {
+                trie: self.trie.fork(),
+                base: Some(base.clone()),
+                committed: Default::default(),
+                prospective: Default::default(),
+                forked_changes: Some(Vec::new()),
+            })
+            .collect()
+    }
+
+    /// Takes the changes committed to this fork since the last call.
+    pub fn take_forked_changes(&mut self) -> Vec<(TrieKey, RawStateChange)> {
+        std::mem::take(self.forked_changes.as_mut().expect("Not a fork of a TrieUpdate."))
+    }
+
+    /// Commits the changes taken from a fork of this update, as if they were
+    /// committed to this update directly.
+    pub fn commit_forked_changes(&mut self, changes: Vec<(TrieKey, RawStateChange)>) {
+        assert!(self.prospective.is_empty(), "Uncommitted changes would be overwritten.");
+        for (trie_key, change) in changes {
+            self.committed
+                .entry(trie_key.to_vec())
+                .or_insert_with(|| RawStateChangesWithTrieKey { trie_key, changes: Vec::new() })
+                .changes
+                .push(change);
+        }
+    }
+
+    /// Returns the last committed change of the key, if any.
+    fn get_committed(&self, key: &[u8]) -> Option<&RawStateChange> {
+        let changes = match self.committed.get(key) {
+            Some(changes_with_trie_key) => changes_with_trie_key,
+            None => self.base.as_ref()?.get(key)?,
+        };
+        changes.changes.last()
     }
 
     pub fn trie(&self) -> &Trie {
@@ -66,10 +125,8 @@ impl TrieUpdate {
         let key = key.to_vec();
         if let Some(key_value) = self.prospective.get(&key) {
             return Ok(key_value.value.as_deref().map(TrieUpdateValuePtr::MemoryRef));
-        } else if let Some(changes_with_trie_key) = self.committed.get(&key) {
-            if let Some(RawStateChange { data, .. }) = changes_with_trie_key.changes.last() {
-                return Ok(data.as_deref().map(TrieUpdateValuePtr::MemoryRef));
-            }
+        } else if let Some(RawStateChange { data, .. }) = self.get_committed(&key) {
+            return Ok(data.as_deref().map(TrieUpdateValuePtr::MemoryRef));
         }
 
         let result = self
@@ -84,10 +141,8 @@ impl TrieUpdate {
         let key = key.to_vec();
         if let Some(key_value) = self.prospective.get(&key) {
             return Ok(key_value.value.as_ref().map(<Vec<u8>>::clone));
-        } else if let Some(changes_with_trie_key) = self.committed.get(&key) {
-            if let Some(RawStateChange { data, .. }) = changes_with_trie_key.changes.last() {
-                return Ok(data.as_ref().map(<Vec<u8>>::clone));
-            }
+        } else if let Some(RawStateChange { data, .. }) = self.get_committed(&key) {
+            return Ok(data.as_ref().map(<Vec<u8>>::clone));
         }
         self.trie.get(&key)
     }
@@ -108,6 +163,12 @@ impl TrieUpdate {
     pub fn commit(&mut self, event: StateChangeCause) {
         let prospective = std::mem::take(&mut self.prospective);
         for (raw_key, TrieKeyValueUpdate { trie_key, value }) in prospective.into_iter() {
+            if let Some(forked_changes) = &mut self.forked_changes {
+                forked_changes.push((
+                    trie_key.clone(),
+                    RawStateChange { cause: event.clone(), data: value.clone() },
+                ));
+            }
             self.committed
                 .entry(raw_key)
                 .or_insert_with(|| RawStateChangesWithTrieKey { trie_key, changes: Vec::new() })
@@ -129,6 +190,7 @@ impl TrieUpdate {
         self,
     ) -> Result<(Trie, TrieChanges, Vec<RawStateChangesWithTrieKey>), StorageError> {
         assert!(self.prospective.is_empty(), "Finalize cannot be called with uncommitted changes.");
+        assert!(self.base.is_none(), "Finalize cannot be called on a fork.");
         let TrieUpdate { trie, committed, .. } = self;
         let mut state_changes = Vec::with_capacity(committed.len());
         let trie_changes =
@@ -316,4 +378,61 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn trie_fork() {
+        let tries = TestTriesBuilder::new().build();
+        let cause = StateChangeCause::TransactionProcessing { tx_hash: CryptoHash::default() };
+        let mut trie_update = tries.new_trie_update(ShardUId::single_shard(), Trie::EMPTY_ROOT);
+        trie_update.set(test_key(b"dog".to_vec()), b"puppy".to_vec());
+        trie_update.set(test_key(b"cat".to_vec()), b"kitten".to_vec());
+        trie_update.commit(cause.clone());
+
+        let mut forks = trie_update.fork(2);
+        // Forks see the changes committed before the fork, but not each other's changes.
+        forks[0].set(test_key(b"dog".to_vec()), b"dog".to_vec());
+        forks[0].commit(cause.clone());
+        forks[0].remove(test_key(b"cat".to_vec()));
+        forks[0].commit(cause.clone());
+        forks[1].set(test_key(b"dog2".to_vec()), b"puppy".to_vec());
+        forks[1].commit(cause.clone());
+        assert_eq!(forks[0].get(&test_key(b"dog".to_vec())), Ok(Some(b"dog".to_vec())));
+        assert_eq!(forks[0].get(&test_key(b"cat".to_vec())), Ok(None));
+        assert_eq!(forks[1].get(&test_key(b"dog".to_vec())), Ok(Some(b"puppy".to_vec())));
+        assert_eq!(forks[1].get(&test_key(b"cat".to_vec())), Ok(Some(b"kitten".to_vec())));
+        let values: Result<Vec<Vec<u8>>, _> =
+            forks[1].iter(&test_key(b"".to_vec()).to_vec()).unwrap().collect();
+        assert_eq!(
+            values.unwrap(),
+            vec![
+                test_key(b"cat".to_vec()).to_vec(),
+                test_key(b"dog".to_vec()).to_vec(),
+                test_key(b"dog2".to_vec()).to_vec(),
+            ]
+        );
+        assert_eq!(trie_update.get(&test_key(b"dog2".to_vec())), Ok(None));
+
+        // Forked changes are taken once, in the order of the commits.
+        let changes = forks[0].take_forked_changes();
+        assert_eq!(changes.len(), 2);
+        assert!(forks[0].take_forked_changes().is_empty());
+        trie_update.commit_forked_changes(changes);
+        trie_update.commit_forked_changes(forks[1].take_forked_changes());
+        assert_eq!(trie_update.get(&test_key(b"dog".to_vec())), Ok(Some(b"dog".to_vec())));
+        assert_eq!(trie_update.get(&test_key(b"cat".to_vec())), Ok(None));
+        assert_eq!(trie_update.get(&test_key(b"dog2".to_vec())), Ok(Some(b"puppy".to_vec())));
+
+        let (_, trie_changes, state_changes) = trie_update.finalize().unwrap();
+        let dog_changes = state_changes
+            .iter()
+            .find(|changes| changes.trie_key == test_key(b"dog".to_vec()))
+            .unwrap();
+        assert_eq!(dog_changes.changes.len(), 2);
+        let mut store_update = tries.store_update();
+        let new_root = tries.apply_all(&trie_changes, ShardUId::single_shard(), &mut store_update);
+        store_update.commit().unwrap();
+        let trie_update = tries.new_trie_update(ShardUId::single_shard(), new_root);
+        assert_eq!(trie_update.get(&test_key(b"dog".to_vec())), Ok(Some(b"dog".to_vec())));
+        assert_eq!(trie_update.get(&test_key(b"cat".to_vec())), Ok(None));
+    }
 }
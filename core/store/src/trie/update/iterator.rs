@@ -3,6 +3,7 @@ use std::ops::Bound;
 
 use crate::trie::update::*;
 use crate::StorageError;
+use near_primitives::types::RawStateChanges;
 
 use crate::trie::TrieIterator;
 
@@ -51,8 +52,8 @@ impl<'a> TrieUpdateIterator<'a> {
         };
         let range = (Bound::Included(prefix), end_bound);
 
-        let committed_iter = state_update.committed.range::<[u8], _>(range).map(
-            |(raw_key, changes_with_trie_key)| {
+        let committed_range = move |committed: &'a RawStateChanges| {
+            committed.range::<[u8], _>(range).map(|(raw_key, changes_with_trie_key)| {
                 let key = raw_key.as_slice();
                 let value = changes_with_trie_key
                     .changes
@@ -62,14 +63,24 @@ impl<'a> TrieUpdateIterator<'a> {
                     .data
                     .as_deref();
                 (key, value)
-            },
-        );
+            })
+        };
+        let committed_iter: Box<dyn Iterator<Item = _>> = match &state_update.base {
+            Some(base) => Box::new(MergeIter {
+                left: (Box::new(committed_range(&**base)) as Box<dyn Iterator<Item = _>>)
+                    .peekable(),
+                right: (Box::new(committed_range(&state_update.committed))
+                    as Box<dyn Iterator<Item = _>>)
+                    .peekable(),
+            }),
+            None => Box::new(committed_range(&state_update.committed)),
+        };
         let prospective_iter = state_update
             .prospective
             .range::<[u8], _>(range)
             .map(|(raw_key, key_value)| (raw_key.as_slice(), key_value.value.as_deref()));
         let overlay_iter = MergeIter {
-            left: (Box::new(committed_iter) as Box<dyn Iterator<Item = _>>).peekable(),
+            left: committed_iter.peekable(),
             right: (Box::new(prospective_iter) as Box<dyn Iterator<Item = _>>).peekable(),
         }
         .peekable();
@@ -69,6 +69,7 @@ pub fn add_blocks(
                     prev.header().height() + 1,
                     signer,
                 )
+                .unwrap()
                 .signature,
             ))],
             Ratio::from_integer(0),
@@ -81,7 +82,8 @@ pub fn add_blocks(
             next_bp_hash,
             block_merkle_tree.root(),
             None,
-        );
+        )
+        .unwrap();
         block_merkle_tree.insert(*block.hash());
         let _ = client.do_send(
             BlockResponse {
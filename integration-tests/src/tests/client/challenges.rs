@@ -42,7 +42,7 @@ fn test_block_with_challenges() {
             left_block_header: borsh::to_vec(&genesis.header()).unwrap(),
             right_block_header: borsh::to_vec(&genesis.header()).unwrap(),
         });
-        let challenge = Challenge::produce(challenge_body, &*signer);
+        let challenge = Challenge::produce(challenge_body, &*signer).unwrap();
         let challenges = vec![challenge];
         block.set_challenges(challenges.clone());
         let block_body_hash = block.compute_block_body_hash().unwrap();
@@ -117,7 +117,8 @@ fn test_verify_block_double_sign_challenge() {
         *b1.header().next_bp_hash(),
         block_merkle_tree.root(),
         None,
-    );
+    )
+    .unwrap();
     let epoch_id = b1.header().epoch_id().clone();
     let valid_challenge = Challenge::produce(
         ChallengeBody::BlockDoubleSign(BlockDoubleSign {
@@ -125,7 +126,8 @@ fn test_verify_block_double_sign_challenge() {
             right_block_header: borsh::to_vec(&b1.header()).unwrap(),
         }),
         &signer,
-    );
+    )
+    .unwrap();
     assert_eq!(
         &validate_challenge(
             env.clients[1].chain.epoch_manager.as_ref(),
@@ -144,7 +146,8 @@ fn test_verify_block_double_sign_challenge() {
             right_block_header: borsh::to_vec(&b1.header()).unwrap(),
         }),
         &signer,
-    );
+    )
+    .unwrap();
     assert!(validate_challenge(
         env.clients[1].chain.epoch_manager.as_ref(),
         env.clients[1].chain.runtime_adapter.as_ref(),
@@ -160,7 +163,8 @@ fn test_verify_block_double_sign_challenge() {
             right_block_header: borsh::to_vec(&b3.header()).unwrap(),
         }),
         &signer,
-    );
+    )
+    .unwrap();
     assert!(validate_challenge(
         env.clients[1].chain.epoch_manager.as_ref(),
         env.clients[1].chain.runtime_adapter.as_ref(),
@@ -320,7 +324,8 @@ fn challenge(
             merkle_proof: merkle_paths[shard_id].clone(),
         }),
         &*env.clients[0].validator_signer.as_ref().unwrap().clone(),
-    );
+    )
+    .unwrap();
     validate_challenge(
         env.clients[0].chain.epoch_manager.as_ref(),
         env.clients[0].chain.runtime_adapter.as_ref(),
@@ -430,7 +435,8 @@ fn test_verify_chunk_invalid_state_challenge() {
         *last_block.header().next_bp_hash(),
         block_merkle_tree.root(),
         None,
-    );
+    )
+    .unwrap();
 
     let challenge_body =
         client.chain.create_chunk_state_challenge(&last_block, &block, &block.chunks()[0]).unwrap();
@@ -460,7 +466,7 @@ fn test_verify_chunk_invalid_state_challenge() {
         // );
     }
     let challenge =
-        Challenge::produce(ChallengeBody::ChunkState(challenge_body), &validator_signer);
+        Challenge::produce(ChallengeBody::ChunkState(challenge_body), &validator_signer).unwrap();
     // Invalidate chunk state challenges because they are not supported yet.
     // TODO (#2445): Enable challenges when they are working correctly.
     assert_matches!(
@@ -212,10 +212,17 @@ fn test_storage_after_commit_of_cold_update() {
         let cold_store = &storage.get_cold_store().unwrap();
         let num_checks = check_iter(client_store, cold_store, col, &no_check_rules);
         // assert that this test actually checks something
-        // apart from StateChangesForSplitStates and StateHeaders, that are empty
+        // apart from StateChangesForSplitStates and StateHeaders, that are empty,
+        // the stateless validation columns, that are only written when
+        // stateless validation is enabled, and the receipt profiles and
+        // origins, that are only written when enabled in the config
         assert!(
             col == DBCol::StateChangesForSplitStates
                 || col == DBCol::StateHeaders
+                || col == DBCol::StateTransitionData
+                || col == DBCol::StateWitnesses
+                || col == DBCol::ReceiptProfiles
+                || col == DBCol::ReceiptOrigins
                 || num_checks > 0
         );
     }
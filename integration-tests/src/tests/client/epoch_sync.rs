@@ -2,8 +2,10 @@ use crate::nearcore_utils::{add_blocks, setup_configs_with_epoch_length};
 use crate::test_helpers::heavy_test;
 use actix::Actor;
 use actix_rt::System;
+use assert_matches::assert_matches;
 use futures::{future, FutureExt};
 use near_actix_test_utils::run_actix;
+use near_chain::near_chain_primitives::error::epoch_sync::EpochSyncInfoError;
 use near_chain::{BlockProcessingArtifact, ChainStoreAccess};
 use near_chain::{ChainGenesis, Provenance};
 use near_chain_configs::Genesis;
@@ -16,6 +18,7 @@ use near_o11y::testonly::{init_integration_logger, init_test_logger};
 use near_o11y::WithSpanContextExt;
 use near_primitives::epoch_manager::block_info::BlockInfo;
 use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
+use near_primitives::errors::epoch_sync::EpochSyncInfoError as EpochSyncInfoErr;
 use near_primitives::state_part::PartId;
 use near_primitives::state_sync::get_num_state_parts;
 use near_primitives::test_utils::create_test_signer;
@@ -310,6 +313,81 @@ fn test_epoch_sync_data_hash_from_epoch_sync_info() {
     }
 }
 
+/// Checks that `EpochSyncInfo` is validated before it is recorded.
+#[test]
+fn test_validate_epoch_sync_info() {
+    init_test_logger();
+
+    let epoch_length = 5;
+    let max_height = epoch_length * 3;
+
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut chain_genesis = ChainGenesis::test();
+    chain_genesis.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(chain_genesis)
+        .clients_count(2)
+        .real_epoch_managers(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .build();
+
+    for h in 1..max_height {
+        let block = env.clients[0].produce_block(h).unwrap().unwrap();
+        env.process_block(0, block, Provenance::PRODUCED);
+    }
+
+    let epoch_id = EpochId::default();
+    let epoch_sync_info =
+        env.clients[0].chain.chain_store().get_epoch_sync_info(&epoch_id).unwrap();
+    assert!(epoch_sync_info.all_block_hashes.len() > 4);
+
+    let mut invalid_epoch_sync_info = epoch_sync_info.clone();
+    invalid_epoch_sync_info.next_next_epoch_info = epoch_sync_info.epoch_info.clone();
+    assert_matches!(
+        env.clients[1].chain.validate_and_record_epoch_sync_info(&invalid_epoch_sync_info),
+        Err(EpochSyncInfoError::EpochSyncInfoErr(
+            EpochSyncInfoErr::InvalidEpochSyncDataHash { .. }
+        ))
+    );
+
+    let mut invalid_epoch_sync_info = epoch_sync_info.clone();
+    invalid_epoch_sync_info.all_block_hashes.swap(1, 2);
+    assert_matches!(
+        env.clients[1].chain.validate_and_record_epoch_sync_info(&invalid_epoch_sync_info),
+        Err(EpochSyncInfoError::EpochSyncInfoErr(EpochSyncInfoErr::InvalidHeaderChain { .. }))
+    );
+
+    let mut invalid_epoch_sync_info = epoch_sync_info.clone();
+    invalid_epoch_sync_info.epoch_info = epoch_sync_info.next_next_epoch_info.clone();
+    assert_matches!(
+        env.clients[1].chain.validate_and_record_epoch_sync_info(&invalid_epoch_sync_info),
+        Err(EpochSyncInfoError::EpochSyncInfoErr(EpochSyncInfoErr::InvalidEpochInfo { .. }))
+    );
+
+    // A header which isn't approved by the block producers of the next epoch
+    // doesn't prove that the epoch ended there.
+    let mut invalid_epoch_sync_info = epoch_sync_info.clone();
+    let mut next_epoch_first_header =
+        invalid_epoch_sync_info.headers.remove(&epoch_sync_info.next_epoch_first_hash).unwrap();
+    next_epoch_first_header.get_mut().inner_rest.approvals = vec![None];
+    next_epoch_first_header.resign(&create_test_signer("test0"));
+    invalid_epoch_sync_info.next_epoch_first_hash = *next_epoch_first_header.hash();
+    invalid_epoch_sync_info
+        .headers
+        .insert(*next_epoch_first_header.hash(), next_epoch_first_header);
+    assert_matches!(
+        env.clients[1].chain.validate_and_record_epoch_sync_info(&invalid_epoch_sync_info),
+        Err(EpochSyncInfoError::EpochSyncInfoErr(EpochSyncInfoErr::NotEnoughApprovals { .. }))
+    );
+    assert_eq!(env.clients[1].chain.header_head().unwrap().height, 0);
+
+    env.clients[1].chain.validate_and_record_epoch_sync_info(&epoch_sync_info).unwrap();
+    assert_eq!(
+        env.clients[1].chain.header_head().unwrap().last_block_hash,
+        *epoch_sync_info.get_epoch_last_hash().unwrap(),
+    );
+}
+
 /// This is an unreliable test that mocks/reimplements sync logic.
 /// After epoch sync is integrated into sync process we can write a better test.
 ///
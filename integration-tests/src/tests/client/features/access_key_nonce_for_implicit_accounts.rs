@@ -683,7 +683,9 @@ impl ChunkForwardingOptimizationTestData {
                 );
             }
             NetworkRequests::ChunkStateWitness(_, _) => {}
+            NetworkRequests::ChunkStateWitnessRequest(_, _) => {}
             NetworkRequests::ChunkEndorsement(_, _) => {}
+            NetworkRequests::ChunkEndorsementBatch(_, _) => {}
             _ => {
                 panic!("Unexpected network request: {:?}", requests);
             }
@@ -84,10 +84,12 @@ impl AdversarialBehaviorTestData {
             NetworkRequests::Challenge(_) => {
                 // challenges not enabled.
             }
-            NetworkRequests::ChunkStateWitness(_, _) => {
+            NetworkRequests::ChunkStateWitness(_, _)
+            | NetworkRequests::ChunkStateWitnessRequest(_, _) => {
                 // TODO(#10265).
             }
-            NetworkRequests::ChunkEndorsement(_, _) => {
+            NetworkRequests::ChunkEndorsement(_, _)
+            | NetworkRequests::ChunkEndorsementBatch(_, _) => {
                 // TODO(#10265).
             }
             _ => {
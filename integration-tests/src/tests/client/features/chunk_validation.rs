@@ -1,10 +1,25 @@
+use borsh::BorshDeserialize;
+use derive_enum_from_into::{EnumFrom, EnumTryInto};
+use near_async::futures::{AsyncComputationSpawner, InlineAsyncComputationSpawner};
+use near_async::test_loop::adhoc::{handle_adhoc_events, AdhocEvent, AdhocEventSender};
+use near_async::test_loop::futures::{drive_async_computations, TestLoopAsyncComputationEvent};
+use near_async::test_loop::{TestLoop, TestLoopBuilder};
+use near_async::time;
+use near_chain::near_chain_primitives::error::{ChunkWitnessValidationError, Error};
+use near_chain::validate::validate_challenge;
 use near_chain::{ChainGenesis, Provenance};
 use near_chain_configs::{Genesis, GenesisConfig, GenesisRecords};
 use near_client::test_utils::TestEnv;
 use near_crypto::{InMemorySigner, KeyType};
+use near_network::types::{NetworkRequests, PeerManagerMessageRequest};
 use near_o11y::testonly::init_integration_logger;
-use near_primitives::block::Tip;
+use near_primitives::block::{Block, Tip};
+use near_primitives::challenge::{Challenge, ChallengeBody, SlashedValidator};
+use near_primitives::chunk_validation::{
+    ChunkEndorsement, ChunkEndorsementPayload, ChunkStateWitness, WitnessTransitionsBase,
+};
 use near_primitives::shard_layout::ShardLayout;
+use near_primitives::sharding::{ShardChunkHeader, ShardChunkHeaderInner, ShardChunkHeaderV3};
 use near_primitives::state_record::StateRecord;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::transaction::SignedTransaction;
@@ -12,27 +27,54 @@ use near_primitives::types::AccountInfo;
 use near_primitives_core::account::{AccessKey, Account};
 use near_primitives_core::checked_feature;
 use near_primitives_core::hash::CryptoHash;
+use near_primitives_core::num_rational::Rational32;
 use near_primitives_core::types::AccountId;
-use near_primitives_core::version::PROTOCOL_VERSION;
+use near_primitives_core::version::{ProtocolFeature, PROTOCOL_VERSION};
+use near_store::DBCol;
+use nearcore::config::GenesisExt;
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 use std::collections::HashSet;
+use std::sync::Arc;
 
 const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
 
-#[test]
-// TODO(#9292): This does not pass yet because state witness production
-// needs to be implemented.
-fn test_chunk_validation_basic() {
+/// Number of validators in the test environment. All of them are block and
+/// chunk producers, and two of them validate each chunk.
+const NUM_VALIDATORS: usize = 8;
+
+/// Sets up an environment of `NUM_VALIDATORS` validators and one more
+/// account, tracking four shards. The chunk state witnesses are validated
+/// inline, so that the endorsements are ready as soon as the witnesses are
+/// delivered. Returns `None` if the ChunkValidation protocol feature is not
+/// enabled.
+fn setup_env() -> Option<(TestEnv, Vec<AccountId>)> {
+    setup_env_with_spawner(Arc::new(InlineAsyncComputationSpawner))
+}
+
+/// Like `setup_env`, but the clients spawn chunk state witness validation
+/// with the given spawner.
+fn setup_env_with_spawner(
+    async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
+) -> Option<(TestEnv, Vec<AccountId>)> {
+    setup_env_with_genesis_config(async_computation_spawner, |_| {})
+}
+
+/// Like `setup_env_with_spawner`, but lets the test adjust the genesis
+/// config first.
+fn setup_env_with_genesis_config(
+    async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
+    adjust_genesis_config: impl FnOnce(&mut GenesisConfig),
+) -> Option<(TestEnv, Vec<AccountId>)> {
     init_integration_logger();
 
     if !checked_feature!("stable", ChunkValidation, PROTOCOL_VERSION) {
         println!("Test not applicable without ChunkValidation enabled");
-        return;
+        return None;
     }
 
     let initial_balance = 100 * ONE_NEAR;
     let validator_stake = 1000000 * ONE_NEAR;
-    let num_accounts = 9;
+    let num_accounts = NUM_VALIDATORS + 1;
     let accounts = (0..num_accounts)
         .map(|i| format!("account{}", i).parse().unwrap())
         .collect::<Vec<AccountId>>();
@@ -48,7 +90,7 @@ fn test_chunk_validation_basic() {
         // for each chunk.
         validators: accounts
             .iter()
-            .take(8)
+            .take(NUM_VALIDATORS)
             .map(|account_id| AccountInfo {
                 account_id: account_id.clone(),
                 public_key: create_test_signer(account_id.as_str()).public_key(),
@@ -58,11 +100,11 @@ fn test_chunk_validation_basic() {
         // We don't care about epoch transitions in this test.
         epoch_length: 10000,
         // The genesis requires this, so set it to something arbitrary.
-        protocol_treasury_account: accounts[8].clone(),
+        protocol_treasury_account: accounts[NUM_VALIDATORS].clone(),
         // Simply make all validators block producers.
-        num_block_producer_seats: 8,
+        num_block_producer_seats: NUM_VALIDATORS as u64,
         // Make all validators produce chunks for all shards.
-        minimum_validators_per_shard: 8,
+        minimum_validators_per_shard: NUM_VALIDATORS as u64,
         // Even though not used for the most recent protocol version,
         // this must still have the same length as the number of shards,
         // or else the genesis fails validation.
@@ -76,7 +118,7 @@ fn test_chunk_validation_basic() {
     let mut records = Vec::new();
     for (i, account) in accounts.iter().enumerate() {
         // The staked amount must be consistent with validators from genesis.
-        let staked = if i < 8 { validator_stake } else { 0 };
+        let staked = if i < NUM_VALIDATORS { validator_stake } else { 0 };
         records.push(StateRecord::Account {
             account_id: account.clone(),
             account: Account::new(initial_balance, staked, CryptoHash::default(), 0),
@@ -89,47 +131,84 @@ fn test_chunk_validation_basic() {
         // The total supply must be correct to pass validation.
         genesis_config.total_supply += initial_balance + staked;
     }
+    adjust_genesis_config(&mut genesis_config);
     let genesis = Genesis::new(genesis_config, GenesisRecords(records)).unwrap();
     let chain_genesis = ChainGenesis::new(&genesis);
 
-    let mut env = TestEnv::builder(chain_genesis)
-        .clients(accounts.iter().take(8).cloned().collect())
+    let env = TestEnv::builder(chain_genesis)
+        .clients(accounts.iter().take(NUM_VALIDATORS).cloned().collect())
         .real_epoch_managers(&genesis.config)
         .nightshade_runtimes(&genesis)
+        .async_computation_spawner(async_computation_spawner)
         .build();
+    Some((env, accounts))
+}
 
-    for round in 0..10 {
-        let heads = env
-            .clients
-            .iter()
-            .map(|client| client.chain.head().unwrap().last_block_hash)
-            .collect::<HashSet<_>>();
-        assert_eq!(heads.len(), 1, "All clients should have the same head");
-        let tip = env.clients[0].chain.head().unwrap();
-
-        let sender_account = accounts[round % num_accounts].clone();
-        let receiver_account = accounts[(round + 1) % num_accounts].clone();
-        let signer = InMemorySigner::from_seed(
-            sender_account.clone(),
-            KeyType::ED25519,
-            sender_account.as_ref(),
-        );
-        let _ = env.clients[0].process_tx(
-            SignedTransaction::send_money(
-                round as u64,
-                sender_account,
-                receiver_account,
-                &signer,
-                ONE_NEAR,
-                tip.last_block_hash,
-            ),
-            false,
-            false,
+/// Submits a transfer transaction, produces the next block and applies it on
+/// all clients, which also makes the chunk producers produce the chunks of
+/// the next height and queue their state witnesses.
+fn produce_and_apply_block(env: &mut TestEnv, accounts: &[AccountId], round: usize) -> Block {
+    let heads = env
+        .clients
+        .iter()
+        .map(|client| client.chain.head().unwrap().last_block_hash)
+        .collect::<HashSet<_>>();
+    assert_eq!(heads.len(), 1, "All clients should have the same head");
+    let tip = env.clients[0].chain.head().unwrap();
+
+    let sender_account = accounts[round % accounts.len()].clone();
+    let receiver_account = accounts[(round + 1) % accounts.len()].clone();
+    let signer = InMemorySigner::from_seed(
+        sender_account.clone(),
+        KeyType::ED25519,
+        sender_account.as_ref(),
+    );
+    let _ = env.clients[0].process_tx(
+        SignedTransaction::send_money(
+            round as u64,
+            sender_account,
+            receiver_account,
+            &signer,
+            ONE_NEAR,
+            tip.last_block_hash,
+        ),
+        false,
+        false,
+    );
+
+    let block_producer = get_block_producer(env, &tip, 1);
+    println!("Producing block at height {} by {}", tip.height + 1, block_producer);
+    let block = env.produce_block_by(&block_producer, tip.height + 1).unwrap();
+
+    // Apply the block.
+    for i in 0..env.clients.len() {
+        println!(
+            "  Applying block at height {} at {}",
+            block.header().height(),
+            env.get_client_id(i)
         );
+        let blocks_processed =
+            env.clients[i].process_block_test(block.clone().into(), Provenance::NONE).unwrap();
+        assert_eq!(blocks_processed, vec![*block.hash()]);
+    }
 
-        let block_producer = get_block_producer(&env, &tip, 1);
-        println!("Producing block at height {} by {}", tip.height + 1, block_producer);
-        let block = env.client(&block_producer).produce_block(tip.height + 1).unwrap().unwrap();
+    env.process_partial_encoded_chunks();
+    for j in 0..env.clients.len() {
+        env.process_shards_manager_responses_and_finish_processing_blocks(j);
+    }
+    block
+}
+
+#[test]
+// TODO(#9292): This does not pass yet because state witness production
+// needs to be implemented.
+fn test_chunk_validation_basic() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    for round in 0..10 {
+        let block = produce_and_apply_block(&mut env, &accounts, round);
         if round > 1 {
             for i in 0..4 {
                 let chunks = block.chunks();
@@ -137,32 +216,630 @@ fn test_chunk_validation_basic() {
                 assert_eq!(chunk.height_created(), chunk.height_included());
             }
         }
+        env.propagate_chunk_state_witnesses();
+    }
 
-        // Apply the block.
-        for i in 0..env.clients.len() {
-            println!(
-                "  Applying block at height {} at {}",
-                block.header().height(),
-                env.get_client_id(i)
+    // Check that we've received at least some chunk approvals.
+    // TODO(#10265): We need to assert exactly how many approvals (or total
+    // stake) we have.
+    let approvals = env.get_all_chunk_endorsements();
+    assert!(!approvals.is_empty());
+}
+
+#[test]
+fn test_chunk_validation_dropped_witnesses() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    for round in 0..5 {
+        produce_and_apply_block(&mut env, &accounts, round);
+        env.drop_chunk_state_witnesses();
+    }
+
+    assert!(env.get_all_chunk_endorsements().is_empty());
+}
+
+#[test]
+fn test_chunk_validation_invalid_witness() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    for round in 0..5 {
+        produce_and_apply_block(&mut env, &accounts, round);
+        let results = env.propagate_chunk_state_witnesses_with(|_, mut witness| {
+            witness.applied_receipts_hash = CryptoHash::hash_bytes(b"invalid");
+            Some(witness)
+        });
+        for (account_id, result) in results {
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::InvalidChunkStateWitness(
+                        ChunkWitnessValidationError::ReceiptsHashMismatch { .. }
+                    ))
+                ),
+                "{account_id} accepted an invalid witness: {result:?}"
             );
-            let blocks_processed =
-                env.clients[i].process_block_test(block.clone().into(), Provenance::NONE).unwrap();
-            assert_eq!(blocks_processed, vec![*block.hash()]);
         }
+    }
+
+    assert!(env.get_all_chunk_endorsements().is_empty());
+}
 
-        env.process_partial_encoded_chunks();
-        for j in 0..env.clients.len() {
-            env.process_shards_manager_responses_and_finish_processing_blocks(j);
+#[test]
+fn test_chunk_validation_misassigned_validator() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    for round in 0..5 {
+        produce_and_apply_block(&mut env, &accounts, round);
+        // Deliver each witness to a validator which is not assigned to
+        // validate the chunk instead of the assigned ones.
+        let mut witnesses = Vec::new();
+        env.propagate_chunk_state_witnesses_with(|_, witness| {
+            witnesses.push(witness);
+            None
+        });
+        for witness in witnesses {
+            let chunk_header = &witness.chunk_header;
+            let epoch_manager = &env.clients[0].epoch_manager;
+            let epoch_id =
+                epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash()).unwrap();
+            let chunk_validators = epoch_manager
+                .get_chunk_validators(
+                    &epoch_id,
+                    chunk_header.shard_id(),
+                    chunk_header.height_created(),
+                )
+                .unwrap();
+            let misassigned = accounts
+                .iter()
+                .take(NUM_VALIDATORS)
+                .find(|account_id| !chunk_validators.contains_key(*account_id))
+                .unwrap()
+                .clone();
+            let result = env.client(&misassigned).process_chunk_state_witness(witness);
+            assert!(matches!(result, Err(Error::NotAChunkValidator)), "{result:?}");
         }
+    }
+}
+
+#[test]
+fn test_chunk_validation_old_witnesses() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    produce_and_apply_block(&mut env, &accounts, 0);
+    let mut witnesses = Vec::new();
+    env.propagate_chunk_state_witnesses_with(|account_id, witness| {
+        witnesses.push((account_id.clone(), witness));
+        None
+    });
+    let horizon = env.clients[0].config.chunk_witness_height_horizon;
+    for round in 1..horizon as usize + 3 {
+        produce_and_apply_block(&mut env, &accounts, round);
+        env.drop_chunk_state_witnesses();
+    }
+
+    // The head has moved past the horizon, so the witnesses are rejected.
+    assert!(!witnesses.is_empty());
+    for (account_id, witness) in witnesses {
+        let result = env.client(&account_id).process_chunk_state_witness(witness);
+        assert!(
+            matches!(
+                result,
+                Err(Error::InvalidChunkStateWitness(
+                    ChunkWitnessValidationError::HeightOutsideHorizon { .. }
+                ))
+            ),
+            "{account_id} accepted an old witness: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_chunk_validation_incremental_witness_missing_base() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    produce_and_apply_block(&mut env, &accounts, 0);
+    let mut witnesses = Vec::new();
+    env.propagate_chunk_state_witnesses_with(|account_id, witness| {
+        witnesses.push((account_id.clone(), witness));
+        None
+    });
+
+    // The chunk validators never validated the transitions the witnesses
+    // build upon, so they request the full witnesses instead.
+    assert!(!witnesses.is_empty());
+    for (account_id, witness) in witnesses {
+        let chunk_hash = witness.chunk_header.chunk_hash();
+        let base = WitnessTransitionsBase {
+            last_block_hash: witness.main_state_transition.block_hash,
+            post_state_root: witness.main_state_transition.post_state_root,
+        };
+        let witness = ChunkStateWitness { transitions_base: Some(base), ..witness };
+        env.client(&account_id).process_chunk_state_witness(witness).unwrap();
+        let idx = accounts.iter().position(|account| account == &account_id).unwrap();
+        let requests = env.network_adapters[idx].requests.read().unwrap();
+        assert!(
+            requests.iter().any(|request| matches!(
+                request,
+                PeerManagerMessageRequest::NetworkRequests(
+                    NetworkRequests::ChunkStateWitnessRequest(_, requested)
+                ) if requested == &chunk_hash
+            )),
+            "{account_id} didn't request the full witness of {chunk_hash:?}"
+        );
+    }
+    assert!(env.get_all_chunk_endorsements().is_empty());
+}
+
+#[test]
+fn test_chunk_validation_missing_endorsements() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    let mut dropped = Vec::new();
+    for round in 0..5 {
+        produce_and_apply_block(&mut env, &accounts, round);
         env.propagate_chunk_state_witnesses();
+        // Drop the endorsements of the first validator, deliver the rest.
+        env.propagate_chunk_endorsements_with(|block_producer, endorsement| {
+            if endorsement.account_id != accounts[0] {
+                return true;
+            }
+            dropped.push((block_producer.clone(), endorsement.inner.chunk_hash.clone()));
+            false
+        });
     }
 
-    // Wait a bit and check that we've received at least some chunk approvals.
-    // TODO(#10265): We need to make this not time-based, and we need to assert
-    // exactly how many approvals (or total stake) we have.
-    std::thread::sleep(std::time::Duration::from_secs(1));
-    let approvals = env.get_all_chunk_endorsements();
-    assert!(!approvals.is_empty());
+    for (block_producer, chunk_hash) in dropped {
+        let status = env.client(&block_producer).chunk_endorsement_status(&chunk_hash).unwrap();
+        assert!(status.missing.contains(&accounts[0]), "{status:?}");
+        assert!(!status.endorsed_by.contains(&accounts[0]), "{status:?}");
+    }
+}
+
+#[test]
+fn test_chunk_validation_double_signed_endorsements() {
+    // Not applicable without ChunkEndorsementSlashing enabled.
+    if !checked_feature!("stable", ChunkEndorsementSlashing, PROTOCOL_VERSION) {
+        return;
+    }
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    produce_and_apply_block(&mut env, &accounts, 0);
+    env.propagate_chunk_state_witnesses();
+    let mut delivered = Vec::new();
+    env.propagate_chunk_endorsements_with(|block_producer, endorsement| {
+        delivered.push((block_producer.clone(), endorsement.clone()));
+        true
+    });
+    let (block_producer, endorsement) = delivered.into_iter().next().unwrap();
+    let validator = endorsement.account_id.clone();
+
+    // The validator also endorses another chunk of the same shard and height.
+    let client = env.client(&block_producer);
+    let chunk_header =
+        client.chain.get_chunk(&endorsement.inner.chunk_hash).unwrap().cloned_header();
+    let ShardChunkHeaderInner::V2(mut inner) = chunk_header.clone().take_inner() else {
+        panic!("Unexpected chunk header inner version");
+    };
+    inner.tx_root = CryptoHash::hash_bytes(b"other chunk");
+    let other_chunk_header = ShardChunkHeader::V3(
+        ShardChunkHeaderV3::from_inner(
+            ShardChunkHeaderInner::V2(inner),
+            &create_test_signer(accounts[0].as_str()),
+        )
+        .unwrap(),
+    );
+    let epoch_id =
+        client.epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash()).unwrap();
+    let payload = ChunkEndorsementPayload::new(
+        other_chunk_header.chunk_hash(),
+        epoch_id,
+        other_chunk_header.height_created(),
+        PROTOCOL_VERSION,
+    );
+    let other_endorsement = ChunkEndorsement {
+        account_id: validator.clone(),
+        signature: create_test_signer(validator.as_str()).sign_chunk_endorsement(&payload).unwrap(),
+        inner: payload.inner(),
+    };
+    client
+        .chunk_endorsement_tracker
+        .process_chunk_endorsement(&other_chunk_header, other_endorsement)
+        .unwrap();
+
+    // The block producer detects the double sign and makes a valid challenge of it.
+    let double_signs = client.chunk_endorsement_tracker.take_double_signs();
+    assert_eq!(double_signs.len(), 1);
+    let challenge = Challenge::produce(
+        ChallengeBody::ChunkEndorsementDoubleSign(double_signs[0].clone()),
+        &**client.validator_signer.as_ref().unwrap(),
+    )
+    .unwrap();
+    let head = client.chain.head().unwrap();
+    let (_, slashed) = validate_challenge(
+        client.epoch_manager.as_ref(),
+        client.runtime_adapter.as_ref(),
+        &head.epoch_id,
+        &head.last_block_hash,
+        &challenge,
+    )
+    .unwrap();
+    assert_eq!(slashed, vec![validator.clone()]);
+
+    // The challenge is included in the next block, and the validator is
+    // slashed by the block after.
+    for client in env.clients.iter_mut() {
+        client.process_challenge(challenge.clone()).unwrap();
+    }
+    let block = produce_and_apply_block(&mut env, &accounts, 1);
+    assert_eq!(block.challenges(), &vec![challenge]);
+    env.propagate_chunk_state_witnesses();
+    env.propagate_chunk_endorsements_with(|_, _| true);
+    let block = produce_and_apply_block(&mut env, &accounts, 2);
+    assert!(block.challenges().is_empty());
+    assert_eq!(
+        block.header().challenges_result(),
+        &vec![SlashedValidator::new(validator.clone(), true)]
+    );
+    let block_info = env.clients[0].epoch_manager.get_block_info(block.hash()).unwrap();
+    assert!(block_info.slashed().contains_key(&validator));
+}
+
+#[test]
+fn test_chunk_validation_single_validator() {
+    init_integration_logger();
+    if !checked_feature!("stable", ChunkValidation, PROTOCOL_VERSION) {
+        println!("Test not applicable without ChunkValidation enabled");
+        return;
+    }
+
+    // The only validator validates all chunks, which it also produces.
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(ChainGenesis::new(&genesis))
+        .real_epoch_managers(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .build();
+    for height in 1..10 {
+        env.produce_block(0, height);
+        // The chunks are endorsed without sending witnesses to anyone.
+        assert!(env.propagate_chunk_state_witnesses_with(|_, witness| Some(witness)).is_empty());
+    }
+
+    let head = env.clients[0].chain.head().unwrap();
+    let block = env.clients[0].chain.get_block(&head.last_block_hash).unwrap();
+    for chunk_header in block.chunks().iter() {
+        let status = env.clients[0].chunk_endorsement_status(&chunk_header.chunk_hash()).unwrap();
+        assert!(status.has_quorum, "{status:?}");
+    }
+    assert!(env.get_all_chunk_endorsements().is_empty());
+}
+
+/// Blocks missing locally, e.g. the ones before the sync hash after state
+/// sync, are skipped by the chunk validation status.
+#[test]
+fn test_chunk_validation_status_missing_block() {
+    init_integration_logger();
+    if !checked_feature!("stable", ChunkValidation, PROTOCOL_VERSION) {
+        println!("Test not applicable without ChunkValidation enabled");
+        return;
+    }
+
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(ChainGenesis::new(&genesis))
+        .real_epoch_managers(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .build();
+    for height in 1..10 {
+        env.produce_block(0, height);
+    }
+
+    let head = env.clients[0].chain.head().unwrap();
+    let missing_block = env.clients[0].chain.get_block(&head.prev_block_hash).unwrap();
+    let mut store_update = env.clients[0].chain.chain_store().store().store_update();
+    store_update.delete(DBCol::Block, missing_block.hash().as_ref());
+    store_update.commit().unwrap();
+    // Restart the client so that the deleted block isn't served from cache.
+    env.restart(0);
+
+    let status = env.clients[0].chunk_validation_status().unwrap();
+    let chunk_hashes: HashSet<_> =
+        status.recent_chunk_endorsements.iter().map(|view| view.chunk_hash.clone()).collect();
+    assert!(!chunk_hashes.is_empty());
+    for chunk_header in missing_block.chunks().iter() {
+        assert!(!chunk_hashes.contains(&chunk_header.chunk_hash()));
+    }
+}
+
+/// Epoch length of the protocol upgrade tests. Short, so that the upgrade
+/// happens after a few blocks.
+const UPGRADE_EPOCH_LENGTH: u64 = 10;
+
+/// Sets up the environment of `setup_env`, but starting on the protocol
+/// version right before ChunkValidation. The first `num_old_validators`
+/// validators keep voting for that version, while the rest vote for the
+/// latest one.
+fn setup_upgrade_env(num_old_validators: usize) -> Option<(TestEnv, Vec<AccountId>)> {
+    let genesis_protocol_version = ProtocolFeature::ChunkValidation.protocol_version() - 1;
+    let (mut env, accounts) =
+        setup_env_with_genesis_config(Arc::new(InlineAsyncComputationSpawner), |config| {
+            config.protocol_version = genesis_protocol_version;
+            config.epoch_length = UPGRADE_EPOCH_LENGTH;
+            // Validators only vote in epochs in which they produce a block,
+            // and with short epochs not all of them get to. Require more than
+            // half of the stake instead of the default 80% for the upgrade.
+            config.protocol_upgrade_stake_threshold = Rational32::new(1, 2);
+        })?;
+    for account_id in accounts.iter().take(num_old_validators) {
+        env.set_protocol_version_override(account_id, Some(genesis_protocol_version));
+    }
+    Some((env, accounts))
+}
+
+/// Whether chunk validation is enabled in the epoch of the block after
+/// `prev_block_hash`.
+fn is_chunk_validation_enabled(env: &TestEnv, prev_block_hash: &CryptoHash) -> bool {
+    let epoch_manager = &env.clients[0].epoch_manager;
+    let epoch_id = epoch_manager.get_epoch_id_from_prev_block(prev_block_hash).unwrap();
+    let protocol_version = epoch_manager.get_epoch_protocol_version(&epoch_id).unwrap();
+    checked_feature!("stable", ChunkValidation, protocol_version)
+}
+
+/// Delivers the chunk state witnesses sent so far, checking that they are all
+/// for chunks of epochs with chunk validation enabled and that the chunk
+/// validators accept them. Returns the delivered witnesses with their targets.
+fn deliver_upgrade_witnesses(env: &mut TestEnv) -> Vec<(AccountId, ChunkStateWitness)> {
+    let mut witnesses = Vec::new();
+    let results = env.propagate_chunk_state_witnesses_with(|account_id, witness| {
+        witnesses.push((account_id.clone(), witness.clone()));
+        Some(witness)
+    });
+    for (account_id, result) in results {
+        assert!(result.is_ok(), "{account_id} failed to process chunk state witness: {result:?}");
+    }
+    for (_, witness) in &witnesses {
+        assert!(
+            is_chunk_validation_enabled(env, witness.chunk_header.prev_block_hash()),
+            "state witness sent for chunk {:?} before chunk validation is enabled",
+            witness.chunk_header.chunk_hash()
+        );
+    }
+    witnesses
+}
+
+#[test]
+fn test_chunk_validation_protocol_upgrade() {
+    let Some((mut env, accounts)) = setup_upgrade_env(1) else {
+        return;
+    };
+    let old_validator = accounts[0].clone();
+
+    let mut witnesses = Vec::new();
+    let mut enabled_at = None;
+    for round in 0..10 * UPGRADE_EPOCH_LENGTH as usize {
+        let block = produce_and_apply_block(&mut env, &accounts, round);
+        let enabled = is_chunk_validation_enabled(&env, block.hash());
+        let new_witnesses = deliver_upgrade_witnesses(&mut env);
+        if !enabled {
+            assert!(new_witnesses.is_empty());
+            continue;
+        }
+        witnesses.extend(new_witnesses);
+        // Keep going for an epoch after the upgrade.
+        let enabled_at = *enabled_at.get_or_insert(round);
+        if round >= enabled_at + UPGRADE_EPOCH_LENGTH as usize {
+            break;
+        }
+    }
+    assert!(enabled_at.is_some(), "chunk validation was never enabled");
+    assert!(!witnesses.is_empty());
+
+    // The validator voting for the old protocol version interoperates with
+    // the others: it produces witnesses which they accept, and validates
+    // theirs.
+    let epoch_manager = &env.clients[0].epoch_manager;
+    assert!(witnesses.iter().any(|(account_id, witness)| {
+        let chunk_header = &witness.chunk_header;
+        let epoch_id =
+            epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash()).unwrap();
+        let chunk_producer = epoch_manager
+            .get_chunk_producer(&epoch_id, chunk_header.height_created(), chunk_header.shard_id())
+            .unwrap();
+        account_id == &old_validator || chunk_producer == old_validator
+    }));
+    assert!(!env.get_all_chunk_endorsements().is_empty());
+}
+
+#[test]
+fn test_chunk_validation_protocol_upgrade_not_voted() {
+    // Half of the stake keeps voting for the old protocol version, which is
+    // not enough for the upgrade.
+    let Some((mut env, accounts)) = setup_upgrade_env(NUM_VALIDATORS / 2) else {
+        return;
+    };
+
+    for round in 0..4 * UPGRADE_EPOCH_LENGTH as usize {
+        let block = produce_and_apply_block(&mut env, &accounts, round);
+        assert!(!is_chunk_validation_enabled(&env, block.hash()));
+        assert!(deliver_upgrade_witnesses(&mut env).is_empty());
+    }
+    assert!(env.get_all_chunk_endorsements().is_empty());
+}
+
+/// Events of the TestLoop which drives chunk validation in a `TestEnv`.
+#[derive(Debug, EnumTryInto, EnumFrom)]
+enum TestEvent {
+    Adhoc(AdhocEvent<TestLoopData>),
+    AsyncComputation(TestLoopAsyncComputationEvent),
+}
+
+#[derive(derive_more::AsMut, derive_more::AsRef)]
+struct TestLoopData {
+    dummy: (), // needed for the drive_async_computations handler
+    env: TestEnv,
+}
+
+type ChunkValidationTestLoop = TestLoop<TestLoopData, TestEvent>;
+
+const NETWORK_DELAY: time::Duration = time::Duration::milliseconds(10);
+const VALIDATION_DELAY: time::Duration = time::Duration::milliseconds(50);
+
+/// Sets up the same environment as `setup_env`, but with chunk state witness
+/// validation run by a TestLoop, so that the tests don't depend on how long
+/// the validation takes in real time.
+fn setup_test_loop() -> Option<(ChunkValidationTestLoop, Vec<AccountId>)> {
+    let builder = TestLoopBuilder::<TestEvent>::new();
+    let spawner = builder.async_computation_spawner(|_| VALIDATION_DELAY);
+    let (env, accounts) = setup_env_with_spawner(Arc::new(spawner))?;
+    let mut test = builder.build(TestLoopData { dummy: (), env });
+    test.register_handler(handle_adhoc_events());
+    test.register_handler(drive_async_computations().widen());
+    Some((test, accounts))
+}
+
+/// Takes the chunk state witnesses sent by the clients so far, and schedules
+/// their delivery to the chunk validators in the test loop, each after the
+/// network delay returned for its target. Returns the target of each
+/// delivered witness.
+fn deliver_chunk_state_witnesses(
+    test: &mut ChunkValidationTestLoop,
+    mut network_delay: impl FnMut(&AccountId) -> time::Duration,
+) -> Vec<AccountId> {
+    let sender = test.sender();
+    let mut delivered = Vec::new();
+    test.data.env.propagate_chunk_state_witnesses_with(|account_id, witness| {
+        delivered.push(account_id.clone());
+        let target = account_id.clone();
+        sender.schedule_adhoc_event(
+            "deliver chunk state witness",
+            move |data: &mut TestLoopData| {
+                data.env.client(&target).process_chunk_state_witness(witness).unwrap();
+            },
+            network_delay(account_id),
+        );
+        None
+    });
+    delivered
+}
+
+#[test]
+fn test_chunk_validation_test_loop() {
+    let Some((mut test, accounts)) = setup_test_loop() else {
+        return;
+    };
+
+    let mut endorsed_chunks = HashSet::new();
+    for round in 0..5 {
+        let block = produce_and_apply_block(&mut test.data.env, &accounts, round);
+        // The chunks endorsed in the previous round must be included in the
+        // block of this round.
+        let included =
+            block.chunks().iter().map(|chunk| chunk.chunk_hash()).collect::<HashSet<_>>();
+        assert!(endorsed_chunks.is_subset(&included), "{endorsed_chunks:?} {included:?}");
+
+        let delivered = deliver_chunk_state_witnesses(&mut test, |_| NETWORK_DELAY);
+        // No chunk is endorsed before the validation completes.
+        test.run_for(NETWORK_DELAY);
+        assert!(test.data.env.get_all_chunk_endorsements().is_empty());
+        test.run_for(VALIDATION_DELAY);
+        let endorsements = test.data.env.propagate_chunk_endorsements_with(|_, _| true);
+        assert_eq!(endorsements.len(), delivered.len());
+        endorsed_chunks = endorsements
+            .into_iter()
+            .map(|endorsement| endorsement.inner.chunk_hash.clone())
+            .collect();
+    }
+}
+
+#[test]
+fn test_chunk_validation_test_loop_delayed_witnesses() {
+    let Some((mut test, accounts)) = setup_test_loop() else {
+        return;
+    };
+    let slow_validator = accounts[0].clone();
+    let slow_network_delay = NETWORK_DELAY * 10;
+
+    for round in 0..5 {
+        produce_and_apply_block(&mut test.data.env, &accounts, round);
+        // Witnesses to the slow validator arrive only after the other
+        // validators have validated their witnesses.
+        let delivered = deliver_chunk_state_witnesses(&mut test, |account_id| {
+            if account_id == &slow_validator {
+                slow_network_delay
+            } else {
+                NETWORK_DELAY
+            }
+        });
+        let num_slow = delivered.iter().filter(|account_id| *account_id == &slow_validator).count();
+
+        test.run_for(NETWORK_DELAY + VALIDATION_DELAY);
+        let endorsements = test.data.env.propagate_chunk_endorsements_with(|_, _| true);
+        assert_eq!(endorsements.len(), delivered.len() - num_slow);
+        assert!(endorsements.iter().all(|endorsement| endorsement.account_id != slow_validator));
+
+        test.run_for(slow_network_delay);
+        let endorsements = test.data.env.take_chunk_endorsements();
+        assert_eq!(endorsements.len(), num_slow);
+        for (block_producer, endorsement) in endorsements {
+            assert_eq!(endorsement.account_id, slow_validator);
+            let chunk_hash = endorsement.inner.chunk_hash.clone();
+            let client = test.data.env.client(&block_producer);
+            client.process_chunk_endorsement(endorsement).unwrap();
+            // With the late endorsement, every chunk validator has endorsed
+            // the chunk.
+            let status = client.chunk_endorsement_status(&chunk_hash).unwrap();
+            assert!(status.missing.is_empty(), "{status:?}");
+        }
+    }
+}
+
+/// Mutates the bytes of a valid serialized state witness and feeds the result
+/// through witness validation. The witness comes from untrusted peers, so
+/// both deserialization and validation must fail with an error rather than
+/// panic on any input.
+#[test]
+fn fuzz_chunk_state_witness_validation() {
+    let Some((mut env, accounts)) = setup_env() else {
+        return;
+    };
+
+    let mut witnesses = Vec::new();
+    for round in 0..3 {
+        produce_and_apply_block(&mut env, &accounts, round);
+        env.propagate_chunk_state_witnesses_with(|account_id, witness| {
+            witnesses.push((account_id.clone(), witness));
+            None
+        });
+    }
+    let (validator, witness) = witnesses.pop().unwrap();
+    let client = env.client(&validator);
+    client.run_chunk_state_witness_validation(witness.clone()).unwrap();
+    let serialized = borsh::to_vec(&witness).unwrap();
+
+    let client = std::panic::AssertUnwindSafe(client);
+    bolero::check!().with_iterations(100).with_type().for_each(|mutations: &Vec<(u32, u8)>| {
+        let mut bytes = serialized.clone();
+        for (offset, mask) in mutations {
+            let len = bytes.len();
+            bytes[*offset as usize % len] ^= mask;
+        }
+        let Ok(witness) = ChunkStateWitness::try_from_slice(&bytes) else {
+            return;
+        };
+        let _ = client.run_chunk_state_witness_validation(witness);
+    });
 }
 
 // Returns the block producer for the height of head + height_offset.
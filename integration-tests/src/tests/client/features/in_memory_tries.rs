@@ -378,13 +378,5 @@ fn run_chain_for_some_blocks_while_sending_money_around(
 /// Returns the number of memtrie roots for the given client and shard, or
 /// None if that shard does not load memtries.
 fn num_memtrie_roots(env: &TestEnv, client_id: usize, shard: ShardUId) -> Option<usize> {
-    Some(
-        env.clients[client_id]
-            .runtime_adapter
-            .get_tries()
-            .get_mem_tries(shard)?
-            .read()
-            .unwrap()
-            .num_roots(),
-    )
+    Some(env.clients[client_id].runtime_adapter.get_tries().get_mem_trie_stats(shard)?.roots.len())
 }
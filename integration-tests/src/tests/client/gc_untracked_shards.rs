@@ -0,0 +1,142 @@
+//! Tests of the garbage collection of the state of the shards a node no longer tracks, enabled by
+//! `gc.gc_untracked_shards`.
+use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
+use near_chain_configs::{Genesis, DEFAULT_GC_NUM_EPOCHS_TO_KEEP};
+use near_client::test_utils::TestEnv;
+use near_epoch_manager::shard_tracker::TrackedConfig;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{AccountId, BlockHeight, EpochHeight, NumBlocks};
+use near_store::flat::{store_helper, FlatStorageStatus};
+use near_store::test_utils::create_test_store;
+use near_store::{DBCol, Store};
+use nearcore::config::GenesisExt;
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+const EPOCH_LENGTH: NumBlocks = 5;
+
+/// The node tracking shards by schedule tracks shard 1 in the epochs with a lower height.
+const LAST_EPOCH_HEIGHT_TRACKING_SHARD_1: EpochHeight = 2;
+
+/// Leaves the epochs in which the node tracks shard 1 well out of the garbage collection window.
+const MAX_HEIGHT: BlockHeight =
+    EPOCH_LENGTH * (LAST_EPOCH_HEIGHT_TRACKING_SHARD_1 + DEFAULT_GC_NUM_EPOCHS_TO_KEEP + 5);
+
+const SHARD_0: ShardUId = ShardUId { version: 1, shard_id: 0 };
+const SHARD_1: ShardUId = ShardUId { version: 1, shard_id: 1 };
+
+/// Client 0 is the only validator and tracks shard 0 by its config, but is assigned both shards.
+/// Client 1 isn't a validator and tracks both shards in the first epochs, then only shard 0.
+fn setup_env(genesis: &Genesis, stores: Vec<Store>) -> TestEnv {
+    let schedule =
+        (0..100)
+            .map(|epoch_height| {
+                if epoch_height <= LAST_EPOCH_HEIGHT_TRACKING_SHARD_1 {
+                    vec![0, 1]
+                } else {
+                    vec![0]
+                }
+            })
+            .collect();
+    let mut env = TestEnv::builder(ChainGenesis::new(genesis))
+        .clients(vec!["test0".parse().unwrap(), "test1".parse().unwrap()])
+        .stores(stores)
+        .real_epoch_managers(&genesis.config)
+        .tracked_configs(vec![
+            TrackedConfig::Schedule(vec![vec![0]]),
+            TrackedConfig::Schedule(schedule),
+        ])
+        .nightshade_runtimes(genesis)
+        .build();
+    for client in &mut env.clients {
+        client.config.gc.gc_untracked_shards = true;
+    }
+    env
+}
+
+/// Returns whether the client has the trie state and the flat state of the shard.
+fn has_state(env: &TestEnv, idx: usize, shard_uid: ShardUId) -> bool {
+    let store = env.clients[idx].chain.chain_store().store();
+    let has_trie_state = store.iter_prefix(DBCol::State, &shard_uid.to_bytes()).next().is_some();
+    let has_flat_state = store_helper::get_flat_storage_status(store, shard_uid).unwrap()
+        != FlatStorageStatus::Empty;
+    assert_eq!(has_trie_state, has_flat_state, "client {idx} shard {shard_uid}");
+    has_trie_state
+}
+
+/// Returns the height of the epoch of the first block left after garbage collection.
+fn tail_epoch_height(env: &TestEnv, idx: usize) -> EpochHeight {
+    let client = &env.clients[idx];
+    let tail = client.chain.chain_store().tail().unwrap();
+    let block_hash = client.chain.get_block_hash_by_height(tail + 1).unwrap();
+    let epoch_id = client.epoch_manager.get_epoch_id(&block_hash).unwrap();
+    client.epoch_manager.get_epoch_info(&epoch_id).unwrap().epoch_height()
+}
+
+fn head_epoch_height(env: &TestEnv, idx: usize) -> EpochHeight {
+    let client = &env.clients[idx];
+    let epoch_id = client.chain.head().unwrap().epoch_id;
+    client.epoch_manager.get_epoch_info(&epoch_id).unwrap().epoch_height()
+}
+
+/// Produces the block at `height` with client 0 and processes it on both clients.
+fn produce_block(env: &mut TestEnv, height: BlockHeight) {
+    let block = env.clients[0].produce_block(height).unwrap().unwrap();
+    for idx in 0..env.clients.len() {
+        env.clients[idx].process_block_test(block.clone().into(), Provenance::NONE).unwrap();
+    }
+    env.process_partial_encoded_chunks();
+    for idx in 0..env.clients.len() {
+        env.process_shards_manager_responses_and_finish_processing_blocks(idx);
+    }
+}
+
+/// Checks that the state of shard 1 is kept by client 1 while an epoch in which it tracks the
+/// shard is in the garbage collection window, and removed once the last one leaves it, also if the
+/// client is restarted in the meantime, so that the flat storage of the shard isn't loaded. Client
+/// 0 keeps the state of both shards, as it's assigned to them.
+#[test]
+fn test_gc_untracked_shards() {
+    init_test_logger();
+    let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+    let mut genesis = Genesis::test_sharded_new_version(accounts, 1, vec![1, 1]);
+    genesis.config.epoch_length = EPOCH_LENGTH;
+    let stores = vec![create_test_store(), create_test_store()];
+    let mut env = setup_env(&genesis, stores.clone());
+
+    let mut height = 1;
+    let mut restarted = false;
+    while has_state(&env, 1, SHARD_1) {
+        assert!(height < MAX_HEIGHT, "the state of shard 1 wasn't removed");
+        // Restart once the node doesn't track shard 1 anymore.
+        if !restarted && head_epoch_height(&env, 1) > LAST_EPOCH_HEIGHT_TRACKING_SHARD_1 + 1 {
+            drop(env);
+            env = setup_env(&genesis, stores.clone());
+            restarted = true;
+        }
+        produce_block(&mut env, height);
+        height += 1;
+
+        for idx in 0..env.clients.len() {
+            assert!(has_state(&env, idx, SHARD_0));
+        }
+        assert!(has_state(&env, 0, SHARD_1));
+        if tail_epoch_height(&env, 1) <= LAST_EPOCH_HEIGHT_TRACKING_SHARD_1 {
+            assert!(has_state(&env, 1, SHARD_1), "removed at height {}", height - 1);
+        }
+    }
+    assert!(restarted);
+    assert!(tail_epoch_height(&env, 1) > LAST_EPOCH_HEIGHT_TRACKING_SHARD_1);
+
+    // Both nodes keep working after the state is removed.
+    for _ in 0..2 * EPOCH_LENGTH {
+        produce_block(&mut env, height);
+        height += 1;
+    }
+    for idx in 0..env.clients.len() {
+        assert_eq!(env.clients[idx].chain.head().unwrap().height, height - 1);
+        assert!(has_state(&env, idx, SHARD_0));
+    }
+    assert!(has_state(&env, 0, SHARD_1));
+    assert!(!has_state(&env, 1, SHARD_1));
+}
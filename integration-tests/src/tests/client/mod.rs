@@ -7,7 +7,9 @@ mod cold_storage;
 mod epoch_sync;
 mod features;
 mod flat_storage;
+mod gc_untracked_shards;
 mod process_blocks;
+mod read_replica;
 mod resharding;
 mod runtimes;
 #[cfg(feature = "sandbox")]
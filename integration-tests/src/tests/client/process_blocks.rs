@@ -354,7 +354,8 @@ fn receive_network_block() {
                 last_block.header.next_bp_hash,
                 block_merkle_tree.root(),
                 None,
-            );
+            )
+            .unwrap();
             actor_handles.client_actor.do_send(
                 BlockResponse { block, peer_id: PeerInfo::random().id, was_requested: false }
                     .with_span_context(),
@@ -435,7 +436,8 @@ fn produce_block_with_approvals() {
                 last_block.header.next_bp_hash,
                 block_merkle_tree.root(),
                 None,
-            );
+            )
+            .unwrap();
             actor_handles.client_actor.do_send(
                 BlockResponse {
                     block: block.clone(),
@@ -458,7 +460,8 @@ fn produce_block_with_approvals() {
                     block.header().height(),
                     10, // the height at which "test1" is producing
                     &signer,
-                );
+                )
+                .unwrap();
                 actor_handles
                     .client_actor
                     .do_send(BlockApproval(approval, PeerInfo::random().id).with_span_context());
@@ -647,7 +650,8 @@ fn invalid_blocks_common(is_requested: bool) {
                 last_block.header.next_bp_hash,
                 block_merkle_tree.root(),
                 None,
-            );
+            )
+            .unwrap();
             // Send block with invalid chunk mask
             let mut block = valid_block.clone();
             block.mut_header().get_mut().inner_rest.chunk_mask = vec![];
@@ -1699,7 +1703,7 @@ fn test_gc_after_state_sync() {
     // mimic what we do in possible_targets
     assert!(env.clients[1].epoch_manager.get_epoch_id_from_prev_block(&prev_block_hash).is_ok());
     let tries = env.clients[1].runtime_adapter.get_tries();
-    env.clients[1].chain.clear_data(tries, &Default::default()).unwrap();
+    env.clients[1].chain.clear_data(tries, &Default::default(), None).unwrap();
 }
 
 #[test]
@@ -2666,6 +2670,48 @@ fn test_refund_receipts_processing() {
     }
 }
 
+/// Archival nodes record the transaction each receipt originates from, including the receipts
+/// generated by other receipts.
+#[test]
+fn test_receipt_origins() {
+    init_test_logger();
+
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = 5;
+    let chain_genesis = ChainGenesis::new(&genesis);
+    let mut env = TestEnv::builder(chain_genesis)
+        .real_epoch_managers(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .archive(true)
+        .build();
+    let genesis_block = env.clients[0].chain.get_block_by_height(0).unwrap();
+    let signer = InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+    // The transfer to a non-existing account fails and generates a refund receipt.
+    let tx = SignedTransaction::send_money(
+        1,
+        "test0".parse().unwrap(),
+        "random_account".parse().unwrap(),
+        &signer,
+        1,
+        *genesis_block.hash(),
+    );
+    let tx_hash = tx.get_hash();
+    assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    for i in 1..6 {
+        env.produce_block(0, i);
+    }
+
+    let chain = &env.clients[0].chain;
+    let tx_outcome = chain.get_execution_outcome(&tx_hash).unwrap();
+    let receipt_id = tx_outcome.outcome_with_id.outcome.receipt_ids[0];
+    let receipt_outcome = chain.get_execution_outcome(&receipt_id).unwrap();
+    let refund_receipt_id = receipt_outcome.outcome_with_id.outcome.receipt_ids[0];
+    let chain_store = chain.chain_store();
+    assert_eq!(chain_store.get_receipt_origin(&receipt_id).unwrap(), Some(tx_hash));
+    assert_eq!(chain_store.get_receipt_origin(&refund_receipt_id).unwrap(), Some(tx_hash));
+    assert_eq!(chain_store.get_receipt_origin(&tx_hash).unwrap(), None);
+}
+
 // Tests that the number of delayed receipts in each shard is bounded based on the gas limit of
 // the chunk and any new receipts are not included if there are too many delayed receipts.
 #[test]
@@ -3216,11 +3262,13 @@ fn test_header_version_downgrade() {
             BlockHeader::BlockHeaderV1(header) => {
                 let header = Arc::make_mut(header);
                 header.inner_rest.latest_protocol_version = PROTOCOL_VERSION;
-                let (hash, signature) = validator_signer.sign_block_header_parts(
-                    header.prev_hash,
-                    &borsh::to_vec(&header.inner_lite).expect("Failed to serialize"),
-                    &borsh::to_vec(&header.inner_rest).expect("Failed to serialize"),
-                );
+                let (hash, signature) = validator_signer
+                    .sign_block_header_parts(
+                        header.prev_hash,
+                        &borsh::to_vec(&header.inner_lite).expect("Failed to serialize"),
+                        &borsh::to_vec(&header.inner_rest).expect("Failed to serialize"),
+                    )
+                    .unwrap();
                 header.hash = hash;
                 header.signature = signature;
             }
@@ -3494,6 +3542,7 @@ fn test_long_chain_with_restart_from_snapshot() {
 /// These tests fail on aarch because the WasmtimeVM::precompile method doesn't populate the cache.
 mod contract_precompilation_tests {
     use super::*;
+    use near_primitives::state_record::StateRecord;
     use near_primitives::test_utils::MockEpochInfoProvider;
     use near_primitives::views::ViewApplyState;
     use near_store::{StoreCompiledContractCache, TrieUpdate};
@@ -3696,6 +3745,55 @@ mod contract_precompilation_tests {
         assert!(caches[1].get(&test_contract_key).unwrap().is_some());
     }
 
+    #[test]
+    #[cfg_attr(all(target_arch = "aarch64", target_vendor = "apple"), ignore)]
+    fn test_precompile_contracts_speculatively() {
+        init_test_logger();
+        let wasm_code = near_test_contracts::rs_contract().to_vec();
+        let contract_code = ContractCode::new(wasm_code.clone(), None);
+        let mut genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+        let records = genesis.force_read_records().as_mut();
+        for record in records.iter_mut() {
+            if let StateRecord::Account { account_id, account } = record {
+                if account_id == "test0" {
+                    account.set_code_hash(*contract_code.hash());
+                }
+            }
+        }
+        records
+            .push(StateRecord::Contract { account_id: "test0".parse().unwrap(), code: wasm_code });
+
+        let env = TestEnv::builder(ChainGenesis::test())
+            .real_stores()
+            .real_epoch_managers(&genesis.config)
+            .nightshade_runtimes(&genesis)
+            .build();
+        let client = &env.clients[0];
+        let head = client.chain.head().unwrap();
+        let state_root = *client
+            .chain
+            .get_chunk_extra(&head.last_block_hash, &ShardUId::single_shard())
+            .unwrap()
+            .state_root();
+        let runtime_config = env.get_runtime_config(0, head.epoch_id.clone());
+        let key = get_contract_cache_key(&contract_code, &runtime_config.wasm_config);
+        let cache = StoreCompiledContractCache::new(client.chain.chain_store().store());
+        assert!(!cache.has(&key).unwrap());
+
+        // The contract is compiled in the background.
+        client.runtime_adapter.precompile_contracts_speculatively(
+            &head.epoch_id,
+            ShardUId::single_shard(),
+            state_root,
+            vec!["test0".parse().unwrap(), "test1".parse().unwrap()],
+        );
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(60);
+        while !cache.has(&key).unwrap() {
+            assert!(std::time::Instant::now() < deadline, "the contract wasn't compiled");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
     #[test]
     #[cfg_attr(all(target_arch = "aarch64", target_vendor = "apple"), ignore)]
     fn test_sync_after_delete_account() {
@@ -0,0 +1,105 @@
+use near_chain::types::{ChainConfig, RuntimeAdapter};
+use near_chain::{Chain, ChainGenesis, DoomslugThresholdMode};
+use near_chain_configs::Genesis;
+use near_client::test_utils::TestEnv;
+use near_client::ProcessTxResponse;
+use near_crypto::{InMemorySigner, KeyType};
+use near_epoch_manager::shard_tracker::ShardTracker;
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::views::{QueryRequest, QueryResponseKind};
+use near_store::{DBCol, Mode, NodeStorage, StoreConfig};
+use nearcore::config::GenesisExt;
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+use nearcore::NightshadeRuntime;
+
+/// Opens a secondary instance next to the database of a live node, and checks
+/// that after catching up with the primary it serves queries about the blocks
+/// processed by the node in the meantime, without ever writing to the database.
+#[test]
+fn test_read_replica_catches_up_with_primary() {
+    init_test_logger();
+    let epoch_length = 5;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let chain_genesis = ChainGenesis::new(&genesis);
+
+    let home_dir = tempfile::Builder::new().prefix("read_replica").tempdir().unwrap();
+    let store_config = StoreConfig::test_config();
+    let opener = NodeStorage::opener(home_dir.path(), false, &store_config, None);
+    let primary = opener.open_in_mode(Mode::ReadWrite).unwrap().get_hot_store();
+    let mut env = TestEnv::builder(chain_genesis.clone())
+        .stores(vec![primary])
+        .real_epoch_managers(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .build();
+    for height in 1..=3 {
+        env.produce_block(0, height);
+    }
+
+    let secondary = opener.open_in_mode(Mode::Secondary).unwrap().get_hot_store();
+    let epoch_manager = EpochManager::new_arc_handle(secondary.clone(), &genesis.config);
+    let runtime = NightshadeRuntime::test(
+        home_dir.path(),
+        secondary.clone(),
+        &genesis.config,
+        epoch_manager.clone(),
+    );
+    let chain = Chain::new(
+        epoch_manager.clone(),
+        ShardTracker::new_empty(epoch_manager.clone()),
+        runtime.clone(),
+        &chain_genesis,
+        DoomslugThresholdMode::NoApprovals,
+        ChainConfig { read_only: true, ..ChainConfig::test() },
+        None,
+    )
+    .unwrap();
+    assert_eq!(chain.head().unwrap().height, 3);
+
+    // The node keeps going, the replica only sees it after catching up.
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let signer = InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+    let tx = SignedTransaction::send_money(
+        1,
+        "test0".parse().unwrap(),
+        "test1".parse().unwrap(),
+        &signer,
+        100,
+        genesis_hash,
+    );
+    assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    for height in 4..=8 {
+        env.produce_block(0, height);
+    }
+    assert_eq!(chain.head().unwrap().height, 3);
+    secondary.catch_up_with_primary().unwrap();
+    let head = chain.head().unwrap();
+    assert_eq!(head.height, 8);
+
+    let account_id = "test1".parse().unwrap();
+    let block = chain.get_block(&head.last_block_hash).unwrap();
+    let shard_id = epoch_manager.account_id_to_shard_id(&account_id, &head.epoch_id).unwrap();
+    let shard_uid = epoch_manager.shard_id_to_uid(shard_id, &head.epoch_id).unwrap();
+    let response = runtime
+        .query(
+            shard_uid,
+            &block.chunks()[shard_id as usize].prev_state_root(),
+            block.header().height(),
+            block.header().raw_timestamp(),
+            block.header().prev_hash(),
+            block.header().hash(),
+            block.header().epoch_id(),
+            &QueryRequest::ViewAccount { account_id: account_id.clone() },
+        )
+        .unwrap();
+    let QueryResponseKind::ViewAccount(account) = response.kind else {
+        panic!("Wrong return value");
+    };
+    assert_eq!(account, env.query_account(account_id));
+
+    let mut store_update = secondary.store_update();
+    store_update.set(DBCol::BlockMisc, b"read_replica", b"");
+    assert!(store_update.commit().is_err());
+}
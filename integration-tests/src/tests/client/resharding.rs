@@ -22,6 +22,7 @@ use near_primitives::utils::MaybeValidated;
 use near_primitives::version::ProtocolFeature;
 use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{ExecutionStatusView, FinalExecutionStatus, QueryRequest};
+use near_primitives_core::checked_feature;
 use near_primitives_core::num_rational::Rational32;
 use near_store::flat::FlatStorageStatus;
 use near_store::test_utils::{gen_account, gen_unique_accounts};
@@ -51,12 +52,17 @@ enum ReshardingType {
     V1,
     // In the V1->V2 resharding outgoing receipts are reassigned to lowest index child.
     V2,
+    // A V2->V3 resharding at the latest protocol version, which doesn't change
+    // the shard layout on its own. The new layout is set in the epoch config
+    // test overrides, so that the resharding happens with chunk validation.
+    Latest,
 }
 
 fn get_target_protocol_version(resharding_type: &ReshardingType) -> ProtocolVersion {
     match resharding_type {
         ReshardingType::V1 => SIMPLE_NIGHTSHADE_PROTOCOL_VERSION,
         ReshardingType::V2 => SIMPLE_NIGHTSHADE_V2_PROTOCOL_VERSION,
+        ReshardingType::Latest => PROTOCOL_VERSION,
     }
 }
 
@@ -64,6 +70,7 @@ fn get_genesis_protocol_version(resharding_type: &ReshardingType) -> ProtocolVer
     match resharding_type {
         ReshardingType::V1 => SIMPLE_NIGHTSHADE_PROTOCOL_VERSION - 1,
         ReshardingType::V2 => SIMPLE_NIGHTSHADE_V2_PROTOCOL_VERSION - 1,
+        ReshardingType::Latest => PROTOCOL_VERSION - 1,
     }
 }
 
@@ -71,10 +78,25 @@ fn get_parent_shard_uids(resharding_type: &ReshardingType) -> Vec<ShardUId> {
     let shard_layout = match resharding_type {
         ReshardingType::V1 => ShardLayout::v0_single_shard(),
         ReshardingType::V2 => ShardLayout::get_simple_nightshade_layout(),
+        ReshardingType::Latest => ShardLayout::get_simple_nightshade_layout_v2(),
     };
     shard_layout.shard_uids().collect()
 }
 
+// The shard layout of the `ReshardingType::Latest` resharding: the simple
+// nightshade layout v2 with its shard 3 split in two, so that the ids of the
+// shards after it change too.
+fn get_latest_shard_layout() -> ShardLayout {
+    ShardLayout::v1(
+        vec!["aurora", "aurora-0", "kkuuue2akv_1630967379.near", "test1", "tge-lockup.sweat"]
+            .into_iter()
+            .map(|s| s.parse().unwrap())
+            .collect(),
+        Some(vec![vec![0], vec![1], vec![2], vec![3, 4], vec![5]]),
+        3,
+    )
+}
+
 // Return the expected number of shards.
 fn get_expected_shards_num(
     epoch_length: u64,
@@ -96,6 +118,13 @@ fn get_expected_shards_num(
                 return 5;
             }
         }
+        ReshardingType::Latest => {
+            if height <= 2 * epoch_length {
+                return 5;
+            } else {
+                return 6;
+            }
+        }
     };
 }
 
@@ -211,9 +240,14 @@ impl TestReshardingEnv {
         // doesn't support requesting chunks and non-validators wouldn't be able
         // to obtain the chunks at all.
         // Same needs to be set in the genesis.
+        let shard_layout_upgrade = match resharding_type {
+            Some(ReshardingType::Latest) => Some((PROTOCOL_VERSION, get_latest_shard_layout())),
+            _ => None,
+        };
         let epoch_config_test_overrides = Some(AllEpochConfigTestOverrides {
             block_producer_kickout_threshold: Some(0),
             chunk_producer_kickout_threshold: Some(0),
+            shard_layout_upgrade,
         });
         let env = builder
             .clients_count(num_clients)
@@ -773,7 +807,7 @@ fn check_outgoing_receipts_reassigned_impl(
                 assert_eq!(receiver_shard_id, shard_id);
             }
         }
-        ReshardingType::V2 => {
+        ReshardingType::V2 | ReshardingType::Latest => {
             // In V1->V2 resharding the outgoing receipts should be reassigned
             // to the lowest index child of the parent shard.
             // We can't directly check that here but we can check that the
@@ -1660,6 +1694,69 @@ fn currupt_state_snapshot(test_env: &TestReshardingEnv) {
     store_update.commit().unwrap();
 }
 
+// Test that the chunk validators accept the state witnesses of the chunks
+// after the resharding, which are applied on top of the last chunks of the
+// parent shards. Only the pre-validation is checked, validating the state
+// transition across a resharding is not supported yet.
+#[test]
+fn test_shard_layout_upgrade_chunk_state_witnesses() {
+    init_test_logger();
+
+    let resharding_type = ReshardingType::Latest;
+    let genesis_protocol_version = get_genesis_protocol_version(&resharding_type);
+    let target_protocol_version = get_target_protocol_version(&resharding_type);
+    if !checked_feature!("stable", ChunkValidation, genesis_protocol_version) {
+        println!("Test not applicable without ChunkValidation enabled");
+        return;
+    }
+
+    // setup
+    let epoch_length = 5;
+    let mut test_env = TestReshardingEnv::new(
+        epoch_length,
+        4,
+        4,
+        10,
+        None,
+        genesis_protocol_version,
+        42,
+        false,
+        Some(resharding_type),
+    );
+    test_env.set_init_tx(vec![]);
+
+    let drop_chunk_condition = DropChunkCondition::new();
+    let mut resharding_witnesses = 0;
+    for _ in 1..4 * epoch_length {
+        test_env.step(&drop_chunk_condition, target_protocol_version);
+
+        let env = &mut test_env.env;
+        let mut witnesses = vec![];
+        let results = env.propagate_chunk_state_witnesses_with(|_, witness| {
+            witnesses.push(witness.clone());
+            Some(witness)
+        });
+        for (account_id, result) in results {
+            assert!(result.is_ok(), "{account_id} rejected chunk state witness: {result:?}");
+        }
+
+        // Count the witnesses whose previous block is in the epoch before
+        // the resharding, so that the previous chunk is of the parent shard.
+        let epoch_manager = &env.clients[0].epoch_manager;
+        for witness in witnesses {
+            let prev_block_hash = witness.chunk_header.prev_block_hash();
+            let prev_epoch_id = epoch_manager.get_epoch_id(prev_block_hash).unwrap();
+            let prev_shard_layout = epoch_manager.get_shard_layout(&prev_epoch_id).unwrap();
+            let shard_layout =
+                epoch_manager.get_shard_layout_from_prev_block(prev_block_hash).unwrap();
+            if shard_layout != prev_shard_layout {
+                resharding_witnesses += 1;
+            }
+        }
+    }
+    assert!(resharding_witnesses > 0, "no chunk state witness spans the resharding");
+}
+
 #[test]
 fn test_shard_layout_upgrade_error_handling_v1() {
     test_shard_layout_upgrade_error_handling_impl(ReshardingType::V1, 42, false);
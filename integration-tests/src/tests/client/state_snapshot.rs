@@ -10,6 +10,7 @@ use near_primitives::shard_layout::ShardUId;
 use near_primitives::transaction::SignedTransaction;
 use near_store::config::StateSnapshotType;
 use near_store::flat::FlatStorageManager;
+use near_store::trie::SnapshotError;
 use near_store::{
     config::TrieCacheConfig, test_utils::create_test_store, Mode, ShardTries, StateSnapshotConfig,
     StoreConfig, TrieConfig,
@@ -33,6 +34,7 @@ impl StateSnaptshotTestEnv {
         hot_store_path: PathBuf,
         state_snapshot_subdir: PathBuf,
         store: &Store,
+        num_retained_snapshots: usize,
     ) -> Self {
         let trie_cache_config = TrieCacheConfig {
             default_max_bytes: bytesize::ByteSize::mb(50),
@@ -43,10 +45,13 @@ impl StateSnaptshotTestEnv {
             shard_cache_config: trie_cache_config.clone(),
             view_shard_cache_config: trie_cache_config,
             enable_receipt_prefetching: false,
+            receipt_prefetch_io_budget: 0,
             sweat_prefetch_receivers: Vec::new(),
             sweat_prefetch_senders: Vec::new(),
             load_mem_tries_for_shards: Vec::new(),
             load_mem_tries_for_all_shards: false,
+            mem_tries_gc_retained_heights: 0,
+            mem_tries_compaction_fragmentation_threshold: None,
         };
         let flat_storage_manager = FlatStorageManager::new(store.clone());
         let shard_uids = [ShardUId::single_shard()];
@@ -56,6 +61,7 @@ impl StateSnaptshotTestEnv {
             hot_store_path: hot_store_path.clone(),
             state_snapshot_subdir: state_snapshot_subdir.clone(),
             compaction_enabled: true,
+            num_retained_snapshots,
         };
         let shard_tries = ShardTries::new(
             store.clone(),
@@ -69,11 +75,24 @@ impl StateSnaptshotTestEnv {
 }
 
 fn set_up_test_env_for_state_snapshots(store: &Store) -> StateSnaptshotTestEnv {
+    set_up_test_env_for_retained_state_snapshots(store, 1)
+}
+
+fn set_up_test_env_for_retained_state_snapshots(
+    store: &Store,
+    num_retained_snapshots: usize,
+) -> StateSnaptshotTestEnv {
     let home_dir =
         tempfile::Builder::new().prefix("storage").tempdir().unwrap().path().to_path_buf();
     let hot_store_path = PathBuf::from("data");
     let state_snapshot_subdir = PathBuf::from("state_snapshot");
-    StateSnaptshotTestEnv::new(home_dir, hot_store_path, state_snapshot_subdir, store)
+    StateSnaptshotTestEnv::new(
+        home_dir,
+        hot_store_path,
+        state_snapshot_subdir,
+        store,
+        num_retained_snapshots,
+    )
 }
 
 #[test]
@@ -264,3 +283,62 @@ fn test_make_state_snapshot() {
         )
     );
 }
+
+#[test]
+// Makes a state snapshot after processing every block, retaining the last two
+// of them, and checks that each retained snapshot can be served.
+fn test_retain_state_snapshots() {
+    init_test_logger();
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(ChainGenesis::test())
+        .clients_count(1)
+        .real_stores()
+        .real_epoch_managers(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .build();
+
+    let store = env.clients[0].chain.chain_store().store();
+    let state_snapshot_test_env = set_up_test_env_for_retained_state_snapshots(store, 2);
+    let shard_tries = &state_snapshot_test_env.shard_tries;
+
+    let mut block_hashes = vec![];
+    for i in 1..=3 {
+        let block = env.clients[0].produce_block(i).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+        shard_tries
+            .create_state_snapshot(*block.hash(), &[ShardUId::single_shard()], &block)
+            .unwrap();
+        block_hashes.push(*block.hash());
+    }
+
+    let snapshot_path = |block_hash: &CryptoHash| {
+        ShardTries::get_state_snapshot_base_dir(
+            block_hash,
+            &state_snapshot_test_env.home_dir,
+            &state_snapshot_test_env.hot_store_path,
+            &state_snapshot_test_env.state_snapshot_subdir,
+        )
+    };
+    let check_retained_snapshots = || {
+        assert_eq!(shard_tries.get_state_snapshot_hashes().unwrap(), block_hashes[1..]);
+        assert_eq!(shard_tries.get_state_snapshot_hash().unwrap(), block_hashes[2]);
+        assert_eq!(
+            shard_tries.get_state_snapshot(&block_hashes[0]).err(),
+            Some(SnapshotError::IncorrectSnapshotRequested(block_hashes[0], block_hashes[2]))
+        );
+        assert!(!snapshot_path(&block_hashes[0]).exists());
+        for block_hash in &block_hashes[1..] {
+            assert!(shard_tries.get_state_snapshot(block_hash).is_ok());
+            assert!(snapshot_path(block_hash).exists());
+        }
+    };
+    check_retained_snapshots();
+
+    // The retained snapshots are opened again after a restart.
+    shard_tries.maybe_open_state_snapshot(|_| Ok(vec![ShardUId::single_shard()])).unwrap();
+    check_retained_snapshots();
+
+    shard_tries.delete_state_snapshot();
+    assert!(shard_tries.get_state_snapshot_hashes().is_err());
+    assert!(!snapshot_path(&block_hashes[2]).exists());
+}
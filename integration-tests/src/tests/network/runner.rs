@@ -8,7 +8,10 @@ use near_chain::types::RuntimeAdapter;
 use near_chain::{Chain, ChainGenesis};
 use near_chain_configs::ClientConfig;
 use near_chunks::shards_manager_actor::start_shards_manager;
-use near_client::{start_client, start_view_client, SyncAdapter};
+use near_client::{
+    start_chunk_witness_actor, start_client, start_view_client, ChunkEndorsementPool,
+    ChunkWitnessProductionTracker, SyncAdapter, ValidatorAccountId,
+};
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_network::actix::ActixSystem;
 use near_network::blacklist;
@@ -75,6 +78,18 @@ fn setup_network_node(
     let adv = near_client::adversarial::Controls::default();
     let state_sync_adapter =
         Arc::new(RwLock::new(SyncAdapter::new(Sender::noop(), Sender::noop())));
+    let chunk_witness_production_tracker = ChunkWitnessProductionTracker::default();
+    let chunk_endorsements = ChunkEndorsementPool::default();
+    let validator_account_id = ValidatorAccountId::new(Some(signer.validator_id().clone()));
+    let (chunk_witness_actor, _) = start_chunk_witness_actor(
+        &client_config,
+        &chain_genesis,
+        epoch_manager.clone(),
+        runtime.store().clone(),
+        network_adapter.as_sender(),
+        chunk_witness_production_tracker.clone(),
+        adv.clone(),
+    );
     let client_actor = start_client(
         client_config.clone(),
         chain_genesis.clone(),
@@ -85,6 +100,10 @@ fn setup_network_node(
         state_sync_adapter,
         network_adapter.clone().into(),
         shards_manager_adapter.as_sender(),
+        chunk_witness_actor.with_auto_span_context().into_sender(),
+        chunk_witness_production_tracker,
+        chunk_endorsements.clone(),
+        validator_account_id.clone(),
         Some(signer.clone()),
         telemetry_actor,
         None,
@@ -94,13 +113,14 @@ fn setup_network_node(
     )
     .0;
     let view_client_actor = start_view_client(
-        config.validator.as_ref().map(|v| v.account_id()),
+        validator_account_id,
         chain_genesis,
         epoch_manager.clone(),
         shard_tracker.clone(),
         runtime.clone(),
         network_adapter.clone().into(),
         client_config.clone(),
+        chunk_endorsements,
         adv,
     );
     let (shards_manager_actor, _) = start_shards_manager(
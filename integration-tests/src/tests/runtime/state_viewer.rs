@@ -3,6 +3,8 @@ use std::{collections::HashMap, io, sync::Arc};
 use borsh::BorshDeserialize;
 
 use crate::runtime_utils::{get_runtime_and_trie, get_test_trie_viewer, TEST_SHARD_UID};
+use near_crypto::{InMemorySigner, KeyType, Signature};
+use near_parameters::RuntimeConfig;
 use near_primitives::{
     account::Account,
     hash::hash as sha256,
@@ -13,7 +15,9 @@ use near_primitives::{
     views::{StateItem, ViewApplyState},
 };
 use near_primitives::{
+    errors::{InvalidTxError, TxExecutionError},
     test_utils::MockEpochInfoProvider,
+    transaction::{ExecutionStatus, SignedTransaction},
     trie_key::TrieKey,
     types::{EpochId, StateChangeCause},
     version::PROTOCOL_VERSION,
@@ -21,7 +25,7 @@ use near_primitives::{
 use near_store::{set_account, NibbleSlice, RawTrieNode, RawTrieNodeWithSize};
 use node_runtime::state_viewer::errors;
 use node_runtime::state_viewer::*;
-use testlib::runtime_utils::alice_account;
+use testlib::runtime_utils::{alice_account, bob_account};
 
 struct ProofVerifier {
     nodes: HashMap<CryptoHash, RawTrieNodeWithSize>,
@@ -126,7 +130,9 @@ fn test_view_call() {
         &MockEpochInfoProvider::default(),
     );
 
-    assert_eq!(result.unwrap(), (10i32).to_le_bytes());
+    let (result, gas_burnt) = result.unwrap();
+    assert_eq!(result, (10i32).to_le_bytes());
+    assert!(gas_burnt > 0);
 }
 
 #[test]
@@ -185,7 +191,7 @@ fn test_view_call_with_args() {
         &mut logs,
         &MockEpochInfoProvider::default(),
     );
-    assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
+    assert_eq!(view_call_result.unwrap().0, 3u64.to_le_bytes().to_vec());
 }
 
 fn assert_view_state(
@@ -411,3 +417,132 @@ fn test_log_when_panic() {
 
     assert_eq!(logs, vec!["hello".to_string()]);
 }
+
+#[test]
+fn test_estimate_transaction() {
+    let viewer = TrieViewer::default();
+    let view_state = || ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+    };
+    let signer = InMemorySigner::from_seed(alice_account(), KeyType::ED25519, "alice.near");
+    let transaction = SignedTransaction::send_money(
+        1,
+        alice_account(),
+        bob_account(),
+        &signer,
+        100,
+        CryptoHash::default(),
+    );
+
+    let estimate = viewer
+        .estimate_transaction(
+            get_test_trie_viewer().1,
+            ReceiverState::SignerShard,
+            view_state(),
+            Arc::new(RuntimeConfig::test()),
+            100,
+            &transaction,
+            true,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+    assert_eq!(estimate.failure, None);
+    let transaction_outcome = estimate.transaction_outcome.unwrap();
+    assert!(transaction_outcome.outcome.gas_burnt > 0);
+    let receipt_outcome = estimate.receipt_outcome.unwrap();
+    assert_eq!(transaction_outcome.outcome.receipt_ids, vec![receipt_outcome.id]);
+    assert!(matches!(receipt_outcome.outcome.status, ExecutionStatus::SuccessValue(_)));
+    // A transfer only generates refunds.
+    assert!(estimate.receipts.iter().all(|receipt| receipt.predecessor_id.is_system()));
+
+    // The receipt is not executed if the state of the receiver isn't available.
+    let estimate = viewer
+        .estimate_transaction(
+            get_test_trie_viewer().1,
+            ReceiverState::Unavailable,
+            view_state(),
+            Arc::new(RuntimeConfig::test()),
+            100,
+            &transaction,
+            true,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+    assert!(estimate.transaction_outcome.is_some());
+    assert!(estimate.receipt_outcome.is_none());
+    assert_eq!(estimate.receipts.len(), 1);
+
+    // The signature is only checked if requested.
+    let unsigned =
+        SignedTransaction::new(Signature::empty(KeyType::ED25519), transaction.transaction);
+    let estimate = viewer
+        .estimate_transaction(
+            get_test_trie_viewer().1,
+            ReceiverState::SignerShard,
+            view_state(),
+            Arc::new(RuntimeConfig::test()),
+            100,
+            &unsigned,
+            false,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+    assert_eq!(estimate.failure, None);
+    let estimate = viewer
+        .estimate_transaction(
+            get_test_trie_viewer().1,
+            ReceiverState::SignerShard,
+            view_state(),
+            Arc::new(RuntimeConfig::test()),
+            100,
+            &unsigned,
+            true,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+    assert_eq!(
+        estimate.failure,
+        Some(TxExecutionError::InvalidTxError(InvalidTxError::InvalidSignature))
+    );
+    assert!(estimate.transaction_outcome.is_none());
+
+    // Transactions attaching more gas than a view call may burn are rejected.
+    let viewer = TrieViewer::new(None, Some(1_000_000));
+    let call = SignedTransaction::call(
+        1,
+        alice_account(),
+        bob_account(),
+        &signer,
+        0,
+        "main".to_string(),
+        vec![],
+        1_000_001,
+        CryptoHash::default(),
+    );
+    let err = viewer
+        .estimate_transaction(
+            get_test_trie_viewer().1,
+            ReceiverState::SignerShard,
+            view_state(),
+            Arc::new(RuntimeConfig::test()),
+            100,
+            &call,
+            true,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        errors::EstimateTransactionError::GasLimitExceeded {
+            prepaid_gas: 1_000_001,
+            limit: 1_000_000
+        }
+    ));
+}
@@ -167,6 +167,7 @@ impl RuntimeUser {
             is_new_chunk: true,
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
+            collect_receipt_profiles: false,
         }
     }
 
@@ -280,7 +281,7 @@ impl User for RuntimeUser {
             current_protocol_version: PROTOCOL_VERSION,
             cache: apply_state.cache,
         };
-        result.result = self
+        (result.result, result.gas_burnt) = self
             .trie_viewer
             .call_function(
                 state_update,
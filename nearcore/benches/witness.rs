@@ -0,0 +1,215 @@
+#[macro_use]
+extern crate bencher;
+
+use bencher::Bencher;
+use near_chain::{ChainGenesis, Provenance};
+use near_chain_configs::Genesis;
+use near_client::test_utils::TestEnv;
+use near_crypto::{InMemorySigner, KeyType};
+use near_o11y::testonly::init_integration_logger;
+use near_primitives::checked_feature;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::{
+    Action, DeployContractAction, FunctionCallAction, SignedTransaction, TransferAction,
+};
+use near_primitives::types::AccountId;
+use near_primitives::version::PROTOCOL_VERSION;
+use nearcore::config::GenesisExt;
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+/// Number of accounts sending transactions. They all live in the single shard
+/// of the chain, whose only validator produces all chunks and witnesses.
+const NUM_ACCOUNTS: usize = 20;
+
+/// Number of transactions submitted for each block.
+const TXS_PER_BLOCK: usize = 100;
+
+/// Number of keys written by each function call of the storage workload.
+const KEYS_PER_CALL: u64 = 200;
+
+/// Synthetic workloads fed through chunk production.
+#[derive(Clone, Copy, Debug)]
+enum Workload {
+    /// No transactions, the witness only holds the implicit state changes.
+    Empty,
+    /// Transfers between accounts, producing many small receipts.
+    Transfers,
+    /// Function calls writing many storage keys.
+    StorageWrites,
+}
+
+struct WitnessBenchEnv {
+    env: TestEnv,
+    accounts: Vec<AccountId>,
+    nonce: u64,
+    witness_sizes: Vec<usize>,
+}
+
+impl WitnessBenchEnv {
+    /// Sets up a chain with a single validator and deploys the test contract
+    /// to all accounts. Returns `None` if the ChunkValidation protocol feature
+    /// is not enabled, as no witnesses are produced then.
+    fn new() -> Option<Self> {
+        init_integration_logger();
+        if !checked_feature!("stable", ChunkValidation, PROTOCOL_VERSION) {
+            println!("Benchmark not applicable without ChunkValidation enabled");
+            return None;
+        }
+        let accounts: Vec<AccountId> =
+            (0..NUM_ACCOUNTS).map(|i| format!("test{}", i).parse().unwrap()).collect();
+        let mut genesis = Genesis::test(accounts.clone(), 1);
+        // Don't let epoch switches interfere with the measurements.
+        genesis.config.epoch_length = 100_000;
+        let env = TestEnv::builder(ChainGenesis::new(&genesis))
+            .real_epoch_managers(&genesis.config)
+            .nightshade_runtimes(&genesis)
+            .build();
+        let mut bench_env = Self { env, accounts, nonce: 0, witness_sizes: vec![] };
+
+        let code = near_test_contracts::rs_contract().to_vec();
+        let block_hash = bench_env.head_hash();
+        for account in bench_env.accounts.clone() {
+            let action = Action::DeployContract(DeployContractAction { code: code.clone() });
+            let tx = bench_env.transaction(&account, &account, vec![action], block_hash);
+            bench_env.env.clients[0].process_tx(tx, false, false);
+        }
+        // Let the deployments and their receipts be applied.
+        for _ in 0..3 {
+            bench_env.produce_block();
+        }
+        bench_env.witness_sizes.clear();
+        Some(bench_env)
+    }
+
+    fn head_hash(&self) -> CryptoHash {
+        self.env.clients[0].chain.head().unwrap().last_block_hash
+    }
+
+    fn transaction(
+        &mut self,
+        sender: &AccountId,
+        receiver: &AccountId,
+        actions: Vec<Action>,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
+        self.nonce += 1;
+        let signer = InMemorySigner::from_seed(sender.clone(), KeyType::ED25519, sender.as_ref());
+        SignedTransaction::from_actions(
+            self.nonce,
+            sender.clone(),
+            receiver.clone(),
+            &signer,
+            actions,
+            block_hash,
+        )
+    }
+
+    /// Submits the transactions of the workload for the next block.
+    fn submit_workload(&mut self, workload: Workload) {
+        let block_hash = self.head_hash();
+        let num_txs = match workload {
+            Workload::Empty => 0,
+            Workload::Transfers | Workload::StorageWrites => TXS_PER_BLOCK,
+        };
+        for i in 0..num_txs {
+            let sender = self.accounts[i % NUM_ACCOUNTS].clone();
+            let (receiver, action) = match workload {
+                Workload::Empty => unreachable!(),
+                Workload::Transfers => (
+                    self.accounts[(i + 1) % NUM_ACCOUNTS].clone(),
+                    Action::Transfer(TransferAction { deposit: 1 }),
+                ),
+                Workload::StorageWrites => {
+                    let args = [0u64.to_le_bytes(), KEYS_PER_CALL.to_le_bytes()].concat();
+                    (
+                        sender.clone(),
+                        Action::FunctionCall(Box::new(FunctionCallAction {
+                            method_name: "insert_strings".to_string(),
+                            args,
+                            gas: 300_000_000_000_000,
+                            deposit: 0,
+                        })),
+                    )
+                }
+            };
+            let tx = self.transaction(&sender, &receiver, vec![action], block_hash);
+            self.env.clients[0].process_tx(tx, false, false);
+        }
+    }
+
+    /// Produces and applies the next block, which makes the validator produce
+    /// the chunk of the next height and its state witness.
+    fn produce_block(&mut self) {
+        let tip = self.env.clients[0].chain.head().unwrap();
+        let block = self.env.clients[0].produce_block(tip.height + 1).unwrap().unwrap();
+        self.env.process_block(0, block, Provenance::PRODUCED);
+        self.env.process_partial_encoded_chunks();
+        self.env.process_shards_manager_responses_and_finish_processing_blocks(0);
+        let witness_sizes = &mut self.witness_sizes;
+        self.env.propagate_chunk_state_witnesses_with(|_, witness| {
+            witness_sizes.push(borsh::object_length(&witness).unwrap());
+            // Validation is not part of the measurement.
+            None
+        });
+    }
+}
+
+/// Returns the total time in seconds spent producing witnesses so far, and
+/// the number of witnesses produced.
+fn witness_production_time() -> (f64, u64) {
+    near_o11y::metrics::gather()
+        .iter()
+        .filter(|family| family.get_name() == "near_chunk_state_witness_production_time")
+        .flat_map(|family| family.get_metric())
+        .map(|metric| metric.get_histogram())
+        .fold((0., 0), |(sum, count), histogram| {
+            (sum + histogram.get_sample_sum(), count + histogram.get_sample_count())
+        })
+}
+
+/// Produces a block with the given workload per iteration, then prints the
+/// sizes of the produced witnesses and the average time taken to assemble
+/// them. The iteration time also includes applying the chunks with storage
+/// recording enabled.
+fn produce_witnesses(bench: &mut Bencher, workload: Workload) {
+    let Some(mut bench_env) = WitnessBenchEnv::new() else {
+        return;
+    };
+    let (start_time, start_count) = witness_production_time();
+    bench.iter(|| {
+        bench_env.submit_workload(workload);
+        bench_env.produce_block();
+    });
+    let (end_time, end_count) = witness_production_time();
+
+    let sizes = &bench_env.witness_sizes;
+    if sizes.is_empty() {
+        println!("{:?}: no witnesses produced", workload);
+        return;
+    }
+    let num_witnesses = (end_count - start_count).max(1);
+    println!(
+        "{:?}: {} witnesses, size avg {} max {} bytes, production time avg {:.3}ms",
+        workload,
+        sizes.len(),
+        sizes.iter().sum::<usize>() / sizes.len(),
+        sizes.iter().max().unwrap(),
+        (end_time - start_time) * 1000. / num_witnesses as f64,
+    );
+}
+
+fn witness_empty(bench: &mut Bencher) {
+    produce_witnesses(bench, Workload::Empty);
+}
+
+fn witness_transfers(bench: &mut Bencher) {
+    produce_witnesses(bench, Workload::Transfers);
+}
+
+fn witness_storage_writes(bench: &mut Bencher) {
+    produce_witnesses(bench, Workload::StorageWrites);
+}
+
+benchmark_group!(benches, witness_empty, witness_transfers, witness_storage_writes);
+
+benchmark_main!(benches);
@@ -0,0 +1,138 @@
+use crate::metrics;
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::types::compaction::{
+    CompactionHandler, CompactionRequest, CompactionStatus,
+};
+use near_primitives::static_clock::StaticClock;
+use near_store::db::ColumnRange;
+use near_store::{DBCol, Store};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
+
+/// The column is compacted in ranges of about this size, so that the progress
+/// can be reported and the rate of the compaction limited between the ranges.
+const RANGE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Compacts a column of the database of the running node in the background,
+/// to reclaim the space freed e.g. by garbage collection or resharding without
+/// stopping the node. Only one compaction runs at a time.
+pub struct CompactionHandlerImpl {
+    hot_store: Store,
+    cold_store: Option<Store>,
+    status: Arc<Mutex<Option<CompactionStatus>>>,
+}
+
+impl CompactionHandlerImpl {
+    pub fn new(hot_store: Store, cold_store: Option<Store>) -> Self {
+        Self { hot_store, cold_store, status: Arc::new(Mutex::new(None)) }
+    }
+}
+
+impl CompactionHandler for CompactionHandlerImpl {
+    fn start(&self, request: CompactionRequest) -> Result<CompactionStatus, RpcError> {
+        let Some(col) = DBCol::iter().find(|col| <&str>::from(*col) == request.column) else {
+            return Err(RpcError::invalid_params(format!("unknown column {}", request.column)));
+        };
+        let store = match (request.cold, &self.cold_store) {
+            (false, _) => self.hot_store.clone(),
+            (true, Some(cold_store)) => cold_store.clone(),
+            (true, None) => {
+                return Err(RpcError::invalid_params("the node has no cold database"));
+            }
+        };
+        if request.max_bytes_per_second == Some(0) {
+            return Err(RpcError::invalid_params("max_bytes_per_second should not be 0"));
+        }
+
+        let mut status = self.status.lock().unwrap();
+        if status.as_ref().is_some_and(|status| !status.finished) {
+            return Err(RpcError::invalid_params("a compaction is already in progress"));
+        }
+        let ranges = store
+            .column_ranges(col, RANGE_SIZE)
+            .map_err(|err| RpcError::new_internal_error(None, err.to_string()))?;
+        let new_status = CompactionStatus {
+            request,
+            started_at_ms: StaticClock::utc().timestamp_millis(),
+            num_ranges: ranges.len(),
+            num_ranges_done: 0,
+            total_bytes: ranges.iter().map(|range| range.size).sum(),
+            bytes_done: 0,
+            finished: false,
+            error: None,
+        };
+        *status = Some(new_status.clone());
+        drop(status);
+
+        tracing::info!(
+            target: "db",
+            column = %col,
+            num_ranges = new_status.num_ranges,
+            total_bytes = new_status.total_bytes,
+            "Starting the compaction of the column",
+        );
+        let max_bytes_per_second = new_status.request.max_bytes_per_second;
+        let status = self.status.clone();
+        std::thread::Builder::new()
+            .name("db_compaction".to_string())
+            .spawn(move || compact_ranges(store, col, ranges, max_bytes_per_second, &status))
+            .map_err(|err| RpcError::new_internal_error(None, err.to_string()))?;
+        Ok(new_status)
+    }
+
+    fn status(&self) -> Option<CompactionStatus> {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+/// Compacts the ranges one at a time, and pauses between them so that on
+/// average no more than `max_bytes_per_second` are compacted.
+fn compact_ranges(
+    store: Store,
+    col: DBCol,
+    ranges: Vec<ColumnRange>,
+    max_bytes_per_second: Option<u64>,
+    status: &Mutex<Option<CompactionStatus>>,
+) {
+    let col_name: &'static str = col.into();
+    let started = Instant::now();
+    let mut bytes_done = 0;
+    let mut result = Ok(());
+    for range in &ranges {
+        if let Err(err) = store.compact_range(col, range) {
+            result = Err(err);
+            break;
+        }
+        bytes_done += range.size;
+        metrics::DATABASE_COMPACTION_BYTES.with_label_values(&[col_name]).inc_by(range.size);
+        if let Some(status) = status.lock().unwrap().as_mut() {
+            status.num_ranges_done += 1;
+            status.bytes_done = bytes_done;
+        }
+        if let Some(max_bytes_per_second) = max_bytes_per_second {
+            let min_elapsed =
+                Duration::from_secs_f64(bytes_done as f64 / max_bytes_per_second as f64);
+            if let Some(pause) = min_elapsed.checked_sub(started.elapsed()) {
+                std::thread::sleep(pause);
+            }
+        }
+    }
+
+    match &result {
+        Ok(()) => tracing::info!(
+            target: "db",
+            column = %col,
+            bytes_done,
+            elapsed = ?started.elapsed(),
+            "Finished the compaction of the column",
+        ),
+        Err(err) => {
+            tracing::error!(target: "db", column = %col, ?err, "Failed to compact the column")
+        }
+    }
+    if let Some(status) = status.lock().unwrap().as_mut() {
+        status.finished = true;
+        status.error = result.err().map(|err| err.to_string());
+    }
+}
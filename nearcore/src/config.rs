@@ -1,21 +1,30 @@
 use crate::download_file::{run_download_file, FileDownloadError};
 use crate::dyn_config::LOG_CONFIG_FILENAME;
+use crate::remote_signer::{RemoteSignerConfig, RemoteValidatorSigner};
 use anyhow::{anyhow, bail, Context};
 use near_chain_configs::{
-    default_enable_multiline_logging, default_epoch_sync_enabled,
-    default_header_sync_expected_height_per_second, default_header_sync_initial_timeout,
-    default_header_sync_progress_timeout, default_header_sync_stall_ban_timeout,
-    default_log_summary_period, default_produce_chunk_add_transactions_time_limit,
-    default_state_sync, default_state_sync_enabled, default_state_sync_timeout,
-    default_sync_check_period, default_sync_height_threshold, default_sync_step_period,
+    default_chunk_endorsement_num_next_block_producers, default_chunk_witness_height_horizon,
+    default_chunk_witness_production_threads, default_enable_multiline_logging,
+    default_epoch_sync_enabled, default_header_sync_expected_height_per_second,
+    default_header_sync_initial_timeout, default_header_sync_progress_timeout,
+    default_header_sync_stall_ban_timeout, default_log_summary_period,
+    default_produce_chunk_add_transactions_time_limit,
+    default_produce_chunk_max_transactions_per_account, default_state_sync,
+    default_state_sync_enabled, default_state_sync_num_concurrent_requests,
+    default_state_sync_timeout, default_sync_check_period, default_sync_height_threshold,
+    default_sync_step_period, default_transaction_pool_future_nonce_limit,
     default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
     default_tx_routing_height_horizon, default_view_client_threads,
-    default_view_client_throttle_period, get_initial_supply, ClientConfig, GCConfig, Genesis,
-    GenesisConfig, GenesisValidationMode, LogSummaryStyle, MutableConfigValue, ReshardingConfig,
-    StateSyncConfig,
+    default_view_client_throttle_period, get_initial_supply, ChunkValidatorConfig, ClientConfig,
+    DoomslugAdaptiveTimersConfig, GCConfig, Genesis, GenesisConfig, GenesisValidationMode,
+    LogSummaryStyle, MaintenanceWindowConfig, MutableConfigValue, NodeStatusFileConfig,
+    ProductionAlertsConfig, ReadReplicaConfig, RemoteChunkValidationConfig, ReshardingConfig,
+    ShadowApplyConfig, StateSyncConfig, ValidatorFailoverConfig,
 };
 use near_config_utils::{ValidationError, ValidationErrors};
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer};
+#[cfg(feature = "grpc")]
+use near_grpc::GrpcConfig;
 #[cfg(feature = "json_rpc")]
 use near_jsonrpc::RpcConfig;
 use near_network::config::NetworkConfig;
@@ -43,7 +52,7 @@ use num_rational::Rational32;
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -194,6 +203,9 @@ pub struct Consensus {
     /// How much to wait for a state sync response before re-requesting
     #[serde(default = "default_state_sync_timeout")]
     pub state_sync_timeout: Duration,
+    /// Maximum number of state parts of a shard requested from peers at the same time
+    #[serde(default = "default_state_sync_num_concurrent_requests")]
+    pub state_sync_num_concurrent_requests: u32,
     /// Expected increase of header head weight per second during header sync
     #[serde(default = "default_header_sync_expected_height_per_second")]
     pub header_sync_expected_height_per_second: u64,
@@ -208,6 +220,11 @@ pub struct Consensus {
     pub doomslug_step_period: Duration,
     #[serde(default = "default_sync_height_threshold")]
     pub sync_height_threshold: u64,
+    /// If set, the doomslug skip delay adapts to the observed latency of
+    /// approvals, between twice the `min_block_production_delay` and the
+    /// `max_block_production_delay`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doomslug_adaptive_timers: Option<DoomslugAdaptiveTimersConfig>,
 }
 
 impl Default for Consensus {
@@ -227,12 +244,14 @@ impl Default for Consensus {
             header_sync_progress_timeout: default_header_sync_progress_timeout(),
             header_sync_stall_ban_timeout: default_header_sync_stall_ban_timeout(),
             state_sync_timeout: default_state_sync_timeout(),
+            state_sync_num_concurrent_requests: default_state_sync_num_concurrent_requests(),
             header_sync_expected_height_per_second: default_header_sync_expected_height_per_second(
             ),
             sync_check_period: default_sync_check_period(),
             sync_step_period: default_sync_step_period(),
             doomslug_step_period: default_doomslug_step_period(),
             sync_height_threshold: default_sync_height_threshold(),
+            doomslug_adaptive_timers: None,
         }
     }
 }
@@ -243,6 +262,15 @@ pub struct Config {
     pub genesis_file: String,
     pub genesis_records_file: Option<String>,
     pub validator_key_file: String,
+    /// Key file of the key the validator is rotating to, if any. Until the
+    /// new key is registered for an epoch, the validator keeps signing with
+    /// the key from `validator_key_file` for that epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validator_next_key_file: Option<String>,
+    /// If set, the validator signs using a remote signing service (e.g. one
+    /// backed by an HSM) instead of the key from `validator_key_file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<RemoteSignerConfig>,
     pub node_key_file: String,
     #[cfg(feature = "json_rpc")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -250,6 +278,9 @@ pub struct Config {
     #[cfg(feature = "rosetta_rpc")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rosetta_rpc: Option<RosettaRpcConfig>,
+    #[cfg(feature = "grpc")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grpc: Option<GrpcConfig>,
     pub telemetry: TelemetryConfig,
     pub network: near_network::config_json::Config,
     pub consensus: Consensus,
@@ -294,6 +325,12 @@ pub struct Config {
     /// The node usually stops within several seconds after reaching the target height.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expected_shutdown: Option<BlockHeight>,
+    /// Upcoming maintenance of the node. The node finds the nearest stretch of
+    /// at least `num_blocks` heights in which its validator has no duties,
+    /// served by the `EXPERIMENTAL_scheduled_maintenance_window` RPC method,
+    /// and shuts down at its start if `auto_restart` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance_window: Option<MaintenanceWindowConfig>,
     /// Whether to use state sync (unreliable and corrupts the DB if fails) or do a block sync instead.
     pub state_sync_enabled: bool,
     /// Options for syncing state.
@@ -301,11 +338,34 @@ pub struct Config {
     pub state_sync: Option<StateSyncConfig>,
     /// Limit of the size of per-shard transaction pool measured in bytes. If not set, the size
     /// will be unbounded.
-    /// New transactions that bring the size of the pool over this limit will be rejected. This
-    /// guarantees that the node will use bounded resources to store incoming transactions.
+    /// New transactions that bring the size of the pool over this limit will be rejected, unless
+    /// transactions with less gas attached to function calls can be evicted to make room for
+    /// them. This guarantees that the node will use bounded resources to store incoming
+    /// transactions.
     /// Setting this value too low (<1MB) on the validator might lead to production of smaller
     /// chunks and underutilizing the capacity of the network.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Limit of the number of transactions of a single signer account in the
+    /// per-shard transaction pool, so that a few accounts can't fill it. If
+    /// not set, the number is unbounded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_pool_account_count_limit: Option<usize>,
+    /// Limit of the size of the transactions of a single signer account in
+    /// the per-shard transaction pool, in bytes. If not set, the size is
+    /// unbounded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_pool_account_size_limit: Option<u64>,
+    /// Limit of the number of transactions of a single signer account held
+    /// back in the per-shard transaction pool because they skip nonces after
+    /// the pending transactions of their access key, until the transactions
+    /// with the missing nonces arrive or get included, instead of including
+    /// them first and making the missing ones invalid. If not set,
+    /// transactions are never held back.
+    pub transaction_pool_future_nonce_limit: Option<usize>,
+    /// Maximum number of transactions of a single account in a chunk. Only
+    /// applies since the `TransactionPriority` protocol feature, when chunk
+    /// producers pick the transactions with the most attached gas first.
+    pub produce_chunk_max_transactions_per_account: usize,
     // Configuration for resharding.
     pub resharding_config: ReshardingConfig,
     /// If the node is not a chunk producer within that many blocks, then route
@@ -316,6 +376,113 @@ pub struct Config {
     /// some limit is reached. This time limit ensures that adding transactions won't take
     /// longer than the specified duration, which helps to produce the chunk quickly.
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+    /// Soft deadline for producing a chunk, measured from the start of its
+    /// production. Adding transactions stops early enough to meet it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produce_chunk_soft_deadline: Option<Duration>,
+    /// If set, chunk validators tracking the shard read trie nodes missing
+    /// from a chunk state witness from their own storage instead of failing
+    /// validation. Such witnesses are still reported as incomplete and the
+    /// chunk is not endorsed.
+    #[serde(skip_serializing_if = "is_false")]
+    pub chunk_validation_db_fallback: bool,
+    /// If set, chunk validators tracking the shard execute chunks both from
+    /// their own state and from the state recorded in the witness, and count
+    /// any differences in the `near_chunk_execution_determinism_checks_total`
+    /// metric. This guards against nondeterminism in the runtime, at the cost
+    /// of executing each chunk twice.
+    #[serde(skip_serializing_if = "is_false")]
+    pub chunk_validation_determinism_check: bool,
+    /// If set, when validation of a chunk state witness of a tracked shard
+    /// fails, chunk validators validate the chunk once more from their own
+    /// state. If that succeeds, the state recorded in the witness diverged
+    /// from the actual state, which is reported as an error and counted in
+    /// the `near_chunk_validation_recorded_storage_divergences_total` metric
+    /// instead of treating the witness as invalid.
+    #[serde(skip_serializing_if = "is_false")]
+    pub chunk_validation_stateful_fallback: bool,
+    /// Collect a detailed gas and IO profile of every receipt executed by the
+    /// node and persist it in `DBCol::ReceiptProfiles`. The profiles are
+    /// returned by `EXPERIMENTAL_tx_status` and garbage collected together
+    /// with the outcomes of the receipts.
+    #[serde(skip_serializing_if = "is_false")]
+    pub save_receipt_profiles: bool,
+    /// Chunk validators send their endorsements to the block producer of the
+    /// chunk's height and to the block producers of that many following
+    /// heights, which include the chunk if the former misses its slot.
+    pub chunk_endorsement_num_next_block_producers: u64,
+    /// Chunk state witnesses for chunks more than that many blocks below or
+    /// above the head are rejected without being processed.
+    pub chunk_witness_height_horizon: BlockHeightDelta,
+    /// Options of chunk validators which can be changed without a restart:
+    /// the maximum size of accepted state witnesses, the number of threads
+    /// validating them and how long endorsements are batched.
+    pub chunk_validator: ChunkValidatorConfig,
+    /// Maximum number of state witnesses of different shards produced in
+    /// parallel. Only matters for chunk producers tracking several shards.
+    pub chunk_witness_production_threads: usize,
+    /// If set, when chunks of a shard are not included in blocks, chunk
+    /// producers send the chunk validators of the previous witness only the
+    /// implicit transitions of the blocks since then, instead of a full
+    /// witness.
+    #[serde(skip_serializing_if = "is_false")]
+    pub incremental_chunk_state_witnesses: bool,
+    /// If set, chunk validators append a summary record of every state witness
+    /// they validate (chunk, size, validation time and result) to this file,
+    /// as JSON lines. Relative paths are relative to the home directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_state_witness_analytics_file: Option<PathBuf>,
+    /// If set, chunk state witnesses are sent to remote workers for
+    /// validation. Requires the `remote_chunk_validation` feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_chunk_validation: Option<RemoteChunkValidationConfig>,
+    /// If set, the node alerts through a webhook or a status file when its
+    /// validator misses too many blocks or chunks in an epoch. A relative
+    /// `status_file` is relative to the home directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub production_alerts: Option<ProductionAlertsConfig>,
+    /// If set, the health, sync status, head, validator role and shards of
+    /// the node are written as JSON to `path` every `period`, replacing the
+    /// file atomically. A relative `path` is relative to the home directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_status_file: Option<NodeStatusFileConfig>,
+    /// If set, the node opens the database of another node running on the
+    /// same machine, at `store.path`, in secondary mode and serves read-only
+    /// RPC from it while the other node keeps running. The node doesn't
+    /// connect to the network and never writes to the database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_replica: Option<ReadReplicaConfig>,
+    /// If set, the contracts called by the transactions and receipts of a
+    /// chunk are compiled on background threads as soon as the chunk is
+    /// received, so that the compilation doesn't delay the application of
+    /// the chunk.
+    #[serde(skip_serializing_if = "is_false")]
+    pub speculative_contract_compilation: bool,
+    /// If set, the node starts with its validator key detached: it follows
+    /// the chain but doesn't produce blocks or chunks or sign approvals and
+    /// chunk endorsements until the key is attached through the
+    /// `/admin/validator_key/attach` endpoint of the RPC. Meant for backup
+    /// nodes holding the key of a running validator.
+    #[serde(skip_serializing_if = "is_false")]
+    pub validator_key_detached: bool,
+    /// If set, the node coordinates with another node holding the same
+    /// validator key through heartbeats, so that exactly one of them signs at
+    /// any time and the other takes over within `failover_timeout` when it
+    /// fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validator_failover: Option<ValidatorFailoverConfig>,
+    /// If set, every new chunk is applied a second time with an alternate
+    /// configuration, e.g. without memtries, and the timings of both
+    /// applications are reported in metrics. Meant for canary nodes only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow_apply: Option<ShadowApplyConfig>,
+    /// If set, the blocks, chunks, approvals, chunk state witnesses and chunk
+    /// endorsements received by the node are appended to this file with the
+    /// time they were received, so that `neard replay` can feed them to a copy
+    /// of the node to reproduce a bug. Relative paths are relative to the home
+    /// directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consensus_log_file: Option<PathBuf>,
 }
 
 fn is_false(value: &bool) -> bool {
@@ -327,11 +494,15 @@ impl Default for Config {
             genesis_file: GENESIS_CONFIG_FILENAME.to_string(),
             genesis_records_file: None,
             validator_key_file: VALIDATOR_KEY_FILE.to_string(),
+            validator_next_key_file: None,
+            remote_signer: None,
             node_key_file: NODE_KEY_FILE.to_string(),
             #[cfg(feature = "json_rpc")]
             rpc: Some(RpcConfig::default()),
             #[cfg(feature = "rosetta_rpc")]
             rosetta_rpc: None,
+            #[cfg(feature = "grpc")]
+            grpc: None,
             telemetry: TelemetryConfig::default(),
             network: Default::default(),
             consensus: Consensus::default(),
@@ -352,14 +523,41 @@ impl Default for Config {
             cold_store: None,
             split_storage: None,
             expected_shutdown: None,
+            maintenance_window: None,
             state_sync: default_state_sync(),
             state_sync_enabled: default_state_sync_enabled(),
             transaction_pool_size_limit: default_transaction_pool_size_limit(),
+            transaction_pool_account_count_limit: None,
+            transaction_pool_account_size_limit: None,
+            transaction_pool_future_nonce_limit: default_transaction_pool_future_nonce_limit(),
+            produce_chunk_max_transactions_per_account:
+                default_produce_chunk_max_transactions_per_account(),
             enable_multiline_logging: default_enable_multiline_logging(),
             resharding_config: ReshardingConfig::default(),
             tx_routing_height_horizon: default_tx_routing_height_horizon(),
             produce_chunk_add_transactions_time_limit:
                 default_produce_chunk_add_transactions_time_limit(),
+            produce_chunk_soft_deadline: None,
+            chunk_validation_db_fallback: false,
+            chunk_validation_determinism_check: false,
+            chunk_validation_stateful_fallback: false,
+            save_receipt_profiles: false,
+            chunk_endorsement_num_next_block_producers:
+                default_chunk_endorsement_num_next_block_producers(),
+            chunk_witness_height_horizon: default_chunk_witness_height_horizon(),
+            chunk_validator: ChunkValidatorConfig::default(),
+            chunk_witness_production_threads: default_chunk_witness_production_threads(),
+            incremental_chunk_state_witnesses: false,
+            chunk_state_witness_analytics_file: None,
+            remote_chunk_validation: None,
+            production_alerts: None,
+            node_status_file: None,
+            read_replica: None,
+            speculative_contract_compilation: false,
+            validator_key_detached: false,
+            validator_failover: None,
+            shadow_apply: None,
+            consensus_log_file: None,
         }
     }
 }
@@ -587,6 +785,8 @@ pub struct NearConfig {
     pub rpc_config: Option<RpcConfig>,
     #[cfg(feature = "rosetta_rpc")]
     pub rosetta_rpc_config: Option<RosettaRpcConfig>,
+    #[cfg(feature = "grpc")]
+    pub grpc_config: Option<GrpcConfig>,
     pub telemetry_config: TelemetryConfig,
     pub genesis: Genesis,
     pub validator_signer: Option<Arc<dyn ValidatorSigner>>,
@@ -599,6 +799,14 @@ impl NearConfig {
         network_key_pair: KeyFile,
         validator_signer: Option<Arc<dyn ValidatorSigner>>,
     ) -> anyhow::Result<Self> {
+        let mut network_config = NetworkConfig::new(
+            config.network.clone(),
+            network_key_pair.secret_key,
+            validator_signer.clone(),
+            config.archive,
+        )?;
+        network_config.max_chunk_state_witness_size =
+            Some(genesis.config.max_chunk_state_witness_size as usize);
         Ok(NearConfig {
             config: config.clone(),
             client_config: ClientConfig {
@@ -609,6 +817,10 @@ impl NearConfig {
                     config.expected_shutdown,
                     "expected_shutdown",
                 ),
+                maintenance_window: MutableConfigValue::new(
+                    config.maintenance_window,
+                    "maintenance_window",
+                ),
                 block_production_tracking_delay: config.consensus.block_production_tracking_delay,
                 min_block_production_delay: config.consensus.min_block_production_delay,
                 max_block_production_delay: config.consensus.max_block_production_delay,
@@ -624,6 +836,9 @@ impl NearConfig {
                     .consensus
                     .header_sync_expected_height_per_second,
                 state_sync_timeout: config.consensus.state_sync_timeout,
+                state_sync_num_concurrent_requests: config
+                    .consensus
+                    .state_sync_num_concurrent_requests,
                 min_num_peers: config.consensus.min_num_peers,
                 log_summary_period: config.log_summary_period,
                 produce_empty_blocks: config.consensus.produce_empty_blocks,
@@ -655,6 +870,11 @@ impl NearConfig {
                 state_sync_enabled: config.state_sync_enabled,
                 state_sync: config.state_sync.unwrap_or_default(),
                 transaction_pool_size_limit: config.transaction_pool_size_limit,
+                transaction_pool_account_count_limit: config.transaction_pool_account_count_limit,
+                transaction_pool_account_size_limit: config.transaction_pool_account_size_limit,
+                transaction_pool_future_nonce_limit: config.transaction_pool_future_nonce_limit,
+                produce_chunk_max_transactions_per_account: config
+                    .produce_chunk_max_transactions_per_account,
                 enable_multiline_logging: config.enable_multiline_logging.unwrap_or(true),
                 resharding_config: MutableConfigValue::new(
                     config.resharding_config,
@@ -665,18 +885,42 @@ impl NearConfig {
                     config.produce_chunk_add_transactions_time_limit,
                     "produce_chunk_add_transactions_time_limit",
                 ),
+                produce_chunk_soft_deadline: MutableConfigValue::new(
+                    config.produce_chunk_soft_deadline,
+                    "produce_chunk_soft_deadline",
+                ),
+                save_state_witnesses: config.store.save_state_witnesses,
+                save_receipt_origins: config.store.save_receipt_origins || config.archive,
+                save_receipt_profiles: config.save_receipt_profiles,
+                chunk_validation_db_fallback: config.chunk_validation_db_fallback,
+                chunk_validation_determinism_check: config.chunk_validation_determinism_check,
+                chunk_validation_stateful_fallback: config.chunk_validation_stateful_fallback,
+                chunk_endorsement_num_next_block_producers: config
+                    .chunk_endorsement_num_next_block_producers,
+                chunk_witness_height_horizon: config.chunk_witness_height_horizon,
+                chunk_validator: MutableConfigValue::new(config.chunk_validator, "chunk_validator"),
+                chunk_witness_production_threads: config.chunk_witness_production_threads,
+                incremental_chunk_state_witnesses: config.incremental_chunk_state_witnesses,
+                chunk_state_witness_analytics_file: config.chunk_state_witness_analytics_file,
+                remote_chunk_validation: config.remote_chunk_validation,
+                production_alerts: config.production_alerts,
+                node_status_file: config.node_status_file,
+                doomslug_adaptive_timers: config.consensus.doomslug_adaptive_timers,
+                read_replica: config.read_replica,
+                speculative_contract_compilation: config.speculative_contract_compilation,
+                validator_key_detached: config.validator_key_detached,
+                validator_failover: config.validator_failover,
+                shadow_apply: config.shadow_apply,
+                consensus_log_file: config.consensus_log_file,
             },
-            network_config: NetworkConfig::new(
-                config.network,
-                network_key_pair.secret_key,
-                validator_signer.clone(),
-                config.archive,
-            )?,
+            network_config,
             telemetry_config: config.telemetry,
             #[cfg(feature = "json_rpc")]
             rpc_config: config.rpc,
             #[cfg(feature = "rosetta_rpc")]
             rosetta_rpc_config: config.rosetta_rpc,
+            #[cfg(feature = "grpc")]
+            grpc_config: config.grpc,
             genesis,
             validator_signer,
         })
@@ -1348,6 +1592,34 @@ impl From<NodeKeyFile> for KeyFile {
     }
 }
 
+/// Loads the validator signer. If a remote signer is configured, signing is
+/// delegated to it, with the validator key file (if present) serving as the
+/// fallback key. Otherwise the signer is loaded from the validator key file
+/// and, if configured, the key file of the key the validator is rotating to.
+/// Returns `None` if the node has no validator key.
+pub fn load_validator_signer(
+    dir: &Path,
+    config: &Config,
+) -> std::io::Result<Option<Arc<dyn ValidatorSigner>>> {
+    let validator_file = dir.join(&config.validator_key_file);
+    if let Some(remote_signer) = &config.remote_signer {
+        let fallback = if validator_file.exists() {
+            Some(InMemorySigner::from_file(&validator_file)?)
+        } else {
+            None
+        };
+        let signer = RemoteValidatorSigner::new(remote_signer.clone(), fallback)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+        return Ok(Some(Arc::new(signer)));
+    }
+    if !validator_file.exists() {
+        return Ok(None);
+    }
+    let next_key_file = config.validator_next_key_file.as_ref().map(|file| dir.join(file));
+    let signer = InMemoryValidatorSigner::from_files(&validator_file, next_key_file.as_deref())?;
+    Ok(Some(Arc::new(signer)))
+}
+
 pub fn load_config(
     dir: &Path,
     genesis_validation: GenesisValidationMode,
@@ -1361,21 +1633,16 @@ pub fn load_config(
         validation_errors.push_errors(e)
     };
 
-    let validator_file = dir.join(&config.validator_key_file);
-    let validator_signer = if validator_file.exists() {
-        match InMemoryValidatorSigner::from_file(&validator_file) {
-            Ok(signer) => Some(Arc::new(signer) as Arc<dyn ValidatorSigner>),
-            Err(_) => {
-                let error_message = format!(
-                    "Failed initializing validator signer from {}",
-                    validator_file.display()
-                );
-                validation_errors.push_validator_key_file_error(error_message);
-                None
-            }
+    let validator_signer = match load_validator_signer(dir, &config) {
+        Ok(signer) => signer,
+        Err(_) => {
+            let error_message = format!(
+                "Failed initializing validator signer from {}",
+                dir.join(&config.validator_key_file).display()
+            );
+            validation_errors.push_validator_key_file_error(error_message);
+            None
         }
-    } else {
-        None
     };
 
     let node_key_path = dir.join(&config.node_key_file);
@@ -1599,9 +1866,21 @@ fn test_config_from_file_skip_validation() {
         // values is probably not worth it but there may be some other defaults
         // we want to ensure that they happen.
         let want_gc = if has_gc {
-            GCConfig { gc_blocks_limit: 42, gc_fork_clean_step: 420, gc_num_epochs_to_keep: 24 }
+            GCConfig {
+                gc_blocks_limit: 42,
+                gc_fork_clean_step: 420,
+                gc_num_epochs_to_keep: 24,
+                gc_block_headers: false,
+                gc_untracked_shards: false,
+            }
         } else {
-            GCConfig { gc_blocks_limit: 2, gc_fork_clean_step: 100, gc_num_epochs_to_keep: 5 }
+            GCConfig {
+                gc_blocks_limit: 2,
+                gc_fork_clean_step: 100,
+                gc_num_epochs_to_keep: 5,
+                gc_block_headers: false,
+                gc_untracked_shards: false,
+            }
         };
         assert_eq!(want_gc, config.gc);
 
@@ -1,7 +1,9 @@
 use near_chain_configs::{ExternalStorageLocation, SyncConfig};
 use near_config_utils::{ValidationError, ValidationErrors};
+use near_store::{DBCol, StoreBackend};
 use std::collections::HashSet;
 use std::path::Path;
+use strum::IntoEnumIterator;
 
 use crate::config::Config;
 
@@ -65,6 +67,13 @@ impl<'a> ConfigValidator<'a> {
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        if let Some(adaptive_timers) = &self.config.consensus.doomslug_adaptive_timers {
+            if adaptive_timers.window_size == 0 || adaptive_timers.latency_multiplier == 0 {
+                let error_message = format!("consensus.doomslug_adaptive_timers values should all be greater than 0, but window_size is {}, latency_multiplier is {}.", adaptive_timers.window_size, adaptive_timers.latency_multiplier);
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
         if self.config.consensus.header_sync_expected_height_per_second == 0 {
             let error_message =
                 "consensus.header_sync_expected_height_per_second should not be 0".to_string();
@@ -79,6 +88,42 @@ impl<'a> ConfigValidator<'a> {
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        if let Some(tracked_shard_schedule) = &self.config.tracked_shard_schedule {
+            if tracked_shard_schedule.is_empty() {
+                let error_message = "tracked_shard_schedule should not be empty".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if !self.config.tracked_shards.is_empty() {
+                let error_message = "tracked_shard_schedule can't be set together with tracked_shards, which makes the node track all shards".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
+        if self.config.archive && self.config.gc.gc_block_headers {
+            let error_message =
+                "gc.gc_block_headers is not supported by archival nodes".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
+        let store_configs =
+            [("store", Some(&self.config.store)), ("cold_store", self.config.cold_store.as_ref())];
+        for (name, store_config) in store_configs {
+            let Some(store_config) = store_config else { continue };
+            for col_name in store_config.column_configs.keys() {
+                if !DBCol::iter().any(|col| <&str>::from(col) == col_name) {
+                    let error_message =
+                        format!("{name}.column_configs contains unknown column {col_name}");
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+        }
+
+        if self.config.store.state_snapshot_config.num_retained_snapshots == 0 {
+            let error_message =
+                "store.state_snapshot_config.num_retained_snapshots should not be 0".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
         if let Some(state_sync) = &self.config.state_sync {
             if let Some(dump_config) = &state_sync.dump {
                 if let Some(restart_dump_for_shards) = &dump_config.restart_dump_for_shards {
@@ -148,6 +193,26 @@ impl<'a> ConfigValidator<'a> {
             }
         }
 
+        if self.config.consensus.state_sync_num_concurrent_requests == 0 {
+            let error_message = format!(
+                "'config.consensus.state_sync_num_concurrent_requests' needs to be greater than 0"
+            );
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
+        if let Some(read_replica) = &self.config.read_replica {
+            if read_replica.catch_up_period.is_zero() {
+                let error_message =
+                    "read_replica.catch_up_period needs to be greater than 0".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if self.config.store.backend != StoreBackend::RocksDb {
+                let error_message =
+                    "read_replica is only supported with the RocksDb store backend".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
         let tx_routing_height_horizon = self.config.tx_routing_height_horizon;
         if tx_routing_height_horizon < 2 {
             let error_message = format!("'config.tx_routing_height_horizon' needs to be at least 2, got {tx_routing_height_horizon}.");
@@ -183,6 +248,24 @@ mod test {
         validate_config(&config).unwrap();
     }
 
+    #[test]
+    #[should_panic(expected = "tracked_shard_schedule can't be set together with tracked_shards")]
+    fn test_tracked_shard_schedule_with_tracked_shards() {
+        let mut config = Config::default();
+        config.tracked_shards.push(0);
+        config.tracked_shard_schedule = Some(vec![vec![0], vec![1]]);
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "gc.gc_block_headers is not supported by archival nodes")]
+    fn test_archive_gc_block_headers() {
+        let mut config = Config::default();
+        config.archive = true;
+        config.gc.gc_block_headers = true;
+        validate_config(&config).unwrap();
+    }
+
     #[test]
     #[should_panic(
         expected = "Configuration with archive = false and save_trie_changes = false is not supported"
@@ -231,6 +314,28 @@ mod test {
         validate_config(&config).unwrap();
     }
 
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: store.column_configs contains unknown column NoSuchColumn"
+    )]
+    fn test_unknown_column_config() {
+        let mut config = Config::default();
+        config.store.column_configs.insert("State".to_string(), Default::default());
+        config.store.column_configs.insert("NoSuchColumn".to_string(), Default::default());
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: read_replica is only supported with the RocksDb store backend"
+    )]
+    fn test_read_replica_in_memory_backend() {
+        let mut config = Config::default();
+        config.read_replica = Some(Default::default());
+        config.store.backend = StoreBackend::InMemory;
+        validate_config(&config).unwrap();
+    }
+
     #[test]
     #[should_panic(
         expected = "\\nconfig.json semantic issue: 'config.tx_routing_height_horizon' needs to be at least 2, got 1."
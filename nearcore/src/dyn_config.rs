@@ -2,8 +2,10 @@ use crate::config::Config;
 use near_chain_configs::UpdateableClientConfig;
 use near_dyn_configs::{UpdateableConfigLoaderError, UpdateableConfigs};
 use near_o11y::log_config::LogConfig;
+use near_primitives::validator_signer::ValidatorSigner;
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 pub const LOG_CONFIG_FILENAME: &str = "log_config.json";
 
@@ -19,22 +21,33 @@ pub fn read_updateable_configs(
             None
         }
     };
-    let updateable_client_config =
-        match Config::from_file(&home_dir.join(crate::config::CONFIG_FILENAME))
-            .map(get_updateable_client_config)
-        {
-            Ok(config) => Some(config),
+    let (updateable_client_config, validator_signer) =
+        match Config::from_file(&home_dir.join(crate::config::CONFIG_FILENAME)) {
+            Ok(config) => {
+                let validator_signer = match read_validator_signer(home_dir, &config) {
+                    Ok(validator_signer) => validator_signer,
+                    Err(err) => {
+                        errs.push(err);
+                        None
+                    }
+                };
+                (Some(get_updateable_client_config(config)), validator_signer)
+            }
             Err(err) => {
                 errs.push(UpdateableConfigLoaderError::ConfigFileError {
                     file: PathBuf::from(crate::config::CONFIG_FILENAME),
                     err: err.into(),
                 });
-                None
+                (None, None)
             }
         };
     if errs.is_empty() {
         crate::metrics::CONFIG_CORRECT.set(1);
-        Ok(UpdateableConfigs { log_config, client_config: updateable_client_config })
+        Ok(UpdateableConfigs {
+            log_config,
+            client_config: updateable_client_config,
+            validator_signer,
+        })
     } else {
         tracing::warn!(target: "neard", "Dynamically updateable configs are not valid. Please fix this ASAP otherwise the node will be unable to restart: {:?}", &errs);
         crate::metrics::CONFIG_CORRECT.set(0);
@@ -47,8 +60,24 @@ pub fn get_updateable_client_config(config: Config) -> UpdateableClientConfig {
     // Keep this list in-sync with `core/dyn-configs/README.md`.
     UpdateableClientConfig {
         expected_shutdown: config.expected_shutdown,
+        maintenance_window: config.maintenance_window,
         resharding_config: config.resharding_config,
         produce_chunk_add_transactions_time_limit: config.produce_chunk_add_transactions_time_limit,
+        produce_chunk_soft_deadline: config.produce_chunk_soft_deadline,
+        chunk_validator: config.chunk_validator,
+    }
+}
+
+fn read_validator_signer(
+    home_dir: &Path,
+    config: &Config,
+) -> Result<Option<Arc<dyn ValidatorSigner>>, UpdateableConfigLoaderError> {
+    match crate::config::load_validator_signer(home_dir, config) {
+        Ok(validator_signer) => Ok(validator_signer),
+        Err(err) => Err(UpdateableConfigLoaderError::OpenAndRead {
+            file: home_dir.join(&config.validator_key_file),
+            err,
+        }),
     }
 }
 
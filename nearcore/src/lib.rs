@@ -1,6 +1,9 @@
+#[cfg(feature = "json_rpc")]
+use crate::compaction::CompactionHandlerImpl;
 pub use crate::config::{init_configs, load_config, load_test_config, NearConfig, NEAR_BASE};
 use crate::entity_debug::EntityDebugHandlerImpl;
 use crate::metrics::spawn_trie_metrics_loop;
+use crate::read_replica::spawn_catch_up_loop;
 pub use crate::runtime::NightshadeRuntime;
 
 use crate::cold_storage::spawn_cold_store_loop;
@@ -21,7 +24,10 @@ use near_chain_configs::ReshardingHandle;
 use near_chain_configs::SyncConfig;
 use near_chunks::shards_manager_actor::start_shards_manager;
 use near_client::sync::adapter::SyncAdapter;
-use near_client::{start_client, start_view_client, ClientActor, ConfigUpdater, ViewClientActor};
+use near_client::{
+    start_chunk_witness_actor, start_client, start_view_client, ChunkEndorsementPool,
+    ChunkWitnessProductionTracker, ClientActor, ConfigUpdater, ValidatorAccountId, ViewClientActor,
+};
 use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
 use near_epoch_manager::EpochManager;
 use near_network::PeerManagerActor;
@@ -39,6 +45,8 @@ use tracing::info;
 
 pub mod append_only_map;
 pub mod cold_storage;
+#[cfg(feature = "json_rpc")]
+mod compaction;
 pub mod config;
 mod config_validate;
 mod download_file;
@@ -48,6 +56,9 @@ mod entity_debug;
 mod entity_debug_serializer;
 mod metrics;
 pub mod migrations;
+mod read_replica;
+pub mod remote_signer;
+pub mod replay;
 mod runtime;
 pub mod state_sync;
 pub mod test_utils;
@@ -76,8 +87,11 @@ pub fn get_default_home() -> PathBuf {
 /// The end goal is to get rid of `archive` option in `config.json` file and
 /// have the type of the node be determined purely based on kind of database
 /// being opened.
+///
+/// A read replica opens the existing database of another node in
+/// [`Mode::Secondary`], without performing migrations.
 pub fn open_storage(home_dir: &Path, near_config: &mut NearConfig) -> anyhow::Result<NodeStorage> {
-    let migrator = migrations::Migrator::new(near_config);
+    let migrator = migrations::Migrator::new(near_config, home_dir);
     let opener = NodeStorage::opener(
         home_dir,
         near_config.client_config.archive,
@@ -85,13 +99,20 @@ pub fn open_storage(home_dir: &Path, near_config: &mut NearConfig) -> anyhow::Re
         near_config.config.cold_store.as_ref(),
     )
     .with_migrator(&migrator);
-    let storage = match opener.open() {
+    let mode = if near_config.client_config.read_replica.is_some() {
+        Mode::Secondary
+    } else {
+        Mode::ReadWrite
+    };
+    let storage = match opener.open_in_mode(mode) {
         Ok(storage) => Ok(storage),
         Err(StoreOpenerError::IO(err)) => {
             Err(anyhow::anyhow!("{err}"))
         }
-        // Cannot happen with Mode::ReadWrite
-        Err(StoreOpenerError::DbDoesNotExist) => unreachable!(),
+        // Only happens with Mode::Secondary
+        Err(StoreOpenerError::DbDoesNotExist) => {
+            Err(anyhow::anyhow!("The database of the primary node doesn't exist"))
+        }
         // Cannot happen with Mode::ReadWrite
         Err(StoreOpenerError::DbAlreadyExists) => unreachable!(),
         Err(StoreOpenerError::HotColdExistenceMismatch) => {
@@ -142,8 +163,13 @@ pub fn open_storage(home_dir: &Path, near_config: &mut NearConfig) -> anyhow::Re
                  by adding tilde to its name) and starting the node.  If that \
                  works, the snapshot can be deleted."))
         }
-        // Cannot happen with Mode::ReadWrite
-        Err(StoreOpenerError::DbVersionMismatchOnRead { .. }) => unreachable!(),
+        // Only happens with Mode::Secondary
+        Err(StoreOpenerError::DbVersionMismatchOnRead { got, want }) => {
+            Err(anyhow::anyhow!(
+                "Database version {got} of the primary node doesn't match the expected version {want}. \
+                The read replica must run the same version of neard as the primary node."
+            ))
+        }
         // Cannot happen when migrator is specified.
         Err(StoreOpenerError::DbVersionMismatch { .. }) => unreachable!(),
         Err(StoreOpenerError::DbVersionMissing { .. }) => {
@@ -213,7 +239,8 @@ pub struct NearNode {
     pub state_sync_dump_handle: Option<StateSyncDumpHandle>,
     /// A handle to control background flat state values inlining migration.
     /// Needed temporarily, will be removed after the migration is completed.
-    pub flat_state_migration_handle: FlatStateValuesInliningMigrationHandle,
+    /// Not set on read replicas.
+    pub flat_state_migration_handle: Option<FlatStateValuesInliningMigrationHandle>,
     // A handle that allows the main process to interrupt resharding if needed.
     // This typically happens when the main process is interrupted.
     pub resharding_handle: ReshardingHandle,
@@ -232,6 +259,23 @@ pub fn start_with_config_and_synchronization(
     config_updater: Option<ConfigUpdater>,
 ) -> anyhow::Result<NearNode> {
     let storage = open_storage(home_dir, &mut config)?;
+    if let Some(path) = &mut config.client_config.chunk_state_witness_analytics_file {
+        *path = home_dir.join(&*path);
+    }
+    if let Some(path) = config
+        .client_config
+        .production_alerts
+        .as_mut()
+        .and_then(|production_alerts| production_alerts.status_file.as_mut())
+    {
+        *path = home_dir.join(&*path);
+    }
+    if let Some(node_status_file) = &mut config.client_config.node_status_file {
+        node_status_file.path = home_dir.join(&node_status_file.path);
+    }
+    if let Some(path) = &mut config.client_config.consensus_log_file {
+        *path = home_dir.join(&*path);
+    }
     let db_metrics_arbiter = if config.client_config.enable_statistics_export {
         let period = config.client_config.log_summary_period;
         let db_metrics_arbiter_handle = spawn_db_metrics_loop(&storage, period)?;
@@ -284,7 +328,12 @@ pub fn start_with_config_and_synchronization(
             (epoch_manager.clone(), shard_tracker.clone(), runtime.clone())
         };
 
-    let cold_store_loop_handle = spawn_cold_store_loop(&config, &storage, epoch_manager.clone())?;
+    let read_replica = config.client_config.read_replica.clone();
+    let cold_store_loop_handle = if read_replica.is_none() {
+        spawn_cold_store_loop(&config, &storage, epoch_manager.clone())?
+    } else {
+        None
+    };
 
     let telemetry = TelemetryActor::new(config.telemetry_config.clone()).start();
     let chain_genesis = ChainGenesis::new(&config.genesis);
@@ -308,15 +357,20 @@ pub fn start_with_config_and_synchronization(
     let shards_manager_adapter = Arc::new(LateBoundSender::default());
     let client_adapter_for_shards_manager = Arc::new(LateBoundSender::default());
     let adv = near_client::adversarial::Controls::new(config.client_config.archive);
+    let chunk_endorsements = ChunkEndorsementPool::default();
+    let validator_account_id = ValidatorAccountId::new(
+        config.validator_signer.as_ref().map(|signer| signer.validator_id().clone()),
+    );
 
     let view_client = start_view_client(
-        config.validator_signer.as_ref().map(|signer| signer.validator_id().clone()),
+        validator_account_id.clone(),
         chain_genesis.clone(),
         view_epoch_manager.clone(),
         view_shard_tracker,
         view_runtime.clone(),
         network_adapter.clone().into(),
         config.client_config.clone(),
+        chunk_endorsements.clone(),
         adv.clone(),
     );
 
@@ -333,6 +387,16 @@ pub fn start_with_config_and_synchronization(
         get_make_snapshot_callback(state_snapshot_actor, runtime.get_flat_storage_manager());
     let snapshot_callbacks = SnapshotCallbacks { make_snapshot_callback, delete_snapshot_callback };
 
+    let chunk_witness_production_tracker = ChunkWitnessProductionTracker::default();
+    let (chunk_witness_actor, chunk_witness_arbiter_handle) = start_chunk_witness_actor(
+        &config.client_config,
+        &chain_genesis,
+        epoch_manager.clone(),
+        runtime.store().clone(),
+        network_adapter.as_sender(),
+        chunk_witness_production_tracker.clone(),
+        adv.clone(),
+    );
     let (client_actor, client_arbiter_handle, resharding_handle) = start_client(
         config.client_config.clone(),
         chain_genesis.clone(),
@@ -343,6 +407,10 @@ pub fn start_with_config_and_synchronization(
         sync_adapter,
         network_adapter.clone().into(),
         shards_manager_adapter.as_sender(),
+        chunk_witness_actor.with_auto_span_context().into_sender(),
+        chunk_witness_production_tracker,
+        chunk_endorsements,
+        validator_account_id,
         config.validator_signer.clone(),
         telemetry,
         Some(snapshot_callbacks),
@@ -365,52 +433,77 @@ pub fn start_with_config_and_synchronization(
     );
     shards_manager_adapter.bind(shards_manager_actor.with_auto_span_context());
 
-    let flat_state_migration_handle =
-        FlatStateValuesInliningMigrationHandle::start_background_migration(
-            storage.get_hot_store(),
-            runtime.get_flat_storage_manager(),
-            config.client_config.client_background_migration_threads,
-        );
-
-    let state_sync_dump_handle = spawn_state_sync_dump(
-        &config.client_config,
-        chain_genesis,
-        epoch_manager,
-        shard_tracker,
-        runtime,
-        config.validator_signer.as_ref().map(|signer| signer.validator_id().clone()),
-    )?;
+    let (flat_state_migration_handle, state_sync_dump_handle) = if read_replica.is_none() {
+        let flat_state_migration_handle =
+            FlatStateValuesInliningMigrationHandle::start_background_migration(
+                storage.get_hot_store(),
+                runtime.get_flat_storage_manager(),
+                config.client_config.client_background_migration_threads,
+            );
+        let state_sync_dump_handle = spawn_state_sync_dump(
+            &config.client_config,
+            chain_genesis,
+            epoch_manager,
+            shard_tracker,
+            runtime,
+            config.validator_signer.as_ref().map(|signer| signer.validator_id().clone()),
+        )?;
+        (Some(flat_state_migration_handle), state_sync_dump_handle)
+    } else {
+        (None, None)
+    };
 
     let hot_store = storage.get_hot_store();
+    let cold_store = storage.get_cold_store();
+    let catch_up_arbiter = match &read_replica {
+        Some(read_replica) => Some(spawn_catch_up_loop(&storage, read_replica.catch_up_period)?),
+        None => None,
+    };
 
     let mut rpc_servers = Vec::new();
-    let network_actor = PeerManagerActor::spawn(
-        time::Clock::real(),
-        storage.into_inner(near_store::Temperature::Hot),
-        config.network_config,
-        Arc::new(near_client::adapter::Adapter::new(client_actor.clone(), view_client.clone())),
-        shards_manager_adapter.as_sender(),
-        genesis_id,
-    )
-    .context("PeerManager::spawn()")?;
-    network_adapter.bind(network_actor.clone().with_auto_span_context());
-    if let SyncConfig::Peers = config.client_config.state_sync.sync {
-        network_adapter_for_sync.bind(network_actor.clone().with_auto_span_context())
-    }
+    let network_actor = if read_replica.is_none() {
+        let network_actor = PeerManagerActor::spawn(
+            time::Clock::real(),
+            storage.into_inner(near_store::Temperature::Hot),
+            config.network_config,
+            Arc::new(near_client::adapter::Adapter::new(client_actor.clone(), view_client.clone())),
+            shards_manager_adapter.as_sender(),
+            genesis_id,
+        )
+        .context("PeerManager::spawn()")?;
+        network_adapter.bind(network_actor.clone().with_auto_span_context());
+        if let SyncConfig::Peers = config.client_config.state_sync.sync {
+            network_adapter_for_sync.bind(network_actor.clone().with_auto_span_context())
+        }
+        Some(network_actor)
+    } else {
+        // A read replica doesn't connect to the network. The senders are bound
+        // to an address whose mailbox is dropped right away, so that messages
+        // sent to the network are discarded instead of blocking the sender.
+        let network_actor = actix::Context::<PeerManagerActor>::new().address();
+        network_adapter.bind(network_actor.clone().with_auto_span_context());
+        network_adapter_for_sync.bind(network_actor.with_auto_span_context());
+        None
+    };
     #[cfg(feature = "json_rpc")]
-    if let Some(rpc_config) = config.rpc_config {
+    if let Some(mut rpc_config) = config.rpc_config {
+        if let Some(admin_config) = &mut rpc_config.admin_config {
+            admin_config.token_file = home_dir.join(&admin_config.token_file);
+        }
         let entity_debug_handler = EntityDebugHandlerImpl {
             epoch_manager: view_epoch_manager,
             runtime: view_runtime,
-            store: hot_store,
+            store: hot_store.clone(),
         };
+        let compaction_handler = CompactionHandlerImpl::new(hot_store, cold_store);
         rpc_servers.extend(near_jsonrpc::start_http(
             rpc_config,
             config.genesis.config.clone(),
             client_actor.clone(),
             view_client.clone(),
-            Some(network_actor),
+            network_actor,
             Arc::new(entity_debug_handler),
+            Arc::new(compaction_handler),
         ));
     }
 
@@ -428,15 +521,27 @@ pub fn start_with_config_and_synchronization(
         ));
     }
 
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_config) = config.grpc_config {
+        near_grpc::start_grpc(grpc_config, view_client.clone());
+    }
+
     rpc_servers.shrink_to_fit();
 
     tracing::trace!(target: "diagnostic", key = "log", "Starting NEAR node with diagnostic activated");
 
-    let mut arbiters =
-        vec![client_arbiter_handle, shards_manager_arbiter_handle, trie_metrics_arbiter];
+    let mut arbiters = vec![
+        client_arbiter_handle,
+        shards_manager_arbiter_handle,
+        chunk_witness_arbiter_handle,
+        trie_metrics_arbiter,
+    ];
     if let Some(db_metrics_arbiter) = db_metrics_arbiter {
         arbiters.push(db_metrics_arbiter);
     }
+    if let Some(catch_up_arbiter) = catch_up_arbiter {
+        arbiters.push(catch_up_arbiter);
+    }
 
     Ok(NearNode {
         client: client_actor,
@@ -1,12 +1,12 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use actix_rt::ArbiterHandle;
 use near_chain::{Block, ChainStore, ChainStoreAccess};
 use near_epoch_manager::EpochManager;
 use near_o11y::metrics::{
     exponential_buckets, linear_buckets, processing_time_buckets, try_create_histogram_vec,
-    try_create_int_counter_vec, try_create_int_gauge, try_create_int_gauge_vec, HistogramVec,
-    IntCounterVec, IntGauge, IntGaugeVec,
+    try_create_int_counter, try_create_int_counter_vec, try_create_int_gauge,
+    try_create_int_gauge_vec, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
 };
 
 use near_primitives::{shard_layout::ShardLayout, state_record::StateRecord, trie_key};
@@ -25,6 +25,14 @@ pub static APPLYING_CHUNKS_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static SPECULATIVELY_COMPILED_CONTRACTS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_speculatively_compiled_contracts_total",
+        "Number of contracts compiled ahead of the application of the chunks calling them",
+    )
+    .unwrap()
+});
+
 pub(crate) static APPLY_CHUNK_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_apply_chunk_delay_seconds",
@@ -65,6 +73,16 @@ pub(crate) static PREPARE_TX_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static PREPARE_TX_STORAGE_PROOF_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_prepare_tx_storage_proof_size",
+        "Size of the state recorded while preparing transactions per produced chunk, as a histogram",
+        &["shard_id"],
+        Some(exponential_buckets(1_000., 2., 20).unwrap()),
+    )
+    .unwrap()
+});
+
 pub(crate) static CONFIG_CORRECT: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge(
         "near_config_correct",
@@ -82,6 +100,15 @@ pub(crate) static COLD_STORE_COPY_RESULT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static DATABASE_COMPACTION_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_database_compaction_bytes_total",
+        "Approximate size of the database ranges compacted on request while the node is running",
+        &["column"],
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_DUMP_ITERATION_ELAPSED: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_state_sync_dump_iteration_elapsed_sec",
@@ -220,7 +247,7 @@ fn get_postponed_receipt_count_for_shard(
     let chunk_extra = chain_store.get_chunk_extra(block.hash(), &shard_uid)?;
     let state_root = chunk_extra.state_root();
     let storage = TrieDBStorage::new(store.clone(), shard_uid);
-    let storage = Rc::new(storage);
+    let storage = Arc::new(storage);
     let flat_storage_chunk_view = None;
     let trie = Trie::new(storage, *state_root, flat_storage_chunk_view);
     get_postponed_receipt_count_for_trie(trie)
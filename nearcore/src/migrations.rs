@@ -1,11 +1,24 @@
-use near_chain::{ChainStore, ChainStoreAccess};
+use crate::NightshadeRuntime;
+use near_chain::chain::collect_receipts_from_response;
+use near_chain::migrations::check_if_block_is_first_with_chunk_of_version;
+use near_chain::types::{
+    ApplyChunkShardContext, RuntimeAdapter, RuntimeStorageConfig, StorageDataSource,
+};
+use near_chain::{Chain, ChainStore, ChainStoreAccess};
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_primitives::block::Block;
+use near_primitives::checked_feature;
+use near_primitives::chunk_validation::StoredChunkStateTransitionData;
 use near_primitives::receipt::ReceiptResult;
 use near_primitives::runtime::migration_data::MigrationData;
-use near_primitives::types::Gas;
-use near_primitives::utils::index_to_bytes;
-use near_store::metadata::{DbVersion, DB_VERSION};
+use near_primitives::types::{Gas, ShardId};
+use near_primitives::utils::{get_block_shard_id, index_to_bytes};
+use near_store::metadata::{DbKind, DbVersion, DB_VERSION};
 use near_store::migrations::BatchedStoreUpdate;
 use near_store::{DBCol, Store};
+use std::collections::BTreeSet;
+use std::path::Path;
 
 /// Fix an issue with block ordinal (#5761)
 // This migration takes at least 3 hours to complete on mainnet
@@ -53,6 +66,191 @@ pub fn do_migrate_30_to_31(
     Ok(())
 }
 
+/// Migrates the database from version 38 to 39.
+///
+/// If `store.backfill_state_transition_data` is enabled, backfills
+/// `DBCol::StateTransitionData`, which is otherwise only written for blocks
+/// applied by a node running a version producing state witnesses.
+pub fn migrate_38_to_39(
+    store: &Store,
+    near_config: &crate::NearConfig,
+    home_dir: &Path,
+) -> anyhow::Result<()> {
+    if !near_config.config.store.backfill_state_transition_data {
+        return Ok(());
+    }
+    // The cold database is migrated separately and doesn't hold the state
+    // needed to re-apply chunks.
+    if store.get_db_kind()? == Some(DbKind::Cold) {
+        return Ok(());
+    }
+    backfill_state_transition_data(store, near_config, home_dir)
+}
+
+/// Saves the state transition data needed to produce the next state witness
+/// of every tracked shard, i.e. for all blocks since the last new chunk of the
+/// shard on the canonical chain. The chunks of those blocks are re-applied
+/// from the trie with storage recording enabled. Data which is already present
+/// is left untouched.
+pub fn backfill_state_transition_data(
+    store: &Store,
+    near_config: &crate::NearConfig,
+    home_dir: &Path,
+) -> anyhow::Result<()> {
+    let genesis_config = &near_config.genesis.config;
+    let chain_store = ChainStore::new(
+        store.clone(),
+        genesis_config.genesis_height,
+        near_config.client_config.save_trie_changes,
+    );
+    let Ok(head) = chain_store.head() else {
+        // Nothing to backfill in a fresh database.
+        return Ok(());
+    };
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), genesis_config);
+    let protocol_version = epoch_manager.get_epoch_protocol_version(&head.epoch_id)?;
+    if !checked_feature!("stable", ChunkValidation, protocol_version) {
+        return Ok(());
+    }
+    let shard_tracker = ShardTracker::new(
+        TrackedConfig::from_config(&near_config.client_config),
+        epoch_manager.clone(),
+    );
+    let runtime =
+        NightshadeRuntime::from_config(home_dir, store.clone(), near_config, epoch_manager.clone());
+    let me = near_config.validator_signer.as_ref().map(|signer| signer.validator_id().clone());
+
+    let mut pending_shards: BTreeSet<ShardId> = epoch_manager
+        .shard_ids(&head.epoch_id)?
+        .into_iter()
+        .filter(|shard_id| {
+            shard_tracker.care_about_shard(me.as_ref(), &head.last_block_hash, *shard_id, true)
+        })
+        .collect();
+    tracing::info!(target: "migrations", ?pending_shards, head_height = head.height, "Backfilling state transition data");
+
+    let mut store_update = BatchedStoreUpdate::new(store, 10_000_000);
+    let mut count = 0;
+    let mut block_hash = head.last_block_hash;
+    while !pending_shards.is_empty() {
+        let block = chain_store.get_block(&block_hash)?;
+        if block.header().height() == genesis_config.genesis_height {
+            break;
+        }
+        for shard_id in pending_shards.clone() {
+            let Some(chunk_header) = block.chunks().get(shard_id as usize).cloned() else {
+                // The shard layout changed, the shard didn't exist before.
+                pending_shards.remove(&shard_id);
+                continue;
+            };
+            let key = get_block_shard_id(&block_hash, shard_id);
+            if !store.exists(DBCol::StateTransitionData, &key)? {
+                let data = record_state_transition_data(
+                    &chain_store,
+                    epoch_manager.as_ref(),
+                    runtime.as_ref(),
+                    &block,
+                    shard_id,
+                )?;
+                store_update.set_ser(DBCol::StateTransitionData, &key, &data)?;
+                count += 1;
+            }
+            if chunk_header.height_included() == block.header().height() {
+                pending_shards.remove(&shard_id);
+            }
+        }
+        block_hash = *block.header().prev_hash();
+    }
+    store_update.finish()?;
+    tracing::info!(target: "migrations", count, "Backfilled state transition data");
+    Ok(())
+}
+
+/// Re-applies the chunk of the shard at the given block, recording the trie
+/// nodes it accesses. The state before the block must still be present in
+/// the trie.
+pub fn record_state_transition_data(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime: &dyn RuntimeAdapter,
+    block: &Block,
+    shard_id: ShardId,
+) -> anyhow::Result<StoredChunkStateTransitionData> {
+    let prev_block = chain_store.get_block(block.header().prev_hash())?;
+    let chunk_header = block.chunks()[shard_id as usize].clone();
+    let is_new_chunk = chunk_header.height_included() == block.header().height();
+    let block_context = Chain::get_apply_chunk_block_context(
+        epoch_manager,
+        block.header(),
+        prev_block.header(),
+        is_new_chunk,
+    )?;
+    let apply_result = if is_new_chunk {
+        let chunk = chain_store.get_chunk(&chunk_header.chunk_hash())?;
+        let receipt_proof_response = chain_store.get_incoming_receipts_for_shard(
+            epoch_manager,
+            shard_id,
+            *block.hash(),
+            prev_block.chunks()[shard_id as usize].height_included(),
+        )?;
+        let receipts = collect_receipts_from_response(&receipt_proof_response);
+        let is_first_block_with_chunk_of_version = check_if_block_is_first_with_chunk_of_version(
+            chain_store,
+            epoch_manager,
+            block.header().prev_hash(),
+            shard_id,
+        )?;
+        runtime.apply_chunk(
+            RuntimeStorageConfig {
+                state_root: chunk_header.prev_state_root(),
+                use_flat_storage: false,
+                source: StorageDataSource::Db,
+                state_patch: Default::default(),
+                record_storage: true,
+            },
+            ApplyChunkShardContext {
+                shard_id,
+                last_validator_proposals: chunk_header.prev_validator_proposals(),
+                gas_limit: chunk_header.gas_limit(),
+                is_new_chunk: true,
+                is_first_block_with_chunk_of_version,
+            },
+            block_context,
+            &receipts,
+            chunk.transactions(),
+        )?
+    } else {
+        let shard_uid = epoch_manager.shard_id_to_uid(shard_id, block.header().epoch_id())?;
+        let prev_chunk_extra = chain_store.get_chunk_extra(prev_block.hash(), &shard_uid)?;
+        runtime.apply_chunk(
+            RuntimeStorageConfig {
+                state_root: *prev_chunk_extra.state_root(),
+                use_flat_storage: false,
+                source: StorageDataSource::Db,
+                state_patch: Default::default(),
+                record_storage: true,
+            },
+            ApplyChunkShardContext {
+                shard_id,
+                last_validator_proposals: prev_chunk_extra.validator_proposals(),
+                gas_limit: prev_chunk_extra.gas_limit(),
+                is_new_chunk: false,
+                is_first_block_with_chunk_of_version: false,
+            },
+            block_context,
+            &[],
+            &[],
+        )?
+    };
+    let proof = apply_result
+        .proof
+        .ok_or_else(|| anyhow::anyhow!("Storage was not recorded for shard {shard_id}"))?;
+    Ok(StoredChunkStateTransitionData {
+        base_state: proof.nodes,
+        receipts_hash: apply_result.applied_receipts_hash,
+    })
+}
+
 /// In test runs reads and writes here used 442 TGas, but in test on live net migration take
 /// between 4 and 4.5s. We do not want to process any receipts in this block
 const GAS_USED_FOR_STORAGE_USAGE_DELTA_MIGRATION: Gas = 1_000_000_000_000_000;
@@ -80,11 +278,12 @@ pub fn load_migration_data(chain_id: &str) -> MigrationData {
 
 pub(super) struct Migrator<'a> {
     config: &'a crate::config::NearConfig,
+    home_dir: &'a Path,
 }
 
 impl<'a> Migrator<'a> {
-    pub fn new(config: &'a crate::config::NearConfig) -> Self {
-        Self { config }
+    pub fn new(config: &'a crate::config::NearConfig, home_dir: &'a Path) -> Self {
+        Self { config, home_dir }
     }
 }
 
@@ -115,6 +314,7 @@ impl<'a> near_store::StoreMigrator for Migrator<'a> {
             }
             36 => near_store::migrations::migrate_36_to_37(store),
             37 => near_store::migrations::migrate_37_to_38(store),
+            38 => migrate_38_to_39(store, self.config, self.home_dir),
             DB_VERSION.. => unreachable!(),
         }
     }
@@ -0,0 +1,43 @@
+use actix_rt::ArbiterHandle;
+use near_store::{NodeStorage, Store, Temperature};
+
+fn catch_up(store: &Store, temperature: Temperature) {
+    if let Err(err) = store.catch_up_with_primary() {
+        tracing::warn!(
+            target: "read_replica",
+            ?temperature,
+            ?err,
+            "Failed to catch up with the primary database",
+        );
+    }
+}
+
+/// Spawns the loop which periodically applies the changes made by the primary
+/// node to the databases opened in secondary mode by a read replica.
+pub fn spawn_catch_up_loop(
+    storage: &NodeStorage,
+    period: std::time::Duration,
+) -> anyhow::Result<ArbiterHandle> {
+    tracing::debug!(target: "read_replica", "Spawning the catch up loop.");
+    let catch_up_arbiter = actix_rt::Arbiter::new();
+
+    let start = tokio::time::Instant::now();
+    let mut interval = actix_rt::time::interval_at(start, period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let hot_store = storage.get_hot_store();
+    let cold_store = storage.get_cold_store();
+
+    catch_up_arbiter.spawn(async move {
+        loop {
+            interval.tick().await;
+
+            catch_up(&hot_store, Temperature::Hot);
+            if let Some(cold_store) = &cold_store {
+                catch_up(cold_store, Temperature::Cold);
+            }
+        }
+    });
+
+    Ok(catch_up_arbiter.handle())
+}
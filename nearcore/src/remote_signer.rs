@@ -0,0 +1,491 @@
+//! Validator signer which delegates signing to a remote signing service, e.g.
+//! one backed by an HSM, so that the validator key never has to be stored on
+//! the node.
+//!
+//! The signer computes the exact bytes to be signed locally and sends them to
+//! the service over HTTP:
+//!
+//! - `POST {url}/sign` with `{"account_id", "public_key", "kind", "data"}`,
+//!   where `data` is base64 encoded, responds with `{"signature"}`.
+//! - `POST {url}/vrf` with the same request responds with
+//!   `{"vrf_value", "vrf_proof"}`, both base64 encoded.
+//!
+//! `kind` tells the service what is being signed (e.g. `block_header` or
+//! `chunk_endorsement`), so that it can apply its own signing policy.
+//! Responses are verified against the validator public key before use. If the
+//! service fails, signing returns an error and whatever was being signed is
+//! skipped, unless a fallback to the local key is configured.
+
+use near_crypto::{InMemorySigner, PublicKey, Signature, Signer};
+use near_o11y::metrics::{try_create_int_counter_vec, IntCounterVec};
+use near_primitives::block::{Approval, ApprovalInner, BlockHeader};
+use near_primitives::challenge::ChallengeBody;
+use near_primitives::chunk_validation::ChunkEndorsementPayload;
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::{AnnounceAccount, PeerId};
+use near_primitives::serialize::{from_base64, to_base64};
+use near_primitives::sharding::ChunkHash;
+use near_primitives::telemetry::TelemetryInfo;
+use near_primitives::types::{AccountId, BlockHeight, EpochId};
+use near_primitives::validator_failover::FailoverHeartbeatInner;
+use near_primitives::validator_signer::{ValidatorSigner, ValidatorSignerError};
+use once_cell::sync::Lazy;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+static REMOTE_SIGNER_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_remote_signer_requests_total",
+        "Number of requests to the remote validator signer by kind and result",
+        &["kind", "result"],
+    )
+    .unwrap()
+});
+
+fn default_remote_signer_timeout() -> Duration {
+    Duration::from_millis(500)
+}
+
+fn default_remote_signer_num_workers() -> usize {
+    4
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct RemoteSignerConfig {
+    /// Base URL of the signing service.
+    pub url: String,
+    /// Account id of the validator.
+    pub account_id: AccountId,
+    /// Public key the signing service signs with.
+    pub public_key: PublicKey,
+    /// How long to wait for the signing service to respond to a request.
+    #[serde(default = "default_remote_signer_timeout")]
+    pub timeout: Duration,
+    /// How many requests to the signing service may be in flight at once, so
+    /// that e.g. a slow block signature doesn't delay chunk endorsements.
+    #[serde(default = "default_remote_signer_num_workers")]
+    pub num_workers: usize,
+    /// If set, signs with the local validator key when the signing service
+    /// fails to produce a valid signature in time. Without a fallback such
+    /// failures are returned as errors, and e.g. the block or endorsement
+    /// being signed is skipped.
+    #[serde(default)]
+    pub fallback_to_local_key: bool,
+}
+
+#[derive(serde::Serialize)]
+struct SignRequest {
+    account_id: AccountId,
+    public_key: PublicKey,
+    kind: &'static str,
+    data: String,
+}
+
+#[derive(serde::Deserialize)]
+struct SignResponse {
+    signature: Signature,
+}
+
+#[derive(serde::Deserialize)]
+struct VrfResponse {
+    vrf_value: String,
+    vrf_proof: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum RemoteSignerError {
+    #[error("request to the signing service failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("failed to parse the signing service response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("signing service didn't respond within {0:?}")]
+    Timeout(Duration),
+    #[error("signing service worker has stopped")]
+    WorkerStopped,
+    #[error("invalid response from the signing service: {0}")]
+    InvalidResponse(String),
+}
+
+type Job = Box<dyn FnOnce(&reqwest::blocking::Client) + Send>;
+
+pub struct RemoteValidatorSigner {
+    config: RemoteSignerConfig,
+    /// Requests are executed by a pool of dedicated threads, as the blocking
+    /// HTTP client must not be used from within an async runtime.
+    jobs: mpsc::Sender<Job>,
+    fallback: Option<InMemorySigner>,
+}
+
+impl RemoteValidatorSigner {
+    /// Creates the signer. `fallback` is the local validator key, which is
+    /// only used if `config.fallback_to_local_key` is set.
+    pub fn new(
+        config: RemoteSignerConfig,
+        fallback: Option<InMemorySigner>,
+    ) -> anyhow::Result<Self> {
+        // VRF proofs, which are needed to produce blocks, require an ed25519 key.
+        anyhow::ensure!(
+            matches!(config.public_key, PublicKey::ED25519(_)),
+            "remote signer key {} must be an ed25519 key",
+            config.public_key
+        );
+        anyhow::ensure!(config.num_workers > 0, "remote signer needs at least one worker");
+        if let Some(fallback) = &fallback {
+            anyhow::ensure!(
+                fallback.account_id == config.account_id
+                    && fallback.public_key() == config.public_key,
+                "local validator key {}:{} doesn't match the remote signer key {}:{}",
+                fallback.account_id,
+                fallback.public_key(),
+                config.account_id,
+                config.public_key
+            );
+        }
+        let client = reqwest::blocking::Client::builder().timeout(config.timeout).build()?;
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..config.num_workers {
+            let client = client.clone();
+            let receiver = receiver.clone();
+            std::thread::Builder::new().name(format!("remote_signer_{i}")).spawn(move || loop {
+                // The lock is only held while waiting for a job, not while running it.
+                let job = receiver.lock().unwrap().recv();
+                match job {
+                    Ok(job) => job(&client),
+                    Err(_) => break,
+                }
+            })?;
+        }
+        let fallback = if config.fallback_to_local_key { fallback } else { None };
+        Ok(Self { config, jobs, fallback })
+    }
+
+    fn request<T: serde::de::DeserializeOwned + Send + 'static>(
+        &self,
+        endpoint: &'static str,
+        kind: &'static str,
+        data: &[u8],
+    ) -> Result<T, RemoteSignerError> {
+        let url = format!("{}/{}", self.config.url.trim_end_matches('/'), endpoint);
+        let request = serde_json::to_vec(&SignRequest {
+            account_id: self.config.account_id.clone(),
+            public_key: self.config.public_key.clone(),
+            kind,
+            data: to_base64(data),
+        })?;
+        let (sender, receiver) = mpsc::channel();
+        let job: Job = Box::new(move |client| {
+            let response = client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(request)
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.bytes())
+                .map_err(RemoteSignerError::from)
+                .and_then(|body| Ok(serde_json::from_slice::<T>(&body)?));
+            // The caller may have given up waiting already.
+            let _ = sender.send(response);
+        });
+        self.jobs.send(job).map_err(|_| RemoteSignerError::WorkerStopped)?;
+        match receiver.recv_timeout(self.config.timeout) {
+            Ok(response) => response,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(RemoteSignerError::Timeout(self.config.timeout))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(RemoteSignerError::WorkerStopped),
+        }
+    }
+
+    fn remote_sign(&self, kind: &'static str, data: &[u8]) -> Result<Signature, RemoteSignerError> {
+        let SignResponse { signature } = self.request("sign", kind, data)?;
+        if !signature.verify(data, &self.config.public_key) {
+            return Err(RemoteSignerError::InvalidResponse(format!(
+                "signature doesn't match public key {}",
+                self.config.public_key
+            )));
+        }
+        Ok(signature)
+    }
+
+    fn remote_vrf(
+        &self,
+        data: &[u8],
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), RemoteSignerError> {
+        let VrfResponse { vrf_value, vrf_proof } = self.request("vrf", "vrf", data)?;
+        fn decode<const N: usize>(field: &str, value: &str) -> Result<[u8; N], RemoteSignerError> {
+            let bytes = from_base64(value).map_err(|err| {
+                RemoteSignerError::InvalidResponse(format!("invalid base64 in {field}: {err}"))
+            })?;
+            bytes.try_into().map_err(|_| {
+                RemoteSignerError::InvalidResponse(format!("{field} must be {N} bytes long"))
+            })
+        }
+        let value = near_crypto::vrf::Value(decode("vrf_value", &vrf_value)?);
+        let proof = near_crypto::vrf::Proof(decode("vrf_proof", &vrf_proof)?);
+        let PublicKey::ED25519(public_key) = &self.config.public_key else {
+            return Err(RemoteSignerError::InvalidResponse(
+                "vrf requires an ed25519 public key".to_string(),
+            ));
+        };
+        let public_key = near_crypto::key_conversion::convert_public_key(public_key)
+            .ok_or_else(|| RemoteSignerError::InvalidResponse("invalid public key".to_string()))?;
+        if !public_key.is_vrf_valid(&data, &value, &proof) {
+            return Err(RemoteSignerError::InvalidResponse(
+                "vrf proof doesn't match public key".to_string(),
+            ));
+        }
+        Ok((value, proof))
+    }
+
+    /// Signs `data` remotely. On failure, signs with the fallback key if there
+    /// is one, otherwise returns the error.
+    fn sign(&self, kind: &'static str, data: &[u8]) -> Result<Signature, ValidatorSignerError> {
+        match self.remote_sign(kind, data) {
+            Ok(signature) => {
+                REMOTE_SIGNER_REQUESTS.with_label_values(&[kind, "ok"]).inc();
+                Ok(signature)
+            }
+            Err(err) => {
+                REMOTE_SIGNER_REQUESTS.with_label_values(&[kind, "error"]).inc();
+                tracing::error!(target: "remote_signer", kind, %err, fallback = self.fallback.is_some(), "Remote signing failed");
+                match &self.fallback {
+                    Some(fallback) => Ok(fallback.sign(data)),
+                    None => Err(ValidatorSignerError(err.to_string())),
+                }
+            }
+        }
+    }
+}
+
+impl ValidatorSigner for RemoteValidatorSigner {
+    fn validator_id(&self) -> &AccountId {
+        &self.config.account_id
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.config.public_key.clone()
+    }
+
+    fn next_public_key(&self) -> Option<PublicKey> {
+        None
+    }
+
+    fn signer_for_public_key(&self, public_key: &PublicKey) -> Option<Arc<dyn ValidatorSigner>> {
+        if public_key == &self.config.public_key {
+            Some(Arc::new(Self {
+                config: self.config.clone(),
+                jobs: self.jobs.clone(),
+                fallback: self.fallback.clone(),
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn sign_telemetry(
+        &self,
+        info: &TelemetryInfo,
+    ) -> Result<serde_json::Value, ValidatorSignerError> {
+        let mut value = serde_json::to_value(info).expect("Telemetry must serialize to JSON");
+        let content = serde_json::to_string(&value).expect("Telemetry must serialize to JSON");
+        value["signature"] = self.sign("telemetry", content.as_bytes())?.to_string().into();
+        Ok(value)
+    }
+
+    fn sign_block_header_parts(
+        &self,
+        prev_hash: CryptoHash,
+        inner_lite: &[u8],
+        inner_rest: &[u8],
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
+        let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
+        Ok((hash, self.sign("block_header", hash.as_ref())?))
+    }
+
+    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Result<Signature, ValidatorSignerError> {
+        self.sign("chunk_hash", chunk_hash.as_ref())
+    }
+
+    fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, ValidatorSignerError> {
+        self.sign("approval", &Approval::get_data_for_sig(inner, target_height))
+    }
+
+    fn sign_chunk_endorsement(
+        &self,
+        payload: &ChunkEndorsementPayload,
+    ) -> Result<Signature, ValidatorSignerError> {
+        self.sign("chunk_endorsement", &payload.signed_data())
+    }
+
+    fn sign_challenge(
+        &self,
+        challenge_body: &ChallengeBody,
+    ) -> Result<(CryptoHash, Signature), ValidatorSignerError> {
+        let hash = CryptoHash::hash_borsh(challenge_body);
+        Ok((hash, self.sign("challenge", hash.as_ref())?))
+    }
+
+    fn sign_failover_heartbeat(
+        &self,
+        inner: &FailoverHeartbeatInner,
+    ) -> Result<Signature, ValidatorSignerError> {
+        self.sign("failover_heartbeat", &borsh::to_vec(inner).unwrap())
+    }
+
+    fn sign_account_announce(
+        &self,
+        account_id: &AccountId,
+        peer_id: &PeerId,
+        epoch_id: &EpochId,
+    ) -> Result<Signature, ValidatorSignerError> {
+        let hash = AnnounceAccount::build_header_hash(account_id, peer_id, epoch_id);
+        self.sign("account_announce", hash.as_ref())
+    }
+
+    fn sign_account_key_payload(
+        &self,
+        proto_bytes: &[u8],
+    ) -> Result<Signature, ValidatorSignerError> {
+        self.sign("account_key_payload", proto_bytes)
+    }
+
+    fn compute_vrf_with_proof(
+        &self,
+        data: &[u8],
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), ValidatorSignerError> {
+        match self.remote_vrf(data) {
+            Ok(result) => {
+                REMOTE_SIGNER_REQUESTS.with_label_values(&["vrf", "ok"]).inc();
+                Ok(result)
+            }
+            Err(err) => {
+                REMOTE_SIGNER_REQUESTS.with_label_values(&["vrf", "error"]).inc();
+                tracing::error!(target: "remote_signer", %err, fallback = self.fallback.is_some(), "Remote VRF computation failed");
+                match &self.fallback {
+                    Some(fallback) => Ok(fallback.compute_vrf_with_proof(data)),
+                    None => Err(ValidatorSignerError(err.to_string())),
+                }
+            }
+        }
+    }
+
+    /// Writes the fallback key, if any. The remote key can't be exported.
+    fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
+        match &self.fallback {
+            Some(fallback) => fallback.write_to_file(path),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "validator key of a remote signer can't be written to a file",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::KeyType;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves a single HTTP request with the given handler.
+    fn serve_once(handler: impl FnOnce(serde_json::Value) -> String + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let response = handler(serde_json::from_slice(&body).unwrap());
+            write!(
+                &stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        });
+        url
+    }
+
+    fn config(url: String, signer: &InMemorySigner) -> RemoteSignerConfig {
+        RemoteSignerConfig {
+            url,
+            account_id: signer.account_id.clone(),
+            public_key: signer.public_key(),
+            timeout: Duration::from_secs(5),
+            num_workers: 2,
+            fallback_to_local_key: true,
+        }
+    }
+
+    #[test]
+    fn test_remote_sign() {
+        let key = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let remote_key = key.clone();
+        let url = serve_once(move |request| {
+            assert_eq!(request["kind"], "chunk_hash");
+            let data = from_base64(request["data"].as_str().unwrap()).unwrap();
+            serde_json::json!({ "signature": remote_key.sign(&data) }).to_string()
+        });
+        let signer = RemoteValidatorSigner::new(config(url, &key), None).unwrap();
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        let signature = signer.sign_chunk_hash(&chunk_hash).unwrap();
+        assert!(signature.verify(chunk_hash.as_ref(), &key.public_key()));
+    }
+
+    #[test]
+    fn test_remote_sign_error() {
+        let key = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let other_key = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "x");
+        let url = serve_once(move |request| {
+            let data = from_base64(request["data"].as_str().unwrap()).unwrap();
+            serde_json::json!({ "signature": other_key.sign(&data) }).to_string()
+        });
+        // Without a fallback, the invalid signature is reported as an error.
+        let signer = RemoteValidatorSigner::new(config(url, &key), None).unwrap();
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        assert!(signer.sign_chunk_hash(&chunk_hash).is_err());
+    }
+
+    #[test]
+    fn test_remote_signer_requires_ed25519() {
+        let key = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::SECP256K1, "test");
+        let config = config("http://127.0.0.1:1".to_string(), &key);
+        assert!(RemoteValidatorSigner::new(config, None).is_err());
+    }
+
+    #[test]
+    fn test_remote_sign_fallback() {
+        let key = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let other_key = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "x");
+        // The service signs with a wrong key, so the fallback key is used.
+        let url = serve_once(move |request| {
+            let data = from_base64(request["data"].as_str().unwrap()).unwrap();
+            serde_json::json!({ "signature": other_key.sign(&data) }).to_string()
+        });
+        let fallback = key.clone();
+        let signer = RemoteValidatorSigner::new(config(url, &key), Some(fallback)).unwrap();
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        let signature = signer.sign_chunk_hash(&chunk_hash).unwrap();
+        assert!(signature.verify(chunk_hash.as_ref(), &key.public_key()));
+    }
+}
@@ -0,0 +1,150 @@
+//! Replays a log of consensus messages recorded by a node, see `consensus_log_file` in the config,
+//! into a client working on the database in the home directory. The database should be a copy of
+//! the database of the recording node taken when it started recording, and is modified by the
+//! replay.
+
+use crate::{open_storage, NearConfig, NightshadeRuntime};
+use anyhow::Context;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use near_async::futures::RayonAsyncComputationSpawner;
+use near_async::messaging::{CanSend, CanSendAsync, Sender};
+use near_chain::ChainGenesis;
+use near_client::consensus_log::{ConsensusLogReader, ConsensusMessage};
+use near_client::{
+    ChunkEndorsementPool, ChunkWitnessProductionTracker, Client, SyncAdapter, SyncStatus,
+    ValidatorAccountId,
+};
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::EpochManager;
+use near_network::state_witness::{DistributeStateWitnessRequest, SendChunkEndorsementsRequest};
+use near_network::types::{
+    PeerManagerMessageRequest, PeerManagerMessageResponse, SetChainInfo, SetValidatorSigner,
+};
+use near_store::genesis::initialize_genesis_state;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// Network of the replaying client, which drops everything the client sends.
+struct DisconnectedNetwork;
+
+impl CanSendAsync<PeerManagerMessageRequest, Result<PeerManagerMessageResponse, ()>>
+    for DisconnectedNetwork
+{
+    fn send_async(
+        &self,
+        _message: PeerManagerMessageRequest,
+    ) -> BoxFuture<'static, Result<PeerManagerMessageResponse, ()>> {
+        async { Err(()) }.boxed()
+    }
+}
+
+impl CanSend<PeerManagerMessageRequest> for DisconnectedNetwork {
+    fn send(&self, _message: PeerManagerMessageRequest) {}
+}
+
+impl CanSend<SetChainInfo> for DisconnectedNetwork {
+    fn send(&self, _message: SetChainInfo) {}
+}
+
+impl CanSend<SetValidatorSigner> for DisconnectedNetwork {
+    fn send(&self, _message: SetValidatorSigner) {}
+}
+
+impl CanSend<DistributeStateWitnessRequest> for DisconnectedNetwork {
+    fn send(&self, _message: DistributeStateWitnessRequest) {}
+}
+
+impl CanSend<SendChunkEndorsementsRequest> for DisconnectedNetwork {
+    fn send(&self, _message: SendChunkEndorsementsRequest) {}
+}
+
+/// Feeds the messages of the log at `log_path` one at a time, in the recorded order, to a client
+/// disconnected from the network, finishing the processing of the blocks each message lets the
+/// client process before feeding the next one.
+pub fn replay_consensus_log(
+    home_dir: &Path,
+    mut config: NearConfig,
+    log_path: &Path,
+) -> anyhow::Result<()> {
+    // The client signs with the validator key to validate witnesses and collect approvals like
+    // the recording node, but nothing it signs leaves it.
+    config.client_config.consensus_log_file = None;
+    config.client_config.validator_key_detached = false;
+    config.client_config.validator_failover = None;
+    let storage = open_storage(home_dir, &mut config)?;
+    initialize_genesis_state(storage.get_hot_store(), &config.genesis, Some(home_dir));
+    let epoch_manager =
+        EpochManager::new_arc_handle(storage.get_hot_store(), &config.genesis.config);
+    let shard_tracker =
+        ShardTracker::new(TrackedConfig::from_config(&config.client_config), epoch_manager.clone());
+    let runtime = NightshadeRuntime::from_config(
+        home_dir,
+        storage.get_hot_store(),
+        &config,
+        epoch_manager.clone(),
+    );
+    let state_sync_adapter =
+        Arc::new(RwLock::new(SyncAdapter::new(Sender::noop(), Sender::noop())));
+    let mut client = Client::new(
+        config.client_config.clone(),
+        ChainGenesis::new(&config.genesis),
+        epoch_manager,
+        shard_tracker,
+        state_sync_adapter,
+        runtime,
+        Arc::new(DisconnectedNetwork).into(),
+        Sender::noop(),
+        Sender::noop(),
+        ChunkWitnessProductionTracker::default(),
+        ChunkEndorsementPool::default(),
+        ValidatorAccountId::default(),
+        config.validator_signer.clone(),
+        true,
+        [0; 32],
+        None,
+        Arc::new(RayonAsyncComputationSpawner),
+    )?;
+    client.sync_status = SyncStatus::NoSync;
+
+    let reader = ConsensusLogReader::open(log_path)
+        .with_context(|| format!("failed to open {}", log_path.display()))?;
+    let mut num_messages = 0;
+    for entry in reader {
+        let entry = entry.with_context(|| format!("failed to read {}", log_path.display()))?;
+        if let ConsensusMessage::Started { head_height, head_hash } = &entry.message {
+            let head = client.chain.head()?;
+            if head.last_block_hash != *head_hash {
+                tracing::warn!(
+                    target: "replay",
+                    recorded_head_height = *head_height,
+                    recorded_head_hash = ?head_hash,
+                    head_height = head.height,
+                    head_hash = ?head.last_block_hash,
+                    "The head differs from the head of the node when it started recording"
+                );
+            }
+        }
+        client.replay_consensus_message(entry.message);
+        num_messages += 1;
+        if num_messages % 1000 == 0 {
+            let head = client.chain.head()?;
+            tracing::info!(
+                target: "replay",
+                num_messages,
+                timestamp_ms = entry.timestamp_ms,
+                head_height = head.height,
+                "Replayed messages"
+            );
+        }
+    }
+    let head = client.chain.head()?;
+    tracing::info!(
+        target: "replay",
+        num_messages,
+        head_height = head.height,
+        head_hash = ?head.last_block_hash,
+        "Replayed the consensus log"
+    );
+    Ok(())
+}
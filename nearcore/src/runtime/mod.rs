@@ -6,7 +6,8 @@ use borsh::BorshDeserialize;
 use errors::FromStateViewerErrors;
 use near_chain::types::{
     ApplyChunkBlockContext, ApplyChunkResult, ApplyChunkShardContext, ApplyResultForResharding,
-    RuntimeAdapter, RuntimeStorageConfig, StorageDataSource, Tip,
+    BlockHeader, PrepareTransactionsLimit, PreparedTransactions, RuntimeAdapter,
+    RuntimeStorageConfig, StorageDataSource, Tip,
 };
 use near_chain::Error;
 use near_chain_configs::{
@@ -34,28 +35,28 @@ use near_primitives::types::{
 };
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, QueryRequest, QueryResponse, QueryResponseKind, ViewApplyState,
-    ViewStateResult,
+    AccessKeyInfoView, CallResult, QueryRequest, QueryResponse, QueryResponseKind,
+    TransactionEstimateView, ViewApplyState, ViewStateResult,
 };
 use near_store::config::StateSnapshotType;
 use near_store::flat::FlatStorageManager;
 use near_store::metadata::DbKind;
 use near_store::{
-    ApplyStatePartResult, DBCol, ShardTries, StateSnapshotConfig, Store,
-    StoreCompiledContractCache, Trie, TrieConfig, WrappedTrieChanges, COLD_HEAD_KEY,
+    get_account, get_code, ApplyStatePartResult, DBCol, ShardTries, SharedCompiledContractCache,
+    StateSnapshotConfig, Store, Trie, TrieConfig, TrieUpdate, WrappedTrieChanges, COLD_HEAD_KEY,
 };
 use near_vm_runner::logic::CompiledContractCache;
-use near_vm_runner::precompile_contract;
 use near_vm_runner::ContractCode;
+use near_vm_runner::{get_contract_cache_key_by_hash, precompile_contract};
 use node_runtime::adapter::ViewRuntimeAdapter;
-use node_runtime::state_viewer::TrieViewer;
+use node_runtime::state_viewer::{ReceiverState, TrieViewer};
 use node_runtime::{
     validate_transaction, verify_and_charge_transaction, ApplyState, Runtime,
     ValidatorAccountsUpdate,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::time::Instant;
 use tracing::{debug, error, info};
@@ -77,6 +78,14 @@ pub struct NightshadeRuntime {
     epoch_manager: Arc<EpochManagerHandle>,
     migration_data: Arc<MigrationData>,
     gc_num_epochs_to_keep: u64,
+    compiled_contract_cache: SharedCompiledContractCache,
+    /// Code hashes of the contracts being compiled by
+    /// `precompile_contracts_speculatively`, so that the compilation of a
+    /// contract called by consecutive chunks isn't started twice.
+    contracts_in_compilation: Arc<Mutex<HashSet<CryptoHash>>>,
+    /// Whether to collect the profiles of the executed receipts, so that the
+    /// chain can save them.
+    save_receipt_profiles: bool,
 }
 
 impl NightshadeRuntime {
@@ -101,8 +110,13 @@ impl NightshadeRuntime {
             hot_store_path: config.config.store.path.clone().unwrap_or(PathBuf::from("data")),
             state_snapshot_subdir: PathBuf::from("state_snapshot"),
             compaction_enabled,
+            num_retained_snapshots: config
+                .config
+                .store
+                .state_snapshot_config
+                .num_retained_snapshots,
         };
-        Self::new(
+        let runtime = Self::new(
             store,
             &config.genesis.config,
             epoch_manager,
@@ -112,7 +126,17 @@ impl NightshadeRuntime {
             config.config.gc.gc_num_epochs_to_keep(),
             TrieConfig::from_store_config(&config.config.store),
             state_snapshot_config,
-        )
+            config.config.store.compiled_contract_cache_size.as_u64(),
+            config.client_config.save_receipt_profiles,
+        );
+        if let Err(err) = runtime.compiled_contract_cache.warm_up() {
+            tracing::warn!(
+                target: "runtime",
+                ?err,
+                "Failed to warm up the compiled contract cache",
+            );
+        }
+        runtime
     }
 
     fn new(
@@ -125,6 +149,8 @@ impl NightshadeRuntime {
         gc_num_epochs_to_keep: u64,
         trie_config: TrieConfig,
         state_snapshot_config: StateSnapshotConfig,
+        compiled_contract_cache_size: u64,
+        save_receipt_profiles: bool,
     ) -> Arc<Self> {
         let runtime_config_store = match runtime_config_store {
             Some(store) => store,
@@ -152,6 +178,8 @@ impl NightshadeRuntime {
         }
 
         let migration_data = Arc::new(load_migration_data(&genesis_config.chain_id));
+        let compiled_contract_cache =
+            SharedCompiledContractCache::new(&store, compiled_contract_cache_size);
         Arc::new(NightshadeRuntime {
             genesis_config: genesis_config.clone(),
             runtime_config_store,
@@ -162,6 +190,9 @@ impl NightshadeRuntime {
             epoch_manager,
             migration_data,
             gc_num_epochs_to_keep: gc_num_epochs_to_keep.max(MIN_GC_NUM_EPOCHS_TO_KEEP),
+            compiled_contract_cache,
+            contracts_in_compilation: Default::default(),
+            save_receipt_profiles,
         })
     }
 
@@ -188,7 +219,10 @@ impl NightshadeRuntime {
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
                 compaction_enabled: false,
+                num_retained_snapshots: 1,
             },
+            0,
+            false,
         )
     }
 
@@ -215,7 +249,10 @@ impl NightshadeRuntime {
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
                 compaction_enabled: false,
+                num_retained_snapshots: 1,
             },
+            0,
+            false,
         )
     }
 
@@ -395,13 +432,14 @@ impl NightshadeRuntime {
             random_seed,
             current_protocol_version,
             config: self.runtime_config_store.get_config(current_protocol_version).clone(),
-            cache: Some(Box::new(StoreCompiledContractCache::new(&self.store))),
+            cache: Some(Box::new(self.compiled_contract_cache.for_shard(shard_id))),
             is_new_chunk,
             migration_data: Arc::clone(&self.migration_data),
             migration_flags: MigrationFlags {
                 is_first_block_of_version,
                 is_first_block_with_chunk_of_version,
             },
+            collect_receipt_profiles: self.save_receipt_profiles,
         };
 
         let instant = Instant::now();
@@ -476,6 +514,8 @@ impl NightshadeRuntime {
             proof: apply_result.proof,
             processed_delayed_receipts: apply_result.processed_delayed_receipts,
             applied_receipts_hash: hash(&borsh::to_vec(receipts).unwrap()),
+            fallback_trie_nodes: vec![],
+            receipt_profiles: apply_result.receipt_profiles,
         };
 
         Ok(result)
@@ -493,29 +533,11 @@ impl NightshadeRuntime {
         .entered();
         let protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
         let runtime_config = self.runtime_config_store.get_config(protocol_version);
-        let compiled_contract_cache: Option<Box<dyn CompiledContractCache>> =
-            Some(Box::new(StoreCompiledContractCache::new(&self.store)));
-        // Execute precompile_contract in parallel but prevent it from using more than half of all
-        // threads so that node will still function normally.
-        rayon::scope(|scope| {
-            let (slot_sender, slot_receiver) = std::sync::mpsc::channel();
-            // Use up-to half of the threads for the compilation.
-            let max_threads = std::cmp::max(rayon::current_num_threads() / 2, 1);
-            for _ in 0..max_threads {
-                slot_sender.send(()).expect("both sender and receiver are owned here");
-            }
-            for code in contract_codes {
-                slot_receiver.recv().expect("could not receive a slot to compile contract");
-                let contract_cache = compiled_contract_cache.as_deref();
-                let slot_sender = slot_sender.clone();
-                scope.spawn(move |_| {
-                    precompile_contract(&code, &runtime_config.wasm_config, contract_cache).ok();
-                    // If this fails, it just means there won't be any more attempts to recv the
-                    // slots
-                    let _ = slot_sender.send(());
-                });
-            }
-        });
+        precompile_contracts_in_parallel(
+            contract_codes,
+            &runtime_config.wasm_config,
+            &self.compiled_contract_cache,
+        );
         Ok(())
     }
 
@@ -576,6 +598,20 @@ impl NightshadeRuntime {
 
         let trie_with_state =
             self.tries.get_trie_with_block_hash_for_shard(shard_uid, *state_root, &prev_hash, true);
+        // If the state root is loaded in memtries, traverse them instead of
+        // the trie on disk, which is much faster than both that and reading
+        // flat storage.
+        if let Some(memtrie) = trie_with_state.with_memtrie_nodes_storage() {
+            let partial_state = match memtrie.get_trie_nodes_for_part_without_flat_storage(part_id)
+            {
+                Ok(partial_state) => partial_state,
+                Err(err) => {
+                    error!(target: "runtime", ?err, part_id.idx, part_id.total, %prev_hash, %state_root, %shard_id, "Can't get trie nodes for state part from memtrie");
+                    return Err(err.into());
+                }
+            };
+            return Ok(borsh::to_vec(&partial_state).expect("serializer should not fail"));
+        }
         let (partial_state, nibbles_begin, nibbles_end) = match trie_with_state
             .get_state_part_boundaries(part_id)
         {
@@ -717,14 +753,26 @@ impl RuntimeAdapter for NightshadeRuntime {
         chain_validate: &mut dyn FnMut(&SignedTransaction) -> bool,
         current_protocol_version: ProtocolVersion,
         time_limit: Option<Duration>,
-    ) -> Result<Vec<SignedTransaction>, Error> {
+        storage_proof_size_limit: Option<usize>,
+    ) -> Result<PreparedTransactions, Error> {
         let start_time = std::time::Instant::now();
         let time_limit_reached = || match time_limit {
             Some(limit_duration) => start_time.elapsed() >= limit_duration,
             None => false,
         };
         let shard_uid = self.get_shard_uid_from_epoch_id(shard_id, epoch_id)?;
-        let mut state_update = self.tries.new_trie_update(shard_uid, state_root);
+        let mut trie = self.tries.get_trie_for_shard(shard_uid, state_root);
+        if storage_proof_size_limit.is_some() {
+            trie = trie.recording_reads();
+        }
+        let mut state_update = TrieUpdate::new(trie);
+        let storage_proof_size_limit_reached = |state_update: &TrieUpdate| match (
+            storage_proof_size_limit,
+            state_update.trie().recorded_storage_size(),
+        ) {
+            (Some(limit), Some(size)) => size >= limit,
+            _ => false,
+        };
 
         // Total amount of gas burnt for converting transactions towards receipts.
         let mut total_gas_burnt = 0;
@@ -771,11 +819,22 @@ impl RuntimeAdapter for NightshadeRuntime {
             / (runtime_config.wasm_config.ext_costs.gas_cost(ExtCosts::storage_write_value_byte)
                 + runtime_config.wasm_config.ext_costs.gas_cost(ExtCosts::storage_read_value_byte));
 
-        while total_gas_burnt < transactions_gas_limit
-            && total_size < size_limit
-            && transactions.len() < new_receipt_count_limit
-            && !time_limit_reached()
-        {
+        let limited_by = loop {
+            if total_gas_burnt >= transactions_gas_limit {
+                break Some(PrepareTransactionsLimit::Gas);
+            }
+            if total_size >= size_limit {
+                break Some(PrepareTransactionsLimit::Size);
+            }
+            if transactions.len() >= new_receipt_count_limit {
+                break Some(PrepareTransactionsLimit::ReceiptCount);
+            }
+            if time_limit_reached() {
+                break Some(PrepareTransactionsLimit::Time);
+            }
+            if storage_proof_size_limit_reached(&state_update) {
+                break Some(PrepareTransactionsLimit::StorageProofSize);
+            }
             if let Some(iter) = pool_iterator.next() {
                 while let Some(tx) = iter.next() {
                     num_checked_transactions += 1;
@@ -815,14 +874,19 @@ impl RuntimeAdapter for NightshadeRuntime {
                     }
                 }
             } else {
-                break;
+                break None;
             }
-        }
-        debug!(target: "runtime", "Transaction filtering results {} valid out of {} pulled from the pool", transactions.len(), num_checked_transactions);
+        };
+        debug!(target: "runtime", ?limited_by, "Transaction filtering results {} valid out of {} pulled from the pool", transactions.len(), num_checked_transactions);
         metrics::PREPARE_TX_SIZE
             .with_label_values(&[&shard_id.to_string()])
             .observe(total_size as f64);
-        Ok(transactions)
+        if let Some(size) = state_update.trie().recorded_storage_size() {
+            metrics::PREPARE_TX_STORAGE_PROOF_SIZE
+                .with_label_values(&[&shard_id.to_string()])
+                .observe(size as f64);
+        }
+        Ok(PreparedTransactions { transactions, limited_by })
     }
 
     fn get_gc_stop_height(&self, block_hash: &CryptoHash) -> BlockHeight {
@@ -848,6 +912,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         let _timer =
             metrics::APPLYING_CHUNKS_TIME.with_label_values(&[&shard_id.to_string()]).start_timer();
 
+        let mut fallback_storage = None;
         let mut trie = match storage_config.source {
             StorageDataSource::Db => self.get_trie_for_shard(
                 shard_id,
@@ -855,6 +920,15 @@ impl RuntimeAdapter for NightshadeRuntime {
                 storage_config.state_root,
                 storage_config.use_flat_storage,
             )?,
+            StorageDataSource::DbWithoutMemtries => {
+                let shard_uid =
+                    self.get_shard_uid_from_prev_hash(shard_id, &block.prev_block_hash)?;
+                self.tries.get_trie_without_mem_tries_for_shard(
+                    shard_uid,
+                    storage_config.state_root,
+                    &block.prev_block_hash,
+                )
+            }
             StorageDataSource::DbTrieOnly => {
                 // If there is no flat storage on disk, use trie but simulate costs with enabled
                 // flat storage by not charging gas for trie nodes.
@@ -872,6 +946,22 @@ impl RuntimeAdapter for NightshadeRuntime {
                 storage_config.state_root,
                 storage_config.use_flat_storage,
             ),
+            StorageDataSource::RecordedWithDbFallback(storage) => {
+                let db_trie = self.get_trie_for_shard(
+                    shard_id,
+                    &block.prev_block_hash,
+                    storage_config.state_root,
+                    false,
+                )?;
+                let (trie, storage) = Trie::from_recorded_storage_with_fallback(
+                    storage,
+                    storage_config.state_root,
+                    storage_config.use_flat_storage,
+                    &db_trie,
+                );
+                fallback_storage = Some(storage);
+                trie
+            }
         };
         if storage_config.record_storage {
             trie = trie.recording_reads();
@@ -885,7 +975,12 @@ impl RuntimeAdapter for NightshadeRuntime {
             transactions,
             storage_config.state_patch,
         ) {
-            Ok(result) => Ok(result),
+            Ok(mut result) => {
+                if let Some(storage) = fallback_storage {
+                    result.fallback_trie_nodes = storage.fallback_nodes();
+                }
+                Ok(result)
+            }
             Err(e) => match e {
                 Error::StorageError(err) => match &err {
                     StorageError::FlatStorageBlockNotSupported(_)
@@ -949,7 +1044,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                     (epoch_info.epoch_height(), epoch_info.protocol_version())
                 };
 
-                let call_function_result = self
+                let (call_function_result, gas_burnt) = self
                     .call_function(
                         &shard_uid,
                         *state_root,
@@ -971,6 +1066,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                     kind: QueryResponseKind::CallResult(CallResult {
                         result: call_function_result,
                         logs,
+                        gas_burnt,
                     }),
                     block_height,
                     block_hash: *block_hash,
@@ -1067,6 +1163,35 @@ impl RuntimeAdapter for NightshadeRuntime {
         res
     }
 
+    fn obtain_state_part_from_trie(
+        &self,
+        shard_id: ShardId,
+        prev_hash: &CryptoHash,
+        state_root: &StateRoot,
+        part_id: PartId,
+    ) -> Result<Vec<u8>, Error> {
+        let _span = tracing::debug_span!(
+            target: "runtime",
+            "obtain_state_part_from_trie",
+            part_id = part_id.idx,
+            shard_id,
+            %prev_hash,
+            ?state_root,
+            num_parts = part_id.total)
+        .entered();
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(prev_hash)?;
+        let shard_uid = self.get_shard_uid_from_epoch_id(shard_id, &epoch_id)?;
+        let trie = self.tries.get_trie_for_shard(shard_uid, *state_root);
+        let partial_state = match trie.get_trie_nodes_for_part_without_flat_storage(part_id) {
+            Ok(partial_state) => partial_state,
+            Err(err) => {
+                error!(target: "runtime", ?err, part_id.idx, part_id.total, %prev_hash, %state_root, %shard_id, "Can't get trie nodes for state part from trie");
+                return Err(err.into());
+            }
+        };
+        Ok(borsh::to_vec(&partial_state).expect("serializer should not fail"))
+    }
+
     fn validate_state_part(&self, state_root: &StateRoot, part_id: PartId, data: &[u8]) -> bool {
         match BorshDeserialize::try_from_slice(data) {
             Ok(trie_nodes) => {
@@ -1220,6 +1345,12 @@ impl RuntimeAdapter for NightshadeRuntime {
             epoch_config.validator_selection_config.minimum_validators_per_shard;
         genesis_config.minimum_stake_ratio =
             epoch_config.validator_selection_config.minimum_stake_ratio;
+        genesis_config.min_chunk_validator_seats_per_shard =
+            epoch_config.chunk_validation_config.min_chunk_validator_seats_per_shard;
+        genesis_config.chunk_endorsement_quorum =
+            epoch_config.chunk_validation_config.chunk_endorsement_quorum;
+        genesis_config.max_chunk_state_witness_size =
+            epoch_config.chunk_validation_config.max_chunk_state_witness_size;
 
         let runtime_config =
             self.runtime_config_store.get_config(protocol_version).as_ref().clone();
@@ -1234,6 +1365,163 @@ impl RuntimeAdapter for NightshadeRuntime {
     fn load_mem_tries_on_startup(&self, shard_uids: &[ShardUId]) -> Result<(), StorageError> {
         self.tries.load_mem_tries_for_enabled_shards(shard_uids)
     }
+
+    fn precompile_contracts_speculatively(
+        &self,
+        epoch_id: &EpochId,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+        account_ids: Vec<AccountId>,
+    ) {
+        let protocol_version = match self.epoch_manager.get_epoch_protocol_version(epoch_id) {
+            Ok(protocol_version) => protocol_version,
+            Err(err) => {
+                debug!(target: "runtime", ?err, "Failed to get the protocol version");
+                return;
+            }
+        };
+        let wasm_config =
+            self.runtime_config_store.get_config(protocol_version).wasm_config.clone();
+        let tries = self.tries.clone();
+        let cache = self.compiled_contract_cache.clone();
+        let contracts_in_compilation = self.contracts_in_compilation.clone();
+        rayon::spawn(move || {
+            let _span = tracing::debug_span!(
+                target: "runtime",
+                "precompile_contracts_speculatively",
+                num_accounts = account_ids.len())
+            .entered();
+            let trie = tries.get_trie_for_shard(shard_uid, state_root);
+            let mut code_hashes = vec![];
+            let mut contract_codes = vec![];
+            for account_id in account_ids {
+                let code_hash = match get_account(&trie, &account_id) {
+                    Ok(Some(account)) => account.code_hash(),
+                    Ok(None) => continue,
+                    Err(err) => {
+                        debug!(target: "runtime", %account_id, ?err, "Failed to read the account");
+                        continue;
+                    }
+                };
+                if code_hash == CryptoHash::default() {
+                    continue;
+                }
+                match cache.has(&get_contract_cache_key_by_hash(code_hash, &wasm_config)) {
+                    Ok(false) => {}
+                    Ok(true) => continue,
+                    Err(err) => {
+                        debug!(target: "runtime", ?err, "Failed to read the contract cache");
+                        continue;
+                    }
+                }
+                if !contracts_in_compilation.lock().unwrap().insert(code_hash) {
+                    continue;
+                }
+                code_hashes.push(code_hash);
+                match get_code(&trie, &account_id, Some(code_hash)) {
+                    Ok(Some(code)) => contract_codes.push(code),
+                    Ok(None) => {}
+                    Err(err) => {
+                        debug!(target: "runtime", %account_id, ?err, "Failed to read the contract");
+                    }
+                }
+            }
+            metrics::SPECULATIVELY_COMPILED_CONTRACTS.inc_by(contract_codes.len() as u64);
+            precompile_contracts_in_parallel(contract_codes, &wasm_config, &cache);
+            let mut contracts_in_compilation = contracts_in_compilation.lock().unwrap();
+            for code_hash in code_hashes {
+                contracts_in_compilation.remove(&code_hash);
+            }
+        });
+    }
+
+    fn estimate_transaction(
+        &self,
+        block_header: &BlockHeader,
+        signer_state: (ShardUId, StateRoot),
+        receiver_state: Option<(ShardUId, StateRoot)>,
+        transaction: &SignedTransaction,
+        verify_signature: bool,
+    ) -> Result<TransactionEstimateView, Error> {
+        let epoch_id = block_header.epoch_id();
+        let epoch_info = self.epoch_manager.get_epoch_info(epoch_id)?;
+        let protocol_version = epoch_info.protocol_version();
+        let (signer_shard_uid, signer_state_root) = signer_state;
+        let view_state = ViewApplyState {
+            block_height: block_header.height(),
+            prev_block_hash: *block_header.prev_hash(),
+            block_hash: *block_header.hash(),
+            epoch_id: epoch_id.clone(),
+            epoch_height: epoch_info.epoch_height(),
+            block_timestamp: block_header.raw_timestamp(),
+            current_protocol_version: protocol_version,
+            cache: Some(Box::new(
+                self.compiled_contract_cache.for_shard(signer_shard_uid.shard_id()),
+            )),
+        };
+        let receiver_state = match receiver_state {
+            None => ReceiverState::Unavailable,
+            Some((shard_uid, _)) if shard_uid == signer_shard_uid => ReceiverState::SignerShard,
+            Some((shard_uid, state_root)) => {
+                ReceiverState::OtherShard(self.tries.new_trie_update_view(shard_uid, state_root))
+            }
+        };
+        let estimate = self
+            .trie_viewer
+            .estimate_transaction(
+                self.tries.new_trie_update_view(signer_shard_uid, signer_state_root),
+                receiver_state,
+                view_state,
+                self.runtime_config_store.get_config(protocol_version).clone(),
+                block_header.next_gas_price(),
+                transaction,
+                verify_signature,
+                self.epoch_manager.as_ref(),
+            )
+            .map_err(|err| Error::Other(err.to_string()))?;
+
+        let mut gas_burnt = 0;
+        let mut tokens_burnt = 0;
+        for outcome in estimate.transaction_outcome.iter().chain(&estimate.receipt_outcome) {
+            gas_burnt += outcome.outcome.gas_burnt;
+            tokens_burnt += outcome.outcome.tokens_burnt;
+        }
+        Ok(TransactionEstimateView {
+            gas_burnt,
+            tokens_burnt,
+            transaction_outcome: estimate.transaction_outcome.map(|outcome| outcome.outcome.into()),
+            receipt_outcome: estimate.receipt_outcome.map(|outcome| outcome.outcome.into()),
+            receipts: estimate.receipts.into_iter().map(Into::into).collect(),
+            failure: estimate.failure,
+        })
+    }
+}
+
+/// Compiles the contracts and puts them into `cache`. Execute precompile_contract in parallel but
+/// prevent it from using more than half of all threads so that node will still function normally.
+fn precompile_contracts_in_parallel(
+    contract_codes: Vec<ContractCode>,
+    wasm_config: &near_parameters::vm::Config,
+    cache: &dyn CompiledContractCache,
+) {
+    rayon::scope(|scope| {
+        let (slot_sender, slot_receiver) = std::sync::mpsc::channel();
+        // Use up-to half of the threads for the compilation.
+        let max_threads = std::cmp::max(rayon::current_num_threads() / 2, 1);
+        for _ in 0..max_threads {
+            slot_sender.send(()).expect("both sender and receiver are owned here");
+        }
+        for code in contract_codes {
+            slot_receiver.recv().expect("could not receive a slot to compile contract");
+            let slot_sender = slot_sender.clone();
+            scope.spawn(move |_| {
+                precompile_contract(&code, wasm_config, Some(cache)).ok();
+                // If this fails, it just means there won't be any more attempts to recv the
+                // slots
+                let _ = slot_sender.send(());
+            });
+        }
+    });
 }
 
 impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
@@ -1273,7 +1561,7 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         logs: &mut Vec<String>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
-    ) -> Result<Vec<u8>, node_runtime::state_viewer::errors::CallFunctionError> {
+    ) -> Result<(Vec<u8>, Gas), node_runtime::state_viewer::errors::CallFunctionError> {
         let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
         let view_state = ViewApplyState {
             block_height: height,
@@ -1283,7 +1571,7 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
             epoch_height,
             block_timestamp,
             current_protocol_version,
-            cache: Some(Box::new(StoreCompiledContractCache::new(&self.tries.get_store()))),
+            cache: Some(Box::new(self.compiled_contract_cache.for_shard(shard_uid.shard_id()))),
         };
         self.trie_viewer.call_function(
             state_update,
@@ -23,7 +23,7 @@ use near_primitives::types::{
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::views::{
     AccountView, CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo,
-    ValidatorKickoutView,
+    ValidatorKickoutView, ValidatorRoleView,
 };
 use near_store::{get_genesis_state_roots, NodeStorage};
 
@@ -189,7 +189,10 @@ impl TestEnv {
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
                 compaction_enabled: false,
+                num_retained_snapshots: 1,
             },
+            0,
+            false,
         );
         let state_roots = get_genesis_state_roots(&store).unwrap().unwrap();
         let genesis_hash = hash(&[0]);
@@ -740,6 +743,26 @@ fn test_state_sync() {
     assert_eq!(account.locked, TESTING_INIT_STAKE);
 }
 
+/// State parts obtained by traversing the trie, as served from the cold
+/// storage, are valid without a state snapshot.
+#[test]
+fn test_obtain_state_part_from_trie() {
+    init_test_logger();
+    let validators = vec!["test1".parse().unwrap(), "test2".parse().unwrap()];
+    let mut env = TestEnv::new(vec![validators], 2, false);
+    env.step_default(vec![]);
+    env.step_default(vec![]);
+    let block_hash = env.head.last_block_hash;
+    let state_root = env.state_roots[0];
+    let num_parts = 3;
+    for part_id in 0..num_parts {
+        let part_id = PartId::new(part_id, num_parts);
+        let state_part =
+            env.runtime.obtain_state_part_from_trie(0, &block_hash, &state_root, part_id).unwrap();
+        assert!(env.runtime.validate_state_part(&state_root, part_id, &state_part));
+    }
+}
+
 #[test]
 fn test_get_validator_info() {
     let num_nodes = 2;
@@ -789,6 +812,7 @@ fn test_get_validator_info() {
             is_slashed: false,
             stake: TESTING_INIT_STAKE,
             shards: vec![0],
+            role: Some(ValidatorRoleView::BlockProducer),
             num_produced_blocks: expected_blocks[0],
             num_expected_blocks: expected_blocks[0],
             num_produced_chunks: expected_chunks[0],
@@ -802,6 +826,7 @@ fn test_get_validator_info() {
             is_slashed: false,
             stake: TESTING_INIT_STAKE,
             shards: vec![0],
+            role: Some(ValidatorRoleView::BlockProducer),
             num_produced_blocks: expected_blocks[1],
             num_expected_blocks: expected_blocks[1],
             num_produced_chunks: expected_chunks[1],
@@ -816,12 +841,14 @@ fn test_get_validator_info() {
             public_key: block_producers[0].public_key(),
             stake: TESTING_INIT_STAKE,
             shards: vec![0],
+            role: Some(ValidatorRoleView::BlockProducer),
         },
         NextEpochValidatorInfo {
             account_id: "test2".parse().unwrap(),
             public_key: block_producers[1].public_key(),
             stake: TESTING_INIT_STAKE,
             shards: vec![0],
+            role: Some(ValidatorRoleView::BlockProducer),
         },
     ];
     let response = env
@@ -875,6 +902,7 @@ fn test_get_validator_info() {
             public_key: block_producers[1].public_key(),
             stake: TESTING_INIT_STAKE,
             shards: vec![0],
+            role: Some(ValidatorRoleView::BlockProducer),
         }]
     );
     assert!(response.current_proposals.is_empty());
@@ -132,6 +132,9 @@ impl NeardCmd {
             NeardSubCommand::UndoBlock(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
+            NeardSubCommand::Replay(cmd) => {
+                cmd.run(&home_dir, genesis_validation)?;
+            }
             NeardSubCommand::Database(cmd) => {
                 cmd.run(&home_dir)?;
             }
@@ -270,6 +273,12 @@ pub(super) enum NeardSubCommand {
     /// reset the head of the chain locally to the prev block of current head
     UndoBlock(UndoBlockCommand),
 
+    /// Replays the consensus messages recorded by a node with
+    /// `consensus_log_file` into a client working on the database in the
+    /// home directory, which should be a copy of the database of the node
+    /// taken when it started recording.
+    Replay(ReplayCmd),
+
     /// Set of commands to run on database
     Database(DatabaseCommand),
 
@@ -584,7 +593,9 @@ impl RunCmd {
                 handle.stop()
             }
             resharding_handle.stop();
-            flat_state_migration_handle.stop();
+            if let Some(handle) = flat_state_migration_handle {
+                handle.stop()
+            }
             futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
                 server.stop(true).await;
                 debug!(target: "neard", "{} server stopped", name);
@@ -835,6 +846,24 @@ fn make_env_filter(verbose: Option<&str>) -> Result<EnvFilter, BuildEnvFilterErr
     Ok(env_filter)
 }
 
+#[derive(clap::Parser)]
+pub(super) struct ReplayCmd {
+    /// Consensus log recorded by the node.
+    #[clap(long)]
+    log: PathBuf,
+}
+
+impl ReplayCmd {
+    pub(super) fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(home_dir, genesis_validation)?;
+        nearcore::replay::replay_consensus_log(home_dir, near_config, &self.log)
+    }
+}
+
 #[derive(clap::Parser)]
 pub(super) struct ValidateConfigCommand {}
 
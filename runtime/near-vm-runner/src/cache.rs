@@ -45,9 +45,15 @@ fn vm_hash(vm_kind: VMKind) -> u64 {
 }
 
 pub fn get_contract_cache_key(code: &ContractCode, config: &Config) -> CryptoHash {
+    get_contract_cache_key_by_hash(*code.hash(), config)
+}
+
+/// Same as [`get_contract_cache_key`], for when only the hash of the code is
+/// known.
+pub fn get_contract_cache_key_by_hash(code_hash: CryptoHash, config: &Config) -> CryptoHash {
     let _span = tracing::debug_span!(target: "vm", "get_key").entered();
     let key = ContractCacheKey::Version4 {
-        code_hash: *code.hash(),
+        code_hash,
         vm_config_non_crypto_hash: config.non_crypto_hash(),
         vm_kind: config.vm_kind,
         vm_hash: vm_hash(config.vm_kind),
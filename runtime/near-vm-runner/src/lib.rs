@@ -25,7 +25,10 @@ mod wasmer_runner;
 mod wasmtime_runner;
 
 pub use crate::logic::with_ext_cost_counter;
-pub use cache::{get_contract_cache_key, precompile_contract, MockCompiledContractCache};
+pub use cache::{
+    get_contract_cache_key, get_contract_cache_key_by_hash, precompile_contract,
+    MockCompiledContractCache,
+};
 pub use code::ContractCode;
 pub use profile::ProfileDataV2;
 pub use profile::ProfileDataV3;
@@ -133,6 +133,7 @@ impl<'c> EstimatorContext<'c> {
             max_length_storage_key: u64::MAX,
 
             max_total_prepaid_gas: u64::MAX,
+            max_outgoing_receipts_size_per_shard: None,
 
             ..runtime_config.wasm_config.limit_config
         };
@@ -156,6 +157,7 @@ impl<'c> EstimatorContext<'c> {
             is_new_chunk: true,
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
+            collect_receipt_profiles: false,
         }
     }
 
@@ -1263,6 +1263,7 @@ mod tests {
             is_new_chunk: false,
             migration_data: Arc::default(),
             migration_flags: MigrationFlags::default(),
+            collect_receipt_profiles: false,
         }
     }
 
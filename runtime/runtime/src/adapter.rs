@@ -3,7 +3,7 @@ use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{
-    AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
+    AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, MerkleHash,
 };
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::ViewStateResult;
@@ -41,7 +41,7 @@ pub trait ViewRuntimeAdapter {
         logs: &mut Vec<String>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
-    ) -> Result<Vec<u8>, crate::state_viewer::errors::CallFunctionError>;
+    ) -> Result<(Vec<u8>, Gas), crate::state_viewer::errors::CallFunctionError>;
 
     fn view_access_key(
         &self,
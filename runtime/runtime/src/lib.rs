@@ -5,6 +5,7 @@ use crate::config::{
     total_prepaid_exec_fees, total_prepaid_gas,
 };
 use crate::prefetch::TriePrefetcher;
+use crate::receipt_sink::ReceiptSink;
 use crate::verifier::{check_storage_stake, validate_receipt, StorageStakingError};
 pub use crate::verifier::{
     validate_transaction, verify_and_charge_transaction, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT,
@@ -15,7 +16,9 @@ use near_parameters::{ActionCosts, RuntimeConfig};
 pub use near_primitives;
 use near_primitives::account::Account;
 use near_primitives::checked_feature;
-use near_primitives::errors::{ActionError, ActionErrorKind, RuntimeError, TxExecutionError};
+use near_primitives::errors::{
+    ActionError, ActionErrorKind, IntegerOverflowError, RuntimeError, TxExecutionError,
+};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
     ActionReceipt, DataReceipt, DelayedReceiptIndices, Receipt, ReceiptEnum, ReceivedData,
@@ -25,12 +28,14 @@ use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::state_record::StateRecord;
 use near_primitives::transaction::ExecutionMetadata;
 use near_primitives::transaction::{
-    Action, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus, LogEntry, SignedTransaction,
+    Action, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus, LogEntry, ReceiptProfile,
+    SignedTransaction,
 };
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
     validator_stake::ValidatorStake, AccountId, Balance, BlockHeight, Compute, EpochHeight,
     EpochId, EpochInfoProvider, Gas, RawStateChangesWithTrieKey, StateChangeCause, StateRoot,
+    TrieNodesCount,
 };
 use near_primitives::utils::{
     create_action_hash, create_receipt_id_from_receipt, create_receipt_id_from_transaction,
@@ -59,8 +64,10 @@ mod balance_checker;
 pub mod config;
 pub mod ext;
 mod metrics;
+mod parallel_execution;
 mod prefetch;
 pub mod receipt_manager;
+mod receipt_sink;
 pub mod state_viewer;
 mod verifier;
 
@@ -100,6 +107,8 @@ pub struct ApplyState {
     pub migration_data: Arc<MigrationData>,
     /// Flags for migrations indicating whether they can be applied at this block
     pub migration_flags: MigrationFlags,
+    /// Whether to collect a `ReceiptProfile` for every executed receipt.
+    pub collect_receipt_profiles: bool,
 }
 
 /// Contains information to update validators accounts at the first block of a new epoch.
@@ -139,6 +148,20 @@ pub struct ApplyStats {
     pub gas_deficit_amount: Balance,
 }
 
+impl ApplyStats {
+    /// Adds the amounts of `other` to these stats.
+    fn merge(&mut self, other: ApplyStats) -> Result<(), IntegerOverflowError> {
+        self.tx_burnt_amount = safe_add_balance(self.tx_burnt_amount, other.tx_burnt_amount)?;
+        self.slashed_burnt_amount =
+            safe_add_balance(self.slashed_burnt_amount, other.slashed_burnt_amount)?;
+        self.other_burnt_amount =
+            safe_add_balance(self.other_burnt_amount, other.other_burnt_amount)?;
+        self.gas_deficit_amount =
+            safe_add_balance(self.gas_deficit_amount, other.gas_deficit_amount)?;
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct ApplyResult {
     pub state_root: StateRoot,
@@ -152,6 +175,9 @@ pub struct ApplyResult {
     pub proof: Option<PartialStorage>,
     pub delayed_receipts_count: u64,
     pub metrics: Option<metrics::ApplyMetrics>,
+    /// Profiles of the executed receipts, by receipt id. Only collected if
+    /// `ApplyState::collect_receipt_profiles` is set.
+    pub receipt_profiles: Vec<(CryptoHash, ReceiptProfile)>,
 }
 
 #[derive(Debug)]
@@ -257,66 +283,87 @@ impl Runtime {
         let _span = tracing::debug_span!(target: "runtime", "process_transaction", tx_hash = %signed_transaction.get_hash()).entered();
         metrics::TRANSACTION_PROCESSED_TOTAL.inc();
 
-        match verify_and_charge_transaction(
+        match self.convert_transaction(state_update, apply_state, signed_transaction, true) {
+            Ok((receipt, outcome)) => {
+                metrics::TRANSACTION_PROCESSED_SUCCESSFULLY_TOTAL.inc();
+                stats.tx_burnt_amount =
+                    safe_add_balance(stats.tx_burnt_amount, outcome.outcome.tokens_burnt)?;
+                Ok((receipt, outcome))
+            }
+            Err(e) => {
+                metrics::TRANSACTION_PROCESSED_FAILED_TOTAL.inc();
+                Err(e)
+            }
+        }
+    }
+
+    /// Verifies the transaction, charges the signer and commits the changes to `state_update`,
+    /// then returns the receipt the transaction is converted to and the outcome of the
+    /// transaction. The changes are rolled back if the verification fails.
+    ///
+    /// The signature is only not verified when estimating the cost of unsigned transactions.
+    fn convert_transaction(
+        &self,
+        state_update: &mut TrieUpdate,
+        apply_state: &ApplyState,
+        signed_transaction: &SignedTransaction,
+        verify_signature: bool,
+    ) -> Result<(Receipt, ExecutionOutcomeWithId), RuntimeError> {
+        let verification_result = match verify_and_charge_transaction(
             &apply_state.config,
             state_update,
             apply_state.gas_price,
             signed_transaction,
-            true,
+            verify_signature,
             Some(apply_state.block_height),
             apply_state.current_protocol_version,
         ) {
-            Ok(verification_result) => {
-                metrics::TRANSACTION_PROCESSED_SUCCESSFULLY_TOTAL.inc();
-                state_update.commit(StateChangeCause::TransactionProcessing {
-                    tx_hash: signed_transaction.get_hash(),
-                });
-                let transaction = &signed_transaction.transaction;
-                let receipt_id = create_receipt_id_from_transaction(
-                    apply_state.current_protocol_version,
-                    signed_transaction,
-                    &apply_state.prev_block_hash,
-                    &apply_state.block_hash,
-                );
-                let receipt = Receipt {
-                    predecessor_id: transaction.signer_id.clone(),
-                    receiver_id: transaction.receiver_id.clone(),
-                    receipt_id,
-                    receipt: ReceiptEnum::Action(ActionReceipt {
-                        signer_id: transaction.signer_id.clone(),
-                        signer_public_key: transaction.public_key.clone(),
-                        gas_price: verification_result.receipt_gas_price,
-                        output_data_receivers: vec![],
-                        input_data_ids: vec![],
-                        actions: transaction.actions.clone(),
-                    }),
-                };
-                stats.tx_burnt_amount =
-                    safe_add_balance(stats.tx_burnt_amount, verification_result.burnt_amount)?;
-                let outcome = ExecutionOutcomeWithId {
-                    id: signed_transaction.get_hash(),
-                    outcome: ExecutionOutcome {
-                        status: ExecutionStatus::SuccessReceiptId(receipt.receipt_id),
-                        logs: vec![],
-                        receipt_ids: vec![receipt.receipt_id],
-                        gas_burnt: verification_result.gas_burnt,
-                        // TODO(#8806): Support compute costs for actions. For now they match burnt gas.
-                        compute_usage: Some(verification_result.gas_burnt),
-                        tokens_burnt: verification_result.burnt_amount,
-                        executor_id: transaction.signer_id.clone(),
-                        // TODO: profile data is only counted in apply_action, which only happened at process_receipt
-                        // VerificationResult needs updates to incorporate profile data to support profile data of txns
-                        metadata: ExecutionMetadata::V1,
-                    },
-                };
-                Ok((receipt, outcome))
-            }
+            Ok(verification_result) => verification_result,
             Err(e) => {
-                metrics::TRANSACTION_PROCESSED_FAILED_TOTAL.inc();
                 state_update.rollback();
-                Err(e)
+                return Err(e);
             }
-        }
+        };
+        state_update.commit(StateChangeCause::TransactionProcessing {
+            tx_hash: signed_transaction.get_hash(),
+        });
+        let transaction = &signed_transaction.transaction;
+        let receipt_id = create_receipt_id_from_transaction(
+            apply_state.current_protocol_version,
+            signed_transaction,
+            &apply_state.prev_block_hash,
+            &apply_state.block_hash,
+        );
+        let receipt = Receipt {
+            predecessor_id: transaction.signer_id.clone(),
+            receiver_id: transaction.receiver_id.clone(),
+            receipt_id,
+            receipt: ReceiptEnum::Action(ActionReceipt {
+                signer_id: transaction.signer_id.clone(),
+                signer_public_key: transaction.public_key.clone(),
+                gas_price: verification_result.receipt_gas_price,
+                output_data_receivers: vec![],
+                input_data_ids: vec![],
+                actions: transaction.actions.clone(),
+            }),
+        };
+        let outcome = ExecutionOutcomeWithId {
+            id: signed_transaction.get_hash(),
+            outcome: ExecutionOutcome {
+                status: ExecutionStatus::SuccessReceiptId(receipt.receipt_id),
+                logs: vec![],
+                receipt_ids: vec![receipt.receipt_id],
+                gas_burnt: verification_result.gas_burnt,
+                // TODO(#8806): Support compute costs for actions. For now they match burnt gas.
+                compute_usage: Some(verification_result.gas_burnt),
+                tokens_burnt: verification_result.burnt_amount,
+                executor_id: transaction.signer_id.clone(),
+                // TODO: profile data is only counted in apply_action, which only happened at process_receipt
+                // VerificationResult needs updates to incorporate profile data to support profile data of txns
+                metadata: ExecutionMetadata::V1,
+            },
+        };
+        Ok((receipt, outcome))
     }
 
     fn apply_action(
@@ -1273,6 +1320,7 @@ impl Runtime {
                 proof,
                 delayed_receipts_count: delayed_receipts_indices.len(),
                 metrics: None,
+                receipt_profiles: vec![],
             });
         }
 
@@ -1281,6 +1329,7 @@ impl Runtime {
         let mut local_receipts = vec![];
         let mut outcomes = vec![];
         let mut processed_delayed_receipts = vec![];
+        let mut receipt_profiles = vec![];
         // This contains the gas "burnt" for refund receipts. Even though we don't actually
         // charge any gas for refund receipts, we still count the gas use towards the block gas
         // limit
@@ -1349,6 +1398,15 @@ impl Runtime {
             tracing::trace!(target: "runtime", ?node_counter_before, ?node_counter_after);
 
             if let Some(outcome_with_id) = result? {
+                if apply_state.collect_receipt_profiles {
+                    let trie_nodes = node_counter_after
+                        .checked_sub(&node_counter_before)
+                        .expect("trie node counters only grow during a receipt");
+                    receipt_profiles.push((
+                        outcome_with_id.id,
+                        receipt_profile(apply_state, &outcome_with_id, &trie_nodes),
+                    ));
+                }
                 *total_gas_burnt =
                     safe_add_gas(*total_gas_burnt, outcome_with_id.outcome.gas_burnt)?;
                 *total_compute_usage = safe_add_compute(
@@ -1447,24 +1505,90 @@ impl Runtime {
             // Prefetcher is allowed to fail
             _ = prefetcher.prefetch_receipts_data(&incoming_receipts);
         }
-        for receipt in incoming_receipts.iter() {
-            // Validating new incoming no matter whether we have available gas or not. We don't
-            // want to store invalid receipts in state as delayed.
-            validate_receipt(
-                &apply_state.config.wasm_config.limit_config,
-                receipt,
-                apply_state.current_protocol_version,
-            )
-            .map_err(RuntimeError::ReceiptValidationError)?;
-            if total_compute_usage < compute_limit {
-                process_receipt(
+        let parallel_results = if checked_feature!(
+            "stable",
+            ParallelReceiptExecution,
+            apply_state.current_protocol_version
+        ) {
+            // All the receipts are validated before any of them is executed, see
+            // `parallel_execution` for the order of the execution.
+            for receipt in incoming_receipts.iter() {
+                validate_receipt(
+                    &apply_state.config.wasm_config.limit_config,
                     receipt,
-                    &mut state_update,
-                    &mut total_gas_burnt,
-                    &mut total_compute_usage,
-                )?;
-            } else {
-                set_delayed_receipt(&mut state_update, &mut delayed_receipts_indices, receipt);
+                    apply_state.current_protocol_version,
+                )
+                .map_err(RuntimeError::ReceiptValidationError)?;
+            }
+            self.execute_receipts_in_parallel(
+                &state_update,
+                apply_state,
+                incoming_receipts,
+                epoch_info_provider,
+            )
+        } else {
+            None
+        };
+        match parallel_results {
+            Some(results) => {
+                for (receipt, result) in incoming_receipts.iter().zip(results) {
+                    if total_compute_usage >= compute_limit {
+                        set_delayed_receipt(
+                            &mut state_update,
+                            &mut delayed_receipts_indices,
+                            receipt,
+                        );
+                        continue;
+                    }
+                    // A receipt has no result only if an earlier receipt of its group failed,
+                    // and that failure was returned when its result was committed.
+                    let result = result.expect("receipt after a failed one is not committed")?;
+                    state_update.commit_forked_changes(result.changes);
+                    outgoing_receipts.extend(result.outgoing_receipts);
+                    validator_proposals.extend(result.validator_proposals);
+                    stats.merge(result.stats)?;
+                    if let Some(profile) = result.profile {
+                        receipt_profiles.push((receipt.receipt_id, profile));
+                    }
+                    if let Some(outcome_with_id) = result.outcome {
+                        total_gas_burnt =
+                            safe_add_gas(total_gas_burnt, outcome_with_id.outcome.gas_burnt)?;
+                        total_compute_usage = safe_add_compute(
+                            total_compute_usage,
+                            outcome_with_id
+                                .outcome
+                                .compute_usage
+                                .expect("`process_receipt` must populate compute usage"),
+                        )?;
+                        outcomes.push(outcome_with_id);
+                    }
+                }
+            }
+            None => {
+                for receipt in incoming_receipts.iter() {
+                    // Validating new incoming no matter whether we have available gas or not. We
+                    // don't want to store invalid receipts in state as delayed.
+                    validate_receipt(
+                        &apply_state.config.wasm_config.limit_config,
+                        receipt,
+                        apply_state.current_protocol_version,
+                    )
+                    .map_err(RuntimeError::ReceiptValidationError)?;
+                    if total_compute_usage < compute_limit {
+                        process_receipt(
+                            receipt,
+                            &mut state_update,
+                            &mut total_gas_burnt,
+                            &mut total_compute_usage,
+                        )?;
+                    } else {
+                        set_delayed_receipt(
+                            &mut state_update,
+                            &mut delayed_receipts_indices,
+                            receipt,
+                        );
+                    }
+                }
             }
         }
         metrics.incoming_receipts_done(total_gas_burnt, total_compute_usage);
@@ -1488,6 +1612,31 @@ impl Runtime {
             &stats,
         )?;
 
+        // The balance check covers the receipts produced by this chunk. Buffering them or
+        // forwarding receipts buffered by previous chunks moves them between the state and the
+        // outgoing receipts without changing the balance.
+        let outgoing_receipts_size_limit =
+            apply_state.config.wasm_config.limit_config.max_outgoing_receipts_size_per_shard;
+        let outgoing_receipts = match outgoing_receipts_size_limit {
+            Some(size_limit)
+                if checked_feature!(
+                    "stable",
+                    OutgoingReceiptsSizeLimit,
+                    apply_state.current_protocol_version
+                ) =>
+            {
+                let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+                let mut receipt_sink =
+                    ReceiptSink::new(&mut state_update, shard_layout, size_limit)?;
+                receipt_sink.forward_buffered_receipts(&mut state_update)?;
+                for receipt in outgoing_receipts {
+                    receipt_sink.forward_or_buffer_receipt(&mut state_update, receipt);
+                }
+                receipt_sink.finalize(&mut state_update, &mut metrics)
+            }
+            _ => outgoing_receipts,
+        };
+
         state_update.commit(StateChangeCause::UpdatedDelayedReceipts);
         self.apply_state_patch(&mut state_update, state_patch);
         let (trie, trie_changes, state_changes) = state_update.finalize()?;
@@ -1518,6 +1667,7 @@ impl Runtime {
             proof,
             delayed_receipts_count: delayed_receipts_indices.len(),
             metrics: Some(metrics),
+            receipt_profiles,
         })
     }
 
@@ -1550,6 +1700,21 @@ impl Runtime {
     }
 }
 
+/// Builds the profile of a receipt from its outcome and the trie nodes read
+/// while executing it.
+pub(crate) fn receipt_profile(
+    apply_state: &ApplyState,
+    outcome_with_id: &ExecutionOutcomeWithId,
+    trie_nodes: &TrieNodesCount,
+) -> ReceiptProfile {
+    let default_profile = ProfileDataV3::default();
+    let profile = match &outcome_with_id.outcome.metadata {
+        ExecutionMetadata::V3(profile) => profile.as_ref(),
+        ExecutionMetadata::V1 | ExecutionMetadata::V2(_) => &default_profile,
+    };
+    ReceiptProfile::new(profile, &apply_state.config.wasm_config, trie_nodes)
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
@@ -1680,6 +1845,7 @@ mod tests {
             is_new_chunk: true,
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
+            collect_receipt_profiles: false,
         };
 
         (runtime, tries, root, apply_state, signer, MockEpochInfoProvider::default())
@@ -2642,6 +2808,189 @@ mod tests {
             assert_matches!(second.outcome.status, ExecutionStatus::Failure(_));
         });
     }
+
+    /// Transfers to alice and to implicit accounts, interleaved, so that every
+    /// receiver gets several receipts.
+    fn generate_receipts_to_many_receivers(small_transfer: u128, n: u64) -> Vec<Receipt> {
+        let receivers: Vec<AccountId> = std::iter::once(alice_account())
+            .chain((1..4u8).map(|i| hex::encode([i; 32]).parse().unwrap()))
+            .collect();
+        let mut receipts = generate_receipts(small_transfer, n);
+        for (i, receipt) in receipts.iter_mut().enumerate() {
+            receipt.receiver_id = receivers[i % receivers.len()].clone();
+        }
+        receipts
+    }
+
+    fn apply_receipts_with_protocol_version(
+        protocol_version: ProtocolVersion,
+        gas_limit: Gas,
+        receipts: &[Receipt],
+    ) -> ApplyResult {
+        let (runtime, tries, root, mut apply_state, _, epoch_info_provider) =
+            setup_runtime(to_yocto(1_000_000), to_yocto(500_000), gas_limit);
+        apply_state.current_protocol_version = protocol_version;
+        runtime
+            .apply(
+                tries.get_trie_for_shard(ShardUId::single_shard(), root),
+                &None,
+                &apply_state,
+                receipts,
+                &[],
+                &epoch_info_provider,
+                Default::default(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parallel_receipt_execution() {
+        let receipts = generate_receipts_to_many_receivers(to_yocto(10_000), 13);
+        let feature_version = ProtocolFeature::ParallelReceiptExecution.protocol_version();
+        let sequential =
+            apply_receipts_with_protocol_version(feature_version - 1, 10u64.pow(15), &receipts);
+        let parallel =
+            apply_receipts_with_protocol_version(feature_version, 10u64.pow(15), &receipts);
+
+        // Transfers don't read the trie through the accounting cache, so executing them in
+        // parallel changes nothing.
+        assert_eq!(parallel.state_root, sequential.state_root);
+        assert_eq!(parallel.outcomes, sequential.outcomes);
+        assert_eq!(parallel.outgoing_receipts, sequential.outgoing_receipts);
+        assert_eq!(
+            borsh::to_vec(&parallel.state_changes).unwrap(),
+            borsh::to_vec(&sequential.state_changes).unwrap()
+        );
+        assert_eq!(parallel.stats.tx_burnt_amount, sequential.stats.tx_burnt_amount);
+        assert_eq!(parallel.delayed_receipts_count, 0);
+        let outcome_ids: Vec<_> = parallel.outcomes.iter().map(|outcome| outcome.id).collect();
+        let receipt_ids: Vec<_> = receipts.iter().map(|receipt| receipt.receipt_id).collect();
+        assert_eq!(outcome_ids, receipt_ids);
+        for outcome in &parallel.outcomes {
+            assert_matches!(outcome.outcome.status, ExecutionStatus::SuccessValue(_));
+        }
+    }
+
+    #[test]
+    fn test_parallel_receipt_execution_gas_limit() {
+        let receipts = generate_receipts_to_many_receivers(to_yocto(10_000), 13);
+        let config = RuntimeConfig::test();
+        let receipt_gas_cost = config.fees.fee(ActionCosts::new_action_receipt).exec_fee()
+            + config.fees.fee(ActionCosts::transfer).exec_fee();
+        let gas_limit = receipt_gas_cost * 5;
+        let feature_version = ProtocolFeature::ParallelReceiptExecution.protocol_version();
+        let sequential =
+            apply_receipts_with_protocol_version(feature_version - 1, gas_limit, &receipts);
+        let parallel = apply_receipts_with_protocol_version(feature_version, gas_limit, &receipts);
+
+        // The receipts over the limit are delayed in their original order, whichever group
+        // they belong to.
+        assert!(parallel.delayed_receipts_count > 0);
+        assert_eq!(parallel.delayed_receipts_count, sequential.delayed_receipts_count);
+        assert_eq!(
+            parallel.outcomes.len() as u64 + parallel.delayed_receipts_count,
+            receipts.len() as u64
+        );
+        assert_eq!(parallel.state_root, sequential.state_root);
+        assert_eq!(parallel.outcomes, sequential.outcomes);
+    }
+
+    #[test]
+    fn test_parallel_receipt_execution_replay_from_proof() {
+        let (runtime, tries, root, mut apply_state, _, epoch_info_provider) =
+            setup_runtime(to_yocto(1_000_000), to_yocto(500_000), 10u64.pow(15));
+        apply_state.current_protocol_version =
+            ProtocolFeature::ParallelReceiptExecution.protocol_version();
+        let receipts = generate_receipts_to_many_receivers(to_yocto(10_000), 13);
+        let apply = |trie| {
+            runtime
+                .apply(
+                    trie,
+                    &None,
+                    &apply_state,
+                    &receipts,
+                    &[],
+                    &epoch_info_provider,
+                    Default::default(),
+                )
+                .unwrap()
+        };
+
+        // The state proof has the nodes read by all the forks, so the chunk can be
+        // applied again from the proof alone.
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard(), root).recording_reads();
+        let apply_result = apply(trie);
+        let proof = apply_result.proof.unwrap();
+        let replayed = apply(Trie::from_recorded_storage(proof, root, false));
+        assert_eq!(replayed.state_root, apply_result.state_root);
+        assert_eq!(replayed.outcomes, apply_result.outcomes);
+    }
+
+    #[test]
+    fn test_receipt_profiles() {
+        let feature_version = ProtocolFeature::ParallelReceiptExecution.protocol_version();
+        for protocol_version in [feature_version - 1, feature_version] {
+            let (runtime, tries, root, mut apply_state, signer, epoch_info_provider) =
+                setup_runtime(to_yocto(1_000_000), to_yocto(500_000), 10u64.pow(15));
+            apply_state.current_protocol_version = protocol_version;
+            let receipts = vec![
+                create_receipt_with_actions(
+                    alice_account(),
+                    signer.clone(),
+                    vec![Action::DeployContract(DeployContractAction {
+                        code: near_test_contracts::rs_contract().to_vec(),
+                    })],
+                ),
+                create_receipt_with_actions(
+                    alice_account(),
+                    signer,
+                    vec![Action::FunctionCall(Box::new(FunctionCallAction {
+                        method_name: "ext_sha256".to_string(),
+                        args: b"profile".to_vec(),
+                        gas: 10u64.pow(13),
+                        deposit: 0,
+                    }))],
+                ),
+            ];
+            let apply = |apply_state: &ApplyState| {
+                runtime
+                    .apply(
+                        tries.get_trie_for_shard(ShardUId::single_shard(), root),
+                        &None,
+                        apply_state,
+                        &receipts,
+                        &[],
+                        &epoch_info_provider,
+                        Default::default(),
+                    )
+                    .unwrap()
+            };
+
+            assert!(apply(&apply_state).receipt_profiles.is_empty());
+
+            apply_state.collect_receipt_profiles = true;
+            let apply_result = apply(&apply_state);
+            let profile_ids: Vec<_> =
+                apply_result.receipt_profiles.iter().map(|(id, _)| *id).collect();
+            let outcome_ids: Vec<_> =
+                apply_result.outcomes.iter().map(|outcome| outcome.id).collect();
+            assert_eq!(profile_ids, outcome_ids);
+
+            let (_, call_profile) = &apply_result.receipt_profiles[1];
+            assert!(call_profile.wasm_ops > 0);
+            assert!(call_profile.action_gas > 0);
+            let sha256 = call_profile
+                .host_functions
+                .iter()
+                .find(|usage| usage.cost == "sha256_base")
+                .unwrap();
+            assert_eq!(sha256.count, 1);
+            assert_eq!(
+                sha256.gas,
+                apply_state.config.wasm_config.ext_costs.gas_cost(ExtCosts::sha256_base)
+            );
+        }
+    }
 }
 
 /// Interface provided for gas cost estimations.
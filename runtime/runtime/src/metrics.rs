@@ -1,8 +1,11 @@
 use near_o11y::metrics::{
-    try_create_histogram_vec, try_create_int_counter, try_create_int_counter_vec, HistogramVec,
-    IntCounter, IntCounterVec,
+    exponential_buckets, try_create_histogram_vec, try_create_int_counter,
+    try_create_int_counter_vec, try_create_int_gauge_vec, HistogramVec, IntCounter, IntCounterVec,
+    IntGaugeVec,
 };
+use near_primitives::types::ShardId;
 use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
 
 pub static ACTION_CALLED_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
@@ -34,6 +37,14 @@ pub static TRANSACTION_PROCESSED_FAILED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static PARALLEL_RECEIPT_EXECUTION_CONFLICTS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_parallel_receipt_execution_conflicts_total",
+        "The number of chunks whose incoming receipts were executed sequentially because \
+         receipts executed in parallel wrote to the same accounts",
+    )
+    .unwrap()
+});
 pub static PREFETCH_ENQUEUED: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_prefetch_enqueued",
@@ -50,6 +61,14 @@ pub static PREFETCH_QUEUE_FULL: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static PREFETCH_BUDGET_EXHAUSTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_prefetch_budget_exhausted",
+        "Prefetch requests for receipt actions dropped over the IO budget of the chunk",
+        &["shard_id"],
+    )
+    .unwrap()
+});
 pub static FUNCTION_CALL_PROCESSED: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_function_call_processed",
@@ -196,6 +215,23 @@ static CHUNK_TX_TGAS: Lazy<HistogramVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+static CHUNK_OUTGOING_RECEIPTS_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_chunk_outgoing_receipts_size",
+        "Size in bytes of the receipts forwarded by a chunk to each receiving shard, as a histogram",
+        &["shard_id", "receiving_shard"],
+        Some(exponential_buckets(1024., 2., 14).unwrap()),
+    )
+    .unwrap()
+});
+static BUFFERED_RECEIPTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_buffered_receipts",
+        "Number of outgoing receipts buffered in the state of a shard because they exceeded the size limit of the receiving shard",
+        &["shard_id", "receiving_shard"],
+    )
+    .unwrap()
+});
 
 /// Buckets used for burned gas in receipts.
 ///
@@ -234,6 +270,9 @@ pub struct ApplyMetrics {
     delayed_receipts_gas: u64,
     incoming_receipts_compute_usage: u64,
     incoming_receipts_gas: u64,
+    /// Size of the forwarded receipts and number of buffered receipts, per
+    /// receiving shard.
+    outgoing_receipts: BTreeMap<ShardId, (u64, u64)>,
 }
 
 impl ApplyMetrics {
@@ -270,6 +309,15 @@ impl ApplyMetrics {
             self.update_accumulated(accumulated_gas, accumulated_compute);
     }
 
+    pub fn outgoing_receipts_done(
+        &mut self,
+        receiving_shard: ShardId,
+        forwarded_size: u64,
+        buffered_receipts: u64,
+    ) {
+        self.outgoing_receipts.insert(receiving_shard, (forwarded_size, buffered_receipts));
+    }
+
     /// Report statistics
     pub fn report(&self, shard_id: &str) {
         const TERA: f64 = 1_000_000_000_000_f64;
@@ -304,5 +352,15 @@ impl ApplyMetrics {
         CHUNK_COMPUTE
             .with_label_values(&[shard_id])
             .observe(self.accumulated_compute as f64 / TERA);
+
+        for (receiving_shard, (forwarded_size, buffered_receipts)) in &self.outgoing_receipts {
+            let receiving_shard = receiving_shard.to_string();
+            CHUNK_OUTGOING_RECEIPTS_SIZE
+                .with_label_values(&[shard_id, &receiving_shard])
+                .observe(*forwarded_size as f64);
+            BUFFERED_RECEIPTS
+                .with_label_values(&[shard_id, &receiving_shard])
+                .set(*buffered_receipts as i64);
+        }
     }
 }
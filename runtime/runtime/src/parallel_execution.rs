@@ -0,0 +1,307 @@
+//! Parallel execution of the incoming receipts of a chunk.
+//!
+//! Since `ProtocolFeature::ParallelReceiptExecution`, the incoming receipts of
+//! a chunk are executed on the rayon thread pool instead of one after another.
+//! Every node applying the chunk, including the chunk validators replaying
+//! the state witness, must end up with the same state, outcomes and gas usage
+//! no matter how many threads it has or how the threads are scheduled. The
+//! execution is therefore split into steps whose results only depend on the
+//! receipts and the state before them:
+//!
+//! 1. All incoming receipts are validated first. An invalid receipt fails the
+//!    chunk, as before.
+//! 2. The receipts are grouped by receiver. The groups are ordered by the
+//!    position of their first receipt, and the receipts of a group keep their
+//!    relative order. Receipts are executed on the state of their receiver
+//!    only, so receipts of different groups are independent.
+//! 3. Each group is executed, in order, on its own fork of the state left by
+//!    the transactions, local receipts and delayed receipts of the chunk. A
+//!    fork has its own trie accounting cache, which is why gas usage of the
+//!    receipts differs from the sequential execution and the parallel
+//!    execution needs a protocol upgrade. The changes, outcome, outgoing
+//!    receipts, validator proposals and balance stats of every receipt are
+//!    kept separately. A group stops at the first receipt which fails with a
+//!    `RuntimeError`.
+//! 4. If any receipt wrote a key which doesn't belong to the account of its
+//!    receiver, the groups weren't independent after all: the results are
+//!    discarded and the receipts are executed sequentially, in their original
+//!    order, on the state of the chunk. This is a safety net, the runtime is
+//!    not expected to write to other accounts.
+//! 5. Otherwise, the results are committed in the original order of the
+//!    receipts, exactly like the sequential execution would: while the compute
+//!    usage of the chunk is under the limit, the changes of the next receipt
+//!    are committed and its gas and compute usage added to the chunk, and once
+//!    the limit is reached the remaining receipts are delayed. The results of
+//!    the delayed receipts are discarded. Receipts of a group are only ever
+//!    committed together with all the receipts before them, so the state each
+//!    committed receipt was executed on is the state it would have seen.
+//!
+//! The trie nodes read by all the forks are added to the state proof of the
+//! chunk, including those of discarded results, so that the chunk validators
+//! can replay all the steps above from the state witness.
+
+use crate::metrics;
+use crate::{receipt_profile, ApplyState, ApplyStats, Runtime};
+use near_primitives::errors::RuntimeError;
+use near_primitives::receipt::Receipt;
+use near_primitives::transaction::{ExecutionOutcomeWithId, ReceiptProfile};
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::types::{AccountId, EpochInfoProvider, RawStateChange};
+use near_store::TrieUpdate;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// The result of the execution of a receipt on a fork of the state.
+pub(crate) struct ReceiptExecutionResult {
+    /// Changes committed to the state by the receipt, in order.
+    pub changes: Vec<(TrieKey, RawStateChange)>,
+    /// None if the receipt was postponed until its input data is received.
+    pub outcome: Option<ExecutionOutcomeWithId>,
+    pub outgoing_receipts: Vec<Receipt>,
+    pub validator_proposals: Vec<ValidatorStake>,
+    pub stats: ApplyStats,
+    /// Only collected if `ApplyState::collect_receipt_profiles` is set.
+    pub profile: Option<ReceiptProfile>,
+}
+
+/// Results of the receipts, by index of the receipt. The results of receipts
+/// following a failed receipt of the same group are None.
+pub(crate) type ReceiptExecutionResults = Vec<Option<Result<ReceiptExecutionResult, RuntimeError>>>;
+
+impl Runtime {
+    /// Executes the receipts in parallel, grouped by receiver, on forks of
+    /// `state_update`. Returns None if the groups turned out to conflict, in
+    /// which case the receipts should be executed sequentially.
+    ///
+    /// See the module documentation for the details.
+    pub(crate) fn execute_receipts_in_parallel(
+        &self,
+        state_update: &TrieUpdate,
+        apply_state: &ApplyState,
+        receipts: &[Receipt],
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Option<ReceiptExecutionResults> {
+        let _span = tracing::debug_span!(
+            target: "runtime",
+            "execute_receipts_in_parallel",
+            num_receipts = receipts.len())
+        .entered();
+        let groups = group_receipts_by_receiver(receipts);
+        let forks = state_update.fork(groups.len());
+        let executed: Vec<_> = forks
+            .into_par_iter()
+            .zip(groups.par_iter())
+            .map(|(mut fork, group)| {
+                let results = self.execute_group(
+                    &mut fork,
+                    apply_state,
+                    receipts,
+                    group,
+                    epoch_info_provider,
+                );
+                (fork, results)
+            })
+            .collect();
+
+        let mut results: ReceiptExecutionResults = receipts.iter().map(|_| None).collect();
+        for (fork, group_results) in executed {
+            state_update.trie().merge_recorded_storage(fork.trie());
+            for (index, result) in group_results {
+                results[index] = Some(result);
+            }
+        }
+        if let Some(index) = find_conflict(receipts, &results) {
+            tracing::debug!(
+                target: "runtime",
+                receipt_id = %receipts[index].receipt_id,
+                "Receipt wrote to another account, executing the receipts sequentially",
+            );
+            metrics::PARALLEL_RECEIPT_EXECUTION_CONFLICTS.inc();
+            return None;
+        }
+        Some(results)
+    }
+
+    /// Executes the receipts of a group in order, until one of them fails.
+    /// Returns the results with the indices of the receipts.
+    fn execute_group(
+        &self,
+        state_update: &mut TrieUpdate,
+        apply_state: &ApplyState,
+        receipts: &[Receipt],
+        group: &[usize],
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Vec<(usize, Result<ReceiptExecutionResult, RuntimeError>)> {
+        let mut results = Vec::with_capacity(group.len());
+        for &index in group {
+            let receipt = &receipts[index];
+            let _span = tracing::debug_span!(
+                target: "runtime",
+                "process_receipt",
+                receipt_id = %receipt.receipt_id,
+                predecessor = %receipt.predecessor_id,
+                receiver = %receipt.receiver_id,
+            )
+            .entered();
+            let mut outgoing_receipts = vec![];
+            let mut validator_proposals = vec![];
+            let mut stats = ApplyStats::default();
+            let node_counter_before = state_update.trie().get_trie_nodes_count();
+            let result = self
+                .process_receipt(
+                    state_update,
+                    apply_state,
+                    receipt,
+                    &mut outgoing_receipts,
+                    &mut validator_proposals,
+                    &mut stats,
+                    epoch_info_provider,
+                )
+                .map(|outcome| {
+                    let profile = outcome
+                        .as_ref()
+                        .filter(|_| apply_state.collect_receipt_profiles)
+                        .map(|outcome_with_id| {
+                            let trie_nodes = state_update
+                                .trie()
+                                .get_trie_nodes_count()
+                                .checked_sub(&node_counter_before)
+                                .expect("trie node counters only grow during a receipt");
+                            receipt_profile(apply_state, outcome_with_id, &trie_nodes)
+                        });
+                    ReceiptExecutionResult {
+                        changes: state_update.take_forked_changes(),
+                        outcome,
+                        outgoing_receipts,
+                        validator_proposals,
+                        stats,
+                        profile,
+                    }
+                });
+            let failed = result.is_err();
+            results.push((index, result));
+            if failed {
+                break;
+            }
+        }
+        results
+    }
+}
+
+/// Groups the indices of the receipts by receiver. The groups are ordered by
+/// their first receipt and keep the order of the receipts.
+pub(crate) fn group_receipts_by_receiver(receipts: &[Receipt]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut group_by_receiver: HashMap<&AccountId, usize> = HashMap::new();
+    for (index, receipt) in receipts.iter().enumerate() {
+        let group = *group_by_receiver.entry(&receipt.receiver_id).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[group].push(index);
+    }
+    groups
+}
+
+/// Returns the index of the first receipt which wrote a key that doesn't
+/// belong to its receiver, if any.
+fn find_conflict(receipts: &[Receipt], results: &ReceiptExecutionResults) -> Option<usize> {
+    results.iter().enumerate().find_map(|(index, result)| {
+        let Some(Ok(result)) = result else {
+            return None;
+        };
+        let receiver_id = &receipts[index].receiver_id;
+        result
+            .changes
+            .iter()
+            .any(|(trie_key, _)| trie_key.get_account_id().as_ref() != Some(receiver_id))
+            .then_some(index)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{KeyType, PublicKey};
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::receipt::{ActionReceipt, ReceiptEnum};
+    use near_primitives::types::StateChangeCause;
+
+    fn receipt(receiver_id: &str, index: u8) -> Receipt {
+        Receipt {
+            predecessor_id: "alice.near".parse().unwrap(),
+            receiver_id: receiver_id.parse().unwrap(),
+            receipt_id: hash(&[index]),
+            receipt: ReceiptEnum::Action(ActionReceipt {
+                signer_id: "alice.near".parse().unwrap(),
+                signer_public_key: PublicKey::empty(KeyType::ED25519),
+                gas_price: 0,
+                output_data_receivers: vec![],
+                input_data_ids: vec![],
+                actions: vec![],
+            }),
+        }
+    }
+
+    fn result_with_changes(
+        keys: Vec<TrieKey>,
+    ) -> Option<Result<ReceiptExecutionResult, RuntimeError>> {
+        let cause = StateChangeCause::ReceiptProcessing { receipt_hash: CryptoHash::default() };
+        Some(Ok(ReceiptExecutionResult {
+            changes: keys
+                .into_iter()
+                .map(|key| (key, RawStateChange { cause: cause.clone(), data: Some(vec![]) }))
+                .collect(),
+            outcome: None,
+            outgoing_receipts: vec![],
+            validator_proposals: vec![],
+            stats: ApplyStats::default(),
+            profile: None,
+        }))
+    }
+
+    #[test]
+    fn test_group_receipts_by_receiver() {
+        let receipts = vec![
+            receipt("bob.near", 0),
+            receipt("carol.near", 1),
+            receipt("bob.near", 2),
+            receipt("dave.near", 3),
+            receipt("carol.near", 4),
+            receipt("bob.near", 5),
+        ];
+        assert_eq!(group_receipts_by_receiver(&receipts), vec![vec![0, 2, 5], vec![1, 4], vec![3]]);
+        assert!(group_receipts_by_receiver(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_find_conflict() {
+        let receipts = vec![receipt("bob.near", 0), receipt("carol.near", 1)];
+        let bob: AccountId = "bob.near".parse().unwrap();
+        let carol: AccountId = "carol.near".parse().unwrap();
+
+        let results = vec![
+            result_with_changes(vec![
+                TrieKey::Account { account_id: bob.clone() },
+                TrieKey::ContractData { account_id: bob.clone(), key: vec![1] },
+            ]),
+            result_with_changes(vec![TrieKey::Account { account_id: carol.clone() }]),
+        ];
+        assert_eq!(find_conflict(&receipts, &results), None);
+
+        // Results of failed receipts are not checked.
+        let results = vec![None, result_with_changes(vec![TrieKey::Account { account_id: carol }])];
+        assert_eq!(find_conflict(&receipts, &results), None);
+
+        let results = vec![
+            result_with_changes(vec![TrieKey::Account { account_id: bob.clone() }]),
+            result_with_changes(vec![TrieKey::Account { account_id: bob }]),
+        ];
+        assert_eq!(find_conflict(&receipts, &results), Some(1));
+
+        // Keys which don't belong to any account are conflicts too.
+        let results = vec![result_with_changes(vec![TrieKey::DelayedReceiptIndices]), None];
+        assert_eq!(find_conflict(&receipts, &results), Some(0));
+    }
+}
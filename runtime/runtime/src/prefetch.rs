@@ -40,6 +40,11 @@
 //! the number of IO threads, and memory checks before staring new DB requests
 //! in the prefetcher. Implementation details for most limits are in
 //! `core/store/src/trie/prefetching_trie_storage.rs`
+//!
+//! The data read by the actions of the receipts, such as the contract code of
+//! function calls, is prefetched within an IO budget per chunk, configured
+//! with `store.receipt_prefetch_io_budget`, so that a chunk full of receipts
+//! can't make the prefetcher read an unbounded amount of data.
 
 use near_o11y::metrics::prometheus;
 use near_o11y::metrics::prometheus::core::GenericCounter;
@@ -50,6 +55,7 @@ use near_primitives::types::AccountId;
 use near_primitives::types::StateRoot;
 use near_store::{PrefetchApi, PrefetchError, Trie};
 use sha2::Digest;
+use std::collections::HashSet;
 use tracing::{debug, warn};
 
 use crate::metrics;
@@ -57,8 +63,14 @@ use crate::metrics;
 pub(crate) struct TriePrefetcher {
     prefetch_api: PrefetchApi,
     trie_root: StateRoot,
+    /// Number of trie keys which can still be prefetched for the actions of
+    /// the receipts of the chunk.
+    io_budget_left: usize,
+    /// Trie keys prefetched for the actions of the receipts of the chunk.
+    prefetched_action_keys: HashSet<Vec<u8>>,
     prefetch_enqueued: GenericCounter<prometheus::core::AtomicU64>,
     prefetch_queue_full: GenericCounter<prometheus::core::AtomicU64>,
+    prefetch_budget_exhausted: GenericCounter<prometheus::core::AtomicU64>,
 }
 
 impl TriePrefetcher {
@@ -68,13 +80,18 @@ impl TriePrefetcher {
                 let trie_root = *trie.get_root();
                 let shard_uid = prefetch_api.shard_uid;
                 let metrics_labels: [&str; 1] = [&shard_uid.shard_id.to_string()];
+                let io_budget_left = prefetch_api.receipt_prefetch_io_budget;
                 return Some(Self {
                     prefetch_api,
                     trie_root,
+                    io_budget_left,
+                    prefetched_action_keys: HashSet::new(),
                     prefetch_enqueued: metrics::PREFETCH_ENQUEUED
                         .with_label_values(&metrics_labels),
                     prefetch_queue_full: metrics::PREFETCH_QUEUE_FULL
                         .with_label_values(&metrics_labels),
+                    prefetch_budget_exhausted: metrics::PREFETCH_BUDGET_EXHAUSTED
+                        .with_label_values(&metrics_labels),
                 });
             }
         }
@@ -98,6 +115,9 @@ impl TriePrefetcher {
                 if self.prefetch_api.enable_receipt_prefetching {
                     let trie_key = TrieKey::Account { account_id: account_id.clone() };
                     self.prefetch_trie_key(trie_key)?;
+                    for action in &action_receipt.actions {
+                        self.prefetch_action_data(&account_id, action)?;
+                    }
                 }
 
                 // SWEAT specific argument prefetcher
@@ -179,6 +199,43 @@ impl TriePrefetcher {
         res
     }
 
+    /// Prefetches the contract code or the access key read by the action of a
+    /// receipt for `account_id`, within the IO budget of the chunk. Keys which
+    /// were already prefetched for the chunk don't count against the budget.
+    fn prefetch_action_data(
+        &mut self,
+        account_id: &AccountId,
+        action: &Action,
+    ) -> Result<(), PrefetchError> {
+        let trie_key = match action {
+            Action::FunctionCall(_) => TrieKey::ContractCode { account_id: account_id.clone() },
+            Action::AddKey(add_key) => TrieKey::AccessKey {
+                account_id: account_id.clone(),
+                public_key: add_key.public_key.clone(),
+            },
+            Action::DeleteKey(delete_key) => TrieKey::AccessKey {
+                account_id: account_id.clone(),
+                public_key: delete_key.public_key.clone(),
+            },
+            Action::Delegate(signed_delegate_action) => TrieKey::AccessKey {
+                account_id: account_id.clone(),
+                public_key: signed_delegate_action.delegate_action.public_key.clone(),
+            },
+            _ => return Ok(()),
+        };
+        let key = trie_key.to_vec();
+        if self.prefetched_action_keys.contains(&key) {
+            return Ok(());
+        }
+        if self.io_budget_left == 0 {
+            self.prefetch_budget_exhausted.inc();
+            return Ok(());
+        }
+        self.io_budget_left -= 1;
+        self.prefetched_action_keys.insert(key);
+        self.prefetch_trie_key(trie_key)
+    }
+
     /// Prefetcher specifically tuned for SWEAT record batch
     ///
     /// Temporary hack, consider removing after merging flat storage, see
@@ -219,6 +276,10 @@ impl TriePrefetcher {
 #[cfg(test)]
 mod tests {
     use super::TriePrefetcher;
+    use near_crypto::{KeyType, PublicKey};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum};
+    use near_primitives::transaction::{Action, FunctionCallAction};
     use near_primitives::{trie_key::TrieKey, types::AccountId};
     use near_store::test_utils::{create_test_store, test_populate_trie};
     use near_store::{ShardTries, ShardUId, StateSnapshotConfig, Trie, TrieConfig};
@@ -300,6 +361,68 @@ mod tests {
         check_prefetch_account(&existing_accounts, &non_existing_account, expected_prefetched);
     }
 
+    #[test]
+    fn test_prefetch_action_data_budget() {
+        let shard_uids = vec![ShardUId::single_shard()];
+        let trie_config = TrieConfig {
+            enable_receipt_prefetching: true,
+            receipt_prefetch_io_budget: 2,
+            ..TrieConfig::default()
+        };
+        let store = create_test_store();
+        let flat_storage_manager = near_store::flat::FlatStorageManager::new(store.clone());
+        let tries = ShardTries::new(
+            store,
+            trie_config,
+            &shard_uids,
+            flat_storage_manager,
+            StateSnapshotConfig::default(),
+        );
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard(), Trie::EMPTY_ROOT);
+        let mut prefetcher =
+            TriePrefetcher::new_if_enabled(&trie).expect("caching storage should have prefetcher");
+
+        // The second call to alice.near reads the same contract code, so it
+        // doesn't count against the budget, and the call to carol.near is
+        // above the budget.
+        let receipts: Vec<_> = ["alice.near", "alice.near", "bob.near", "carol.near"]
+            .into_iter()
+            .map(function_call_receipt)
+            .collect();
+        prefetcher.prefetch_receipts_data(&receipts).unwrap();
+
+        assert_eq!(prefetcher.io_budget_left, 0);
+        let expected_keys: std::collections::HashSet<_> = ["alice.near", "bob.near"]
+            .into_iter()
+            .map(|account_id| {
+                TrieKey::ContractCode { account_id: account_id.parse().unwrap() }.to_vec()
+            })
+            .collect();
+        assert_eq!(prefetcher.prefetched_action_keys, expected_keys);
+        prefetcher.clear();
+    }
+
+    fn function_call_receipt(receiver_id: &str) -> Receipt {
+        Receipt {
+            predecessor_id: "relayer.near".parse().unwrap(),
+            receiver_id: receiver_id.parse().unwrap(),
+            receipt_id: CryptoHash::default(),
+            receipt: ReceiptEnum::Action(ActionReceipt {
+                signer_id: "relayer.near".parse().unwrap(),
+                signer_public_key: PublicKey::empty(KeyType::ED25519),
+                gas_price: 0,
+                output_data_receivers: vec![],
+                input_data_ids: vec![],
+                actions: vec![Action::FunctionCall(Box::new(FunctionCallAction {
+                    method_name: "call".to_string(),
+                    args: vec![],
+                    gas: 0,
+                    deposit: 0,
+                }))],
+            }),
+        }
+    }
+
     #[track_caller]
     fn check_prefetch_account(input: &[&str], prefetch: &[&str], expected_prefetched: usize) {
         let input_keys = accounts_to_trie_keys(input);
@@ -0,0 +1,309 @@
+use crate::metrics::ApplyMetrics;
+use near_primitives::errors::StorageError;
+use near_primitives::receipt::{BufferedReceiptIndices, Receipt};
+use near_primitives::shard_layout::{account_id_to_shard_id, ShardLayout};
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::ShardId;
+use near_store::{get, set, TrieUpdate};
+use std::collections::HashMap;
+
+/// Forwards the outgoing receipts of a chunk to the receiving shards, sending
+/// at most `size_limit` bytes of receipts to each shard per chunk.
+///
+/// Receipts over the limit are buffered in the state of the sending shard, in
+/// a queue per receiving shard, and forwarded by the next chunks before any
+/// newer receipt to the same shard, so that receipts between a pair of shards
+/// stay in order. The first receipt forwarded to a shard by a chunk is never
+/// limited, so that receipts larger than the limit don't get stuck.
+pub(crate) struct ReceiptSink {
+    shard_layout: ShardLayout,
+    size_limit: u64,
+    indices: BufferedReceiptIndices,
+    initial_indices: BufferedReceiptIndices,
+    /// Size of the receipts forwarded to each receiving shard by this chunk.
+    forwarded_size: HashMap<ShardId, u64>,
+    forwarded_receipts: Vec<Receipt>,
+}
+
+impl ReceiptSink {
+    pub fn new(
+        state_update: &mut TrieUpdate,
+        shard_layout: ShardLayout,
+        size_limit: u64,
+    ) -> Result<Self, StorageError> {
+        let initial_indices: BufferedReceiptIndices =
+            get(state_update, &TrieKey::BufferedReceiptIndices)?.unwrap_or_else(|| {
+                BufferedReceiptIndices {
+                    shard_layout_version: shard_layout.version(),
+                    shard_buffers: Default::default(),
+                }
+            });
+        let mut sink = Self {
+            shard_layout,
+            size_limit,
+            indices: initial_indices.clone(),
+            initial_indices,
+            forwarded_size: HashMap::new(),
+            forwarded_receipts: vec![],
+        };
+        if sink.indices.shard_layout_version != sink.shard_layout.version() {
+            sink.regroup_buffered_receipts(state_update)?;
+        }
+        Ok(sink)
+    }
+
+    /// Moves the receipts buffered for the shards of a previous shard layout
+    /// to the queues of their receiving shards in the current layout. As the
+    /// shards of a layout only get split, the receipts to each shard of the
+    /// current layout come from a single queue and keep their order.
+    fn regroup_buffered_receipts(
+        &mut self,
+        state_update: &mut TrieUpdate,
+    ) -> Result<(), StorageError> {
+        // All the receipts are read before writing any, as the queues of both
+        // layouts can use the same keys.
+        let mut receipts = vec![];
+        for (&receiving_shard, buffer) in &self.indices.shard_buffers {
+            for index in buffer.first_index..buffer.next_available_index {
+                let key = TrieKey::BufferedReceipt { receiving_shard, index };
+                let receipt: Receipt = get(state_update, &key)?.ok_or_else(|| {
+                    StorageError::StorageInconsistentState(format!(
+                        "Buffered receipt #{} to shard {} should be in the state",
+                        index, receiving_shard
+                    ))
+                })?;
+                state_update.remove(key);
+                receipts.push(receipt);
+            }
+        }
+        self.indices = BufferedReceiptIndices {
+            shard_layout_version: self.shard_layout.version(),
+            shard_buffers: Default::default(),
+        };
+        for receipt in receipts {
+            let receiving_shard = account_id_to_shard_id(&receipt.receiver_id, &self.shard_layout);
+            let buffer = self.indices.shard_buffers.entry(receiving_shard).or_default();
+            set(
+                state_update,
+                TrieKey::BufferedReceipt { receiving_shard, index: buffer.next_available_index },
+                &receipt,
+            );
+            buffer.next_available_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Forwards the receipts buffered by the previous chunks, as long as they
+    /// fit in the limits of their receiving shards.
+    pub fn forward_buffered_receipts(
+        &mut self,
+        state_update: &mut TrieUpdate,
+    ) -> Result<(), StorageError> {
+        for (&receiving_shard, buffer) in self.indices.shard_buffers.iter_mut() {
+            while buffer.len() > 0 {
+                let key = TrieKey::BufferedReceipt { receiving_shard, index: buffer.first_index };
+                let receipt: Receipt = get(state_update, &key)?.ok_or_else(|| {
+                    StorageError::StorageInconsistentState(format!(
+                        "Buffered receipt #{} to shard {} should be in the state",
+                        buffer.first_index, receiving_shard
+                    ))
+                })?;
+                if !try_reserve(
+                    &mut self.forwarded_size,
+                    self.size_limit,
+                    receiving_shard,
+                    &receipt,
+                ) {
+                    break;
+                }
+                state_update.remove(key);
+                buffer.first_index += 1;
+                self.forwarded_receipts.push(receipt);
+            }
+        }
+        Ok(())
+    }
+
+    /// Forwards a new outgoing receipt if no receipts to its receiving shard
+    /// are buffered and it fits in the limit, and buffers it otherwise.
+    pub fn forward_or_buffer_receipt(&mut self, state_update: &mut TrieUpdate, receipt: Receipt) {
+        let receiving_shard = account_id_to_shard_id(&receipt.receiver_id, &self.shard_layout);
+        let buffer = self.indices.shard_buffers.entry(receiving_shard).or_default();
+        if buffer.len() == 0
+            && try_reserve(&mut self.forwarded_size, self.size_limit, receiving_shard, &receipt)
+        {
+            self.forwarded_receipts.push(receipt);
+            return;
+        }
+        set(
+            state_update,
+            TrieKey::BufferedReceipt { receiving_shard, index: buffer.next_available_index },
+            &receipt,
+        );
+        buffer.next_available_index += 1;
+    }
+
+    /// Stores the indices of the buffers and returns the receipts to forward.
+    pub fn finalize(
+        mut self,
+        state_update: &mut TrieUpdate,
+        metrics: &mut ApplyMetrics,
+    ) -> Vec<Receipt> {
+        self.indices.shard_buffers.retain(|_, buffer| buffer.len() > 0);
+        if self.indices != self.initial_indices {
+            if self.indices.shard_buffers.is_empty() {
+                state_update.remove(TrieKey::BufferedReceiptIndices);
+            } else {
+                set(state_update, TrieKey::BufferedReceiptIndices, &self.indices);
+            }
+        }
+        for shard_id in self.shard_layout.shard_ids() {
+            metrics.outgoing_receipts_done(
+                shard_id,
+                self.forwarded_size.get(&shard_id).copied().unwrap_or_default(),
+                self.indices.shard_buffers.get(&shard_id).map_or(0, |buffer| buffer.len()),
+            );
+        }
+        self.forwarded_receipts
+    }
+}
+
+/// Adds the size of the receipt to the size forwarded to its receiving shard,
+/// unless it would exceed the limit. Returns whether the receipt fits.
+fn try_reserve(
+    forwarded_size: &mut HashMap<ShardId, u64>,
+    size_limit: u64,
+    receiving_shard: ShardId,
+    receipt: &Receipt,
+) -> bool {
+    let size = borsh::object_length(receipt).unwrap() as u64;
+    let forwarded = forwarded_size.entry(receiving_shard).or_default();
+    if *forwarded > 0 && *forwarded + size > size_limit {
+        return false;
+    }
+    *forwarded += size;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::types::AccountId;
+    use near_store::test_utils::TestTriesBuilder;
+    use near_store::TrieAccess;
+
+    fn receipt(receiver_id: &str, refund: u128) -> Receipt {
+        Receipt::new_balance_refund(&receiver_id.parse::<AccountId>().unwrap(), refund)
+    }
+
+    #[test]
+    fn test_receipt_sink() {
+        let tries = TestTriesBuilder::new().build();
+        let mut state_update = tries.new_trie_update(ShardUId::single_shard(), Default::default());
+        // "alice" is in shard 0, "bob" in shard 1.
+        let shard_layout = ShardLayout::v1(vec!["b".parse().unwrap()], None, 1);
+        let receipt_size = borsh::object_length(&receipt("alice", 0)).unwrap() as u64;
+        let size_limit = 2 * receipt_size;
+
+        let mut sink =
+            ReceiptSink::new(&mut state_update, shard_layout.clone(), size_limit).unwrap();
+        sink.forward_buffered_receipts(&mut state_update).unwrap();
+        for refund in 0..3 {
+            sink.forward_or_buffer_receipt(&mut state_update, receipt("alice", refund));
+        }
+        sink.forward_or_buffer_receipt(&mut state_update, receipt("bob", 0));
+        let forwarded = sink.finalize(&mut state_update, &mut ApplyMetrics::default());
+        assert_eq!(forwarded, vec![receipt("alice", 0), receipt("alice", 1), receipt("bob", 0)]);
+        let indices: BufferedReceiptIndices =
+            get(&state_update, &TrieKey::BufferedReceiptIndices).unwrap().unwrap();
+        assert_eq!(indices.shard_buffers.len(), 1);
+        assert_eq!(indices.shard_buffers[&0].len(), 1);
+
+        // The buffered receipt is forwarded before the new receipts.
+        let mut sink =
+            ReceiptSink::new(&mut state_update, shard_layout.clone(), size_limit).unwrap();
+        sink.forward_buffered_receipts(&mut state_update).unwrap();
+        for refund in 3..5 {
+            sink.forward_or_buffer_receipt(&mut state_update, receipt("alice", refund));
+        }
+        let forwarded = sink.finalize(&mut state_update, &mut ApplyMetrics::default());
+        assert_eq!(forwarded, vec![receipt("alice", 2), receipt("alice", 3)]);
+
+        let mut sink = ReceiptSink::new(&mut state_update, shard_layout, size_limit).unwrap();
+        sink.forward_buffered_receipts(&mut state_update).unwrap();
+        let forwarded = sink.finalize(&mut state_update, &mut ApplyMetrics::default());
+        assert_eq!(forwarded, vec![receipt("alice", 4)]);
+        assert!(state_update.get(&TrieKey::BufferedReceiptIndices).unwrap().is_none());
+        let key = TrieKey::BufferedReceipt { receiving_shard: 0, index: 1 };
+        assert!(state_update.get(&key).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_receipt_sink_after_resharding() {
+        let tries = TestTriesBuilder::new().build();
+        let mut state_update = tries.new_trie_update(ShardUId::single_shard(), Default::default());
+        // "alice" and "bob" are in shard 0 of the parent layout, which is split
+        // into shards 0 and 1, "carol" is in shard 1, which becomes shard 2.
+        let parent_layout = ShardLayout::v1(vec!["c".parse().unwrap()], None, 1);
+        let child_layout = ShardLayout::v1(
+            vec!["b".parse().unwrap(), "c".parse().unwrap()],
+            Some(vec![vec![0, 1], vec![2]]),
+            2,
+        );
+        let size_limit = borsh::object_length(&receipt("alice", 0)).unwrap() as u64;
+
+        let mut sink = ReceiptSink::new(&mut state_update, parent_layout, size_limit).unwrap();
+        sink.forward_buffered_receipts(&mut state_update).unwrap();
+        let receivers = ["carol", "carol", "alice", "bob", "carol", "bob", "alice"];
+        for (refund, receiver) in receivers.into_iter().enumerate() {
+            sink.forward_or_buffer_receipt(&mut state_update, receipt(receiver, refund as u128));
+        }
+        let forwarded = sink.finalize(&mut state_update, &mut ApplyMetrics::default());
+        assert_eq!(forwarded, vec![receipt("carol", 0), receipt("alice", 2)]);
+
+        // The receipts buffered for the parent layout are regrouped by the
+        // shards of the child layout, before the new receipts.
+        let mut sink =
+            ReceiptSink::new(&mut state_update, child_layout.clone(), size_limit).unwrap();
+        for (refund, receiver) in ["carol", "alice", "bob"].into_iter().enumerate() {
+            sink.forward_or_buffer_receipt(
+                &mut state_update,
+                receipt(receiver, 7 + refund as u128),
+            );
+        }
+        let forwarded = sink.finalize(&mut state_update, &mut ApplyMetrics::default());
+        assert!(forwarded.is_empty());
+        let indices: BufferedReceiptIndices =
+            get(&state_update, &TrieKey::BufferedReceiptIndices).unwrap().unwrap();
+        assert_eq!(indices.shard_layout_version, 2);
+        let lens: Vec<_> = indices
+            .shard_buffers
+            .iter()
+            .map(|(&shard_id, buffer)| (shard_id, buffer.len()))
+            .collect();
+        assert_eq!(lens, vec![(0, 2), (1, 3), (2, 3)]);
+
+        let mut forwarded = vec![];
+        for _ in 0..3 {
+            let mut sink =
+                ReceiptSink::new(&mut state_update, child_layout.clone(), size_limit).unwrap();
+            sink.forward_buffered_receipts(&mut state_update).unwrap();
+            forwarded.extend(sink.finalize(&mut state_update, &mut ApplyMetrics::default()));
+        }
+        assert_eq!(
+            forwarded,
+            vec![
+                receipt("alice", 6),
+                receipt("bob", 3),
+                receipt("carol", 1),
+                receipt("alice", 8),
+                receipt("bob", 5),
+                receipt("carol", 4),
+                receipt("bob", 9),
+                receipt("carol", 7),
+            ]
+        );
+        assert!(state_update.get(&TrieKey::BufferedReceiptIndices).unwrap().is_none());
+    }
+}
@@ -54,6 +54,17 @@ pub enum CallFunctionError {
     VMError { error_message: String },
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum EstimateTransactionError {
+    #[error("Transaction attaches {prepaid_gas} gas, more than the limit of {limit}")]
+    GasLimitExceeded {
+        prepaid_gas: near_primitives::types::Gas,
+        limit: near_primitives::types::Gas,
+    },
+    #[error("Internal error: #{error_message}")]
+    InternalError { error_message: String },
+}
+
 impl From<ViewAccountError> for ViewContractCodeError {
     fn from(view_account_error: ViewAccountError) -> Self {
         match view_account_error {
@@ -99,3 +110,9 @@ impl From<near_primitives::errors::StorageError> for CallFunctionError {
         Self::InternalError { error_message: storage_error.to_string() }
     }
 }
+
+impl From<near_primitives::errors::RuntimeError> for EstimateTransactionError {
+    fn from(runtime_error: near_primitives::errors::RuntimeError) -> Self {
+        Self::InternalError { error_message: runtime_error.to_string() }
+    }
+}
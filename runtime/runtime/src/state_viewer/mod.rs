@@ -1,17 +1,21 @@
 use crate::actions::execute_function_call;
+use crate::config::total_prepaid_gas;
 use crate::ext::RuntimeExt;
 use crate::receipt_manager::ReceiptManager;
-use crate::ApplyState;
+use crate::{ApplyState, ApplyStats, Runtime};
 use near_crypto::{KeyType, PublicKey};
-use near_parameters::RuntimeConfigStore;
+use near_parameters::{RuntimeConfig, RuntimeConfigStore};
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::borsh::BorshDeserialize;
+use near_primitives::errors::{RuntimeError, TxExecutionError};
 use near_primitives::hash::CryptoHash;
-use near_primitives::receipt::ActionReceipt;
+use near_primitives::receipt::{ActionReceipt, Receipt};
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
-use near_primitives::transaction::FunctionCallAction;
+use near_primitives::transaction::{
+    ExecutionOutcomeWithId, ExecutionStatus, FunctionCallAction, SignedTransaction,
+};
 use near_primitives::trie_key::trie_key_parsers;
-use near_primitives::types::{AccountId, EpochInfoProvider, Gas};
+use near_primitives::types::{AccountId, Balance, EpochInfoProvider, Gas};
 use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{StateItem, ViewApplyState, ViewStateResult};
 use near_primitives_core::config::ViewConfig;
@@ -23,6 +27,31 @@ use tracing::debug;
 
 pub mod errors;
 
+/// State on which `TrieViewer::estimate_transaction` executes the receipt of
+/// the transaction.
+pub enum ReceiverState {
+    /// The receiver is in the shard of the signer.
+    SignerShard,
+    /// The receiver is in another shard.
+    OtherShard(TrieUpdate),
+    /// The state of the shard of the receiver isn't available, the receipt is
+    /// not executed.
+    Unavailable,
+}
+
+/// The result of `TrieViewer::estimate_transaction`.
+#[derive(Debug)]
+pub struct TransactionEstimate {
+    /// None if the transaction is invalid.
+    pub transaction_outcome: Option<ExecutionOutcomeWithId>,
+    /// None if the transaction is invalid or its receipt wasn't executed.
+    pub receipt_outcome: Option<ExecutionOutcomeWithId>,
+    /// Receipts generated by the receipt of the transaction, or the receipt
+    /// itself if it wasn't executed.
+    pub receipts: Vec<Receipt>,
+    pub failure: Option<TxExecutionError>,
+}
+
 pub struct TrieViewer {
     /// Upper bound of the byte size of contract state that is still viewable. None is no limit
     state_size_limit: Option<u64>,
@@ -153,6 +182,8 @@ impl TrieViewer {
         Ok(ViewStateResult { values, proof })
     }
 
+    /// Executes a view call, limited to `max_gas_burnt_view` gas. Returns the
+    /// value returned by the function and the gas burnt by its execution.
     pub fn call_function(
         &self,
         mut state_update: TrieUpdate,
@@ -162,7 +193,7 @@ impl TrieViewer {
         args: &[u8],
         logs: &mut Vec<String>,
         epoch_info_provider: &dyn EpochInfoProvider,
-    ) -> Result<Vec<u8>, errors::CallFunctionError> {
+    ) -> Result<(Vec<u8>, Gas), errors::CallFunctionError> {
         let now = Instant::now();
         let root = *state_update.get_root();
         let mut account = get_account(&state_update, contract_id)?.ok_or_else(|| {
@@ -205,6 +236,7 @@ impl TrieViewer {
             is_new_chunk: false,
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
+            collect_receipt_profiles: false,
         };
         let action_receipt = ActionReceipt {
             signer_id: originator_id.clone(),
@@ -251,7 +283,106 @@ impl TrieViewer {
                 ReturnData::Value(buf) => buf,
                 ReturnData::ReceiptIndex(_) | ReturnData::None => vec![],
             };
-            Ok(result)
+            Ok((result, outcome.burnt_gas))
         }
     }
+
+    /// Converts the transaction to a receipt on the state of the signer, then
+    /// executes the receipt on the state of the receiver, like a chunk would.
+    /// The changes are only applied to the given trie updates, which are
+    /// dropped afterwards, so nothing is committed to the store.
+    ///
+    /// An invalid transaction or a failed receipt is reported in the
+    /// `failure` of the estimate rather than as an error. Transactions that
+    /// attach more than `max_gas_burnt_view` gas are rejected.
+    pub fn estimate_transaction(
+        &self,
+        mut signer_state_update: TrieUpdate,
+        receiver_state: ReceiverState,
+        view_state: ViewApplyState,
+        config: Arc<RuntimeConfig>,
+        gas_price: Balance,
+        signed_transaction: &SignedTransaction,
+        verify_signature: bool,
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Result<TransactionEstimate, errors::EstimateTransactionError> {
+        let prepaid_gas =
+            total_prepaid_gas(&signed_transaction.transaction.actions).map_err(|err| {
+                errors::EstimateTransactionError::InternalError { error_message: err.to_string() }
+            })?;
+        if prepaid_gas > self.max_gas_burnt_view {
+            return Err(errors::EstimateTransactionError::GasLimitExceeded {
+                prepaid_gas,
+                limit: self.max_gas_burnt_view,
+            });
+        }
+        let apply_state = ApplyState {
+            block_height: view_state.block_height,
+            prev_block_hash: view_state.prev_block_hash,
+            block_hash: view_state.block_hash,
+            epoch_id: view_state.epoch_id.clone(),
+            epoch_height: view_state.epoch_height,
+            gas_price,
+            block_timestamp: view_state.block_timestamp,
+            gas_limit: None,
+            random_seed: *signer_state_update.get_root(),
+            current_protocol_version: view_state.current_protocol_version,
+            config,
+            cache: view_state.cache,
+            is_new_chunk: false,
+            migration_data: Arc::new(MigrationData::default()),
+            migration_flags: MigrationFlags::default(),
+            collect_receipt_profiles: false,
+        };
+        let runtime = Runtime::new();
+        let (receipt, transaction_outcome) = match runtime.convert_transaction(
+            &mut signer_state_update,
+            &apply_state,
+            signed_transaction,
+            verify_signature,
+        ) {
+            Ok(result) => result,
+            Err(RuntimeError::InvalidTxError(err)) => {
+                return Ok(TransactionEstimate {
+                    transaction_outcome: None,
+                    receipt_outcome: None,
+                    receipts: vec![],
+                    failure: Some(TxExecutionError::InvalidTxError(err)),
+                });
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let mut receiver_state_update = match receiver_state {
+            ReceiverState::SignerShard => signer_state_update,
+            ReceiverState::OtherShard(state_update) => state_update,
+            ReceiverState::Unavailable => {
+                return Ok(TransactionEstimate {
+                    transaction_outcome: Some(transaction_outcome),
+                    receipt_outcome: None,
+                    receipts: vec![receipt],
+                    failure: None,
+                });
+            }
+        };
+        let mut receipts = vec![];
+        let receipt_outcome = runtime.process_receipt(
+            &mut receiver_state_update,
+            &apply_state,
+            &receipt,
+            &mut receipts,
+            &mut vec![],
+            &mut ApplyStats::default(),
+            epoch_info_provider,
+        )?;
+        let failure = match receipt_outcome.as_ref().map(|outcome| &outcome.outcome.status) {
+            Some(ExecutionStatus::Failure(err)) => Some(err.clone()),
+            _ => None,
+        };
+        Ok(TransactionEstimate {
+            transaction_outcome: Some(transaction_outcome),
+            receipt_outcome,
+            receipts,
+            failure,
+        })
+    }
 }
@@ -104,6 +104,7 @@ impl StandaloneRuntime {
             is_new_chunk: true,
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
+            collect_receipt_profiles: false,
         };
 
         Self {
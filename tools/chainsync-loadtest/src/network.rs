@@ -91,6 +91,7 @@ impl Network {
                     tier1_connections: vec![],
                     tier1_accounts_keys: vec![],
                     tier1_accounts_data: vec![],
+                    snapshot_hosts: vec![],
                 }),
                 info_futures: Default::default(),
             }),
@@ -307,5 +308,12 @@ impl near_network::client::Client for Network {
 
     async fn chunk_state_witness(&self, _witness: ChunkStateWitness) {}
 
+    async fn chunk_state_witness_request(
+        &self,
+        _chunk_hash: ChunkHash,
+    ) -> Option<ChunkStateWitness> {
+        None
+    }
+
     async fn chunk_endorsement(&self, _endorsement: ChunkEndorsement) {}
 }
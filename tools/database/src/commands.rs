@@ -3,10 +3,16 @@ use crate::analyse_data_size_distribution::AnalyseDataSizeDistributionCommand;
 use crate::analyse_gas_usage::AnalyseGasUsageCommand;
 use crate::compact::RunCompactionCommand;
 use crate::corrupt::CorruptStateSnapshotCommand;
+use crate::gc_block_headers::GcBlockHeadersCommand;
 use crate::make_snapshot::MakeSnapshotCommand;
-use crate::memtrie::LoadMemTrieCommand;
+use crate::memtrie::{LoadMemTrieCommand, MemTrieStatsCommand};
+use crate::online_compaction::OnlineCompactionCommand;
 use crate::run_migrations::RunMigrationsCommand;
+use crate::shard_state::{ExportShardStateCommand, ImportShardStateCommand};
 use crate::state_perf::StatePerfCommand;
+use crate::state_snapshots::ListStateSnapshotsCommand;
+use crate::state_transition_data::RegenerateTransitionDataCommand;
+use crate::stats::DatabaseStatsCommand;
 use clap::Parser;
 use std::path::PathBuf;
 
@@ -28,24 +34,52 @@ enum SubCommand {
     /// Change DbKind of hot or cold db.
     ChangeDbKind(ChangeDbKindCommand),
 
+    /// Compact a column of the database of a running node, without stopping
+    /// it.
+    Compact(OnlineCompactionCommand),
+
     /// Run SST file compaction on database
     CompactDatabase(RunCompactionCommand),
 
     /// Corrupt the state snapshot.
     CorruptStateSnapshot(CorruptStateSnapshotCommand),
 
+    /// Export the state of a shard at a given height to a portable file.
+    ExportShardState(ExportShardStateCommand),
+
+    /// Delete the old block headers kept from before `gc.gc_block_headers`
+    /// was enabled. The node must be stopped.
+    GcBlockHeaders(GcBlockHeadersCommand),
+
+    /// Import the state of a shard from a file written by export-shard-state.
+    ImportShardState(ImportShardStateCommand),
+
     /// Make snapshot of the database
     MakeSnapshot(MakeSnapshotCommand),
 
+    /// Regenerate the data needed to produce state witnesses for a range of
+    /// blocks by re-applying their chunks.
+    RegenerateTransitionData(RegenerateTransitionDataCommand),
+
     /// Run migrations,
     RunMigrations(RunMigrationsCommand),
 
+    /// List the retained state snapshots.
+    Snapshots(ListStateSnapshotsCommand),
+
+    /// Print the per-column RocksDB statistics, such as the size, the pending
+    /// compaction and the block cache usage of each column.
+    Stats(DatabaseStatsCommand),
+
     /// Run performance test for State column reads.
     /// Uses RocksDB data specified via --home argument.
     StatePerf(StatePerfCommand),
 
     /// Loads an in-memory trie for research purposes.
     LoadMemTrie(LoadMemTrieCommand),
+
+    /// Loads an in-memory trie and prints its roots and memory usage.
+    MemtrieStats(MemTrieStatsCommand),
 }
 
 impl DatabaseCommand {
@@ -54,8 +88,12 @@ impl DatabaseCommand {
             SubCommand::AnalyseDataSizeDistribution(cmd) => cmd.run(home),
             SubCommand::AnalyseGasUsage(cmd) => cmd.run(home),
             SubCommand::ChangeDbKind(cmd) => cmd.run(home),
+            SubCommand::Compact(cmd) => cmd.run(home),
             SubCommand::CompactDatabase(cmd) => cmd.run(home),
             SubCommand::CorruptStateSnapshot(cmd) => cmd.run(home),
+            SubCommand::ExportShardState(cmd) => cmd.run(home),
+            SubCommand::GcBlockHeaders(cmd) => cmd.run(home),
+            SubCommand::ImportShardState(cmd) => cmd.run(home),
             SubCommand::MakeSnapshot(cmd) => {
                 let near_config = nearcore::config::load_config(
                     &home,
@@ -64,7 +102,17 @@ impl DatabaseCommand {
                 .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
                 cmd.run(home, near_config.config.archive, &near_config.config.store)
             }
+            SubCommand::RegenerateTransitionData(cmd) => {
+                let near_config = nearcore::config::load_config(
+                    &home,
+                    near_chain_configs::GenesisValidationMode::UnsafeFast,
+                )
+                .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+                cmd.run(near_config, home)
+            }
             SubCommand::RunMigrations(cmd) => cmd.run(home),
+            SubCommand::Snapshots(cmd) => cmd.run(home),
+            SubCommand::Stats(cmd) => cmd.run(home),
             SubCommand::StatePerf(cmd) => cmd.run(home),
             SubCommand::LoadMemTrie(cmd) => {
                 let near_config = nearcore::config::load_config(
@@ -74,6 +122,14 @@ impl DatabaseCommand {
                 .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
                 cmd.run(near_config, home)
             }
+            SubCommand::MemtrieStats(cmd) => {
+                let near_config = nearcore::config::load_config(
+                    &home,
+                    near_chain_configs::GenesisValidationMode::UnsafeFast,
+                )
+                .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+                cmd.run(near_config, home)
+            }
         }
     }
 }
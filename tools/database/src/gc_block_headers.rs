@@ -0,0 +1,29 @@
+use crate::utils::open_rocksdb;
+use near_chain::clear_old_block_headers;
+use std::path::Path;
+
+/// Deletes the old block headers which were kept before `gc.gc_block_headers`
+/// was enabled, i.e. the headers below the chunk tail except the epoch
+/// boundaries. The garbage collection only deletes the headers of the heights
+/// it collects after the option is enabled. The node must be stopped.
+#[derive(clap::Parser)]
+pub(crate) struct GcBlockHeadersCommand {}
+
+impl GcBlockHeadersCommand {
+    pub(crate) fn run(&self, home: &Path) -> anyhow::Result<()> {
+        let config = nearcore::config::Config::from_file_skip_validation(
+            &home.join(nearcore::config::CONFIG_FILENAME),
+        )?;
+        anyhow::ensure!(!config.archive, "block headers can't be deleted on an archival node");
+        anyhow::ensure!(
+            config.gc.gc_block_headers,
+            "gc.gc_block_headers must be enabled in config.json, otherwise the garbage \\
+             collection keeps the headers of the heights it collects"
+        );
+        let rocksdb = open_rocksdb(home, near_store::Mode::ReadWrite)?;
+        let store = near_store::NodeStorage::new(std::sync::Arc::new(rocksdb)).get_hot_store();
+        let num_deleted = clear_old_block_headers(&store)?;
+        println!("Deleted {num_deleted} block headers");
+        Ok(())
+    }
+}
@@ -5,8 +5,14 @@ mod block_iterators;
 pub mod commands;
 mod compact;
 mod corrupt;
+mod gc_block_headers;
 mod make_snapshot;
 mod memtrie;
+mod online_compaction;
 mod run_migrations;
+mod shard_state;
 mod state_perf;
+mod state_snapshots;
+mod state_transition_data;
+mod stats;
 mod utils;
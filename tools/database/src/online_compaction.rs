@@ -0,0 +1,84 @@
+use clap::Parser;
+use near_jsonrpc_primitives::types::compaction::{CompactionRequest, CompactionStatus};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Compacts a column of the database of a running node, through its admin
+/// HTTP endpoint, and reports the progress until the compaction finishes.
+/// Requires `rpc.enable_debug_rpc` to be set in the config of the node.
+#[derive(Parser)]
+pub(crate) struct OnlineCompactionCommand {
+    /// Column to compact, e.g. `State`.
+    #[clap(long)]
+    column: String,
+    /// Compact the column of the cold database instead of the hot one.
+    #[clap(long)]
+    cold: bool,
+    /// Limits the rate of the compaction, in bytes per second.
+    #[clap(long)]
+    max_bytes_per_second: Option<u64>,
+    /// Address of the RPC server of the node. Read from the config of the node
+    /// if not set.
+    #[clap(long)]
+    rpc_addr: Option<String>,
+    /// Only start the compaction, without waiting for it to finish.
+    #[clap(long)]
+    no_wait: bool,
+}
+
+impl OnlineCompactionCommand {
+    pub(crate) fn run(&self, home: &PathBuf) -> anyhow::Result<()> {
+        let rpc_addr = match &self.rpc_addr {
+            Some(rpc_addr) => rpc_addr.clone(),
+            None => nearcore::config::Config::from_file_skip_validation(
+                &home.join(nearcore::config::CONFIG_FILENAME),
+            )?
+            .rpc_addr()
+            .ok_or_else(|| anyhow::anyhow!("the RPC server of the node isn't configured"))?,
+        };
+        let url = format!("http://{rpc_addr}/debug/api/compaction");
+        let client = reqwest::blocking::Client::new();
+
+        let request = CompactionRequest {
+            column: self.column.clone(),
+            cold: self.cold,
+            max_bytes_per_second: self.max_bytes_per_second,
+        };
+        let response = client.post(&url).json(&request).send()?;
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "failed to start the compaction: {}: {}",
+                response.status(),
+                response.text()?
+            );
+        }
+        let mut status: CompactionStatus = response.json()?;
+        print_status(&status);
+        while !self.no_wait && !status.finished {
+            std::thread::sleep(Duration::from_secs(10));
+            status = client
+                .get(&url)
+                .send()?
+                .error_for_status()?
+                .json::<Option<CompactionStatus>>()?
+                .ok_or_else(|| anyhow::anyhow!("the node restarted during the compaction"))?;
+            print_status(&status);
+        }
+        if let Some(error) = status.error {
+            anyhow::bail!("the compaction failed: {error}");
+        }
+        Ok(())
+    }
+}
+
+fn print_status(status: &CompactionStatus) {
+    let state = if status.finished { "finished" } else { "in progress" };
+    eprintln!(
+        "Compaction of {}: {state}, {}/{} ranges, {}/{} bytes",
+        status.request.column,
+        status.num_ranges_done,
+        status.num_ranges,
+        status.bytes_done,
+        status.total_bytes,
+    );
+}
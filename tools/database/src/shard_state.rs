@@ -0,0 +1,259 @@
+use crate::utils::open_rocksdb;
+use anyhow::Context;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::get_block_shard_uid;
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_primitives::types::{BlockHeight, StateRoot};
+use near_primitives::utils::index_to_bytes;
+use near_store::flat::{FlatStateChanges, FlatStorageManager};
+use near_store::{
+    DBCol, NodeStorage, ShardTries, ShardUId, StateSnapshotConfig, Trie, TrieConfig, TrieDBStorage,
+};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Identifies files written by `export-shard-state`.
+const MAGIC: &[u8; 16] = b"near-shard-state";
+/// Version of the file format. Bump it when changing the format, and keep
+/// reading the older versions if possible.
+const VERSION: u32 = 1;
+
+/// Size of the entries applied to the trie at once when importing.
+const IMPORT_BATCH_SIZE_BYTES: usize = 500 * 1000_000;
+
+/// Describes the state stored in a shard state file.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ShardStateHeader {
+    pub shard_uid: ShardUId,
+    pub height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub state_root: StateRoot,
+}
+
+/// Writes a shard state file. The file consists of `MAGIC`, the borsh encoded
+/// `VERSION` and `ShardStateHeader`, and the key-value pairs of the state,
+/// each borsh encoded as `Some((key, value))`, followed by `None`.
+pub(crate) struct ShardStateWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> ShardStateWriter<W> {
+    pub fn new(mut writer: W, header: &ShardStateHeader) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        VERSION.serialize(&mut writer)?;
+        header.serialize(&mut writer)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_entry(&mut self, key: Vec<u8>, value: Vec<u8>) -> std::io::Result<()> {
+        Some((key, value)).serialize(&mut self.writer)
+    }
+
+    pub fn finish(mut self) -> std::io::Result<W> {
+        None::<(Vec<u8>, Vec<u8>)>.serialize(&mut self.writer)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads a shard state file written by `ShardStateWriter`, iterating over its
+/// key-value pairs.
+pub(crate) struct ShardStateReader<R: Read> {
+    reader: R,
+    header: ShardStateHeader,
+    finished: bool,
+}
+
+impl<R: Read> ShardStateReader<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        anyhow::ensure!(&magic == MAGIC, "not a shard state file");
+        let version = u32::deserialize_reader(&mut reader)?;
+        anyhow::ensure!(version == VERSION, "unsupported shard state file version {version}");
+        let header = ShardStateHeader::deserialize_reader(&mut reader)?;
+        Ok(Self { reader, header, finished: false })
+    }
+
+    pub fn header(&self) -> &ShardStateHeader {
+        &self.header
+    }
+}
+
+impl<R: Read> Iterator for ShardStateReader<R> {
+    type Item = std::io::Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match Option::<(Vec<u8>, Vec<u8>)>::deserialize_reader(&mut self.reader) {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Exports the state of a shard after the block at the given height to a
+/// portable file, to construct reproducible test fixtures or analyse the
+/// state offline. Keys are written in the order of the trie, so exports of
+/// the same state are identical.
+#[derive(clap::Parser)]
+pub struct ExportShardStateCommand {
+    /// Shard UID, e.g. `s0.v3`.
+    #[clap(long)]
+    shard: ShardUId,
+    #[clap(long)]
+    height: BlockHeight,
+    #[clap(long)]
+    out: PathBuf,
+}
+
+impl ExportShardStateCommand {
+    pub fn run(&self, home: &Path) -> anyhow::Result<()> {
+        let rocksdb = Arc::new(open_rocksdb(home, near_store::Mode::ReadOnly)?);
+        let store = NodeStorage::new(rocksdb).get_hot_store();
+        let block_hash = store
+            .get_ser::<CryptoHash>(DBCol::BlockHeight, &index_to_bytes(self.height))?
+            .with_context(|| format!("no block at height {}", self.height))?;
+        let chunk_extra = store
+            .get_ser::<ChunkExtra>(
+                DBCol::ChunkExtra,
+                &get_block_shard_uid(&block_hash, &self.shard),
+            )?
+            .with_context(|| format!("no chunk extra for shard {} at {block_hash}", self.shard))?;
+        let header = ShardStateHeader {
+            shard_uid: self.shard,
+            height: self.height,
+            block_hash,
+            state_root: *chunk_extra.state_root(),
+        };
+
+        let trie =
+            Trie::new(Arc::new(TrieDBStorage::new(store, self.shard)), header.state_root, None);
+        let mut writer = ShardStateWriter::new(BufWriter::new(File::create(&self.out)?), &header)?;
+        let timer = Instant::now();
+        let mut num_entries = 0u64;
+        for item in trie.iter()? {
+            let (key, value) = item?;
+            writer.write_entry(key, value)?;
+            num_entries += 1;
+        }
+        writer.finish()?;
+        println!(
+            "{:.2?} : Exported {num_entries} entries of shard {} with state root {} to {}",
+            timer.elapsed(),
+            self.shard,
+            header.state_root,
+            self.out.display()
+        );
+        Ok(())
+    }
+}
+
+/// Imports a file written by `export-shard-state`, writing the trie and the
+/// flat state of the shard. Other data, such as the flat storage status or
+/// the blocks, is not written.
+#[derive(clap::Parser)]
+pub struct ImportShardStateCommand {
+    #[clap(long)]
+    file: PathBuf,
+}
+
+impl ImportShardStateCommand {
+    pub fn run(&self, home: &Path) -> anyhow::Result<()> {
+        let mut reader = ShardStateReader::new(BufReader::new(File::open(&self.file)?))?;
+        let header = reader.header().clone();
+        let shard_uid = header.shard_uid;
+
+        let rocksdb = Arc::new(open_rocksdb(home, near_store::Mode::ReadWrite)?);
+        let store = NodeStorage::new(rocksdb).get_hot_store();
+        let tries = ShardTries::new(
+            store.clone(),
+            TrieConfig::default(),
+            &[shard_uid],
+            FlatStorageManager::new(store),
+            StateSnapshotConfig::default(),
+        );
+        let mut state_root = Trie::EMPTY_ROOT;
+        let timer = Instant::now();
+        loop {
+            let mut batch = vec![];
+            let mut batch_size = 0;
+            while batch_size < IMPORT_BATCH_SIZE_BYTES {
+                let Some(entry) = reader.next() else {
+                    break;
+                };
+                let (key, value) = entry?;
+                batch_size += key.len() + value.len();
+                batch.push((key, Some(value)));
+            }
+            if batch.is_empty() {
+                break;
+            }
+
+            let mut store_update = tries.store_update();
+            FlatStateChanges::from_raw_key_value(&batch)
+                .apply_to_flat_state(&mut store_update, shard_uid);
+            let trie_changes = tries.get_trie_for_shard(shard_uid, state_root).update(batch)?;
+            state_root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+            store_update.commit()?;
+            println!("{:.2?} : Imported {batch_size} bytes", timer.elapsed());
+        }
+        anyhow::ensure!(
+            state_root == header.state_root,
+            "imported state root {state_root} differs from the exported {}",
+            header.state_root
+        );
+        println!(
+            "{:.2?} : Imported state of shard {shard_uid} at height {} with state root {state_root}",
+            timer.elapsed(),
+            header.height
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_state_file_roundtrip() {
+        let header = ShardStateHeader {
+            shard_uid: ShardUId::single_shard(),
+            height: 10,
+            block_hash: CryptoHash::hash_bytes(b"block"),
+            state_root: CryptoHash::hash_bytes(b"state"),
+        };
+        let entries: Vec<_> = (0..5u8).map(|i| (vec![i], vec![i; i as usize])).collect();
+        let mut writer = ShardStateWriter::new(Vec::new(), &header).unwrap();
+        for (key, value) in entries.clone() {
+            writer.write_entry(key, value).unwrap();
+        }
+        let file = writer.finish().unwrap();
+
+        let reader = ShardStateReader::new(file.as_slice()).unwrap();
+        assert_eq!(reader.header(), &header);
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), entries);
+
+        let mut other_version = file.clone();
+        other_version[MAGIC.len()] = 2;
+        assert!(ShardStateReader::new(other_version.as_slice()).is_err());
+        // Truncated files are reported.
+        let truncated = &file[..file.len() - 3];
+        let reader = ShardStateReader::new(truncated).unwrap();
+        assert!(reader.collect::<Result<Vec<_>, _>>().is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use clap::Parser;
+use near_primitives::block::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_store::{DBCol, Mode, NodeStorage, ShardTries, STATE_SNAPSHOTS_KEY, STATE_SNAPSHOT_KEY};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+pub(crate) struct ListStateSnapshotsCommand {}
+
+impl ListStateSnapshotsCommand {
+    pub(crate) fn run(&self, home: &PathBuf) -> anyhow::Result<()> {
+        let config = nearcore::config::Config::from_file_skip_validation(
+            &home.join(nearcore::config::CONFIG_FILENAME),
+        )?;
+        let store_config = &config.store;
+        let hot_store_path = store_config.path.clone().unwrap_or_else(|| PathBuf::from("data"));
+        let state_snapshot_subdir = Path::new("state_snapshot");
+
+        let storage = NodeStorage::opener(home, config.archive, store_config, None)
+            .open_in_mode(Mode::ReadOnly)?;
+        let store = storage.get_hot_store();
+        let snapshot_hashes: Vec<CryptoHash> =
+            match store.get_ser(DBCol::BlockMisc, STATE_SNAPSHOTS_KEY)? {
+                Some(snapshot_hashes) => snapshot_hashes,
+                None => store.get_ser(DBCol::BlockMisc, STATE_SNAPSHOT_KEY)?.into_iter().collect(),
+            };
+
+        println!("Retained state snapshots, oldest first:");
+        for snapshot_hash in &snapshot_hashes {
+            let height = store
+                .get_ser::<BlockHeader>(DBCol::BlockHeader, snapshot_hash.as_ref())?
+                .map(|header| header.height());
+            let path = ShardTries::get_state_snapshot_base_dir(
+                snapshot_hash,
+                home,
+                &hot_store_path,
+                state_snapshot_subdir,
+            );
+            let status = if path.exists() { "" } else { " (missing)" };
+            println!("  {snapshot_hash} height: {height:?} path: {}{status}", path.display());
+        }
+
+        // Snapshots left behind e.g. by a crash are deleted when the next
+        // snapshot is made.
+        let retained: HashSet<String> = snapshot_hashes.iter().map(ToString::to_string).collect();
+        let parent_path = home.join(&hot_store_path).join(state_snapshot_subdir);
+        if parent_path.exists() {
+            for entry in std::fs::read_dir(&parent_path)? {
+                let entry = entry?;
+                if !retained.contains(entry.file_name().to_string_lossy().as_ref()) {
+                    println!("Not retained: {}", entry.path().display());
+                }
+            }
+        }
+        Ok(())
+    }
+}
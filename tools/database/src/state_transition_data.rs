@@ -0,0 +1,85 @@
+use crate::utils::open_rocksdb;
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_epoch_manager::EpochManager;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::utils::get_block_shard_id;
+use near_store::DBCol;
+use nearcore::migrations::record_state_transition_data;
+use nearcore::{NearConfig, NightshadeRuntime};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Re-applies the chunks of the blocks in the given height range with storage
+/// recording enabled, and overwrites the state transition data used to produce
+/// state witnesses. Useful when the data went missing, e.g. after an unclean
+/// shutdown. The node must track the shards and still have the state of the
+/// blocks, i.e. they must not be garbage collected yet.
+#[derive(clap::Parser)]
+pub(crate) struct RegenerateTransitionDataCommand {
+    /// Height of the first block to regenerate the data for.
+    #[clap(long)]
+    from_height: BlockHeight,
+    /// Height of the last block to regenerate the data for.
+    #[clap(long)]
+    to_height: BlockHeight,
+    /// Only regenerate the data of the given shard. By default, the data of
+    /// all shards is regenerated.
+    #[clap(long)]
+    shard_id: Option<ShardId>,
+}
+
+impl RegenerateTransitionDataCommand {
+    pub(crate) fn run(&self, near_config: NearConfig, home: &Path) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.from_height <= self.to_height,
+            "--from-height must not be greater than --to-height"
+        );
+        let rocksdb = Arc::new(open_rocksdb(home, near_store::Mode::ReadWrite)?);
+        let store = near_store::NodeStorage::new(rocksdb).get_hot_store();
+        let chain_store = ChainStore::new(
+            store.clone(),
+            near_config.genesis.config.genesis_height,
+            near_config.client_config.save_trie_changes,
+        );
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let runtime = NightshadeRuntime::from_config(
+            home,
+            store.clone(),
+            &near_config,
+            epoch_manager.clone(),
+        );
+
+        let mut count = 0;
+        for height in self.from_height..=self.to_height {
+            let Ok(block_hash) = chain_store.get_block_hash_by_height(height) else {
+                println!("No block at height {height}, skipping");
+                continue;
+            };
+            let block = chain_store.get_block(&block_hash)?;
+            let shard_ids = match self.shard_id {
+                Some(shard_id) => vec![shard_id],
+                None => (0..block.chunks().len() as ShardId).collect(),
+            };
+            let mut store_update = store.store_update();
+            for shard_id in shard_ids {
+                let data = record_state_transition_data(
+                    &chain_store,
+                    epoch_manager.as_ref(),
+                    runtime.as_ref(),
+                    &block,
+                    shard_id,
+                )?;
+                store_update.set_ser(
+                    DBCol::StateTransitionData,
+                    &get_block_shard_id(&block_hash, shard_id),
+                    &data,
+                )?;
+                count += 1;
+            }
+            store_update.commit()?;
+        }
+        println!("Regenerated state transition data for {count} block and shard pairs");
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use clap::Parser;
+use near_store::db::StatsValue;
+use near_store::{Mode, NodeStorage, StoreConfig};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+pub(crate) struct DatabaseStatsCommand {
+    /// Print the statistics of the cold database instead of the hot one.
+    #[clap(long)]
+    cold: bool,
+    /// Print only the statistics of the given column, e.g. `State`.
+    #[clap(long)]
+    column: Option<String>,
+}
+
+impl DatabaseStatsCommand {
+    pub(crate) fn run(&self, home: &PathBuf) -> anyhow::Result<()> {
+        let config = nearcore::config::Config::from_file_skip_validation(
+            &home.join(nearcore::config::CONFIG_FILENAME),
+        )?;
+        let storage =
+            NodeStorage::opener(home, config.archive, &config.store, config.cold_store.as_ref())
+                .open_in_mode(Mode::ReadOnly)?;
+        let store = if self.cold {
+            storage
+                .get_cold_store()
+                .ok_or_else(|| anyhow::anyhow!("the node has no cold database"))?
+        } else {
+            storage.get_hot_store()
+        };
+        let Some(stats) = store.get_store_statistics() else {
+            anyhow::bail!("the database doesn't provide any statistics");
+        };
+
+        // Property name -> column -> value.
+        let mut columns: BTreeMap<&'static str, BTreeMap<String, i64>> = BTreeMap::new();
+        for (stat_name, values) in &stats.data {
+            for value in values {
+                if let StatsValue::ColumnValue(col, value) = value {
+                    let col_name: &'static str = (*col).into();
+                    columns.entry(col_name).or_default().insert(stat_name.clone(), *value);
+                }
+            }
+        }
+        if let Some(column) = &self.column {
+            if !columns.contains_key(column.as_str()) {
+                anyhow::bail!("unknown column {column}");
+            }
+            columns.retain(|col_name, _| col_name == column);
+        }
+
+        for (col_name, properties) in &columns {
+            println!("{col_name}:");
+            for (stat_name, value) in properties {
+                println!("  {stat_name}: {value}");
+            }
+        }
+        let store_config = if self.cold { config.cold_store.as_ref() } else { None };
+        print_column_config(store_config.unwrap_or(&config.store), &columns);
+        Ok(())
+    }
+}
+
+/// Prints the per-column settings overridden in the config of the node.
+fn print_column_config(
+    store_config: &StoreConfig,
+    columns: &BTreeMap<&'static str, BTreeMap<String, i64>>,
+) {
+    for (col_name, column_config) in &store_config.column_configs {
+        if columns.contains_key(col_name.as_str()) {
+            println!("{col_name} config: {column_config:?}");
+        }
+    }
+}
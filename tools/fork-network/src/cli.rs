@@ -496,6 +496,7 @@ impl ForkNetworkCommand {
         let mut contract_code_updated = 0;
         let mut postponed_receipts_updated = 0;
         let mut delayed_receipts_updated = 0;
+        let mut buffered_receipts_updated = 0;
         let mut received_data_updated = 0;
         let mut fake_block_height = block_height + 1;
         for item in store_helper::iter_flat_state_entries(shard_uid, &store, None, None) {
@@ -601,6 +602,28 @@ impl ForkNetworkCommand {
                         }
                         index_delayed_receipt += 1;
                     }
+                    StateRecord::BufferedReceipt { receiving_shard, index, receipt } => {
+                        // TODO(eth-implicit) Change back to is_implicit() when ETH-implicit accounts are supported.
+                        if receipt.predecessor_id.get_account_type()
+                            == AccountType::NearImplicitAccount
+                            || receipt.receiver_id.get_account_type()
+                                == AccountType::NearImplicitAccount
+                        {
+                            let new_receipt = Receipt {
+                                predecessor_id: map_account(&receipt.predecessor_id, None),
+                                receiver_id: map_account(&receipt.receiver_id, None),
+                                receipt_id: receipt.receipt_id,
+                                receipt: receipt.receipt,
+                            };
+                            storage_mutator.delete_buffered_receipt(receiving_shard, index)?;
+                            storage_mutator.set_buffered_receipt(
+                                receiving_shard,
+                                index,
+                                &new_receipt,
+                            )?;
+                            buffered_receipts_updated += 1;
+                        }
+                    }
                 }
                 records_parsed += 1;
             } else {
@@ -617,6 +640,7 @@ impl ForkNetworkCommand {
                         + contract_code_updated
                         + postponed_receipts_updated
                         + delayed_receipts_updated
+                        + buffered_receipts_updated
                         + received_data_updated,
                 );
                 let state_root = storage_mutator.commit(&shard_uid, fake_block_height)?;
@@ -636,6 +660,7 @@ impl ForkNetworkCommand {
             contract_data_updated,
             postponed_receipts_updated,
             delayed_receipts_updated,
+            buffered_receipts_updated,
             received_data_updated,
             num_has_full_key = has_full_key.len(),
             "Pass 1 done"
@@ -804,6 +829,13 @@ impl ForkNetworkCommand {
                 .validator_selection_config
                 .minimum_validators_per_shard,
             minimum_stake_ratio: epoch_config.validator_selection_config.minimum_stake_ratio,
+            min_chunk_validator_seats_per_shard: epoch_config
+                .chunk_validation_config
+                .min_chunk_validator_seats_per_shard,
+            chunk_endorsement_quorum: epoch_config.chunk_validation_config.chunk_endorsement_quorum,
+            max_chunk_state_witness_size: epoch_config
+                .chunk_validation_config
+                .max_chunk_state_witness_size,
             dynamic_resharding: false,
             protocol_version: epoch_info.protocol_version(),
             validators: new_validator_accounts,
@@ -6,7 +6,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::Receipt;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::trie_key::TrieKey;
-use near_primitives::types::{AccountId, StateRoot};
+use near_primitives::types::{AccountId, ShardId, StateRoot};
 use near_primitives::types::{StoreKey, StoreValue};
 use near_store::{flat::FlatStateChanges, DBCol, ShardTries};
 use nearcore::NightshadeRuntime;
@@ -137,6 +137,23 @@ impl SingleShardStorageMutator {
         self.remove(TrieKey::DelayedReceipt { index })
     }
 
+    pub(crate) fn set_buffered_receipt(
+        &mut self,
+        receiving_shard: ShardId,
+        index: u64,
+        receipt: &Receipt,
+    ) -> anyhow::Result<()> {
+        self.set(TrieKey::BufferedReceipt { receiving_shard, index }, borsh::to_vec(receipt)?)
+    }
+
+    pub(crate) fn delete_buffered_receipt(
+        &mut self,
+        receiving_shard: ShardId,
+        index: u64,
+    ) -> anyhow::Result<()> {
+        self.remove(TrieKey::BufferedReceipt { receiving_shard, index })
+    }
+
     pub(crate) fn should_commit(&self, batch_size: u64) -> bool {
         self.updates.len() >= batch_size as usize
     }
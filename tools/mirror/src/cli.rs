@@ -39,6 +39,13 @@ struct RunCmd {
     /// whatever's currently stored in --source-home
     #[clap(long)]
     online_source: bool,
+    /// If provided, we will start by sending transactions coming from
+    /// this height in the source chain instead of the ones right after the
+    /// target chain's genesis. Transactions in the blocks skipped this way
+    /// are never sent, so transactions depending on them may fail. Ignored
+    /// if the mirror already sent transactions to the target chain
+    #[clap(long)]
+    start_height: Option<BlockHeight>,
     /// If provided, we will stop after sending transactions coming from
     /// this height in the source chain
     #[clap(long)]
@@ -78,6 +85,7 @@ impl RunCmd {
                     self.source_home,
                     self.target_home,
                     secret,
+                    self.start_height,
                     self.stop_height,
                     self.online_source,
                 ))
@@ -91,7 +91,7 @@ pub fn map_records<P: AsRef<Path>>(
                 }
                 records_seq.serialize_element(&r).unwrap();
             }
-            StateRecord::DelayedReceipt(receipt) => {
+            StateRecord::DelayedReceipt(receipt) | StateRecord::BufferedReceipt { receipt, .. } => {
                 // TODO(eth-implicit) Change back to is_implicit() when ETH-implicit accounts are supported.
                 if receipt.predecessor_id.get_account_type() == AccountType::NearImplicitAccount
                     || receipt.receiver_id.get_account_type() == AccountType::NearImplicitAccount
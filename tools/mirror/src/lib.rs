@@ -878,6 +878,7 @@ impl<T: ChainAccess> TxMirror<T> {
             sync_mode: near_indexer::SyncModeEnum::FromInterruption,
             await_for_node_synced: near_indexer::AwaitForNodeSyncedEnum::StreamWhileSyncing,
             validate_genesis: false,
+            streamer_mode: near_indexer::StreamerModeEnum::FinalBlocks,
         })
         .context("failed to start target chain indexer")?;
         let (target_view_client, target_client) = target_indexer.client_actors();
@@ -1755,9 +1756,30 @@ impl<T: ChainAccess> TxMirror<T> {
         }
     }
 
-    async fn run(mut self, stop_height: Option<BlockHeight>) -> anyhow::Result<()> {
+    async fn run(
+        mut self,
+        start_height: Option<BlockHeight>,
+        stop_height: Option<BlockHeight>,
+    ) -> anyhow::Result<()> {
         let last_stored_height = get_last_source_height(&self.db)?;
-        let last_height = last_stored_height.unwrap_or(self.target_genesis_height - 1);
+        let last_height = match (last_stored_height, start_height) {
+            (Some(last_stored_height), Some(start_height)) => {
+                tracing::warn!(target: "mirror", last_stored_height, start_height, "ignoring --start-height since transactions were already sent to the target chain");
+                last_stored_height
+            }
+            (Some(last_stored_height), None) => last_stored_height,
+            (None, Some(start_height)) => {
+                if start_height < self.target_genesis_height {
+                    anyhow::bail!(
+                        "--start-height was {} but the target chain's genesis height is {}",
+                        start_height,
+                        self.target_genesis_height
+                    );
+                }
+                start_height - 1
+            }
+            (None, None) => self.target_genesis_height - 1,
+        };
 
         let next_heights = self.source_chain_access.init(last_height, CREATE_ACCOUNT_DELTA).await?;
 
@@ -1830,6 +1852,7 @@ async fn run<P: AsRef<Path>>(
     source_home: P,
     target_home: P,
     secret: Option<[u8; crate::secret::SECRET_LEN]>,
+    start_height: Option<BlockHeight>,
     stop_height: Option<BlockHeight>,
     online_source: bool,
 ) -> anyhow::Result<()> {
@@ -1838,11 +1861,13 @@ async fn run<P: AsRef<Path>>(
         let stop_height = stop_height.unwrap_or(
             source_chain_access.head_height().await.context("could not fetch source chain head")?,
         );
-        TxMirror::new(source_chain_access, target_home, secret)?.run(Some(stop_height)).await
+        TxMirror::new(source_chain_access, target_home, secret)?
+            .run(start_height, Some(stop_height))
+            .await
     } else {
         tracing::warn!(target: "mirror", "FIXME: currently --online-source will skip DeployContract actions");
         TxMirror::new(crate::online::ChainAccess::new(source_home)?, target_home, secret)?
-            .run(stop_height)
+            .run(start_height, stop_height)
             .await
     }
 }
@@ -247,6 +247,9 @@ fn load_snapshot(load_cmd: LoadCmd) {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_receipt_origins: false,
+            shadow_apply: None,
+            read_only: false,
         },
         None,
     )
@@ -19,6 +19,9 @@ use std::str::FromStr;
 pub enum StateViewerSubCommand {
     /// Apply block at some height for shard.
     Apply(ApplyCmd),
+    /// Apply all chunks of a block, optionally printing what every receipt did.
+    #[clap(alias = "apply_block")]
+    ApplyBlock(ApplyBlockCmd),
     /// Apply a chunk, even if it's not included in any block on disk
     #[clap(alias = "apply_chunk")]
     ApplyChunk(ApplyChunkCmd),
@@ -46,6 +49,8 @@ pub enum StateViewerSubCommand {
     /// List account names with contracts deployed.
     #[clap(alias = "contract_accounts")]
     ContractAccounts(ContractAccountsCmd),
+    /// Print the keys of the state of a shard created, updated or deleted between two blocks.
+    Diff(DiffCmd),
     /// Dump contract data in storage of given account to binary file.
     #[clap(alias = "dump_account_storage")]
     DumpAccountStorage(DumpAccountStorageCmd),
@@ -124,6 +129,7 @@ impl StateViewerSubCommand {
 
         match self {
             StateViewerSubCommand::Apply(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::ApplyBlock(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ApplyChunk(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ApplyRange(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ApplyReceipt(cmd) => cmd.run(home_dir, near_config, store),
@@ -133,6 +139,7 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::Chunks(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::ClearCache => clear_cache(store),
             StateViewerSubCommand::ContractAccounts(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::Diff(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpAccountStorage(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpCode(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpState(cmd) => cmd.run(home_dir, near_config, store),
@@ -179,6 +186,37 @@ impl ApplyCmd {
     }
 }
 
+#[derive(clap::Parser)]
+pub struct ApplyBlockCmd {
+    #[clap(long)]
+    block_hash: CryptoHash,
+    /// Print the outcome, gas and trie accesses of every transaction and
+    /// receipt of the chunks.
+    #[clap(long)]
+    trace: bool,
+    /// Directory to write the storage proof recorded while applying each
+    /// chunk to, as `<block_hash>_<shard_id>.proof`.
+    #[clap(long)]
+    storage_proof_dir: Option<PathBuf>,
+    #[clap(long)]
+    use_flat_storage: bool,
+}
+
+impl ApplyBlockCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        apply_block_with_trace(
+            self.block_hash,
+            self.trace,
+            self.storage_proof_dir.as_deref(),
+            self.use_flat_storage,
+            home_dir,
+            near_config,
+            store,
+        )
+        .unwrap();
+    }
+}
+
 #[derive(clap::Parser)]
 pub struct ApplyChunkCmd {
     #[clap(long)]
@@ -308,6 +346,37 @@ impl ContractAccountsCmd {
     }
 }
 
+#[derive(clap::Parser)]
+pub struct DiffCmd {
+    /// Id of the shard in the epochs of both blocks.
+    #[clap(long)]
+    shard: ShardId,
+    /// Block after which the state is compared.
+    #[clap(long)]
+    from: CryptoHash,
+    /// Block after which the state is compared to the state after `from`.
+    #[clap(long)]
+    to: CryptoHash,
+    /// Print the diff as JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+impl DiffCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        crate::state_diff::diff_state(
+            self.shard,
+            self.from,
+            self.to,
+            self.json,
+            home_dir,
+            near_config,
+            store,
+        )
+        .unwrap();
+    }
+}
+
 #[derive(clap::Parser)]
 pub struct DumpAccountStorageCmd {
     #[clap(long)]
@@ -679,6 +748,7 @@ pub enum RecordType {
     PostponedReceipt = col::POSTPONED_RECEIPT,
     DelayedReceiptOrIndices = col::DELAYED_RECEIPT_OR_INDICES,
     ContractData = col::CONTRACT_DATA,
+    BufferedReceiptOrIndices = col::BUFFERED_RECEIPT_OR_INDICES,
 }
 
 impl clap::ValueEnum for RecordType {
@@ -693,6 +763,7 @@ impl clap::ValueEnum for RecordType {
             Self::PostponedReceipt,
             Self::DelayedReceiptOrIndices,
             Self::ContractData,
+            Self::BufferedReceiptOrIndices,
         ]
     }
 
@@ -711,6 +782,9 @@ impl clap::ValueEnum for RecordType {
                 Some(clap::builder::PossibleValue::new("delayed-receipt-or-indices"))
             }
             Self::ContractData => Some(clap::builder::PossibleValue::new("contract-data")),
+            Self::BufferedReceiptOrIndices => {
+                Some(clap::builder::PossibleValue::new("buffered-receipt-or-indices"))
+            }
         }
     }
 }
@@ -47,7 +47,6 @@ use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
 use std::sync::Arc;
 use yansi::Color::Red;
 
@@ -58,6 +57,7 @@ pub(crate) fn apply_block(
     runtime: &dyn RuntimeAdapter,
     chain_store: &mut ChainStore,
     use_flat_storage: bool,
+    record_storage: bool,
 ) -> (Block, ApplyChunkResult) {
     let block = chain_store.get_block(&block_hash).unwrap();
     let height = block.header().height();
@@ -88,9 +88,12 @@ pub(crate) fn apply_block(
         )
         .unwrap();
 
+        let mut storage_config =
+            RuntimeStorageConfig::new(*chunk_inner.prev_state_root(), use_flat_storage);
+        storage_config.record_storage = record_storage;
         runtime
             .apply_chunk(
-                RuntimeStorageConfig::new(*chunk_inner.prev_state_root(), use_flat_storage),
+                storage_config,
                 ApplyChunkShardContext {
                     shard_id,
                     last_validator_proposals: chunk_inner.prev_validator_proposals(),
@@ -110,9 +113,12 @@ pub(crate) fn apply_block(
         let chunk_extra =
             chain_store.get_chunk_extra(block.header().prev_hash(), &shard_uid).unwrap();
 
+        let mut storage_config =
+            RuntimeStorageConfig::new(*chunk_extra.state_root(), use_flat_storage);
+        storage_config.record_storage = record_storage;
         runtime
             .apply_chunk(
-                RuntimeStorageConfig::new(*chunk_extra.state_root(), use_flat_storage),
+                storage_config,
                 ApplyChunkShardContext {
                     shard_id,
                     last_validator_proposals: chunk_extra.validator_proposals(),
@@ -156,6 +162,7 @@ pub(crate) fn apply_block_at_height(
         runtime.as_ref(),
         &mut chain_store,
         use_flat_storage,
+        false,
     );
     check_apply_block_result(
         &block,
@@ -166,6 +173,102 @@ pub(crate) fn apply_block_at_height(
     )
 }
 
+/// Applies the chunks of all shards of the block and checks the results
+/// against the stored chunk extras. With `trace`, also prints the outcome of
+/// every transaction and receipt along with the trie nodes it read.
+pub(crate) fn apply_block_with_trace(
+    block_hash: CryptoHash,
+    trace: bool,
+    storage_proof_dir: Option<&Path>,
+    use_flat_storage: bool,
+    home_dir: &Path,
+    mut near_config: NearConfig,
+    store: Store,
+) -> anyhow::Result<()> {
+    // Receipt profiles carry the trie accesses of every receipt.
+    near_config.client_config.save_receipt_profiles |= trace;
+    let mut chain_store = ChainStore::new(
+        store.clone(),
+        near_config.genesis.config.genesis_height,
+        near_config.client_config.save_trie_changes,
+    );
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+    let runtime =
+        NightshadeRuntime::from_config(home_dir, store, &near_config, epoch_manager.clone());
+    let epoch_id = chain_store.get_block_header(&block_hash)?.epoch_id().clone();
+    if let Some(storage_proof_dir) = storage_proof_dir {
+        fs::create_dir_all(storage_proof_dir)?;
+    }
+    let mut mismatched_shards = vec![];
+    for shard_id in epoch_manager.shard_ids(&epoch_id)? {
+        let (block, apply_result) = apply_block(
+            block_hash,
+            shard_id,
+            epoch_manager.as_ref(),
+            runtime.as_ref(),
+            &mut chain_store,
+            use_flat_storage,
+            storage_proof_dir.is_some(),
+        );
+        if let Err(err) = check_apply_block_result(
+            &block,
+            &apply_result,
+            epoch_manager.as_ref(),
+            &chain_store,
+            shard_id,
+        ) {
+            println!("{}", Red.paint(format!("shard {}: {:#}", shard_id, err)));
+            mismatched_shards.push(shard_id);
+        }
+        if trace {
+            print_apply_trace(&apply_result);
+        }
+        if let (Some(storage_proof_dir), Some(proof)) = (storage_proof_dir, &apply_result.proof) {
+            let path = storage_proof_dir.join(format!("{}_{}.proof", block_hash, shard_id));
+            fs::write(&path, borsh::to_vec(&proof.nodes)?)?;
+            println!(
+                "storage proof of {} trie values written to {}",
+                proof.nodes.len(),
+                path.display()
+            );
+        }
+    }
+    if !mismatched_shards.is_empty() {
+        anyhow::bail!(
+            "results of shards {:?} don't match the stored chunk extras",
+            mismatched_shards
+        );
+    }
+    Ok(())
+}
+
+/// Prints the outcomes of the transactions and receipts of an applied chunk,
+/// in the order they were executed.
+fn print_apply_trace(apply_result: &ApplyChunkResult) {
+    let profiles: HashMap<_, _> =
+        apply_result.receipt_profiles.iter().map(|(id, profile)| (id, profile)).collect();
+    println!(
+        "{} outcomes, {} gas burnt, {} outgoing receipts",
+        apply_result.outcomes.len(),
+        apply_result.total_gas_burnt,
+        apply_result.outgoing_receipts.len()
+    );
+    for outcome_with_id in &apply_result.outcomes {
+        let outcome = &outcome_with_id.outcome;
+        print!(
+            "  {} executor {} gas {} status {:?}",
+            outcome_with_id.id, outcome.executor_id, outcome.gas_burnt, outcome.status
+        );
+        match profiles.get(&outcome_with_id.id) {
+            Some(profile) => println!(
+                " trie db reads {} trie mem reads {} wasm ops {}",
+                profile.trie_db_reads, profile.trie_mem_reads, profile.wasm_ops
+            ),
+            None => println!(),
+        }
+    }
+}
+
 pub(crate) fn apply_chunk(
     home_dir: &Path,
     near_config: NearConfig,
@@ -872,7 +975,7 @@ fn get_trie(store: Store, hash: CryptoHash, shard_id: u32, shard_version: u32) -
     let trie_config: TrieConfig = Default::default();
     let shard_cache = TrieCache::new(&trie_config, shard_uid, true);
     let trie_storage = TrieCachingStorage::new(store, shard_cache, shard_uid, true, None);
-    Trie::new(Rc::new(trie_storage), hash, None)
+    Trie::new(Arc::new(trie_storage), hash, None)
 }
 
 pub(crate) fn view_trie(
@@ -1036,7 +1139,7 @@ pub(crate) fn contract_accounts(
         let storage = TrieDBStorage::new(store.clone(), shard_uid);
         // We don't need flat state to traverse all accounts.
         let flat_storage_chunk_view = None;
-        Trie::new(Rc::new(storage), state_root, flat_storage_chunk_view)
+        Trie::new(Arc::new(storage), state_root, flat_storage_chunk_view)
     });
 
     filter.write_header(&mut std::io::stdout().lock())?;
@@ -9,6 +9,7 @@ mod epoch_info;
 mod rocksdb_stats;
 mod scan_db;
 mod state_changes;
+mod state_diff;
 mod state_dump;
 mod state_parts;
 mod trie_iteration_benchmark;
@@ -0,0 +1,294 @@
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::to_base64;
+use near_primitives::trie_key::{col, trie_key_parsers};
+use near_primitives::types::{AccountId, ShardId, StateRoot};
+use near_store::{ShardUId, Store, Trie};
+use nearcore::{NearConfig, NightshadeRuntime};
+use serde_json::json;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeyChange {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl KeyChange {
+    fn as_str(&self) -> &'static str {
+        match self {
+            KeyChange::Created => "created",
+            KeyChange::Updated => "updated",
+            KeyChange::Deleted => "deleted",
+        }
+    }
+}
+
+/// A key whose value differs between two tries. Only the sizes of the values
+/// are kept, as values such as contract code can be large.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct KeyDiff {
+    pub key: Vec<u8>,
+    pub old_size: Option<usize>,
+    pub new_size: Option<usize>,
+}
+
+impl KeyDiff {
+    fn change(&self) -> KeyChange {
+        match (self.old_size, self.new_size) {
+            (None, _) => KeyChange::Created,
+            (_, None) => KeyChange::Deleted,
+            _ => KeyChange::Updated,
+        }
+    }
+}
+
+/// Walks both tries in key order and returns the keys which were created,
+/// updated or deleted going from `from` to `to`.
+pub(crate) fn diff_tries(from: &Trie, to: &Trie) -> anyhow::Result<Vec<KeyDiff>> {
+    let mut from_iter = from.iter()?;
+    let mut to_iter = to.iter()?;
+    let mut from_item = from_iter.next().transpose()?;
+    let mut to_item = to_iter.next().transpose()?;
+    let mut diffs = vec![];
+    loop {
+        let ordering = match (&from_item, &to_item) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((from_key, _)), Some((to_key, _))) => from_key.cmp(to_key),
+        };
+        match ordering {
+            Ordering::Less => {
+                let (key, value) = from_item.take().unwrap();
+                diffs.push(KeyDiff { key, old_size: Some(value.len()), new_size: None });
+                from_item = from_iter.next().transpose()?;
+            }
+            Ordering::Greater => {
+                let (key, value) = to_item.take().unwrap();
+                diffs.push(KeyDiff { key, old_size: None, new_size: Some(value.len()) });
+                to_item = to_iter.next().transpose()?;
+            }
+            Ordering::Equal => {
+                let (key, old_value) = from_item.take().unwrap();
+                let (_, new_value) = to_item.take().unwrap();
+                if old_value != new_value {
+                    diffs.push(KeyDiff {
+                        key,
+                        old_size: Some(old_value.len()),
+                        new_size: Some(new_value.len()),
+                    });
+                }
+                from_item = from_iter.next().transpose()?;
+                to_item = to_iter.next().transpose()?;
+            }
+        }
+    }
+    Ok(diffs)
+}
+
+/// Returns the name of the kind of record stored under the raw trie key.
+fn key_type(key: &[u8]) -> &'static str {
+    let Some(&first_byte) = key.first() else {
+        return "Unknown";
+    };
+    let column = col::NON_DELAYED_RECEIPT_COLUMNS.iter().find(|(col, _)| *col == first_byte);
+    if let Some((_, name)) = column {
+        return *name;
+    }
+    match first_byte {
+        col::DELAYED_RECEIPT_OR_INDICES => "DelayedReceiptOrIndices",
+        col::BUFFERED_RECEIPT_OR_INDICES => "BufferedReceiptOrIndices",
+        _ => "Unknown",
+    }
+}
+
+/// Groups the diffs by the account whose record changed. Keys which don't
+/// belong to an account, e.g. delayed receipts, are grouped under `None`.
+fn group_by_account(diffs: Vec<KeyDiff>) -> BTreeMap<Option<AccountId>, Vec<KeyDiff>> {
+    let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for diff in diffs {
+        let account_id = trie_key_parsers::parse_account_id_from_raw_key(&diff.key).ok().flatten();
+        groups.entry(account_id).or_default().push(diff);
+    }
+    groups
+}
+
+/// Returns the shard uid and the state root of the shard after the block.
+fn state_after_block(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    block_hash: &CryptoHash,
+    shard_id: ShardId,
+) -> anyhow::Result<(ShardUId, StateRoot)> {
+    let header = chain_store.get_block_header(block_hash)?;
+    let shard_uid = epoch_manager.shard_id_to_uid(shard_id, header.epoch_id())?;
+    let chunk_extra = chain_store.get_chunk_extra(block_hash, &shard_uid)?;
+    Ok((shard_uid, *chunk_extra.state_root()))
+}
+
+/// Prints the keys of the state of the shard which were created, updated or
+/// deleted between the blocks, grouped by account. The shard is looked up in
+/// the epoch of each block, so across a resharding the parent shard can be
+/// compared with one of its children.
+pub(crate) fn diff_state(
+    shard_id: ShardId,
+    from: CryptoHash,
+    to: CryptoHash,
+    json_output: bool,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+) -> anyhow::Result<()> {
+    let chain_store = ChainStore::new(
+        store.clone(),
+        near_config.genesis.config.genesis_height,
+        near_config.client_config.save_trie_changes,
+    );
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+    let runtime =
+        NightshadeRuntime::from_config(home_dir, store, &near_config, epoch_manager.clone());
+    let tries = runtime.get_tries();
+    let (from_shard_uid, from_root) =
+        state_after_block(&chain_store, epoch_manager.as_ref(), &from, shard_id)?;
+    let (to_shard_uid, to_root) =
+        state_after_block(&chain_store, epoch_manager.as_ref(), &to, shard_id)?;
+    let diffs = diff_tries(
+        &tries.get_view_trie_for_shard(from_shard_uid, from_root),
+        &tries.get_view_trie_for_shard(to_shard_uid, to_root),
+    )?;
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for diff in &diffs {
+        *counts.entry(diff.change().as_str()).or_default() += 1;
+    }
+    let groups = group_by_account(diffs);
+
+    if json_output {
+        let accounts: serde_json::Map<_, _> = groups
+            .iter()
+            .map(|(account_id, diffs)| {
+                let account = account_id.as_ref().map_or("", |account_id| account_id.as_str());
+                let diffs: Vec<_> = diffs
+                    .iter()
+                    .map(|diff| {
+                        json!({
+                            "change": diff.change().as_str(),
+                            "type": key_type(&diff.key),
+                            "key": to_base64(&diff.key),
+                            "old_size": diff.old_size,
+                            "new_size": diff.new_size,
+                        })
+                    })
+                    .collect();
+                (account.to_string(), diffs.into())
+            })
+            .collect();
+        let output = json!({
+            "shard_id": shard_id,
+            "from": {
+                "block_hash": from,
+                "shard_uid": from_shard_uid.to_string(),
+                "state_root": from_root,
+            },
+            "to": {
+                "block_hash": to,
+                "shard_uid": to_shard_uid.to_string(),
+                "state_root": to_root,
+            },
+            "counts": counts,
+            "accounts": accounts,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!(
+        "shard {} from block {} (state root {}) to block {} (state root {}): {:?}",
+        shard_id, from, from_root, to, to_root, counts
+    );
+    for (account_id, diffs) in &groups {
+        match account_id {
+            Some(account_id) => println!("{}", account_id),
+            None => println!("<no account>"),
+        }
+        for diff in diffs {
+            let sizes = match (diff.old_size, diff.new_size) {
+                (Some(old_size), Some(new_size)) => format!("{} -> {} bytes", old_size, new_size),
+                (Some(size), None) | (None, Some(size)) => format!("{} bytes", size),
+                (None, None) => unreachable!(),
+            };
+            println!(
+                "  {} {} {} ({})",
+                diff.change().as_str(),
+                key_type(&diff.key),
+                to_base64(&diff.key),
+                sizes
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::trie_key::TrieKey;
+    use near_store::test_utils::{test_populate_trie, TestTriesBuilder};
+
+    fn account_key(account: &str) -> Vec<u8> {
+        TrieKey::Account { account_id: account.parse().unwrap() }.to_vec()
+    }
+
+    #[test]
+    fn test_diff_tries() {
+        let tries = TestTriesBuilder::new().build();
+        let shard_uid = ShardUId::single_shard();
+        let from_root = test_populate_trie(
+            &tries,
+            &Trie::EMPTY_ROOT,
+            shard_uid,
+            vec![
+                (account_key("alice.near"), Some(vec![1])),
+                (account_key("bob.near"), Some(vec![2])),
+                (account_key("carol.near"), Some(vec![3])),
+            ],
+        );
+        let to_root = test_populate_trie(
+            &tries,
+            &from_root,
+            shard_uid,
+            vec![
+                (account_key("alice.near"), None),
+                (account_key("bob.near"), Some(vec![2, 2])),
+                (account_key("dave.near"), Some(vec![4])),
+            ],
+        );
+        let diffs = diff_tries(
+            &tries.get_trie_for_shard(shard_uid, from_root),
+            &tries.get_trie_for_shard(shard_uid, to_root),
+        )
+        .unwrap();
+        assert_eq!(
+            diffs,
+            vec![
+                KeyDiff { key: account_key("alice.near"), old_size: Some(1), new_size: None },
+                KeyDiff { key: account_key("bob.near"), old_size: Some(1), new_size: Some(2) },
+                KeyDiff { key: account_key("dave.near"), old_size: None, new_size: Some(1) },
+            ]
+        );
+        assert_eq!(
+            diffs.iter().map(KeyDiff::change).collect::<Vec<_>>(),
+            vec![KeyChange::Deleted, KeyChange::Updated, KeyChange::Created]
+        );
+
+        let groups = group_by_account(diffs);
+        let accounts: Vec<_> =
+            groups.keys().map(|account_id| account_id.as_ref().unwrap().to_string()).collect();
+        assert_eq!(accounts, vec!["alice.near", "bob.near", "dave.near"]);
+        assert_eq!(key_type(&account_key("alice.near")), "Account");
+    }
+}
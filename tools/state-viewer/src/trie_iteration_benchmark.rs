@@ -1,4 +1,4 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Instant};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc, time::Instant};
 
 use near_chain::{ChainStore, ChainStoreAccess};
 use near_epoch_manager::EpochManager;
@@ -47,6 +47,7 @@ impl ColumnCountMap {
             col::POSTPONED_RECEIPT_ID => "POSTPONED_RECEIPT_ID",
             col::PENDING_DATA_COUNT => "PENDING_DATA_COUNT",
             col::POSTPONED_RECEIPT => "POSTPONED_RECEIPT",
+            col::BUFFERED_RECEIPT_OR_INDICES => "BUFFERED_RECEIPT_OR_INDICES",
             _ => unreachable!(),
         }
     }
@@ -147,7 +148,7 @@ impl TrieIterationBenchmarkCmd {
         let state_root = chunk_header.prev_state_root();
         let storage = TrieDBStorage::new(store.clone(), shard_uid);
         let flat_storage_chunk_view = None;
-        Trie::new(Rc::new(storage), state_root, flat_storage_chunk_view)
+        Trie::new(Arc::new(storage), state_root, flat_storage_chunk_view)
     }
 
     fn iter_trie(&self, trie: &Trie) {
@@ -235,6 +236,8 @@ impl TrieIterationBenchmarkCmd {
             // key for delayed receipt only contains account id, nothing to prune
             // key for delayed receipt indices is a shard singleton, nothing to prune
             col::DELAYED_RECEIPT_OR_INDICES => false,
+            // keys for buffered receipts and their indices don't contain account ids
+            col::BUFFERED_RECEIPT_OR_INDICES => false,
 
             // Most columns use the ACCOUNT_DATA_SEPARATOR to indicate the end
             // of the accound id in the trie key. For those columns the